@@ -1,36 +1,76 @@
 #[derive(Debug)]
 pub enum Command {
-    Init { path: String },
+    Init { path: String, separate_git_dir: Option<String>, bare: bool },
     Commit { 
         message: String,
         amend: bool,
         reuse_message: Option<String>,
         edit: bool,
+        verbose: bool,
     },
-    Add { paths: Vec<String> },
-    Status { porcelain: bool, color: String }, 
-    Diff { paths: Vec<String>, cached: bool },
+    Add { paths: Vec<String>, interactive: bool, patch: bool, intent_to_add: bool },
+    Status { porcelain: bool, color: String, exit_code: bool, conflicts: bool, verbose: bool }, 
+    Diff { paths: Vec<String>, cached: bool, exit_code: bool, submodule: Option<String>, renames: Vec<String> },
     Branch { 
         name: String, 
         start_point: Option<String>,
         verbose: bool,
         delete: bool,
-        force: bool
+        force: bool,
+        rename: bool,
+        set_upstream: Option<String>,
+        unset_upstream: bool,
+        /// The `--column` words, `never` for `--no-column`
+        column: Option<String>,
+    },
+    Checkout {
+        target: String,
+        autostash: Option<bool>,
+        detach: bool,
+        merge: bool,
+        ours: bool,
+        theirs: bool,
+        paths: Vec<String>,
+        /// `-b`/`-B`: the branch to create at the target and switch to
+        new_branch: Option<String>,
+        /// `-B`: reset the branch if it exists
+        force_branch: bool,
     },
-    Checkout { target: String },
     Log {
         revisions: Vec<String>,
         abbrev: bool,
         format: String,
         patch: bool,
         decorate: String,
+        diff_merges: String,
+        reverse: bool,
+        boundary: bool,
+        ancestry_path: bool,
+        graph: bool,
+        /// "stat" or "shortstat" to summarize the changes of each commit
+        stat: Option<String>,
+        /// Limits on the commits shown, as option name and value
+        filters: Vec<(String, String)>,
     },
     Merge {
-        branch: String,
+        branches: Vec<String>,
         message: Option<String>,
         abort: bool,
         continue_merge: bool,
         tool: Option<String>, 
+        autostash: Option<bool>,
+        summary: bool,
+        squash: bool,
+        strategy: Option<String>,
+        strategy_options: Vec<String>,
+    },
+    Mv {
+        sources: Vec<String>,
+        destination: String,
+        force: bool,
+        skip_errors: bool,
+        dry_run: bool,
+        verbose: bool,
     },
     Rm {
         files: Vec<String>,
@@ -38,6 +78,283 @@ pub enum Command {
         force: bool,
         recursive: bool,
     },
+    Restore {
+        paths: Vec<String>,
+        source: Option<String>,
+        staged: bool,
+        worktree: bool,
+    },
+    Stash {
+        action: String,
+        entry: Option<String>,
+        message: Option<String>,
+        include_untracked: bool,
+        keep_index: bool,
+        patch: bool,
+    },
+    Fetch {
+        remote: Option<String>,
+        refspecs: Vec<String>,
+        prune: Option<bool>,
+        prune_tags: Option<bool>,
+        negotiation_tips: Vec<String>,
+        refmap: Option<Vec<String>>,
+        filter: Option<String>,
+    },
+    Comment {
+        action: String,
+        commit: Option<String>,
+        anchor: Option<String>,
+        message: Option<String>,
+        path: Option<String>,
+    },
+    Push {
+        remote: Option<String>,
+        refspecs: Vec<String>,
+        force: bool,
+        leases: Vec<String>,
+        mirror: bool,
+    },
+    Clone {
+        url: String,
+        directory: Option<String>,
+        bare: bool,
+        mirror: bool,
+        filter: Option<String>,
+    },
+    Pull {
+        remote: Option<String>,
+        branch: Option<String>,
+        rebase: Option<bool>,
+    },
+    Rebase {
+        upstream: Option<String>,
+        onto: Option<String>,
+        interactive: bool,
+        exec: Vec<String>,
+        rebase_merges: bool,
+        continue_rebase: bool,
+        abort: bool,
+        skip: bool,
+    },
+    Show {
+        objects: Vec<String>,
+        patch: bool,
+    },
+    CheckAttr {
+        attributes: Vec<String>,
+        paths: Vec<String>,
+        all: bool,
+    },
+    Grep {
+        patterns: Vec<String>,
+        args: Vec<String>,
+        paths: Vec<String>,
+        line_number: bool,
+        ignore_case: bool,
+        invert: bool,
+        word: bool,
+        fixed: bool,
+        files_with_matches: bool,
+        count: bool,
+        cached: bool,
+    },
+    VerifyRepoLocks {
+        break_stale: bool,
+        stale_after: Option<u64>,
+    },
+    Clean {
+        paths: Vec<String>,
+        dry_run: bool,
+        force: bool,
+        interactive: bool,
+        directories: bool,
+        ignored: bool,
+        only_ignored: bool,
+        excludes: Vec<String>,
+    },
+    VerifyTag {
+        tags: Vec<String>,
+        verbose: bool,
+    },
+    FormatPatch {
+        revision: Option<String>,
+        output_directory: Option<String>,
+        stdout: bool,
+        numbered: Option<bool>,
+        start_number: Option<usize>,
+        subject_prefix: Option<String>,
+        max_count: Option<usize>,
+    },
+    Apply {
+        patches: Vec<String>,
+        check: bool,
+        index: bool,
+        cached: bool,
+        reverse: bool,
+        strip: Option<usize>,
+        min_context: Option<usize>,
+        three_way: bool,
+        verbose: bool,
+    },
+    Am {
+        mailboxes: Vec<String>,
+        three_way: bool,
+        continue_am: bool,
+        skip: bool,
+        abort: bool,
+        quit: bool,
+        show_current_patch: bool,
+    },
+    UpdateRef {
+        name: String,
+        /// None with -d, to delete the ref
+        new: Option<String>,
+        old: Option<String>,
+        no_deref: bool,
+        message: Option<String>,
+    },
+    SymbolicRef {
+        name: String,
+        target: Option<String>,
+        delete: bool,
+        short: bool,
+        quiet: bool,
+        message: Option<String>,
+    },
+    ForEachRef {
+        patterns: Vec<String>,
+        format: Option<String>,
+        sort: Vec<String>,
+        count: Option<usize>,
+    },
+    Var {
+        name: Option<String>,
+        list: bool,
+        show_origin: bool,
+    },
+    WriteTree {
+        prefix: Option<String>,
+    },
+    CommitTree {
+        tree: String,
+        parents: Vec<String>,
+        messages: Vec<String>,
+        file: Option<String>,
+    },
+    ReadTree {
+        trees: Vec<String>,
+        merge: bool,
+        update: bool,
+        prefix: Option<String>,
+        empty: bool,
+    },
+    Subtree {
+        action: String,
+        prefix: String,
+        args: Vec<String>,
+        message: Option<String>,
+        branch: Option<String>,
+    },
+    Difftool {
+        revisions: Vec<String>,
+        paths: Vec<String>,
+        dir_diff: bool,
+        tool: Option<String>,
+        no_prompt: bool,
+        cached: bool,
+    },
+    Switch {
+        target: Option<String>,
+        create: Option<String>,
+        force_create: bool,
+        detach: bool,
+        merge: bool,
+        autostash: Option<bool>,
+    },
+    Bugreport {
+        output: Option<String>,
+        stdout: bool,
+    },
+    Bisect {
+        action: String,
+        args: Vec<String>,
+    },
+    Worktree {
+        action: String,
+        args: Vec<String>,
+        verbose: bool,
+        porcelain: bool,
+        dry_run: bool,
+        /// `add -b`: the branch to create for the new worktree
+        new_branch: Option<String>,
+        detach: bool,
+        force: bool,
+        reason: Option<String>,
+        expire: Option<String>,
+    },
+    Remote {
+        action: String,
+        args: Vec<String>,
+        verbose: bool,
+        dry_run: bool,
+        /// `set-head -a`: ask the remote which branch its HEAD is on
+        auto: bool,
+        /// `set-head -d`: forget the remote HEAD
+        delete: bool,
+    },
+    Blame {
+        revision: Option<String>,
+        file: String,
+        ignore_revs: Vec<String>,
+        ignore_revs_file: Option<String>,
+        abbrev: Option<usize>,
+        incremental: bool,
+    },
+    Repack {
+        all: bool,
+        delete: bool,
+        write_commit_graph: Option<bool>,
+        write_bitmaps: Option<bool>,
+    },
+    Gc {
+        prune: Option<String>,
+        auto: bool,
+        detach: Option<bool>,
+        write_commit_graph: Option<bool>,
+        write_bitmaps: Option<bool>,
+    },
+    Reflog {
+        reference: Option<String>,
+    },
+    Tag {
+        args: Vec<String>,
+        annotate: bool,
+        message: Option<String>,
+        delete: bool,
+        list: bool,
+        force: bool,
+        contains: Vec<String>,
+        no_contains: Vec<String>,
+        points_at: Vec<String>,
+        sort: Option<String>,
+        sign: Option<bool>,
+        local_user: Option<String>,
+        verify: bool,
+        column: Option<String>,
+    },
+    RequestPull {
+        start: String,
+        url: String,
+        end: Option<String>,
+    },
+    Daemon {
+        directories: Vec<String>,
+        base_path: Option<String>,
+        export_all: bool,
+        listen: Option<String>,
+        port: Option<u16>,
+    },
     Reset {
         files: Vec<String>,
         soft: bool,
@@ -51,7 +368,10 @@ pub enum Command {
         r#continue: bool,
         abort: bool,
         quit: bool,
+        skip: bool,
         mainline: Option<u32>,
+        allow_empty: bool,
+        keep_redundant_commits: bool,
     },
     Revert {
         args: Vec<String>,
@@ -1,4 +1,5 @@
 use crate::cli::args::{CliArgs, Command};
+use crate::core::trace;
 use crate::errors::error::Error;
 
 pub struct CliParser;
@@ -15,20 +16,57 @@ impl CliParser {
 
         let command = args[1].to_lowercase();
         let cli_args = match command.as_str() {
-            "init" => CliArgs {
-                command: Command::Init {
-                    path: args.get(2).map(|s| s.to_owned()).unwrap_or(".".to_string()),
-                },
+            "init" => {
+                let mut path = None;
+                let mut separate_git_dir = None;
+                let mut bare = false;
+
+                let mut i = 2;
+                while i < args.len() {
+                    if args[i] == "--bare" {
+                        bare = true;
+                        i += 1;
+                    } else if args[i] == "-q" || args[i] == "--quiet" {
+                        trace::set_quiet(true);
+                        i += 1;
+                    } else if args[i] == "--separate-git-dir" {
+                        if i + 1 < args.len() {
+                            separate_git_dir = Some(args[i + 1].to_owned());
+                            i += 2;
+                        } else {
+                            return Err(Error::Generic("--separate-git-dir requires a value".to_string()));
+                        }
+                    } else if let Some(value) = args[i].strip_prefix("--separate-git-dir=") {
+                        separate_git_dir = Some(value.to_owned());
+                        i += 1;
+                    } else {
+                        path = Some(args[i].to_owned());
+                        i += 1;
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Init {
+                        path: path.unwrap_or(".".to_string()),
+                        separate_git_dir,
+                        bare,
+                    },
+                }
             },
             "commit" => {
                 let mut message = None; // Use Option for message initially
                 let mut amend = false;
                 let mut reuse_message = None;
                 let mut edit = false;
+                let mut verbose = false;
                 
                 let mut i = 2;
                 while i < args.len() {
                     match args[i].as_str() {
+                        "-q" | "--quiet" => {
+                            trace::set_quiet(true);
+                            i += 1;
+                        },
                         "--message" | "-m" => {
                             if i + 1 < args.len() {
                                 message = Some(args[i + 1].to_owned());
@@ -45,6 +83,10 @@ impl CliParser {
                             edit = true;
                             i += 1;
                         },
+                        "--verbose" | "-v" => {
+                            verbose = true;
+                            i += 1;
+                        },
                         "--reuse-message" | "-C" => {
                             if i + 1 < args.len() {
                                 reuse_message = Some(args[i + 1].to_owned());
@@ -86,12 +128,8 @@ impl CliParser {
                     }
                 }
 
-                // No message needed with --amend (can reuse previous commit message)
-                if message.is_none() && reuse_message.is_none() && !amend {
-                    // Try reading from standard input or editor if no -m is provided (like git)
-                    // For now, we'll require a message one way or another
-                    return Err(Error::Generic("Commit message is required. Use --message/-m, --file/-F, --reuse-message/-C, or --amend".to_string()));
-                }
+                // Without a message one is composed in the editor, starting
+                // from commit.template when it is set
 
                 CliArgs {
                     command: Command::Commit {
@@ -99,22 +137,48 @@ impl CliParser {
                         amend,
                         reuse_message,
                         edit,
+                        verbose,
                     },
                 }
             },
             "add" => {
-                if args.len() < 3 {
+                let mut paths = Vec::new();
+                let mut interactive = false;
+                let mut patch = false;
+                let mut intent_to_add = false;
+
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-i" | "--interactive" => interactive = true,
+                        "-p" | "--patch" => patch = true,
+                        "-N" | "--intent-to-add" => intent_to_add = true,
+                        _ => paths.push(arg.clone()),
+                    }
+                }
+
+                if paths.is_empty() && !interactive && !patch {
                     return Err(Error::Generic("File path(s) are required for add command".to_string()));
                 }
+
                 CliArgs {
                     command: Command::Add {
-                        paths: args[2..].to_vec(),
+                        paths,
+                        interactive,
+                        patch,
+                        intent_to_add,
                     },
                 }
             },
             "status" => {
                 // Check for --porcelain flag
                 let porcelain = args.iter().skip(2).any(|arg| arg == "--porcelain");
+                let exit_code = args.iter().skip(2).any(|arg| arg == "--exit-code");
+                let conflicts = args.iter().skip(2).any(|arg| arg == "--conflicts");
+                let verbose = args.iter().skip(2).any(|arg| arg == "-v" || arg == "--verbose");
+                if args.iter().skip(2).any(|arg| arg == "-q" || arg == "--quiet") {
+                    trace::set_quiet(true);
+                }
 
                 // Check for --color option
                 let color = args.iter().skip(2).enumerate().find_map(|(i, arg)| {
@@ -132,6 +196,9 @@ impl CliParser {
                     command: Command::Status {
                         porcelain,
                         color,
+                        exit_code,
+                        conflicts,
+                        verbose,
                     },
                 }
             },
@@ -139,11 +206,25 @@ impl CliParser {
                 // Parse diff command arguments
                 let mut paths = Vec::new();
                 let mut cached = false;
+                let mut exit_code = false;
+                let mut submodule = None;
+                let mut renames = Vec::new();
 
                 // Check for --cached or --staged flag
                 for arg in args.iter().skip(2) {
                     if arg == "--cached" || arg == "--staged" {
                         cached = true;
+                    } else if arg == "-q" || arg == "--quiet" {
+                        trace::set_quiet(true);
+                    } else if arg == "--exit-code" {
+                        exit_code = true;
+                    } else if arg == "--submodule" {
+                        submodule = Some("log".to_string());
+                    } else if let Some(format) = arg.strip_prefix("--submodule=") {
+                        submodule = Some(format.to_string());
+                    } else if arg.starts_with("-M") || arg.starts_with("-C") || arg.starts_with("--find-renames")
+                        || arg.starts_with("--find-copies") || arg == "--no-renames" {
+                        renames.push(arg.clone());
                     } else if !arg.starts_with('-') { // Assume non-flag arguments are paths
                         paths.push(arg.clone());
                     } else {
@@ -156,6 +237,9 @@ impl CliParser {
                     command: Command::Diff {
                         paths,
                         cached,
+                        exit_code,
+                        submodule,
+                        renames,
                     },
                 }
             },
@@ -166,12 +250,17 @@ impl CliParser {
                 let mut verbose = false;
                 let mut delete = false;
                 let mut force = false;
+                let mut rename = false;
+                let mut set_upstream = None;
+                let mut unset_upstream = false;
+                let mut column = None;
 
                 // Process all arguments for options
                 let mut i = 2;
                 while i < args.len() {
                     let arg = &args[i];
                     match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
                         "-v" | "--verbose" => {
                             verbose = true;
                         },
@@ -185,6 +274,28 @@ impl CliParser {
                             delete = true;
                             force = true;
                         },
+                        "-m" | "--move" => {
+                            rename = true;
+                        },
+                        "-M" => {
+                            rename = true;
+                            force = true;
+                        },
+                        "-u" | "--set-upstream-to" => {
+                            i += 1;
+                            set_upstream = Some(args.get(i).cloned().ok_or_else(|| {
+                                Error::Generic(format!("option '{}' requires a value", arg))
+                            })?);
+                        },
+                        a if a.starts_with("--set-upstream-to=") => {
+                            set_upstream = Some(a["--set-upstream-to=".len()..].to_string());
+                        },
+                        "--unset-upstream" => {
+                            unset_upstream = true;
+                        },
+                        "--column" => column = Some(String::new()),
+                        a if a.starts_with("--column=") => column = Some(a["--column=".len()..].to_string()),
+                        "--no-column" => column = Some("never".to_string()),
                         // Check for other potential flags if needed
                         a if a.starts_with('-') => {
                             // Allow flags to appear anywhere relative to positional args
@@ -210,6 +321,12 @@ impl CliParser {
                 if delete && name.is_empty() {
                      return Err(Error::Generic("Branch name required for delete operation".to_string()));
                 }
+                if rename && name.is_empty() {
+                     return Err(Error::Generic("Branch name required for rename operation".to_string()));
+                }
+                if (set_upstream.is_some() || unset_upstream) && start_point.is_some() {
+                     return Err(Error::Generic("too many arguments to set a branch's upstream".to_string()));
+                }
 
 
                 CliArgs {
@@ -218,25 +335,158 @@ impl CliParser {
                         start_point,
                         verbose,
                         delete,
-                        force
+                        force,
+                        rename,
+                        set_upstream,
+                        unset_upstream,
+                        column,
                     },
                 }
             },
             "checkout" => {
-                if args.len() < 3 {
-                    return Err(Error::Generic("No checkout target specified (branch, commit, or path)".to_string()));
-                }
                  // Allow multiple targets for file checkout? Git's behavior is complex here.
                  // For now, assume one target (branch or commit).
                  // Handle `checkout -- <paths...>` separately if needed.
-                let target = args[2].clone();
+                let mut target = None;
+                let mut autostash = None;
+                let mut detach = false;
+                let mut merge = false;
+                let (mut ours, mut theirs) = (false, false);
+                let mut paths = Vec::new();
+                let mut new_branch = None;
+                let mut force_branch = false;
+
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-b" | "-B" => {
+                            force_branch = arg == "-B";
+                            new_branch = Some(args.get(i + 1).cloned().ok_or_else(|| {
+                                Error::Generic(format!("switch `{}' requires a value", &arg[1..]))
+                            })?);
+                            i += 1;
+                        },
+                        "--detach" => detach = true,
+                        "-m" | "--merge" => merge = true,
+                        "--autostash" => autostash = Some(true),
+                        "--no-autostash" => autostash = Some(false),
+                        "--ours" => ours = true,
+                        "--theirs" => theirs = true,
+                        "--" => {},
+                        _ if ours || theirs => paths.push(arg.clone()),
+                        _ if target.is_none() => target = Some(arg.clone()),
+                        _ => return Err(Error::Generic(format!("Unexpected argument for checkout: {}", arg))),
+                    }
+                    i += 1;
+                }
+
+                // With --ours or --theirs every argument is a path
+                if ours && theirs {
+                    return Err(Error::Generic("--ours and --theirs are mutually exclusive".to_string()));
+                }
+                if ours || theirs {
+                    paths.splice(0..0, target.take());
+                }
+                if new_branch.is_some() && (detach || ours || theirs) {
+                    return Err(Error::Generic("-b and -B cannot be used with --detach, --ours or --theirs".to_string()));
+                }
+
+                // `--detach` without a target detaches at the current commit
+                let target = match target {
+                    Some(target) => target,
+                    None if detach || new_branch.is_some() => "HEAD".to_string(),
+                    None if ours || theirs => String::new(),
+                    None => return Err(Error::Generic("No checkout target specified (branch, commit, or path)".to_string())),
+                };
 
                 CliArgs {
                     command: Command::Checkout {
                         target,
+                        autostash,
+                        detach,
+                        merge,
+                        ours,
+                        theirs,
+                        paths,
+                        new_branch,
+                        force_branch,
                     },
                 }
             },
+            "difftool" => {
+                let (mut revisions, mut paths) = (Vec::new(), Vec::new());
+                let (mut dir_diff, mut no_prompt, mut cached) = (false, false, false);
+                let mut tool = None;
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-d" | "--dir-diff" => dir_diff = true,
+                        "-y" | "--no-prompt" => no_prompt = true,
+                        "--prompt" => no_prompt = false,
+                        "--cached" | "--staged" => cached = true,
+                        "-t" | "--tool" => {
+                            tool = Some(args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?);
+                            i += 1;
+                        },
+                        a if a.starts_with("--tool=") => tool = Some(a["--tool=".len()..].to_string()),
+                        "--" => {
+                            paths.extend(args[i + 1..].iter().cloned());
+                            break;
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for difftool: {}", a)));
+                        },
+                        // As for diff, existing files are paths and the rest revisions
+                        a if std::path::Path::new(a).exists() => paths.push(a.to_string()),
+                        a => revisions.push(a.to_string()),
+                    }
+                    i += 1;
+                }
+
+                CliArgs {
+                    command: Command::Difftool { revisions, paths, dir_diff, tool, no_prompt, cached },
+                }
+            },
+            "switch" => {
+                let (mut target, mut create) = (None, None);
+                let (mut force_create, mut detach, mut merge) = (false, false, false);
+                let mut autostash = None;
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-c" | "--create" | "-C" | "--force-create" => {
+                            force_create = matches!(args[i].as_str(), "-C" | "--force-create");
+                            create = Some(args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?);
+                            i += 1;
+                        },
+                        a if a.starts_with("--create=") => create = Some(a["--create=".len()..].to_string()),
+                        a if a.starts_with("--force-create=") => {
+                            force_create = true;
+                            create = Some(a["--force-create=".len()..].to_string());
+                        },
+                        "-d" | "--detach" => detach = true,
+                        "-m" | "--merge" => merge = true,
+                        "--autostash" => autostash = Some(true),
+                        "--no-autostash" => autostash = Some(false),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for switch: {}", a)));
+                        },
+                        _ if target.is_none() => target = Some(args[i].clone()),
+                        a => return Err(Error::Generic(format!("Unexpected argument for switch: {}", a))),
+                    }
+                    i += 1;
+                }
+
+                CliArgs {
+                    command: Command::Switch { target, create, force_create, detach, merge, autostash },
+                }
+            },
             "log" => {
                 // Parse log command options
                 let mut revisions = Vec::new();
@@ -244,12 +494,18 @@ impl CliParser {
                 let mut format = "medium".to_string();
                 let mut patch = false;
                 let mut decorate = "auto".to_string();
+                let mut diff_merges = "off".to_string();
+                let (mut reverse, mut boundary, mut ancestry_path) = (false, false, false);
+                let mut graph = false;
+                let mut stat = None;
+                let mut filters: Vec<(String, String)> = Vec::new();
 
                 // Process arguments
                 let mut i = 2;
                 while i < args.len() {
                     let arg = &args[i];
                     match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
                         "--abbrev-commit" => {
                             abbrev = true;
                         },
@@ -282,6 +538,14 @@ impl CliParser {
                         "-s" | "--no-patch" => {
                             patch = false;
                         },
+                        "-m" => {
+                            diff_merges = "separate".to_string();
+                        },
+                        "--cc" => {
+                            // --cc implies a patch
+                            diff_merges = "cc".to_string();
+                            patch = true;
+                        },
                         "--decorate" => {
                             // Allow setting decorate without a value, default to short/auto later
                              decorate = "auto".to_string();
@@ -297,6 +561,46 @@ impl CliParser {
                         "--no-decorate" => {
                             decorate = "no".to_string();
                         },
+                        "--reverse" => reverse = true,
+                        "--boundary" => boundary = true,
+                        "--ancestry-path" => ancestry_path = true,
+                        "--graph" => graph = true,
+                        "--stat" | "--shortstat" => stat = Some(arg.trim_start_matches('-').to_string()),
+                        "-n" | "--max-count" | "--skip" | "--since" | "--after" | "--until" | "--before"
+                            | "--author" | "--committer" | "--grep" => {
+                            if i + 1 >= args.len() {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                            filters.push((arg.trim_start_matches('-').to_string(), args[i + 1].clone()));
+                            i += 1;
+                        },
+                        "-i" | "--regexp-ignore-case" => filters.push(("regexp-ignore-case".to_string(), "true".to_string())),
+                        "--all-match" => filters.push(("all-match".to_string(), "true".to_string())),
+                        "--follow" => filters.push(("follow".to_string(), "true".to_string())),
+                        "-L" => {
+                            let value = args.get(i + 1)
+                                .ok_or_else(|| Error::Generic(format!("Option '{}' requires a value", arg)))?;
+                            filters.push(("line-range".to_string(), value.clone()));
+                            i += 1;
+                        },
+                        a if a.starts_with("-L") => filters.push(("line-range".to_string(), a[2..].to_string())),
+                        "--" => {
+                            // Paths from here on, kept behind the "--"
+                            revisions.extend(args[i..].iter().cloned());
+                            break;
+                        },
+                        a if a.starts_with("--") && a.contains('=') => {
+                            let (name, value) = a[2..].split_once('=').unwrap_or_default();
+                            if !["max-count", "skip", "since", "after", "until", "before", "author", "committer", "grep"].contains(&name) {
+                                return Err(Error::Generic(format!("Unknown option for log: {}", a)));
+                            }
+                            filters.push((name.to_string(), value.to_string()));
+                        },
+                        // -n5 and -5 both limit the log to five commits
+                        a if a.strip_prefix("-n").or_else(|| a.strip_prefix('-'))
+                            .is_some_and(|count| !count.is_empty() && count.chars().all(|c| c.is_ascii_digit())) => {
+                            filters.push(("max-count".to_string(), a.trim_start_matches(['-', 'n']).to_string()));
+                        },
                         a if a.starts_with('-') => {
                             // Unknown flag
                              return Err(Error::Generic(format!("Unknown option for log: {}", a)));
@@ -309,6 +613,10 @@ impl CliParser {
                      i += 1; // Increment for the current argument
                 }
 
+                if graph && reverse {
+                    return Err(Error::Generic("options '--reverse' and '--graph' cannot be used together".to_string()));
+                }
+
                 CliArgs {
                     command: Command::Log {
                         revisions,
@@ -316,6 +624,50 @@ impl CliParser {
                         format,
                         patch,
                         decorate,
+                        diff_merges,
+                        reverse,
+                        boundary,
+                        ancestry_path,
+                        graph,
+                        stat,
+                        filters,
+                    },
+                }
+            },
+            "mv" => {
+                let mut paths = Vec::new();
+                let mut force = false;
+                let mut skip_errors = false;
+                let mut dry_run = false;
+                let mut verbose = false;
+
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-f" | "--force" => force = true,
+                        "-k" => skip_errors = true,
+                        "-n" | "--dry-run" => dry_run = true,
+                        "-v" | "--verbose" => verbose = true,
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for mv: {}", a)));
+                        },
+                        _ => paths.push(arg.clone()),
+                    }
+                }
+
+                if paths.len() < 2 {
+                    return Err(Error::Generic("usage: ash mv [-f] [-k] [-n] [-v] <source>... <destination>".to_string()));
+                }
+                let destination = paths.pop().unwrap_or_default();
+
+                CliArgs {
+                    command: Command::Mv {
+                        sources: paths,
+                        destination,
+                        force,
+                        skip_errors,
+                        dry_run,
+                        verbose,
                     },
                 }
             },
@@ -331,6 +683,7 @@ impl CliParser {
                 while i < args.len() {
                     let arg = &args[i];
                     match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
                         "--cached" => {
                             cached = true;
                         },
@@ -365,130 +718,1541 @@ impl CliParser {
                     },
                 }
             },
-            "merge" => {
-                let mut branch = String::new();
-                let mut message = None;
-                let mut abort = false;
-                let mut continue_merge = false;
-                let mut tool = None; 
+            "restore" => {
+                let mut paths = Vec::new();
+                let mut source = None;
+                let mut staged = false;
+                let mut worktree = false;
+                let mut only_paths = false;
 
                 let mut i = 2;
                 while i < args.len() {
                     let arg = &args[i];
                     match arg.as_str() {
-                        "--message" | "-m" => {
-                            if i + 1 < args.len() {
-                                message = Some(args[i + 1].clone());
-                                i += 1; 
-                            } else {
-                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
-                            }
-                        },
-                        "--abort" => {
-                            abort = true;
-                        },
-                        "--continue" => {
-                            continue_merge = true;
-                        },
-                        "--tool" | "-t" => {  // Added tool handling
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        _ if only_paths => paths.push(arg.clone()),
+                        "--" => only_paths = true,
+                        "-S" | "--staged" => staged = true,
+                        "-W" | "--worktree" => worktree = true,
+                        "-s" | "--source" => {
                             if i + 1 < args.len() {
-                                tool = Some(args[i + 1].clone());
+                                source = Some(args[i + 1].clone());
                                 i += 1;
                             } else {
-                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                                return Err(Error::Generic("--source requires a value".to_string()));
                             }
                         },
-                        "--tool-only" => { 
-                            tool = Some("default".to_string());
-                        },
-                        // Allow unknown flags for now or add error handling
-                        _ if arg.starts_with('-') => {
-                            return Err(Error::Generic(format!("Unknown option for merge: {}", arg)));
+                        a if a.starts_with("--source=") => {
+                            source = Some(a["--source=".len()..].to_string());
                         },
-                        // Assume the first non-flag argument is the branch name
-                        _ if branch.is_empty() => {
-                            branch = arg.clone();
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for restore: {}", a)));
                         },
-                        // Handle unexpected additional arguments
-                        _ => {
-                            return Err(Error::Generic(format!("Unexpected argument for merge: {}", arg)));
-                        }
+                        _ => paths.push(arg.clone()),
                     }
-                    i += 1; // Increment index
+                    i += 1;
                 }
 
-                // Special case: if --tool-only or --tool is provided without branch, it means
-                // we want to just run the tool on existing conflicts
-                let resolve_only = tool.is_some() && branch.is_empty() && !abort && !continue_merge;
-                
-                // Branch name is required unless --abort, --continue, or just running the tool
-                if branch.is_empty() && !abort && !continue_merge && !resolve_only {
-                    return Err(Error::Generic("No branch specified for merge and not using --abort, --continue, or --tool".to_string()));
+                if paths.is_empty() {
+                    return Err(Error::Generic("you must specify path(s) to restore".to_string()));
                 }
-                
-                // Cannot specify branch name with --abort or --continue
-                if !branch.is_empty() && (abort || continue_merge) {
-                    return Err(Error::Generic("Cannot specify branch name with --abort or --continue".to_string()));
+
+                CliArgs {
+                    command: Command::Restore {
+                        paths,
+                        source,
+                        staged,
+                        worktree,
+                    },
+                }
+            },
+            "fetch" => {
+                let mut remote = None;
+                let mut refspecs = Vec::new();
+                let mut prune = None;
+                let mut prune_tags = None;
+                let mut negotiation_tips = Vec::new();
+                let mut refmap: Option<Vec<String>> = None;
+                let mut filter = None;
+
+                for arg in args.iter().skip(2) {
+                    if let Some(tip) = arg.strip_prefix("--negotiation-tip=") {
+                        negotiation_tips.push(tip.to_string());
+                    } else if let Some(spec) = arg.strip_prefix("--refmap=") {
+                        refmap.get_or_insert_with(Vec::new).push(spec.to_string());
+                    } else if let Some(spec) = arg.strip_prefix("--filter=") {
+                        filter = Some(spec.to_string());
+                    } else if arg == "-p" || arg == "--prune" {
+                        prune = Some(true);
+                    } else if arg == "--no-prune" {
+                        prune = Some(false);
+                    } else if arg == "-P" || arg == "--prune-tags" {
+                        prune_tags = Some(true);
+                    } else if arg == "--no-prune-tags" {
+                        prune_tags = Some(false);
+                    } else if arg == "-q" || arg == "--quiet" {
+                        trace::set_quiet(true);
+                    } else if arg.starts_with('-') {
+                        return Err(Error::Generic(format!("Unknown option for fetch: {}", arg)));
+                    } else if remote.is_none() {
+                        remote = Some(arg.clone());
+                    } else {
+                        refspecs.push(arg.clone());
+                    }
                 }
 
                 CliArgs {
-                    command: Command::Merge {
-                        branch,
-                        message,
-                        abort,
-                        continue_merge,
-                        tool,
+                    command: Command::Fetch {
+                        remote,
+                        refspecs,
+                        prune,
+                        prune_tags,
+                        negotiation_tips,
+                        refmap,
+                        filter,
                     },
                 }
             },
-            "reset" => {
-                // Parse reset options
-                let mut files = Vec::new();
-                let mut soft = false;
-                let mut mixed = false;
-                let mut hard = false;
+            "push" => {
+                let mut remote = None;
+                let mut refspecs = Vec::new();
                 let mut force = false;
-                let mut reuse_message = None;
-                
-                // Process all arguments for options
-                let mut i = 2;
-                while i < args.len() {
-                    let arg = &args[i];
+                let mut leases = Vec::new();
+                let mut mirror = false;
+
+                for arg in args.iter().skip(2) {
                     match arg.as_str() {
-                        "--soft" => {
-                            soft = true;
-                            i += 1;
-                        },
-                        "--mixed" => {
-                            mixed = true;
-                            i += 1;
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-f" | "--force" => force = true,
+                        "--mirror" => mirror = true,
+                        "--force-with-lease" => leases.push(String::new()),
+                        a if a.starts_with("--force-with-lease=") => {
+                            leases.push(a["--force-with-lease=".len()..].to_string());
                         },
-                        "--hard" => {
-                            hard = true;
-                            i += 1;
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for push: {}", a)));
                         },
-                        "--force" | "-f" => {
-                            force = true;
-                            i += 1;
+                        _ if remote.is_none() => remote = Some(arg.clone()),
+                        _ => refspecs.push(arg.clone()),
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Push {
+                        remote,
+                        refspecs,
+                        force,
+                        leases,
+                        mirror,
+                    },
+                }
+            },
+            "clone" => {
+                let mut positional = Vec::new();
+                let mut bare = false;
+                let mut mirror = false;
+                let mut filter = None;
+
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--bare" => bare = true,
+                        "--mirror" => mirror = true,
+                        a if a.starts_with("--filter=") => filter = Some(a["--filter=".len()..].to_string()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for clone: {}", a)));
                         },
-                        "--reuse-message" | "-C" => {
+                        _ => positional.push(arg.clone()),
+                    }
+                }
+
+                if positional.is_empty() || positional.len() > 2 {
+                    return Err(Error::Generic("Usage: ash clone [--bare | --mirror] [--filter=<spec>] <url> [<directory>]".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Clone {
+                        url: positional[0].clone(),
+                        directory: positional.get(1).cloned(),
+                        bare,
+                        mirror,
+                        filter,
+                    },
+                }
+            },
+            "comment" => {
+                let action = match args.get(2).map(|s| s.as_str()) {
+                    Some("add") => "add".to_string(),
+                    Some("list") | None => "list".to_string(),
+                    Some(other) => {
+                        return Err(Error::Generic(format!("Unknown comment subcommand: {}", other)));
+                    }
+                };
+
+                let mut positional = Vec::new();
+                let mut message = None;
+                let mut path = None;
+
+                let mut i = 3;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-m" | "--message" => {
                             if i + 1 < args.len() {
-                                reuse_message = Some(args[i + 1].clone());
-                                i += 2;
+                                message = Some(args[i + 1].clone());
+                                i += 1;
                             } else {
-                                return Err(Error::Generic("--reuse-message requires a value".to_string()));
+                                return Err(Error::Generic("--message requires a value".to_string()));
                             }
                         },
-                        arg if arg.starts_with('-') => {
-                            return Err(Error::Generic(format!("Unknown option for reset: {}", arg)));
+                        "--" => {
+                            path = args.get(i + 1).cloned();
+                            break;
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for comment: {}", a)));
                         },
+                        _ => positional.push(arg.clone()),
+                    }
+                    i += 1;
+                }
+
+                let (commit, anchor) = if action == "add" {
+                    let message_given = message.is_some();
+                    match (positional.len(), message_given) {
+                        (1, true) => (None, positional.pop()),
+                        (2, true) => (positional.first().cloned(), positional.pop()),
                         _ => {
-                            files.push(arg.clone());
-                            i += 1;
+                            return Err(Error::Generic("usage: ash comment add [<commit>] <path>:<line> -m <message>".to_string()));
                         }
                     }
-                }
-                
+                } else {
+                    if positional.len() > 1 || message.is_some() {
+                        return Err(Error::Generic("usage: ash comment list [<commit>] [-- <path>]".to_string()));
+                    }
+                    (positional.pop(), None)
+                };
+
+                CliArgs {
+                    command: Command::Comment {
+                        action,
+                        commit,
+                        anchor,
+                        message,
+                        path,
+                    },
+                }
+            },
+            "pull" => {
+                let mut rebase = None;
+                let mut positional = Vec::new();
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-r" | "--rebase" => rebase = Some(true),
+                        "--no-rebase" => rebase = Some(false),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for pull: {}", a)));
+                        },
+                        _ => positional.push(arg.clone()),
+                    }
+                }
+                if positional.len() > 2 {
+                    return Err(Error::Generic("usage: ash pull [--rebase] [<remote> [<branch>]]".to_string()));
+                }
+
+                let mut positional = positional.into_iter();
+                CliArgs {
+                    command: Command::Pull {
+                        remote: positional.next(),
+                        branch: positional.next(),
+                        rebase,
+                    },
+                }
+            },
+            "rebase" => {
+                let mut onto = None;
+                let mut interactive = false;
+                let mut exec = Vec::new();
+                let mut rebase_merges = false;
+                let mut continue_rebase = false;
+                let mut abort = false;
+                let mut skip = false;
+                let mut positional = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = args[i].as_str();
+                    match arg {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-i" | "--interactive" => interactive = true,
+                        "-r" | "--rebase-merges" => rebase_merges = true,
+                        "--no-rebase-merges" => rebase_merges = false,
+                        "--continue" => continue_rebase = true,
+                        "--abort" => abort = true,
+                        "--skip" => skip = true,
+                        "--onto" => {
+                            i += 1;
+                            onto = Some(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic("option '--onto' requires a value".to_string()))?);
+                        },
+                        a if a.starts_with("--onto=") => onto = Some(a["--onto=".len()..].to_string()),
+                        "-x" | "--exec" => {
+                            i += 1;
+                            exec.push(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", arg)))?);
+                        },
+                        a if a.starts_with("--exec=") => exec.push(a["--exec=".len()..].to_string()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for rebase: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                let actions = [continue_rebase, abort, skip].iter().filter(|flag| **flag).count();
+                if positional.len() > 1 || actions > 1 || (actions == 1 && (onto.is_some() || interactive || rebase_merges || !exec.is_empty() || !positional.is_empty())) {
+                    return Err(Error::Generic(
+                        "usage: ash rebase [-i] [-r] [-x <cmd>] [--onto <newbase>] [<upstream>] | --continue | --skip | --abort".to_string()
+                    ));
+                }
+
+                CliArgs {
+                    command: Command::Rebase {
+                        upstream: positional.into_iter().next(),
+                        onto,
+                        interactive,
+                        exec,
+                        rebase_merges,
+                        continue_rebase,
+                        abort,
+                        skip,
+                    },
+                }
+            },
+            "show" => {
+                let mut objects = Vec::new();
+                let mut patch = true;
+
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-s" | "--no-patch" => patch = false,
+                        "-p" | "--patch" => patch = true,
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for show: {}", a)));
+                        },
+                        _ => objects.push(arg.clone()),
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Show { objects, patch },
+                }
+            },
+            "check-attr" => {
+                let mut all = false;
+                let mut names = Vec::new();
+                let mut paths = Vec::new();
+                let mut after_separator = false;
+
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "--" if !after_separator => after_separator = true,
+                        "-a" | "--all" if !after_separator => all = true,
+                        a if a.starts_with('-') && !after_separator => {
+                            return Err(Error::Generic(format!("Unknown option for check-attr: {}", a)));
+                        },
+                        _ if after_separator => paths.push(arg.clone()),
+                        _ => names.push(arg.clone()),
+                    }
+                }
+                // Without "--" the first argument is the attribute, unless all
+                // of them are wanted, and the rest are paths
+                if !after_separator {
+                    let split = if all || names.is_empty() { 0 } else { 1 };
+                    paths = names.split_off(split);
+                }
+                if paths.is_empty() || (all != names.is_empty()) {
+                    return Err(Error::Generic("usage: ash check-attr (-a | <attr>...) [--] <path>...".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::CheckAttr { attributes: names, paths, all },
+                }
+            },
+            "grep" => {
+                let mut patterns = Vec::new();
+                let mut positional = Vec::new();
+                let mut paths = Vec::new();
+                let (mut line_number, mut ignore_case, mut invert, mut word) = (false, false, false, false);
+                let (mut fixed, mut files_with_matches, mut count, mut cached) = (false, false, false, false);
+
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-n" | "--line-number" => line_number = true,
+                        "-i" | "--ignore-case" => ignore_case = true,
+                        "-v" | "--invert-match" => invert = true,
+                        "-w" | "--word-regexp" => word = true,
+                        "-F" | "--fixed-strings" => fixed = true,
+                        "-E" | "--extended-regexp" => fixed = false,
+                        "-l" | "--files-with-matches" | "--name-only" => files_with_matches = true,
+                        "-c" | "--count" => count = true,
+                        "--cached" => cached = true,
+                        "-e" => {
+                            i += 1;
+                            patterns.push(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic("switch 'e' requires a value".to_string()))?);
+                        },
+                        "--" => {
+                            paths.extend(args[i + 1..].iter().cloned());
+                            break;
+                        },
+                        a if a.starts_with('-') && a.len() > 1 => {
+                            return Err(Error::Generic(format!("Unknown option for grep: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                // Without -e, the first argument is the pattern
+                if patterns.is_empty() {
+                    if positional.is_empty() {
+                        return Err(Error::Generic(
+                            "usage: ash grep [-n] [-i] [-v] [-w] [-F] [-l] [-c] [--cached] [-e] <pattern> [<tree>...] [[--] <path>...]".to_string()
+                        ));
+                    }
+                    patterns.push(positional.remove(0));
+                }
+
+                CliArgs {
+                    command: Command::Grep {
+                        patterns,
+                        args: positional,
+                        paths,
+                        line_number,
+                        ignore_case,
+                        invert,
+                        word,
+                        fixed,
+                        files_with_matches,
+                        count,
+                        cached,
+                    },
+                }
+            },
+            "verify-repo-locks" => {
+                let mut break_stale = false;
+                let mut stale_after = None;
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--break-stale" => break_stale = true,
+                        a if a.starts_with("--stale-after=") => {
+                            let value = &a["--stale-after=".len()..];
+                            stale_after = Some(value.parse::<u64>()
+                                .map_err(|_| Error::Generic(format!("option 'stale-after' expects a number of seconds, got '{}'", value)))?);
+                        },
+                        a => return Err(Error::Generic(format!("Unknown option for verify-repo-locks: {}", a))),
+                    }
+                }
+                CliArgs {
+                    command: Command::VerifyRepoLocks { break_stale, stale_after },
+                }
+            },
+            "clean" => {
+                let mut paths = Vec::new();
+                let mut excludes = Vec::new();
+                let (mut dry_run, mut force, mut interactive) = (false, false, false);
+                let (mut directories, mut ignored, mut only_ignored) = (false, false, false);
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-n" | "--dry-run" => dry_run = true,
+                        "-f" | "--force" => force = true,
+                        "-i" | "--interactive" => interactive = true,
+                        "-d" => directories = true,
+                        "-x" => ignored = true,
+                        "-X" => only_ignored = true,
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-e" | "--exclude" => {
+                            i += 1;
+                            excludes.push(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic("option '-e' requires a value".to_string()))?);
+                        },
+                        "--" => {
+                            paths.extend(args[i + 1..].iter().cloned());
+                            break;
+                        },
+                        a if a.starts_with("--exclude=") => excludes.push(a["--exclude=".len()..].to_string()),
+                        a if a.starts_with('-') && a.len() > 2 && !a.starts_with("--") => {
+                            // Bundled flags such as -fdx
+                            for flag in a[1..].chars() {
+                                match flag {
+                                    'n' => dry_run = true,
+                                    'f' => force = true,
+                                    'i' => interactive = true,
+                                    'd' => directories = true,
+                                    'x' => ignored = true,
+                                    'X' => only_ignored = true,
+                                    'q' => {},
+                                    _ => return Err(Error::Generic(format!("Unknown option for clean: -{}", flag))),
+                                }
+                            }
+                        },
+                        a if a.starts_with('-') => return Err(Error::Generic(format!("Unknown option for clean: {}", a))),
+                        _ => paths.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+                if ignored && only_ignored {
+                    return Err(Error::Generic("options '-x' and '-X' cannot be used together".to_string()));
+                }
+                CliArgs {
+                    command: Command::Clean { paths, dry_run, force, interactive, directories, ignored, only_ignored, excludes },
+                }
+            },
+            "verify-tag" => {
+                let mut tags = Vec::new();
+                let mut verbose = false;
+                for arg in args.iter().skip(2) {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-v" | "--verbose" => verbose = true,
+                        a if a.starts_with('-') => return Err(Error::Generic(format!("Unknown option for verify-tag: {}", a))),
+                        _ => tags.push(arg.clone()),
+                    }
+                }
+                if tags.is_empty() {
+                    return Err(Error::Generic("Usage: ash verify-tag [-v] <tag>...".to_string()));
+                }
+                CliArgs {
+                    command: Command::VerifyTag { tags, verbose },
+                }
+            },
+            "format-patch" => {
+                let mut revision = None;
+                let mut output_directory = None;
+                let mut stdout = false;
+                let mut numbered = None;
+                let mut start_number = None;
+                let mut subject_prefix = None;
+                let mut max_count = None;
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = args[i].as_str();
+                    match arg {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-o" | "--output-directory" => {
+                            i += 1;
+                            output_directory = Some(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", arg)))?);
+                        },
+                        a if a.starts_with("--output-directory=") => output_directory = Some(a["--output-directory=".len()..].to_string()),
+                        "--stdout" => stdout = true,
+                        "-n" | "--numbered" => numbered = Some(true),
+                        "-N" | "--no-numbered" => numbered = Some(false),
+                        a if a.starts_with("--start-number=") => {
+                            let value = &a["--start-number=".len()..];
+                            start_number = Some(value.parse::<usize>()
+                                .map_err(|_| Error::Generic(format!("Invalid start number: {}", value)))?);
+                        },
+                        a if a.starts_with("--subject-prefix=") => subject_prefix = Some(a["--subject-prefix=".len()..].to_string()),
+                        a if a.len() > 1 && a.starts_with('-') && a[1..].chars().all(|c| c.is_ascii_digit()) => {
+                            max_count = a[1..].parse::<usize>().ok();
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for format-patch: {}", a)));
+                        },
+                        _ if revision.is_some() => {
+                            return Err(Error::Generic(
+                                "usage: ash format-patch [-o <dir>] [--stdout] [-n | -N] [--start-number=<n>] [--subject-prefix=<prefix>] [-<n>] [<since> | <revision-range>]".to_string()
+                            ));
+                        },
+                        _ => revision = Some(args[i].clone()),
+                    }
+                    i += 1;
+                }
+                CliArgs {
+                    command: Command::FormatPatch { revision, output_directory, stdout, numbered, start_number, subject_prefix, max_count },
+                }
+            },
+            "apply" => {
+                let mut patches = Vec::new();
+                let (mut check, mut index, mut cached, mut reverse, mut three_way, mut verbose) = (false, false, false, false, false, false);
+                let mut strip = None;
+                let mut min_context = None;
+                let number = |value: &str, what: &str| value.parse::<usize>()
+                    .map_err(|_| Error::Generic(format!("Invalid {}: {}", what, value)));
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--check" => check = true,
+                        "--index" => index = true,
+                        "--cached" => cached = true,
+                        "-R" | "--reverse" => reverse = true,
+                        "-3" | "--3way" => three_way = true,
+                        "-v" | "--verbose" => verbose = true,
+                        a if a.starts_with("-p") && a.len() > 2 => strip = Some(number(&a[2..], "strip count")?),
+                        a if a.starts_with("-C") && a.len() > 2 => min_context = Some(number(&a[2..], "context count")?),
+                        "-" => patches.push(arg.clone()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for apply: {}", a)));
+                        },
+                        _ => patches.push(arg.clone()),
+                    }
+                }
+                if index && cached {
+                    return Err(Error::Generic("options '--index' and '--cached' cannot be used together".to_string()));
+                }
+                CliArgs {
+                    command: Command::Apply { patches, check, index, cached, reverse, strip, min_context, three_way, verbose },
+                }
+            },
+            "am" => {
+                let mut mailboxes = Vec::new();
+                let (mut three_way, mut continue_am, mut skip, mut abort, mut quit, mut show_current_patch) = (false, false, false, false, false, false);
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-3" | "--3way" => three_way = true,
+                        "--continue" | "-r" | "--resolved" => continue_am = true,
+                        "--skip" => skip = true,
+                        "--abort" => abort = true,
+                        "--quit" => quit = true,
+                        "--show-current-patch" => show_current_patch = true,
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for am: {}", a)));
+                        },
+                        _ => mailboxes.push(arg.clone()),
+                    }
+                }
+                CliArgs {
+                    command: Command::Am { mailboxes, three_way, continue_am, skip, abort, quit, show_current_patch },
+                }
+            },
+            "update-ref" => {
+                let (mut delete, mut no_deref) = (false, false);
+                let mut message = None;
+                let mut positional = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-d" => delete = true,
+                        "--no-deref" => no_deref = true,
+                        "-m" => {
+                            message = Some(args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?);
+                            i += 1;
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for update-ref: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                let usage = "usage: ash update-ref [-m <reason>] [--no-deref] (-d <refname> [<old-oid>] | <refname> <new-oid> [<old-oid>])";
+                let mut positional = positional.into_iter();
+                let name = positional.next().ok_or_else(|| Error::Generic(usage.to_string()))?;
+                let new = if delete { None } else { Some(positional.next().ok_or_else(|| Error::Generic(usage.to_string()))?) };
+                let old = positional.next();
+                if positional.next().is_some() {
+                    return Err(Error::Generic(usage.to_string()));
+                }
+
+                CliArgs {
+                    command: Command::UpdateRef { name, new, old, no_deref, message },
+                }
+            },
+            "symbolic-ref" => {
+                let (mut delete, mut short, mut quiet) = (false, false, false);
+                let mut message = None;
+                let mut positional = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-d" | "--delete" => delete = true,
+                        "--short" => short = true,
+                        "-q" | "--quiet" => quiet = true,
+                        "-m" => {
+                            message = Some(args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?);
+                            i += 1;
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for symbolic-ref: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                let usage = "usage: ash symbolic-ref [-q] [--short] [-d] <name> [-m <reason>] [<ref>]";
+                let mut positional = positional.into_iter();
+                let name = positional.next().ok_or_else(|| Error::Generic(usage.to_string()))?;
+                let target = positional.next();
+                if positional.next().is_some() || (delete && target.is_some()) {
+                    return Err(Error::Generic(usage.to_string()));
+                }
+
+                CliArgs {
+                    command: Command::SymbolicRef { name, target, delete, short, quiet, message },
+                }
+            },
+            "for-each-ref" => {
+                let (mut format, mut sort, mut count) = (None, Vec::new(), None);
+                let mut patterns = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = args[i].as_str();
+                    // Each option takes its value as `--opt=<value>` or the next argument
+                    let (option, inline) = match arg.split_once('=') {
+                        Some((option, value)) if option.starts_with("--") => (option, Some(value.to_string())),
+                        _ => (arg, None),
+                    };
+                    let mut value = || -> Result<String, Error> {
+                        match &inline {
+                            Some(value) => Ok(value.clone()),
+                            None => {
+                                i += 1;
+                                args.get(i).cloned().ok_or_else(|| Error::Generic(format!("option '{}' requires a value", option)))
+                            },
+                        }
+                    };
+                    match option {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--format" => format = Some(value()?),
+                        "--sort" => sort.push(value()?),
+                        "--count" => {
+                            let n = value()?;
+                            count = Some(n.parse().map_err(|_| Error::Generic(format!("invalid --count argument: '{}'", n)))?);
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for for-each-ref: {}", a)));
+                        },
+                        _ => patterns.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                CliArgs {
+                    command: Command::ForEachRef { patterns, format, sort, count },
+                }
+            },
+            "var" => {
+                let (mut name, mut list, mut show_origin) = (None, false, false);
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-l" | "--list" => list = true,
+                        "--show-origin" => show_origin = true,
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for var: {}", a)));
+                        },
+                        _ if name.is_some() => {
+                            return Err(Error::Generic("usage: ash var (-l | <variable>)".to_string()));
+                        },
+                        _ => name = Some(arg.clone()),
+                    }
+                }
+                if list == name.is_some() {
+                    return Err(Error::Generic("usage: ash var (-l | <variable>)".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Var { name, list, show_origin },
+                }
+            },
+            "write-tree" => {
+                let mut prefix = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        a if a.starts_with("--prefix=") => prefix = Some(a["--prefix=".len()..].to_string()),
+                        a => return Err(Error::Generic(format!("Unknown option for write-tree: {}", a))),
+                    }
+                }
+                CliArgs {
+                    command: Command::WriteTree { prefix },
+                }
+            },
+            "commit-tree" => {
+                let mut tree = None;
+                let mut parents = Vec::new();
+                let mut messages = Vec::new();
+                let mut file = None;
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-p" | "-m" | "-F" => {
+                            let value = args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("Option '{}' requires a value", arg)))?;
+                            match arg.as_str() {
+                                "-p" => parents.push(value),
+                                "-m" => messages.push(value),
+                                _ => file = Some(value),
+                            }
+                            i += 1;
+                        },
+                        a if a.starts_with('-') => return Err(Error::Generic(format!("Unknown option for commit-tree: {}", a))),
+                        _ if tree.is_none() => tree = Some(arg.clone()),
+                        _ => return Err(Error::Generic(format!("Unexpected argument for commit-tree: {}", arg))),
+                    }
+                    i += 1;
+                }
+                let tree = tree.ok_or_else(|| Error::Generic("usage: ash commit-tree <tree> [(-p <parent>)...] [(-m <message>)...] [(-F <file>)...]".to_string()))?;
+                CliArgs {
+                    command: Command::CommitTree { tree, parents, messages, file },
+                }
+            },
+            "read-tree" => {
+                let mut trees = Vec::new();
+                let (mut merge, mut update, mut empty) = (false, false, false);
+                let mut prefix = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-m" => merge = true,
+                        "-u" => update = true,
+                        "--empty" => empty = true,
+                        a if a.starts_with("--prefix=") => prefix = Some(a["--prefix=".len()..].to_string()),
+                        a if a.starts_with('-') => return Err(Error::Generic(format!("Unknown option for read-tree: {}", a))),
+                        _ => trees.push(arg.clone()),
+                    }
+                }
+                CliArgs {
+                    command: Command::ReadTree { trees, merge, update, prefix, empty },
+                }
+            },
+            "subtree" => {
+                let (mut prefix, mut message, mut branch) = (None, None, None);
+                let mut positional = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-P" | "--prefix" | "-m" | "--message" | "-b" | "--branch" => {
+                            let value = args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?;
+                            match args[i].as_str() {
+                                "-P" | "--prefix" => prefix = Some(value),
+                                "-m" | "--message" => message = Some(value),
+                                _ => branch = Some(value),
+                            }
+                            i += 1;
+                        },
+                        a if a.starts_with("--prefix=") => prefix = Some(a["--prefix=".len()..].to_string()),
+                        a if a.starts_with("--message=") => message = Some(a["--message=".len()..].to_string()),
+                        a if a.starts_with("--branch=") => branch = Some(a["--branch=".len()..].to_string()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for subtree: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                let usage = "usage: ash subtree add --prefix=<prefix> (<commit> | <repository> <ref>) | merge --prefix=<prefix> <commit> | pull --prefix=<prefix> <repository> <ref> | split --prefix=<prefix> [-b <branch>] [<commit>]";
+                let action = if positional.is_empty() { return Err(Error::Generic(usage.to_string())) } else { positional.remove(0) };
+                let expected = match action.as_str() {
+                    "add" => 1..=2,
+                    "merge" => 1..=1,
+                    "pull" => 2..=2,
+                    "split" => 0..=1,
+                    other => {
+                        return Err(Error::Generic(format!("Unknown subtree subcommand: {}", other)));
+                    }
+                };
+                if !expected.contains(&positional.len()) {
+                    return Err(Error::Generic(usage.to_string()));
+                }
+                let prefix = prefix.ok_or_else(|| Error::Generic("you must provide the --prefix option.".to_string()))?;
+
+                CliArgs {
+                    command: Command::Subtree { action, prefix, args: positional, message, branch },
+                }
+            },
+            "bugreport" => {
+                let mut output = None;
+                let mut stdout = false;
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-o" | "--output-directory" => {
+                            i += 1;
+                            output = Some(args.get(i).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i - 1])))?);
+                        },
+                        a if a.starts_with("--output-directory=") => output = Some(a["--output-directory=".len()..].to_string()),
+                        "--stdout" => stdout = true,
+                        a => return Err(Error::Generic(format!("Unknown option for bugreport: {}", a))),
+                    }
+                    i += 1;
+                }
+                CliArgs {
+                    command: Command::Bugreport { output, stdout },
+                }
+            },
+            "bisect" => {
+                let action = args.get(2).cloned().ok_or_else(|| Error::Generic(
+                    "usage: ash bisect (start [<bad> [<good>...]] | bad | good | skip [<rev>...] | reset [<commit>] | run <cmd>...)".to_string()
+                ))?;
+                CliArgs {
+                    command: Command::Bisect {
+                        action,
+                        args: args[3..].to_vec(),
+                    },
+                }
+            },
+            "worktree" => {
+                let (mut verbose, mut porcelain, mut dry_run) = (false, false, false);
+                let (mut detach, mut force) = (false, false);
+                let (mut new_branch, mut reason, mut expire) = (None, None, None);
+                let mut positional = Vec::new();
+                let mut i = 2;
+                while i < args.len() {
+                    match args[i].as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-v" | "--verbose" => verbose = true,
+                        "--porcelain" => porcelain = true,
+                        "-n" | "--dry-run" => dry_run = true,
+                        "--detach" => detach = true,
+                        "-f" | "--force" => force = true,
+                        "-b" | "--reason" | "--expire" => {
+                            let value = args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("option '{}' requires a value", args[i])))?;
+                            match args[i].as_str() {
+                                "-b" => new_branch = Some(value),
+                                "--reason" => reason = Some(value),
+                                _ => expire = Some(value),
+                            }
+                            i += 1;
+                        },
+                        a if a.starts_with("--reason=") => reason = Some(a["--reason=".len()..].to_string()),
+                        a if a.starts_with("--expire=") => expire = Some(a["--expire=".len()..].to_string()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for worktree: {}", a)));
+                        },
+                        _ => positional.push(args[i].clone()),
+                    }
+                    i += 1;
+                }
+
+                let usage = "usage: ash worktree add [-b <branch>] [--detach] <path> [<commit-ish>] | list [-v|--porcelain] | lock [--reason <string>] <worktree> | unlock <worktree> | remove [-f] <worktree> | prune [-n] [-v] [--expire <expire>]";
+                let action = if positional.is_empty() { return Err(Error::Generic(usage.to_string())) } else { positional.remove(0) };
+                let expected = match action.as_str() {
+                    "list" | "prune" => 0..=0,
+                    "lock" | "unlock" | "remove" => 1..=1,
+                    "add" => 1..=2,
+                    other => {
+                        return Err(Error::Generic(format!("Unknown worktree subcommand: {}", other)));
+                    }
+                };
+                if !expected.contains(&positional.len()) {
+                    return Err(Error::Generic(usage.to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Worktree {
+                        action,
+                        args: positional,
+                        verbose,
+                        porcelain,
+                        dry_run,
+                        new_branch,
+                        detach,
+                        force,
+                        reason,
+                        expire,
+                    },
+                }
+            },
+            "remote" => {
+                let mut verbose = false;
+                let mut dry_run = false;
+                let (mut auto, mut delete) = (false, false);
+                let mut positional = Vec::new();
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-v" | "--verbose" => verbose = true,
+                        "-n" | "--dry-run" => dry_run = true,
+                        "-a" | "--auto" => auto = true,
+                        "-d" | "--delete" => delete = true,
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for remote: {}", a)));
+                        },
+                        _ => positional.push(arg.clone()),
+                    }
+                }
+
+                let action = if positional.is_empty() { "list".to_string() } else { positional.remove(0) };
+                let expected = match action.as_str() {
+                    "list" => 0,
+                    "remove" | "rm" | "show" | "prune" => 1,
+                    "set-head" if auto || delete => 1,
+                    "add" | "rename" | "set-head" => 2,
+                    other => {
+                        return Err(Error::Generic(format!("Unknown remote subcommand: {}", other)));
+                    }
+                };
+                if positional.len() != expected || (auto && delete) {
+                    return Err(Error::Generic(
+                        "usage: ash remote [-v] | add <name> <url> | rename <old> <new> | remove <name> | show <name> | prune [-n] <name> | set-head <name> (-a | -d | <branch>)".to_string()
+                    ));
+                }
+
+                CliArgs {
+                    command: Command::Remote {
+                        action,
+                        args: positional,
+                        verbose,
+                        dry_run,
+                        auto,
+                        delete,
+                    },
+                }
+            },
+            "repack" => {
+                let mut all = false;
+                let mut delete = false;
+                let mut write_commit_graph = None;
+                let mut write_bitmaps = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-a" => all = true,
+                        "-d" => delete = true,
+                        "-ad" | "-da" => {
+                            all = true;
+                            delete = true;
+                        },
+                        "--write-commit-graph" => write_commit_graph = Some(true),
+                        "--no-write-commit-graph" => write_commit_graph = Some(false),
+                        "-b" | "--write-bitmaps" | "--write-bitmap-index" => write_bitmaps = Some(true),
+                        "--no-write-bitmaps" | "--no-write-bitmap-index" => write_bitmaps = Some(false),
+                        other => {
+                            return Err(Error::Generic(format!("Unknown option for repack: {}", other)));
+                        }
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Repack {
+                        all,
+                        delete,
+                        write_commit_graph,
+                        write_bitmaps,
+                    },
+                }
+            },
+            "gc" => {
+                let mut prune = None;
+                let mut auto = false;
+                let mut detach = None;
+                let mut write_commit_graph = None;
+                let mut write_bitmaps = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--prune" => prune = None,
+                        "--write-commit-graph" => write_commit_graph = Some(true),
+                        "--no-write-commit-graph" => write_commit_graph = Some(false),
+                        "--write-bitmaps" | "--write-bitmap-index" => write_bitmaps = Some(true),
+                        "--no-write-bitmaps" | "--no-write-bitmap-index" => write_bitmaps = Some(false),
+                        "--no-prune" => prune = Some("never".to_string()),
+                        "--auto" => auto = true,
+                        "--detach" => detach = Some(true),
+                        "--no-detach" => detach = Some(false),
+                        a if a.starts_with("--prune=") => prune = Some(a["--prune=".len()..].to_string()),
+                        other => {
+                            return Err(Error::Generic(format!("Unknown option for gc: {}", other)));
+                        }
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Gc { prune, auto, detach, write_commit_graph, write_bitmaps },
+                }
+            },
+            "reflog" => {
+                let mut rest: Vec<&String> = args[2..].iter().collect();
+                if rest.iter().any(|arg| *arg == "-q" || *arg == "--quiet") {
+                    trace::set_quiet(true);
+                    rest.retain(|arg| *arg != "-q" && *arg != "--quiet");
+                }
+                if rest.first().is_some_and(|arg| *arg == "show") {
+                    rest.remove(0);
+                }
+                if let Some(option) = rest.iter().find(|arg| arg.starts_with('-')) {
+                    return Err(Error::Generic(format!("Unknown option for reflog: {}", option)));
+                }
+                if rest.len() > 1 {
+                    return Err(Error::Generic("Usage: ash reflog [show] [<ref>]".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Reflog {
+                        reference: rest.first().map(|arg| arg.to_string()),
+                    },
+                }
+            },
+            "tag" => {
+                let mut positional = Vec::new();
+                let mut annotate = false;
+                let mut message = None;
+                let mut delete = false;
+                let mut list = false;
+                let mut force = false;
+                let mut contains = Vec::new();
+                let mut no_contains = Vec::new();
+                let mut points_at = Vec::new();
+                let mut sort = None;
+                let mut sign = None;
+                let mut local_user = None;
+                let mut verify = false;
+                let mut column = None;
+
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-a" | "--annotate" => annotate = true,
+                        "-d" | "--delete" => delete = true,
+                        "-l" | "--list" => list = true,
+                        "-f" | "--force" => force = true,
+                        "-s" | "--sign" => sign = Some(true),
+                        "--no-sign" => sign = Some(false),
+                        "-v" | "--verify" => verify = true,
+                        "-u" | "--local-user" => {
+                            if i + 1 >= args.len() {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                            local_user = Some(args[i + 1].clone());
+                            sign = Some(true);
+                            i += 1;
+                        },
+                        a if a.starts_with("--local-user=") => {
+                            local_user = Some(a["--local-user=".len()..].to_string());
+                            sign = Some(true);
+                        },
+                        "-m" | "--message" => {
+                            if i + 1 >= args.len() {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                            message = Some(args[i + 1].clone());
+                            i += 1;
+                        },
+                        a if a.starts_with("--message=") => message = Some(a["--message=".len()..].to_string()),
+                        // The commit is HEAD when the option comes last
+                        "--contains" | "--no-contains" | "--points-at" => {
+                            let value = if i + 1 < args.len() {
+                                i += 1;
+                                args[i].clone()
+                            } else {
+                                "HEAD".to_string()
+                            };
+                            match arg.as_str() {
+                                "--contains" => contains.push(value),
+                                "--no-contains" => no_contains.push(value),
+                                _ => points_at.push(value),
+                            }
+                            list = true;
+                        },
+                        a if a.starts_with("--contains=") => {
+                            contains.push(a["--contains=".len()..].to_string());
+                            list = true;
+                        },
+                        a if a.starts_with("--no-contains=") => {
+                            no_contains.push(a["--no-contains=".len()..].to_string());
+                            list = true;
+                        },
+                        a if a.starts_with("--points-at=") => {
+                            points_at.push(a["--points-at=".len()..].to_string());
+                            list = true;
+                        },
+                        a if a.starts_with("--sort=") => sort = Some(a["--sort=".len()..].to_string()),
+                        "--column" => column = Some(String::new()),
+                        a if a.starts_with("--column=") => column = Some(a["--column=".len()..].to_string()),
+                        "--no-column" => column = Some("never".to_string()),
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for tag: {}", a)));
+                        },
+                        _ => positional.push(arg.clone()),
+                    }
+                    i += 1;
+                }
+
+                if (delete || verify) && positional.is_empty() {
+                    return Err(Error::Generic(format!("Tag name required for {} operation", if delete { "delete" } else { "verify" })));
+                }
+                if !delete && !list && !verify && positional.len() > 2 {
+                    return Err(Error::Generic("Usage: ash tag [-a | -s | -u <key-id>] [-f] [-m <msg>] <tagname> [<commit>]".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Tag {
+                        args: positional,
+                        annotate,
+                        message,
+                        delete,
+                        list,
+                        force,
+                        contains,
+                        no_contains,
+                        points_at,
+                        sort,
+                        sign,
+                        local_user,
+                        verify,
+                        column,
+                    },
+                }
+            },
+            "request-pull" => {
+                if let Some(option) = args[2..].iter().find(|arg| arg.starts_with('-')) {
+                    return Err(Error::Generic(format!("Unknown option for request-pull: {}", option)));
+                }
+                if args.len() < 4 || args.len() > 5 {
+                    return Err(Error::Generic("Usage: ash request-pull <start> <url> [<end>]".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::RequestPull {
+                        start: args[2].clone(),
+                        url: args[3].clone(),
+                        end: args.get(4).cloned(),
+                    },
+                }
+            },
+            "daemon" => {
+                let mut directories = Vec::new();
+                let mut base_path = None;
+                let mut export_all = false;
+                let mut listen = None;
+                let mut port = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--export-all" => export_all = true,
+                        a if a.starts_with("--base-path=") => base_path = Some(a["--base-path=".len()..].to_string()),
+                        a if a.starts_with("--listen=") => listen = Some(a["--listen=".len()..].to_string()),
+                        a if a.starts_with("--port=") => {
+                            let value = &a["--port=".len()..];
+                            port = Some(value.parse::<u16>()
+                                .map_err(|_| Error::Generic(format!("Invalid port: {}", value)))?);
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for daemon: {}", a)));
+                        },
+                        directory => directories.push(directory.to_string()),
+                    }
+                }
+
+                CliArgs {
+                    command: Command::Daemon { directories, base_path, export_all, listen, port },
+                }
+            },
+            "blame" => {
+                let mut positional = Vec::new();
+                let mut ignore_revs = Vec::new();
+                let mut ignore_revs_file = None;
+                let mut abbrev = None;
+                let mut incremental = false;
+
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--ignore-rev" | "--ignore-revs-file" => {
+                            if i + 1 >= args.len() {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                            if arg == "--ignore-rev" {
+                                ignore_revs.push(args[i + 1].clone());
+                            } else {
+                                ignore_revs_file = Some(args[i + 1].clone());
+                            }
+                            i += 1;
+                        },
+                        // Without a value, back to the default length
+                        "--abbrev" => abbrev = None,
+                        a if a.starts_with("--abbrev=") => {
+                            let value = &a["--abbrev=".len()..];
+                            abbrev = Some(value.parse::<usize>()
+                                .map_err(|_| Error::Generic(format!("option 'abbrev' expects a numerical value, got '{}'", value)))?);
+                        },
+                        // Full commit ids
+                        "-l" => abbrev = Some(40),
+                        "--incremental" => incremental = true,
+                        "--" => {
+                            positional.extend(args[i + 1..].iter().cloned());
+                            break;
+                        },
+                        a if a.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for blame: {}", a)));
+                        },
+                        _ => positional.push(arg.clone()),
+                    }
+                    i += 1;
+                }
+
+                let (revision, file) = match positional.len() {
+                    1 => (None, positional.remove(0)),
+                    2 => (Some(positional.remove(0)), positional.remove(0)),
+                    _ => {
+                        return Err(Error::Generic("usage: ash blame [--abbrev=<n>] [-l] [--incremental] [--ignore-rev <rev>] [--ignore-revs-file <file>] [<rev>] <file>".to_string()));
+                    }
+                };
+
+                CliArgs {
+                    command: Command::Blame {
+                        revision,
+                        file,
+                        ignore_revs,
+                        ignore_revs_file,
+                        abbrev,
+                        incremental,
+                    },
+                }
+            },
+            "stash" => {
+                let mut action = "push".to_string();
+                let mut entry = None;
+                let mut message = None;
+                let mut include_untracked = false;
+                let mut keep_index = false;
+                let mut patch = false;
+
+                let mut i = 2;
+                if i < args.len() && ["push", "pop", "apply", "drop", "list", "show"].contains(&args[i].as_str()) {
+                    action = args[i].clone();
+                    i += 1;
+                }
+
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "-u" | "--include-untracked" => include_untracked = true,
+                        "--no-include-untracked" => include_untracked = false,
+                        "-k" | "--keep-index" => keep_index = true,
+                        "--no-keep-index" => keep_index = false,
+                        "-p" | "--patch" if action == "show" => patch = true,
+                        "-m" | "--message" => {
+                            if i + 1 < args.len() {
+                                message = Some(args[i + 1].clone());
+                                i += 1;
+                            } else {
+                                return Err(Error::Generic("--message requires a value".to_string()));
+                            }
+                        },
+                        a if !a.starts_with('-') && entry.is_none() && ["pop", "apply", "drop", "show"].contains(&action.as_str()) => {
+                            entry = Some(a.to_string());
+                        },
+                        _ => {
+                            return Err(Error::Generic(format!("Unknown option for stash {}: {}", action, arg)));
+                        }
+                    }
+                    i += 1;
+                }
+
+                if action != "push" && (message.is_some() || include_untracked || keep_index) {
+                    return Err(Error::Generic(format!("stash {} does not take push options", action)));
+                }
+
+                CliArgs {
+                    command: Command::Stash {
+                        action,
+                        entry,
+                        message,
+                        include_untracked,
+                        keep_index,
+                        patch,
+                    },
+                }
+            },
+            "merge" => {
+                let mut branches = Vec::new();
+                let mut message = None;
+                let mut abort = false;
+                let mut continue_merge = false;
+                let mut tool = None; 
+                let mut autostash = None;
+                let mut summary = false;
+                let mut squash = false;
+                let mut strategy = None;
+                let mut strategy_options = Vec::new();
+
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => trace::set_quiet(true),
+                        "--message" | "-m" => {
+                            if i + 1 < args.len() {
+                                message = Some(args[i + 1].clone());
+                                i += 1; 
+                            } else {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                        },
+                        "--abort" => {
+                            abort = true;
+                        },
+                        "--continue" => {
+                            continue_merge = true;
+                        },
+                        "--tool" | "-t" => {  // Added tool handling
+                            if i + 1 < args.len() {
+                                tool = Some(args[i + 1].clone());
+                                i += 1;
+                            } else {
+                                return Err(Error::Generic(format!("Option '{}' requires a value", arg)));
+                            }
+                        },
+                        "--tool-only" => { 
+                            tool = Some("default".to_string());
+                        },
+                        "--autostash" => {
+                            autostash = Some(true);
+                        },
+                        "--no-autostash" => {
+                            autostash = Some(false);
+                        },
+                        "--summary" => {
+                            summary = true;
+                        },
+                        "--squash" => {
+                            squash = true;
+                        },
+                        "--no-squash" => {
+                            squash = false;
+                        },
+                        "--strategy" | "-s" | "--strategy-option" | "-X" => {
+                            let value = args.get(i + 1).cloned()
+                                .ok_or_else(|| Error::Generic(format!("Option '{}' requires a value", arg)))?;
+                            if arg == "--strategy" || arg == "-s" {
+                                strategy = Some(value);
+                            } else {
+                                strategy_options.push(value);
+                            }
+                            i += 1;
+                        },
+                        _ if arg.starts_with("--strategy=") => {
+                            strategy = Some(arg["--strategy=".len()..].to_string());
+                        },
+                        _ if arg.starts_with("--strategy-option=") => {
+                            strategy_options.push(arg["--strategy-option=".len()..].to_string());
+                        },
+                        _ if arg.starts_with("-X") => {
+                            strategy_options.push(arg[2..].to_string());
+                        },
+                        // Allow unknown flags for now or add error handling
+                        _ if arg.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for merge: {}", arg)));
+                        },
+                        // Every other argument is a branch to merge
+                        _ => {
+                            branches.push(arg.clone());
+                        }
+                    }
+                    i += 1; // Increment index
+                }
+
+                // Special case: if --tool-only or --tool is provided without branch, it means
+                // we want to just run the tool on existing conflicts
+                let resolve_only = tool.is_some() && branches.is_empty() && !abort && !continue_merge;
+                
+                // Branch name is required unless --abort, --continue, or just running the tool
+                if branches.is_empty() && !abort && !continue_merge && !resolve_only {
+                    return Err(Error::Generic("No branch specified for merge and not using --abort, --continue, or --tool".to_string()));
+                }
+                
+                // Cannot specify branch name with --abort or --continue
+                if !branches.is_empty() && (abort || continue_merge) {
+                    return Err(Error::Generic("Cannot specify branch name with --abort or --continue".to_string()));
+                }
+
+                CliArgs {
+                    command: Command::Merge {
+                        branches,
+                        message,
+                        abort,
+                        continue_merge,
+                        tool,
+                        autostash,
+                        summary,
+                        squash,
+                        strategy,
+                        strategy_options,
+                    },
+                }
+            },
+            "reset" => {
+                // Parse reset options
+                let mut files = Vec::new();
+                let mut soft = false;
+                let mut mixed = false;
+                let mut hard = false;
+                let mut force = false;
+                let mut reuse_message = None;
+                
+                // Process all arguments for options
+                let mut i = 2;
+                while i < args.len() {
+                    let arg = &args[i];
+                    match arg.as_str() {
+                        "-q" | "--quiet" => {
+                            trace::set_quiet(true);
+                            i += 1;
+                        },
+                        "--soft" => {
+                            soft = true;
+                            i += 1;
+                        },
+                        "--mixed" => {
+                            mixed = true;
+                            i += 1;
+                        },
+                        "--hard" => {
+                            hard = true;
+                            i += 1;
+                        },
+                        "--force" | "-f" => {
+                            force = true;
+                            i += 1;
+                        },
+                        "--reuse-message" | "-C" => {
+                            if i + 1 < args.len() {
+                                reuse_message = Some(args[i + 1].clone());
+                                i += 2;
+                            } else {
+                                return Err(Error::Generic("--reuse-message requires a value".to_string()));
+                            }
+                        },
+                        arg if arg.starts_with('-') => {
+                            return Err(Error::Generic(format!("Unknown option for reset: {}", arg)));
+                        },
+                        _ => {
+                            files.push(arg.clone());
+                            i += 1;
+                        }
+                    }
+                }
+                
                 CliArgs {
                     command: Command::Reset {
                         files,
@@ -505,11 +2269,18 @@ impl CliParser {
                 let mut continue_op = false;
                 let mut abort = false;
                 let mut quit = false;
+                let mut skip = false;
                 let mut mainline = None;
+                let mut allow_empty = false;
+                let mut keep_redundant_commits = false;
                 
                 let mut i = 2;
                 while i < args.len() {
                     match args[i].as_str() {
+                        "-q" | "--quiet" => {
+                            trace::set_quiet(true);
+                            i += 1;
+                        },
                         "--continue" => {
                             continue_op = true;
                             i += 1;
@@ -522,6 +2293,18 @@ impl CliParser {
                             quit = true;
                             i += 1;
                         },
+                        "--skip" => {
+                            skip = true;
+                            i += 1;
+                        },
+                        "--allow-empty" => {
+                            allow_empty = true;
+                            i += 1;
+                        },
+                        "--keep-redundant-commits" => {
+                            keep_redundant_commits = true;
+                            i += 1;
+                        },
                         "-m" | "--mainline" => {
                             if i + 1 < args.len() {
                                 match args[i + 1].parse::<u32>() {
@@ -546,15 +2329,15 @@ impl CliParser {
                 }
                 
                 // Check for invalid combinations
-                if (continue_op && abort) || (continue_op && quit) || (abort && quit) {
-                    return Err(Error::Generic("Cannot combine --continue, --abort, and --quit".to_string()));
+                if [continue_op, abort, quit, skip].iter().filter(|&&op| op).count() > 1 {
+                    return Err(Error::Generic("Cannot combine --continue, --skip, --abort, and --quit".to_string()));
                 }
                 
-                if (continue_op || abort || quit) && !commit_args.is_empty() {
-                    return Err(Error::Generic("Cannot combine --continue, --abort, or --quit with commits".to_string()));
+                if (continue_op || abort || quit || skip) && !commit_args.is_empty() {
+                    return Err(Error::Generic("Cannot combine --continue, --skip, --abort, or --quit with commits".to_string()));
                 }
                 
-                if commit_args.is_empty() && !(continue_op || abort || quit) {
+                if commit_args.is_empty() && !(continue_op || abort || quit || skip) {
                     return Err(Error::Generic("cherry-pick requires at least one commit".to_string()));
                 }
                 
@@ -564,7 +2347,10 @@ impl CliParser {
                         r#continue: continue_op,
                         abort,
                         quit,
+                        skip,
                         mainline,
+                        allow_empty,
+                        keep_redundant_commits,
                     },
                 }
             },
@@ -578,6 +2364,10 @@ impl CliParser {
                 let mut i = 2;
                 while i < args.len() {
                     match args[i].as_str() {
+                        "-q" | "--quiet" => {
+                            trace::set_quiet(true);
+                            i += 1;
+                        },
                         "--continue" => {
                             continue_op = true;
                             i += 1;
@@ -648,24 +2438,64 @@ impl CliParser {
 
     pub fn format_help() -> String {
         format!(
-            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
+            "{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}\n{}",
             "Usage: ash <command> [options]",
             "Commands:",
-            "  init [path]                       Initialize a new repository",
-            "  add <paths...>                    Add file contents to the index",
-            "  commit -m <message>               Commit changes to the repository",
-            "  status [--porcelain] [--color=...] Show the working tree status",
-            "  diff [--cached] [paths...]        Show changes (HEAD vs index or index vs workspace)",
-            "  branch [-v] [-d|-D] [<n> [<sp>]]  Manage branches (list, create, delete)",
-            "  checkout <target>                 Switch branches or restore working tree files",
-            "  log [--oneline] [--decorate=...]  Show commit logs",
-            "  merge <branch> [-m <msg>]         Merge the specified branch into the current branch",
+            "  init [--bare] [--separate-git-dir <dir>] [path] Initialize a new repository",
+            "  add [-i|-p|-N] <paths...>         Add file contents to the index (interactively with -i/-p, or only the paths with -N)",
+            "  commit [-v] [-m <message>]        Commit changes to the repository",
+            "  status [--porcelain] [-v] [--color=...] [--exit-code] [--conflicts] Show the working tree status",
+            "  diff [--cached [<commit>]] [--exit-code] [--submodule[=<format>]] [-M[<n>] | -C[<n>] | --no-renames] [paths...] Show changes (commit vs index or index vs workspace)",
+            "  difftool [-d|--dir-diff] [-t <tool>] [-y] [--cached] [<commit> [<commit>]] [--] [<path>...] Show changes in an external diff tool",
+            "  branch [-v | --[no-]column[=<opts>]] [-d|-D|-m|-M] [<n> [<sp>]] | (-u <upstream> | --unset-upstream) [<n>]  Manage branches (list, create, delete, rename, track)",
+            "  checkout [-m] [--detach] [--autostash] [-b|-B <branch>] <target> Switch branches or restore working tree files",
+            "  checkout (--ours | --theirs) [--] <paths>...  Take one side of conflicted files",
+            "  switch [-m] [--autostash] (<branch> | -c|-C <new-branch> [<start>] | --detach [<commit>]) Switch branches",
+            "  mv [-f] [-k] [-n] [-v] <source>... <destination> Move or rename files, directories or symlinks",
+            "  restore [-s <rev>] [-S] [-W] <paths...> Restore files in the index and/or working tree",
+            "  fetch [-p|--prune] [-P|--prune-tags] [--negotiation-tip=<rev>] [--refmap=<spec>] [--filter=<spec>] [<remote>|<url>] [<refspec>...] Download objects and refs from another repository",
+            "  clone [--bare | --mirror] [--filter=<spec>] <url> [<directory>] Copy a repository, or mirror every ref of it",
+            "  push [-f] [--force-with-lease[=<ref>[:<expect>]]] [--mirror] [<remote>|<url>] [[+]<src>[:<dst>]...] Update remote refs along with their objects",
+            "  comment add [<commit>] <path>:<line> -m <msg> | list [<commit>] Review comments kept in refs/notes/comments",
+            "  pull [--rebase] [<remote> [<branch>]] Fetch from another repository and merge or rebase onto it",
+            "  rebase [-i] [-r] [-x <cmd>] [--onto <newbase>] [<upstream>] | --continue | --skip | --abort Replay the current branch's commits onto another base",
+            "  show [-s|--no-patch] [<object>...] Show commits, trees, blobs and tags",
+            "  check-attr (-a | <attr>...) [--] <path>... Show the attributes .ashattributes files give paths",
+            "  grep [-n] [-i] [-v] [-w] [-F] [-l] [-c] [--cached] [-e] <pattern> [<tree>...] [[--] <path>...] Print lines of tracked files matching a pattern",
+            "  verify-repo-locks [--break-stale] [--stale-after=<seconds>] List the locks held in the repository, or remove stale ones",
+            "  clean [-n|-f|-i] [-dxX] [-e <pattern>] [<path>...]  Remove untracked files",
+            "  verify-tag [-v] <tag>...         Check the GnuPG signatures of tags",
+            "  worktree add|list|lock|unlock|remove|prune  Manage the linked working trees",
+            "  format-patch [-o <dir>] [--stdout] [-n|-N] [--start-number=<n>] [--subject-prefix=<prefix>] [-<n>] [<since> | <range>] Write commits out as mailbox patches",
+            "  apply [--check] [--index | --cached] [-R] [-3] [-p<n>] [-C<n>] [<patch>...] Apply a patch to the working tree and/or index",
+            "  am [-3] [<mbox>...] | --continue | --skip | --abort | --quit | --show-current-patch Apply a mailbox of patches as commits",
+            "  update-ref [-m <reason>] [--no-deref] (-d <ref> [<old>] | <ref> <new> [<old>]) Set or delete a ref, checking its old value",
+            "  symbolic-ref [-q] [--short] [-d] <name> [-m <reason>] [<ref>] Read, set or delete a symbolic ref such as HEAD",
+            "  for-each-ref [--format=<format>] [--sort=<key>]... [--count=<n>] [<pattern>...] Print refs, the fields of each as the format says",
+            "  var [--show-origin] (-l | <variable>) Print the editor, pager, identity and default branch commands use",
+            "  write-tree [--prefix=<prefix>/]  Write the index as trees and print the root tree's ID",
+            "  commit-tree <tree> [-p <parent>]... [-m <msg>]... [-F <file>] Create a commit object from a tree and print its ID",
+            "  read-tree [-m] [-u] [--prefix=<prefix>/] [--empty] <tree-ish> [<tree-ish2> [<tree-ish3>]] Read trees into the index, merging two or three with -m",
+            "  subtree add|merge|pull|split --prefix=<prefix> [-m <msg>] [-b <branch>] ... Keep another project in a subdirectory, or split its history back out",
+            "  bugreport [-o <path>] [--stdout]  Write a report on ash and this repository to attach to an issue",
+            "  bisect start [<bad> [<good>...]] | bad | good | skip [<rev>...] | reset [<commit>] | run <cmd>... Find the commit that introduced a bug by binary search",
+            "  remote [-v] | add <name> <url> | rename <old> <new> | remove <name> | show <name> | prune [-n] <name> | set-head <name> (-a | -d | <branch>) Manage the set of tracked repositories",
+            "  blame [--abbrev[=<n>]] [-l] [--incremental] [--ignore-rev <rev>] [--ignore-revs-file <file>] [<rev>] <file> Show what revision last modified each line",
+            "  repack [-a] [-d] [--[no-]write-commit-graph] [-b|--[no-]write-bitmaps]  Pack loose objects, or with -a everything, into one pack",
+            "  gc [--auto [--[no-]detach]] [--prune=<date>|--no-prune] [--[no-]write-commit-graph] [--[no-]write-bitmaps]  Prune unreachable objects, pack the rest and pack refs",
+            "  tag [-a | -s | -u <key-id>] [-m <msg>] [-f] <name> [<commit>] | -d <name>... | -v <name>... | [-l [<pattern>]] [--contains <commit>] [--no-contains <commit>] [--points-at <object>] [--sort=<key>] [--[no-]column[=<opts>]] Manage tags",
+            "  reflog [show] [<ref>]             Show where HEAD or a ref has pointed, newest first",
+            "  request-pull <start> <url> [<end>] Summarize changes since <start> for others to pull",
+            "  daemon [--base-path=<dir>] [--export-all] [--listen=<host>] [--port=<n>] [<directory>...] Serve repositories read-only over ash:// URLs",
+            "  stash [push] [-u] [-k] [-m <msg>] | pop | apply | drop [<stash>] | list | show [-p] [<stash>] Stash local changes, or manage stash entries",
+            "  log [--oneline] [--graph] [-p [-m|--cc]] [--stat|--shortstat] [--reverse] [--boundary] [--ancestry-path] [-n <n>] [--skip=<n>] [--since=<date>] [--until=<date>] [--author=<re>] [--committer=<re>] [--grep=<re>] [--follow] [-L <start>,<end>:<file>] [<range>...] [--] [<path>...] Show commit logs",
+            "  merge <branch>... [-m <msg>] [--autostash] [--summary] [--squash] [-s <strategy>] [-X ours|theirs] Merge the specified branch into the current branch",
             "        --abort                     Abort the current merge resolution process",
             "        --continue                  Continue the merge after resolving conflicts",
             "        --tool=<tool>               Use specified tool to resolve merge conflicts",
             "        --tool-only                 Run merge tool to resolve conflicts without merging",
             "Common Options:",
-            "  (Options specific to commands listed above)",
+            "  -q, --quiet                      Only print errors and command results (or set ASH_QUIET=1)",
             "  --help                           Display this help message"
         )
     }
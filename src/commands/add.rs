@@ -7,15 +7,21 @@ use crate::core::database::database::Database;
 use crate::core::database::tree::{Tree, TreeEntry, TREE_MODE};
 use crate::core::database::commit::Commit;
 use crate::core::index::index::Index;
+use crate::core::ignore::IGNORE_FILE;
 use crate::core::workspace::Workspace;
 use crate::core::refs::Refs;
 use crate::errors::error::Error;
 use std::fs;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 pub struct AddCommand;
 
 impl AddCommand {
-    pub fn execute(paths: &[String]) -> Result<(), Error> {
+    /// Stage the files under `paths`, and remove the deleted ones. With
+    /// `intent_to_add`, untracked files are only recorded as paths to be
+    /// added, leaving their content out of the index for now.
+    pub fn execute(paths: &[String], intent_to_add: bool) -> Result<(), Error> {
         let start_time = Instant::now();
         
         if paths.is_empty() {
@@ -23,7 +29,7 @@ impl AddCommand {
         }
     
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Verify .ash directory exists
         if !git_path.exists() {
@@ -31,7 +37,7 @@ impl AddCommand {
         }
         
         let workspace = Workspace::new(root_path);
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = Index::new(git_path.join("index"));
         let refs = Refs::new(&git_path);
         
@@ -39,6 +45,8 @@ impl AddCommand {
         let mut files_to_add: HashSet<PathBuf> = HashSet::new();
         let mut files_to_delete: HashSet<String> = HashSet::new();
         let mut had_missing_valid_files = false;
+        // Named on the command line but ignored, so left out
+        let mut ignored_paths: Vec<String> = Vec::new();
         
         // Try to acquire the lock on the index
         if !index.load_for_update()? {
@@ -85,7 +93,7 @@ impl AddCommand {
                 
                 // Handle case where the path is exactly a file in the index
                 if existing_oids.contains_key(&rel_path_str) {
-                    println!("File {} has been deleted, will remove from index", rel_path_str);
+                    trace!("File {} has been deleted, will remove from index", rel_path_str);
                     files_to_delete.insert(rel_path_str);
                     continue;
                 }
@@ -102,7 +110,7 @@ impl AddCommand {
                 // Find all entries that start with this prefix (meaning they're in this directory)
                 for key in existing_oids.keys() {
                     if key.starts_with(&prefix_to_check) || key == &rel_path_str {
-                        println!("Found index entry {} under directory {}, will remove", key, rel_path_str);
+                        trace!("Found index entry {} under directory {}, will remove", key, rel_path_str);
                         files_to_delete.insert(key.clone());
                         has_matches = true;
                     }
@@ -123,7 +131,11 @@ impl AddCommand {
             match workspace.list_files_from(&path, &existing_oids) {
                 Ok((found_files, missing_files)) => {
                     if found_files.is_empty() && missing_files.is_empty() {
-                        println!("warning: '{}' didn't match any files", path_str);
+                        if workspace.is_ignored(&path, workspace.root_path.join(&path).is_dir()) {
+                            ignored_paths.push(path_str.clone());
+                        } else {
+                            println!("warning: '{}' didn't match any files", path_str);
+                        }
                     } else {
                         // Add found files to set
                         for file in found_files {
@@ -144,6 +156,13 @@ impl AddCommand {
             }
         }
         
+        if !ignored_paths.is_empty() {
+            println!("The following paths are ignored by one of your {} files:", IGNORE_FILE);
+            for path in &ignored_paths {
+                println!("{}", path);
+            }
+        }
+        
         // If any paths were invalid (not in workspace or index), exit without modifying the index
         if had_missing_valid_files && !has_deleted_dirs && files_to_add.is_empty() && files_to_delete.is_empty() {
             index.rollback()?;
@@ -153,7 +172,7 @@ impl AddCommand {
         // If no files were found to add or delete, exit early
         if files_to_add.is_empty() && files_to_delete.is_empty() {
             index.rollback()?;
-            println!("No files to add or remove");
+            trace!("No files to add or remove");
             return Ok(());
         }
         
@@ -163,7 +182,7 @@ impl AddCommand {
                 index.keys.remove(path_str);
                 index.changed = true;
                 deleted_count += 1;
-                println!("Removed {} from index", path_str);
+                trace!("Removed {} from index", path_str);
             }
         }
         
@@ -184,7 +203,16 @@ impl AddCommand {
                     // Pre-compute hash to check if the file has changed
                     let new_oid = database.hash_file_data(&data);
                     
-                    if let Some(old_oid) = existing_oids.get(&file_key) {
+                    // Only untracked files are recorded as intent-to-add
+                    if intent_to_add && existing_oids.contains_key(&file_key) {
+                        unchanged_count += 1;
+                        continue;
+                    }
+
+                    // A conflicted entry is resolved by adding it, whatever its
+                    // content, and an intent-to-add one has none staged yet
+                    let conflicted = index.get_entry(&file_key).is_some_and(|entry| entry.stage != 0 || entry.intent_to_add);
+                    if let (Some(old_oid), false) = (existing_oids.get(&file_key), conflicted) {
                         if old_oid == &new_oid {
                             // File exists in index with same content, skip it
                             unchanged_count += 1;
@@ -224,8 +252,8 @@ impl AddCommand {
         
         // Second pass: process all files that need to be updated
         for (file_path, data, stat) in blobs_to_save {
-            // Create and store the blob
-            let mut blob = Blob::new(data);
+            // Create and store the blob, the empty one for an intent-to-add path
+            let mut blob = Blob::new(if intent_to_add { Vec::new() } else { data });
             if let Err(e) = database.store(&mut blob) {
                 // Release the lock if we fail to store the blob
                 index.rollback()?;
@@ -247,7 +275,9 @@ impl AddCommand {
             };
             
             // Add to index
-            if let Err(e) = index.add(&file_path, oid, &stat) {
+            if intent_to_add {
+                index.add_intent_to_add(&file_path, oid, &stat);
+            } else if let Err(e) = index.add(&file_path, oid, &stat) {
                 index.rollback()?;
                 return Err(e);
             }
@@ -258,10 +288,10 @@ impl AddCommand {
             // Determine if it's a new or modified file
             let file_path_str = file_path.to_string_lossy().to_string();
             if existing_oids.contains_key(&file_path_str) {
-                println!("Modified file: {}", file_path_str);
+                trace!("Modified file: {}", file_path_str);
                 modified_files += 1;
             } else {
-                println!("New file: {}", file_path_str);
+                trace!("New file: {}", file_path_str);
                 new_files += 1;
             }
             
@@ -278,23 +308,23 @@ impl AddCommand {
                 
                 // Only load from HEAD if we have a commit
                 if let Ok(Some(head_oid)) = refs.read_head() {
-                    println!("Examining HEAD commit: {}", head_oid);
+                    trace!("Examining HEAD commit: {}", head_oid);
                     
                     if let Ok(commit_obj) = database.load(&head_oid) {
                         if let Some(commit) = commit_obj.as_any().downcast_ref::<Commit>() {
                             let root_tree_oid = commit.get_tree();
-                            println!("Root tree OID: {}", root_tree_oid);
+                            trace!("Root tree OID: {}", root_tree_oid);
                             
                             // Recursively collect all files from HEAD tree
                             Self::collect_files_from_tree(&mut database, root_tree_oid, PathBuf::new(), &mut head_files)?;
                             
-                            println!("Found {} files in HEAD", head_files.len());
+                            trace!("Found {} files in HEAD", head_files.len());
                         }
                     }
                 }
                 
                 // Count how many files are new vs modified
-                println!("Debug -- files_to_add: {}, added_count: {}, processed_paths: {}", 
+                trace!("Debug -- files_to_add: {}, added_count: {}, processed_paths: {}", 
                          files_to_add.len(), added_count, processed_paths.len());
                 
                 // Format output message
@@ -338,7 +368,7 @@ impl AddCommand {
                 }
                 
                 if unchanged_count > 0 {
-                    println!(
+                    trace!(
                         "{} added to index, {} file{} unchanged ({:.2}s)",
                         message,
                         unchanged_count,
@@ -346,7 +376,7 @@ impl AddCommand {
                         elapsed.as_secs_f32()
                     );
                 } else {
-                    println!(
+                    trace!(
                         "{} added to index ({:.2}s)",
                         message,
                         elapsed.as_secs_f32()
@@ -360,7 +390,7 @@ impl AddCommand {
         } else if unchanged_count > 0 {
             // If we didn't add any files, release the lock
             index.rollback()?;
-            println!(
+            trace!(
                 "No files changed, {} file{} already up to date",
                 unchanged_count,
                 if unchanged_count == 1 { "" } else { "s" }
@@ -369,7 +399,7 @@ impl AddCommand {
         } else {
             // If we didn't add any files, release the lock
             index.rollback()?;
-            println!("No changes were made to the index");
+            trace!("No changes were made to the index");
             Ok(())
         }
     }
@@ -381,7 +411,7 @@ impl AddCommand {
         prefix: PathBuf,
         files: &mut HashMap<String, String>
     ) -> Result<(), Error> {
-        println!("Traversing tree: {} at path: {}", tree_oid, prefix.display());
+        trace!("Traversing tree: {} at path: {}", tree_oid, prefix.display());
         
         // Load the object
         let obj = database.load(tree_oid)?;
@@ -402,18 +432,18 @@ impl AddCommand {
                     TreeEntry::Blob(oid, mode) => {
                         // If this is a directory entry masquerading as a blob
                         if *mode == TREE_MODE || mode.is_directory() {
-                            println!("Found directory stored as blob: {} -> {}", entry_path_str, oid);
+                            trace!("Found directory stored as blob: {} -> {}", entry_path_str, oid);
                             // Recursively process this directory
                             Self::collect_files_from_tree(database, oid, entry_path, files)?;
                         } else {
                             // Regular file
-                            println!("Found file: {} -> {}", entry_path_str, oid);
+                            trace!("Found file: {} -> {}", entry_path_str, oid);
                             files.insert(entry_path_str, oid.clone());
                         }
                     },
                     TreeEntry::Tree(subtree) => {
                         if let Some(subtree_oid) = subtree.get_oid() {
-                            println!("Found directory: {} -> {}", entry_path_str, subtree_oid);
+                            trace!("Found directory: {} -> {}", entry_path_str, subtree_oid);
                             // Recursively process this directory
                             Self::collect_files_from_tree(database, subtree_oid, entry_path, files)?;
                         } else {
@@ -428,13 +458,13 @@ impl AddCommand {
         
         // If object is a blob, try to parse it as a tree
         if obj.get_type() == "blob" {
-            println!("Object is a blob, attempting to parse as tree...");
+            trace!("Object is a blob, attempting to parse as tree...");
             
             // Attempt to parse blob as a tree (this handles directories stored as blobs)
             let blob_data = obj.to_bytes();
             match Tree::parse(&blob_data) {
                 Ok(parsed_tree) => {
-                    println!("Successfully parsed blob as tree with {} entries", parsed_tree.get_entries().len());
+                    trace!("Successfully parsed blob as tree with {} entries", parsed_tree.get_entries().len());
                     
                     // Process each entry in the parsed tree
                     for (name, entry) in parsed_tree.get_entries() {
@@ -449,17 +479,17 @@ impl AddCommand {
                         match entry {
                             TreeEntry::Blob(oid, mode) => {
                                 if *mode == TREE_MODE || mode.is_directory() {
-                                    println!("Found directory in parsed tree: {} -> {}", entry_path_str, oid);
+                                    trace!("Found directory in parsed tree: {} -> {}", entry_path_str, oid);
                                     // Recursively process this directory
                                     Self::collect_files_from_tree(database, oid, entry_path, files)?;
                                 } else {
-                                    println!("Found file in parsed tree: {} -> {}", entry_path_str, oid);
+                                    trace!("Found file in parsed tree: {} -> {}", entry_path_str, oid);
                                     files.insert(entry_path_str, oid.clone());
                                 }
                             },
                             TreeEntry::Tree(subtree) => {
                                 if let Some(subtree_oid) = subtree.get_oid() {
-                                    println!("Found directory in parsed tree: {} -> {}", entry_path_str, subtree_oid);
+                                    trace!("Found directory in parsed tree: {} -> {}", entry_path_str, subtree_oid);
                                     // Recursively process this directory
                                     Self::collect_files_from_tree(database, subtree_oid, entry_path, files)?;
                                 } else {
@@ -475,12 +505,12 @@ impl AddCommand {
                     // If we're at a non-root path, this might be a file
                     if !prefix.as_os_str().is_empty() {
                         let path_str = prefix.to_string_lossy().to_string();
-                        println!("Adding file at path: {} -> {}", path_str, tree_oid);
+                        trace!("Adding file at path: {} -> {}", path_str, tree_oid);
                         files.insert(path_str, tree_oid.to_string());
                         return Ok(());
                     }
                     
-                    println!("Failed to parse blob as tree: {}", e);
+                    trace!("Failed to parse blob as tree: {}", e);
                 }
             }
         }
@@ -492,7 +522,7 @@ impl AddCommand {
             for (path, oid) in files.clone() {  // Clone to avoid borrowing issues
                 // Only look at top-level directory entries (no path separators)
                 if !path.contains('/') {
-                    println!("Checking top-level entry for deeper traversal: {} -> {}", path, oid);
+                    trace!("Checking top-level entry for deeper traversal: {} -> {}", path, oid);
                     
                     // Try to load and traverse it as a directory
                     let dir_path = PathBuf::from(&path);
@@ -504,7 +534,7 @@ impl AddCommand {
             }
         }
         
-        println!("Object {} is neither a tree nor a blob that can be parsed as a tree", tree_oid);
+        trace!("Object {} is neither a tree nor a blob that can be parsed as a tree", tree_oid);
         Ok(())
     }
 }
\ No newline at end of file
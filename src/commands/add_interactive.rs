@@ -0,0 +1,578 @@
+// src/commands/add_interactive.rs - interactive index curation (`ash add -i` / `ash add -p`)
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::color::Color;
+use crate::core::database::blob::Blob;
+use crate::core::database::database::Database;
+use crate::core::diff::diff;
+use crate::core::diff::hunk::{self, Hunk};
+use crate::core::diff::myers::{diff_lines, format_diff, is_binary_content, Edit};
+use crate::core::file_mode::FileMode;
+use crate::core::index::index::Index;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::Refs;
+use crate::core::workspace::Workspace;
+use crate::errors::error::Error;
+use crate::core::repository::git_dir;
+
+const MENU: [&str; 8] = ["status", "update", "revert", "add untracked", "patch", "diff", "quit", "help"];
+
+const HELP: &str = "status        - show paths with changes
+update        - add working tree state to the staged set of changes
+revert        - revert staged set of changes back to the HEAD version
+add untracked - add contents of untracked files to the staged set of changes
+patch         - pick hunks and update selectively
+diff          - view diff between HEAD and index
+quit          - quit";
+
+const PATCH_HELP: &str = "y - stage this hunk
+n - do not stage this hunk
+q - quit; do not stage this hunk or any of the remaining ones
+a - stage this hunk and all later hunks in the file
+d - do not stage this hunk or any of the later hunks in the file
+? - print help";
+
+// Summary of a tracked path that differs between HEAD, index and workspace
+struct FileStatus {
+    path: String,
+    staged: Option<(usize, usize)>,
+    unstaged: Option<(usize, usize)>,
+}
+
+// Result of walking the hunks of a single file in patch mode
+enum PatchOutcome {
+    Continue,
+    Quit,
+}
+
+pub struct AddInteractiveCommand<'a> {
+    git_path: PathBuf,
+    workspace: Workspace,
+    database: Database,
+    index: Index,
+    head_files: HashMap<String, String>,
+    head_modes: HashMap<String, FileMode>,
+    input: Box<dyn BufRead + 'a>,
+}
+
+impl<'a> AddInteractiveCommand<'a> {
+    /// Run the interactive menu, or go straight to hunk selection when `patch_only` is set
+    pub fn execute(patch_only: bool, paths: &[String]) -> Result<(), Error> {
+        let root_path = Path::new(".");
+        let git_path = git_dir::resolve(root_path);
+
+        if !git_path.exists() {
+            return Err(Error::Generic("Not an ash repository (or any of the parent directories): .ash directory not found".into()));
+        }
+
+        let stdin = io::stdin();
+        let mut session = AddInteractiveCommand {
+            git_path: git_path.clone(),
+            workspace: Workspace::new(root_path),
+            database: Database::new(git_dir::objects(&git_path)),
+            index: Index::new(git_path.join("index")),
+            head_files: HashMap::new(),
+            head_modes: HashMap::new(),
+            input: Box::new(stdin.lock()),
+        };
+
+        if !session.index.load_for_update()? {
+            return Err(Error::Lock(
+                "Unable to acquire lock on index. Another process may be using it.".to_string()
+            ));
+        }
+
+        let result = session.load_head_files().and_then(|_| {
+            if patch_only {
+                session.patch_paths(paths, false).map(|_| ())
+            } else {
+                session.main_loop()
+            }
+        });
+
+        match result {
+            Ok(()) => {
+                session.index.write_updates()?;
+                Ok(())
+            },
+            Err(e) => {
+                session.index.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    fn load_head_files(&mut self) -> Result<(), Error> {
+        let refs = Refs::new(&self.git_path);
+        if let Some(head_oid) = refs.read_head()? {
+            let diff = self.database.tree_diff(None, Some(&head_oid), &PathFilter::new())?;
+            for (path, (_, new_entry)) in diff {
+                if let Some(entry) = new_entry {
+                    let path = path.to_string_lossy().to_string();
+                    self.head_modes.insert(path.clone(), entry.get_file_mode());
+                    self.head_files.insert(path, entry.get_oid().to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn main_loop(&mut self) -> Result<(), Error> {
+        self.print_status()?;
+
+        loop {
+            println!("{}", Color::bold("*** Commands ***"));
+            let menu: Vec<String> = MENU.iter().enumerate()
+                .map(|(i, name)| format!("  {}: {}", i + 1, name))
+                .collect();
+            for row in menu.chunks(4) {
+                println!("{}", row.join("\t"));
+            }
+
+            let answer = match self.prompt("What now")? {
+                Some(answer) => answer,
+                None => break,
+            };
+
+            let command = match answer.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= MENU.len() => MENU[n - 1],
+                _ => match MENU.iter().find(|name| !answer.is_empty() && name.starts_with(&answer)) {
+                    Some(name) => *name,
+                    None => {
+                        println!("Huh ({})?", answer);
+                        continue;
+                    }
+                },
+            };
+
+            match command {
+                "status" => self.print_status()?,
+                "update" => self.update()?,
+                "revert" => self.revert()?,
+                "add untracked" => self.add_untracked()?,
+                "patch" => {
+                    self.patch_paths(&[], true)?;
+                },
+                "diff" => self.show_staged_diff()?,
+                "help" => println!("{}", HELP),
+                _ => {
+                    println!("Bye.");
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Read one answer from the user; None means end of input
+    fn prompt(&mut self, label: &str) -> Result<Option<String>, Error> {
+        print!("{}> ", Color::blue(label));
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    }
+
+    // Present a numbered list and return the indexes chosen by the user
+    fn select(&mut self, label: &str, items: &[String]) -> Result<Vec<usize>, Error> {
+        if items.is_empty() {
+            println!("No {}.", if label == "Add untracked" { "untracked files" } else { "changes" });
+            return Ok(Vec::new());
+        }
+
+        for (i, item) in items.iter().enumerate() {
+            println!("{:>3}: {}", i + 1, item);
+        }
+
+        let answer = match self.prompt(&format!("{}>", label))? {
+            Some(answer) => answer,
+            None => return Ok(Vec::new()),
+        };
+
+        Ok(parse_selection(&answer, items.len()))
+    }
+
+    fn collect_status(&mut self) -> Result<Vec<FileStatus>, Error> {
+        let mut paths: Vec<String> = self.index.each_entry()
+            .filter(|entry| entry.stage == 0)
+            .map(|entry| entry.get_path().to_string())
+            .collect();
+        for path in self.head_files.keys() {
+            if !self.index.tracked(path) {
+                paths.push(path.clone());
+            }
+        }
+        paths.sort();
+        paths.dedup();
+
+        let mut result = Vec::new();
+        for path in paths {
+            let head_oid = self.head_files.get(&path).cloned();
+            let index_oid = self.index.get_entry(&path).map(|e| e.get_oid().to_string());
+            // An intent-to-add path has nothing staged yet
+            let staged_oid = index_oid.clone().filter(|_| !self.index.get_entry(&path).is_some_and(|e| e.intent_to_add));
+
+            let staged = if head_oid != staged_oid {
+                let old = self.blob_content(head_oid.as_deref())?;
+                let new = self.blob_content(staged_oid.as_deref())?;
+                Some(line_stats(&old, &new))
+            } else {
+                None
+            };
+
+            let unstaged = match &index_oid {
+                Some(oid) => {
+                    let worktree = self.worktree_content(&path);
+                    let worktree_oid = worktree.as_ref().map(|data| self.database.hash_file_data(data));
+                    if worktree_oid.as_deref() != Some(oid.as_str()) {
+                        let old = self.blob_content(Some(oid))?;
+                        Some(line_stats(&old, &worktree.unwrap_or_default()))
+                    } else {
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            if staged.is_some() || unstaged.is_some() {
+                result.push(FileStatus { path, staged, unstaged });
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn print_status(&mut self) -> Result<(), Error> {
+        let statuses = self.collect_status()?;
+        if statuses.is_empty() {
+            println!("No changes.");
+            return Ok(());
+        }
+
+        println!("     {:>13}{:>13} path", "staged", "unstaged");
+        for (i, status) in statuses.iter().enumerate() {
+            println!("{:>3}: {}", i + 1, format_status_row(status));
+        }
+        println!();
+        Ok(())
+    }
+
+    fn update(&mut self) -> Result<(), Error> {
+        let candidates: Vec<FileStatus> = self.collect_status()?
+            .into_iter()
+            .filter(|status| status.unstaged.is_some())
+            .collect();
+        let rows: Vec<String> = candidates.iter().map(format_status_row).collect();
+
+        let chosen = self.select("Update", &rows)?;
+        for i in &chosen {
+            self.stage_whole_file(&candidates[*i].path)?;
+        }
+        if !chosen.is_empty() {
+            println!("updated {} path{}", chosen.len(), if chosen.len() == 1 { "" } else { "s" });
+        }
+        Ok(())
+    }
+
+    fn revert(&mut self) -> Result<(), Error> {
+        let candidates: Vec<FileStatus> = self.collect_status()?
+            .into_iter()
+            .filter(|status| status.staged.is_some())
+            .collect();
+        let rows: Vec<String> = candidates.iter().map(format_status_row).collect();
+
+        let chosen = self.select("Revert", &rows)?;
+        for i in &chosen {
+            let path = candidates[*i].path.clone();
+            match self.head_files.get(&path).cloned() {
+                Some(oid) => {
+                    let size = self.blob_content(Some(&oid))?.len();
+                    let mode = self.head_modes.get(&path).copied().unwrap_or(FileMode::REGULAR);
+                    self.index.add_blob(Path::new(&path), &oid, mode, size);
+                },
+                None => self.index.remove(Path::new(&path))?,
+            }
+        }
+        if !chosen.is_empty() {
+            println!("reverted {} path{}", chosen.len(), if chosen.len() == 1 { "" } else { "s" });
+        }
+        Ok(())
+    }
+
+    fn add_untracked(&mut self) -> Result<(), Error> {
+        let mut untracked: Vec<String> = self.workspace.list_files()?
+            .into_iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .filter(|path| !self.index.tracked(path))
+            .collect();
+        untracked.sort();
+
+        let chosen = self.select("Add untracked", &untracked)?;
+        for i in &chosen {
+            self.stage_whole_file(&untracked[*i])?;
+        }
+        if !chosen.is_empty() {
+            println!("added {} path{}", chosen.len(), if chosen.len() == 1 { "" } else { "s" });
+        }
+        Ok(())
+    }
+
+    fn show_staged_diff(&mut self) -> Result<(), Error> {
+        let candidates: Vec<FileStatus> = self.collect_status()?
+            .into_iter()
+            .filter(|status| status.staged.is_some())
+            .collect();
+        let rows: Vec<String> = candidates.iter().map(format_status_row).collect();
+
+        let chosen = self.select("Review diff", &rows)?;
+        for i in chosen {
+            let path = candidates[i].path.clone();
+            let head_oid = self.head_files.get(&path).cloned();
+            let index_oid = self.index.get_entry(&path).map(|e| e.get_oid().to_string());
+            let old = self.blob_content(head_oid.as_deref())?;
+            let new = self.blob_content(index_oid.as_deref())?;
+
+            println!("{}", Color::bold(&format!("diff --ash a/{} b/{}", path, path)));
+            if is_binary_content(&old) || is_binary_content(&new) {
+                println!("Binary files a/{} and b/{} differ", path, path);
+                continue;
+            }
+            let a_lines = diff::split_lines(&String::from_utf8_lossy(&old));
+            let b_lines = diff::split_lines(&String::from_utf8_lossy(&new));
+            let edits = diff_lines(&a_lines, &b_lines);
+            print!("{}", diff::colorize_diff(&format_diff(&a_lines, &b_lines, &edits, 3)));
+        }
+        Ok(())
+    }
+
+    // Walk the hunks of every modified file (optionally restricted to `paths`).
+    // From the menu the user first picks which files to patch.
+    fn patch_paths(&mut self, paths: &[String], from_menu: bool) -> Result<PatchOutcome, Error> {
+        let mut candidates: Vec<String> = self.collect_status()?
+            .into_iter()
+            .filter(|status| status.unstaged.is_some())
+            .map(|status| status.path)
+            .filter(|path| paths.is_empty() || paths.iter().any(|p| {
+                let p = p.trim_end_matches('/');
+                p == "." || path == p || path.starts_with(&format!("{}/", p))
+            }))
+            .collect();
+
+        if candidates.is_empty() {
+            println!("No changes.");
+            return Ok(PatchOutcome::Continue);
+        }
+
+        if from_menu {
+            let rows = candidates.clone();
+            let chosen = self.select("Patch update", &rows)?;
+            candidates = chosen.into_iter().map(|i| rows[i].clone()).collect();
+        }
+
+        for path in candidates {
+            if let PatchOutcome::Quit = self.patch_file(&path)? {
+                return Ok(PatchOutcome::Quit);
+            }
+        }
+        Ok(PatchOutcome::Continue)
+    }
+
+    fn patch_file(&mut self, path: &str) -> Result<PatchOutcome, Error> {
+        let index_oid = match self.index.get_entry(path) {
+            Some(entry) => entry.get_oid().to_string(),
+            None => return Ok(PatchOutcome::Continue),
+        };
+        let old = self.blob_content(Some(&index_oid))?;
+        let new = match self.worktree_content(path) {
+            Some(data) => data,
+            None => {
+                // Deletions are staged as a whole
+                println!("{}", Color::bold(&format!("deleted file {}", path)));
+                if self.ask_yes_no("Stage deletion [y,n]?")? == Some(true) {
+                    self.index.remove(Path::new(path))?;
+                }
+                return Ok(PatchOutcome::Continue);
+            }
+        };
+
+        if is_binary_content(&old) || is_binary_content(&new) {
+            println!("Binary file {} is not supported in patch mode; use update instead", path);
+            return Ok(PatchOutcome::Continue);
+        }
+
+        let old_text = String::from_utf8_lossy(&old).to_string();
+        let new_text = String::from_utf8_lossy(&new).to_string();
+        let a_lines = diff::split_lines(&old_text);
+        let b_lines = diff::split_lines(&new_text);
+        let edits = diff_lines(&a_lines, &b_lines);
+        let hunks = hunk::build_hunks(&a_lines, &b_lines, &edits, 3);
+        if hunks.is_empty() {
+            return Ok(PatchOutcome::Continue);
+        }
+
+        println!("{}", Color::bold(&format!("diff --ash a/{} b/{}", path, path)));
+        let mut accepted = vec![false; hunks.len()];
+        let mut outcome = PatchOutcome::Continue;
+        let mut i = 0;
+
+        while i < hunks.len() {
+            print_hunk(&hunks[i]);
+            let label = format!("({}/{}) Stage this hunk [y,n,q,a,d,?]?", i + 1, hunks.len());
+            let answer = match self.prompt(&label)? {
+                Some(answer) => answer,
+                None => {
+                    outcome = PatchOutcome::Quit;
+                    break;
+                }
+            };
+
+            match answer.chars().next() {
+                Some('y') => accepted[i] = true,
+                Some('n') => {},
+                Some('a') => {
+                    for flag in accepted.iter_mut().skip(i) {
+                        *flag = true;
+                    }
+                    break;
+                },
+                Some('d') => break,
+                Some('q') => {
+                    outcome = PatchOutcome::Quit;
+                    break;
+                },
+                _ => {
+                    println!("{}", Color::red(PATCH_HELP));
+                    continue;
+                }
+            }
+            i += 1;
+        }
+
+        if accepted.iter().any(|flag| *flag) {
+            let lines = hunk::apply_hunks(&a_lines, &b_lines, &edits, &hunks, &accepted);
+            let trailing = if accepted.iter().all(|flag| *flag) {
+                new_text.ends_with('\n')
+            } else {
+                old_text.ends_with('\n') || new_text.ends_with('\n')
+            };
+            let content = hunk::join_lines(&lines, trailing).into_bytes();
+            self.stage_content(path, content)?;
+        }
+
+        Ok(outcome)
+    }
+
+    fn ask_yes_no(&mut self, label: &str) -> Result<Option<bool>, Error> {
+        Ok(self.prompt(label)?.map(|answer| answer.starts_with('y')))
+    }
+
+    fn stage_whole_file(&mut self, path: &str) -> Result<(), Error> {
+        let file_path = Path::new(path);
+        if !self.workspace.path_exists(file_path)? {
+            return self.index.remove(file_path);
+        }
+
+        let data = self.workspace.read_file(file_path)?;
+        let stat = self.workspace.stat_file(file_path)?;
+        let mut blob = Blob::new(data);
+        self.database.store(&mut blob)?;
+        let oid = blob.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Blob OID not set after storage".into()))?;
+        self.index.add(file_path, &oid, &stat)
+    }
+
+    fn stage_content(&mut self, path: &str, content: Vec<u8>) -> Result<(), Error> {
+        let size = content.len();
+        let mut blob = Blob::new(content);
+        self.database.store(&mut blob)?;
+        let oid = blob.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Blob OID not set after storage".into()))?;
+        // The mode the path is tracked with, since the file may be gone
+        let mode = match self.index.get_entry(path) {
+            Some(entry) => *entry.get_mode(),
+            None => self.head_modes.get(path).copied().unwrap_or(FileMode::REGULAR),
+        };
+        self.index.add_blob(Path::new(path), &oid, mode, size);
+        Ok(())
+    }
+
+    fn blob_content(&mut self, oid: Option<&str>) -> Result<Vec<u8>, Error> {
+        match oid {
+            Some(oid) => Ok(self.database.load(oid)?.to_bytes()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn worktree_content(&self, path: &str) -> Option<Vec<u8>> {
+        self.workspace.read_file(Path::new(path)).ok()
+    }
+}
+
+fn line_stats(old: &[u8], new: &[u8]) -> (usize, usize) {
+    if is_binary_content(old) || is_binary_content(new) {
+        return (0, 0);
+    }
+    let a_lines = diff::split_lines(&String::from_utf8_lossy(old));
+    let b_lines = diff::split_lines(&String::from_utf8_lossy(new));
+    let edits = diff_lines(&a_lines, &b_lines);
+    let added = edits.iter().filter(|e| matches!(e, Edit::Insert(_))).count();
+    let removed = edits.iter().filter(|e| matches!(e, Edit::Delete(_))).count();
+    (added, removed)
+}
+
+fn format_status_row(status: &FileStatus) -> String {
+    let describe = |stats: &Option<(usize, usize)>| match stats {
+        Some((added, removed)) => format!("+{}/-{}", added, removed),
+        None => "unchanged".to_string(),
+    };
+    format!("{:>13}{:>13} {}", describe(&status.staged), describe(&status.unstaged), status.path)
+}
+
+fn print_hunk(hunk: &Hunk) {
+    println!("{}", Color::cyan(&hunk.header()));
+    for line in &hunk.lines {
+        if line.starts_with('+') {
+            println!("{}", Color::green(line));
+        } else if line.starts_with('-') {
+            println!("{}", Color::red(line));
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+// Parse answers such as "1", "2-4", "1,3 5" or "*" into zero-based indexes
+fn parse_selection(answer: &str, count: usize) -> Vec<usize> {
+    let mut chosen = Vec::new();
+
+    for token in answer.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+        if token == "*" {
+            return (0..count).collect();
+        }
+
+        let (start, end) = match token.split_once('-') {
+            Some((start, end)) => (start.parse::<usize>().ok(), end.parse::<usize>().ok().or(Some(count))),
+            None => {
+                let n = token.parse::<usize>().ok();
+                (n, n)
+            }
+        };
+
+        if let (Some(start), Some(end)) = (start, end) {
+            for n in start.max(1)..=end.min(count) {
+                if !chosen.contains(&(n - 1)) {
+                    chosen.push(n - 1);
+                }
+            }
+        }
+    }
+
+    chosen
+}
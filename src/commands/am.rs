@@ -0,0 +1,274 @@
+// src/commands/am.rs - apply a mailbox of patches as commits
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::commands::apply::{ApplyCommand, ApplyOptions};
+use crate::commands::commit_writer::CommitWriter;
+use crate::commands::rebase::RebaseCommand;
+use crate::commands::reset::{ResetCommand, ORIG_HEAD};
+use crate::core::diff::patch::{self, FilePatch};
+use crate::core::mailbox::Mail;
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+// The state of a session under the git dir: the mails numbered from 0001,
+// the number of the one being applied and of the last, and how it started
+pub const STATE_DIR: &str = "rebase-apply";
+const NEXT_FILE: &str = "next";
+const LAST_FILE: &str = "last";
+const THREE_WAY_FILE: &str = "threeway";
+const ORIG_HEAD_FILE: &str = "orig-head";
+
+/// What `am` fails with once it has said which patch stopped it and how to
+/// go on
+pub const AM_STOPPED: &str = "am stopped";
+
+const RESOLVE_ADVICE: &str = "\
+When you have resolved this problem, run \"ash am --continue\".
+If you prefer to skip this patch, run \"ash am --skip\" instead.
+To restore the original branch and stop patching, run \"ash am --abort\".";
+
+/// How `ash am` applies the patches
+#[derive(Debug, Clone, Default)]
+pub struct AmOptions {
+    /// Fall back on a three-way merge with the blobs a patch was made
+    /// against when it does not apply, leaving conflicts to resolve
+    pub three_way: bool,
+}
+
+pub struct AmCommand;
+
+impl AmCommand {
+    /// Split the mailboxes, standard input when none are given, into
+    /// their mails and commit the patch in each on top of HEAD in turn,
+    /// with the author, date and message of the mail. A patch that does
+    /// not apply stops the session, to be resumed with --continue once
+    /// the changes are made by hand, or --skip.
+    pub fn execute(mailboxes: &[String], options: &AmOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let state_path = Self::state_path(&repo.path);
+        if state_path.exists() {
+            return Err(Error::Generic(format!("previous rebase directory .ash/{} still exists but mbox given.", STATE_DIR)));
+        }
+
+        let head_oid = repo.refs.read_head()?;
+        if let Some(head_oid) = &head_oid {
+            repo.index.load()?;
+            if !RebaseCommand::index_matches(&mut repo, head_oid)? {
+                return Err(Error::Generic("Dirty index: cannot apply patches".to_string()));
+            }
+        }
+
+        let mut text = String::new();
+        if mailboxes.is_empty() {
+            io::stdin().read_to_string(&mut text)?;
+        }
+        for mailbox in mailboxes {
+            text.push_str(&fs::read_to_string(mailbox)
+                .map_err(|e| Error::Generic(format!("could not open '{}' for reading: {}", mailbox, e)))?);
+        }
+        let mails = Mail::split(&text);
+        if mails.is_empty() {
+            return Err(Error::Generic("Patch format detection failed.".to_string()));
+        }
+
+        fs::create_dir_all(&state_path)?;
+        for (i, mail) in mails.iter().enumerate() {
+            fs::write(state_path.join(format!("{:04}", i + 1)), mail)?;
+        }
+        fs::write(state_path.join(LAST_FILE), format!("{}\n", mails.len()))?;
+        fs::write(state_path.join(NEXT_FILE), "1\n")?;
+        if let Some(head_oid) = &head_oid {
+            fs::write(state_path.join(ORIG_HEAD_FILE), format!("{}\n", head_oid))?;
+            fs::write(git_dir::resolve(&repo.path).join(ORIG_HEAD), format!("{}\n", head_oid))?;
+        }
+        if options.three_way {
+            fs::write(state_path.join(THREE_WAY_FILE), "")?;
+        }
+        Self::run(&state_path)
+    }
+
+    /// Commit the stopped patch from the index as it now is, and go on
+    pub fn continue_am() -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let state_path = Self::require_session(&repo.path)?;
+        repo.index.load()?;
+        if repo.index.has_conflict() {
+            return Err(Error::Generic(
+                "You still have unmerged paths in your index.\nYou should 'ash add' each file with resolved conflicts to mark them as such.".to_string()
+            ));
+        }
+        let head_oid = repo.refs.read_head()?;
+        let unchanged = match &head_oid {
+            Some(head_oid) => RebaseCommand::index_matches(&mut repo, head_oid)?,
+            None => repo.index.each_entry().next().is_none(),
+        };
+        if unchanged {
+            return Err(Error::Generic(
+                "No changes - did you forget to use 'ash add'?\nIf there is nothing left to stage, chances are that something else\nalready introduced the same changes; you might want to skip this patch.".to_string()
+            ));
+        }
+
+        let next = Self::number(&state_path, NEXT_FILE)?;
+        let mail = Mail::parse(&Self::read_mail(&state_path, next)?)?;
+        println!("Applying: {}", mail.subject);
+        Self::commit(&mut repo, &mail)?;
+        fs::write(state_path.join(NEXT_FILE), format!("{}\n", next + 1))?;
+        Self::run(&state_path)
+    }
+
+    /// Drop the stopped patch, and what of it was applied, and go on
+    pub fn skip() -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let state_path = Self::require_session(&repo.path)?;
+        let next = Self::number(&state_path, NEXT_FILE)?;
+        Self::discard(&mut repo, &state_path, next)?;
+        fs::write(state_path.join(NEXT_FILE), format!("{}\n", next + 1))?;
+        Self::run(&state_path)
+    }
+
+    /// Stop the session and put HEAD, the index and the working tree back
+    /// as they were before it
+    pub fn abort() -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let state_path = Self::require_session(&repo.path)?;
+        let next = Self::number(&state_path, NEXT_FILE)?;
+        Self::discard(&mut repo, &state_path, next)?;
+
+        if let Ok(original) = fs::read_to_string(state_path.join(ORIG_HEAD_FILE)) {
+            let original = original.trim().to_string();
+            if repo.refs.read_head()?.as_deref() != Some(original.as_str()) {
+                ResetCommand::execute(&[original], false, false, true, true, None)?;
+            }
+        }
+        fs::remove_dir_all(&state_path)?;
+        Ok(())
+    }
+
+    /// Stop the session, keeping HEAD, the index and the working tree
+    pub fn quit() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let state_path = Self::require_session(&repo.path)?;
+        fs::remove_dir_all(&state_path)?;
+        Ok(())
+    }
+
+    /// Print the mail of the patch the session stopped at
+    pub fn show_current_patch() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let state_path = Self::require_session(&repo.path)?;
+        let next = Self::number(&state_path, NEXT_FILE)?;
+        print!("{}", Self::read_mail(&state_path, next)?);
+        Ok(())
+    }
+
+    // Apply and commit the mails from `next` on, stopping at the first
+    // that does not apply
+    fn run(state_path: &Path) -> Result<(), Error> {
+        let three_way = state_path.join(THREE_WAY_FILE).exists();
+        let last = Self::number(state_path, LAST_FILE)?;
+        loop {
+            let next = Self::number(state_path, NEXT_FILE)?;
+            if next > last {
+                break;
+            }
+            let mail = Mail::parse(&Self::read_mail(state_path, next)?)?;
+            let patches = patch::parse(&mail.patch, 1)?;
+            println!("Applying: {}", mail.subject);
+            if patches.is_empty() {
+                eprintln!("Patch is empty.");
+                Self::stopped(next, &mail);
+                return Err(Error::Generic(AM_STOPPED.to_string()));
+            }
+
+            let mut repo = Repository::new(".")?;
+            let apply_options = ApplyOptions { index: true, three_way, ..ApplyOptions::default() };
+            if ApplyCommand::apply(&mut repo, &patches, &apply_options).is_err() {
+                if !three_way {
+                    println!("hint: Use 'ash am --show-current-patch' to see the failed patch");
+                }
+                Self::stopped(next, &mail);
+                return Err(Error::Generic(AM_STOPPED.to_string()));
+            }
+            repo.index.load()?;
+            Self::commit(&mut repo, &mail)?;
+            fs::write(state_path.join(NEXT_FILE), format!("{}\n", next + 1))?;
+        }
+        fs::remove_dir_all(state_path)?;
+        Ok(())
+    }
+
+    fn stopped(number: usize, mail: &Mail) {
+        println!("Patch failed at {:04} {}", number, mail.subject);
+        println!("{}", RESOLVE_ADVICE);
+    }
+
+    fn commit(repo: &mut Repository, mail: &Mail) -> Result<(), Error> {
+        let git_path = git_dir::resolve(&repo.path);
+        let parents: Vec<String> = repo.refs.read_head()?.into_iter().collect();
+        let mut writer = CommitWriter::new(
+            Path::new("."),
+            git_path,
+            &mut repo.database,
+            &mut repo.index,
+            &repo.refs,
+        );
+        writer.reflog_action = "am".to_string();
+        writer.write_commit(parents, &mail.message(), Some(mail.author.clone()))?;
+        Ok(())
+    }
+
+    // Put the paths the patch of mail `number` touches back as HEAD has
+    // them, in the index and the working tree, conflicts and all
+    fn discard(repo: &mut Repository, state_path: &Path, number: usize) -> Result<(), Error> {
+        let patches: Vec<FilePatch> = match Mail::parse(&Self::read_mail(state_path, number)?) {
+            Ok(mail) => patch::parse(&mail.patch, 1).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        let head = match repo.refs.read_head()? {
+            Some(head_oid) => head_files(repo, &head_oid)?,
+            None => Default::default(),
+        };
+
+        repo.index.load_for_update()?;
+        for path in patches.iter().flat_map(|patch| [&patch.old_path, &patch.new_path]).flatten() {
+            let file_path = Path::new(path);
+            repo.index.remove(file_path)?;
+            match head.get(path.as_str()) {
+                Some(entry) => {
+                    let data = repo.database.load(entry.get_oid())?.to_bytes();
+                    repo.workspace.write_file(file_path, &data)?;
+                    let stat = repo.workspace.stat_file(file_path)?;
+                    repo.index.add(file_path, entry.get_oid(), &stat)?;
+                },
+                None => repo.workspace.remove_file(file_path)?,
+            }
+        }
+        repo.index.write_updates()?;
+        Ok(())
+    }
+
+    fn state_path(root_path: &Path) -> PathBuf {
+        git_dir::resolve(root_path).join(STATE_DIR)
+    }
+
+    fn require_session(root_path: &Path) -> Result<PathBuf, Error> {
+        let state_path = Self::state_path(root_path);
+        if !state_path.is_dir() {
+            return Err(Error::Generic("Resolve operation not in progress, we are not resuming.".to_string()));
+        }
+        Ok(state_path)
+    }
+
+    fn number(state_path: &Path, file: &str) -> Result<usize, Error> {
+        fs::read_to_string(state_path.join(file))?.trim().parse::<usize>()
+            .map_err(|_| Error::Generic(format!("corrupt am state: {}", file)))
+    }
+
+    fn read_mail(state_path: &Path, number: usize) -> Result<String, Error> {
+        Ok(fs::read_to_string(state_path.join(format!("{:04}", number)))?)
+    }
+}
@@ -0,0 +1,317 @@
+// src/commands/apply.rs - apply patches to the working tree and the index
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::core::database::blob::Blob;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::diff::patch::{self, FilePatch};
+use crate::core::file_mode::FileMode;
+use crate::core::merge::diff3;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+/// What `apply` fails with once it has reported the patches that did not
+/// apply, so that there is nothing left to say
+pub const PATCH_FAILED: &str = "patch does not apply";
+/// What `apply` fails with when a three-way merge left conflicts, which
+/// are in the working tree and the index to resolve
+pub const PATCH_CONFLICTS: &str = "patch applied with conflicts";
+
+/// Where and how `ash apply` applies a patch
+#[derive(Debug, Clone, Default)]
+pub struct ApplyOptions {
+    /// Only see whether the patch applies
+    pub check: bool,
+    /// Apply to the index as well as the working tree, which must match it
+    pub index: bool,
+    /// Apply to the index alone
+    pub cached: bool,
+    pub reverse: bool,
+    /// How many leading components to take off the paths, 1 by default
+    pub strip: Option<usize>,
+    /// How many lines of context must still match on either side when a
+    /// hunk does not apply with all of it
+    pub min_context: Option<usize>,
+    /// When a file patch does not apply, merge it with the blob it was
+    /// made against; implies `index`
+    pub three_way: bool,
+    pub verbose: bool,
+}
+
+impl ApplyOptions {
+    fn uses_index(&self) -> bool {
+        self.index || self.cached || self.three_way
+    }
+}
+
+// What a file patch comes to
+enum Outcome {
+    // The new contents of the file, None when it is deleted
+    Applied(Option<Vec<u8>>),
+    // The merge with conflict markers, and the base, our and their blobs
+    Conflicted(Vec<u8>, [String; 3]),
+}
+
+pub struct ApplyCommand;
+
+impl ApplyCommand {
+    /// Apply the patches in `files`, standard input when there are none or
+    /// for "-". The working tree is patched, or the index with `cached`, or
+    /// both with `index`. Nothing is changed unless every file patch
+    /// applies; those that do not are reported.
+    pub fn execute(files: &[String], options: &ApplyOptions) -> Result<(), Error> {
+        let mut text = String::new();
+        if files.is_empty() {
+            io::stdin().read_to_string(&mut text)?;
+        }
+        for file in files {
+            if file == "-" {
+                io::stdin().read_to_string(&mut text)?;
+            } else {
+                text.push_str(&fs::read_to_string(file)
+                    .map_err(|e| Error::Generic(format!("can't open patch '{}': {}", file, e)))?);
+            }
+        }
+
+        let patches = patch::parse(&text, options.strip.unwrap_or(1))?;
+        if patches.is_empty() {
+            return Err(Error::Generic("No valid patches in input".to_string()));
+        }
+        let mut repo = Repository::new(".")?;
+        Self::apply(&mut repo, &patches, options)
+    }
+
+    /// Apply `patches` to the repository as `options` say, all of them or,
+    /// when one does not apply, none
+    pub fn apply(repo: &mut Repository, patches: &[FilePatch], options: &ApplyOptions) -> Result<(), Error> {
+        let locked = options.uses_index() && !options.check;
+        if locked {
+            repo.index.load_for_update()?;
+        } else {
+            repo.index.load()?;
+        }
+
+        // The files as the patches so far leave them, so that a patch can
+        // build on one before it
+        let mut patched: HashMap<String, Option<Vec<u8>>> = HashMap::new();
+        let mut outcomes = Vec::new();
+        let mut failed = false;
+        for patch in patches {
+            let patch = if options.reverse { patch.reverse() } else { patch.clone() };
+            if options.verbose {
+                eprintln!("Checking patch {}...", patch.path());
+            }
+            match Self::apply_one(repo, &patch, options, &mut patched) {
+                Ok(outcome) => outcomes.push((patch, outcome)),
+                Err(e) => {
+                    eprintln!("error: {}", Self::message(&e));
+                    eprintln!("error: {}: patch does not apply", patch.path());
+                    failed = true;
+                },
+            }
+        }
+        if failed || options.check {
+            if locked {
+                repo.index.rollback()?;
+            }
+            return if failed { Err(Error::Generic(PATCH_FAILED.to_string())) } else { Ok(()) };
+        }
+
+        let mut conflicted = false;
+        for (patch, outcome) in outcomes {
+            if let Some(old_path) = &patch.old_path {
+                if patch.is_deletion() || patch.is_rename() {
+                    if !options.cached {
+                        repo.workspace.remove_file(Path::new(old_path))?;
+                    }
+                    if options.uses_index() {
+                        repo.index.remove(Path::new(old_path))?;
+                    }
+                }
+            }
+            let path = match &patch.new_path {
+                Some(path) => path,
+                None => continue,
+            };
+            match outcome {
+                Outcome::Applied(data) => {
+                    Self::write(repo, path, &data.unwrap_or_default(), patch.mode(), options)?;
+                    if options.verbose {
+                        eprintln!("Applied patch {} cleanly.", path);
+                    }
+                },
+                Outcome::Conflicted(data, [base, ours, theirs]) => {
+                    repo.workspace.write_file(Path::new(path), &data)?;
+                    let entries = [base, ours, theirs].into_iter()
+                        .map(|oid| Some(DatabaseEntry::new(path.clone(), oid, patch.mode())))
+                        .collect();
+                    repo.index.add_conflict(Path::new(path), entries);
+                    eprintln!("Applied patch to '{}' with conflicts.", path);
+                    println!("U {}", path);
+                    conflicted = true;
+                },
+            }
+        }
+        if locked {
+            repo.index.write_updates()?;
+        }
+        if conflicted {
+            return Err(Error::Generic(PATCH_CONFLICTS.to_string()));
+        }
+        Ok(())
+    }
+
+    fn apply_one(
+        repo: &mut Repository,
+        patch: &FilePatch,
+        options: &ApplyOptions,
+        patched: &mut HashMap<String, Option<Vec<u8>>>,
+    ) -> Result<Outcome, Error> {
+        let preimage = match &patch.old_path {
+            Some(path) => Self::preimage(repo, path, options, patched)?,
+            None => {
+                let path = patch.path();
+                let exists = match patched.get(path) {
+                    Some(content) => content.is_some(),
+                    None => !options.cached && repo.workspace.path_exists(Path::new(path))?,
+                };
+                if exists {
+                    return Err(Error::Generic(format!("{}: already exists in working directory", path)));
+                }
+                if options.uses_index() && !patched.contains_key(path) && repo.index.tracked(path) {
+                    return Err(Error::Generic(format!("{}: already exists in index", path)));
+                }
+                Vec::new()
+            },
+        };
+        let text = String::from_utf8(preimage)
+            .map_err(|_| Error::Generic(format!("{}: cannot apply a text patch to a binary file", patch.path())))?;
+
+        let outcome = match patch.apply(&text, options.min_context) {
+            Ok(content) => Outcome::Applied(Some(content.into_bytes())),
+            Err(e) if options.three_way && !patch.is_creation() => Self::merge(repo, patch, &text, options).map_err(|_| e)?,
+            Err(e) => return Err(e),
+        };
+        let outcome = match outcome {
+            Outcome::Applied(Some(content)) if patch.is_deletion() => {
+                if !content.is_empty() {
+                    return Err(Error::Generic(format!("{}: removal patch leaves file contents", patch.path())));
+                }
+                Outcome::Applied(None)
+            },
+            outcome => outcome,
+        };
+
+        if let Some(old_path) = &patch.old_path {
+            if patch.is_deletion() || patch.is_rename() {
+                patched.insert(old_path.clone(), None);
+            }
+        }
+        if let (Some(new_path), Outcome::Applied(Some(content)) | Outcome::Conflicted(content, _)) = (&patch.new_path, &outcome) {
+            patched.insert(new_path.clone(), Some(content.clone()));
+        }
+        Ok(outcome)
+    }
+
+    // The file `path` as the patch finds it: as a patch before left it,
+    // else from the index with `cached`, else from the working tree, which
+    // must then match the index if that is patched too
+    fn preimage(
+        repo: &mut Repository,
+        path: &str,
+        options: &ApplyOptions,
+        patched: &HashMap<String, Option<Vec<u8>>>,
+    ) -> Result<Vec<u8>, Error> {
+        if let Some(content) = patched.get(path) {
+            return content.clone().ok_or_else(|| Error::Generic(format!("{}: No such file or directory", path)));
+        }
+        let indexed = repo.index.get_entry(path).map(|entry| entry.get_oid().to_string());
+        if options.uses_index() && indexed.is_none() {
+            return Err(Error::Generic(format!("{}: does not exist in index", path)));
+        }
+        if options.cached {
+            return Ok(repo.database.load(&indexed.unwrap_or_default())?.to_bytes());
+        }
+
+        let data = repo.workspace.read_file(Path::new(path))
+            .map_err(|_| Error::Generic(format!("{}: No such file or directory", path)))?;
+        if let Some(oid) = indexed.filter(|_| options.uses_index()) {
+            if repo.database.hash_file_data(&data) != oid {
+                return Err(Error::Generic(format!("{}: does not match index", path)));
+            }
+        }
+        Ok(data)
+    }
+
+    // Apply the patch to the blob it was made against and merge the result
+    // with `ours`, the file as it is now
+    fn merge(repo: &mut Repository, patch: &FilePatch, ours: &str, options: &ApplyOptions) -> Result<Outcome, Error> {
+        let lacking = || Error::Generic(format!("{}: repository lacks the necessary blob to perform 3-way merge", patch.path()));
+        let prefix = patch.old_oid.as_deref().ok_or_else(lacking)?;
+        let base_oid = match repo.database.prefix_match(prefix)?.as_slice() {
+            [oid] => oid.clone(),
+            _ => return Err(lacking()),
+        };
+        let base = String::from_utf8_lossy(&repo.database.load(&base_oid)?.to_bytes()).to_string();
+        let theirs = patch.apply(&base, options.min_context)?;
+
+        let result = diff3::merge(&base, ours, &theirs)?;
+        if result.is_clean() {
+            eprintln!("Applied patch to '{}' cleanly.", patch.path());
+            return Ok(Outcome::Applied(Some(result.to_string(None, None).into_bytes())));
+        }
+        let ours_oid = Self::store(repo, ours.as_bytes().to_vec())?;
+        let theirs_oid = Self::store(repo, theirs.into_bytes())?;
+        Ok(Outcome::Conflicted(result.to_string(Some("ours"), Some("theirs")).into_bytes(), [base_oid, ours_oid, theirs_oid]))
+    }
+
+    // Write the patched file where `options` say, with its mode
+    fn write(repo: &mut Repository, path: &str, data: &[u8], mode: &str, options: &ApplyOptions) -> Result<(), Error> {
+        let file_path = Path::new(path);
+        if !options.cached {
+            repo.workspace.write_file(file_path, data)?;
+            Self::set_executable(&repo.workspace.root_path.join(file_path), FileMode::is_executable(FileMode::parse(mode).0))?;
+        }
+        if !options.uses_index() {
+            return Ok(());
+        }
+        let oid = Self::store(repo, data.to_vec())?;
+        if options.cached {
+            repo.index.add_blob(file_path, &oid, FileMode::parse(mode), data.len());
+        } else {
+            let stat = repo.workspace.stat_file(file_path)?;
+            repo.index.add(file_path, &oid, &stat)?;
+        }
+        Ok(())
+    }
+
+    fn store(repo: &mut Repository, data: Vec<u8>) -> Result<String, Error> {
+        let mut blob = Blob::new(data);
+        repo.database.store(&mut blob)
+    }
+
+    #[cfg(unix)]
+    fn set_executable(path: &Path, executable: bool) -> Result<(), Error> {
+        use std::os::unix::fs::PermissionsExt;
+        let mut permissions = fs::metadata(path)?.permissions();
+        let mode = permissions.mode();
+        permissions.set_mode(if executable { mode | ((mode & 0o444) >> 2) } else { mode & !0o111 });
+        fs::set_permissions(path, permissions)?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn set_executable(_path: &Path, _executable: bool) -> Result<(), Error> {
+        Ok(())
+    }
+
+    // The text of an error without the kind of error before it
+    fn message(error: &Error) -> String {
+        match error {
+            Error::Generic(message) => message.clone(),
+            error => error.to_string(),
+        }
+    }
+}
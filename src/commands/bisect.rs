@@ -0,0 +1,339 @@
+// src/commands/bisect.rs - find the commit that introduced a bug by binary search
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::commands::checkout::CheckoutCommand;
+use crate::core::database::commit::Commit;
+use crate::core::refs::{Reference, HEAD};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::core::revlist::RevList;
+use crate::errors::error::Error;
+
+// State of a bisection, under the repository's git dir: the branch or
+// commit to go back to, the commits marked so far, and what was done
+const BISECT_START: &str = "BISECT_START";
+const BISECT_BAD: &str = "BISECT_BAD";
+const BISECT_GOOD: &str = "BISECT_GOOD";
+const BISECT_SKIP: &str = "BISECT_SKIP";
+const BISECT_LOG: &str = "BISECT_LOG";
+const STATE_FILES: [&str; 5] = [BISECT_START, BISECT_BAD, BISECT_GOOD, BISECT_SKIP, BISECT_LOG];
+
+// Exit code of a `bisect run` command for a commit that cannot be tested
+const SKIP_EXIT_CODE: i32 = 125;
+
+// Where a bisection stands after the latest mark
+enum Step {
+    // The commit checked out to be tested next
+    Test,
+    FirstBad,
+    // Only skipped commits are left; any of them may be the first bad one
+    OnlySkipped,
+    Waiting,
+}
+
+pub struct BisectCommand;
+
+impl BisectCommand {
+    pub fn execute(action: &str, args: &[String]) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        let known = ["start", "bad", "good", "skip", "reset", "run"];
+        if !known.contains(&action) {
+            return Err(Error::Generic(format!(
+                "unknown bisect subcommand '{}'\nusage: ash bisect (start [<bad> [<good>...]] | bad | good | skip [<rev>...] | reset [<commit>] | run <cmd>...)",
+                action
+            )));
+        }
+        if action != "start" && action != "reset" && !git_path.join(BISECT_START).exists() {
+            return Err(Error::Generic("You need to start by \"ash bisect start\"".to_string()));
+        }
+
+        match action {
+            "start" => Self::start(&mut repo, &git_path, args).map(|_| ()),
+            "bad" | "good" | "skip" => {
+                let revs = if args.is_empty() { vec![HEAD.to_string()] } else { args.to_vec() };
+                Self::mark(&mut repo, &git_path, action, &revs).map(|_| ())
+            },
+            "reset" => Self::reset(&git_path, args.first().map(String::as_str)),
+            _ => Self::run(&mut repo, &git_path, args),
+        }
+    }
+
+    // Begin a new bisection, remembering where HEAD is to go back there,
+    // with the first of `revs` bad and the rest good
+    fn start(repo: &mut Repository, git_path: &Path, revs: &[String]) -> Result<Step, Error> {
+        let mut oids = Vec::new();
+        for rev in revs {
+            oids.push(Self::resolve(repo, rev)?);
+        }
+
+        // Starting over keeps the place the first start came from
+        let original = match fs::read_to_string(git_path.join(BISECT_START)) {
+            Ok(original) => original.trim().to_string(),
+            Err(_) => match repo.refs.current_ref()? {
+                Reference::Symbolic(path) if path != HEAD => repo.refs.short_name(&path),
+                _ => repo.refs.read_head()?
+                    .ok_or_else(|| Error::Generic("bad HEAD - I need a HEAD".to_string()))?,
+            },
+        };
+        Self::clear(git_path)?;
+        fs::write(git_path.join(BISECT_START), format!("{}\n", original))?;
+        Self::log(git_path, format!("ash bisect start {}", revs.join(" ")).trim_end())?;
+
+        let mut oids = oids.into_iter();
+        if let Some(bad) = oids.next() {
+            Self::record(repo, git_path, "bad", &bad)?;
+        }
+        for good in oids {
+            Self::record(repo, git_path, "good", &good)?;
+        }
+        Self::next(repo, git_path)
+    }
+
+    // Mark `revs` with `term` and move on to the next commit to test
+    fn mark(repo: &mut Repository, git_path: &Path, term: &str, revs: &[String]) -> Result<Step, Error> {
+        if term == "bad" && revs.len() > 1 {
+            return Err(Error::Generic("'ash bisect bad' can take only one argument.".to_string()));
+        }
+        let mut oids = Vec::new();
+        for rev in revs {
+            oids.push(Self::resolve(repo, rev)?);
+        }
+        for oid in &oids {
+            Self::record(repo, git_path, term, oid)?;
+        }
+        Self::next(repo, git_path)
+    }
+
+    fn record(repo: &mut Repository, git_path: &Path, term: &str, oid: &str) -> Result<(), Error> {
+        match term {
+            "bad" => fs::write(git_path.join(BISECT_BAD), format!("{}\n", oid))?,
+            "good" => Self::append(&git_path.join(BISECT_GOOD), oid)?,
+            _ => Self::append(&git_path.join(BISECT_SKIP), oid)?,
+        }
+        let title = Self::load_commit(repo, oid)?.title_line();
+        Self::log(git_path, &format!("# {}: [{}] {}\nash bisect {} {}", term, oid, title.trim(), term, oid))
+    }
+
+    /// Go back to where the bisection started, or to `commit`, and forget it
+    fn reset(git_path: &Path, commit: Option<&str>) -> Result<(), Error> {
+        let original = match fs::read_to_string(git_path.join(BISECT_START)) {
+            Ok(original) => original.trim().to_string(),
+            Err(_) => {
+                println!("We are not bisecting.");
+                return Ok(());
+            },
+        };
+        CheckoutCommand::execute(commit.unwrap_or(&original), None, false, false)?;
+        Self::clear(git_path)
+    }
+
+    // Let `command` decide on each commit to test: exit code 0 marks it
+    // good, 125 skips it, and anything else below 128 marks it bad
+    fn run(repo: &mut Repository, git_path: &Path, command: &[String]) -> Result<(), Error> {
+        if command.is_empty() {
+            return Err(Error::Generic("bisect run failed: no command provided.".to_string()));
+        }
+        let label = command.join(" ");
+
+        // The commit to test first is the one already checked out
+        if !git_path.join(BISECT_BAD).exists() || Self::read_list(&git_path.join(BISECT_GOOD)).is_empty() {
+            return Err(Error::Generic("bisect run failed: mark a good and a bad commit first".to_string()));
+        }
+
+        loop {
+            println!("running '{}'", label);
+            // The arguments go to the program as given, spaces and all
+            let status = Command::new("sh").args(["-c", "\"$@\"", "sh"]).args(command).status()
+                .map_err(|e| Error::Generic(format!("bisect run failed: cannot run '{}': {}", label, e)))?;
+            let term = match status.code() {
+                Some(0) => "good",
+                Some(SKIP_EXIT_CODE) => "skip",
+                Some(code) if (1..128).contains(&code) => "bad",
+                code => {
+                    return Err(Error::Generic(format!(
+                        "bisect run failed: exit code {} from '{}' is < 0 or >= 128",
+                        code.map_or_else(|| "(signal)".to_string(), |code| code.to_string()),
+                        label
+                    )));
+                },
+            };
+            match Self::mark(repo, git_path, term, &[HEAD.to_string()])? {
+                Step::Test | Step::Waiting => {},
+                Step::FirstBad => {
+                    println!("bisect found first bad commit");
+                    return Ok(());
+                },
+                Step::OnlySkipped => {
+                    return Err(Error::Generic("bisect run cannot continue any more".to_string()));
+                },
+            }
+        }
+    }
+
+    // Pick the next commit to test and check it out, or report the first
+    // bad commit when there is nothing left to test
+    fn next(repo: &mut Repository, git_path: &Path) -> Result<Step, Error> {
+        let bad = fs::read_to_string(git_path.join(BISECT_BAD)).ok().map(|bad| bad.trim().to_string());
+        let goods = Self::read_list(&git_path.join(BISECT_GOOD));
+        let bad = match bad {
+            Some(bad) if !goods.is_empty() => bad,
+            Some(_) => {
+                println!("status: waiting for good commit(s), bad commit known");
+                return Ok(Step::Waiting);
+            },
+            None if goods.is_empty() => {
+                println!("status: waiting for both good and bad commits");
+                return Ok(Step::Waiting);
+            },
+            None => {
+                println!("status: waiting for bad commit, {} good commit{} known", goods.len(), if goods.len() == 1 { "" } else { "s" });
+                return Ok(Step::Waiting);
+            },
+        };
+        let skipped: HashSet<String> = Self::read_list(&git_path.join(BISECT_SKIP)).into_iter().collect();
+
+        // Suspects: commits that lead to the bad one from the good ones
+        let mut revs = vec![bad.clone()];
+        revs.extend(goods.iter().map(|good| format!("^{}", good)));
+        let mut candidates = Vec::new();
+        let mut revlist = RevList::new(&mut repo.database, &repo.refs, &revs, true)?;
+        while let Some(commit) = revlist.next() {
+            candidates.push(commit?);
+        }
+        if candidates.is_empty() {
+            return Err(Error::Generic(format!("some good revisions are not ancestors of the bad revision {}", bad)));
+        }
+
+        let testable: Vec<&Commit> = candidates.iter()
+            .filter(|commit| commit.get_oid().is_some_and(|oid| *oid != bad && !skipped.contains(oid)))
+            .collect();
+        if testable.is_empty() {
+            let left: Vec<String> = candidates.iter()
+                .filter_map(|commit| commit.get_oid().cloned())
+                .filter(|oid| *oid == bad || skipped.contains(oid))
+                .collect();
+            if left.len() == 1 {
+                Self::report_first_bad(repo, &bad)?;
+                return Ok(Step::FirstBad);
+            }
+            println!("There are only 'skip'ped commits left to test.");
+            println!("The first bad commit could be any of:");
+            for oid in &left {
+                println!("{}", oid);
+            }
+            println!("We cannot bisect more!");
+            return Ok(Step::OnlySkipped);
+        }
+
+        // The best commit to test splits the suspects most evenly: if it is
+        // bad, its own ancestors among them remain, otherwise the rest do
+        let total = candidates.len();
+        let ancestors = Self::ancestor_counts(&candidates);
+        let (next, reach) = testable.iter()
+            .filter_map(|commit| commit.get_oid())
+            .map(|oid| (oid.clone(), ancestors[oid]))
+            .max_by_key(|(_, reach)| (*reach).min(total - reach))
+            .unwrap_or_default();
+
+        let left = reach.max(total - reach).saturating_sub(1);
+        let steps = (left + 1).ilog2() as usize;
+        println!(
+            "Bisecting: {} revision{} left to test after this (roughly {} step{})",
+            left, if left == 1 { "" } else { "s" }, steps, if steps == 1 { "" } else { "s" }
+        );
+        CheckoutCommand::execute(&next, None, true, false)?;
+        let title = Self::load_commit(repo, &next)?.title_line();
+        println!("[{}] {}", next, title.trim());
+        Ok(Step::Test)
+    }
+
+    // For each commit, how many of `commits` it can reach, itself included
+    fn ancestor_counts(commits: &[Commit]) -> HashMap<String, usize> {
+        let parents: HashMap<&str, Vec<String>> = commits.iter()
+            .filter_map(|commit| commit.get_oid().map(|oid| (oid.as_str(), commit.get_parents())))
+            .collect();
+
+        let mut counts = HashMap::new();
+        for &oid in parents.keys() {
+            let mut seen = HashSet::from([oid.to_string()]);
+            let mut queue = vec![oid.to_string()];
+            while let Some(current) = queue.pop() {
+                for parent in parents.get(current.as_str()).into_iter().flatten() {
+                    if parents.contains_key(parent.as_str()) && seen.insert(parent.clone()) {
+                        queue.push(parent.clone());
+                    }
+                }
+            }
+            counts.insert(oid.to_string(), seen.len());
+        }
+        counts
+    }
+
+    fn report_first_bad(repo: &mut Repository, oid: &str) -> Result<(), Error> {
+        let commit = Self::load_commit(repo, oid)?;
+        println!("{} is the first bad commit", oid);
+        println!("commit {}", oid);
+        if let Some(author) = commit.get_author() {
+            println!("Author: {} <{}>", author.name, author.email);
+            println!("Date:   {}", author.short_date());
+        }
+        println!();
+        for line in commit.get_message().lines() {
+            println!("    {}", line);
+        }
+        Ok(())
+    }
+
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        let mut revision = Revision::new(repo, name);
+        match revision.resolve(COMMIT) {
+            Ok(oid) => Ok(oid),
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn load_commit(repo: &mut Repository, oid: &str) -> Result<Commit, Error> {
+        repo.database.load(oid)?.as_any().downcast_ref::<Commit>().cloned()
+            .ok_or_else(|| Error::Generic(format!("{} is not a commit", oid)))
+    }
+
+    fn read_list(path: &PathBuf) -> Vec<String> {
+        fs::read_to_string(path).unwrap_or_default()
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn append(path: &Path, line: &str) -> Result<(), Error> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+
+    fn log(git_path: &Path, entry: &str) -> Result<(), Error> {
+        Self::append(&git_path.join(BISECT_LOG), entry)
+    }
+
+    fn clear(git_path: &Path) -> Result<(), Error> {
+        for name in STATE_FILES {
+            match fs::remove_file(git_path.join(name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+}
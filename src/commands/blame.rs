@@ -0,0 +1,168 @@
+// src/commands/blame.rs - show which commit last changed each line of a file
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+
+use crate::errors::error::Error;
+use crate::core::blame::Blame;
+use crate::core::config::Config;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::pager::Pager;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
+
+// Hex digits of each commit id shown by default; one more column is used
+// so that boundary commits can be marked with `^` without losing alignment
+pub const DEFAULT_ABBREV: usize = 7;
+const MIN_ABBREV: usize = 4;
+const OID_LENGTH: usize = 40;
+
+pub struct BlameCommand;
+
+impl BlameCommand {
+    /// Annotate `file` as it is in `revision` (HEAD by default), blaming
+    /// through the commits given with `--ignore-rev` or listed in ignore-revs
+    /// files. Files from `blame.ignoreRevsFile` are read first; an empty
+    /// `--ignore-revs-file` discards them. Commit ids are shortened to
+    /// `abbrev` digits plus one, and the result goes through the pager.
+    /// With `incremental`, ranges are printed as soon as they are found,
+    /// in git's machine-readable `--incremental` format.
+    pub fn execute(
+        revision: Option<&str>,
+        file: &str,
+        ignore_revs: &[String],
+        ignore_revs_file: Option<&str>,
+        abbrev: Option<usize>,
+        incremental: bool,
+    ) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        let start = Self::resolve_commit(&mut repo, revision.unwrap_or("HEAD"))?;
+
+        let mut files = config.get_all("blame.ignoreRevsFile");
+        match ignore_revs_file {
+            Some("") => files.clear(),
+            Some(path) => files.push(path.to_string()),
+            None => {},
+        }
+
+        let mut ignored = Vec::new();
+        for path in files {
+            let path = repo.path.join(&path);
+            let content = fs::read_to_string(&path).map_err(|_| {
+                Error::Generic(format!("could not open object name list: {}", path.display()))
+            })?;
+            for line in content.lines() {
+                let name = line.split('#').next().unwrap_or_default().trim();
+                if !name.is_empty() {
+                    ignored.push(Self::resolve_commit(&mut repo, name)?);
+                }
+            }
+        }
+        for name in ignore_revs {
+            ignored.push(Self::resolve_commit(&mut repo, name)?);
+        }
+
+        let path = file.trim_start_matches("./");
+        if incremental {
+            return Self::incremental(&mut repo, &start, path, &ignored);
+        }
+        let mut blame = Blame::new(&mut repo.database);
+        for oid in &ignored {
+            blame.ignore(oid);
+        }
+        let lines = blame.run(&start, path)?;
+
+        // Commit details for the annotation, loaded once per commit
+        let mut commits: HashMap<String, Commit> = HashMap::new();
+        for line in &lines {
+            if !commits.contains_key(&line.commit) {
+                let object = repo.database.load(&line.commit)?;
+                if let Some(commit) = object.as_any().downcast_ref::<Commit>() {
+                    commits.insert(line.commit.clone(), commit.clone());
+                }
+            }
+        }
+
+        let name_width = commits.values().map(|c| c.author.name.chars().count()).max().unwrap_or(0);
+        let number_width = lines.len().to_string().len();
+        let id_width = (abbrev.unwrap_or(DEFAULT_ABBREV).max(MIN_ABBREV) + 1).min(OID_LENGTH);
+
+        let mut pager = Pager::new();
+        pager.start()?;
+        for (number, line) in lines.iter().enumerate() {
+            if pager.exited_early() {
+                break;
+            }
+            let commit = &commits[&line.commit];
+            // Root commits are marked as boundaries, as in git
+            let id = if commit.get_parents().is_empty() {
+                format!("^{}", &line.commit[..id_width - 1])
+            } else {
+                line.commit[..id_width].to_string()
+            };
+            pager.write(&format!(
+                "{} ({:<name_width$} {} {:>number_width$}) {}\n",
+                id,
+                commit.author.name,
+                commit.author.timestamp.format("%Y-%m-%d %H:%M:%S %z"),
+                number + 1,
+                line.content,
+                name_width = name_width,
+                number_width = number_width,
+            ))?;
+        }
+        pager.close()?;
+
+        Ok(())
+    }
+
+    // Each range as `<oid> <orig line> <final line> <count>`, followed the
+    // first time a commit shows up by its details, and by the file name
+    fn incremental(repo: &mut Repository, start: &str, path: &str, ignored: &[String]) -> Result<(), Error> {
+        // The walk holds the repository's database, so commits are read
+        // through a database of their own
+        let mut commits = Database::new(git_dir::objects(&git_dir::resolve(&repo.path)));
+        let mut seen = HashSet::new();
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        let mut blame = Blame::new(&mut repo.database);
+        for oid in ignored {
+            blame.ignore(oid);
+        }
+        blame.run_incremental(start, path, |range| {
+            writeln!(out, "{} {} {} {}", range.commit, range.orig_start + 1, range.start + 1, range.lines.len())?;
+            if seen.insert(range.commit.clone()) {
+                let object = commits.load(&range.commit)?;
+                if let Some(commit) = object.as_any().downcast_ref::<Commit>() {
+                    writeln!(out, "author {}", commit.author.name)?;
+                    writeln!(out, "author-mail <{}>", commit.author.email)?;
+                    writeln!(out, "author-time {}", commit.author.timestamp.timestamp())?;
+                    writeln!(out, "author-tz +0000")?;
+                    writeln!(out, "committer {}", commit.committer.name)?;
+                    writeln!(out, "committer-mail <{}>", commit.committer.email)?;
+                    writeln!(out, "committer-time {}", commit.committer.timestamp.timestamp())?;
+                    writeln!(out, "committer-tz +0000")?;
+                    writeln!(out, "summary {}", commit.title_line())?;
+                    if commit.get_parents().is_empty() {
+                        writeln!(out, "boundary")?;
+                    }
+                }
+            }
+            writeln!(out, "filename {}", path)?;
+            out.flush()?;
+            Ok(())
+        })
+    }
+
+    fn resolve_commit(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        let mut revision = Revision::new(repo, name);
+        revision.resolve("commit")
+            .map_err(|_| Error::Generic(format!("invalid object name: {}", name)))
+    }
+}
@@ -3,8 +3,14 @@ use crate::errors::error::Error;
 use crate::core::repository::repository::Repository;
 use crate::core::revision::Revision;
 use crate::core::color::Color;
-use crate::core::refs::Reference;
+use crate::core::column::Columns;
+use crate::core::refs::{Reference, Refs};
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+use crate::core::repository::worktree::Worktree;
+use crate::core::remote::upstream::{self, Upstream};
 use crate::core::database::commit::Commit;
+use crate::trace;
 
 pub struct BranchCommand;
 
@@ -16,25 +22,40 @@ impl BranchCommand {
         let verbose = std::env::var("ASH_BRANCH_VERBOSE").unwrap_or_default() == "1";
         let delete = std::env::var("ASH_BRANCH_DELETE").unwrap_or_default() == "1";
         let force = std::env::var("ASH_BRANCH_FORCE").unwrap_or_default() == "1";
+        let rename = std::env::var("ASH_BRANCH_RENAME").unwrap_or_default() == "1";
+        let column = std::env::var("ASH_BRANCH_COLUMN").ok();
         
         // Handle no arguments - list branches
         if branch_name.is_empty() {
-            return Self::list_branches(verbose);
+            return Self::list_branches(verbose, column.as_deref());
         }
         
         // Handle delete branch
         if delete {
             return Self::delete_branch(branch_name, force);
         }
+
+        // Handle rename: `-m <old> <new>`, or `-m <new>` for the current branch
+        if rename {
+            return match start_point {
+                Some(new_name) => Self::rename_branch(Some(branch_name), new_name, force),
+                None => Self::rename_branch(None, branch_name, force),
+            };
+        }
         
         // Default behavior: create a new branch
         Self::create_branch(branch_name, start_point, force)
     }
     
-    // List all branches in the repository
-    fn list_branches(verbose: bool) -> Result<(), Error> {
+    // List all branches in the repository, in columns as `column.branch`
+    // or the `--column` words say
+    fn list_branches(verbose: bool, column: Option<&str>) -> Result<(), Error> {
         let start_time = Instant::now();
         let mut repo = Repository::new(".")?;
+        let columns = Columns::load(&Config::load(&git_dir::resolve(&repo.path))?, "branch", column)?;
+        if verbose && columns.is_active() {
+            return Err(Error::Generic("--column and --verbose are incompatible".to_string()));
+        }
         
         // Get current branch
         let current_ref = repo.refs.current_ref()?;
@@ -65,6 +86,7 @@ impl BranchCommand {
         branch_names.sort_by(|a, b| a.0.cmp(&b.0));
         
         // Print each branch
+        let mut lines = Vec::new();
         for (name, reference) in branch_names {
             let mut info = Self::format_branch(&reference, &current_ref, &repo);
             
@@ -73,11 +95,12 @@ impl BranchCommand {
                 info.push_str(&extended_info);
             }
             
-            println!("{}", info);
+            lines.push(info);
         }
+        columns.print(&lines);
         
         let elapsed = start_time.elapsed();
-        println!("\nBranch command completed in {:.2}s", elapsed.as_secs_f32());
+        trace!("\nBranch command completed in {:.2}s", elapsed.as_secs_f32());
         
         Ok(())
     }
@@ -126,8 +149,15 @@ impl BranchCommand {
             
             // Add padding to align commit info
             let padding = " ".repeat(max_width.saturating_sub(name.len()));
+
+            // How the branch stands against its upstream, if it has one
+            let git_path = git_dir::resolve(&repo.path);
+            let track = match Upstream::of(&Config::load(&git_path)?, name) {
+                Some(upstream) => format!("{} ", upstream::track(&mut repo.database, &repo.refs, &git_path, &upstream, &oid)?),
+                None => String::new(),
+            };
             
-            Ok(format!("{} {} {}", padding, Color::yellow(short_oid), title))
+            Ok(format!("{} {} {}{}", padding, Color::yellow(short_oid), track, title))
         } else {
             Ok(String::new())
         }
@@ -162,13 +192,14 @@ impl BranchCommand {
         };
         
         // Create the branch
-        match repo.refs.create_branch(branch_name, &start_oid) {
+        let reflog_message = format!("branch: Created from {}", start_point.unwrap_or("HEAD"));
+        match repo.refs.create_branch(branch_name, &start_oid, &reflog_message) {
             Ok(_) => {
-                println!("Created branch '{}' at {}", branch_name, &start_oid[0..8]);
+                trace!("Created branch '{}' at {}", branch_name, &start_oid[0..8]);
                 
                 let start_time = Instant::now();
                 let elapsed = start_time.elapsed();
-                println!("Branch command completed in {:.2}s", elapsed.as_secs_f32());
+                trace!("Branch command completed in {:.2}s", elapsed.as_secs_f32());
                 
                 Ok(())
             },
@@ -176,6 +207,97 @@ impl BranchCommand {
         }
     }
     
+    // Make `upstream` the upstream of `branch_name`, the current branch by
+    // default
+    pub fn set_upstream(branch_name: Option<&str>, upstream: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let branch = match Self::upstream_branch(&repo, branch_name) {
+            Err(_) if branch_name.is_none() => return Err(Error::Generic(format!(
+                "could not set upstream of HEAD to {} when it does not point to any branch.", upstream
+            ))),
+            result => result?,
+        };
+
+        let upstream = Upstream::find(&git_path, &repo.refs, upstream)?;
+        upstream.set(&git_path, &branch)?;
+        println!("branch '{}' set up to track '{}'.", branch, upstream.display(&git_path));
+        Ok(())
+    }
+
+    // Forget the upstream of `branch_name`, the current branch by default
+    pub fn unset_upstream(branch_name: Option<&str>) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let branch = Self::upstream_branch(&repo, branch_name)?;
+        if !Upstream::unset(&git_dir::resolve(&repo.path), &branch)? {
+            return Err(Error::Generic(format!("branch '{}' has no upstream information", branch)));
+        }
+        Ok(())
+    }
+
+    // The branch an upstream is set or unset for, which must exist
+    fn upstream_branch(repo: &Repository, branch_name: Option<&str>) -> Result<String, Error> {
+        let branch = match branch_name {
+            Some(name) => name.to_string(),
+            None => match repo.refs.current_ref()? {
+                Reference::Symbolic(path) if path != "HEAD" => repo.refs.short_name(&path),
+                _ => return Err(Error::Generic("HEAD does not point to a branch".to_string())),
+            },
+        };
+        if repo.refs.read_ref(&format!("refs/heads/{}", branch))?.is_none() {
+            return Err(Error::Generic(format!("branch '{}' does not exist", branch)));
+        }
+        Ok(branch)
+    }
+
+    // Rename a branch, the current one when `old_name` is None, pointing
+    // every worktree that has it checked out at the new name
+    fn rename_branch(old_name: Option<&str>, new_name: &str, force: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        let old_name = match old_name {
+            Some(name) => name.to_string(),
+            None => match repo.refs.current_ref()? {
+                Reference::Symbolic(path) => repo.refs.short_name(&path),
+                _ => return Err(Error::Generic("cannot rename the current branch while not on any".to_string())),
+            },
+        };
+        let (old_ref, new_ref) = (format!("refs/heads/{}", old_name), format!("refs/heads/{}", new_name));
+
+        // A forced rename may not pull a checked-out branch from under a worktree
+        if force && old_name != new_name {
+            if let Some(location) = Worktree::checked_out(&git_path, &new_ref)? {
+                return Err(Error::Generic(format!(
+                    "cannot force update the branch '{}' used by worktree at '{}'", new_name, location
+                )));
+            }
+        }
+
+        let message = format!("Branch: renamed {} to {}", old_ref, new_ref);
+        repo.refs.rename_branch(&old_name, new_name, force, &message)?;
+
+        let common = git_dir::common(&git_path);
+        let heads = std::iter::once(common.clone())
+            .chain(Worktree::list(&git_path)?.into_iter().map(|worktree| worktree.admin_path));
+        for head in heads {
+            let refs = Refs::new(&head);
+            if refs.current_ref()? == Reference::Symbolic(old_ref.clone()) {
+                refs.set_symbolic_ref("HEAD", &new_ref)?;
+            }
+        }
+
+        // The branch's settings, such as its upstream, go along with it
+        let mut config = Config::load(&git_path)?;
+        if force && old_name != new_name {
+            config.remove_section(&format!("branch.{}", new_name))?;
+        }
+        config.rename_section(&format!("branch.{}", old_name), &format!("branch.{}", new_name))?;
+
+        trace!("Renamed branch '{}' to '{}'", old_name, new_name);
+        Ok(())
+    }
+
     // Delete a branch
     fn delete_branch(branch_name: &str, force: bool) -> Result<(), Error> {
         // Force is required for now since we don't have merge functionality
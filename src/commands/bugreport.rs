@@ -0,0 +1,226 @@
+// src/commands/bugreport.rs - collect what a maintainer needs to look into a problem
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use chrono::Local;
+use regex::Regex;
+
+use crate::core::config::Config;
+use crate::core::reflog::Reflog;
+use crate::core::refs::{Reference, HEAD};
+use crate::core::repository::format::FORMAT_VERSION_KEY;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::trace;
+use crate::errors::error::Error;
+
+// How much of the HEAD reflog and of the command log go into a report
+const REFLOG_ENTRIES: usize = 10;
+const COMMAND_ENTRIES: usize = 20;
+
+// Config variables whose values are never copied into a report
+const SECRET_NAMES: [&str; 7] = ["password", "passwd", "token", "secret", "cookie", "extraheader", "authorization"];
+const REDACTED: &str = "<redacted>";
+
+// Files in the git dir that show an operation was left unfinished
+const IN_PROGRESS: [(&str, &str); 7] = [
+    ("MERGE_HEAD", "merge"),
+    ("CHERRY_PICK_HEAD", "cherry-pick"),
+    ("REVERT_HEAD", "revert"),
+    ("rebase-merge", "rebase"),
+    ("rebase-apply", "am"),
+    ("BISECT_START", "bisect"),
+    ("AUTOSTASH", "autostash"),
+];
+
+const TEMPLATE: &str = "\
+Thank you for filling out an ash bug report!
+Please answer the following questions to help us understand your issue.
+
+What did you do before the bug happened? (Steps to reproduce your issue)
+
+What did you expect to happen? (Expected behavior)
+
+What happened instead? (Actual behavior)
+
+What's different between what you expected and what actually happened?
+
+Anything else you want to add:
+
+Please review the rest of the bug report below.
+You can delete any lines you don't wish to share.
+";
+
+pub struct BugreportCommand;
+
+impl BugreportCommand {
+    /// Write a report about the system and the repository in the current
+    /// directory, if any, to `output` (a file, or a directory to create it
+    /// in; the current directory by default), or to stdout
+    pub fn execute(output: Option<&str>, stdout: bool) -> Result<(), Error> {
+        let mut report = String::from(TEMPLATE);
+        report.push_str(&Self::system_info());
+
+        // A repository ash refuses to work on is still worth reporting on
+        let git_path = git_dir::resolve(Path::new("."));
+        if git_dir::common(&git_path).join("config").is_file() {
+            report.push_str(&Self::repository_info(&git_path)?);
+        } else {
+            report.push_str("\n[Repository]\nnot in an ash repository\n");
+        }
+
+        if stdout {
+            print!("{}", report);
+            return Ok(());
+        }
+
+        let path = Self::report_path(output);
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        if path.exists() {
+            return Err(Error::Generic(format!("unable to create '{}': File exists", path.display())));
+        }
+        fs::write(&path, report)?;
+        println!("Created new report at '{}'.", path.display());
+        Ok(())
+    }
+
+    fn report_path(output: Option<&str>) -> PathBuf {
+        let name = format!("ash-bugreport-{}.txt", Local::now().format("%Y-%m-%d-%H%M"));
+        match output {
+            Some(output) if Path::new(output).is_dir() || output.ends_with('/') => Path::new(output).join(name),
+            Some(output) => PathBuf::from(output),
+            None => PathBuf::from(name),
+        }
+    }
+
+    fn system_info() -> String {
+        let mut info = String::from("\n[System Info]\n");
+        info.push_str(&format!("ash version: {}\n", env!("CARGO_PKG_VERSION")));
+        info.push_str(&format!("platform: {} {}\n", env::consts::OS, env::consts::ARCH));
+        let uname = Command::new("uname").arg("-a").output().ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+        if let Some(uname) = uname {
+            info.push_str(&format!("uname: {}\n", uname));
+        }
+        if let Ok(shell) = env::var("SHELL") {
+            info.push_str(&format!("$SHELL (typically, interactive shell): {}\n", shell));
+        }
+        // Which of the variables that change ash's behaviour are set
+        for name in ["ASH_QUIET", "ASH_PAGER", "GIT_DIR", "GIT_EDITOR", "GIT_SEQUENCE_EDITOR"] {
+            if let Ok(value) = env::var(name) {
+                info.push_str(&format!("{}: {}\n", name, value));
+            }
+        }
+        info
+    }
+
+    fn repository_info(git_path: &Path) -> Result<String, Error> {
+        let config = Config::load(git_path)?;
+        let mut info = String::new();
+
+        info.push_str("\n[Repository Format]\n");
+        info.push_str(&format!("{}: {}\n", FORMAT_VERSION_KEY, config.get(FORMAT_VERSION_KEY).unwrap_or_else(|| "0 (unset)".to_string())));
+        let extensions = config.variables("extensions");
+        if extensions.is_empty() {
+            info.push_str("extensions: none\n");
+        }
+        for (name, value) in extensions {
+            info.push_str(&format!("extensions.{}: {}\n", name, value));
+        }
+
+        info.push_str("\n[Repository Config]\n");
+        for (key, value) in config.entries() {
+            info.push_str(&format!("{}={}\n", key, Self::redact(&key, &value)));
+        }
+
+        info.push_str("\n[Repository Stats]\n");
+        match Repository::new(".") {
+            Ok(repo) => info.push_str(&Self::stats(&repo, git_path)?),
+            Err(e) => info.push_str(&format!("unavailable: {}\n", e)),
+        }
+
+        info.push_str(&format!("\n[Recent Reflog (HEAD, newest first, up to {})]\n", REFLOG_ENTRIES));
+        let entries = Reflog::new(git_path).read(HEAD)?;
+        for entry in entries.iter().rev().take(REFLOG_ENTRIES) {
+            info.push_str(&format!("{} {} {}\n", &entry.old[..7], &entry.new[..7], entry.message));
+        }
+
+        info.push_str(&format!("\n[Recent Commands (oldest first, up to {})]\n", COMMAND_ENTRIES));
+        let commands = trace::read_command_log(git_path);
+        for line in &commands[commands.len().saturating_sub(COMMAND_ENTRIES)..] {
+            info.push_str(&format!("{}\n", Self::redact_urls(line)));
+        }
+        Ok(info)
+    }
+
+    fn stats(repo: &Repository, git_path: &Path) -> Result<String, Error> {
+        let mut stats = String::new();
+
+        let (mut loose, mut loose_size) = (0, 0);
+        let (mut packs, mut pack_size) = (0, 0);
+        for entry in walkdir::WalkDir::new(git_dir::objects(git_path)).into_iter().flatten() {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            let in_pack_dir = entry.path().parent().and_then(Path::file_name).is_some_and(|dir| dir == "pack");
+            if in_pack_dir {
+                pack_size += size;
+                if entry.path().extension().is_some_and(|extension| extension == "pack") {
+                    packs += 1;
+                }
+            } else if entry.path().parent().and_then(Path::file_name).is_some_and(|dir| dir.len() == 2) {
+                loose += 1;
+                loose_size += size;
+            }
+        }
+        stats.push_str(&format!("loose objects: {} ({} KiB)\n", loose, loose_size / 1024));
+        stats.push_str(&format!("packs: {} ({} KiB)\n", packs, pack_size / 1024));
+
+        let head = match repo.refs.current_ref()? {
+            Reference::Symbolic(path) if path != HEAD => repo.refs.short_name(&path),
+            _ => "(detached)".to_string(),
+        };
+        stats.push_str(&format!("HEAD: {}\n", head));
+        stats.push_str(&format!("branches: {}\n", repo.refs.list_refs_under("refs/heads")?.len()));
+        stats.push_str(&format!("tags: {}\n", repo.refs.list_refs_under("refs/tags")?.len()));
+        stats.push_str(&format!("remote-tracking refs: {}\n", repo.refs.list_refs_under("refs/remotes")?.len()));
+        stats.push_str(&format!("packed-refs: {}\n", if git_dir::common(git_path).join("packed-refs").exists() { "yes" } else { "no" }));
+
+        let index_size = fs::metadata(git_path.join("index")).map(|metadata| metadata.len()).unwrap_or(0);
+        stats.push_str(&format!("index: {} bytes\n", index_size));
+
+        let in_progress: Vec<&str> = IN_PROGRESS.iter()
+            .filter(|(file, _)| git_path.join(file).exists())
+            .map(|(_, operation)| *operation)
+            .collect();
+        stats.push_str(&format!("in progress: {}\n", if in_progress.is_empty() { "none".to_string() } else { in_progress.join(", ") }));
+
+        let locks: Vec<String> = walkdir::WalkDir::new(git_path).max_depth(3).into_iter().flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "lock"))
+            .filter_map(|entry| entry.path().strip_prefix(git_path).ok().map(|path| path.display().to_string()))
+            .collect();
+        stats.push_str(&format!("lock files: {}\n", if locks.is_empty() { "none".to_string() } else { locks.join(", ") }));
+        Ok(stats)
+    }
+
+    // Hide the value of anything that looks like a credential, and the
+    // user info of any URL
+    fn redact(key: &str, value: &str) -> String {
+        let name = key.rsplit('.').next().unwrap_or(key).to_lowercase();
+        if SECRET_NAMES.iter().any(|secret| name.contains(secret)) {
+            return REDACTED.to_string();
+        }
+        Self::redact_urls(value)
+    }
+
+    fn redact_urls(text: &str) -> String {
+        let userinfo = Regex::new(r"([a-zA-Z][a-zA-Z0-9+.-]*://)[^/@\s]+@").unwrap();
+        userinfo.replace_all(text, format!("${{1}}{}@", REDACTED).as_str()).to_string()
+    }
+}
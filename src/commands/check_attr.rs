@@ -0,0 +1,32 @@
+// src/commands/check_attr.rs - show the attributes of paths
+use std::path::Path;
+
+use crate::core::attributes::{AttrValue, Attributes};
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+pub struct CheckAttrCommand;
+
+impl CheckAttrCommand {
+    /// Print `<path>: <attribute>: <value>` for each of `attributes` on each
+    /// of `paths`, or with `all` every attribute specified for them
+    pub fn execute(attributes: &[String], paths: &[String], all: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let lookup = Attributes::new(&repo.path);
+
+        for path in paths {
+            let path = path.trim_start_matches("./");
+            if all {
+                for (name, value) in lookup.check_all(Path::new(path)) {
+                    println!("{}: {}: {}", path, name, value.describe());
+                }
+                continue;
+            }
+            for name in attributes {
+                let value: AttrValue = lookup.get(Path::new(path), name);
+                println!("{}: {}: {}", path, name, value.describe());
+            }
+        }
+        Ok(())
+    }
+}
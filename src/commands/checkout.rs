@@ -1,20 +1,55 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 use std::io::{self, Write};
 use crate::errors::error::Error;
 use crate::core::revision::Revision;
 use crate::core::repository::repository::Repository;
+use crate::core::advice;
 use crate::core::color::Color;
 use crate::core::refs::Reference;
 use crate::core::database::commit::Commit;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::merge::diff3;
+use crate::core::repository::autostash::{self, head_files, Autostash};
+use crate::core::repository::git_dir;
+use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
+use crate::trace;
 
 pub struct CheckoutCommand;
 
 impl CheckoutCommand {
-    pub fn execute(target: &str) -> Result<(), Error> {
+    pub fn execute(target: &str, autostash: Option<bool>, detach: bool, merge: bool) -> Result<(), Error> {
+        Self::switch(target, None, autostash, detach, merge)
+    }
+
+    /// Create the branch `name` at `start_point`, or HEAD, and check it
+    /// out; with `force` an existing branch is reset there instead. The
+    /// branch is only made once the working tree has been switched.
+    pub fn create_branch(name: &str, start_point: Option<&str>, force: bool, autostash: Option<bool>, merge: bool) -> Result<(), Error> {
+        Self::switch(start_point.unwrap_or("HEAD"), Some((name, force)), autostash, false, merge)
+    }
+
+    fn switch(target: &str, new_branch: Option<(&str, bool)>, autostash: Option<bool>, detach: bool, merge: bool) -> Result<(), Error> {
         let start_time = Instant::now();
         
         // Initialize repository
         let mut repo = Repository::new(".")?;
+
+        // Whether the new branch is there already, to be reset
+        let reset = match new_branch {
+            Some((name, force)) => {
+                if !repo.refs.is_valid_branch_name(name) {
+                    return Err(Error::Generic(format!("'{}' is not a valid branch name.", name)));
+                }
+                let exists = repo.refs.read_ref(&format!("refs/heads/{}", name))?.is_some();
+                if exists && !force {
+                    return Err(Error::Generic(format!("A branch named '{}' already exists.", name)));
+                }
+                exists
+            },
+            None => false,
+        };
         
         // Read current reference information
         let current_ref = repo.refs.current_ref()?;
@@ -41,6 +76,31 @@ impl CheckoutCommand {
         
         // Create a tree diff between current and target commits
         let tree_diff = repo.tree_diff(current_oid.as_deref(), Some(&target_oid))?;
+
+        // With --merge, local changes are carried over by a three-way merge,
+        // so only changes that would actually conflict stop the checkout
+        if merge {
+            let conflicts = Self::merge_conflicts(&mut repo, &tree_diff)?;
+            if !conflicts.is_empty() {
+                for path in &conflicts {
+                    eprintln!("error: Your local changes to '{}' conflict with '{}'", path, target);
+                }
+                eprintln!("Aborting");
+                return Err(Error::Generic("Checkout failed due to conflicts".to_string()));
+            }
+        }
+
+        // Move local changes out of the way; they are reapplied on the target
+        let stash = Autostash::for_repository(&repo);
+        let carry = merge || autostash::enabled(&repo, "checkout", autostash)?;
+        let stashed = carry && stash.save(&mut repo)?;
+
+        // --detach stores the commit id in HEAD even when the target is a branch
+        let head_target = match new_branch {
+            Some((name, _)) => name,
+            None if detach => target_oid.as_str(),
+            None => target,
+        };
         
         // Load the index for update
         repo.index.load_for_update()?;
@@ -52,18 +112,43 @@ impl CheckoutCommand {
             Ok(_) => {
                 // Migration succeeded, write index updates
                 repo.index.write_updates()?;
+
+                let switched = match new_branch {
+                    Some((name, _)) if reset => {
+                        let branch_ref = format!("refs/heads/{}", name);
+                        repo.refs.update_ref(&branch_ref, &target_oid, &format!("branch: Reset to {}", target))?;
+                        match &current_ref {
+                            Reference::Symbolic(path) if path.ends_with(&branch_ref) => Some("Reset branch"),
+                            _ => Some("Switched to and reset branch"),
+                        }
+                    },
+                    Some((name, _)) => {
+                        repo.refs.create_branch(name, &target_oid, &format!("branch: Created from {}", target))?;
+                        Some("Switched to a new branch")
+                    },
+                    None => None,
+                };
                 
                 // Update HEAD to point to the new target or branch
-                repo.refs.set_head(target, &target_oid)?;
+                let from = match &current_ref {
+                    Reference::Symbolic(path) => repo.refs.short_name(path),
+                    Reference::Direct(oid) => oid.clone(),
+                };
+                let reflog_message = format!("checkout: moving from {} to {}", from, head_target);
+                repo.refs.set_head(head_target, &target_oid, &reflog_message)?;
                 
                 // Get the new reference for output
                 let new_ref = repo.refs.current_ref()?;
                 
                 // Print status information
-                Self::print_checkout_status(&repo, &current_ref, &current_oid, &new_ref, target, &target_oid)?;
+                Self::print_checkout_status(&repo, &current_ref, &current_oid, &new_ref, target, &target_oid, switched)?;
+
+                if stashed {
+                    stash.apply(&mut repo)?;
+                }
                 
                 let elapsed = start_time.elapsed();
-                println!("Checkout completed in {:.2}s", elapsed.as_secs_f32());
+                trace!("Checkout completed in {:.2}s", elapsed.as_secs_f32());
                 
                 Ok(())
             },
@@ -81,12 +166,67 @@ impl CheckoutCommand {
                 }
                 
                 eprintln!("Aborting");
+
+                // Put the stashed changes back where they came from
+                if stashed {
+                    stash.apply(&mut repo)?;
+                }
                 
                 Err(Error::Generic("Checkout failed due to conflicts".to_string()))
             }
         }
     }
     
+    // Locally modified files that change between the two commits and whose
+    // changes cannot be merged cleanly onto the target version
+    fn merge_conflicts(
+        repo: &mut Repository,
+        tree_diff: &HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
+    ) -> Result<Vec<String>, Error> {
+        let mut conflicts = Vec::new();
+
+        for (path, (old, new)) in tree_diff {
+            let old = match old {
+                Some(entry) if !entry.get_file_mode().is_directory() => entry,
+                _ => continue,
+            };
+            if new.as_ref().is_some_and(|entry| entry.get_file_mode().is_directory()) {
+                continue;
+            }
+
+            let local = if repo.workspace.path_exists(path)? {
+                Some(repo.workspace.read_file(path)?)
+            } else {
+                None
+            };
+            let base = repo.database.load(old.get_oid())?.to_bytes();
+            if local.as_ref() == Some(&base) {
+                continue;
+            }
+
+            let clean = match (new, local) {
+                (Some(new), Some(local)) => {
+                    let theirs = repo.database.load(new.get_oid())?.to_bytes();
+                    diff3::merge(
+                        &String::from_utf8_lossy(&base),
+                        &String::from_utf8_lossy(&theirs),
+                        &String::from_utf8_lossy(&local),
+                    )?.is_clean()
+                },
+                // Deleted locally and unchanged on the target is no conflict either
+                (None, None) => true,
+                _ => false,
+            };
+
+            if !clean {
+                conflicts.push(path.to_string_lossy().to_string());
+            }
+        }
+
+        conflicts.sort();
+        Ok(conflicts)
+    }
+
     // Print checkout status based on previous and current state
     fn print_checkout_status(
         repo: &Repository,
@@ -94,8 +234,13 @@ impl CheckoutCommand {
         current_oid: &Option<String>,
         new_ref: &Reference,
         target: &str,
-        target_oid: &str
+        target_oid: &str,
+        switched: Option<&str>
     ) -> Result<(), Error> {
+        if crate::core::trace::is_quiet() {
+            return Ok(());
+        }
+
         let stderr = io::stderr();
         let mut stderr_handle = stderr.lock();
         
@@ -107,7 +252,7 @@ impl CheckoutCommand {
         }
         
         // Print detachment notice if HEAD was attached but is now detached
-        if Self::is_detached_head(new_ref) && !Self::is_detached_head(current_ref) {
+        if Self::is_detached_head(new_ref) && !Self::is_detached_head(current_ref) && advice::enabled(advice::DETACHED_HEAD) {
             writeln!(stderr_handle, "Note: checking out '{}'.", target)?;
             writeln!(stderr_handle, "")?;
             writeln!(stderr_handle, "You are in 'detached HEAD' state. You can look around, make experimental")?;
@@ -130,7 +275,9 @@ impl CheckoutCommand {
             match new_ref {
                 Reference::Symbolic(path) => {
                     let branch_name = repo.refs.short_name(path);
-                    if new_ref == current_ref {
+                    if let Some(switched) = switched {
+                        writeln!(stderr_handle, "{} '{}'", switched, branch_name)?;
+                    } else if new_ref == current_ref {
                         writeln!(stderr_handle, "Already on '{}'", branch_name)?;
                     } else {
                         writeln!(stderr_handle, "Switched to branch '{}'", branch_name)?;
@@ -146,6 +293,48 @@ impl CheckoutCommand {
         Ok(())
     }
     
+    /// Write to the working tree the version of each of `paths` that HEAD
+    /// has, or with `theirs` the commit a stopped merge or cherry-pick is
+    /// bringing in, to settle a conflict on one side. The paths stay
+    /// unmerged until they are added. Paths without a conflict are checked
+    /// out from the index.
+    pub fn checkout_side(paths: &[String], theirs: bool) -> Result<(), Error> {
+        if paths.is_empty() {
+            return Err(Error::Generic("--ours and --theirs need the paths to check out".to_string()));
+        }
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let (side, oid) = if theirs {
+            let pending = PendingCommit::new(&git_path);
+            match pending.merge_type() {
+                Some(r#type @ (PendingCommitType::Merge | PendingCommitType::CherryPick)) => ("their", pending.merge_oid(r#type)?),
+                _ => return Err(Error::Generic("--theirs needs a merge or cherry-pick in progress".to_string())),
+            }
+        } else {
+            let head_oid = repo.refs.read_head()?
+                .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+            ("our", head_oid)
+        };
+        let files = head_files(&mut repo, &oid)?;
+        repo.index.load()?;
+        let conflicted = repo.index.conflict_paths();
+
+        for path in paths {
+            let blob_oid = if conflicted.contains(path) {
+                files.get(path.as_str())
+                    .ok_or_else(|| Error::Generic(format!("path '{}' does not have {} version", path, side)))?
+                    .get_oid().to_string()
+            } else {
+                repo.index.get_entry(path)
+                    .ok_or_else(|| Error::Generic(format!("pathspec '{}' did not match any file(s) known to ash", path)))?
+                    .get_oid().to_string()
+            };
+            let data = repo.database.load(&blob_oid)?.to_bytes();
+            repo.workspace.write_file(Path::new(path), &data)?;
+        }
+        Ok(())
+    }
+
     // Check if HEAD is detached (pointing directly to a commit)
     fn is_detached_head(reference: &Reference) -> bool {
         match reference {
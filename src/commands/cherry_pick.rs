@@ -1,16 +1,27 @@
+// src/commands/cherry_pick.rs - commit the changes of existing commits anew
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::core::database::commit::Commit;
-use crate::core::database::database::Database;
-use crate::core::refs::Refs;
-use crate::errors::error::Error;
-use crate::core::index::index::Index;
-use crate::core::repository::sequencer::{Action, Sequencer};
-use crate::core::revlist::RevList;
 use crate::commands::commit_writer::CommitWriter;
-use crate::core::revision::Revision;
+use crate::commands::rebase::RebaseCommand;
+use crate::commands::reset::ResetCommand;
+use crate::core::advice;
+use crate::core::database::author::Author;
+use crate::core::database::commit::Commit;
+use crate::core::diff::patch_id::patch_id;
+use crate::core::editor::Editor;
+use crate::core::merge::inputs;
+use crate::core::merge::resolve::Resolve;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::HEAD;
+use crate::core::repository::git_dir;
+use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
 use crate::core::repository::repository::Repository;
+use crate::core::repository::sequencer::{Action, Sequencer};
+use crate::core::revision::{Revision, COMMIT};
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::errors::error::Error;
+use crate::trace;
 
 // Constants
 const CONFLICT_NOTES: &str = "\
@@ -18,146 +29,364 @@ after resolving the conflicts, mark the corrected paths
 with 'ash add <paths>' or 'ash rm <paths>'
 and commit the result with 'ash commit'";
 
+const EMPTY_NOTES: &str = "\
+The previous cherry-pick is now empty, possibly due to conflict resolution.
+If you wish to commit it anyway, use:
+
+    ash cherry-pick --continue
+
+Otherwise, please use 'ash cherry-pick --skip'";
+
+/// What `cherry-pick` fails with once it has said which commit stopped it
+/// and how to go on
+pub const CHERRY_PICK_STOPPED: &str = "cherry-pick stopped";
+
+// How the sequence was started, kept with it for --continue and --skip
+const MAINLINE_OPTION: &str = "mainline";
+const ALLOW_EMPTY_OPTION: &str = "allow-empty";
+const KEEP_REDUNDANT_OPTION: &str = "keep-redundant-commits";
+
+/// How `ash cherry-pick` picks its commits
+#[derive(Debug, Clone, Default)]
+pub struct CherryPickOptions {
+    /// The parent, from 1, whose changes to a merge are picked
+    pub mainline: Option<u32>,
+    /// Keep commits that change nothing, instead of stopping at them
+    pub allow_empty: bool,
+    /// Keep commits whose changes HEAD has already, as empty commits,
+    /// instead of stopping at them; implies `allow_empty`
+    pub keep_redundant_commits: bool,
+}
+
 pub struct CherryPickCommand;
 
 impl CherryPickCommand {
-    pub fn execute(
-        args: &[String],
-        continue_op: bool,
-        abort: bool,
-        quit: bool,
-        mainline: Option<u32>,
-    ) -> Result<(), Error> {
-        let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
-        let repo_path = git_path.clone();
-        let db_path = git_path.join("objects");
-        let index_path = git_path.join("index");
-
-        // Verify repository exists
-        if !git_path.exists() {
-            return Err(Error::Generic("Not an AsheraFlow repository: .ash directory not found".into()));
-        }
-
-        // Initialize repository
+    /// Commit the changes each of `args` made to its parent on top of HEAD
+    /// in turn, with its author and message. A commit that conflicts stops
+    /// the sequence, as does one that comes to nothing: one that changed
+    /// nothing to begin with, unless `options.allow_empty`, or whose
+    /// changes are already there, by the patch ID of a commit HEAD has or
+    /// by the merge, unless `options.keep_redundant_commits`. The sequence
+    /// goes on with --continue or --skip.
+    pub fn execute(args: &[String], options: &CherryPickOptions) -> Result<(), Error> {
         let mut repo = Repository::new(".")?;
-        
-        // Create cherry-pick options map
-        let mut options = HashMap::new();
-        if let Some(mainline) = mainline {
-            options.insert(String::from("mainline"), mainline.to_string());
-        }
-
-        // Initialize sequencer
-        let mut sequencer = Sequencer::new(repo_path.clone());
-
-        if continue_op {
-            println!("Continuing cherry-pick operation...");
-            sequencer.load()?;
-            if sequencer.next_command().is_some() {
-                println!("Continuing with the next commit");
-                sequencer.drop_command()?;
-            } else {
-                println!("No commits left to cherry-pick");
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Sequencer::new(git_path.clone());
+        if sequencer.in_progress() {
+            return Err(Error::Generic(
+                "a cherry-pick or revert is already in progress\nhint: try \"ash cherry-pick (--continue | --skip | --quit | --abort)\"".to_string()
+            ));
+        }
+
+        let mut commits = Vec::new();
+        for arg in args {
+            let oid = Self::resolve(&mut repo, arg)?;
+            let commit = Self::load_commit(&mut repo, &oid)?;
+            match (commit.get_parents().len() > 1, options.mainline) {
+                (true, None) => {
+                    return Err(Error::Generic(format!("commit {} is a merge but no -m option was given.", oid)));
+                },
+                (false, Some(_)) => {
+                    return Err(Error::Generic(format!("mainline was specified but commit {} is not a merge.", oid)));
+                },
+                (true, Some(mainline)) if mainline as usize > commit.get_parents().len() => {
+                    return Err(Error::Generic(format!("commit {} does not have parent {}", oid, mainline)));
+                },
+                _ => commits.push(commit),
             }
-            return Ok(());
-        } else if abort {
-            println!("Aborting cherry-pick operation...");
-            if let Err(e) = sequencer.abort() {
-                println!("Warning during abort: {}", e);
+        }
+
+        let mut sequence_options = HashMap::new();
+        if let Some(mainline) = options.mainline {
+            sequence_options.insert(MAINLINE_OPTION.to_string(), mainline.to_string());
+        }
+        if options.allow_empty || options.keep_redundant_commits {
+            sequence_options.insert(ALLOW_EMPTY_OPTION.to_string(), "true".to_string());
+        }
+        if options.keep_redundant_commits {
+            sequence_options.insert(KEEP_REDUNDANT_OPTION.to_string(), "true".to_string());
+        }
+
+        trace!("Starting cherry-pick operation for {} commits...", commits.len());
+        sequencer.start(&sequence_options)?;
+        for commit in commits {
+            sequencer.add_pick(commit);
+        }
+        Self::run(&mut sequencer)
+    }
+
+    /// Commit the pick the sequence stopped at as the index now has it,
+    /// even if that changes nothing, unless it was committed already, and
+    /// go on
+    pub fn continue_pick() -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Self::load(&git_path)?;
+
+        let resumed = Self::commit_stopped(&mut repo, &git_path, &sequencer)
+            .and_then(|_| sequencer.drop_command());
+        if let Err(e) = resumed {
+            sequencer.dump()?;
+            return Err(e);
+        }
+        Self::run(&mut sequencer)
+    }
+
+    /// Leave out the pick the sequence stopped at, and what of it was
+    /// applied, and go on
+    pub fn skip() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Self::load(&git_path)?;
+
+        let pending = PendingCommit::new(&git_path);
+        if pending.in_progress(PendingCommitType::CherryPick) {
+            pending.clear(PendingCommitType::CherryPick)?;
+        }
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+        ResetCommand::execute(&[head_oid], false, false, true, true, None)?;
+        sequencer.drop_command()?;
+        Self::run(&mut sequencer)
+    }
+
+    /// Give up on the sequence, putting HEAD, the index and the working
+    /// tree back as they were before it, unless HEAD has moved since
+    pub fn abort() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Sequencer::new(git_path.clone());
+        if !sequencer.in_progress() {
+            return Err(Error::Generic("no cherry-pick or revert in progress".to_string()));
+        }
+
+        let pending = PendingCommit::new(&git_path);
+        if pending.in_progress(PendingCommitType::CherryPick) {
+            pending.clear(PendingCommitType::CherryPick)?;
+        }
+        let original = sequencer.original_head()?;
+        match sequencer.abort() {
+            Ok(()) => ResetCommand::execute(&[original], false, false, true, true, None),
+            Err(e) => {
+                println!("warning: {}", e);
+                Ok(())
+            },
+        }
+    }
+
+    /// Stop the sequence, keeping HEAD, the index and the working tree
+    pub fn quit() -> Result<(), Error> {
+        let git_path = git_dir::resolve(Path::new("."));
+        let pending = PendingCommit::new(&git_path);
+        if pending.in_progress(PendingCommitType::CherryPick) {
+            pending.clear(PendingCommitType::CherryPick)?;
+        }
+        Sequencer::new(git_path).quit()
+    }
+
+    // Pick the commits left, stopping at the first that conflicts or comes
+    // to nothing with the rest saved for --continue
+    fn run(sequencer: &mut Sequencer) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        while let Some((action, commit)) = sequencer.next_command() {
+            if action != Action::Pick {
+                sequencer.dump()?;
+                return Err(Error::Generic(format!("{} action not supported in cherry-pick", action)));
             }
-            return Ok(());
-        } else if quit {
-            println!("Quitting cherry-pick operation without aborting...");
-            sequencer.quit()?;
-            return Ok(());
-        } else {
-            println!("Starting cherry-pick operation for {} commits...", args.len());
-            sequencer.start(&options)?;
-            
-            // Resolve each commit hash separately using Revision
-            let mut resolved_oids = Vec::new();
-            for arg in args {
-                let mut revision = Revision::new(&mut repo, arg);
-                match revision.resolve("commit") {
-                    Ok(oid) => {
-                        resolved_oids.push(oid);
-                    },
-                    Err(e) => {
-                        // Handle invalid revision
-                        for err in revision.errors {
-                            eprintln!("error: {}", err.message);
-                            for hint in &err.hint {
-                                eprintln!("hint: {}", hint);
-                            }
-                        }
-                        return Err(e);
-                    }
-                }
+            if let Err(e) = Self::pick(&mut repo, &git_path, sequencer, &commit) {
+                sequencer.dump()?;
+                return Err(e);
             }
-            
-            // Get the commits to cherry-pick using resolved OIDs
-            let mut commits = Vec::new();
-            for oid in resolved_oids {
-                let commit_obj = repo.database.load(&oid)?;
-                if let Some(commit) = commit_obj.as_any().downcast_ref::<Commit>() {
-                    commits.push(commit.clone());
-                } else {
-                    return Err(Error::Generic(format!("Object {} is not a commit", oid)));
-                }
+            sequencer.drop_command()?;
+        }
+
+        sequencer.quit()
+    }
+
+    // Apply the changes `commit` made to its parent onto HEAD and commit
+    // them, or stop
+    fn pick(repo: &mut Repository, git_path: &Path, sequencer: &Sequencer, commit: &Commit) -> Result<(), Error> {
+        let oid = commit.get_oid().cloned().unwrap_or_default();
+        let name = format!("{}... {}", repo.database.short_oid(&oid), commit.title_line().trim());
+        let parent = Self::select_parent(sequencer, commit)?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+        let allow_empty = sequencer.get_option(ALLOW_EMPTY_OPTION)?.is_some();
+        let keep_redundant = sequencer.get_option(KEEP_REDUNDANT_OPTION)?.is_some();
+        trace!("Cherry-picking commit: {}", oid);
+
+        if repo.database.tree_diff(parent.as_deref(), Some(&oid), &PathFilter::new())?.is_empty() {
+            repo.index.load()?;
+            return if allow_empty {
+                Self::commit(repo, git_path, &head_oid, commit.get_message(), commit.get_author().cloned())
+            } else {
+                Self::stop_empty(git_path, commit)
+            };
+        }
+        if Self::already_applied(repo, parent.as_deref(), &oid, &head_oid)? {
+            trace!("{} makes the changes of a commit HEAD has", oid);
+            repo.index.load()?;
+            return if keep_redundant {
+                Self::commit(repo, git_path, &head_oid, commit.get_message(), commit.get_author().cloned())
+            } else {
+                Self::stop_empty(git_path, commit)
+            };
+        }
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+        let inputs = inputs::CherryPick::new(HEAD.to_string(), name.clone(), head_oid.clone(), oid.clone(), parent.into_iter().collect());
+        let result = Resolve::new(&mut repo.database, &repo.workspace, &mut repo.index, &inputs).execute();
+        repo.index.write_updates()?;
+        if repo.index.has_conflict() {
+            return Self::stop_conflict(repo, git_path, commit, &name);
+        }
+        result?;
+
+        if RebaseCommand::index_matches(repo, &head_oid)? && !keep_redundant {
+            return Self::stop_empty(git_path, commit);
+        }
+        Self::commit(repo, git_path, &head_oid, commit.get_message(), commit.get_author().cloned())
+    }
+
+    // Commit the resolution of the pick the sequence stopped at with the
+    // message in MERGE_MSG, unless there is none to commit
+    fn commit_stopped(repo: &mut Repository, git_path: &Path, sequencer: &Sequencer) -> Result<(), Error> {
+        let pending = PendingCommit::new(git_path);
+        if !pending.in_progress(PendingCommitType::CherryPick) {
+            return Ok(());
+        }
+        repo.index.load()?;
+        if repo.index.has_conflict() {
+            return Err(Error::Generic(
+                "Committing is not possible because you have unmerged files.\nhint: Fix them up in the work tree, and then use 'ash add/rm <file>'\nhint: as appropriate to mark resolution and make a commit.".to_string()
+            ));
+        }
+
+        let (_, commit) = sequencer.next_command()
+            .ok_or_else(|| Error::Generic("no cherry-pick in progress".to_string()))?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+        let message = Editor::remove_notes(&pending.merge_message()?)
+            .unwrap_or_else(|| commit.get_message().to_string());
+        Self::commit(repo, git_path, &head_oid, &message, commit.get_author().cloned())?;
+        pending.clear(PendingCommitType::CherryPick)
+    }
+
+    fn commit(repo: &mut Repository, git_path: &Path, head_oid: &str, message: &str, author: Option<Author>) -> Result<(), Error> {
+        let mut writer = CommitWriter::new(
+            Path::new("."),
+            git_path.to_path_buf(),
+            &mut repo.database,
+            &mut repo.index,
+            &repo.refs,
+        );
+        writer.reflog_action = "cherry-pick".to_string();
+        let new_commit = writer.write_commit(vec![head_oid.to_string()], message, author)?;
+        writer.print_commit(&new_commit)
+    }
+
+    // Stop at a pick that comes to nothing, for --continue to commit it
+    // anyway or --skip to leave it out
+    fn stop_empty(git_path: &Path, commit: &Commit) -> Result<(), Error> {
+        let pending = PendingCommit::new(git_path);
+        pending.start(commit.get_oid().map_or("", String::as_str), PendingCommitType::CherryPick)?;
+        std::fs::write(&pending.message_path, commit.get_message())?;
+        eprintln!("{}", EMPTY_NOTES);
+        Err(Error::Generic(CHERRY_PICK_STOPPED.to_string()))
+    }
+
+    // Stop at a pick that conflicts, with its message and the conflicted
+    // paths in MERGE_MSG for the commit that resolves it
+    fn stop_conflict(repo: &mut Repository, git_path: &Path, commit: &Commit, name: &str) -> Result<(), Error> {
+        let pending = PendingCommit::new(git_path);
+        pending.start(commit.get_oid().map_or("", String::as_str), PendingCommitType::CherryPick)?;
+        let conflicts = repo.index.conflict_paths();
+        Editor::edit(pending.message_path.clone(), None, |editor| {
+            editor.write(commit.get_message().trim_end())?;
+            editor.write("")?;
+            editor.note("Conflicts:")?;
+            for path in &conflicts {
+                editor.note(&format!("\t{}", path))?;
             }
-            
-            // Add commits to the sequencer
-            for commit in commits.iter().rev() {
-                sequencer.add_pick(commit.clone());
+            editor.close();
+            Ok(())
+        })?;
+
+        eprintln!("error: could not apply {}", name);
+        advice::advise(advice::MERGE_CONFLICT, CONFLICT_NOTES);
+        Err(Error::Generic(CHERRY_PICK_STOPPED.to_string()))
+    }
+
+    // Whether a commit HEAD has and `oid` does not makes the same changes
+    // as `oid` makes to `parent`, going by their patch IDs
+    fn already_applied(repo: &mut Repository, parent: Option<&str>, oid: &str, head_oid: &str) -> Result<bool, Error> {
+        let id = match patch_id(&mut repo.database, parent, oid)? {
+            Some(id) => id,
+            None => return Ok(false),
+        };
+        let range = [format!("^{}", oid), head_oid.to_string()];
+        let walked = RevList::new(&mut repo.database, &repo.refs, &range, false)?
+            .commits(&WalkOptions::default())?;
+        for walked in walked.iter().filter(|walked| !walked.commit.is_merge()) {
+            let walked_oid = walked.commit.get_oid().cloned().unwrap_or_default();
+            if patch_id(&mut repo.database, walked.commit.get_parent().map(String::as_str), &walked_oid)?.as_deref() == Some(id.as_str()) {
+                return Ok(true);
             }
-            
-            println!("Added {} commits to cherry-pick", commits.len());
-        }
-        
-        // Process the first commit
-        if let Some((action, commit)) = sequencer.next_command() {
-            // Initialize commit writer
-            let mut commit_writer = CommitWriter::new(
-                root_path,
-                repo_path,
-                &mut repo.database,
-                &mut repo.index,
-                &repo.refs
-            );
-            
-            match action {
-                Action::Pick => {
-                    let commit_oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
-                    println!("Cherry-picking commit: {}", commit_oid);
-                    
-                    // Get original author and message
-                    let author = match commit.get_author() {
-                        Some(a) => a.clone(),
-                        None => commit_writer.current_author()
-                    };
-                    let message = commit.get_message().to_string();
-                    
-                    // Get the current HEAD as parent
-                    let head_ref = repo.refs.read_head()?.unwrap_or_default();
-                    
-                    // Use CommitWriter to handle the commit creation
-                    let parents = vec![head_ref];
-                    let new_commit = commit_writer.write_commit(parents, &message, Some(author))?;
-                    
-                    // Print commit information
-                    commit_writer.print_commit(&new_commit)?;
-                    
-                    // Remove the cherry-pick command from the sequencer
-                    sequencer.drop_command()?;
-                    println!("Successfully cherry-picked commit");
-                },
-                Action::Revert => {
-                    return Err(Error::Generic("Revert action not supported in cherry-pick".into()));
+        }
+        Ok(false)
+    }
+
+    // The parent whose changes to `commit` are picked: the one --mainline
+    // names for a merge, or the only one, or none for a root commit
+    fn select_parent(sequencer: &Sequencer, commit: &Commit) -> Result<Option<String>, Error> {
+        let parents = commit.get_parents();
+        let mainline = match sequencer.get_option(MAINLINE_OPTION)? {
+            Some(value) => value.parse::<usize>()
+                .map_err(|_| Error::Generic(format!("invalid mainline: {}", value)))?,
+            None => 1,
+        };
+        match parents.get(mainline.saturating_sub(1)) {
+            Some(parent) => Ok(Some(parent.clone())),
+            None if parents.is_empty() => Ok(None),
+            None => Err(Error::Generic(format!(
+                "commit {} does not have parent {}", commit.get_oid().map_or("", String::as_str), mainline
+            ))),
+        }
+    }
+
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        let mut revision = Revision::new(repo, name);
+        match revision.resolve(COMMIT) {
+            Ok(oid) => Ok(oid),
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                    for hint in &err.hint {
+                        eprintln!("hint: {}", hint);
+                    }
                 }
+                Err(e)
             }
         }
-        
-        Ok(())
     }
-} 
\ No newline at end of file
+
+    fn load_commit(repo: &mut Repository, oid: &str) -> Result<Commit, Error> {
+        match repo.database.load(oid)?.as_any().downcast_ref::<Commit>() {
+            Some(commit) => Ok(commit.clone()),
+            None => Err(Error::Generic(format!("Object {} is not a commit", oid))),
+        }
+    }
+
+    fn load(git_path: &Path) -> Result<Sequencer, Error> {
+        let mut sequencer = Sequencer::new(PathBuf::from(git_path));
+        if !sequencer.in_progress() {
+            return Err(Error::Generic("no cherry-pick or revert in progress".to_string()));
+        }
+        sequencer.load()?;
+        Ok(sequencer)
+    }
+}
@@ -0,0 +1,340 @@
+// src/commands/clean.rs - remove untracked files from the working tree
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::core::color::Color;
+use crate::core::config::Config;
+use crate::core::ignore::{glob_to_regex, Ignore, ALWAYS_IGNORED};
+use crate::core::index::index::Index;
+use crate::core::repository::git_dir;
+use crate::core::workspace::Workspace;
+use crate::errors::error::Error;
+
+const REQUIRE_FORCE_KEY: &str = "clean.requireForce";
+
+/// What `ash clean` was asked to remove, and how
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// Only say what would be removed
+    pub dry_run: bool,
+    pub force: bool,
+    pub interactive: bool,
+    /// Remove untracked directories as a whole, not only the files in
+    /// directories that hold tracked ones
+    pub directories: bool,
+    /// Remove ignored files too
+    pub ignored: bool,
+    /// Remove only ignored files
+    pub only_ignored: bool,
+    /// Patterns taken as ignore patterns on top of the ignore files
+    pub excludes: Vec<String>,
+    /// Only clean these paths
+    pub paths: Vec<String>,
+}
+
+// Something that would be removed: a file, or a directory with everything
+// in it
+#[derive(Debug, Clone)]
+struct Item {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+impl Item {
+    // As listed: directories end with a slash
+    fn display(&self) -> String {
+        let path = self.path.to_string_lossy().to_string();
+        if self.is_dir { format!("{}/", path) } else { path }
+    }
+}
+
+// Where a path stands against the paths asked for
+#[derive(PartialEq)]
+enum Scope {
+    Inside,
+    Above,
+    Outside,
+}
+
+pub struct CleanCommand;
+
+impl CleanCommand {
+    /// Remove the untracked files `options` select, or with `dry_run` say
+    /// which would go. Unless `clean.requireForce` is false, nothing is
+    /// removed without `force` or `interactive`. The interactive session
+    /// lists what would go and lets the list be narrowed by pattern or by
+    /// number, or each item be asked about, before anything is removed.
+    /// Repositories nested in the working tree are always left alone.
+    pub fn execute(options: &CleanOptions) -> Result<(), Error> {
+        let root_path = Path::new(".");
+        let git_path = git_dir::resolve(root_path);
+        if !git_path.exists() {
+            return Err(Error::Generic("not an ash repository (or any of the parent directories): .ash directory not found".into()));
+        }
+        let require_force = Config::load(&git_path).ok()
+            .and_then(|config| config.get_bool(REQUIRE_FORCE_KEY))
+            .unwrap_or(true);
+        if require_force && !options.force && !options.dry_run && !options.interactive {
+            return Err(Error::Generic(
+                "clean.requireForce defaults to true and neither -i, -n, nor -f given; refusing to clean".to_string()
+            ));
+        }
+
+        let mut index = Index::new(git_path.join("index"));
+        index.load()?;
+        let excludes = options.excludes.iter()
+            .map(|pattern| Regex::new(&glob_to_regex(pattern))
+                .map_err(|e| Error::Generic(format!("invalid pattern '{}': {}", pattern, e))))
+            .collect::<Result<Vec<Regex>, Error>>()?;
+        let paths: Vec<PathBuf> = options.paths.iter()
+            .map(|path| Path::new(path.trim_end_matches('/')).components().collect())
+            .collect();
+
+        let mut collector = Collector { root_path, index: &index, options, excludes, paths, ignore: Ignore::new(root_path) };
+        let mut items = Vec::new();
+        collector.collect(Path::new(""), &mut items)?;
+
+        if options.dry_run {
+            for item in &items {
+                println!("Would remove {}", item.display());
+            }
+            return Ok(());
+        }
+        if options.interactive {
+            items = match Self::interact(items)? {
+                Some(items) => items,
+                None => return Ok(()),
+            };
+        }
+        Self::remove(&Workspace::new(root_path), &items)
+    }
+
+    fn remove(workspace: &Workspace, items: &[Item]) -> Result<(), Error> {
+        for item in items {
+            println!("Removing {}", item.display());
+            if item.is_dir {
+                workspace.force_remove_directory(&item.path)?;
+            } else {
+                workspace.remove_file(&item.path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The items to remove once the user is done choosing, or None to remove
+    // nothing. Asking about each item settles the list there and then.
+    fn interact(mut items: Vec<Item>) -> Result<Option<Vec<Item>>, Error> {
+        loop {
+            if items.is_empty() {
+                println!("No more files to clean, exiting.");
+                return Ok(None);
+            }
+            println!("Would remove the following items:");
+            for item in &items {
+                println!("  {}", item.display());
+            }
+            println!("Options:");
+            println!("  1. Clean, removing the items listed");
+            println!("  2. Filter by pattern, keeping the items that match from removal");
+            println!("  3. Select by numbers the items to remove");
+            println!("  4. Ask about each item");
+            println!("  q. Quit without removing anything");
+
+            let choice = match Self::prompt("Enter choice: ")? {
+                Some(choice) => choice,
+                None => return Ok(None),
+            };
+            match choice.as_str() {
+                "1" | "c" | "clean" => return Ok(Some(items)),
+                "2" | "f" | "filter" => items = Self::filter_by_pattern(items)?,
+                "3" | "s" | "select" => items = Self::select_by_numbers(items)?,
+                "4" | "a" | "ask" => return Self::ask_each(items).map(Some),
+                "q" | "quit" => {
+                    println!("Bye.");
+                    return Ok(None);
+                },
+                "" => {},
+                _ => println!("  {} Unknown choice '{}'", Color::red("✗"), choice),
+            }
+        }
+    }
+
+    // Drop the items matching any of the globs entered, as ignore patterns
+    // would
+    fn filter_by_pattern(items: Vec<Item>) -> Result<Vec<Item>, Error> {
+        let input = match Self::prompt("Patterns of items to keep, separated by spaces: ")? {
+            Some(input) if !input.is_empty() => input,
+            _ => return Ok(items),
+        };
+        let mut patterns = Vec::new();
+        for pattern in input.split_whitespace() {
+            match Regex::new(&glob_to_regex(pattern.trim_end_matches('/'))) {
+                Ok(regex) => patterns.push(regex),
+                Err(_) => println!("  {} Invalid pattern '{}'", Color::red("✗"), pattern),
+            }
+        }
+        Ok(items.into_iter()
+            .filter(|item| {
+                let path = item.path.to_string_lossy();
+                let name = item.path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                !patterns.iter().any(|regex| regex.is_match(&path) || regex.is_match(&name))
+            })
+            .collect())
+    }
+
+    // Keep only the items chosen by number: "1 3", "2-4" or "*" for all
+    fn select_by_numbers(items: Vec<Item>) -> Result<Vec<Item>, Error> {
+        for (i, item) in items.iter().enumerate() {
+            println!("  {:>2}: {}", i + 1, item.display());
+        }
+        let input = match Self::prompt("Numbers to remove: ")? {
+            Some(input) if !input.is_empty() => input,
+            _ => return Ok(items),
+        };
+        if input == "*" {
+            return Ok(items);
+        }
+
+        let mut chosen = vec![false; items.len()];
+        for choice in input.split([' ', ',']).filter(|choice| !choice.is_empty()) {
+            let (first, last) = match choice.split_once('-') {
+                Some((first, last)) => (first.parse::<usize>().ok(), last.parse::<usize>().ok()),
+                None => (choice.parse::<usize>().ok(), choice.parse::<usize>().ok()),
+            };
+            match (first, last) {
+                (Some(first), Some(last)) if first >= 1 && first <= last && last <= items.len() => {
+                    chosen[first - 1..last].iter_mut().for_each(|chosen| *chosen = true);
+                },
+                _ => println!("  {} Huh ({})?", Color::red("✗"), choice),
+            }
+        }
+        Ok(items.into_iter().zip(chosen).filter(|(_, chosen)| *chosen).map(|(item, _)| item).collect())
+    }
+
+    // Ask about every item, keeping those answered with yes
+    fn ask_each(items: Vec<Item>) -> Result<Vec<Item>, Error> {
+        let mut agreed = Vec::new();
+        for item in items {
+            match Self::prompt(&format!("Remove {} [y/N]? ", item.display()))? {
+                Some(answer) if answer.eq_ignore_ascii_case("y") || answer.eq_ignore_ascii_case("yes") => agreed.push(item),
+                Some(_) => {},
+                None => break,
+            }
+        }
+        Ok(agreed)
+    }
+
+    // The line entered after `text`, trimmed, or None at the end of input
+    fn prompt(text: &str) -> Result<Option<String>, Error> {
+        print!("{}", text);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    }
+}
+
+// Walks the working tree for what `ash clean` would remove
+struct Collector<'a> {
+    root_path: &'a Path,
+    index: &'a Index,
+    options: &'a CleanOptions,
+    excludes: Vec<Regex>,
+    paths: Vec<PathBuf>,
+    ignore: Ignore,
+}
+
+impl Collector<'_> {
+    // Add the items under `dir`, relative to the root, to `items`. Returns
+    // whether everything under it was added, so that a directory can go as
+    // a whole.
+    fn collect(&mut self, dir: &Path, items: &mut Vec<Item>) -> Result<bool, Error> {
+        let mark = self.ignore.enter(&dir.to_string_lossy());
+        let mut entries: Vec<fs::DirEntry> = fs::read_dir(self.root_path.join(dir))?.flatten().collect();
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut everything = true;
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if ALWAYS_IGNORED.contains(&name.as_str()) {
+                everything = false;
+                continue;
+            }
+            let path = dir.join(&name);
+            let scope = self.scope(&path);
+            if scope == Scope::Outside {
+                everything = false;
+                continue;
+            }
+            let is_dir = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false);
+            let ignored = self.ignore.is_ignored(&path.to_string_lossy(), is_dir)
+                || self.excludes.iter().any(|regex| regex.is_match(&name) || regex.is_match(&path.to_string_lossy()));
+
+            if !is_dir {
+                if scope == Scope::Inside && !self.index.tracked_file(&path) && self.wanted(ignored) {
+                    items.push(Item { path, is_dir: false });
+                } else {
+                    everything = false;
+                }
+                continue;
+            }
+
+            // Another repository is not ours to clean
+            if ALWAYS_IGNORED.iter().any(|repository| self.root_path.join(&path).join(repository).exists()) {
+                everything = false;
+                continue;
+            }
+            let untracked = !self.index.tracked_directory(&path);
+            if scope == Scope::Inside && untracked && !self.options.directories {
+                everything = false;
+                continue;
+            }
+            // An ignored directory goes as a whole or not at all
+            if scope == Scope::Inside && untracked && ignored {
+                if self.wanted(true) {
+                    items.push(Item { path, is_dir: true });
+                } else {
+                    everything = false;
+                }
+                continue;
+            }
+
+            let start = items.len();
+            let all = self.collect(&path, items)?;
+            if scope == Scope::Inside && untracked && all {
+                // Everything in it goes, so the directory does
+                items.truncate(start);
+                items.push(Item { path, is_dir: true });
+            } else {
+                everything = false;
+            }
+        }
+        self.ignore.leave(mark);
+        Ok(everything)
+    }
+
+    // Whether an untracked path is to be removed, given whether it is ignored
+    fn wanted(&self, ignored: bool) -> bool {
+        if self.options.only_ignored {
+            ignored
+        } else {
+            self.options.ignored || !ignored
+        }
+    }
+
+    fn scope(&self, path: &Path) -> Scope {
+        if self.paths.is_empty() || self.paths.iter().any(|wanted| path.starts_with(wanted)) {
+            Scope::Inside
+        } else if self.paths.iter().any(|wanted| wanted.starts_with(path)) {
+            Scope::Above
+        } else {
+            Scope::Outside
+        }
+    }
+}
@@ -0,0 +1,155 @@
+// src/commands/clone.rs - copy another repository into a new directory
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::error::Error;
+use crate::commands::fetch::{FetchCommand, FetchOptions};
+use crate::commands::init::InitCommand;
+use crate::commands::remote::RemoteCommand;
+use crate::core::config::Config;
+use crate::core::remote::{self, transport, DEFAULT_REMOTE};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+
+const MIRROR_REFSPEC: &str = "+refs/*:refs/*";
+const BARE_REFSPECS: [&str; 2] = ["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+
+pub struct CloneCommand;
+
+impl CloneCommand {
+    /// Clone `url` into `directory`, by default named after the URL. A
+    /// `bare` clone has no worktree and takes the remote branches and tags
+    /// as its own. A `mirror` clone is bare too, but copies every ref under
+    /// refs/ and remembers to keep doing so: later fetches replicate
+    /// updates and deletions exactly, and a push to it sends every ref.
+    /// With a `filter` the clone is partial, see `FetchOptions::filter`.
+    pub fn execute(url: &str, directory: Option<&str>, bare: bool, mirror: bool, filter: Option<&str>) -> Result<(), Error> {
+        let bare = bare || mirror;
+
+        // Relative paths would not survive moving into the new repository
+        let url = match Path::new(url).canonicalize() {
+            Ok(path) if !url.contains("://") => path.to_string_lossy().to_string(),
+            _ => url.to_string(),
+        };
+        let advertised = transport::connect(&url)?.advertised_refs()?;
+
+        let directory = match directory {
+            Some(directory) => directory.to_string(),
+            None => Self::directory_for(&url, bare),
+        };
+        let target = Path::new(&directory);
+        let existed = target.exists();
+        if existed && fs::read_dir(target)?.next().is_some() {
+            return Err(Error::Generic(format!(
+                "destination path '{}' already exists and is not an empty directory.", directory
+            )));
+        }
+
+        if !crate::core::trace::is_quiet() {
+            let kind = if bare { "bare repository " } else { "" };
+            eprintln!("Cloning into {}'{}'...", kind, directory);
+        }
+
+        // Leave nothing half-made behind
+        let cwd = env::current_dir()?;
+        let result = Self::populate(&url, &cwd.join(target), &advertised, bare, mirror, filter);
+        if result.is_err() {
+            env::set_current_dir(&cwd)?;
+            if existed {
+                for entry in fs::read_dir(target)?.flatten() {
+                    let _ = fs::remove_dir_all(entry.path()).or_else(|_| fs::remove_file(entry.path()));
+                }
+            } else {
+                let _ = fs::remove_dir_all(target);
+            }
+        }
+        result
+    }
+
+    fn populate(
+        url: &str,
+        directory: &Path,
+        advertised: &BTreeMap<String, String>,
+        bare: bool,
+        mirror: bool,
+        filter: Option<&str>,
+    ) -> Result<(), Error> {
+        InitCommand::execute(&directory.to_string_lossy(), None, bare)?;
+        env::set_current_dir(directory)?;
+
+        let git_path = git_dir::resolve(&env::current_dir()?);
+
+        let refspecs: Vec<String> = if bare {
+            let mut config = Config::load(&git_path)?;
+            config.add(&format!("remote.{}.url", DEFAULT_REMOTE), url)?;
+            if mirror {
+                config.add(&format!("remote.{}.fetch", DEFAULT_REMOTE), MIRROR_REFSPEC)?;
+                config.add(&format!("remote.{}.mirror", DEFAULT_REMOTE), "true")?;
+                Vec::new()
+            } else {
+                BARE_REFSPECS.iter().map(|spec| spec.to_string()).collect()
+            }
+        } else {
+            RemoteCommand::add(DEFAULT_REMOTE, url)?;
+            Vec::new()
+        };
+
+        let options = FetchOptions { filter: filter.map(str::to_string), ..FetchOptions::default() };
+        FetchCommand::execute(Some(DEFAULT_REMOTE), &refspecs, &options)?;
+        // Opened only now, so that a filtered fetch's promisor remote is known
+        let mut repo = Repository::new(".")?;
+
+        let (branch, oid) = match remote::guess_head(advertised) {
+            Some(head) => head,
+            None => {
+                eprintln!("warning: You appear to have cloned an empty repository.");
+                return Ok(());
+            }
+        };
+
+        if bare {
+            repo.refs.set_head(&branch, &oid, &format!("clone: from {}", url))?;
+            return Ok(());
+        }
+
+        // Remember the remote's default branch, then check it out, tracking
+        // its upstream
+        repo.refs.set_symbolic_ref(
+            &format!("refs/remotes/{}/HEAD", DEFAULT_REMOTE),
+            &format!("refs/remotes/{}/{}", DEFAULT_REMOTE, branch),
+        )?;
+        let reflog_message = format!("clone: from {}", url);
+        repo.refs.update_ref(&format!("refs/heads/{}", branch), &oid, &reflog_message)?;
+        repo.refs.set_head(&branch, &oid, &reflog_message)?;
+        let mut config = Config::load(&git_path)?;
+        config.add(&format!("branch.{}.remote", branch), DEFAULT_REMOTE)?;
+        config.add(&format!("branch.{}.merge", branch), &format!("refs/heads/{}", branch))?;
+
+        let tree_diff = repo.tree_diff(None, Some(&oid))?;
+        repo.index.load_for_update()?;
+        let mut migration = repo.migration(tree_diff);
+        if let Err(e) = migration.apply_changes() {
+            repo.index.rollback()?;
+            return Err(e);
+        }
+        repo.index.write_updates()?;
+        Ok(())
+    }
+
+    // `../upstream/` or `../upstream/.ash` clone into `upstream`, bare
+    // clones into `upstream.ash`
+    fn directory_for(url: &str, bare: bool) -> String {
+        let path = url.trim_end_matches('/');
+        let path = path.strip_suffix(&format!("/{}", git_dir::GIT_DIR_NAME)).unwrap_or(path);
+        let name = path.rsplit(['/', ':']).next().unwrap_or(path);
+        let name = name.strip_suffix(git_dir::GIT_DIR_NAME).unwrap_or(name).trim_end_matches('.');
+
+        if bare {
+            format!("{}{}", name, git_dir::GIT_DIR_NAME)
+        } else {
+            name.to_string()
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// src/commands/comment.rs - add and list review comments kept in refs/notes/comments
+use crate::errors::error::Error;
+use crate::core::color::Color;
+use crate::core::comments::{ReviewComment, COMMENTS_REF};
+use crate::core::notes::Notes;
+use crate::core::repository::autostash::{current_author, head_files};
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
+
+pub struct CommentCommand;
+
+impl CommentCommand {
+    /// Attach a comment to `<path>:<line>` as it is in `commit`
+    pub fn add(commit: &str, anchor: &str, message: &str) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let commit_oid = Self::resolve_commit(&mut repo, commit)?;
+
+        let (path, line) = anchor.rsplit_once(':')
+            .and_then(|(path, line)| line.parse::<usize>().ok().map(|line| (path.to_string(), line)))
+            .ok_or_else(|| Error::Generic(format!("'{}' is not of the form <path>:<line>", anchor)))?;
+
+        let lines = Self::file_lines(&mut repo, &commit_oid, &path)?
+            .ok_or_else(|| Error::Generic(format!("path '{}' does not exist in '{}'", path, commit)))?;
+        if line == 0 || line > lines.len() {
+            return Err(Error::Generic(format!("'{}' has only {} lines", path, lines.len())));
+        }
+
+        if message.trim().is_empty() {
+            return Err(Error::Generic("Aborting comment due to empty message".to_string()));
+        }
+
+        let mut notes = Notes::new(&mut repo.database, &repo.refs, COMMENTS_REF);
+        let mut comments = match notes.read(&commit_oid)? {
+            Some(text) => ReviewComment::parse_all(&text)?,
+            None => Vec::new(),
+        };
+        comments.push(ReviewComment {
+            path: path.clone(),
+            line,
+            author: current_author(),
+            body: message.trim_end().to_string(),
+        });
+
+        let summary = format!("Comment on {} {}:{}", &commit_oid[..7], path, line);
+        notes.write(&commit_oid, &ReviewComment::format_all(&comments), current_author(), &summary)?;
+
+        println!("{}", summary);
+        Ok(())
+    }
+
+    /// Show the comments on `commit`, or on every commit, optionally only for `path`
+    pub fn list(commit: Option<&str>, path: Option<&str>) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+
+        let annotated = match commit {
+            Some(commit) => {
+                let oid = Self::resolve_commit(&mut repo, commit)?;
+                let mut notes = Notes::new(&mut repo.database, &repo.refs, COMMENTS_REF);
+                notes.read(&oid)?.map(|text| (oid, text)).into_iter().collect()
+            },
+            None => Notes::new(&mut repo.database, &repo.refs, COMMENTS_REF).list()?,
+        };
+
+        for (commit_oid, text) in annotated {
+            let mut comments = ReviewComment::parse_all(&text)?;
+            comments.retain(|comment| path.is_none_or(|p| comment.path == p));
+            comments.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+
+            for comment in comments {
+                println!("{} {}:{}", Color::yellow(&format!("comment {}", &commit_oid[..7])), comment.path, comment.line);
+                println!("Author: {} <{}>", comment.author.name, comment.author.email);
+                println!("Date:   {}", comment.author.timestamp.format("%a %b %e %H:%M:%S %Y"));
+                println!();
+
+                if let Some(lines) = Self::file_lines(&mut repo, &commit_oid, &comment.path)? {
+                    if let Some(source) = lines.get(comment.line - 1) {
+                        println!("  {} | {}", Color::cyan(&comment.line.to_string()), source);
+                        println!();
+                    }
+                }
+
+                for line in comment.body.lines() {
+                    println!("    {}", line);
+                }
+                println!();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_commit(repo: &mut Repository, commit: &str) -> Result<String, Error> {
+        let mut revision = Revision::new(repo, commit);
+        match revision.resolve("commit") {
+            Ok(oid) => Ok(oid),
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                    for hint in &err.hint {
+                        eprintln!("hint: {}", hint);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    // Lines of `path` as it is in `commit_oid`, or None if it does not exist there
+    fn file_lines(repo: &mut Repository, commit_oid: &str, path: &str) -> Result<Option<Vec<String>>, Error> {
+        let files = head_files(repo, commit_oid)?;
+        match files.get(path) {
+            Some(entry) => {
+                let data = repo.database.load(entry.get_oid())?.to_bytes();
+                Ok(Some(String::from_utf8_lossy(&data).lines().map(str::to_string).collect()))
+            },
+            None => Ok(None),
+        }
+    }
+}
@@ -4,28 +4,35 @@ use std::time::Instant;
 
 use crate::core::database::database::Database;
 use crate::core::database::commit::Commit as DatabaseCommit;
+use crate::core::editor::Editor;
 use crate::core::index::index::Index;
 use crate::core::refs::Refs;
 use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
 use crate::commands::commit_writer::CommitWriter;
 use crate::errors::error::Error;
+use crate::core::repository::git_dir;
+use crate::trace;
+
+/// The variables that name the editor for messages, the first one set
+/// winning; the editor's default runs when none is
+pub const EDITOR_VARIABLES: [&str; 3] = ["GIT_EDITOR", "VISUAL", "EDITOR"];
 
 pub struct CommitCommand;
 
 impl CommitCommand {
-    pub fn execute(message: &str, amend: bool, reuse_message: Option<&str>, edit: bool) -> Result<(), Error> {
+    pub fn execute(message: &str, amend: bool, reuse_message: Option<&str>, edit: bool, verbose: bool) -> Result<(), Error> {
         let start_time = Instant::now();
         
         // Initialize repository components
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Verify .ash directory exists
         if !git_path.exists() {
             return Err(Error::Generic("Not an ash repository: .ash directory not found".into()));
         }
         
-        let db_path = git_path.join("objects");
+        let db_path = git_dir::objects(&git_path);
         let mut database = Database::new(db_path);
         
         // Check for the index file
@@ -44,7 +51,7 @@ impl CommitCommand {
         
         // Load the index
         match index.load() {
-            Ok(_) => println!("Index loaded successfully"),
+            Ok(_) => trace!("Index loaded successfully"),
             Err(e) => return Err(Error::Generic(format!("Error loading index: {}", e))),
         }
         
@@ -63,6 +70,7 @@ impl CommitCommand {
             &mut index,
             &refs
         );
+        commit_writer.verbose = verbose;
         
         // Check if there is a pending merge or other operation
         if commit_writer.pending_commit.in_progress(PendingCommitType::Merge) {
@@ -80,11 +88,14 @@ impl CommitCommand {
         
         // Get the message
         let mut msg = None;
+        let mut template = None;
+        let squash_message = commit_writer.pending_commit.squash_message()?;
         
         if !message.is_empty() {
             msg = Some(message.to_string());
+            commit_writer.message_source = vec!["message".to_string()];
             if !edit {
-                println!("Using provided message: {}", message);
+                trace!("Using provided message: {}", message);
             }
         } else if let Some(rev) = reuse_message {
             // Reuse message from another commit
@@ -92,20 +103,37 @@ impl CommitCommand {
             if msg.is_none() {
                 return Err(Error::Generic(format!("Could not get message for revision: {}", rev)));
             }
-            println!("Reusing message from commit: {}", rev);
+            commit_writer.message_source = vec!["commit".to_string(), rev.to_string()];
+            trace!("Reusing message from commit: {}", rev);
+        } else if let Some(text) = squash_message.clone() {
+            commit_writer.message_source = vec!["squash".to_string()];
+            msg = Some(text);
+        } else if let Some(text) = commit_writer.template()? {
+            commit_writer.message_source = vec!["template".to_string()];
+            template = Some(text.clone());
+            msg = Some(text);
         }
         
         // If we should edit the message, or if no message was provided
-        if edit || msg.is_none() {
+        if edit || msg.is_none() || template.is_some() || commit_writer.message_source == ["squash"] {
             // Use the editor to get the message
             let edited_message = commit_writer.compose_message(get_editor_command(), msg.as_deref())?;
             
             if let Some(message_text) = edited_message {
+                // A template committed as it was holds nothing of the user's
+                if template.as_deref().and_then(Editor::remove_notes).as_deref() == Some(message_text.as_str()) {
+                    return Err(Error::Generic("Aborting commit; you did not edit the message from the template".to_string()));
+                }
                 msg = Some(message_text);
             } else {
                 // If the editor returned None, abort the commit
                 return Err(Error::Generic("Aborting commit due to empty commit message".to_string()));
             }
+        } else if let Some(message_text) = msg.take() {
+            msg = commit_writer.prepare_message(&message_text)?;
+            if msg.is_none() {
+                return Err(Error::Generic("Aborting commit due to empty commit message".to_string()));
+            }
         }
         
         // Verify we have a message
@@ -117,7 +145,7 @@ impl CommitCommand {
             // Get the parent commit OID
             let parent = match refs.read_head() {
                 Ok(p) => {
-                    println!("HEAD read successfully: {:?}", p);
+                    trace!("HEAD read successfully: {:?}", p);
                     if let Some(oid) = p {
                         vec![oid]
                     } else {
@@ -132,11 +160,14 @@ impl CommitCommand {
             
             // Create and write the commit
             let commit = commit_writer.write_commit(parent, &message_text, None)?;
+            if squash_message.is_some() {
+                commit_writer.pending_commit.clear_squash()?;
+            }
             
             // Print commit information
             commit_writer.print_commit(&commit)?;
             
-            println!("Commit completed in {:?}", start_time.elapsed());
+            trace!("Commit completed in {:?}", start_time.elapsed());
             Ok(())
         } else {
             Err(Error::Generic("No commit message provided".to_string()))
@@ -145,8 +176,5 @@ impl CommitCommand {
 }
 
 pub fn get_editor_command() -> Option<String> {
-    env::var("GIT_EDITOR")
-        .or_else(|_| env::var("VISUAL"))
-        .or_else(|_| env::var("EDITOR"))
-        .ok()
+    EDITOR_VARIABLES.iter().find_map(|name| env::var(name).ok())
 }
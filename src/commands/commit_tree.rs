@@ -0,0 +1,63 @@
+// src/commands/commit_tree.rs - create a commit object from a tree
+use std::fs;
+use std::io::{self, Read};
+
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
+use crate::core::repository::autostash::current_author;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+pub struct CommitTreeCommand;
+
+impl CommitTreeCommand {
+    /// Store a commit of `tree` with `parents`, in that order, and print
+    /// its ID, leaving HEAD and the refs alone. The message is the
+    /// `messages` as paragraphs, else read from `file`, else from standard
+    /// input.
+    pub fn execute(tree: &str, parents: &[String], messages: &[String], file: Option<&str>) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let tree_oid = Revision::new(&mut repo, tree).resolve("tree")
+            .map_err(|_| Error::Generic(format!("not a valid tree object name {}", tree)))?;
+
+        let mut parent_oids: Vec<String> = Vec::new();
+        for parent in parents {
+            let oid = Revision::new(&mut repo, parent).resolve(COMMIT)
+                .map_err(|_| Error::Generic(format!("not a valid object name {}", parent)))?;
+            if parent_oids.contains(&oid) {
+                eprintln!("error: duplicate parent {} ignored", oid);
+            } else {
+                parent_oids.push(oid);
+            }
+        }
+
+        let mut message = if !messages.is_empty() {
+            messages.join("\n\n")
+        } else if let Some(file) = file.filter(|file| *file != "-") {
+            fs::read_to_string(file)
+                .map_err(|e| Error::Generic(format!("could not read log file '{}': {}", file, e)))?
+        } else {
+            Self::read_stdin()?
+        };
+        message.truncate(message.trim_end().len());
+
+        // Further parents are recorded as trailers, as for any merge
+        let extra: Vec<String> = parent_oids.iter().skip(1)
+            .map(|oid| format!("{} {}", MERGE_PARENT_TRAILER, oid))
+            .collect();
+        if !extra.is_empty() {
+            message = format!("{}\n\n{}", message, extra.join("\n"));
+        }
+
+        let mut commit = Commit::new(parent_oids.first().cloned(), tree_oid, current_author(), message);
+        repo.database.store(&mut commit)?;
+        println!("{}", commit.get_oid().cloned().unwrap_or_default());
+        Ok(())
+    }
+
+    fn read_stdin() -> Result<String, Error> {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    }
+}
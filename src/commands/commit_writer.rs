@@ -1,18 +1,27 @@
-use std::fs::read_to_string;
+use std::collections::HashMap;
+use std::fs::{self, read_to_string};
 use std::path::{Path, PathBuf};
 
 use chrono::Local;
+use regex::Regex;
 
+use crate::core::advice;
+use crate::core::config::Config;
 use crate::core::database::author::Author;
-use crate::core::database::commit::Commit;
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
 use crate::core::database::database::{Database, GitObject};
 use crate::core::database::entry::DatabaseEntry;
 use crate::core::database::tree::Tree;
+use crate::core::diff::diff;
+use crate::core::diff::myers::{diff_lines, format_diff, is_binary_content};
+use crate::core::hooks::{self, PREPARE_COMMIT_MSG};
 use crate::core::index::index::Index;
-use crate::core::refs::Refs;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::{Reference, Refs};
 use crate::core::editor::Editor;
 use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
 use crate::errors::error::Error;
+use crate::trace;
 
 pub const COMMIT_NOTES: &str = "Please enter the commit message for your changes. Lines starting with
 '#' will be ignored, and an empty message aborts the commit.";
@@ -27,9 +36,15 @@ If this is not correct, please remove the file
 \t.ash/CHERRY_PICK_HEAD
 and try again.";
 
-pub const CONFLICT_MESSAGE: &str = "hint: Fix them up in the work tree, and then use 'ash add <file>'
-hint: as appropriate to mark resolution and make a commit.
-fatal: Exiting because of an unresolved conflict.";
+const TEMPLATE_KEY: &str = "commit.template";
+const TASK_ID_KEY: &str = "task.id";
+const TASK_PATTERN_KEY: &str = "task.pattern";
+const TASK_SPRINT_KEY: &str = "task.sprint";
+// Task ids as most trackers write them, e.g. ASH-142
+const DEFAULT_TASK_PATTERN: &str = "[A-Z][A-Z0-9]+-[0-9]+";
+
+pub const CONFLICT_MESSAGE: &str = "Fix them up in the work tree, and then use 'ash add <file>'
+as appropriate to mark resolution and make a commit.";
 
 pub struct CommitWriter<'a> {
     root_path: &'a Path,
@@ -38,6 +53,12 @@ pub struct CommitWriter<'a> {
     pub index: &'a mut Index,
     pub refs: &'a Refs,
     pub pending_commit: PendingCommit,
+    pub verbose: bool,
+    // What HEAD's reflog says moved it, e.g. "cherry-pick"
+    pub reflog_action: String,
+    // Where the message comes from, passed on to the prepare-commit-msg
+    // hook: "message", "template", "merge" or "commit" and its id
+    pub message_source: Vec<String>,
 }
 
 impl<'a> CommitWriter<'a> {
@@ -57,6 +78,9 @@ impl<'a> CommitWriter<'a> {
             index,
             refs,
             pending_commit,
+            verbose: false,
+            reflog_action: "commit".to_string(),
+            message_source: Vec::new(),
         }
     }
 
@@ -86,29 +110,49 @@ impl<'a> CommitWriter<'a> {
         // Use current author as committer 
         let committer = self.current_author();
         
+        let action = match parents.len() {
+            0 if self.reflog_action == "commit" => "commit (initial)".to_string(),
+            n if n > 1 && self.reflog_action == "commit" => "commit (merge)".to_string(),
+            _ => self.reflog_action.clone(),
+        };
+        let reflog_message = format!("{}: {}", action, message.lines().next().unwrap_or(""));
+
         // Get the first parent or None
         let parent = parents.first().cloned();
         
+        // Further parents are recorded as trailers in the message
+        let trailers: Vec<String> = parents.iter().skip(1)
+            .map(|extra| format!("{} {}", MERGE_PARENT_TRAILER, extra))
+            .filter(|trailer| !message.contains(trailer.as_str()))
+            .collect();
+        let message = if trailers.is_empty() {
+            message.to_string()
+        } else {
+            format!("{}\n\n{}", message.trim_end(), trailers.join("\n"))
+        };
+        
         let mut commit = Commit::new_with_committer(
             parent,
             tree.get_oid().map(|s| s.to_string()).unwrap_or_default(),
             author,
             committer,
-            message.to_string()
+            message
         );
 
         self.database.store(&mut commit)?;
         
         // Get the commit OID, making sure we handle the option correctly
         let oid = commit.get_oid().map(|s| s.to_string()).unwrap_or_default();
-        self.refs.update_head(&oid)?;
+        self.refs.update_head(&oid, &reflog_message)?;
 
         Ok(commit)
     }
 
     pub fn write_tree(&mut self) -> Result<Tree, Error> {
         // Create a collection of DatabaseEntry from index entries
+        // Intent-to-add paths have no content to commit yet
         let entries: Vec<DatabaseEntry> = self.index.entries.values()
+            .filter(|entry| !entry.intent_to_add)
             .map(|entry| DatabaseEntry::new(
                 entry.get_path().to_string(),
                 entry.get_oid().to_string(),
@@ -178,23 +222,31 @@ impl<'a> CommitWriter<'a> {
         
         // Print commit info
         let title_line = commit.get_message().lines().next().unwrap_or("");
-        println!("[{}] {}", info_str, title_line);
+        trace!("[{}] {}", info_str, title_line);
         
         Ok(())
     }
 
     pub fn compose_message(&mut self, editor_cmd: Option<String>, initial_message: Option<&str>) -> Result<Option<String>, Error> {
+        let staged_diff = self.verbose_diff()?;
+        let (git_path, source) = (self.git_path.clone(), self.message_source.clone());
         self.edit_file(self.commit_message_path(), editor_cmd, |editor| {
             if let Some(msg) = initial_message {
                 editor.write(msg)?;
             }
             editor.write("")?;
             editor.note(COMMIT_NOTES)?;
-            Ok(())
+            if let Some(diff) = staged_diff {
+                editor.scissors()?;
+                editor.note(&diff)?;
+            }
+            Self::run_prepare_hook(&git_path, editor.path(), &source)
         })
     }
 
     pub fn compose_merge_message(&mut self, editor_cmd: Option<String>, initial_message: &str, notes: Option<&str>) -> Result<Option<String>, Error> {
+        let staged_diff = self.verbose_diff()?;
+        let (git_path, source) = (self.git_path.clone(), self.message_source.clone());
         self.edit_file(self.commit_message_path(), editor_cmd, |editor| {
             editor.write(initial_message)?;
             
@@ -204,10 +256,139 @@ impl<'a> CommitWriter<'a> {
             
             editor.write("")?;
             editor.note(COMMIT_NOTES)?;
-            Ok(())
+            if let Some(diff) = staged_diff {
+                editor.scissors()?;
+                editor.note(&diff)?;
+            }
+            Self::run_prepare_hook(&git_path, editor.path(), &source)
         })
     }
 
+    /// Give the prepare-commit-msg hook its say over a message that is
+    /// committed without opening the editor. Without a hook the message is
+    /// returned as it is; None means the hook left it empty.
+    pub fn prepare_message(&mut self, message: &str) -> Result<Option<String>, Error> {
+        if hooks::find(&self.git_path, PREPARE_COMMIT_MSG).is_none() {
+            return Ok(Some(message.to_string()));
+        }
+        let path = self.commit_message_path();
+        fs::write(&path, format!("{}\n", message.trim_end()))?;
+        Self::run_prepare_hook(&self.git_path, &path, &self.message_source)?;
+
+        let prepared = read_to_string(&path)?;
+        let prepared = prepared.trim();
+        Ok(if prepared.is_empty() { None } else { Some(format!("{}\n", prepared)) })
+    }
+
+    // Run the prepare-commit-msg hook on the message file at `path`; the
+    // commit is aborted if it fails
+    fn run_prepare_hook(git_path: &Path, path: &Path, source: &[String]) -> Result<(), Error> {
+        let path = path.to_string_lossy();
+        let mut args = vec![path.as_ref()];
+        args.extend(source.iter().map(String::as_str));
+        if !hooks::run(git_path, PREPARE_COMMIT_MSG, &args)? {
+            return Err(Error::Generic(format!("{} hook failed, aborting commit", PREPARE_COMMIT_MSG)));
+        }
+        Ok(())
+    }
+
+    /// The file `commit.template` names, with its `{{branch}}`, `{{task}}`
+    /// and `{{sprint}}` variables filled in, or None when no template is set.
+    /// The task id is `task.id` or else the first part of the branch name
+    /// that matches `task.pattern`; the sprint is `task.sprint`.
+    pub fn template(&self) -> Result<Option<String>, Error> {
+        let config = Config::load(&self.git_path)?;
+        let name = match config.get(TEMPLATE_KEY) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let path = match (name.strip_prefix("~/"), std::env::var("HOME")) {
+            (Some(rest), Ok(home)) => Path::new(&home).join(rest),
+            _ => self.root_path.join(&name),
+        };
+        let template = read_to_string(&path)
+            .map_err(|_| Error::Generic(format!("could not read commit message template '{}'", name)))?;
+
+        let branch = match self.refs.current_ref()? {
+            Reference::Symbolic(target) => self.refs.short_name(&target),
+            Reference::Direct(_) => "HEAD".to_string(),
+        };
+        let task = match config.get(TASK_ID_KEY) {
+            Some(task) => task,
+            None => {
+                let pattern = config.get(TASK_PATTERN_KEY).unwrap_or_else(|| DEFAULT_TASK_PATTERN.to_string());
+                let pattern = Regex::new(&pattern)
+                    .map_err(|e| Error::Generic(format!("invalid {}: {}", TASK_PATTERN_KEY, e)))?;
+                pattern.find(&branch).map(|found| found.as_str().to_string()).unwrap_or_default()
+            },
+        };
+        let sprint = config.get(TASK_SPRINT_KEY).unwrap_or_default();
+
+        Ok(Some(template
+            .replace("{{branch}}", &branch)
+            .replace("{{task}}", &task)
+            .replace("{{sprint}}", &sprint)))
+    }
+
+    // The staged diff shown below the scissors line with --verbose
+    fn verbose_diff(&mut self) -> Result<Option<String>, Error> {
+        if !self.verbose {
+            return Ok(None);
+        }
+        Ok(Some(self.staged_diff()?))
+    }
+
+    /// Diff between the HEAD tree and the index, as it will be committed
+    pub fn staged_diff(&mut self) -> Result<String, Error> {
+        let mut head_files: HashMap<String, String> = HashMap::new();
+        if let Some(head_oid) = self.refs.read_head()? {
+            let changes = self.database.tree_diff(None, Some(&head_oid), &PathFilter::new())?;
+            for (path, (_, new_entry)) in changes {
+                if let Some(entry) = new_entry {
+                    head_files.insert(path.to_string_lossy().to_string(), entry.get_oid().to_string());
+                }
+            }
+        }
+
+        let mut paths: Vec<String> = self.index.entries.keys().cloned().collect();
+        paths.extend(head_files.keys().filter(|p| !self.index.entries.contains_key(*p)).cloned());
+        paths.sort();
+
+        let mut output = String::new();
+        for path in paths {
+            let head_oid = head_files.get(&path).cloned();
+            let index_oid = self.index.entries.get(&path).map(|e| e.get_oid().to_string());
+            if head_oid == index_oid {
+                continue;
+            }
+
+            output.push_str(&format!("diff --ash a/{} b/{}\n", path, path));
+            output.push_str(&format!("--- {}\n", if head_oid.is_some() { format!("a/{}", path) } else { "/dev/null".to_string() }));
+            output.push_str(&format!("+++ {}\n", if index_oid.is_some() { format!("b/{}", path) } else { "/dev/null".to_string() }));
+
+            let old = match &head_oid {
+                Some(oid) => self.database.load(oid)?.to_bytes(),
+                None => Vec::new(),
+            };
+            let new = match &index_oid {
+                Some(oid) => self.database.load(oid)?.to_bytes(),
+                None => Vec::new(),
+            };
+
+            if is_binary_content(&old) || is_binary_content(&new) {
+                output.push_str(&format!("Binary files a/{} and b/{} differ\n", path, path));
+                continue;
+            }
+
+            let a_lines = diff::split_lines(&String::from_utf8_lossy(&old));
+            let b_lines = diff::split_lines(&String::from_utf8_lossy(&new));
+            let edits = diff_lines(&a_lines, &b_lines);
+            output.push_str(&format_diff(&a_lines, &b_lines, &edits, 3));
+        }
+
+        Ok(output)
+    }
+
     pub fn edit_file<F>(&mut self, path: PathBuf, editor_cmd: Option<String>, f: F) -> Result<Option<String>, Error>
     where
         F: FnOnce(&mut Editor) -> Result<(), Error>,
@@ -225,7 +406,8 @@ impl<'a> CommitWriter<'a> {
         }
 
         println!("error: Committing is not possible because you have unmerged files.");
-        println!("{}", CONFLICT_MESSAGE);
+        advice::advise(advice::RESOLVE_CONFLICT, CONFLICT_MESSAGE);
+        println!("fatal: Exiting because of an unresolved conflict.");
 
         Err(Error::Generic("Unresolved conflicts exist in the index".to_string()))
     }
@@ -241,6 +423,7 @@ impl<'a> CommitWriter<'a> {
             .ok_or_else(|| Error::Generic("Invalid commit object".to_string()))?;
             
         let tree = self.write_tree()?;
+        self.message_source = vec!["commit".to_string(), head_oid.clone()];
         let message = self.compose_message(editor_cmd, Some(old_commit.get_message()))?
             .ok_or_else(|| Error::Generic("Aborting commit due to empty commit message".to_string()))?;
             
@@ -269,7 +452,8 @@ impl<'a> CommitWriter<'a> {
         let new_oid = new_commit.get_oid()
             .ok_or_else(|| Error::Generic("New commit has no OID".to_string()))?;
             
-        self.refs.update_head(new_oid)?;
+        let reflog_message = format!("commit (amend): {}", new_commit.get_message().lines().next().unwrap_or(""));
+        self.refs.update_head(new_oid, &reflog_message)?;
         
         self.print_commit(&new_commit)?;
         
@@ -294,6 +478,10 @@ impl<'a> CommitWriter<'a> {
             PendingCommitType::CherryPick => Some(CHERRY_PICK_NOTES),
             PendingCommitType::Revert => None,
         };
+        self.message_source = vec![match r#type {
+            PendingCommitType::Merge => "merge",
+            _ => "message",
+        }.to_string()];
         
         match r#type {
             PendingCommitType::Merge => self.write_merge_commit(editor_cmd, notes)?,
@@ -305,11 +493,9 @@ impl<'a> CommitWriter<'a> {
     }
     
     fn write_merge_commit(&mut self, editor_cmd: Option<String>, notes: Option<&str>) -> Result<(), Error> {
-        let parents = vec![
-            self.refs.read_head()?.unwrap_or_default(),
-            self.pending_commit.merge_oid(PendingCommitType::Merge)?,
-        ];
-        
+        let mut parents = vec![self.refs.read_head()?.unwrap_or_default()];
+        parents.extend(self.pending_commit.merge_oids()?);
+
         let merge_message = self.pending_commit.merge_message()?;
         let message = self.compose_merge_message(editor_cmd, &merge_message, notes)?
             .ok_or_else(|| Error::Generic("Aborting merge commit due to empty message".to_string()))?;
@@ -340,6 +526,7 @@ impl<'a> CommitWriter<'a> {
         let message = self.compose_merge_message(editor_cmd, &merge_message, notes)?
             .ok_or_else(|| Error::Generic("Aborting cherry-pick commit due to empty message".to_string()))?;
             
+        self.reflog_action = "cherry-pick".to_string();
         let commit = self.write_commit(parents, &message, Some(author))?;
         self.print_commit(&commit)?;
         
@@ -357,6 +544,7 @@ impl<'a> CommitWriter<'a> {
         let message = self.compose_merge_message(editor_cmd, &merge_message, None)?
             .ok_or_else(|| Error::Generic("Aborting revert commit due to empty message".to_string()))?;
             
+        self.reflog_action = "revert".to_string();
         let commit = self.write_commit(parents, &message, None)?;
         self.print_commit(&commit)?;
         
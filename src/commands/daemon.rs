@@ -0,0 +1,21 @@
+// src/commands/daemon.rs - share repositories read-only over TCP
+use crate::errors::error::Error;
+use crate::core::protocol::daemon::{Daemon, DEFAULT_PORT};
+
+const DEFAULT_LISTEN: &str = "0.0.0.0";
+
+pub struct DaemonCommand;
+
+impl DaemonCommand {
+    /// Serve repositories for anonymous fetching and cloning through
+    /// `ash://host[:port]/path` URLs until killed. Only repositories at or
+    /// below one of `directories` are served, or with none given, any under
+    /// `base_path`, against which requested paths are then resolved. Each
+    /// one must also contain `ash-daemon-export-ok` unless `export_all`.
+    /// Pushing is always refused.
+    pub fn execute(directories: &[String], base_path: Option<&str>, export_all: bool, listen: Option<&str>, port: Option<u16>) -> Result<(), Error> {
+        let daemon = Daemon::new(base_path, directories, export_all)?;
+        let address = format!("{}:{}", listen.unwrap_or(DEFAULT_LISTEN), port.unwrap_or(DEFAULT_PORT));
+        daemon.listen(&address)
+    }
+}
@@ -1,36 +1,50 @@
 // src/commands/diff.rs - updated to use pager
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 use crate::core::color::Color;
 use crate::core::database::database::Database;
 use crate::core::database::tree::{Tree, TreeEntry, TREE_MODE};
 use crate::core::index::index::Index;
+use crate::core::index::entry::Entry;
 use crate::core::database::commit::Commit;
 use crate::core::refs::Refs;
+use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
 use crate::core::workspace::Workspace;
+use crate::core::diff::combined;
 use crate::core::diff::diff;
-use crate::core::diff::myers::{diff_lines, format_diff, is_binary_content};
+use crate::core::diff::myers::{diff_lines, format_diff};
+use crate::core::diff::rename::{self, Pair, PairKind, RenameDetection};
+use crate::core::diff::submodule::{self, SubmoduleFormat, GITLINK_MODE};
+use crate::core::path_filter::PathFilter;
 use crate::errors::error::Error;
 use crate::core::pager::Pager;
+use crate::core::repository::git_dir;
 
 pub struct DiffCommand;
 
 impl DiffCommand {
-    /// Execute diff command between index/HEAD and working tree
-    pub fn execute(paths: &[String], cached: bool) -> Result<(), Error> {
+    /// Execute diff command between index/HEAD and working tree, showing
+    /// submodules as `submodule` (or `diff.submodule`) says and looking for
+    /// renames and copies as the `renames` options (or `diff.renames`) do.
+    /// Returns whether any differences were found.
+    pub fn execute(paths: &[String], cached: bool, submodule: Option<&str>, renames: &[String]) -> Result<bool, Error> {
         let start_time = Instant::now();
         
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Verifică dacă directorul .ash există
         if !git_path.exists() {
             return Err(Error::Generic("fatal: not an ash repository (or any of the parent directories): .ash directory not found".into()));
         }
         
+        let submodule = SubmoduleFormat::resolve(submodule, &git_path)?;
+        let detection = RenameDetection::resolve(renames, &git_path)?;
         let workspace = Workspace::new(root_path);
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = Index::new(git_path.join("index"));
         
         // Load the index first
@@ -38,6 +52,17 @@ impl DiffCommand {
         
         let refs = Refs::new(&git_path);
         
+        // With --cached, the first argument may name the commit to compare the index against
+        let mut paths = paths.to_vec();
+        let base = if cached {
+            Self::resolve_base(&refs, &mut paths)?
+        } else {
+            None
+        };
+        
+        // During a merge, conflicted paths are diffed against both parents
+        let merge_parents = Self::merge_parents(&git_path, &refs)?;
+        
         // Initialize the pager
         let mut pager = Pager::new();
         
@@ -45,23 +70,29 @@ impl DiffCommand {
         pager.start()?;
         
         // Execute diff commands
-        let result = if paths.is_empty() {
+        let result = if paths.is_empty() && cached {
+            // Dacă flag-ul cached este setat, compară indexul cu HEAD
+            Self::diff_index_vs_head(&workspace, &mut database, &index, base.as_deref(), submodule, detection, &mut pager)
+        } else if paths.is_empty() {
             // Treat the entire repository
-            Self::diff_all(&workspace, &mut database, &index, &refs, cached, &mut pager)
+            Self::diff_all(&workspace, &mut database, &index, &merge_parents, submodule, &mut pager)
         } else {
             // Process specific paths
-            let mut overall_result = Ok(());
+            let mut overall_result = Ok(false);
             
-            for path_str in paths {
+            for path_str in &paths {
                 // Stop processing if user exited pager
-                if !pager.is_enabled() {
+                if pager.exited_early() {
                     break;
                 }
                 
                 let path = PathBuf::from(path_str);
-                if let Err(e) = Self::diff_path(&workspace, &mut database, &index, &refs, &path, cached, &mut pager) {
-                    overall_result = Err(e);
-                    break;
+                match Self::diff_path(&workspace, &mut database, &index, base.as_deref(), &merge_parents, &path, cached, &mut pager) {
+                    Ok(changed) => overall_result = overall_result.map(|found| found || changed),
+                    Err(e) => {
+                        overall_result = Err(e);
+                        break;
+                    }
                 }
             }
             
@@ -69,7 +100,7 @@ impl DiffCommand {
         };
         
         // Only show completion message if pager is still active (user hasn't exited)
-        if pager.is_enabled() {
+        if pager.is_enabled() && !crate::core::trace::is_quiet() {
             let elapsed = start_time.elapsed();
             let _ = pager.write(&format!("\n{}\n", Color::cyan(&format!("Diff completed in {:.2}s", elapsed.as_secs_f32()))));
         }
@@ -81,47 +112,166 @@ impl DiffCommand {
         match (result, close_result) {
             (Err(e), _) => Err(e),
             (_, Err(e)) => Err(e),
-            _ => Ok(()),
+            (Ok(found), _) => Ok(found),
         }
     }
 
-    /// Diff all changed files in the repository
-    fn diff_all(
+    /// Determine the commit the index is compared against with --cached.
+    /// If the first argument resolves to a commit and is not a file in the
+    /// workspace it is taken as the base and removed from `paths`; otherwise
+    /// HEAD is used.
+    fn resolve_base(refs: &Refs, paths: &mut Vec<String>) -> Result<Option<String>, Error> {
+        if let Some(first_arg) = paths.first().cloned() {
+            if !Path::new(&first_arg).exists() {
+                let mut repo = Repository::new(".")?;
+                let mut revision = Revision::new(&mut repo, &first_arg);
+                if let Ok(oid) = revision.resolve("commit") {
+                    paths.remove(0);
+                    return Ok(Some(oid));
+                }
+                // Nu este o revizie validă, o tratăm ca pe o cale de fișier
+            }
+        }
+        
+        refs.read_head()
+    }
+
+    /// The commits being merged when a merge, cherry-pick or revert stopped on conflicts
+    fn merge_parents(git_path: &Path, refs: &Refs) -> Result<Vec<String>, Error> {
+        let pending_commit = PendingCommit::new(git_path);
+        let merge_type = match pending_commit.merge_type() {
+            Some(merge_type) => merge_type,
+            None => return Ok(Vec::new()),
+        };
+        
+        let mut parents = Vec::new();
+        if let Some(head_oid) = refs.read_head()? {
+            parents.push(head_oid);
+        }
+        match merge_type {
+            PendingCommitType::Merge => parents.extend(pending_commit.merge_oids()?),
+            _ => parents.push(pending_commit.merge_oid(merge_type)?),
+        }
+        
+        Ok(parents)
+    }
+
+    /// Show a combined diff of the working file against each merge parent
+    fn diff_conflicted(
         workspace: &Workspace,
         database: &mut Database,
-        index: &Index,
-        refs: &Refs,
-        cached: bool,
+        merge_parents: &[String],
+        path: &str,
         pager: &mut Pager
     ) -> Result<(), Error> {
-        // Dacă flag-ul cached este setat, compară indexul cu HEAD
-        if cached {
-            return Self::diff_index_vs_head(workspace, database, index, refs, pager);
+        if merge_parents.len() < 2 {
+            pager.write(&format!("* Unmerged path {}\n", diff::header_path("", path)))?;
+            return Ok(());
+        }
+        
+        let mut parent_contents = Vec::new();
+        let mut parent_oids = Vec::new();
+        for commit_oid in merge_parents {
+            let commit_obj = database.load(commit_oid)?;
+            let commit = match commit_obj.as_any().downcast_ref::<Commit>() {
+                Some(c) => c,
+                None => return Err(Error::Generic(format!("Object {} is not a commit", commit_oid))),
+            };
+            
+            let mut files: HashMap<String, String> = HashMap::new();
+            DiffCommand::collect_files_from_commit(database, commit, &mut files)?;
+            
+            match files.get(path) {
+                Some(oid) => {
+                    parent_contents.push(database.load(oid)?.to_bytes());
+                    parent_oids.push(oid[..7.min(oid.len())].to_string());
+                },
+                None => {
+                    parent_contents.push(Vec::new());
+                    parent_oids.push("0000000".to_string());
+                }
+            }
         }
         
-        // În caz contrar, compară arborele de lucru cu indexul
+        let working_content = if workspace.path_exists(Path::new(path))? {
+            workspace.read_file(Path::new(path))?
+        } else {
+            Vec::new()
+        };
+        
+        pager.write(&format!("{}\n", Color::bold(&format!("diff --cc {}", diff::header_path("", path)))))?;
+        pager.write(&format!("index {}..0000000\n", parent_oids.join(",")))?;
+        
+        let mut contents: Vec<&[u8]> = parent_contents.iter().map(Vec::as_slice).collect();
+        contents.push(&working_content);
+        if workspace.attributes().is_binary(Path::new(path), &contents) {
+            pager.write("Binary files differ\n")?;
+            return Ok(());
+        }
+        
+        pager.write(&format!("--- {}\n", diff::header_path("a/", path)))?;
+        pager.write(&format!("+++ {}\n", diff::header_path("b/", path)))?;
+        
+        let parent_lines: Vec<Vec<String>> = parent_contents.iter()
+            .map(|content| diff::split_lines(&String::from_utf8_lossy(content)))
+            .collect();
+        let working_lines = diff::split_lines(&String::from_utf8_lossy(&working_content));
+        
+        let raw_diff = combined::combined_diff(&parent_lines, &working_lines, 3);
+        pager.write(&combined::colorize(&raw_diff, parent_lines.len()))?;
+        
+        Ok(())
+    }
+
+    /// Diff all changed files in the repository
+    fn diff_all(
+        workspace: &Workspace,
+        database: &mut Database,
+        index: &Index,
+        merge_parents: &[String],
+        submodule: SubmoduleFormat,
+        pager: &mut Pager
+    ) -> Result<bool, Error> {
+        // Compară arborele de lucru cu indexul
         let mut has_changes = false;
         
         // Obține toate fișierele din index
         for entry in index.each_entry() {
             let path = Path::new(entry.get_path());
             
+            // Fișierele în conflict sunt afișate ca diff combinat
+            if entry.stage > 0 {
+                has_changes = true;
+                Self::diff_conflicted(workspace, database, merge_parents, entry.get_path(), pager)?;
+                continue;
+            }
+            
+            // A submodule has changed when another commit is checked out in
+            // it; one that is not checked out has not
+            if entry.mode_octal() == GITLINK_MODE {
+                if let Some(oid) = submodule::checked_out(Path::new("."), entry.get_path()).filter(|oid| oid != entry.get_oid()) {
+                    has_changes = true;
+                    pager.write(&submodule::describe(Path::new("."), entry.get_path(), Some(entry.get_oid()), Some(&oid), submodule))?;
+                }
+                continue;
+            }
+
             // Sări dacă fișierul nu există în workspace
             if !workspace.path_exists(path)? {
                 has_changes = true;
                 let path_str = path.display().to_string();
-                pager.write(&format!("diff --ash a/{} b/{}\n", Color::cyan(&path_str), Color::cyan(&path_str)))?;
+                pager.write(&format!("diff --ash {} {}\n", Color::cyan(&diff::header_path("a/", &path_str)), Color::cyan(&diff::header_path("b/", &path_str))))?;
                 pager.write(&format!("{} {}\n", Color::red("deleted file mode"), Color::red(&entry.mode_octal())))?;
-                pager.write(&format!("--- a/{}\n", Color::red(&path_str)))?;
+                pager.write(&format!("--- {}\n", Color::red(&diff::header_path("a/", &path_str))))?;
                 pager.write(&format!("+++ {}\n", Color::red("/dev/null")))?;
                 
                 // Obține conținutul blob-ului din baza de date
                 let blob_obj = database.load(entry.get_oid())?;
-                let content = blob_obj.to_bytes();
+                let content = workspace.attributes().textconv(Path::new(path), blob_obj.to_bytes())?;
                 
                 // Verifică dacă conținutul este binar
-                if is_binary_content(&content) {
-                    pager.write(&format!("Binary file a/{} has been deleted\n", path_str))?;
+                if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                    pager.write(&format!("Binary file {} has been deleted\n", diff::header_path("a/", &path_str)))?;
                     continue;
                 }
                 
@@ -141,6 +291,13 @@ impl DiffCommand {
             // Calculează hash-ul pentru conținutul fișierului
             let file_hash = database.hash_file_data(&file_content);
             
+            // An intent-to-add path is new, all of it, even when empty
+            if entry.intent_to_add {
+                has_changes = true;
+                Self::diff_intent_to_add(workspace, entry, &file_hash, file_content, pager)?;
+                continue;
+            }
+
             // Dacă hash-ul se potrivește, nu există nicio modificare
             if file_hash == entry.get_oid() {
                 continue;
@@ -150,11 +307,11 @@ impl DiffCommand {
             
             // Tipărește antetul diff-ului
             let path_str = path.display().to_string();
-            pager.write(&format!("diff --ash a/{} b/{}\n", Color::cyan(&path_str), Color::cyan(&path_str)))?;
+            pager.write(&format!("diff --ash {} {}\n", Color::cyan(&diff::header_path("a/", &path_str)), Color::cyan(&diff::header_path("b/", &path_str))))?;
             
             // Verifică dacă fișierul este binar
-            if is_binary_content(&file_content) {
-                pager.write(&format!("Binary files a/{} and b/{} differ\n", path_str, path_str))?;
+            if workspace.attributes().is_binary(Path::new(path), &[&file_content]) {
+                pager.write(&format!("Binary files {} and {} differ\n", diff::header_path("a/", &path_str), diff::header_path("b/", &path_str)))?;
                 continue;
             }
             
@@ -170,6 +327,31 @@ impl DiffCommand {
             pager.write(&format!("{}\n", Color::green("No changes")))?;
         }
         
+        Ok(has_changes)
+    }
+
+    // The diff of an intent-to-add file, from nothing to its content
+    fn diff_intent_to_add(workspace: &Workspace, entry: &Entry, file_hash: &str, content: Vec<u8>, pager: &mut Pager) -> Result<(), Error> {
+        let path = entry.get_path();
+        pager.write(&format!("diff --ash {} {}\n", Color::cyan(&diff::header_path("a/", path)), Color::cyan(&diff::header_path("b/", path))))?;
+        pager.write(&format!("{} {}\n", Color::green("new file mode"), Color::green(&entry.mode_octal())))?;
+        pager.write(&format!("index 0000000..{}\n", &file_hash[..7.min(file_hash.len())]))?;
+        if content.is_empty() {
+            return Ok(());
+        }
+        let content = workspace.attributes().textconv(Path::new(path), content)?;
+        if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+            pager.write(&format!("Binary file {} created\n", diff::header_path("b/", path)))?;
+            return Ok(());
+        }
+
+        pager.write(&format!("--- {}\n", Color::red("/dev/null")))?;
+        pager.write(&format!("+++ {}\n", Color::green(&diff::header_path("b/", path))))?;
+        let lines = diff::split_lines(&String::from_utf8_lossy(&content));
+        pager.write(&format!("{}\n", Color::cyan(&format!("@@ -0,0 +1,{} @@", lines.len()))))?;
+        for line in &lines {
+            pager.write(&format!("{}\n", Color::green(&format!("+{}", line))))?;
+        }
         Ok(())
     }
 
@@ -347,28 +529,44 @@ impl DiffCommand {
         workspace: &Workspace,
         database: &mut Database,
         index: &Index,
-        refs: &Refs,
+        base: Option<&str>,
+        merge_parents: &[String],
         path: &Path,
         cached: bool,
         pager: &mut Pager
-    ) -> Result<(), Error> {
+    ) -> Result<bool, Error> {
         let path_str = path.to_string_lossy().to_string();
         
         // Dacă calea este în index
         if let Some(entry) = index.get_entry(&path_str) {
+            if !cached && entry.stage > 0 {
+                Self::diff_conflicted(workspace, database, merge_parents, &path_str, pager)?;
+                return Ok(true);
+            }
+
+            if entry.intent_to_add {
+                if cached || !workspace.path_exists(path)? {
+                    return Ok(false);
+                }
+                let file_content = workspace.read_file(path)?;
+                let file_hash = database.hash_file_data(&file_content);
+                Self::diff_intent_to_add(workspace, entry, &file_hash, file_content, pager)?;
+                return Ok(true);
+            }
+            
             if cached {
                 // Compară indexul cu HEAD
-                let head_oid = match refs.read_head()? {
+                let head_oid = match base {
                     Some(oid) => oid,
                     None => {
                         // Fără HEAD, arată ca fișier nou
                         let index_obj = database.load(entry.get_oid())?;
-                        let content = index_obj.to_bytes();
+                        let content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                         
                         // Verifică dacă fișierul este binar
-                        if is_binary_content(&content) {
-                            pager.write(&format!("Binary file b/{} created\n", path_str))?;
-                            return Ok(());
+                        if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                            pager.write(&format!("Binary file {} created\n", diff::header_path("b/", &path_str)))?;
+                            return Ok(true);
                         }
                         
                         // Generează un hash fictiv pentru formatul git
@@ -377,7 +575,7 @@ impl DiffCommand {
                         
                         pager.write(&format!("index 0000000..{} 100644\n", index_hash_short))?;
                         pager.write(&format!("--- /dev/null\n"))?;
-                        pager.write(&format!("+++ b/{}\n", path_str))?;
+                        pager.write(&format!("+++ {}\n", diff::header_path("b/", &path_str)))?;
                         pager.write(&format!("@@ -0,0 +1,{} @@\n", content.len()))?;
                         
                         let lines = diff::split_lines(&String::from_utf8_lossy(&content));
@@ -386,7 +584,7 @@ impl DiffCommand {
                             pager.write(&format!("{}\n", Color::green(&format!("+{}", line))))?;
                         }
                         
-                        return Ok(());
+                        return Ok(true);
                     }
                 };
                 
@@ -404,7 +602,7 @@ impl DiffCommand {
                     // Fișierul există atât în HEAD, cât și în index
                     if head_oid == entry.get_oid() {
                         pager.write(&format!("{}\n", Color::green(&format!("No changes staged for {}", path_str))))?;
-                        return Ok(());
+                        return Ok(false);
                     }
                     
                     // Compară versiunile din HEAD și index
@@ -412,13 +610,13 @@ impl DiffCommand {
                     let head_obj = database.load(head_oid)?;
                     let index_obj = database.load(entry.get_oid())?;
                     
-                    let head_content = head_obj.to_bytes();
-                    let index_content = index_obj.to_bytes();
+                    let head_content = workspace.attributes().textconv(Path::new(path), head_obj.to_bytes())?;
+                    let index_content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                     
                     // Verifică dacă vreunul dintre fișiere este binar
-                    if is_binary_content(&head_content) || is_binary_content(&index_content) {
-                        pager.write(&format!("Binary files a/{} and b/{} differ\n", path_str, path_str))?;
-                        return Ok(());
+                    if workspace.attributes().is_binary(Path::new(path), &[&head_content, &index_content]) {
+                        pager.write(&format!("Binary files {} and {} differ\n", diff::header_path("a/", &path_str), diff::header_path("b/", &path_str)))?;
+                        return Ok(true);
                     }
                     
                     // Generează hash-uri scurte pentru formatul git
@@ -426,8 +624,8 @@ impl DiffCommand {
                     let index_hash_short = if entry.get_oid().len() >= 7 { &entry.get_oid()[0..7] } else { entry.get_oid() };
                     
                     pager.write(&format!("index {}..{} {}\n", head_hash_short, index_hash_short, entry.mode_octal()))?;
-                    pager.write(&format!("--- a/{}\n", path_str))?;
-                    pager.write(&format!("+++ b/{}\n", path_str))?;
+                    pager.write(&format!("--- {}\n", diff::header_path("a/", &path_str)))?;
+                    pager.write(&format!("+++ {}\n", diff::header_path("b/", &path_str)))?;
                     
                     let head_lines = diff::split_lines(&String::from_utf8_lossy(&head_content));
                     let index_lines = diff::split_lines(&String::from_utf8_lossy(&index_content));
@@ -441,12 +639,12 @@ impl DiffCommand {
                 } else {
                     // Fișierul este în index, dar nu în HEAD (fișier nou)
                     let index_obj = database.load(entry.get_oid())?;
-                    let content = index_obj.to_bytes();
+                    let content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                     
                     // Verifică dacă fișierul este binar
-                    if is_binary_content(&content) {
-                        pager.write(&format!("Binary file b/{} created\n", path_str))?;
-                        return Ok(());
+                    if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                        pager.write(&format!("Binary file {} created\n", diff::header_path("b/", &path_str)))?;
+                        return Ok(true);
                     }
                     
                     // Generează un hash fictiv pentru formatul git
@@ -455,7 +653,7 @@ impl DiffCommand {
                     
                     pager.write(&format!("index 0000000..{} {}\n", index_hash_short, entry.mode_octal()))?;
                     pager.write(&format!("--- /dev/null\n"))?;
-                    pager.write(&format!("+++ b/{}\n", path_str))?;
+                    pager.write(&format!("+++ {}\n", diff::header_path("b/", &path_str)))?;
                     pager.write(&format!("@@ -0,0 +1,{} @@\n", content.len()))?;
                     
                     let lines = diff::split_lines(&String::from_utf8_lossy(&content));
@@ -468,12 +666,12 @@ impl DiffCommand {
                 // Compară indexul cu arborele de lucru
                 if !workspace.path_exists(path)? {
                     let index_obj = database.load(entry.get_oid())?;
-                    let content = index_obj.to_bytes();
+                    let content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                     
                     // Verifică dacă fișierul este binar
-                    if is_binary_content(&content) {
-                        pager.write(&format!("Binary file a/{} has been deleted\n", path_str))?;
-                        return Ok(());
+                    if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                        pager.write(&format!("Binary file {} has been deleted\n", diff::header_path("a/", &path_str)))?;
+                        return Ok(true);
                     }
                     
                     // Generează un hash fictiv pentru formatul git
@@ -481,7 +679,7 @@ impl DiffCommand {
                     let index_hash_short = if index_hash.len() >= 7 { &index_hash[0..7] } else { index_hash };
                     
                     pager.write(&format!("index {}..0000000 {}\n", index_hash_short, entry.mode_octal()))?;
-                    pager.write(&format!("--- a/{}\n", path_str))?;
+                    pager.write(&format!("--- {}\n", diff::header_path("a/", &path_str)))?;
                     pager.write(&format!("+++ /dev/null\n"))?;
                     pager.write(&format!("@@ -1,{} +0,0 @@\n", content.len()))?;
                     
@@ -491,7 +689,7 @@ impl DiffCommand {
                         pager.write(&format!("{}\n", Color::red(&format!("-{}", line))))?;
                     }
                     
-                    return Ok(());
+                    return Ok(true);
                 }
                 
                 // Citește copia de lucru
@@ -503,17 +701,17 @@ impl DiffCommand {
                 // Dacă hash-ul se potrivește, nu există nicio modificare
                 if file_hash == entry.get_oid() {
                     pager.write(&format!("{}\n", Color::green(&format!("No changes in {}", path_str))))?;
-                    return Ok(());
+                    return Ok(false);
                 }
                 
                 // Verifică dacă fișierul este binar
-                if is_binary_content(&file_content) {
+                if workspace.attributes().is_binary(Path::new(path), &[&file_content]) {
                     pager.write(&format!("index {}..{} {}\n", 
                             &entry.get_oid()[0..std::cmp::min(7, entry.get_oid().len())], 
                             &file_hash[0..std::cmp::min(7, file_hash.len())], 
                             entry.mode_octal()))?;
-                    pager.write(&format!("Binary files a/{} and b/{} differ\n", path_str, path_str))?;
-                    return Ok(());
+                    pager.write(&format!("Binary files {} and {} differ\n", diff::header_path("a/", &path_str), diff::header_path("b/", &path_str)))?;
+                    return Ok(true);
                 }
                 
                 // Arată diff-ul între index și copia de lucru
@@ -522,8 +720,8 @@ impl DiffCommand {
                 let file_hash_short = if file_hash.len() >= 7 { &file_hash[0..7] } else { &file_hash };
                 
                 pager.write(&format!("index {}..{} {}\n", index_hash_short, file_hash_short, entry.mode_octal()))?;
-                pager.write(&format!("--- a/{}\n", path_str))?;
-                pager.write(&format!("+++ b/{}\n", path_str))?;
+                pager.write(&format!("--- {}\n", diff::header_path("a/", &path_str)))?;
+                pager.write(&format!("+++ {}\n", diff::header_path("b/", &path_str)))?;
                 
                 // Folosește diff_with_database din modulul diff pentru a obține conținutul diff-ului
                 let raw_diff_output = diff::diff_with_database(workspace, database, path, entry.get_oid(), 3)?;
@@ -547,24 +745,27 @@ impl DiffCommand {
             } else {
                 pager.write(&format!("{}\n", Color::red(&format!("error: path '{}' does not exist", path_str))))?;
             }
+            return Ok(false);
         }
         
-        Ok(())
+        Ok(true)
     }
 
     fn diff_index_vs_head(
         workspace: &Workspace,
         database: &mut Database,
         index: &Index,
-        refs: &Refs,
+        base: Option<&str>,
+        submodule: SubmoduleFormat,
+        detection: RenameDetection,
         pager: &mut Pager
-    ) -> Result<(), Error> {
-        // Obține commit-ul HEAD
-        let head_oid = match refs.read_head()? {
+    ) -> Result<bool, Error> {
+        // Obține commit-ul de bază (HEAD implicit)
+        let head_oid = match base {
             Some(oid) => oid,
             None => {
                 pager.write(&format!("{}\n", Color::yellow("No HEAD commit found. Index contains initial version.")))?;
-                return Ok(());
+                return Ok(index.each_entry().next().is_some());
             }
         };
         
@@ -578,13 +779,61 @@ impl DiffCommand {
         // Obține fișierele din HEAD
         let mut head_files: HashMap<String, String> = HashMap::new();
         DiffCommand::collect_files_from_commit(database, commit, &mut head_files)?;
+        // Submodules point at commits of other repositories, not at blobs
+        let head_gitlinks: HashSet<String> = database.tree_diff(None, Some(head_oid), &PathFilter::new())?
+            .into_iter()
+            .filter(|(_, (_, entry))| entry.as_ref().is_some_and(|entry| entry.get_mode() == GITLINK_MODE))
+            .map(|(path, _)| path.to_string_lossy().to_string())
+            .collect();
+        
+        // Files removed from the index and added to it may be one renamed
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        let mut kept = Vec::new();
+        for entry in index.each_entry().filter(|entry| !entry.intent_to_add) {
+            let path = entry.get_path();
+            if entry.mode_octal() == GITLINK_MODE || head_gitlinks.contains(path) {
+                continue;
+            }
+            match head_files.get(path) {
+                None => added.push((path.to_string(), entry.get_oid().to_string())),
+                Some(head_oid) if detection.copies_harder || head_oid != entry.get_oid() => {
+                    kept.push((path.to_string(), head_oid.clone()));
+                },
+                Some(_) => {},
+            }
+        }
+        for (path, head_oid) in &head_files {
+            if !index.tracked(path) && !head_gitlinks.contains(path) {
+                removed.push((path.clone(), head_oid.clone()));
+            }
+        }
+        removed.sort();
+        let pairs = rename::detect(database, &removed, &added, &kept, detection)?;
         
         let mut has_changes = false;
         
-        // Compară fișierele din index cu HEAD
-        for entry in index.each_entry() {
+        // Compară fișierele din index cu HEAD; intent-to-add paths have
+        // nothing staged
+        for entry in index.each_entry().filter(|entry| !entry.intent_to_add) {
             let path = entry.get_path();
             
+            if entry.mode_octal() == GITLINK_MODE || head_gitlinks.contains(path) {
+                let old = head_files.get(path).map(String::as_str);
+                if old != Some(entry.get_oid()) {
+                    has_changes = true;
+                    pager.write(&submodule::describe(Path::new("."), path, old, Some(entry.get_oid()), submodule))?;
+                }
+                continue;
+            }
+            
+            if let Some(pair) = pairs.iter().find(|pair| pair.to == path) {
+                has_changes = true;
+                let from_oid = &head_files[&pair.from];
+                Self::diff_pair(workspace, database, pair, from_oid, entry.get_oid(), &entry.mode_octal(), pager)?;
+                continue;
+            }
+            
             if let Some(head_oid) = head_files.get(path) {
                 // Fișierul există atât în index, cât și în HEAD
                 if head_oid == entry.get_oid() {
@@ -600,19 +849,19 @@ impl DiffCommand {
                 let index_hash_short = if entry.get_oid().len() >= 7 { &entry.get_oid()[0..7] } else { entry.get_oid() };
                 
                 pager.write(&format!("index {}..{} {}\n", head_hash_short, index_hash_short, entry.mode_octal()))?;
-                pager.write(&format!("--- a/{}\n", path))?;
-                pager.write(&format!("+++ b/{}\n", path))?;
+                pager.write(&format!("--- {}\n", diff::header_path("a/", path)))?;
+                pager.write(&format!("+++ {}\n", diff::header_path("b/", path)))?;
                 
                 // Încarcă ambele versiuni
                 let head_obj = database.load(head_oid)?;
                 let index_obj = database.load(entry.get_oid())?;
                 
-                let head_content = head_obj.to_bytes();
-                let index_content = index_obj.to_bytes();
+                let head_content = workspace.attributes().textconv(Path::new(path), head_obj.to_bytes())?;
+                let index_content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                 
                 // Verifică dacă fișierul este binar
-                if is_binary_content(&head_content) || is_binary_content(&index_content) {
-                    pager.write(&format!("Binary files a/{} and b/{} differ\n", path, path))?;
+                if workspace.attributes().is_binary(Path::new(path), &[&head_content, &index_content]) {
+                    pager.write(&format!("Binary files {} and {} differ\n", diff::header_path("a/", path), diff::header_path("b/", path)))?;
                     continue;
                 }
                 
@@ -635,15 +884,15 @@ impl DiffCommand {
                 
                 pager.write(&format!("index 0000000..{} {}\n", index_hash_short, entry.mode_octal()))?;
                 pager.write(&format!("--- /dev/null\n"))?;
-                pager.write(&format!("+++ b/{}\n", path))?;
+                pager.write(&format!("+++ {}\n", diff::header_path("b/", path)))?;
                 
                 // Încarcă versiunea din index
                 let index_obj = database.load(entry.get_oid())?;
-                let content = index_obj.to_bytes();
+                let content = workspace.attributes().textconv(Path::new(path), index_obj.to_bytes())?;
                 
                 // Verifică dacă fișierul este binar
-                if is_binary_content(&content) {
-                    pager.write(&format!("Binary file b/{} created\n", path))?;
+                if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                    pager.write(&format!("Binary file {} created\n", diff::header_path("b/", path)))?;
                     continue;
                 }
                 
@@ -665,20 +914,30 @@ impl DiffCommand {
                 // Fișierul a fost în HEAD, dar a fost eliminat din index
                 has_changes = true;
                 
+                // A renamed file is shown where it went
+                if pairs.iter().any(|pair| pair.kind == PairKind::Rename && pair.from == *path) {
+                    continue;
+                }
+                
+                if head_gitlinks.contains(path) {
+                    pager.write(&submodule::describe(Path::new("."), path, Some(head_oid), None, submodule))?;
+                    continue;
+                }
+                
                 // Generează hash-ul pentru antetul git
                 let head_hash_short = if head_oid.len() >= 7 { &head_oid[0..7] } else { head_oid };
                 
                 pager.write(&format!("index {}..0000000\n", head_hash_short))?;
-                pager.write(&format!("--- a/{}\n", path))?;
+                pager.write(&format!("--- {}\n", diff::header_path("a/", path)))?;
                 pager.write(&format!("+++ /dev/null\n"))?;
                 
                 // Încarcă versiunea din HEAD
                 let head_obj = database.load(head_oid)?;
-                let content = head_obj.to_bytes();
+                let content = workspace.attributes().textconv(Path::new(path), head_obj.to_bytes())?;
                 
                 // Verifică dacă fișierul este binar
-                if is_binary_content(&content) {
-                    pager.write(&format!("Binary file a/{} deleted\n", path))?;
+                if workspace.attributes().is_binary(Path::new(path), &[&content]) {
+                    pager.write(&format!("Binary file {} deleted\n", diff::header_path("a/", path)))?;
                     continue;
                 }
                 
@@ -698,6 +957,47 @@ impl DiffCommand {
             pager.write(&format!("{}\n", Color::green("No changes staged for commit")))?;
         }
         
+        Ok(has_changes)
+    }
+
+    /// Show a renamed or copied file: where it came from, how alike the two
+    /// are and, unless they are the same, the changes made on the way
+    fn diff_pair(
+        workspace: &Workspace,
+        database: &mut Database,
+        pair: &Pair,
+        from_oid: &str,
+        to_oid: &str,
+        mode: &str,
+        pager: &mut Pager
+    ) -> Result<(), Error> {
+        let verb = match pair.kind {
+            PairKind::Rename => "rename",
+            PairKind::Copy => "copy",
+        };
+        pager.write(&format!("diff --ash {} {}\n", Color::cyan(&diff::header_path("a/", &pair.from)), Color::cyan(&diff::header_path("b/", &pair.to))))?;
+        pager.write(&format!("similarity index {}%\n", pair.similarity))?;
+        pager.write(&format!("{} from {}\n", verb, diff::header_path("", &pair.from)))?;
+        pager.write(&format!("{} to {}\n", verb, diff::header_path("", &pair.to)))?;
+        if from_oid == to_oid {
+            return Ok(());
+        }
+        
+        pager.write(&format!("index {}..{} {}\n", &from_oid[..7.min(from_oid.len())], &to_oid[..7.min(to_oid.len())], mode))?;
+        pager.write(&format!("--- {}\n", diff::header_path("a/", &pair.from)))?;
+        pager.write(&format!("+++ {}\n", diff::header_path("b/", &pair.to)))?;
+        
+        let from_content = workspace.attributes().textconv(Path::new(&pair.from), database.load(from_oid)?.to_bytes())?;
+        let to_content = workspace.attributes().textconv(Path::new(&pair.to), database.load(to_oid)?.to_bytes())?;
+        if workspace.attributes().is_binary(Path::new(&pair.to), &[&from_content, &to_content]) {
+            pager.write(&format!("Binary files {} and {} differ\n", diff::header_path("a/", &pair.from), diff::header_path("b/", &pair.to)))?;
+            return Ok(());
+        }
+        
+        let from_lines = diff::split_lines(&String::from_utf8_lossy(&from_content));
+        let to_lines = diff::split_lines(&String::from_utf8_lossy(&to_content));
+        let edits = diff_lines(&from_lines, &to_lines);
+        pager.write(&Self::colorize_diff_output(&format_diff(&from_lines, &to_lines, &edits, 3)))?;
         Ok(())
     }  
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+// src/commands/difftool.rs - show changes in an external diff tool
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use crate::core::config::Config;
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+/// How `ash difftool` shows the changes
+#[derive(Debug, Clone, Default)]
+pub struct DifftoolOptions {
+    /// Copy the changed files of each side into a directory of its own and
+    /// run the tool once on the two
+    pub dir_diff: bool,
+    pub tool: Option<String>,
+    /// Launch the tool for each file without asking first
+    pub no_prompt: bool,
+    pub cached: bool,
+}
+
+// One side of the comparison
+enum Side {
+    Commit(String),
+    Index,
+    Worktree,
+}
+
+// Where a side's version of a file is
+enum Source {
+    Blob(String),
+    File(PathBuf),
+}
+
+pub struct DifftoolCommand;
+
+impl DifftoolCommand {
+    /// Show what `ash diff` would for the same `revisions` and `paths` in
+    /// the tool `--tool`, `diff.tool` or `merge.tool` names, run as
+    /// `difftool.<tool>.cmd` says with `$LOCAL` and `$REMOTE` the two
+    /// versions: file by file, asking before each unless `difftool.prompt`
+    /// is false, or with `dir_diff` once for two directories. Edits made
+    /// to the working tree side of a directory diff are copied back.
+    pub fn execute(revisions: &[String], paths: &[String], options: &DifftoolOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let config = Config::load(&git_dir::resolve(&repo.path))?;
+        let command = Self::tool_command(&config, options.tool.as_deref())?;
+
+        let (left, right) = Self::sides(&mut repo, revisions, options.cached)?;
+        let left_files = Self::files(&mut repo, &left)?;
+        let right_files = Self::files(&mut repo, &right)?;
+
+        let mut changed = Vec::new();
+        let names: BTreeSet<&String> = left_files.keys().chain(right_files.keys()).collect();
+        for name in names {
+            let selected = paths.is_empty() || paths.iter().any(|path| {
+                let path = path.trim_start_matches("./").trim_end_matches('/');
+                path.is_empty() || name == path || name.starts_with(&format!("{}/", path))
+            });
+            if !selected {
+                continue;
+            }
+            let (old, new) = (left_files.get(name), right_files.get(name));
+            let same = match (old, new) {
+                (Some(Source::Blob(a)), Some(Source::Blob(b))) => a == b,
+                (Some(old), Some(new)) => Self::read(&mut repo, old)? == Self::read(&mut repo, new)?,
+                _ => false,
+            };
+            if !same {
+                changed.push(name.clone());
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        let temp = env::temp_dir().join(format!("ash-difftool-{}", process::id()));
+        let result = if options.dir_diff {
+            Self::dir_diff(&mut repo, &command, &temp, &changed, (&left_files, &right_files), matches!(right, Side::Worktree))
+        } else {
+            let prompt = !options.no_prompt && config.get_bool("difftool.prompt").unwrap_or(true);
+            let trust_exit_code = config.get_bool("difftool.trustExitCode").unwrap_or(false);
+            Self::each_file(&mut repo, &command, &temp, &changed, (&left_files, &right_files), prompt, trust_exit_code)
+        };
+        let _ = fs::remove_dir_all(&temp);
+        result
+    }
+
+    // `difftool.<tool>.cmd`, or the tool itself run on the two files
+    fn tool_command(config: &Config, tool: Option<&str>) -> Result<String, Error> {
+        let tool = tool.map(str::to_string)
+            .or_else(|| config.get("diff.tool"))
+            .or_else(|| config.get("merge.tool"))
+            .ok_or_else(|| Error::Generic("no diff tool given: use --tool=<tool> or set diff.tool".to_string()))?;
+        if let Some(command) = config.get(&format!("difftool.{}.cmd", tool)) {
+            return Ok(command);
+        }
+        let program = match tool.as_str() {
+            "vimdiff" => "vim -d",
+            "nvimdiff" => "nvim -d",
+            "gvimdiff" => "gvim -d",
+            other => other,
+        };
+        Ok(format!("{} \"$LOCAL\" \"$REMOTE\"", program))
+    }
+
+    // The sides `ash diff` would compare: the index and the working tree;
+    // with `cached` a commit (HEAD by default) and the index; a commit and
+    // the working tree; or two commits, also given as `A..B`
+    fn sides(repo: &mut Repository, revisions: &[String], cached: bool) -> Result<(Side, Side), Error> {
+        let mut commit = |name: &str| -> Result<Side, Error> {
+            let name = if name.is_empty() { "HEAD" } else { name };
+            Revision::new(repo, name).resolve(COMMIT).map(Side::Commit)
+        };
+        match (revisions, cached) {
+            ([], false) => Ok((Side::Index, Side::Worktree)),
+            ([], true) => Ok((commit("HEAD")?, Side::Index)),
+            ([base], true) => Ok((commit(base)?, Side::Index)),
+            ([range], false) if range.contains("..") => {
+                let (from, to) = range.split_once("..").unwrap_or_default();
+                Ok((commit(from)?, commit(to)?))
+            },
+            ([base], false) => Ok((commit(base)?, Side::Worktree)),
+            ([from, to], false) => Ok((commit(from)?, commit(to)?)),
+            _ => Err(Error::Generic("too many revisions for difftool".to_string())),
+        }
+    }
+
+    // The files of a side by path; the working tree has the tracked ones
+    fn files(repo: &mut Repository, side: &Side) -> Result<BTreeMap<String, Source>, Error> {
+        match side {
+            Side::Commit(oid) => Ok(head_files(repo, oid)?.into_iter()
+                .map(|(path, entry)| (path, Source::Blob(entry.get_oid().to_string())))
+                .collect()),
+            Side::Index => {
+                repo.index.load()?;
+                Ok(repo.index.each_entry()
+                    .map(|entry| (entry.get_path().to_string(), Source::Blob(entry.get_oid().to_string())))
+                    .collect())
+            },
+            Side::Worktree => {
+                repo.index.load()?;
+                Ok(repo.index.each_entry()
+                    .map(|entry| (entry.get_path().to_string(), repo.path.join(entry.get_path())))
+                    .filter(|(_, path)| path.is_file())
+                    .map(|(name, path)| (name, Source::File(path)))
+                    .collect())
+            },
+        }
+    }
+
+    fn read(repo: &mut Repository, source: &Source) -> Result<Vec<u8>, Error> {
+        match source {
+            Source::Blob(oid) => Ok(repo.database.load(oid)?.to_bytes()),
+            Source::File(path) => Ok(fs::read(path)?),
+        }
+    }
+
+    // Write a side's version of `name` under `dir`, empty if it has none
+    fn write(repo: &mut Repository, dir: &Path, name: &str, source: Option<&Source>) -> Result<PathBuf, Error> {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = match source {
+            Some(source) => Self::read(repo, source)?,
+            None => Vec::new(),
+        };
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    fn dir_diff(
+        repo: &mut Repository,
+        command: &str,
+        temp: &Path,
+        changed: &[String],
+        (left, right): (&BTreeMap<String, Source>, &BTreeMap<String, Source>),
+        right_is_worktree: bool,
+    ) -> Result<(), Error> {
+        let (left_dir, right_dir) = (temp.join("left"), temp.join("right"));
+        fs::create_dir_all(&left_dir)?;
+        fs::create_dir_all(&right_dir)?;
+        // Files missing on one side are missing from its directory too
+        let mut copied = Vec::new();
+        for name in changed {
+            if let Some(source) = left.get(name) {
+                Self::write(repo, &left_dir, name, Some(source))?;
+            }
+            if let Some(source) = right.get(name) {
+                Self::write(repo, &right_dir, name, Some(source))?;
+                copied.push((name, Self::read(repo, source)?));
+            }
+        }
+
+        Self::run(command, &left_dir, &right_dir, None)?;
+
+        // The working tree side stood in for the files themselves
+        if right_is_worktree {
+            for (name, original) in copied {
+                if let Ok(edited) = fs::read(right_dir.join(name)) {
+                    if edited != original {
+                        fs::write(repo.path.join(name), edited)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn each_file(
+        repo: &mut Repository,
+        command: &str,
+        temp: &Path,
+        changed: &[String],
+        (left, right): (&BTreeMap<String, Source>, &BTreeMap<String, Source>),
+        prompt: bool,
+        trust_exit_code: bool,
+    ) -> Result<(), Error> {
+        let tool = command.split_whitespace().next().unwrap_or(command);
+        for (position, name) in changed.iter().enumerate() {
+            if prompt {
+                println!("\nViewing ({}/{}): '{}'", position + 1, changed.len(), name);
+                print!("Launch '{}' [Y/n]? ", tool);
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                io::stdin().lock().read_line(&mut answer)?;
+                if answer.trim().eq_ignore_ascii_case("n") {
+                    continue;
+                }
+            }
+
+            let local = Self::write(repo, &temp.join("left"), name, left.get(name))?;
+            // The working tree's own file is shown, so it can be edited
+            let remote = match right.get(name) {
+                Some(Source::File(path)) => path.clone(),
+                source => Self::write(repo, &temp.join("right"), name, source)?,
+            };
+            let success = Self::run(command, &local, &remote, Some(name))?;
+            if !success && trust_exit_code {
+                return Err(Error::Generic(format!("external diff tool failed on '{}'", name)));
+            }
+        }
+        Ok(())
+    }
+
+    // Run the tool with $LOCAL and $REMOTE, and $MERGED and $BASE naming
+    // the file when there is one; whether it exited with 0
+    fn run(command: &str, local: &Path, remote: &Path, name: Option<&str>) -> Result<bool, Error> {
+        let mut tool = Command::new("sh");
+        tool.arg("-c").arg(command).env("LOCAL", local).env("REMOTE", remote);
+        if let Some(name) = name {
+            tool.env("MERGED", name).env("BASE", name);
+        }
+        let status = tool.status()
+            .map_err(|e| Error::Generic(format!("cannot run diff tool: {}", e)))?;
+        Ok(status.success())
+    }
+}
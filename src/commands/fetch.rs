@@ -0,0 +1,311 @@
+// src/commands/fetch.rs - download objects and refs from another repository
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::protocol::resume::PartialPack;
+use crate::core::protocol::throttle::Throttle;
+use crate::core::protocol::{self, negotiator::Negotiator, ObjectFilter};
+use crate::core::refs::{Reference, HEAD};
+use crate::core::remote::refspec::{short_ref_name, Refspec};
+use crate::core::remote::transport;
+use crate::core::remote::{self, Remote, DEFAULT_REMOTE};
+use crate::core::repository::{format, git_dir};
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
+use crate::trace;
+
+const FETCH_HEAD: &str = "FETCH_HEAD";
+const TAGS_REFSPEC: &str = "refs/tags/*:refs/tags/*";
+
+// A remote ref selected by the refspecs and where it goes locally
+struct FetchedRef {
+    name: String,
+    oid: String,
+    local: Option<String>,
+    force: bool,
+}
+
+/// What a fetch does besides following its refspecs
+#[derive(Debug, Clone, Default)]
+pub struct FetchOptions {
+    /// Delete local refs whose remote refs are gone; None for the config
+    pub prune: Option<bool>,
+    /// Prune local tags the same way; None for the config
+    pub prune_tags: Option<bool>,
+    /// Refs (globs allowed) or commits whose history is offered to the
+    /// remote as what we have, instead of every local ref
+    pub negotiation_tips: Vec<String>,
+    /// Refspecs that also store what command-line refspecs fetch, e.g. in
+    /// remote-tracking refs; None for the remote's configured refspecs
+    pub refmap: Option<Vec<String>>,
+    /// Leave out objects the filter matches; the remote is remembered as
+    /// holding them, and they are fetched from it when first needed
+    pub filter: Option<String>,
+}
+
+pub struct FetchCommand;
+
+impl FetchCommand {
+    /// Fetch from `remote` (a configured name or a URL, `origin` by default)
+    /// using `refspecs`, or the remote's configured refspecs when none are given.
+    /// With `prune`, local refs the refspecs fill from remote refs that are
+    /// gone get deleted; `prune_tags` prunes local tags the same way. Both
+    /// default to `remote.<name>.prune[Tags]`, then `fetch.prune[Tags]`;
+    /// a mirror remote is always pruned unless told otherwise. A `filter`
+    /// defaults to the `remote.<name>.partialCloneFilter` of an earlier one.
+    /// Returns the remote refs that were fetched, by full name, with their IDs.
+    pub fn execute(remote: Option<&str>, refspecs: &[String], options: &FetchOptions) -> Result<Vec<(String, String)>, Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let mut config = Config::load(&git_path)?;
+
+        let remote = Remote::resolve(&git_path, remote.unwrap_or(DEFAULT_REMOTE)).map_err(|e| match remote {
+            Some(_) => e,
+            None => Error::Generic("No remote repository specified.".to_string()),
+        })?;
+
+        let setting = |key: &str| {
+            remote.name.as_ref()
+                .and_then(|name| config.get_bool(&format!("remote.{}.{}", name, key)))
+                .or_else(|| config.get_bool(&format!("fetch.{}", key)))
+                .unwrap_or(false)
+        };
+        let prune_tags = options.prune_tags.unwrap_or_else(|| setting("pruneTags"));
+        let prune = options.prune.unwrap_or_else(|| remote.mirror || setting("prune")) || prune_tags;
+
+        let filter_key = remote.name.as_ref().map(|name| format!("remote.{}.partialCloneFilter", name));
+        let filter = match options.filter.clone().or_else(|| filter_key.as_ref().and_then(|key| config.get(key))) {
+            Some(spec) => Some(ObjectFilter::parse(&spec)?),
+            None => None,
+        };
+
+        // Command-line refspecs also update what the refmap maps their
+        // refs to, by default the remote-tracking refs
+        let refmap = match &options.refmap {
+            Some(refmap) => refmap.iter()
+                .filter(|spec| !spec.is_empty())
+                .map(|spec| Refspec::parse(spec))
+                .collect::<Result<Vec<_>, _>>()?,
+            None if !refspecs.is_empty() => remote.fetch_specs.clone(),
+            None => Vec::new(),
+        };
+
+        let mut specs = if refspecs.is_empty() {
+            remote.fetch_specs.clone()
+        } else {
+            refspecs.iter().map(|spec| Refspec::parse(spec)).collect::<Result<Vec<_>, _>>()?
+        };
+        if prune_tags {
+            specs.push(Refspec::parse(&format!("+{}", TAGS_REFSPEC))?);
+        }
+
+        let mut transport = transport::connect(&remote.url)?;
+        let advertised = transport.advertised_refs()?;
+
+        // A bare URL with no refspecs fetches the remote HEAD
+        let specs = if specs.is_empty() {
+            vec![Refspec::parse(HEAD)?]
+        } else {
+            specs
+        };
+
+        let mut fetched = Vec::new();
+        for spec in &specs {
+            let mut matched = false;
+            for (name, oid) in &advertised {
+                // HEAD is only fetched when asked for by name
+                if name == HEAD && spec.source != HEAD {
+                    continue;
+                }
+                if let Some(local) = spec.map(name) {
+                    matched = true;
+                    let mapped = || refmap.iter().find_map(|map| map.map(name).flatten().map(|local| (local, map.force)));
+                    let (local, force) = match local {
+                        Some(local) => (Some(local), spec.force),
+                        None => match mapped() {
+                            Some((local, force)) => (Some(local), force),
+                            None => (None, spec.force),
+                        },
+                    };
+                    fetched.push(FetchedRef { name: name.clone(), oid: oid.clone(), local, force });
+                }
+            }
+            if !matched && !spec.source.contains('*') {
+                return Err(Error::Generic(format!("couldn't find remote ref {}", spec.source)));
+            }
+        }
+
+        // Download whatever objects are missing
+        let mut wants = Vec::new();
+        let mut seen = HashSet::new();
+        for fetched_ref in &fetched {
+            if !repo.database.exists(&fetched_ref.oid) && seen.insert(fetched_ref.oid.clone()) {
+                wants.push(fetched_ref.oid.clone());
+            }
+        }
+
+        let tips = Self::local_tips(&mut repo, &options.negotiation_tips)?;
+        if !wants.is_empty() {
+            // An interrupted fetch of the same refs is continued where it
+            // stopped, with the state it negotiated
+            let throttle = Throttle::from_config(&config)?;
+            let mut pack = PartialPack::open(&git_path, &remote.url, &wants, filter, throttle)?;
+            let common = match pack.common() {
+                Some(common) => common.to_vec(),
+                None => {
+                    let common = Negotiator::new(&mut repo.database).negotiate(transport.as_mut(), &tips)?;
+                    pack.begin(&common)?;
+                    common
+                },
+            };
+            let checksum = transport.fetch_objects(&wants, &common, filter, &mut pack)?;
+            let objects = pack.finish(&checksum)?;
+
+            let deltas = protocol::unpack_objects(&mut repo.database, &objects)?;
+            trace!("Received {} objects ({} as deltas)", objects.len(), deltas);
+        }
+
+        // Later fetches and lazy lookups of what was left out need to know
+        // where it lives
+        if let (Some(filter), Some(name), Some(key)) = (filter, &remote.name, &filter_key) {
+            if config.get(key) != Some(filter.to_string()) {
+                config.unset_all(key)?;
+                config.add(key, &filter.to_string())?;
+            }
+            let promisor_key = format!("remote.{}.promisor", name);
+            if config.get_bool(&promisor_key) != Some(true) {
+                config.unset_all(&promisor_key)?;
+                config.add(&promisor_key, "true")?;
+            }
+            // Versions of ash that cannot fetch what was left out must not
+            // treat the repository as complete
+            format::require_extension(&mut config, "partialclone", name)?;
+        }
+
+        Self::write_fetch_head(&git_path, &remote.url, &fetched)?;
+
+        // Prune first, so a branch `a` deleted upstream does not stand in
+        // the way of a new `a/b`
+        let mut lines = Vec::new();
+        if prune {
+            for stale in remote::stale_refs(&repo.refs, &specs, &advertised)? {
+                repo.refs.delete_ref(&stale)?;
+                lines.push(format!(" - {:<17} {:<10} -> {}", "[deleted]", "(none)", short_ref_name(&stale)));
+            }
+        }
+
+        // Update local refs, refusing to lose history unless forced
+        let mut rejected = false;
+        for fetched_ref in &fetched {
+            let local = match &fetched_ref.local {
+                Some(local) => local,
+                None => continue,
+            };
+            let old = repo.refs.read_ref(local)?;
+            let from = short_ref_name(&fetched_ref.name).to_string();
+            let to = short_ref_name(local).to_string();
+
+            let (flag, summary, note, reason) = match old {
+                Some(old) if old == fetched_ref.oid => continue,
+                None => {
+                    let kind = match local.as_str() {
+                        l if l.starts_with("refs/tags/") => "[new tag]",
+                        l if l.starts_with("refs/heads/") || l.starts_with("refs/remotes/") => "[new branch]",
+                        _ => "[new ref]",
+                    };
+                    let reason = if local.starts_with("refs/tags/") { "storing tag" } else { "storing head" };
+                    ('*', kind.to_string(), String::new(), reason)
+                },
+                Some(old) => {
+                    let fast_forward = remote::is_fast_forward(&mut repo.database, &old, &fetched_ref.oid)?;
+                    let range = |sep: &str| format!(
+                        "{}{}{}",
+                        repo.database.short_oid(&old),
+                        sep,
+                        repo.database.short_oid(&fetched_ref.oid)
+                    );
+                    if fast_forward {
+                        (' ', range(".."), String::new(), "fast-forward")
+                    } else if fetched_ref.force {
+                        ('+', range("..."), "  (forced update)".to_string(), "forced-update")
+                    } else {
+                        rejected = true;
+                        lines.push(format!(" ! {:<17} {:<10} -> {}  (non-fast-forward)", "[rejected]", from, to));
+                        continue;
+                    }
+                },
+            };
+
+            repo.refs.update_ref(local, &fetched_ref.oid, &format!("fetch {}: {}", remote.name.as_deref().unwrap_or(&remote.url), reason))?;
+            lines.push(format!(" {} {:<17} {:<10} -> {}{}", flag, summary, from, to, note));
+        }
+
+        if !lines.is_empty() && (!crate::core::trace::is_quiet() || rejected) {
+            eprintln!("From {}", remote.url);
+            for line in lines {
+                eprintln!("{}", line);
+            }
+        }
+
+        if rejected {
+            return Err(Error::Generic("some local refs could not be updated".to_string()));
+        }
+        Ok(fetched.into_iter().map(|fetched_ref| (fetched_ref.name, fetched_ref.oid)).collect())
+    }
+
+    // Commits to start negotiation from: HEAD, branches, remote-tracking
+    // refs and tags, or only the given refs, ref globs and commits
+    fn local_tips(repo: &mut Repository, negotiation_tips: &[String]) -> Result<Vec<String>, Error> {
+        if negotiation_tips.is_empty() {
+            let mut tips: Vec<String> = repo.refs.read_head()?.into_iter().collect();
+            for prefix in ["refs/heads", "refs/remotes", "refs/tags"] {
+                for reference in repo.refs.list_refs_under(prefix)? {
+                    if let Reference::Symbolic(name) = reference {
+                        tips.extend(repo.refs.read_ref(&name)?);
+                    }
+                }
+            }
+            return Ok(tips);
+        }
+
+        let mut tips = Vec::new();
+        for tip in negotiation_tips {
+            if tip.contains('*') {
+                let pattern = Refspec::parse(tip)?;
+                for reference in repo.refs.list_refs_under("refs")? {
+                    if let Reference::Symbolic(name) = reference {
+                        if pattern.map(&name).is_some() {
+                            tips.extend(repo.refs.read_ref(&name)?);
+                        }
+                    }
+                }
+            } else {
+                let mut revision = Revision::new(repo, tip);
+                let oid = revision.resolve("commit")
+                    .map_err(|_| Error::Generic(format!("the object {} does not exist", tip)))?;
+                tips.push(oid);
+            }
+        }
+        trace!("Negotiating from {} tips", tips.len());
+        Ok(tips)
+    }
+
+    // Record what was fetched; the first ref is the one `merge FETCH_HEAD` would use
+    fn write_fetch_head(git_path: &Path, url: &str, fetched: &[FetchedRef]) -> Result<(), Error> {
+        let mut content = String::new();
+        for (i, fetched_ref) in fetched.iter().enumerate() {
+            let description = match fetched_ref.name.as_str() {
+                HEAD => String::new(),
+                name if name.starts_with("refs/tags/") => format!("tag '{}' of ", short_ref_name(name)),
+                name => format!("branch '{}' of ", short_ref_name(name)),
+            };
+            let merge_flag = if i == 0 { "" } else { "not-for-merge" };
+            content.push_str(&format!("{}\t{}\t{}{}\n", fetched_ref.oid, merge_flag, description, url));
+        }
+        fs::write(git_path.join(FETCH_HEAD), content)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+// src/commands/for_each_ref.rs - print refs in a format of the user's choosing
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use regex::Regex;
+
+use crate::commands::tag::TagCommand;
+use crate::core::config::Config;
+use crate::core::database::author::Author;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::GitObject;
+use crate::core::database::tag::Tag;
+use crate::core::ignore::glob_to_regex;
+use crate::core::refs::Reference;
+use crate::core::remote::refspec::short_ref_name;
+use crate::core::remote::upstream::{self, Upstream};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+const DEFAULT_FORMAT: &str = "%(objectname) %(objecttype)\t%(refname)";
+const DEFAULT_SORT: &str = "refname";
+
+/// Which refs `ash for-each-ref` prints, in what order and how
+#[derive(Debug, Clone, Default)]
+pub struct ForEachRefOptions {
+    pub format: Option<String>,
+    /// Keys to sort by, the last one deciding first; `-` in front of a
+    /// key sorts it the other way round
+    pub sort: Vec<String>,
+    pub count: Option<usize>,
+}
+
+// A value to sort by: dates and sizes compare as numbers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Number(i64),
+    Text(String),
+}
+
+// A ref with the objects its fields come from
+struct Item {
+    name: String,
+    oid: String,
+    object: Box<dyn GitObject>,
+    // What an annotated tag points at, for the `*` fields
+    peeled: Option<(String, Box<dyn GitObject>)>,
+}
+
+pub struct ForEachRefCommand;
+
+impl ForEachRefCommand {
+    /// Print each ref that `patterns` match (every ref when there are
+    /// none) with `options.format`, in which `%(<field>)` stands for a field
+    /// of the ref or of the object it points at, `%%` for a `%` and `%xx`
+    /// for the byte with that hex code. A pattern matches the ref it names,
+    /// the refs under it, or as a glob the full names of refs.
+    pub fn execute(patterns: &[String], options: &ForEachRefOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let format = options.format.as_deref().unwrap_or(DEFAULT_FORMAT);
+        let segments = Self::parse_format(format)?;
+        let matchers = patterns.iter()
+            .map(|pattern| Regex::new(&format!("^{}$", glob_to_regex(pattern)))
+                .map_err(|e| Error::Generic(format!("invalid pattern '{}': {}", pattern, e))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut items = Vec::new();
+        for reference in repo.refs.list_refs_under("refs")? {
+            let name = match reference {
+                Reference::Symbolic(name) => name,
+                Reference::Direct(_) => continue,
+            };
+            let selected = patterns.is_empty() || patterns.iter().zip(&matchers).any(|(pattern, matcher)| {
+                let prefix = pattern.trim_end_matches('/');
+                name == prefix || name.starts_with(&format!("{}/", prefix)) || matcher.is_match(&name)
+            });
+            if !selected {
+                continue;
+            }
+            let oid = match repo.refs.read_ref(&name)? {
+                Some(oid) => oid,
+                None => continue,
+            };
+            let object = repo.database.load(&oid)?;
+            let peeled = match object.as_any().downcast_ref::<Tag>() {
+                Some(_) => {
+                    let target = repo.database.peel(&oid)?;
+                    let target_object = repo.database.load(&target)?;
+                    Some((target, target_object))
+                },
+                None => None,
+            };
+            items.push(Item { name, oid, object, peeled });
+        }
+
+        let sort = if options.sort.is_empty() { vec![DEFAULT_SORT.to_string()] } else { options.sort.clone() };
+        for key in &sort {
+            let (descending, field) = match key.strip_prefix('-') {
+                Some(field) => (true, field),
+                None => (false, key.as_str()),
+            };
+            let mut keyed = Vec::new();
+            for item in items {
+                let value = match field {
+                    "version:refname" | "v:refname" => Key::Text(item.name.clone()),
+                    _ => Self::sort_key(&mut repo, &item, field)?,
+                };
+                keyed.push((value, item));
+            }
+            let by_version = matches!(field, "version:refname" | "v:refname");
+            keyed.sort_by(|(a, _), (b, _)| {
+                let order = match (a, b) {
+                    (Key::Text(a), Key::Text(b)) if by_version => TagCommand::compare_versions(a, b),
+                    _ => a.cmp(b),
+                };
+                if descending { order.reverse() } else { order }
+            });
+            items = keyed.into_iter().map(|(_, item)| item).collect();
+        }
+
+        for item in items.iter().take(options.count.unwrap_or(usize::MAX)) {
+            let mut line = String::new();
+            for segment in &segments {
+                match segment {
+                    Segment::Literal(text) => line.push_str(text),
+                    Segment::Field(field) => line.push_str(&Self::field(&mut repo, item, field)?),
+                }
+            }
+            println!("{}", line);
+        }
+        Ok(())
+    }
+
+    // Split a format into its literal text and `%(<field>)`s, checking
+    // that every field is known
+    fn parse_format(format: &str) -> Result<Vec<Segment>, Error> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut rest = format;
+        while let Some(position) = rest.find('%') {
+            literal.push_str(&rest[..position]);
+            rest = &rest[position + 1..];
+            if let Some(after) = rest.strip_prefix('%') {
+                literal.push('%');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('(') {
+                let end = after.find(')')
+                    .ok_or_else(|| Error::Generic(format!("malformed format string {}", format)))?;
+                let field = &after[..end];
+                Self::check_field(field)?;
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field(field.to_string()));
+                rest = &after[end + 1..];
+            } else if let Some(byte) = rest.get(..2).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                literal.push(byte as char);
+                rest = &rest[2..];
+            } else {
+                literal.push('%');
+            }
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(segments)
+    }
+
+    fn check_field(field: &str) -> Result<(), Error> {
+        let (name, modifier) = Self::split_field(field);
+        let known = match name {
+            "refname" => modifier.is_none_or(|modifier| {
+                modifier == "short" || ["strip=", "lstrip="].iter()
+                    .any(|prefix| modifier.strip_prefix(prefix).is_some_and(|n| n.parse::<usize>().is_ok()))
+            }),
+            "objectname" => modifier.is_none_or(|modifier| modifier == "short"),
+            "upstream" => modifier.is_none_or(|modifier| ["short", "track", "trackshort"].contains(&modifier)),
+            "authordate" | "committerdate" | "taggerdate" | "creatordate" => {
+                modifier.is_none_or(|modifier| ["short", "iso", "unix", "raw", "default"].contains(&modifier))
+            },
+            "objecttype" | "objectsize" | "HEAD" | "subject" | "body" | "contents" | "tree" | "parent" | "object" | "type"
+            | "authorname" | "authoremail" | "committername" | "committeremail" | "taggername" | "taggeremail" => modifier.is_none(),
+            _ => false,
+        };
+        if known {
+            Ok(())
+        } else {
+            Err(Error::Generic(format!("unknown field name: {}", field)))
+        }
+    }
+
+    // `name:modifier` as the two, without the `*` for the peeled object
+    fn split_field(field: &str) -> (&str, Option<&str>) {
+        let field = field.strip_prefix('*').unwrap_or(field);
+        match field.split_once(':') {
+            Some((name, modifier)) => (name, Some(modifier)),
+            None => (field, None),
+        }
+    }
+
+    fn sort_key(repo: &mut Repository, item: &Item, field: &str) -> Result<Key, Error> {
+        Self::check_field(field)?;
+        let (name, _) = Self::split_field(field);
+        if name.ends_with("date") {
+            let object = Self::object_for(item, field);
+            return Ok(Key::Number(Self::person(object, name).map_or(0, |person| person.timestamp.timestamp())));
+        }
+        if name == "objectsize" {
+            return Ok(Key::Number(item.object.to_bytes().len() as i64));
+        }
+        Ok(Key::Text(Self::field(repo, item, field)?))
+    }
+
+    // The object a field is about: the ref's own, or with `*` what an
+    // annotated tag points at (None for other refs)
+    fn object_for<'a>(item: &'a Item, field: &str) -> Option<&'a dyn GitObject> {
+        if field.starts_with('*') {
+            item.peeled.as_ref().map(|(_, object)| object.as_ref())
+        } else {
+            Some(item.object.as_ref())
+        }
+    }
+
+    // The author, committer or tagger a `<who><what>` field is about;
+    // `creator` is the committer of a commit or the tagger of a tag
+    fn person<'a>(object: Option<&'a dyn GitObject>, name: &str) -> Option<&'a Author> {
+        let object = object?;
+        let commit = object.as_any().downcast_ref::<Commit>();
+        let tag = object.as_any().downcast_ref::<Tag>();
+        if name.starts_with("author") {
+            commit.and_then(Commit::get_author)
+        } else if name.starts_with("committer") {
+            commit.and_then(Commit::get_committer)
+        } else if name.starts_with("tagger") {
+            tag.map(|tag| &tag.tagger)
+        } else {
+            commit.and_then(Commit::get_committer).or(tag.map(|tag| &tag.tagger))
+        }
+    }
+
+    fn field(repo: &mut Repository, item: &Item, field: &str) -> Result<String, Error> {
+        let (name, modifier) = Self::split_field(field);
+        let (oid, object) = if field.starts_with('*') {
+            match &item.peeled {
+                Some((oid, object)) => (oid.as_str(), object),
+                None => return Ok(String::new()),
+            }
+        } else {
+            (item.oid.as_str(), &item.object)
+        };
+        let commit = object.as_any().downcast_ref::<Commit>();
+        let tag = object.as_any().downcast_ref::<Tag>();
+        let message = commit.map(Commit::get_message).or(tag.map(|tag| tag.message.as_str())).unwrap_or_default();
+
+        let value = match name {
+            "refname" => match modifier {
+                Some("short") => short_ref_name(&item.name).to_string(),
+                Some(modifier) => {
+                    let n = modifier.split_once('=').and_then(|(_, n)| n.parse().ok()).unwrap_or(0);
+                    item.name.split('/').skip(n).collect::<Vec<_>>().join("/")
+                },
+                None => item.name.clone(),
+            },
+            "objectname" => match modifier {
+                Some(_) => repo.database.short_oid(oid),
+                None => oid.to_string(),
+            },
+            "objecttype" => object.get_type().to_string(),
+            "objectsize" => object.to_bytes().len().to_string(),
+            "tree" => commit.map(|commit| commit.get_tree().to_string()).unwrap_or_default(),
+            "parent" => commit.map(|commit| commit.get_parents().join(" ")).unwrap_or_default(),
+            "object" => tag.map(|tag| tag.object.clone()).unwrap_or_default(),
+            "type" => tag.map(|tag| tag.object_type.clone()).unwrap_or_default(),
+            "HEAD" => {
+                let head = repo.refs.current_ref()?;
+                if head == Reference::Symbolic(item.name.clone()) { "*" } else { " " }.to_string()
+            },
+            "subject" => message.lines().next().unwrap_or_default().to_string(),
+            "body" => message.split_once("\n\n").map(|(_, body)| body.to_string()).unwrap_or_default(),
+            "contents" => message.to_string(),
+            "upstream" => return Self::upstream_field(repo, item, modifier),
+            _ => {
+                let person = match Self::person(Some(object.as_ref()), name) {
+                    Some(person) => person,
+                    None => return Ok(String::new()),
+                };
+                if name.ends_with("name") {
+                    person.name.clone()
+                } else if name.ends_with("email") {
+                    format!("<{}>", person.email)
+                } else {
+                    let timestamp = person.timestamp;
+                    match modifier {
+                        Some("short") => person.short_date(),
+                        Some("iso") => timestamp.format("%Y-%m-%d %H:%M:%S +0000").to_string(),
+                        Some("unix") => timestamp.timestamp().to_string(),
+                        Some("raw") => format!("{} +0000", timestamp.timestamp()),
+                        _ => timestamp.format("%a %b %-d %H:%M:%S %Y +0000").to_string(),
+                    }
+                }
+            },
+        };
+        Ok(value)
+    }
+
+    // The upstream of a branch: its remote-tracking ref, shortened, or how
+    // far ahead and behind of it the branch is
+    fn upstream_field(repo: &mut Repository, item: &Item, modifier: Option<&str>) -> Result<String, Error> {
+        let git_path: PathBuf = git_dir::resolve(&repo.path);
+        let upstream = match item.name.strip_prefix("refs/heads/") {
+            Some(branch) => Upstream::of(&Config::load(&git_path)?, branch),
+            None => None,
+        };
+        let upstream = match upstream {
+            Some(upstream) => upstream,
+            None => return Ok(String::new()),
+        };
+        let tracking = upstream.tracking_ref(&git_path);
+        let track = matches!(modifier, Some("track") | Some("trackshort"));
+        if !track {
+            return Ok(match modifier {
+                Some("short") => short_ref_name(&tracking).to_string(),
+                _ => tracking,
+            });
+        }
+
+        let theirs = match repo.refs.read_ref(&tracking)? {
+            Some(theirs) => theirs,
+            None => return Ok(if modifier == Some("track") { "[gone]".to_string() } else { String::new() }),
+        };
+        let (ahead, behind) = upstream::ahead_behind(&mut repo.database, &repo.refs, &item.oid, &theirs)?;
+        Ok(match (modifier, ahead.cmp(&0), behind.cmp(&0)) {
+            (Some("trackshort"), Ordering::Equal, Ordering::Equal) => "=".to_string(),
+            (Some("trackshort"), _, Ordering::Equal) => ">".to_string(),
+            (Some("trackshort"), Ordering::Equal, _) => "<".to_string(),
+            (Some("trackshort"), _, _) => "<>".to_string(),
+            (_, Ordering::Equal, Ordering::Equal) => String::new(),
+            (_, _, Ordering::Equal) => format!("[ahead {}]", ahead),
+            (_, Ordering::Equal, _) => format!("[behind {}]", behind),
+            _ => format!("[ahead {}, behind {}]", ahead, behind),
+        })
+    }
+}
+
+enum Segment {
+    Literal(String),
+    Field(String),
+}
@@ -0,0 +1,271 @@
+// src/commands/format_patch.rs - write commits out as mailbox patches
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::diff::hunk;
+use crate::core::diff::myers;
+use crate::core::diff::stat::{format_stat, stat_trees};
+use crate::core::path_filter::PathFilter;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT, HEAD};
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::errors::error::Error;
+
+// The date in the "From <oid>" line that starts every patch, as git
+// writes it, so that mail tools recognize the file as a mailbox
+const MAGIC_DATE: &str = "Mon Sep 17 00:00:00 2001";
+const NULL_OID: &str = "0000000";
+const NO_NEWLINE: &str = "\\ No newline at end of file";
+// Longest a patch file name gets, numbering and suffix included
+const NAME_MAX: usize = 64;
+const SUFFIX: &str = ".patch";
+
+/// How `ash format-patch` names and numbers the patches it writes
+#[derive(Debug, Clone, Default)]
+pub struct FormatPatchOptions {
+    /// Where to write the patch files, the current directory by default
+    pub output_directory: Option<String>,
+    /// Write all the patches to standard output instead, one mailbox
+    pub stdout: bool,
+    /// `Some(true)` to always number patches as [PATCH n/m], `Some(false)`
+    /// never to; by default they are numbered when there are several
+    pub numbered: Option<bool>,
+    pub start_number: Option<usize>,
+    /// What the subject starts with inside the brackets, "PATCH" by default
+    pub subject_prefix: Option<String>,
+    /// Only the last this many commits from the revision given
+    pub max_count: Option<usize>,
+}
+
+pub struct FormatPatchCommand;
+
+impl FormatPatchCommand {
+    /// Write a patch for each commit in `revision`, oldest first: a range
+    /// `A..B`, or a single commit taken as everything since it up to HEAD;
+    /// with `options.max_count` the last that many commits up to it, HEAD
+    /// by default. Each patch is a mail whose headers give the author,
+    /// the date and the subject, then the rest of the message, a diffstat
+    /// and the diff against the parent. Merges have no single diff and
+    /// are left out. The names of the files written are printed.
+    pub fn execute(revision: Option<&str>, options: &FormatPatchOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let mut resolve = |name: &str| -> Result<String, Error> {
+            let name = if name.is_empty() { HEAD } else { name };
+            Revision::new(&mut repo, name).resolve(COMMIT)
+        };
+
+        let walk_args = match (revision, options.max_count) {
+            (Some(range), _) if range.contains("..") => {
+                let (start, end) = range.split_once("..").unwrap_or_default();
+                vec![format!("^{}", resolve(start)?), resolve(end)?]
+            },
+            (revision, Some(_)) => vec![resolve(revision.unwrap_or(HEAD))?],
+            (Some(since), None) => vec![format!("^{}", resolve(since)?), resolve(HEAD)?],
+            (None, None) => {
+                return Err(Error::Generic("need a revision: <since>, <since>..<until> or -<n>".to_string()));
+            },
+        };
+
+        let walk_options = WalkOptions { topo_order: true, ..WalkOptions::default() };
+        let mut commits: Vec<Commit> = RevList::new(&mut repo.database, &repo.refs, &walk_args, false)?
+            .commits(&walk_options)?
+            .into_iter()
+            .map(|walked| walked.commit)
+            .filter(|commit| commit.get_parents().len() <= 1)
+            .collect();
+        if let Some(max_count) = options.max_count {
+            commits.truncate(max_count);
+        }
+        commits.reverse();
+
+        let total = commits.len();
+        let numbered = options.numbered.unwrap_or(total > 1);
+        let start_number = options.start_number.unwrap_or(1);
+        let prefix = options.subject_prefix.as_deref().unwrap_or("PATCH");
+
+        let directory = PathBuf::from(options.output_directory.as_deref().unwrap_or_default());
+        if !options.stdout && options.output_directory.is_some() && !directory.exists() {
+            fs::create_dir_all(&directory)
+                .map_err(|e| Error::Generic(format!("could not create directory '{}': {}", directory.display(), e)))?;
+        }
+
+        for (i, commit) in commits.iter().enumerate() {
+            let number = start_number + i;
+            let tag = if numbered {
+                format!("[{} {}/{}]", prefix, number, start_number + total - 1)
+            } else if prefix.is_empty() {
+                String::new()
+            } else {
+                format!("[{}]", prefix)
+            };
+            let patch = Self::format(&mut repo.database, commit, &tag)?;
+
+            if options.stdout {
+                print!("{}", patch);
+                continue;
+            }
+            let path = Self::file_name(&directory, number, &Self::subject(commit));
+            fs::write(&path, patch)
+                .map_err(|e| Error::Generic(format!("could not write '{}': {}", path.display(), e)))?;
+            println!("{}", path.display());
+        }
+        Ok(())
+    }
+
+    // The whole patch for `commit`, the subject led by `tag`
+    fn format(database: &mut Database, commit: &Commit, tag: &str) -> Result<String, Error> {
+        let oid = commit.get_oid().cloned().unwrap_or_default();
+        let parent = commit.get_parents().into_iter().next();
+
+        let mut patch = format!("From {} {}\n", oid, MAGIC_DATE);
+        if let Some(author) = commit.get_author() {
+            patch.push_str(&format!("From: {} <{}>\n", author.name, author.email));
+            patch.push_str(&format!("Date: {}\n", author.timestamp.format("%a, %-d %b %Y %H:%M:%S +0000")));
+        }
+        let subject = Self::subject(commit);
+        if tag.is_empty() {
+            patch.push_str(&format!("Subject: {}\n\n", subject));
+        } else {
+            patch.push_str(&format!("Subject: {} {}\n\n", tag, subject));
+        }
+        let body = Self::body(commit);
+        if !body.is_empty() {
+            patch.push_str(&format!("{}\n", body));
+        }
+
+        patch.push_str("---\n");
+        let stats = stat_trees(database, parent.as_deref(), Some(&oid), &PathFilter::new())?;
+        patch.push_str(&format_stat(&stats));
+        patch.push('\n');
+        patch.push_str(&Self::diff(database, parent.as_deref(), &oid)?);
+        patch.push_str(&format!("-- \nash {}\n\n", env!("CARGO_PKG_VERSION")));
+        Ok(patch)
+    }
+
+    // The first paragraph of the message, on one line
+    fn subject(commit: &Commit) -> String {
+        commit.get_message().trim_start()
+            .lines()
+            .take_while(|line| !line.trim().is_empty())
+            .map(str::trim)
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    // The message after its first paragraph
+    fn body(commit: &Commit) -> String {
+        let message = commit.get_message().trim_start();
+        let mut lines = message.lines();
+        for line in lines.by_ref() {
+            if line.trim().is_empty() {
+                break;
+            }
+        }
+        lines.collect::<Vec<&str>>().join("\n").trim().to_string()
+    }
+
+    // The diff between the trees of `parent` and `oid`, without color
+    fn diff(database: &mut Database, parent: Option<&str>, oid: &str) -> Result<String, Error> {
+        let changes = database.tree_diff(parent, Some(oid), &PathFilter::new())?;
+        let mut paths: Vec<&PathBuf> = changes.keys().collect();
+        paths.sort();
+
+        let mut output = String::new();
+        for path in paths {
+            let path_str = path.to_string_lossy();
+            let (old, new) = &changes[path];
+            output.push_str(&format!("diff --ash a/{} b/{}\n", path_str, path_str));
+            match (old, new) {
+                (None, Some(new)) => output.push_str(&format!("new file mode {}\n", new.get_mode())),
+                (Some(old), None) => output.push_str(&format!("deleted file mode {}\n", old.get_mode())),
+                (Some(old), Some(new)) if old.get_mode() != new.get_mode() => {
+                    output.push_str(&format!("old mode {}\nnew mode {}\n", old.get_mode(), new.get_mode()));
+                },
+                _ => {},
+            }
+            let old_oid = old.as_ref().map_or(NULL_OID, |entry| &entry.get_oid()[..7.min(entry.get_oid().len())]);
+            let new_oid = new.as_ref().map_or(NULL_OID, |entry| &entry.get_oid()[..7.min(entry.get_oid().len())]);
+            // Only the mode changed
+            if old_oid == new_oid {
+                continue;
+            }
+            match (old, new) {
+                (Some(old), Some(new)) if old.get_mode() == new.get_mode() => {
+                    output.push_str(&format!("index {}..{} {}\n", old_oid, new_oid, new.get_mode()));
+                },
+                _ => output.push_str(&format!("index {}..{}\n", old_oid, new_oid)),
+            }
+
+            let old_content = Self::content(database, old.as_ref())?;
+            let new_content = Self::content(database, new.as_ref())?;
+            // An empty file made or removed
+            if old_content == new_content {
+                continue;
+            }
+            let old_name = if old.is_some() { format!("a/{}", path_str) } else { "/dev/null".to_string() };
+            let new_name = if new.is_some() { format!("b/{}", path_str) } else { "/dev/null".to_string() };
+            if myers::is_binary_content(&old_content) || myers::is_binary_content(&new_content) {
+                output.push_str(&format!("Binary files {} and {} differ\n", old_name, new_name));
+                continue;
+            }
+
+            output.push_str(&format!("--- {}\n+++ {}\n", old_name, new_name));
+            // Lines keep their newline, so that a last line without one
+            // differs from the same line with it and can be marked
+            let old_lines = Self::lines(&old_content);
+            let new_lines = Self::lines(&new_content);
+            let edits = myers::diff_lines(&old_lines, &new_lines);
+            for hunk in hunk::build_unified_hunks(&old_lines, &new_lines, &edits, 3) {
+                output.push_str(&format!("{}\n", hunk.header()));
+                for line in &hunk.lines {
+                    match line.strip_suffix('\n') {
+                        Some(line) => output.push_str(&format!("{}\n", line)),
+                        None => output.push_str(&format!("{}\n{}\n", line, NO_NEWLINE)),
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    fn lines(content: &[u8]) -> Vec<String> {
+        String::from_utf8_lossy(content).split_inclusive('\n').map(str::to_string).collect()
+    }
+
+    fn content(database: &mut Database, entry: Option<&DatabaseEntry>) -> Result<Vec<u8>, Error> {
+        match entry {
+            Some(entry) => Ok(database.load(entry.get_oid())?.to_bytes()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    // "0001-fix-the-thing.patch": the number, then the subject with runs of
+    // anything but letters, digits, dots and underscores made a dash
+    fn file_name(directory: &Path, number: usize, subject: &str) -> PathBuf {
+        let mut name = format!("{:04}-", number);
+        let room = NAME_MAX.saturating_sub(name.len() + SUFFIX.len());
+        let mut sanitized = String::new();
+        let mut dash = false;
+        for c in subject.trim_start_matches('.').chars() {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' {
+                if dash && !sanitized.is_empty() {
+                    sanitized.push('-');
+                }
+                dash = false;
+                sanitized.push(c);
+            } else {
+                dash = true;
+            }
+            if sanitized.len() >= room {
+                break;
+            }
+        }
+        sanitized.truncate(room);
+        name.push_str(sanitized.trim_end_matches(['.', '-']));
+        name.push_str(SUFFIX);
+        directory.join(name)
+    }
+}
@@ -0,0 +1,303 @@
+// src/commands/gc.rs - drop unreachable objects, pack the rest and pack refs
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime};
+
+use crate::commands::repack::{IndexOptions, RepackCommand};
+use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::lockfile::Lockfile;
+use crate::core::refs::Reference;
+use crate::core::index::index::Index;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::repository::worktree::Worktree;
+use crate::trace;
+
+const DEFAULT_PRUNE_EXPIRE: &str = "2.weeks.ago";
+const AUTO_KEY: &str = "gc.auto";
+const AUTO_PACK_LIMIT_KEY: &str = "gc.autoPackLimit";
+const AUTO_DETACH_KEY: &str = "gc.autoDetach";
+const WRITE_COMMIT_GRAPH_KEY: &str = "gc.writeCommitGraph";
+const WRITE_BITMAPS_KEY: &str = "repack.writeBitmaps";
+const DEFAULT_AUTO: i64 = 6700;
+const DEFAULT_AUTO_PACK_LIMIT: i64 = 50;
+// Loose objects are counted in this one of the 256 fan-out directories and
+// taken to be spread evenly over all of them
+const SAMPLE_DIR: &str = "17";
+// Where state files other than refs may name objects
+const LOGS_DIR: &str = "logs";
+const SEQUENCER_DIR: &str = "sequencer";
+const INDEX_FILE: &str = "index";
+// Held for as long as a collection runs, as `gc.lock`
+pub const GC_LOCK: &str = "gc";
+
+pub struct GcCommand;
+
+impl GcCommand {
+    /// Collect garbage. Everything reachable from refs, HEAD and the other
+    /// state files, the reflogs and the index is packed into a single pack,
+    /// and loose refs into packed-refs. Unreachable loose objects older
+    /// than `prune` (`gc.pruneExpire`, two weeks by default) are deleted;
+    /// younger ones may belong to a command still running, so they stay.
+    /// Only one collection runs in a repository at a time.
+    ///
+    /// The commit-graph is rewritten too unless `options` or
+    /// `gc.writeCommitGraph` turn it off, and the pack gets a bitmap when
+    /// they or `repack.writeBitmaps` ask for one.
+    ///
+    /// With `auto`, nothing is done unless there are more loose objects
+    /// than `gc.auto` (6700) or more packs than `gc.autoPackLimit` (50);
+    /// either set to 0 turns its check off. The collection then runs in
+    /// the background unless `detach` or `gc.autoDetach` says otherwise,
+    /// and is skipped if another is running.
+    pub fn execute(prune: Option<&str>, auto: bool, detach: Option<bool>, options: IndexOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        if auto {
+            let config = Config::load(&git_path)?;
+            if !Self::needs_collecting(&git_path, &config)? {
+                return Ok(());
+            }
+            if detach.or_else(|| config.get_bool(AUTO_DETACH_KEY)).unwrap_or(true) {
+                eprintln!("Auto packing the repository in background for optimum performance.");
+                eprintln!("See \"ash gc\" for manual housekeeping.");
+                Command::new(env::current_exe()?)
+                    .args(["gc", "--auto", "--no-detach"])
+                    .current_dir(&repo.path)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                return Ok(());
+            }
+            eprintln!("Auto packing the repository for optimum performance.");
+        }
+
+        let mut lock = Lockfile::new(git_path.join(GC_LOCK));
+        if lock.hold_for_update().is_err() {
+            if auto {
+                trace!("gc is already running, skipping automatic collection");
+                return Ok(());
+            }
+            return Err(Error::Lock(
+                "gc is already running in this repository (see ash verify-repo-locks)".to_string()
+            ));
+        }
+        let result = Self::collect(&mut repo, &git_path, prune, options);
+        let _ = lock.rollback();
+        result
+    }
+
+    fn collect(repo: &mut Repository, git_path: &Path, prune: Option<&str>, options: IndexOptions) -> Result<(), Error> {
+        let config = Config::load(git_path)?;
+
+        let expire = prune.map(str::to_string)
+            .or_else(|| config.get("gc.pruneExpire"))
+            .unwrap_or_else(|| DEFAULT_PRUNE_EXPIRE.to_string());
+        let now = SystemTime::now();
+        let cutoff = Self::parse_expiry(&expire, now)?;
+
+        let roots = Self::roots(repo, git_path)?;
+        let reachable = repo.database.connected_objects(&roots);
+        trace!("{} objects reachable from {} roots", reachable.len(), roots.len());
+
+        // Unreachable objects in packs are written out loose, so they get
+        // the same grace period as any other, unless it is already over
+        let keep_unreachable = cutoff.is_none_or(|cutoff| cutoff < now);
+        let old_packs: Vec<_> = repo.database.packs().iter().map(|pack| pack.path().to_path_buf()).collect();
+        if keep_unreachable {
+            let packed: Vec<String> = repo.database.packs().iter()
+                .flat_map(|pack| pack.oids().iter().cloned())
+                .filter(|oid| !reachable.contains(oid))
+                .collect();
+            for oid in packed {
+                let (obj_type, data) = repo.database.load_raw(&oid)?;
+                repo.database.store_raw(&obj_type, &data)?;
+            }
+        }
+
+        let loose = repo.database.loose_oids()?;
+        let mut pack = None;
+        let mut keep: Vec<String> = reachable.into_iter().collect();
+        keep.sort();
+        if !keep.is_empty() {
+            let summary = repo.database.write_pack(&keep)?;
+            let name = summary.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            println!("Packed {} objects ({} as deltas) into {}", summary.objects, summary.deltas, name);
+
+            for pack in old_packs.iter().filter(|path| **path != summary.path) {
+                RepackCommand::remove_pack(pack)?;
+                trace!("Removed {}", pack.display());
+            }
+            repo.database.reload_packs();
+            pack = Some(summary.path);
+        }
+
+        let mut pruned = 0;
+        for oid in &loose {
+            if keep.binary_search(oid).is_ok() {
+                repo.database.remove_loose(oid)?;
+                continue;
+            }
+            let expired = match cutoff {
+                Some(cutoff) => repo.database.loose_modified(oid)? <= cutoff,
+                None => false,
+            };
+            if expired {
+                repo.database.remove_loose(oid)?;
+                pruned += 1;
+            }
+        }
+        if pruned > 0 {
+            println!("Pruned {} unreachable objects", pruned);
+        }
+
+        let packed_refs = repo.refs.pack_refs()?;
+        if !packed_refs.is_empty() {
+            println!("Packed {} refs", packed_refs.len());
+        }
+
+        let write_commit_graph = options.write_commit_graph
+            .or_else(|| config.get_bool(WRITE_COMMIT_GRAPH_KEY))
+            .unwrap_or(true);
+        let write_bitmaps = options.write_bitmaps.or_else(|| config.get_bool(WRITE_BITMAPS_KEY)).unwrap_or(false);
+        RepackCommand::write_indexes(repo, pack.as_deref(), write_commit_graph, write_bitmaps)
+    }
+
+    /// Start an automatic collection if the repository needs one, after a
+    /// command that adds objects. A failure is reported but does not
+    /// fail the command.
+    pub fn run_auto() {
+        if let Err(e) = Self::execute(None, true, None, IndexOptions::default()) {
+            eprintln!("warning: automatic gc failed: {}", e);
+        }
+    }
+
+    // Whether `gc --auto` should collect: too many loose objects, estimated
+    // from one fan-out directory, or too many packs
+    fn needs_collecting(git_path: &Path, config: &Config) -> Result<bool, Error> {
+        let setting = |key: &str, default: i64| -> Result<i64, Error> {
+            config.get(key)
+                .map(|value| value.parse::<i64>()
+                    .map_err(|_| Error::Generic(format!("bad numeric config value '{}' for '{}'", value, key))))
+                .transpose()
+                .map(|value| value.unwrap_or(default))
+        };
+        let auto = setting(AUTO_KEY, DEFAULT_AUTO)?;
+        if auto <= 0 {
+            return Ok(false);
+        }
+
+        let objects = git_dir::objects(git_path);
+        let loose = fs::read_dir(objects.join(SAMPLE_DIR)).into_iter().flatten().flatten()
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.len() == 38 && name.chars().all(|c| c.is_ascii_hexdigit())
+            })
+            .count() as i64;
+        // The threshold over the 256 directories, rounded up
+        if loose > (auto + 255) / 256 {
+            return Ok(true);
+        }
+
+        let pack_limit = setting(AUTO_PACK_LIMIT_KEY, DEFAULT_AUTO_PACK_LIMIT)?;
+        let packs = fs::read_dir(objects.join("pack")).into_iter().flatten().flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "pack"))
+            .count() as i64;
+        Ok(pack_limit > 0 && packs > pack_limit)
+    }
+
+    // Objects that must survive: what refs and HEAD point at, anything
+    // named by a state file such as ORIG_HEAD, MERGE_HEAD or AUTOSTASH, by
+    // a reflog or a sequencer, and the blobs staged in the index. Each
+    // linked worktree has HEAD, the index, reflogs and state files of its
+    // own in its administrative directory, which count the same way.
+    fn roots(repo: &mut Repository, git_path: &Path) -> Result<Vec<String>, Error> {
+        let mut roots: Vec<String> = repo.refs.read_head()?.into_iter().collect();
+        for reference in repo.refs.list_refs_under("refs")? {
+            if let Reference::Symbolic(name) = reference {
+                roots.extend(repo.refs.read_ref(&name)?);
+            }
+        }
+
+        let mut dirs = vec![git_dir::common(git_path)];
+        dirs.extend(Worktree::list(git_path)?.into_iter().map(|worktree| worktree.admin_path));
+
+        let mut files = Vec::new();
+        for dir in &dirs {
+            for entry in fs::read_dir(dir)?.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if entry.path().is_file() && name.chars().all(|c| c.is_ascii_uppercase() || c == '_') {
+                    files.push(entry.path());
+                }
+            }
+            Self::collect_files(&dir.join(LOGS_DIR), &mut files);
+            Self::collect_files(&dir.join(SEQUENCER_DIR), &mut files);
+        }
+
+        for file in files {
+            let content = fs::read_to_string(&file).unwrap_or_default();
+            roots.extend(content.split_whitespace()
+                .filter(|word| word.len() == 40 && word.chars().all(|c| c.is_ascii_hexdigit()))
+                .filter(|oid| repo.database.exists(oid))
+                .map(str::to_string));
+        }
+
+        for dir in &dirs {
+            let mut index = Index::new(dir.join(INDEX_FILE));
+            if index.get_pathname().exists() {
+                index.load()?;
+                roots.extend(index.each_entry().map(|entry| entry.get_oid().to_string()));
+            }
+        }
+
+        roots.sort();
+        roots.dedup();
+        Ok(roots)
+    }
+
+    fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                Self::collect_files(&path, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    // `now`, `never`, or a relative date such as `2.weeks.ago` or
+    // `3 days ago`; None means nothing ever expires
+    pub fn parse_expiry(value: &str, now: SystemTime) -> Result<Option<SystemTime>, Error> {
+        match value {
+            "never" | "false" => return Ok(None),
+            "now" | "all" => return Ok(Some(now)),
+            _ => {},
+        }
+
+        let invalid = || Error::Generic(format!("unsupported expiry date '{}'", value));
+        let parts: Vec<&str> = value.split(['.', ' ']).filter(|part| !part.is_empty()).collect();
+        let (count, unit) = match parts.as_slice() {
+            [count, unit, "ago"] => (count.parse::<u64>().map_err(|_| invalid())?, *unit),
+            _ => return Err(invalid()),
+        };
+
+        let seconds = match unit.trim_end_matches('s') {
+            "second" => 1,
+            "minute" => 60,
+            "hour" => 60 * 60,
+            "day" => 24 * 60 * 60,
+            "week" => 7 * 24 * 60 * 60,
+            "month" => 30 * 24 * 60 * 60,
+            "year" => 365 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        Ok(Some(now.checked_sub(Duration::from_secs(count * seconds)).unwrap_or(SystemTime::UNIX_EPOCH)))
+    }
+}
@@ -0,0 +1,190 @@
+// src/commands/grep.rs - search the contents of tracked files
+use std::path::PathBuf;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::core::color::Color;
+use crate::core::diff::myers::is_binary_content;
+use crate::core::pager::Pager;
+use crate::core::path_filter::PathFilter;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+/// How lines are matched and what is printed for them
+#[derive(Debug, Clone, Default)]
+pub struct GrepOptions {
+    pub line_number: bool,
+    pub ignore_case: bool,
+    pub invert: bool,
+    pub word: bool,
+    pub fixed: bool,
+    pub files_with_matches: bool,
+    pub count: bool,
+    pub cached: bool,
+}
+
+pub struct GrepCommand;
+
+impl GrepCommand {
+    /// Print the lines matching any of `patterns` in the tracked files of
+    /// the working tree, of the index with `--cached`, or of each tree or
+    /// commit in `args`; the other `args`, and all of `paths`, limit the
+    /// search to those paths. Returns whether anything matched.
+    pub fn execute(patterns: &[String], args: &[String], paths: &[String], options: &GrepOptions) -> Result<bool, Error> {
+        let mut repo = Repository::new(".")?;
+        let regex = Self::build_regex(patterns, options)?;
+
+        // Arguments before `--` are trees while they name one, paths after
+        let mut trees = Vec::new();
+        let mut pathspecs: Vec<String> = Vec::new();
+        for arg in args {
+            match Self::resolve_tree(&mut repo, arg) {
+                Some(oid) if pathspecs.is_empty() => trees.push((arg.clone(), oid)),
+                _ => {
+                    if !repo.path.join(arg).exists() {
+                        return Err(Error::Generic(format!(
+                            "ambiguous argument '{}': unknown revision or path not in the working tree.\n\
+                             Use '--' to separate paths from revisions, like this:\n\
+                             'ash grep <pattern> [<rev>...] -- [<file>...]'",
+                            arg
+                        )));
+                    }
+                    pathspecs.push(arg.clone());
+                },
+            }
+        }
+        pathspecs.extend(paths.iter().cloned());
+        let pathspecs: Vec<String> = pathspecs.iter()
+            .map(|path| path.trim_start_matches("./").trim_end_matches('/').to_string())
+            .collect();
+        if options.cached && !trees.is_empty() {
+            return Err(Error::Generic("--cached cannot be used with a tree".to_string()));
+        }
+
+        // What to search: a display prefix, a path and how to get its content
+        let mut files: Vec<(String, String, Source)> = Vec::new();
+        if trees.is_empty() {
+            repo.index.load()?;
+            for entry in repo.index.each_entry() {
+                if !Self::matches_pathspec(&entry.path, &pathspecs) {
+                    continue;
+                }
+                let source = if options.cached { Source::Blob(entry.oid.clone()) } else { Source::Workspace };
+                files.push((String::new(), entry.path.clone(), source));
+            }
+        } else {
+            let filter = PathFilter::build(&pathspecs.iter().filter(|path| !path.is_empty() && *path != ".").map(PathBuf::from).collect::<Vec<_>>());
+            for (name, oid) in &trees {
+                let mut entries: Vec<(String, String)> = repo.database.tree_diff(None, Some(oid), &filter)?
+                    .into_iter()
+                    .filter_map(|(path, (_, entry))| entry.map(|entry| (path.to_string_lossy().to_string(), entry.get_oid().to_string())))
+                    .filter(|(path, _)| Self::matches_pathspec(path, &pathspecs))
+                    .collect();
+                entries.sort();
+                for (path, oid) in entries {
+                    files.push((format!("{}:", name), path, Source::Blob(oid)));
+                }
+            }
+        }
+        if trees.is_empty() {
+            files.sort_by(|a, b| a.1.cmp(&b.1));
+        }
+
+        let mut pager = Pager::new();
+        pager.start()?;
+        let mut found = false;
+        for (prefix, path, source) in files {
+            if pager.exited_early() {
+                break;
+            }
+            let content = match source {
+                Source::Workspace => match repo.workspace.read_file(&PathBuf::from(&path)) {
+                    Ok(content) => content,
+                    // Deleted from the working tree, so nothing to search
+                    Err(_) => continue,
+                },
+                Source::Blob(oid) => match repo.database.load_raw(&oid)? {
+                    (obj_type, data) if obj_type == "blob" => data,
+                    // Submodules have no content here
+                    _ => continue,
+                },
+            };
+            let name = Color::magenta(&format!("{}{}", prefix, path));
+            found |= Self::search(&mut pager, &name, &content, &regex, options)?;
+        }
+        pager.close()?;
+        Ok(found)
+    }
+
+    // Print what `options` ask for about the lines of `content` that match
+    fn search(pager: &mut Pager, name: &str, content: &[u8], regex: &Regex, options: &GrepOptions) -> Result<bool, Error> {
+        let text = String::from_utf8_lossy(content);
+        let matches: Vec<(usize, &str)> = text.lines()
+            .enumerate()
+            .filter(|(_, line)| regex.is_match(line) != options.invert)
+            .collect();
+        if matches.is_empty() {
+            return Ok(false);
+        }
+
+        let separator = Color::cyan(":");
+        if options.files_with_matches {
+            pager.write(&format!("{}\n", name))?;
+        } else if options.count {
+            pager.write(&format!("{}{}{}\n", name, separator, matches.len()))?;
+        } else if is_binary_content(content) {
+            pager.write(&format!("Binary file {} matches\n", name))?;
+        } else {
+            for (number, line) in matches {
+                let line = if options.invert {
+                    line.to_string()
+                } else {
+                    regex.replace_all(line, |captures: &regex::Captures| Color::red(&Color::bold(&captures[0]))).to_string()
+                };
+                if options.line_number {
+                    pager.write(&format!("{}{}{}{}{}\n", name, separator, Color::green(&(number + 1).to_string()), separator, line))?;
+                } else {
+                    pager.write(&format!("{}{}{}\n", name, separator, line))?;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    fn build_regex(patterns: &[String], options: &GrepOptions) -> Result<Regex, Error> {
+        let alternatives: Vec<String> = patterns.iter()
+            .map(|pattern| {
+                let pattern = if options.fixed { regex::escape(pattern) } else { pattern.clone() };
+                if options.word { format!(r"\b(?:{})\b", pattern) } else { format!("(?:{})", pattern) }
+            })
+            .collect();
+        RegexBuilder::new(&alternatives.join("|"))
+            .case_insensitive(options.ignore_case)
+            .build()
+            .map_err(|e| Error::Generic(format!("invalid pattern: {}", e)))
+    }
+
+    // The tree `name` stands for, if it names a commit or a tree
+    fn resolve_tree(repo: &mut Repository, name: &str) -> Option<String> {
+        for expected_type in [COMMIT, "tree"] {
+            if let Ok(oid) = Revision::new(repo, name).resolve(expected_type) {
+                return Some(oid);
+            }
+        }
+        None
+    }
+
+    // A path matches a pathspec naming it or a directory above it
+    fn matches_pathspec(path: &str, pathspecs: &[String]) -> bool {
+        pathspecs.is_empty() || pathspecs.iter().any(|spec| {
+            spec.is_empty() || spec == "." || path == spec || path.starts_with(&format!("{}/", spec))
+        })
+    }
+}
+
+// Where the content of a file to search comes from
+enum Source {
+    Workspace,
+    Blob(String),
+}
@@ -3,16 +3,25 @@ use crate::core::repository::repository::Repository;
 use crate::errors::error::Error;
 use crate::validators::path_validator::PathValidator;
 use crate::core::refs::Refs;
+use crate::core::config::Config;
+use crate::core::repository::{format, git_dir};
 use std::fs;
 use std::path::Path;
+use crate::trace;
 
 pub struct InitCommand;
 
-// Default branch name
-const DEFAULT_BRANCH: &str = "master";
+/// The branch HEAD points at in a new repository
+pub const DEFAULT_BRANCH: &str = "master";
 
 impl InitCommand {
-    pub fn execute(path: &str) -> Result<(), Error> {
+    /// Create a repository at `path`. A `bare` one keeps the metadata in
+    /// `path` itself and has no worktree.
+    pub fn execute(path: &str, separate_git_dir: Option<&str>, bare: bool) -> Result<(), Error> {
+        if bare && separate_git_dir.is_some() {
+            return Err(Error::Generic("options '--separate-git-dir' and '--bare' cannot be used together".to_string()));
+        }
+
         // Use the init-specific validator
         PathValidator::validate_for_init(path)?;
         
@@ -28,9 +37,25 @@ impl InitCommand {
             })?;
         }
         
+        // Keep the metadata outside the worktree, leaving a pointer file behind
+        if let Some(dir) = separate_git_dir {
+            let dir_path = Path::new(dir);
+            fs::create_dir_all(dir_path).map_err(|e| {
+                Error::DirectoryCreation(format!(
+                    "Failed to create directory '{}': {}",
+                    dir_path.display(),
+                    e
+                ))
+            })?;
+            let dir_path = dir_path.canonicalize().map_err(|e| {
+                Error::PathResolution(format!("Failed to resolve path '{}': {}", dir, e))
+            })?;
+            git_dir::write_pointer(path_obj, &dir_path)?;
+        }
+        
         // Initialize the repository
         let repo = Repository::new(path)?;
-        let git_path = repo.create_git_directory()?;
+        let git_path = if bare { repo.path.clone() } else { repo.create_git_directory()? };
         
         for dir in &["objects", "refs", "refs/heads"] {
             repo.create_directory(&git_path.join(dir))?;
@@ -39,9 +64,15 @@ impl InitCommand {
         // Initialize HEAD to point to master branch
         let refs = Refs::new(&git_path);
         let relative_path = format!("refs/heads/{}", DEFAULT_BRANCH);
-        refs.set_head(&relative_path, &format!("ref: {}", relative_path))?;
+        refs.set_head(&relative_path, &format!("ref: {}", relative_path), "")?;
+
+        let mut config = Config::load(&git_path)?;
+        format::initialize(&mut config)?;
+        if bare {
+            config.add("core.bare", "true")?;
+        }
 
-        println!("Initialized empty Ash repository in {}", git_path.display());
+        trace!("Initialized empty Ash repository in {}", git_path.display());
         Ok(())
     }
 }
\ No newline at end of file
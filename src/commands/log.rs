@@ -1,16 +1,28 @@
 // src/commands/log.rs with all fixes applied
 use std::time::Instant;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+use regex::{Regex, RegexBuilder};
+
 use crate::errors::error::Error;
+use crate::core::attributes::Attributes;
+use crate::core::blame::{Blame, LineChange};
 use crate::core::color::Color;
 use crate::core::pager::Pager;
 use crate::core::database::database::Database;
 use crate::core::database::commit::Commit;
+use crate::core::diff::{combined, diff};
+use crate::core::diff::myers::{diff_lines, Edit};
+use crate::core::diff::stat::{format_stat, stat_trees, summary_line};
+use crate::core::graph::Graph;
 use crate::core::path_filter::PathFilter;
 use crate::core::refs::{Refs, Reference};
-use crate::core::revision::Revision;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT, HEAD};
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::core::repository::git_dir;
 
 pub struct LogCommand;
 
@@ -20,56 +32,67 @@ impl LogCommand {
         
         // Initialize repository components
         let root_path = std::path::Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Verify .ash directory exists
         if !git_path.exists() {
             return Err(Error::Generic("Not an ash repository (or any of the parent directories): .ash directory not found".into()));
         }
         
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let refs = Refs::new(&git_path);
+        let attributes = Attributes::new(root_path);
         
         // Parse options
         let abbrev = options.get("abbrev").map_or(false, |v| v == "true");
         let format_default = "medium".to_string();
         let format = options.get("format").unwrap_or(&format_default);
         let patch = options.get("patch").map_or(false, |v| v == "true");
+        // How merge commits are diffed: "off" (default), "separate" (-m) or "cc" (--cc)
+        let diff_merges_default = "off".to_string();
+        let diff_merges = options.get("diff_merges").unwrap_or(&diff_merges_default);
         let decorate_default = "auto".to_string();
         let decorate = options.get("decorate").unwrap_or(&decorate_default);
+        let graph = options.get("graph").is_some_and(|v| v == "true");
+        // "stat" for a line per changed file and the totals, "shortstat" for
+        // the totals only
+        let stat = options.get("stat").filter(|v| !v.is_empty());
         
         // Initialize pager for output
         let mut pager = Pager::new();
         pager.start()?;
         
-        // Determine the starting commit - Use HEAD if no revision is specified
-        let head_oid = if revisions.is_empty() {
-            refs.read_head()?.ok_or_else(|| Error::Generic("No HEAD commit found. Repository may be empty.".to_string()))?
-        } else {
-            // Resolve the requested revision to a commit ID
-            let mut repo = crate::core::repository::repository::Repository::new(".")?;
-            let mut revision = Revision::new(&mut repo, &revisions[0]);
-            revision.resolve("commit")?
+        // Arguments naming files limit the log to commits changing them,
+        // the others are revisions and ranges; those after "--" are paths
+        // whether the files are still there or not
+        let (revisions, paths) = match revisions.iter().position(|arg| arg == "--") {
+            Some(at) => (&revisions[..at], &revisions[at + 1..]),
+            None => (revisions, &[] as &[String]),
         };
-        
-        // Check for path filtering
         let mut path_filter = PathFilter::new();
-        let mut path_args = Vec::new();
-        
-        for arg in revisions {
-            let path = PathBuf::from(arg);
-            if path.exists() {
-                path_args.push(path);
-            }
-        }
-        
+        let path_args: Vec<PathBuf> = revisions.iter()
+            .map(PathBuf::from)
+            .filter(|path| path.exists())
+            .chain(paths.iter().map(|path| PathBuf::from(path.trim_start_matches("./").trim_end_matches('/'))))
+            .collect();
         if !path_args.is_empty() {
             path_filter = PathFilter::build(&path_args);
         }
+        let mut walk_args = Self::resolve_revisions(revisions)?;
+        if walk_args.is_empty() {
+            walk_args.push(refs.read_head()?.ok_or_else(|| Error::Generic("No HEAD commit found. Repository may be empty.".to_string()))?);
+        }
+        walk_args.push("--".to_string());
+        walk_args.extend(path_args.iter().map(|path| path.to_string_lossy().to_string()));
+
+        let walk_options = Self::walk_options(options)?;
+        if walk_options.follow && path_args.len() != 1 {
+            return Err(Error::Generic("--follow requires exactly one pathspec".to_string()));
+        }
         
         // Build reverse ref map for decoration if needed
         let reverse_refs = if decorate != "no" {
-            build_reverse_refs(&refs)?
+            build_reverse_refs(&refs, &mut database)?
         } else {
             HashMap::new()
         };
@@ -80,88 +103,142 @@ impl LogCommand {
         } else {
             Reference::Direct(String::new())
         };
+
+        // -L follows lines rather than walking every commit
+        if let Some(spec) = options.get("line-range") {
+            if !path_args.is_empty() {
+                return Err(Error::Generic("-L<range>:<file> cannot be used with pathspec".to_string()));
+            }
+            let (path, first, last) = Self::parse_line_range(spec)?;
+            let start = walk_args.iter()
+                .find(|arg| !arg.starts_with('^') && *arg != "--")
+                .cloned()
+                .unwrap_or_default();
+            let changes = Blame::new(&mut database).line_history(&start, &path, first, last)?;
+            for (position, change) in changes.iter().enumerate() {
+                if position > 0 && format != "oneline" {
+                    pager.write("\n")?;
+                }
+                let commit = database.load(&change.commit)?.as_any().downcast_ref::<Commit>().cloned()
+                    .ok_or_else(|| Error::Generic(format!("Object {} is not a commit", change.commit)))?;
+                if format == "oneline" {
+                    show_commit_oneline(&mut pager, &commit, abbrev, decorate, &reverse_refs, &current_ref, false)?;
+                } else {
+                    show_commit_medium(&mut pager, &commit, abbrev, decorate, &reverse_refs, &current_ref, false)?;
+                    pager.write("\n")?;
+                }
+                show_line_change(&mut pager, change)?;
+                if pager.exited_early() {
+                    break;
+                }
+            }
+            pager.close()?;
+            return Ok(());
+        }
+
+        let commits = RevList::new(&mut database, &refs, &walk_args, false)?.commits(&walk_options)?;
         
-        // Iterate through history beginning with the start commit
-        let mut oid = head_oid;
+        // With --graph each commit's output is written out line by line
+        // after the graph, which only links commits that are both shown
+        let mut lanes = Graph::new();
+        let shown: HashSet<String> = commits.iter()
+            .filter_map(|walked| walked.commit.get_oid().cloned())
+            .collect();
+
         let mut first = true;
-        
-        while !oid.is_empty() {
-            let commit_obj = database.load(&oid)?;
-            let commit = match commit_obj.as_any().downcast_ref::<Commit>() {
-                Some(c) => c,
-                None => return Err(Error::Generic(format!("Object {} is not a commit", oid))),
-            };
+        for (position, walked) in commits.iter().enumerate() {
+            let commit = &walked.commit;
+            let oid = commit.get_oid().cloned().unwrap_or_default();
+
+            // Add a blank line between commits except before the first one;
+            // the graph puts it after each commit but the last instead
+            if !first && format != "oneline" && !graph {
+                pager.write("\n")?;
+            }
+            first = false;
+            if graph {
+                let parents: Vec<String> = commit.get_parents().into_iter()
+                    .filter(|parent| shown.contains(parent))
+                    .collect();
+                lanes.commit(&oid, &parents, walked.boundary);
+                pager.begin_capture();
+            }
+            // The graph marks boundary commits itself
+            let boundary = walked.boundary && !graph;
             
-            // Check if commit affects any of the filtered paths
-            let commit_affects_paths = if !path_args.is_empty() {
-                // Get parent commit
-                let parent_oid = commit.get_parent();
-                
-                // Get diff between this commit and parent
-                let diff = database.tree_diff(
-                    parent_oid.as_deref().map(|s| s.as_str()), 
-                    Some(&oid), 
-                    &path_filter
-                )?;
-                
-                // If diff is empty, this commit doesn't affect any of the paths
-                !diff.is_empty()
-            } else {
-                // No path filtering, show all commits
-                true
-            };
+            // Display the commit based on format
+            match format.as_str() {
+                "oneline" => {
+                    show_commit_oneline(&mut pager, commit, abbrev, decorate, &reverse_refs, &current_ref, boundary)?;
+                },
+                _ => { // medium (default) format
+                    show_commit_medium(&mut pager, commit, abbrev, decorate, &reverse_refs, &current_ref, boundary)?;
+                }
+            }
             
-            // Only show commit if it affects the filtered paths
-            if commit_affects_paths {
-                // Add a blank line between commits except before the first one
-                if !first && format != "oneline" {
+            if let Some(stat) = stat.filter(|_| !walked.boundary) {
+                show_stat(&mut pager, &mut database, commit, stat, diff_merges, format != "oneline", &path_filter)?;
+            }
+
+            // Show patch if requested; boundary commits are only context
+            if patch && !walked.boundary {
+                if format != "oneline" {
                     pager.write("\n")?;
                 }
-                first = false;
                 
-                // Display the commit based on format
-                match format.as_str() {
-                    "oneline" => {
-                        show_commit_oneline(&mut pager, commit, abbrev, decorate, &reverse_refs, &current_ref)?;
-                    },
-                    _ => { // medium (default) format
-                        show_commit_medium(&mut pager, commit, abbrev, decorate, &reverse_refs, &current_ref)?;
-                    }
-                }
-                
-                // Show patch if requested
-                if patch {
-                    if format != "oneline" {
-                        pager.write("\n")?;
+                // Get diff with possible path filtering
+                let parents = commit.get_parents();
+                if parents.len() > 1 {
+                    match diff_merges.as_str() {
+                        "separate" => {
+                            for parent in &parents {
+                                pager.write(&format!("(from {})\n", parent))?;
+                                show_patch(&mut pager, &mut database, &attributes, Some(parent), &oid, &path_filter)?;
+                            }
+                        },
+                        "cc" => {
+                            show_combined_patch(&mut pager, &mut database, &attributes, &parents, &oid, &path_filter)?;
+                        },
+                        _ => {}
                     }
-                    
-                    // Get diff with possible path filtering
-                    let parent_oid = commit.get_parent();
+                } else {
                     show_patch(
                         &mut pager, 
                         &mut database, 
-                        parent_oid.as_deref().map(|s| s.as_str()), 
+                        &attributes,
+                        parents.first().map(|s| s.as_str()), 
                         &oid, 
                         &path_filter
                     )?;
                 }
             }
-            
-            // Move to parent commit
-            if let Some(parent) = commit.get_parent() {
-                oid = parent.clone();
-            } else {
-                break;
+
+            if graph {
+                if format != "oneline" && position + 1 < commits.len() {
+                    pager.write("\n")?;
+                }
+                let output = pager.end_capture();
+                for line in output.split_inclusive('\n') {
+                    let prefix = lanes.next_prefix();
+                    if line == "\n" {
+                        pager.write(&format!("{}\n", prefix.trim_end()))?;
+                    } else {
+                        pager.write(&format!("{}{}", prefix, line))?;
+                    }
+                }
+                for row in lanes.remaining() {
+                    pager.write(&format!("{}\n", row))?;
+                }
             }
             
             // Check if the pager was closed by the user
-            if !pager.is_enabled() {
+            if pager.exited_early() {
                 break;
             }
         }
         
         // Display timing info
-        if pager.is_enabled() {
+        if pager.is_enabled() && !crate::core::trace::is_quiet() {
             let elapsed = start_time.elapsed();
             pager.write(&format!("\n{}\n", Color::cyan(&format!("Log completed in {:.2}s", elapsed.as_secs_f32()))))?;
         }
@@ -171,10 +248,143 @@ impl LogCommand {
         
         Ok(())
     }
+
+    // The revisions among `args` as RevList takes them, every name resolved
+    // to a commit id: "A..B" becomes "^A" and "B", and "^A" stays excluded
+    // The traversal controls and limits in `options`
+    fn walk_options(options: &HashMap<String, String>) -> Result<WalkOptions, Error> {
+        let flag = |name: &str| options.get(name).is_some_and(|v| v == "true");
+        let count = |name: &str| -> Result<Option<usize>, Error> {
+            options.get(name)
+                .map(|value| value.parse::<usize>()
+                    .map_err(|_| Error::Generic(format!("'{}' is not a number", value))))
+                .transpose()
+        };
+        let date = |names: [&str; 2]| -> Result<Option<i64>, Error> {
+            names.iter().find_map(|name| options.get(*name)).map(|value| Self::parse_date(value)).transpose()
+        };
+        let ignore_case = flag("regexp-ignore-case");
+        let patterns = |name: &str| -> Result<Vec<Regex>, Error> {
+            options.get(name).into_iter()
+                .flat_map(|patterns| patterns.lines())
+                .map(|pattern| RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .map_err(|e| Error::Generic(format!("invalid regex '{}' for --{}: {}", pattern, name, e))))
+                .collect()
+        };
+
+        Ok(WalkOptions {
+            reverse: flag("reverse"),
+            boundary: flag("boundary"),
+            ancestry_path: flag("ancestry_path"),
+            max_count: count("max-count")?.or(count("n")?),
+            skip: count("skip")?.unwrap_or(0),
+            since: date(["since", "after"])?,
+            until: date(["until", "before"])?,
+            authors: patterns("author")?,
+            committers: patterns("committer")?,
+            grep: patterns("grep")?,
+            all_match: flag("all-match"),
+            topo_order: flag("graph"),
+            follow: flag("follow"),
+        })
+    }
+
+    // `<start>,<end>:<file>` as the file and its lines from 0, the end
+    // None for the end of the file: either number may be left out, and
+    // the end may be `+<count>` lines from the start
+    fn parse_line_range(spec: &str) -> Result<(String, usize, Option<usize>), Error> {
+        let invalid = || Error::Generic(format!("-L argument not 'start,end:file': '{}'", spec));
+        let (range, path) = spec.split_once(':').ok_or_else(invalid)?;
+        let (start, end) = range.split_once(',').ok_or_else(invalid)?;
+        if path.is_empty() {
+            return Err(invalid());
+        }
+
+        let number = |value: &str| value.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(invalid);
+        let start = if start.is_empty() { 1 } else { number(start)? };
+        let end = match end {
+            "" => None,
+            count if count.starts_with('+') => Some(start + number(&count[1..])? - 1),
+            end => Some(number(end)?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok((path.trim_start_matches("./").to_string(), start - 1, end.map(|end| end - 1)))
+    }
+
+    // A date as --since and --until take it: seconds since the epoch
+    // (`@<seconds>`), `YYYY-MM-DD[ HH:MM[:SS]]` in local time, `now`,
+    // `yesterday`, or a relative date such as `2.weeks.ago` or `3 days ago`
+    fn parse_date(value: &str) -> Result<i64, Error> {
+        let now = Local::now();
+        let invalid = || Error::Generic(format!("invalid date '{}'", value));
+        match value {
+            "now" => return Ok(now.timestamp()),
+            "yesterday" => return Ok(now.timestamp() - 24 * 60 * 60),
+            _ => {},
+        }
+        if let Some(seconds) = value.strip_prefix('@') {
+            return seconds.parse::<i64>().map_err(|_| invalid());
+        }
+
+        for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(value, format) {
+                return Local.from_local_datetime(&time).earliest().map(|time| time.timestamp()).ok_or_else(invalid);
+            }
+        }
+        if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            let time = day.and_hms_opt(0, 0, 0).ok_or_else(invalid)?;
+            return Local.from_local_datetime(&time).earliest().map(|time| time.timestamp()).ok_or_else(invalid);
+        }
+
+        let parts: Vec<&str> = value.split(['.', ' ']).filter(|part| !part.is_empty()).collect();
+        let (count, unit) = match parts.as_slice() {
+            [count, unit, "ago"] => (count.parse::<i64>().map_err(|_| invalid())?, *unit),
+            _ => return Err(invalid()),
+        };
+        let seconds = match unit.trim_end_matches('s') {
+            "second" => 1,
+            "minute" => 60,
+            "hour" => 60 * 60,
+            "day" => 24 * 60 * 60,
+            "week" => 7 * 24 * 60 * 60,
+            "month" => 30 * 24 * 60 * 60,
+            "year" => 365 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        Ok(now.timestamp() - count * seconds)
+    }
+
+    fn resolve_revisions(args: &[String]) -> Result<Vec<String>, Error> {
+        let mut repo = Repository::new(".")?;
+        let mut resolve = |name: &str| -> Result<String, Error> {
+            let name = if name.is_empty() { HEAD } else { name };
+            Revision::new(&mut repo, name).resolve(COMMIT)
+        };
+
+        let mut resolved = Vec::new();
+        for arg in args {
+            if PathBuf::from(arg).exists() {
+                continue;
+            }
+            if let Some((start, end)) = arg.split_once("..") {
+                resolved.push(format!("^{}", resolve(start)?));
+                resolved.push(resolve(end)?);
+            } else if let Some(excluded) = arg.strip_prefix('^') {
+                resolved.push(format!("^{}", resolve(excluded)?));
+            } else {
+                resolved.push(resolve(arg)?);
+            }
+        }
+        Ok(resolved)
+    }
 }
 
 // Helper function to build a map from commit OIDs to the refs that point to them
-fn build_reverse_refs(refs: &Refs) -> Result<HashMap<String, Vec<Reference>>, Error> {
+fn build_reverse_refs(refs: &Refs, database: &mut Database) -> Result<HashMap<String, Vec<Reference>>, Error> {
     let mut reverse_refs = HashMap::new();
     
     // Get current HEAD reference
@@ -192,18 +402,29 @@ fn build_reverse_refs(refs: &Refs) -> Result<HashMap<String, Vec<Reference>>, Er
             }
         }
     }
+
+    // Tags decorate the commit they finally point at
+    for tag_ref in refs.list_refs_under("refs/tags")? {
+        if let Reference::Symbolic(path) = &tag_ref {
+            if let Ok(Some(oid)) = refs.read_ref(path) {
+                let oid = database.peel(&oid)?;
+                reverse_refs.entry(oid).or_insert_with(Vec::new).push(tag_ref.clone());
+            }
+        }
+    }
     
     Ok(reverse_refs)
 }
 
 // Display a commit in the medium format (default)
-fn show_commit_medium(
+pub fn show_commit_medium(
     pager: &mut Pager,
     commit: &Commit,
     abbrev: bool,
     decorate: &str,
     reverse_refs: &HashMap<String, Vec<Reference>>,
-    current_ref: &Reference
+    current_ref: &Reference,
+    boundary: bool
 ) -> Result<(), Error> {
     // Format the commit ID
     let oid = if abbrev {
@@ -223,8 +444,16 @@ fn show_commit_medium(
     };
     
     // Display commit header
-    pager.write(&format!("{} {}{}\n", Color::yellow("commit"), oid, decoration))?;
-    
+    let marker = if boundary { "- " } else { "" };
+    pager.write(&format!("{} {}{}{}\n", Color::yellow("commit"), marker, oid, decoration))?;
+
+    // A merge lists its parents, however many it has
+    let parents = commit.get_parents();
+    if parents.len() > 1 {
+        let abbreviated: Vec<&str> = parents.iter().map(|parent| &parent[..parent.len().min(7)]).collect();
+        pager.write(&format!("Merge: {}\n", abbreviated.join(" ")))?;
+    }
+
     // Display author information
     if let Some(author) = commit.get_author() {
         pager.write(&format!("Author: {} <{}>\n", author.name, author.email))?;
@@ -247,7 +476,8 @@ fn show_commit_oneline(
     abbrev: bool,
     decorate: &str,
     reverse_refs: &HashMap<String, Vec<Reference>>,
-    current_ref: &Reference
+    current_ref: &Reference,
+    boundary: bool
 ) -> Result<(), Error> {
     // Format the commit ID
     let oid = if abbrev {
@@ -270,7 +500,8 @@ fn show_commit_oneline(
     let title = commit.title_line();
     
     // Display the single line - Fix: use &oid for Color::yellow
-    pager.write(&format!("{} {}{} {}\n", Color::yellow(&oid), decoration, "", title))?;
+    let marker = if boundary { "- " } else { "" };
+    pager.write(&format!("{}{} {}{} {}\n", marker, Color::yellow(&oid), decoration, "", title))?;
     
     Ok(())
 }
@@ -292,6 +523,7 @@ fn format_decoration(
             // Format each ref name
             let mut ref_names = Vec::new();
             let mut has_head = false;
+            let mut head_shown = false;
             
             for reference in refs {
                 match reference {
@@ -301,6 +533,12 @@ fn format_decoration(
                             has_head = true;
                             continue;
                         }
+
+                        if let Some(tag) = path.strip_prefix("refs/tags/") {
+                            let name = if decorate == "full" { path.as_str() } else { tag };
+                            ref_names.push(Color::yellow(&format!("tag: {}", name)));
+                            continue;
+                        }
                         
                         // Format branch name
                         let name = if decorate == "full" {
@@ -317,6 +555,7 @@ fn format_decoration(
                         // Check if this is the current branch
                         if current_ref == reference {
                             if has_head {
+                                head_shown = true;
                                 ref_names.push(format!("{} -> {}", 
                                     Color::cyan("HEAD"), 
                                     Color::green(&name)));
@@ -336,9 +575,9 @@ fn format_decoration(
                 }
             }
             
-            // If HEAD points to this commit but we have no branch to annotate
-            if has_head && ref_names.is_empty() {
-                ref_names.push(Color::cyan("HEAD"));
+            // If HEAD points to this commit but not through a branch shown here
+            if has_head && !head_shown {
+                ref_names.insert(0, Color::cyan("HEAD"));
             }
             
             // Format the final decoration
@@ -351,10 +590,53 @@ fn format_decoration(
     String::new()
 }
 
-// Display the diff for a commit
-fn show_patch(
+// Display how many lines each file changed in `commit` gained and lost,
+// then the totals, or with "shortstat" the totals only. Merges are only
+// counted with -m, against each parent, or --cc, against the first.
+fn show_stat(
+    pager: &mut Pager,
+    database: &mut Database,
+    commit: &Commit,
+    stat: &str,
+    diff_merges: &str,
+    blank_line: bool,
+    path_filter: &PathFilter
+) -> Result<(), Error> {
+    let oid = commit.get_oid().cloned().unwrap_or_default();
+    let parents = commit.get_parents();
+    let bases: Vec<Option<&str>> = match (parents.len(), diff_merges) {
+        (0, _) => vec![None],
+        (1, _) | (_, "cc") => vec![Some(parents[0].as_str())],
+        (_, "separate") => parents.iter().map(|parent| Some(parent.as_str())).collect(),
+        _ => Vec::new(),
+    };
+
+    let separate = bases.len() > 1;
+    for base in bases {
+        let stats = stat_trees(database, base, Some(&oid), path_filter)?;
+        if stats.is_empty() {
+            continue;
+        }
+        if blank_line {
+            pager.write("\n")?;
+        }
+        if let Some(parent) = base.filter(|_| separate) {
+            pager.write(&format!("(from {})\n", parent))?;
+        }
+        if stat == "shortstat" {
+            pager.write(&format!("{}\n", summary_line(&stats)))?;
+        } else {
+            pager.write(&format_stat(&stats))?;
+        }
+    }
+    Ok(())
+}
+
+// Display the diff for a commit, files with a textconv driver converted
+pub fn show_patch(
     pager: &mut Pager,
     database: &mut Database,
+    attributes: &Attributes,
     parent_oid: Option<&str>,
     commit_oid: &str,
     path_filter: &PathFilter
@@ -404,8 +686,8 @@ fn show_patch(
             let old_obj = database.load(old.get_oid())?;
             let new_obj = database.load(new.get_oid())?;
             
-            let old_content = old_obj.to_bytes();
-            let new_content = new_obj.to_bytes();
+            let old_content = attributes.textconv(path, old_obj.to_bytes())?;
+            let new_content = attributes.textconv(path, new_obj.to_bytes())?;
             
             // Check if files are binary
             if is_binary_content(&old_content) || is_binary_content(&new_content) {
@@ -428,7 +710,7 @@ fn show_patch(
             
             // Load and check file content
             let old_obj = database.load(old.get_oid())?;
-            let old_content = old_obj.to_bytes();
+            let old_content = attributes.textconv(path, old_obj.to_bytes())?;
             
             // Check if file is binary
             if is_binary_content(&old_content) {
@@ -455,7 +737,7 @@ fn show_patch(
             
             // Load and check file content
             let new_obj = database.load(new.get_oid())?;
-            let new_content = new_obj.to_bytes();
+            let new_content = attributes.textconv(path, new_obj.to_bytes())?;
             
             // Check if file is binary
             if is_binary_content(&new_content) {
@@ -478,6 +760,99 @@ fn show_patch(
     Ok(())
 }
 
+// Show the combined diff of a merge commit against all of its parents.
+// Only files that differ from every parent are shown.
+pub fn show_combined_patch(
+    pager: &mut Pager,
+    database: &mut Database,
+    attributes: &Attributes,
+    parents: &[String],
+    commit_oid: &str,
+    path_filter: &PathFilter
+) -> Result<(), Error> {
+    let mut diffs = Vec::new();
+    for parent in parents {
+        diffs.push(database.tree_diff(Some(parent), Some(commit_oid), path_filter)?);
+    }
+    
+    let mut paths: Vec<&PathBuf> = diffs[0].keys()
+        .filter(|path| diffs.iter().all(|diff| diff.contains_key(*path)))
+        .collect();
+    paths.sort_by(|a, b| a.to_string_lossy().cmp(&b.to_string_lossy()));
+    
+    for path in paths {
+        let path_str = path.to_string_lossy();
+        pager.write(&format!("{}\n", Color::cyan(&format!("diff --cc {}", path_str))))?;
+        
+        let mut parent_contents = Vec::new();
+        let mut parent_oids = Vec::new();
+        for diff in &diffs {
+            match &diff[path].0 {
+                Some(old) => {
+                    parent_contents.push(attributes.textconv(path, database.load(old.get_oid())?.to_bytes())?);
+                    parent_oids.push(old.get_oid()[..7.min(old.get_oid().len())].to_string());
+                },
+                None => {
+                    parent_contents.push(Vec::new());
+                    parent_oids.push("0000000".to_string());
+                }
+            }
+        }
+        
+        let (new_content, new_oid) = match &diffs[0][path].1 {
+            Some(new) => (attributes.textconv(path, database.load(new.get_oid())?.to_bytes())?, new.get_oid()[..7.min(new.get_oid().len())].to_string()),
+            None => (Vec::new(), "0000000".to_string()),
+        };
+        
+        pager.write(&format!("index {}..{}\n", parent_oids.join(","), new_oid))?;
+        
+        if is_binary_content(&new_content) || parent_contents.iter().any(|c| is_binary_content(c)) {
+            pager.write(&format!("{}\n", Color::yellow("Binary files differ")))?;
+            continue;
+        }
+        
+        pager.write(&format!("--- a/{}\n", path_str))?;
+        pager.write(&format!("+++ b/{}\n", path_str))?;
+        
+        let parent_lines: Vec<Vec<String>> = parent_contents.iter()
+            .map(|content| diff::split_lines(&String::from_utf8_lossy(content)))
+            .collect();
+        let new_lines = diff::split_lines(&String::from_utf8_lossy(&new_content));
+        
+        let raw_diff = combined::combined_diff(&parent_lines, &new_lines, 3);
+        pager.write(&combined::colorize(&raw_diff, parent_lines.len()))?;
+    }
+    
+    Ok(())
+}
+
+// The range `log -L` follows as `change` left it, against its parent's
+fn show_line_change(pager: &mut Pager, change: &LineChange) -> Result<(), Error> {
+    pager.write(&format!("{}\n", Color::cyan(&format!("diff --ash a/{} b/{}", change.old_path, change.path))))?;
+    if change.old_lines.is_empty() && change.old_start == 0 {
+        pager.write("--- /dev/null\n")?;
+    } else {
+        pager.write(&format!("--- a/{}\n", change.old_path))?;
+    }
+    pager.write(&format!("+++ b/{}\n", change.path))?;
+
+    let start = |start: usize, len: usize| if len == 0 { start } else { start + 1 };
+    pager.write(&format!("{}\n", Color::cyan(&format!(
+        "@@ -{},{} +{},{} @@",
+        start(change.old_start, change.old_lines.len()), change.old_lines.len(),
+        start(change.new_start, change.new_lines.len()), change.new_lines.len(),
+    ))))?;
+    for edit in diff_lines(&change.old_lines, &change.new_lines) {
+        let line = match edit {
+            Edit::Equal(_, new) => format!(" {}", change.new_lines[new]),
+            Edit::Delete(old) => Color::red(&format!("-{}", change.old_lines[old])),
+            Edit::Insert(new) => Color::green(&format!("+{}", change.new_lines[new])),
+        };
+        pager.write(&format!("{}\n", line))?;
+    }
+    Ok(())
+}
+
 // Display a diff between two files
 fn display_diff(pager: &mut Pager, old_text: &str, new_text: &str) -> Result<(), Error> {
     // Split text into lines
@@ -4,100 +4,195 @@ use std::env;
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
 use crate::errors::error::Error;
+use crate::core::merge::bases::Bases;
 use crate::core::merge::inputs::Inputs;
+use crate::core::merge::diff3::Favor;
 use crate::core::merge::resolve::Resolve;
+use crate::core::merge::subtree;
 use crate::core::refs::Refs;
 use crate::core::database::database::Database;
 use crate::core::database::database::GitObject;
-use crate::core::database::commit::Commit;
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
 use crate::core::database::author::Author;
 use crate::core::path_filter::PathFilter;
 use crate::core::workspace::Workspace;
 use crate::core::database::tree::{Tree, TreeEntry};
 use crate::core::file_mode::FileMode;
 use crate::core::database::entry::DatabaseEntry;
+use crate::core::editor::Editor;
+use crate::core::repository::git_dir;
+use crate::core::repository::pending_commit::{PendingCommit, PendingCommitType};
+use crate::core::repository::autostash::{self, Autostash};
+use crate::core::repository::repository::Repository;
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::commands::reset::ORIG_HEAD;
+use crate::trace;
+
+
+/// How a merge settles the two sides: recursively, file by file, or by
+/// taking the whole tree of one side, or recursively with their side read
+/// into the subdirectory it is kept in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strategy {
+    #[default]
+    Recursive,
+    Ours,
+    Theirs,
+    Subtree,
+}
+
+impl Strategy {
+    fn name(&self) -> &'static str {
+        match self {
+            Strategy::Recursive => "recursive",
+            Strategy::Ours => "ours",
+            Strategy::Theirs => "theirs",
+            Strategy::Subtree => "subtree",
+        }
+    }
+}
+
+/// The strategy `ash merge -s` names and the `-X` options given to it
+#[derive(Debug, Clone, Default)]
+pub struct MergeStrategy {
+    pub strategy: Strategy,
+    /// The side that wins conflicting changes in a recursive merge
+    pub favor: Option<Favor>,
+    /// The directory their side is kept in, from `-X subtree=<path>`; a
+    /// subtree merge finds it itself without one
+    pub subtree: Option<String>,
+}
+
+impl MergeStrategy {
+    pub fn parse(name: Option<&str>, options: &[String]) -> Result<Self, Error> {
+        let strategy = match name {
+            None | Some("recursive") | Some("ort") => Strategy::Recursive,
+            Some("ours") => Strategy::Ours,
+            Some("theirs") => Strategy::Theirs,
+            Some("subtree") => Strategy::Subtree,
+            Some(name) => return Err(Error::Generic(format!(
+                "Could not find merge strategy '{}'.\nAvailable strategies are: ort ours recursive subtree theirs.", name
+            ))),
+        };
+        let mut favor = None;
+        let mut subtree = None;
+        for option in options {
+            match option.as_str() {
+                "ours" => favor = Some(Favor::Ours),
+                "theirs" => favor = Some(Favor::Theirs),
+                _ if option.starts_with("subtree=") => {
+                    subtree = Some(option["subtree=".len()..].trim_matches('/').to_string());
+                },
+                _ => return Err(Error::Generic(format!("unknown strategy option: -X{}", option))),
+            }
+        }
+        Ok(Self { strategy, favor, subtree })
+    }
 
-
-const MERGE_MSG: &str = "\
-Merge branch '%s'
-
-# Please enter a commit message to explain why this merge is necessary,
-# especially if it merges an updated upstream into a topic branch.
-#
-# Lines starting with '#' will be ignored, and an empty message aborts
-# the commit.
-";
+    // Whether their side is read into a subdirectory of ours
+    fn shifts(&self) -> bool {
+        self.strategy == Strategy::Subtree || self.subtree.is_some()
+    }
+}
 
 pub struct MergeCommand;
 
 impl MergeCommand {
-    pub fn execute(revision: &str, message: Option<&str>) -> Result<(), Error> {
+    /// Merge `revisions` into the current branch, more than one of them in
+    /// a single octopus merge. With `summary` the conflicts are reported as
+    /// one grouped list instead of a line each. With `squash` the merged
+    /// tree is left in the index and the working tree, with a message in
+    /// SQUASH_MSG, for `ash commit` to record as an ordinary commit.
+    pub fn execute(revisions: &[String], message: Option<&str>, autostash: Option<bool>, summary: bool, squash: bool, strategy: &MergeStrategy) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let stash = Autostash::for_repository(&repo);
+        let stashed = autostash::enabled(&repo, "merge", autostash)? && stash.save(&mut repo)?;
+
+        let result = match revisions {
+            [revision] => Self::merge(revision, message, summary, squash, strategy),
+            _ => Self::octopus(revisions, message, summary, squash, strategy),
+        };
+
+        if stashed {
+            match &result {
+                // Left pending until `merge --continue` or `merge --abort`
+                Err(e) if e.to_string().contains("fix conflicts") => {
+                    trace!("Local changes are stashed and will be reapplied once the merge is concluded.");
+                },
+                _ => stash.apply(&mut Repository::new(".")?)?,
+            }
+        }
+
+        result
+    }
+
+    fn merge(revision: &str, message: Option<&str>, summary: bool, squash: bool, strategy: &MergeStrategy) -> Result<(), Error> {
         let start_time = Instant::now();
 
-        println!("Merge started...");
+        trace!("Merge started...");
 
         // --- Debug: Print environment details ---
-        println!("==== Merge Environment Debug ====");
+        trace!("==== Merge Environment Debug ====");
         match std::env::current_dir() {
-            Ok(cwd) => println!("Current directory: {}", cwd.display()),
-            Err(e) => println!("Warning: Could not get current directory: {}", e),
+            Ok(cwd) => trace!("Current directory: {}", cwd.display()),
+            Err(e) => trace!("Warning: Could not get current directory: {}", e),
         }
          let repo_root_display = ".";
-         println!("Workspace root: {}", repo_root_display);
-         let git_dir_path = Path::new(repo_root_display).join(".ash");
-         println!("Git directory: {}", git_dir_path.display());
+         trace!("Workspace root: {}", repo_root_display);
+         let git_dir_path = git_dir::resolve(Path::new(repo_root_display));
+         trace!("Git directory: {}", git_dir_path.display());
          if git_dir_path.exists() {
-             println!("  Exists: true");
+             trace!("  Exists: true");
              if git_dir_path.is_dir() {
-                 println!("  Is directory: true");
+                 trace!("  Is directory: true");
                  match std::fs::metadata(&git_dir_path) {
                      Ok(meta) => {
                          #[cfg(unix)]
                          {
                              use std::os::unix::fs::PermissionsExt;
-                             println!("  Permissions: {:o}", meta.permissions().mode());
+                             trace!("  Permissions: {:o}", meta.permissions().mode());
                          }
                          #[cfg(not(unix))]
                          {
-                             println!("  Permissions: (Windows - check manually)");
+                             trace!("  Permissions: (Windows - check manually)");
                          }
                      }
-                     Err(e) => println!("  Warning: Could not get metadata: {}", e),
+                     Err(e) => trace!("  Warning: Could not get metadata: {}", e),
                  }
              } else {
-                 println!("  Is directory: false");
+                 trace!("  Is directory: false");
              }
          } else {
-             println!("  Exists: false");
+             trace!("  Exists: false");
          }
 
-         println!("\nContents of current directory:");
+         trace!("\nContents of current directory:");
          match std::fs::read_dir(".") {
              Ok(entries) => {
                  for entry_result in entries {
                      if let Ok(entry) = entry_result {
                          let path = entry.path();
                          let type_str = if path.is_dir() { "(directory)" } else if path.is_file() { "(file)" } else { "(other)" };
-                         println!("  {} {}", path.display(), type_str);
+                         trace!("  {} {}", path.display(), type_str);
                      }
                  }
              }
-             Err(e) => println!("  Warning: Could not read current directory contents: {}", e),
+             Err(e) => trace!("  Warning: Could not read current directory contents: {}", e),
          }
-         println!("================================");
+         trace!("================================");
          // --- End Debug ---
 
 
         // Initialize repository components
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
 
         if !git_path.exists() {
             return Err(Error::Generic("Not an ash repository (or any of the parent directories): .ash directory not found".into()));
         }
 
         let workspace = Workspace::new(root_path);
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = crate::core::index::index::Index::new(git_path.join("index"));
         let refs = Refs::new(&git_path);
 
@@ -116,16 +211,20 @@ impl MergeCommand {
                 Some(oid) => oid,
                 None => return Err(Error::Generic("No HEAD commit found. Create an initial commit first.".into())),
             };
+            // Lets `merge --abort` and `reset --hard ORIG_HEAD` get back here
+            std::fs::write(git_path.join(ORIG_HEAD), format!("{}\n", head_oid))?;
 
-            let inputs = Inputs::new(&mut database, &refs, "HEAD".to_string(), revision.to_string())?;
+            let mut inputs = Inputs::new(&mut database, &refs, "HEAD".to_string(), revision.to_string())?;
 
             if inputs.already_merged() {
                 println!("Already up to date.");
                 return Err(Error::Generic("Already up to date.".into())); // Use error channel for special messages
             }
 
-            if inputs.is_fast_forward() {
-                println!("Fast-forward possible.");
+            // A squash takes the other side's tree without moving HEAD to
+            // it, and a subtree merge keeps it in a directory of ours
+            if inputs.is_fast_forward() && !squash && !strategy.shifts() {
+                trace!("Fast-forward possible.");
                 // Pass mutable refs to database and index into fast forward
                 return Self::handle_fast_forward(
                     &mut database,
@@ -133,24 +232,60 @@ impl MergeCommand {
                     &mut index,
                     &refs,
                     &inputs.left_oid,
-                    &inputs.right_oid
+                    &inputs.right_oid,
+                    &format!("merge {}: Fast-forward", revision)
                 );
                 // NOTE: handle_fast_forward now handles its own index write/commit/rollback
             }
 
+            // Merged from the other side, ours comes out whole; merged from
+            // our own HEAD, theirs does
+            match strategy.strategy {
+                Strategy::Recursive | Strategy::Subtree => {},
+                Strategy::Ours => inputs.base_oids = vec![inputs.right_oid.clone()],
+                Strategy::Theirs => inputs.base_oids = vec![inputs.left_oid.clone()],
+            }
+            let shifted = if strategy.shifts() {
+                Some(Self::shift_inputs(&mut database, &inputs, strategy.subtree.as_deref())?)
+            } else {
+                None
+            };
+
             // --- Recursive Merge ---
-             println!("Performing recursive merge.");
-            let mut merge_resolver = Resolve::new(&mut database, &workspace, &mut index, &inputs);
-            merge_resolver.on_progress = |info| println!("{}", info);
+             trace!("Performing {} merge.", strategy.strategy.name());
+            let mut merge_resolver = Resolve::new(&mut database, &workspace, &mut index, shifted.as_ref().unwrap_or(&inputs));
+            merge_resolver.favor = strategy.favor;
+            merge_resolver.on_progress = if summary {
+                |info: String| if !Self::is_conflict_detail(&info) { println!("{}", info) }
+            } else {
+                |info: String| println!("{}", info)
+            };
 
              let merge_result = merge_resolver.execute();
+             let conflicts = merge_resolver.conflict_summary();
 
              if let Err(e) = merge_result {
                   if e.to_string().contains("Automatic merge failed") || e.to_string().contains("fix conflicts") {
+                       let conflicted = index.conflict_paths();
                        // Write index with conflicts before returning error
                        if !index.write_updates()? {
                            println!("Warning: Index with conflicts was not written (no changes detected by index module).");
                        }
+                       // Record the merge in progress so diff, commit and --continue can find it
+                       if squash {
+                           Self::write_squash_message(&mut database, &refs, &git_path, &inputs.left_oid, std::slice::from_ref(&inputs.right_oid))?;
+                       } else {
+                           PendingCommit::new(&git_path).start(&inputs.right_oid, PendingCommitType::Merge)?;
+                           let commit_message = message.map(str::to_string)
+                               .unwrap_or_else(|| format!("Merge branch '{}' into {}", revision, inputs.left_name));
+                           Self::write_merge_message(&git_path, &commit_message, &conflicted)?;
+                       }
+                       conflicts.save(&git_path)?;
+                       if summary {
+                           for line in conflicts.lines() {
+                               println!("{}", line);
+                           }
+                       }
                        return Err(e); // Return conflict error, index lock committed/rolled back by write_updates
                   } else {
                        return Err(e); // Return other resolve errors, index lock released by guard/closure end
@@ -162,37 +297,34 @@ impl MergeCommand {
                  println!("Warning: Index write reported no changes after successful merge resolution.");
             }
 
+            if squash {
+                Self::write_squash_message(&mut database, &refs, &git_path, &inputs.left_oid, std::slice::from_ref(&inputs.right_oid))?;
+                println!("Squash commit -- not updating HEAD");
+                return Ok(());
+            }
+
 
             // --- Commit the successful merge ---
             let commit_message = message.map(|s| s.to_string()).unwrap_or_else(|| {
                 format!("Merge branch '{}' into {}", revision, inputs.left_name)
             });
-             // Ensure Author details are configured
-             let author_name = env::var("GIT_AUTHOR_NAME").unwrap_or_else(|_| {
-                 eprintln!("Warning: GIT_AUTHOR_NAME not set. Using default.");
-                 "Default Author".to_string()
-             });
-             let author_email = env::var("GIT_AUTHOR_EMAIL").unwrap_or_else(|_| {
-                  eprintln!("Warning: GIT_AUTHOR_EMAIL not set. Using default.");
-                  "author@example.com".to_string()
-             });
-            let author = Author::new(author_name, author_email);
+            let author = Self::author();
 
 
             let tree_oid = Self::write_tree_from_index(&mut database, &index)?; // Pass immutable index now
 
             let parent1 = head_oid.clone();
             let parent2 = inputs.right_oid.clone();
-            let final_message = format!("{}\n\nMerge-Parent: {}", commit_message, parent2); // Simplified parent info
+            let final_message = format!("{}\n\n{} {}", commit_message, MERGE_PARENT_TRAILER, parent2); // Simplified parent info
 
              let mut commit = Commit::new( Some(parent1), tree_oid.clone(), author.clone(), final_message );
 
              database.store(&mut commit)?;
              let commit_oid = commit.get_oid().cloned().ok_or(Error::Generic("Commit OID not set after storage".into()))?;
-             refs.update_head(&commit_oid)?;
+             refs.update_head(&commit_oid, &format!("merge {}: Merge made by the '{}' strategy.", revision, strategy.strategy.name()))?;
 
              let elapsed = start_time.elapsed();
-             println!("Merge completed successfully in {:.2}s", elapsed.as_secs_f32());
+             trace!("Merge completed successfully in {:.2}s", elapsed.as_secs_f32());
 
             Ok(()) // Success for recursive merge
 
@@ -219,7 +351,296 @@ impl MergeCommand {
     }
 
 
+    // Merge several heads into HEAD at once, one after another onto the
+    // tree merged so far, for a single commit with all of them as parents.
+    // Heads already merged, or that another head brings in, are left out.
+    // Only the last head may conflict, leaving the conflicts to resolve
+    // and commit; a conflict before it puts back the paths the merge had
+    // changed.
+    fn octopus(revisions: &[String], message: Option<&str>, summary: bool, squash: bool, strategy: &MergeStrategy) -> Result<(), Error> {
+        let root_path = Path::new(".");
+        let git_path = git_dir::resolve(root_path);
+        let workspace = Workspace::new(root_path);
+        let mut database = Database::new(git_dir::objects(&git_path));
+        let mut index = crate::core::index::index::Index::new(git_path.join("index"));
+        let refs = Refs::new(&git_path);
+
+        let head_oid = refs.read_head()?
+            .ok_or_else(|| Error::Generic("No HEAD commit found. Create an initial commit first.".into()))?;
+        let heads = Self::reduce_heads(&mut database, &refs, revisions)?;
+        match heads.as_slice() {
+            [] => {
+                println!("Already up to date.");
+                return Ok(());
+            },
+            [(revision, _)] => return Self::merge(revision, message, summary, squash, strategy),
+            _ => {},
+        }
+        if strategy.strategy == Strategy::Theirs {
+            return Err(Error::Generic("Merge with strategy theirs failed: it takes a single head.".into()));
+        }
+
+        if !index.load_for_update()? {
+            return Err(Error::Lock("Failed to acquire lock on index".to_string()));
+        }
+        if index.has_conflict() {
+            index.rollback()?;
+            return Err(Error::Generic("Cannot merge with conflicts. Fix conflicts and commit first.".into()));
+        }
+        std::fs::write(git_path.join(ORIG_HEAD), format!("{}\n", head_oid))?;
+        let original: HashMap<String, String> = index.each_entry()
+            .map(|entry| (entry.get_path().to_string(), entry.get_oid().to_string()))
+            .collect();
+        let author = Self::author();
+
+        // Each head is merged onto a commit of what the ones before it came
+        // to, so that the next finds its bases from there
+        let mut current = head_oid.clone();
+        for (position, (revision, oid)) in heads.iter().enumerate() {
+            if strategy.strategy == Strategy::Ours {
+                break;
+            }
+            println!("Trying simple merge with {}", revision);
+            let base_oids = Bases::new(&mut database)?.find(&current, oid)?;
+            let inputs = Inputs {
+                left_name: "HEAD".to_string(),
+                right_name: revision.clone(),
+                left_oid: current.clone(),
+                right_oid: oid.clone(),
+                base_oids,
+            };
+            let mut merge_resolver = Resolve::new(&mut database, &workspace, &mut index, &inputs);
+            merge_resolver.favor = strategy.favor;
+            merge_resolver.on_progress = if summary {
+                |info: String| if !Self::is_conflict_detail(&info) { println!("{}", info) }
+            } else {
+                |info: String| println!("{}", info)
+            };
+            let merge_result = merge_resolver.execute();
+            let conflicts = merge_resolver.conflict_summary();
+
+            match merge_result {
+                Ok(()) => {},
+                Err(e) if e.to_string().contains("fix conflicts") && position + 1 == heads.len() => {
+                    let conflicted = index.conflict_paths();
+                    index.write_updates()?;
+                    if squash {
+                        let oids: Vec<String> = heads.iter().map(|(_, oid)| oid.clone()).collect();
+                        Self::write_squash_message(&mut database, &refs, &git_path, &head_oid, &oids)?;
+                    } else {
+                        let oids: Vec<&str> = heads.iter().map(|(_, oid)| oid.as_str()).collect();
+                        PendingCommit::new(&git_path).start(&oids.join("\n"), PendingCommitType::Merge)?;
+                        Self::write_merge_message(&git_path, &Self::octopus_message(&heads, message), &conflicted)?;
+                    }
+                    conflicts.save(&git_path)?;
+                    if summary {
+                        for line in conflicts.lines() {
+                            println!("{}", line);
+                        }
+                    }
+                    return Err(e);
+                },
+                Err(e) => {
+                    let conflicted = e.to_string().contains("fix conflicts");
+                    Self::restore_paths(&mut database, &workspace, &mut index, &original)?;
+                    if conflicted {
+                        println!("Automatic merge failed; should not be doing an octopus.");
+                        return Err(Error::Generic("Merge with strategy octopus failed.".into()));
+                    }
+                    return Err(e);
+                },
+            }
+
+            if position + 1 < heads.len() {
+                let tree_oid = Self::write_tree_from_index(&mut database, &index)?;
+                let mut step = Commit::new(
+                    Some(current.clone()),
+                    tree_oid,
+                    author.clone(),
+                    format!("octopus step\n\n{} {}", MERGE_PARENT_TRAILER, oid),
+                );
+                database.store(&mut step)?;
+                current = step.get_oid().cloned().ok_or(Error::Generic("Commit OID not set after storage".into()))?;
+            }
+        }
+
+        if !index.write_updates()? {
+            trace!("Index write reported no changes after octopus merge.");
+        }
+        let oids: Vec<String> = heads.iter().map(|(_, oid)| oid.clone()).collect();
+        if squash {
+            Self::write_squash_message(&mut database, &refs, &git_path, &head_oid, &oids)?;
+            println!("Squash commit -- not updating HEAD");
+            return Ok(());
+        }
+
+        let commit_message = Self::octopus_message(&heads, message);
+        let trailers: Vec<String> = oids.iter().map(|oid| format!("{} {}", MERGE_PARENT_TRAILER, oid)).collect();
+        let tree_oid = Self::write_tree_from_index(&mut database, &index)?;
+        let mut commit = Commit::new(Some(head_oid), tree_oid, author, format!("{}\n\n{}", commit_message, trailers.join("\n")));
+        database.store(&mut commit)?;
+        let commit_oid = commit.get_oid().cloned().ok_or(Error::Generic("Commit OID not set after storage".into()))?;
+        let strategy_name = if strategy.strategy == Strategy::Ours { "ours" } else { "octopus" };
+        refs.update_head(&commit_oid, &format!("merge {}: Merge made by the '{}' strategy.", revisions.join(" "), strategy_name))?;
+        Ok(())
+    }
+
+    // The message of an octopus merge, `message` if one was given
+    fn octopus_message(heads: &[(String, String)], message: Option<&str>) -> String {
+        let names: Vec<String> = heads.iter().map(|(revision, _)| format!("'{}'", revision)).collect();
+        match (message, names.split_last()) {
+            (Some(message), _) => message.to_string(),
+            (None, Some((last, others))) => format!("Merge branches {} and {} into HEAD", others.join(", "), last),
+            (None, None) => "Merge into HEAD".to_string(),
+        }
+    }
+
+    // Leave the message of a merge that stopped on conflicts in MERGE_MSG,
+    // the conflicted paths listed below it as comments, for the user to
+    // edit and `merge --continue` or `commit` to conclude the merge with
+    fn write_merge_message(git_path: &Path, message: &str, conflicts: &[String]) -> Result<(), Error> {
+        Editor::edit(PendingCommit::new(git_path).message_path, None, |editor| {
+            editor.write(message.trim_end())?;
+            if !conflicts.is_empty() {
+                editor.write("")?;
+                editor.note("Conflicts:")?;
+                for path in conflicts {
+                    editor.note(&format!("\t{}", path))?;
+                }
+            }
+            editor.close();
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    // The inputs of a subtree merge: their side and the base read in at
+    // `prefix` of our tree, or where their files are found in it
+    fn shift_inputs(database: &mut Database, inputs: &Inputs, prefix: Option<&str>) -> Result<Inputs, Error> {
+        let prefix = match prefix {
+            Some(prefix) => prefix.to_string(),
+            None => {
+                let mut theirs = vec![inputs.right_oid.clone()];
+                theirs.extend(inputs.base_oids.iter().cloned());
+                subtree::find_prefix(database, &inputs.left_oid, &theirs)?.ok_or_else(|| Error::Generic(
+                    format!("Could not find a subtree of HEAD that {} fits in; use -X subtree=<path>", inputs.right_name)
+                ))?
+            },
+        };
+        trace!("Merging {} as the subtree {}", inputs.right_name, prefix);
+        let base_oids = match inputs.base_oids.first() {
+            Some(base) => vec![subtree::shift(database, &inputs.left_oid, Some(base), &prefix)?],
+            None => vec![subtree::shift(database, &inputs.left_oid, None, &prefix)?],
+        };
+        Ok(Inputs {
+            left_name: inputs.left_name.clone(),
+            right_name: inputs.right_name.clone(),
+            left_oid: inputs.left_oid.clone(),
+            right_oid: subtree::shift(database, &inputs.left_oid, Some(&inputs.right_oid), &prefix)?,
+            base_oids,
+        })
+    }
+
+    // The heads of `revisions` left to merge into HEAD, with the
+    // names they were given: not those it has already, said so, nor those
+    // another of them brings in
+    fn reduce_heads(database: &mut Database, refs: &Refs, revisions: &[String]) -> Result<Vec<(String, String)>, Error> {
+        let mut heads: Vec<(String, String)> = Vec::new();
+        for revision in revisions {
+            let inputs = Inputs::new(database, refs, "HEAD".to_string(), revision.clone())?;
+            if inputs.already_merged() {
+                println!("Already up to date with {}", revision);
+            } else if !heads.iter().any(|(_, oid)| *oid == inputs.right_oid) {
+                heads.push((revision.clone(), inputs.right_oid));
+            }
+        }
+
+        let mut reduced = Vec::new();
+        for (i, (revision, oid)) in heads.iter().enumerate() {
+            let mut brought_in = false;
+            for (j, (_, other)) in heads.iter().enumerate() {
+                if i != j && Bases::new(database)?.find(oid, other)? == vec![oid.clone()] {
+                    brought_in = true;
+                    break;
+                }
+            }
+            if !brought_in {
+                reduced.push((revision.clone(), oid.clone()));
+            }
+        }
+        Ok(reduced)
+    }
+
+    // Put back, in the working tree, the paths an unfinished merge left
+    // other than the index had them, and let go of the index
+    fn restore_paths(
+        database: &mut Database,
+        workspace: &Workspace,
+        index: &mut crate::core::index::index::Index,
+        original: &HashMap<String, String>,
+    ) -> Result<(), Error> {
+        let mut changed: HashSet<String> = index.each_entry()
+            .filter(|entry| original.get(entry.get_path()).map(String::as_str) != Some(entry.get_oid()) || entry.stage > 0)
+            .map(|entry| entry.get_path().to_string())
+            .collect();
+        changed.extend(original.keys().filter(|path| index.get_entry(path).is_none()).cloned());
+        index.rollback()?;
+
+        for path in changed {
+            let file_path = Path::new(&path);
+            match original.get(&path) {
+                Some(oid) => workspace.write_file(file_path, &database.load(oid)?.to_bytes())?,
+                None => workspace.remove_file(file_path)?,
+            }
+        }
+        Ok(())
+    }
+
+    // The author of a merge commit, from the environment
+    fn author() -> Author {
+        let author_name = env::var("GIT_AUTHOR_NAME").unwrap_or_else(|_| {
+            eprintln!("Warning: GIT_AUTHOR_NAME not set. Using default.");
+            "Default Author".to_string()
+        });
+        let author_email = env::var("GIT_AUTHOR_EMAIL").unwrap_or_else(|_| {
+            eprintln!("Warning: GIT_AUTHOR_EMAIL not set. Using default.");
+            "author@example.com".to_string()
+        });
+        Author::new(author_name, author_email)
+    }
+
+    // Write SQUASH_MSG: every commit `rights` bring that `left` lacks,
+    // newest first, for the squashed commit to describe
+    fn write_squash_message(database: &mut Database, refs: &Refs, git_path: &Path, left: &str, rights: &[String]) -> Result<(), Error> {
+        let mut range = vec![format!("^{}", left)];
+        range.extend(rights.iter().cloned());
+        let commits = RevList::new(database, refs, &range, false)?.commits(&WalkOptions::default())?;
+
+        let mut text = String::from("Squashed commit of the following:\n");
+        for walked in commits {
+            let commit = walked.commit;
+            text.push_str(&format!("\ncommit {}\n", commit.get_oid().map(String::as_str).unwrap_or_default()));
+            if let Some(author) = commit.get_author() {
+                text.push_str(&format!("Author: {} <{}>\n", author.name, author.email));
+                text.push_str(&format!("Date:   {}\n", author.short_date()));
+            }
+            text.push('\n');
+            for line in commit.get_message().trim_end().lines() {
+                text.push_str(&format!("    {}\n", line).replace("    \n", "\n"));
+            }
+        }
+        std::fs::write(PendingCommit::new(git_path).squash_message_path, text)?;
+        Ok(())
+    }
+
     // --- *** REVISED handle_fast_forward using DIFF approach *** ---
+    // The per-path conflict messages that --summary folds into its report
+    fn is_conflict_detail(info: &str) -> bool {
+        info.starts_with("CONFLICT")
+            || info.starts_with("Adding conflicting file")
+            || info.starts_with("  Renaming file version")
+    }
+
     fn handle_fast_forward(
         database: &mut Database,
         workspace: &Workspace,
@@ -227,13 +648,14 @@ impl MergeCommand {
         refs: &Refs,
         current_oid: &str,
         target_oid: &str,
+        reflog_message: &str,
     ) -> Result<(), Error> {
         // Note: index is already locked by the caller (execute)
         let a_short = &current_oid[0..std::cmp::min(8, current_oid.len())];
         let b_short = &target_oid[0..std::cmp::min(8, target_oid.len())];
 
-        println!("Updating {}..{}", a_short, b_short);
-        println!("Fast-forward");
+        trace!("Updating {}..{}", a_short, b_short);
+        trace!("Fast-forward");
 
         // 1. Get the tree OID for the target commit
         let target_commit_obj = database.load(target_oid)?;
@@ -242,7 +664,7 @@ impl MergeCommand {
             None => return Err(Error::Generic(format!("Target OID {} is not a commit", target_oid))),
         };
         let target_tree_oid = target_commit.get_tree();
-        println!("Target tree OID: {}", target_tree_oid);
+        trace!("Target tree OID: {}", target_tree_oid);
 
         // 2. Get the tree OID for the current commit
         let current_commit_obj = database.load(current_oid)?;
@@ -251,27 +673,27 @@ impl MergeCommand {
             None => return Err(Error::Generic(format!("Current HEAD OID {} is not a commit", current_oid))),
         };
         let current_tree_oid = current_commit.get_tree();
-        println!("Current tree OID: {}", current_tree_oid);
+        trace!("Current tree OID: {}", current_tree_oid);
 
         // 3. Calculate the diff between the current tree and the target tree
         let path_filter = PathFilter::new();
-        println!("Calculating tree diff between current ({}) and target ({})", current_tree_oid, target_tree_oid);
+        trace!("Calculating tree diff between current ({}) and target ({})", current_tree_oid, target_tree_oid);
         let tree_diff = database.tree_diff(Some(current_tree_oid), Some(target_tree_oid), &path_filter)?;
-        println!("Tree diff calculated, {} changes found", tree_diff.len());
+        trace!("Tree diff calculated, {} changes found", tree_diff.len());
 
         let mut diff_applied = false; // Track if we actually applied changes
 
         // 4. Apply the changes from the diff to the workspace and index
         if tree_diff.is_empty() {
-            println!("No tree changes detected between commits.");
+            trace!("No tree changes detected between commits.");
             index.set_changed(false); // No changes to index
         } else {
             for (path, (old_entry, new_entry)) in &tree_diff { // Iterate over reference
-                println!("Applying change for: {}", path.display());
+                trace!("Applying change for: {}", path.display());
                 match (old_entry, new_entry) {
                     (Some(_old), Some(new)) => { // Modified
                         if new.get_file_mode().is_directory() {
-                            println!("  -> Modified Directory (ensuring exists)");
+                            trace!("  -> Modified Directory (ensuring exists)");
                             workspace.make_directory(&path)?;
                             
                             // FIXED: Process directory contents explicitly
@@ -281,13 +703,13 @@ impl MergeCommand {
                                 Self::process_tree_entries(tree, path, database, workspace, index)?;
                             }
                         } else {
-                            println!("  -> Modified File");
+                            trace!("  -> Modified File");
                             Self::update_workspace_file(database, workspace, index, &path, new.get_oid(), &new.get_file_mode())?;
                         }
                     },
                     (None, Some(new)) => { // Added
                         if new.get_file_mode().is_directory() {
-                            println!("  -> Added Directory");
+                            trace!("  -> Added Directory");
                             workspace.make_directory(&path)?;
                             
                             // FIXED: Process directory contents for newly added directories
@@ -296,19 +718,19 @@ impl MergeCommand {
                                 Self::process_tree_entries(tree, path, database, workspace, index)?;
                             }
                         } else {
-                            println!("  -> Added File");
+                            trace!("  -> Added File");
                             Self::update_workspace_file(database, workspace, index, &path, new.get_oid(), &new.get_file_mode())?;
                         }
                     },
                     (Some(old), None) => { // Deleted
-                        println!("  -> Deleted");
+                        trace!("  -> Deleted");
                         let path_str = path.to_string_lossy().to_string();
                         // Check type before removing
                         if old.get_file_mode().is_directory() {
-                            println!("  -> Removing directory: {}", path.display());
+                            trace!("  -> Removing directory: {}", path.display());
                             workspace.force_remove_directory(&path)?; // Use force for simplicity
                         } else {
-                            println!("  -> Removing file: {}", path.display());
+                            trace!("  -> Removing file: {}", path.display());
                             workspace.remove_file(&path)?;
                         }
                         index.remove(&PathBuf::from(&path_str))?; // Remove from index
@@ -323,13 +745,13 @@ impl MergeCommand {
         }
 
         // 5. Write the updated index
-        println!("Attempting to write index updates...");
+        trace!("Attempting to write index updates...");
         match index.write_updates() {
             Ok(updated) => {
                 if updated {
-                    println!("Index successfully written.");
+                    trace!("Index successfully written.");
                 } else if !diff_applied {
-                    println!("Index write skipped: No changes were applied.");
+                    trace!("Index write skipped: No changes were applied.");
                     // No rollback needed, index lock will be released by caller
                 } else {
                     println!("Warning: Index write reported no changes, but diff was applied.");
@@ -344,9 +766,9 @@ impl MergeCommand {
         }
 
         // 6. Update HEAD reference
-        println!("Attempting to update HEAD to {}", target_oid);
-        match refs.update_head(target_oid) {
-            Ok(_) => println!("Successfully updated HEAD"),
+        trace!("Attempting to update HEAD to {}", target_oid);
+        match refs.update_head(target_oid, reflog_message) {
+            Ok(_) => trace!("Successfully updated HEAD"),
             Err(e) => {
                 println!("ERROR updating HEAD: {}", e);
                 // Potentially leave repo in inconsistent state (index updated, HEAD not)
@@ -354,7 +776,7 @@ impl MergeCommand {
             }
         }
 
-        println!("Fast-forward merge completed.");
+        trace!("Fast-forward merge completed.");
         // Index lock is committed by write_updates or rolled back by caller on error
         Ok(())
     }
@@ -397,14 +819,14 @@ impl MergeCommand {
             match entry {
                 TreeEntry::Blob(oid, mode) => {
                     // E un fișier, scrie-l în workspace
-                    println!("  -> Writing file in directory: {}", entry_path.display());
+                    trace!("  -> Writing file in directory: {}", entry_path.display());
                     Self::update_workspace_file(database, workspace, index, &entry_path, oid, mode)?;
                     // Elimină acest fișier din lista fișierelor existente (l-am procesat deja)
                     current_files.remove(name);
                 },
                 TreeEntry::Tree(subtree) => {
                     // E un director, asigură-te că există și apoi procesează-l recursiv
-                    println!("  -> Processing subdirectory: {}", entry_path.display());
+                    trace!("  -> Processing subdirectory: {}", entry_path.display());
                     workspace.make_directory(&entry_path)?;
                     
                     if let Some(subtree_oid) = subtree.get_oid() {
@@ -424,7 +846,7 @@ impl MergeCommand {
             let old_path = parent_path.join(&old_name);
             let path_str = old_path.to_string_lossy().to_string();
             
-            println!("  -> Removing file not in target tree: {}", old_path.display());
+            trace!("  -> Removing file not in target tree: {}", old_path.display());
             
             // Verifică dacă e director sau fișier
             let full_path = workspace.root_path.join(&old_path);
@@ -469,7 +891,7 @@ impl MergeCommand {
     // --- write_tree_from_index - Takes immutable index ---
     fn write_tree_from_index(database: &mut Database, index: &crate::core::index::index::Index) -> Result<String, Error> {
         let database_entries: Vec<_> = index.each_entry()
-            .filter(|entry| entry.stage == 0) // Only include stage 0 entries
+            .filter(|entry| entry.stage == 0 && !entry.intent_to_add) // Only include stage 0 entries with content
             .map(|index_entry| {
                 DatabaseEntry::new(
                     index_entry.get_path().to_string(),
@@ -16,6 +16,9 @@ use crate::core::refs::Refs;
 use crate::core::color::Color;
 use crate::core::file_mode::FileMode;
 use crate::core::diff::diff;
+use crate::core::merge::diff3::{self, ConflictStyle};
+use crate::core::repository::git_dir;
+use crate::trace;
 
 pub struct MergeToolCommand;
 
@@ -38,11 +41,11 @@ impl MergeToolCommand {
     pub fn execute(tool: Option<&str>) -> Result<(), Error> {
         let start_time = Instant::now();
         
-        println!("Starting merge resolution tool...");
+        trace!("Starting merge resolution tool...");
         
         // Initialize repository components
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Verify .ash directory exists
         if !git_path.exists() {
@@ -50,7 +53,7 @@ impl MergeToolCommand {
         }
         
         let workspace = Workspace::new(root_path);
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = Index::new(git_path.join("index"));
         
         // Try to acquire the lock on the index
@@ -92,7 +95,7 @@ impl MergeToolCommand {
                 let path_str = entry.get_path().to_string();
                 let entry_info = (entry.get_oid().to_string(), entry.stage);
                 
-                println!("Found conflict entry: {} (stage {})", path_str, entry.stage);
+                trace!("Found conflict entry: {} (stage {})", path_str, entry.stage);
                 
                 // Add to our conflict map
                 if !conflict_entries.contains_key(&path_str) {
@@ -109,8 +112,8 @@ impl MergeToolCommand {
             // Check if this is a directory
             let full_path = workspace.root_path.join(&path);
             if full_path.exists() && full_path.is_dir() {
-                println!("\nDirectory conflict detected: {}", Color::yellow(path_str));
-                println!("Exploring directory for conflicted files...");
+                trace!("\nDirectory conflict detected: {}", Color::yellow(path_str));
+                trace!("Exploring directory for conflicted files...");
                 
                 // Explore the directory for conflict files
                 let (resolved, skipped) = Self::explore_directory_for_conflicts(
@@ -157,10 +160,10 @@ impl MergeToolCommand {
         // Save index with potentially resolved conflicts
         if index.is_changed() {
             index.write_updates()?;
-            println!("\nUpdated index written successfully.");
+            trace!("\nUpdated index written successfully.");
         } else {
             index.rollback()?;
-            println!("\nNo changes made to index.");
+            trace!("\nNo changes made to index.");
         }
         
         // Check if all conflicts were resolved
@@ -173,10 +176,10 @@ impl MergeToolCommand {
         
         // Print summary
         let elapsed = start_time.elapsed();
-        println!("\nMerge tool completed in {:.2}s", elapsed.as_secs_f32());
-        println!("Files resolved: {}", Color::green(&resolved_count.to_string()));
-        println!("Files skipped: {}", Color::yellow(&skipped_count.to_string()));
-        println!("Conflicts remaining: {}", Color::red(&index.conflict_paths().len().to_string()));
+        trace!("\nMerge tool completed in {:.2}s", elapsed.as_secs_f32());
+        trace!("Files resolved: {}", Color::green(&resolved_count.to_string()));
+        trace!("Files skipped: {}", Color::yellow(&skipped_count.to_string()));
+        trace!("Conflicts remaining: {}", Color::red(&index.conflict_paths().len().to_string()));
         
         Ok(())
     }
@@ -201,7 +204,7 @@ impl MergeToolCommand {
             format!("{}/", dir_path_str)
         };
         
-        println!("DEBUG: Exploring directory: {}", dir_path.display());
+        trace!("DEBUG: Exploring directory: {}", dir_path.display());
         
         // Find all conflict entries under this directory
         let mut files_to_process = Vec::new();
@@ -209,7 +212,7 @@ impl MergeToolCommand {
         // Check for exact directory conflict match
         let dir_is_conflict = conflict_entries.contains_key(&dir_path_str);
         if dir_is_conflict {
-            println!("DEBUG: Directory itself is marked as a conflict: {}", dir_path_str);
+            trace!("DEBUG: Directory itself is marked as a conflict: {}", dir_path_str);
         }
         
         // Recursively explore the physical directory structure to find potential conflict files
@@ -226,7 +229,7 @@ impl MergeToolCommand {
         for (conflict_path, entries) in conflict_entries {
             // If the conflict path is within this directory
             if conflict_path == &dir_path_str || conflict_path.starts_with(&dir_prefix) {
-                println!("DEBUG: Found conflict path under directory: {}", conflict_path);
+                trace!("DEBUG: Found conflict path under directory: {}", conflict_path);
                 
                 // Check if we've already added this path
                 if !files_to_process.iter().any(|info| info.path_str == *conflict_path) {
@@ -275,7 +278,7 @@ impl MergeToolCommand {
         // add it to skipped count
         if files_count == 0 {
             if dir_is_conflict {
-                println!("Directory {} is a conflict but no conflict files found.", dir_path_str);
+                trace!("Directory {} is a conflict but no conflict files found.", dir_path_str);
                 
                 // Try to explore subdirectories for conflicts
                 let full_dir_path = workspace.root_path.join(dir_path);
@@ -287,7 +290,7 @@ impl MergeToolCommand {
                                 .unwrap_or(&path);
                             
                             if path.is_dir() {
-                                println!("Recursively exploring subdirectory: {}", rel_path.display());
+                                trace!("Recursively exploring subdirectory: {}", rel_path.display());
                                 let (sub_resolved, sub_skipped) = Self::explore_directory_for_conflicts(
                                     workspace, database, index, rel_path, conflict_entries, editor
                                 )?;
@@ -303,7 +306,7 @@ impl MergeToolCommand {
                     skipped_count += 1;
                 }
             } else {
-                println!("No conflict files found in directory: {}", dir_path.display());
+                trace!("No conflict files found in directory: {}", dir_path.display());
             }
         }
         
@@ -333,15 +336,15 @@ impl MergeToolCommand {
                 .to_path_buf();
             
             let path_str = rel_path.to_string_lossy().to_string();
-            println!("DEBUG: Found entry: {}", path.display());
-            println!("DEBUG: Relative path: {}", path_str);
+            trace!("DEBUG: Found entry: {}", path.display());
+            trace!("DEBUG: Relative path: {}", path_str);
             
             if path.is_file() {
-                println!("DEBUG: Found file: {}", path_str);
+                trace!("DEBUG: Found file: {}", path_str);
                 
                 // Check if this file has conflict entries
                 if let Some(entries) = conflict_entries.get(&path_str) {
-                    println!("DEBUG: Found conflict entries for file: {}", path_str);
+                    trace!("DEBUG: Found conflict entries for file: {}", path_str);
                     
                     let mut info = ConflictInfo {
                         path_str: path_str.clone(),
@@ -362,10 +365,10 @@ impl MergeToolCommand {
                     
                     files_to_process.push(info);
                 } else {
-                    println!("DEBUG: No conflict entries found for file: {}", path_str);
+                    trace!("DEBUG: No conflict entries found for file: {}", path_str);
                 }
             } else if path.is_dir() {
-                println!("DEBUG: Found directory: {}", path_str);
+                trace!("DEBUG: Found directory: {}", path_str);
                 
                 // Recursively explore this directory
                 Self::explore_physical_directory(
@@ -394,14 +397,14 @@ impl MergeToolCommand {
         println!("Processing conflict in file: {}", Color::yellow(path_str));
         
         // Debug conflict info
-        println!("  Base OID: {:?}", info.base_oid);
-        println!("  Ours OID: {:?}", info.ours_oid);
-        println!("  Theirs OID: {:?}", info.theirs_oid);
+        trace!("  Base OID: {:?}", info.base_oid);
+        trace!("  Ours OID: {:?}", info.ours_oid);
+        trace!("  Theirs OID: {:?}", info.theirs_oid);
         
         // Check if this is a directory
         let full_path = workspace.root_path.join(path);
         if full_path.exists() && full_path.is_dir() {
-            println!("  This is a directory conflict. Checking for actual conflicting files...");
+            trace!("  This is a directory conflict. Checking for actual conflicting files...");
             
             // Try to find actual conflict files within the directory
             let dir_conflicts = Self::find_directory_conflict_files(
@@ -412,14 +415,14 @@ impl MergeToolCommand {
             )?;
             
             if dir_conflicts.is_empty() {
-                println!("  No specific file conflicts found in directory");
+                trace!("  No specific file conflicts found in directory");
                 return Ok(false);
             }
             
             // Process each conflicting file
             let mut all_resolved = true;
             for (rel_path, file_info) in dir_conflicts {
-                println!("  Processing specific file conflict: {}", rel_path.display());
+                trace!("  Processing specific file conflict: {}", rel_path.display());
                 match Self::process_conflict(workspace, database, index, &file_info, editor) {
                     Ok(true) => println!("    ✓ Resolved conflict in file: {}", rel_path.display()),
                     Ok(false) => {
@@ -559,8 +562,10 @@ impl MergeToolCommand {
                                     
                                     let parts: Vec<&str> = content_str.split(ours_start_marker).collect();
                                     if parts.len() > 1 {
-                                        // Take everything after the marker until "======="
+                                        // Take everything after the marker until "=======",
+                                        // or the base section of a diff3-style conflict
                                         let ours_section = parts[1].split("=======").next().unwrap_or("");
+                                        let ours_section = ours_section.split("|||||||").next().unwrap_or("");
                                         ours_content = ours_section.trim().to_string();
                                         
                                         println!("    Extracted content ({} bytes):\n{}", ours_content.len(), ours_content);
@@ -843,10 +848,18 @@ impl MergeToolCommand {
 
         // Prepare conflict output with intelligent handling of diffs
         let mut conflict_content = String::new();
+        let style = ConflictStyle::load(&git_dir::resolve(&workspace.root_path));
+        // diff3 and zdiff3 show the base between the two sides
+        let base_section = if style != ConflictStyle::Merge && has_base {
+            format!("||||||| BASE\n{}", base_str)
+        } else {
+            String::new()
+        };
 
         if !has_ours && has_theirs {
             // File only exists in theirs
             conflict_content.push_str("<<<<<<< OURS (file doesn't exist)\n");
+            conflict_content.push_str(&base_section);
             conflict_content.push_str("=======\n");
             conflict_content.push_str(&theirs_str);
             conflict_content.push_str(">>>>>>> THEIRS\n");
@@ -854,8 +867,14 @@ impl MergeToolCommand {
             // File only exists in ours
             conflict_content.push_str("<<<<<<< OURS\n");
             conflict_content.push_str(&ours_str);
+            conflict_content.push_str(&base_section);
             conflict_content.push_str("=======\n");
             conflict_content.push_str(">>>>>>> THEIRS (file doesn't exist)\n");
+        } else if style != ConflictStyle::Merge {
+            // With the base shown, the conflicts are the chunks a
+            // three-way merge leaves
+            let result = diff3::merge(&base_str, &ours_str, &theirs_str)?;
+            conflict_content.push_str(&result.to_styled_string(Some("OURS"), Some("BASE"), Some("THEIRS"), style));
         } else {
             // Both versions exist, compare line by line
             let ours_lines: Vec<&str> = ours_str.lines().collect();
@@ -1194,7 +1213,7 @@ impl MergeToolCommand {
         for path in ours_files.keys() { all_paths.insert(path.clone()); }
         for path in theirs_files.keys() { all_paths.insert(path.clone()); }
         
-        println!("  Found {} unique files in directory tree", all_paths.len());
+        trace!("  Found {} unique files in directory tree", all_paths.len());
         
         // Check each path for conflicts
         for path in all_paths {
@@ -1233,18 +1252,18 @@ impl MergeToolCommand {
             
             // Only count as a conflict if content differs and both versions exist
             if is_content_same || (!ours_exists && !theirs_exists) {
-                println!("  Skipping file with identical content or nonexistent: {}", path.display());
+                trace!("  Skipping file with identical content or nonexistent: {}", path.display());
                 continue;
             }
             
             // Additional check - if the file doesn't physically exist but is in the index
             // with the same OID, it's not actually a conflict
             if ours_oid.is_some() && theirs_oid.is_some() && ours_oid == theirs_oid {
-                println!("  Skipping file with same OID in both branches: {}", path.display());
+                trace!("  Skipping file with same OID in both branches: {}", path.display());
                 continue;
             }
             
-            println!("  Confirmed conflict in file: {}", path.display());
+            trace!("  Confirmed conflict in file: {}", path.display());
             
             // Create conflict info for this file
             let conflict_info = ConflictInfo {
@@ -9,7 +9,46 @@ pub mod log;
 pub mod merge;
 pub mod merge_tool;
 pub mod rm;
+pub mod restore;
 pub mod reset;
 pub mod commit_writer;
 pub mod cherry_pick;
-pub mod revert;
\ No newline at end of file
+pub mod revert;
+pub mod add_interactive;pub mod stash;
+pub mod fetch;
+pub mod comment;
+pub mod push;
+pub mod blame;
+pub mod remote;
+pub mod pull;
+pub mod repack;
+pub mod clone;
+pub mod gc;
+pub mod reflog;
+pub mod request_pull;
+pub mod tag;
+pub mod daemon;
+pub mod rebase;
+pub mod bisect;
+pub mod bugreport;
+pub mod grep;
+pub mod mv;
+pub mod show;
+pub mod verify_repo_locks;
+pub mod check_attr;
+pub mod verify_tag;
+pub mod clean;
+pub mod worktree;
+pub mod format_patch;
+pub mod apply;
+pub mod am;
+pub mod write_tree;
+pub mod commit_tree;
+pub mod read_tree;
+pub mod subtree;
+pub mod switch;
+pub mod difftool;
+pub mod update_ref;
+pub mod symbolic_ref;
+pub mod for_each_ref;
+pub mod var;
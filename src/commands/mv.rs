@@ -0,0 +1,176 @@
+// src/commands/mv.rs - move or rename files in the working tree and the index
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+pub struct MvCommand;
+
+// A move that passed every check: where from, where to, and whether it
+// takes a whole directory along
+struct Move {
+    source: String,
+    target: String,
+    directory: bool,
+}
+
+impl MvCommand {
+    /// Move or rename `sources` to `destination` in the working tree and in
+    /// the index together, keeping each entry's blob so later diffs can pair
+    /// the old and new names. With several sources, or when `destination` is
+    /// a directory, each source is moved into it. `force` overwrites a file
+    /// already at the destination, `skip_errors` leaves out the sources that
+    /// cannot be moved, and `dry_run` only reports what would be done.
+    pub fn execute(
+        sources: &[String],
+        destination: &str,
+        force: bool,
+        skip_errors: bool,
+        dry_run: bool,
+        verbose: bool,
+    ) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock(
+                "Unable to acquire lock on index. Another process may be using it.".to_string()
+            ));
+        }
+
+        let result = Self::plan(&repo, sources, destination, force, skip_errors)
+            .and_then(|moves| {
+                if dry_run {
+                    for step in &moves {
+                        println!("Checking rename of '{}' to '{}'", step.source, step.target);
+                        println!("Renaming {} to {}", step.source, step.target);
+                    }
+                    return Ok(());
+                }
+                Self::apply(&mut repo, &moves, verbose)
+            });
+        match result {
+            Ok(()) if !dry_run => {
+                repo.index.write_updates()?;
+                Ok(())
+            },
+            other => {
+                repo.index.rollback()?;
+                other
+            },
+        }
+    }
+
+    // Check every source before anything is touched, so a bad one leaves
+    // the working tree and the index as they were
+    fn plan(repo: &Repository, sources: &[String], destination: &str, force: bool, skip_errors: bool) -> Result<Vec<Move>, Error> {
+        let destination = Self::normalize(destination);
+        let into_directory = repo.path.join(&destination).is_dir();
+        if sources.len() > 1 && !into_directory {
+            return Err(Error::Generic(format!("destination '{}' is not a directory", destination)));
+        }
+
+        let mut moves = Vec::new();
+        let mut targets = HashSet::new();
+        for source in sources {
+            let source = Self::normalize(source);
+            let target = if into_directory {
+                let name = Path::new(&source).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                if destination.is_empty() { name } else { format!("{}/{}", destination, name) }
+            } else {
+                destination.clone()
+            };
+
+            match Self::check(repo, &source, &target, force, &targets) {
+                Ok(directory) => {
+                    targets.insert(target.clone());
+                    moves.push(Move { source, target, directory });
+                },
+                Err(_) if skip_errors => {},
+                Err(reason) => {
+                    return Err(Error::Generic(format!("{}, source={}, destination={}", reason, source, target)));
+                },
+            }
+        }
+        Ok(moves)
+    }
+
+    // Why `source` cannot be moved to `target`, or whether it is a directory
+    fn check(repo: &Repository, source: &str, target: &str, force: bool, targets: &HashSet<String>) -> Result<bool, String> {
+        let source_path = repo.path.join(source);
+        let target_path = repo.path.join(target);
+        let metadata = fs::symlink_metadata(&source_path).map_err(|_| "bad source".to_string())?;
+
+        if source.is_empty() || source == target || target.starts_with(&format!("{}/", source)) {
+            return Err("can not move directory into itself".to_string());
+        }
+        if targets.contains(target) {
+            return Err("multiple sources for the same target".to_string());
+        }
+        let parent = target_path.parent().unwrap_or(&repo.path);
+        if !parent.is_dir() {
+            return Err("destination directory does not exist".to_string());
+        }
+
+        if metadata.is_dir() {
+            if !repo.index.tracked_directory(Path::new(source)) {
+                return Err("source directory is empty".to_string());
+            }
+            if fs::symlink_metadata(&target_path).is_ok() {
+                return Err("destination already exists".to_string());
+            }
+            return Ok(true);
+        }
+
+        match repo.index.get_entry(source) {
+            None => return Err("not under version control".to_string()),
+            Some(entry) if entry.stage > 0 => return Err("conflicted".to_string()),
+            Some(_) => {},
+        }
+        if let Ok(existing) = fs::symlink_metadata(&target_path) {
+            // Only a file may be overwritten, and only when asked to
+            if !force || existing.is_dir() {
+                return Err("destination exists".to_string());
+            }
+        }
+        Ok(false)
+    }
+
+    // Rename in the working tree first and undo what was done if any
+    // rename fails; the index is only changed once they all succeeded
+    fn apply(repo: &mut Repository, moves: &[Move], verbose: bool) -> Result<(), Error> {
+        let mut done: Vec<&Move> = Vec::new();
+        for step in moves {
+            if let Err(e) = fs::rename(repo.path.join(&step.source), repo.path.join(&step.target)) {
+                for undone in done.iter().rev() {
+                    let _ = fs::rename(repo.path.join(&undone.target), repo.path.join(&undone.source));
+                }
+                return Err(Error::Generic(format!("renaming '{}' failed: {}", step.source, e)));
+            }
+            done.push(step);
+        }
+
+        for step in moves {
+            if verbose {
+                println!("Renaming {} to {}", step.source, step.target);
+            }
+            if step.directory {
+                for child in repo.index.child_paths(Path::new(&step.source)) {
+                    let child = child.to_string_lossy().to_string();
+                    let renamed = format!("{}{}", step.target, &child[step.source.len()..]);
+                    repo.index.rename(&child, &renamed);
+                }
+            } else {
+                // A file overwritten with --force stops being tracked on its own
+                repo.index.remove(Path::new(&step.target))?;
+                repo.index.rename(&step.source, &step.target);
+            }
+        }
+        Ok(())
+    }
+
+    fn normalize(path: &str) -> String {
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        if path == "." { String::new() } else { path.to_string() }
+    }
+}
@@ -0,0 +1,111 @@
+// src/commands/pull.rs - fetch from another repository and integrate the result
+use std::path::Path;
+
+use crate::errors::error::Error;
+use crate::commands::fetch::{FetchCommand, FetchOptions};
+use crate::commands::merge::{MergeCommand, MergeStrategy};
+use crate::commands::rebase::{RebaseCommand, RebaseOptions};
+use crate::commands::reset::ORIG_HEAD;
+use crate::core::config::Config;
+use crate::core::refs::Reference;
+use crate::core::remote::refspec::short_ref_name;
+use crate::core::remote::{Remote, DEFAULT_REMOTE};
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+
+pub struct PullCommand;
+
+impl PullCommand {
+    /// Fetch `branch` from `remote` and merge it into the current branch, or
+    /// with `rebase` replay the local commits on top of it. Both default to
+    /// the current branch's `branch.<name>.remote` / `branch.<name>.merge`,
+    /// then to `origin` and the branch of the same name; `rebase` defaults
+    /// to `branch.<name>.rebase`, then `pull.rebase`.
+    pub fn execute(remote: Option<&str>, branch: Option<&str>, rebase: Option<bool>) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        let current = match repo.refs.current_ref()? {
+            Reference::Symbolic(path) if path != "HEAD" => short_ref_name(&path).to_string(),
+            _ => return Err(Error::Generic("You are not currently on a branch.".to_string())),
+        };
+        let head_oid = repo.refs.read_head()?;
+
+        let remote_name = remote.map(str::to_string)
+            .or_else(|| config.get(&format!("branch.{}.remote", current)))
+            .unwrap_or_else(|| DEFAULT_REMOTE.to_string());
+        let merge_ref = match branch {
+            Some(branch) if branch.starts_with("refs/") => branch.to_string(),
+            Some(branch) => format!("refs/heads/{}", branch),
+            None => config.get(&format!("branch.{}.merge", current))
+                .unwrap_or_else(|| format!("refs/heads/{}", current)),
+        };
+        let rebase = rebase
+            .or_else(|| config.get_bool(&format!("branch.{}.rebase", current)))
+            .or_else(|| config.get_bool("pull.rebase"))
+            .unwrap_or(false);
+
+        // A rebase rewrites the worktree from scratch, so it must be clean
+        if let (true, Some(head_oid)) = (rebase, &head_oid) {
+            if RebaseCommand::has_local_changes(&mut repo, head_oid)? {
+                return Err(Error::Generic(
+                    "cannot pull with rebase: You have unstaged or uncommitted changes.\nPlease commit or stash them.".to_string()
+                ));
+            }
+        }
+
+        // Configured remotes fetch their usual refspecs, which keeps the
+        // remote-tracking branches current; anything else fetches just the branch
+        let resolved = Remote::resolve(&git_path, &remote_name)?;
+        let refspecs = match (&resolved.name, branch) {
+            (Some(_), None) => Vec::new(),
+            _ => vec![merge_ref.clone()],
+        };
+        let fetched = FetchCommand::execute(Some(&remote_name), &refspecs, &FetchOptions::default())?;
+
+        let upstream = fetched.into_iter()
+            .find(|(name, _)| *name == merge_ref)
+            .map(|(_, oid)| oid)
+            .ok_or_else(|| Error::Generic(format!("couldn't find remote ref {}", merge_ref)))?;
+
+        // An unborn branch simply starts at the fetched commit
+        let head_oid = match head_oid {
+            Some(head_oid) => head_oid,
+            None => {
+                let files = head_files(&mut repo, &upstream)?;
+                if let Some(path) = files.keys().find(|path| repo.workspace.root_path.join(path).exists()) {
+                    return Err(Error::Generic(format!("untracked working tree file '{}' would be overwritten", path)));
+                }
+                if !repo.index.load_for_update()? {
+                    return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+                }
+                repo.refs.update_head(&upstream, "initial pull")?;
+                for (path, entry) in files {
+                    let file = Path::new(&path);
+                    if let Some(parent) = file.parent().filter(|p| !p.as_os_str().is_empty()) {
+                        repo.workspace.make_directory(parent)?;
+                    }
+                    let content = repo.database.load(entry.get_oid())?.to_bytes();
+                    repo.workspace.write_file(file, &content)?;
+                    let stat = repo.workspace.stat_file(file)?;
+                    repo.index.add(file, entry.get_oid(), &stat)?;
+                }
+                repo.index.write_updates()?;
+                return Ok(());
+            }
+        };
+        std::fs::write(git_path.join(ORIG_HEAD), format!("{}\n", head_oid))?;
+
+        if rebase {
+            RebaseCommand::start(repo, &current, &head_oid, &upstream, &upstream, &RebaseOptions::default())
+        } else {
+            let message = format!("Merge branch '{}' of {} into {}", short_ref_name(&merge_ref), resolved.url, current);
+            match MergeCommand::execute(std::slice::from_ref(&upstream), Some(&message), None, false, false, &MergeStrategy::default()) {
+                Err(e) if e.to_string().contains("Already up to date") => Ok(()),
+                result => result,
+            }
+        }
+    }
+}
@@ -0,0 +1,354 @@
+// src/commands/push.rs - send local commits to another repository and update its refs
+use std::collections::{BTreeMap, HashSet};
+
+use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::protocol::resume::PushProgress;
+use crate::core::protocol::throttle::Throttle;
+use crate::core::protocol::{self, RefUpdate};
+use crate::core::refs::Reference;
+use crate::core::remote::refspec::short_ref_name;
+use crate::core::remote::transport;
+use crate::core::remote::upstream::Upstream;
+use crate::core::remote::{self, Remote, DEFAULT_REMOTE};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
+use crate::trace;
+
+// Objects sent to the remote at a time
+const PUSH_BATCH_SIZE: usize = 256;
+
+// One `[+]<src>:<dst>` request from the command line
+struct PushSpec {
+    source: String,
+    new: Option<String>,
+    destination: String,
+    force: bool,
+}
+
+// One `--force-with-lease[=<ref>[:<expect>]]`; without a ref it covers
+// every destination, without an expected value the remote-tracking ref
+// says where the remote ref should be
+struct Lease {
+    name: Option<String>,
+    expect: Option<String>,
+}
+
+pub struct PushCommand;
+
+impl PushCommand {
+    /// Push `refspecs` (the current branch by default) to `remote`, a
+    /// configured name or a URL, by default the current branch's upstream
+    /// remote or else `origin`; the current branch goes to its upstream
+    /// branch on that remote, if it has one there. `leases` are the
+    /// values of `--force-with-lease`, empty for the bare option: a leased
+    /// ref may be rewritten, but only if the remote still has it where we
+    /// expect. With `mirror`, or to a mirror remote, every ref under refs/
+    /// is pushed as it is here and remote refs we do not have are deleted.
+    pub fn execute(remote: Option<&str>, refspecs: &[String], force: bool, leases: &[String], mirror: bool) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        let upstream = match repo.refs.current_ref()? {
+            Reference::Symbolic(path) if path != "HEAD" => Upstream::of(&Config::load(&git_path)?, short_ref_name(&path))
+                .filter(|upstream| !upstream.is_local()),
+            _ => None,
+        };
+        let default_remote = upstream.as_ref().map_or(DEFAULT_REMOTE, |upstream| upstream.remote.as_str());
+        let remote = Remote::resolve(&git_path, remote.unwrap_or(default_remote)).map_err(|e| match remote {
+            Some(_) => e,
+            None => Error::Generic("No configured push destination.".to_string()),
+        })?;
+
+        if mirror && !refspecs.is_empty() {
+            return Err(Error::Generic("--mirror can't be combined with refspecs".to_string()));
+        }
+        let mirror = mirror || (refspecs.is_empty() && remote.mirror);
+
+        let mut transport = transport::connect(&remote.url)?;
+        let advertised = transport.advertised_refs()?;
+
+        let specs = if mirror {
+            Self::mirror_specs(&repo, &advertised)?
+        } else if refspecs.is_empty() {
+            let upstream = upstream.filter(|upstream| remote.name.as_deref() == Some(upstream.remote.as_str()));
+            match repo.refs.current_ref()? {
+                Reference::Symbolic(path) if path != "HEAD" => {
+                    let spec = match upstream {
+                        Some(upstream) => format!("{}:{}", path, upstream.merge),
+                        None => path,
+                    };
+                    vec![Self::parse_spec(&mut repo, &spec, force)?]
+                },
+                _ => return Err(Error::Generic("You are not currently on a branch.".to_string())),
+            }
+        } else {
+            refspecs.iter()
+                .map(|spec| Self::parse_spec(&mut repo, spec, force))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let leases: Vec<Lease> = leases.iter().map(|lease| Self::parse_lease(lease)).collect();
+
+        // Decide what to update, refusing to drop remote history unless forced
+        let mut lines = Vec::new();
+        let mut updates = Vec::new();
+        let mut rejected = false;
+
+        for spec in &specs {
+            let old = advertised.get(&spec.destination).cloned();
+            let from = short_ref_name(&spec.source).to_string();
+            let to = short_ref_name(&spec.destination).to_string();
+
+            // A lease allows a forced update, as long as nobody has moved
+            // the remote ref since we last looked at it
+            let leased = match Self::lease_for(&leases, &spec.destination) {
+                Some(lease) => {
+                    let expected = Self::expected_value(&mut repo, &remote, lease, &spec.destination)?;
+                    if expected != old {
+                        rejected = true;
+                        lines.push(format!(" ! {:<17} {} -> {} (stale info)", "[rejected]", from, to));
+                        continue;
+                    }
+                    true
+                },
+                None => false,
+            };
+
+            let (flag, summary, note) = match (&old, &spec.new) {
+                (None, None) => {
+                    return Err(Error::Generic(format!("unable to delete '{}': remote ref does not exist", to)));
+                },
+                (Some(old), Some(new)) if old == new => continue,
+                (Some(_), None) => ('-', "[deleted]".to_string(), String::new()),
+                (None, Some(_)) => {
+                    let kind = match spec.destination.as_str() {
+                        d if d.starts_with("refs/tags/") => "[new tag]",
+                        d if d.starts_with("refs/heads/") => "[new branch]",
+                        _ => "[new reference]",
+                    };
+                    ('*', kind.to_string(), String::new())
+                },
+                (Some(old), Some(new)) => {
+                    let known = repo.database.exists(old);
+                    let fast_forward = known && remote::is_fast_forward(&mut repo.database, old, new)?;
+                    let range = |sep: &str| format!(
+                        "{}{}{}",
+                        repo.database.short_oid(old),
+                        sep,
+                        repo.database.short_oid(new)
+                    );
+
+                    if fast_forward {
+                        (' ', range(".."), String::new())
+                    } else if spec.force || leased {
+                        ('+', range("..."), " (forced update)".to_string())
+                    } else {
+                        rejected = true;
+                        let reason = if known { "non-fast-forward" } else { "fetch first" };
+                        lines.push(format!(" ! {:<17} {} -> {} ({})", "[rejected]", from, to, reason));
+                        continue;
+                    }
+                },
+            };
+
+            let display = if spec.new.is_none() { to.clone() } else { format!("{} -> {}", from, to) };
+            // Remember which report line belongs to the update
+            lines.push(format!(" {} {:<17} {}{}", flag, summary, display, note));
+            updates.push((RefUpdate {
+                name: spec.destination.clone(),
+                old,
+                new: spec.new.clone(),
+            }, lines.len() - 1, display));
+        }
+
+        if !updates.is_empty() {
+            // Send what the remote is missing: everything reachable from the new
+            // values that is not reachable from a remote ref we also have
+            let tips: Vec<String> = updates.iter().filter_map(|(update, _, _)| update.new.clone()).collect();
+            let known: Vec<String> = advertised.values()
+                .filter(|oid| repo.database.exists(oid))
+                .cloned()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+
+            // Sent in batches the remote stores as they arrive, so an
+            // interrupted push of the same refs skips what it already has
+            let objects = protocol::pack_objects(&mut repo.database, &tips, &known, None)?;
+            let mut progress = PushProgress::open(&git_path, &remote.url, &tips, &known)?;
+            let mut throttle = Throttle::from_config(&Config::load(&git_path)?)?;
+            for batch in objects.get(progress.sent()..).unwrap_or_default().chunks(PUSH_BATCH_SIZE) {
+                throttle.consume(batch.iter().map(|object| object.data.len()).sum());
+                transport.send_objects(batch, &protocol::checksum(batch))?;
+                progress.record(batch.len())?;
+            }
+            progress.finish();
+            trace!(
+                "Sent {} objects ({} as deltas)",
+                objects.len(),
+                objects.iter().filter(|object| object.base.is_some()).count()
+            );
+
+            let requests: Vec<RefUpdate> = updates.iter().map(|(update, _, _)| update.clone()).collect();
+            let results = transport.update_refs(&requests)?;
+            for ((update, line, display), result) in updates.iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        // A mirror has no remote-tracking refs of its own
+                        if let (Some(name), false) = (&remote.name, mirror) {
+                            Self::update_tracking_ref(&repo, name, update)?;
+                        }
+                    },
+                    Err(reason) => {
+                        rejected = true;
+                        lines[*line] = format!(" ! {:<17} {} ({})", "[remote rejected]", display, reason);
+                    }
+                }
+            }
+        }
+
+        if lines.is_empty() {
+            eprintln!("Everything up-to-date");
+        } else if !crate::core::trace::is_quiet() || rejected {
+            eprintln!("To {}", remote.url);
+            for line in lines {
+                eprintln!("{}", line);
+            }
+        }
+
+        if rejected {
+            return Err(Error::Generic(format!("failed to push some refs to '{}'", remote.url)));
+        }
+        Ok(())
+    }
+
+    // Every local ref under refs/ as it is, and a deletion for every
+    // remote ref we do not have
+    fn mirror_specs(repo: &Repository, advertised: &BTreeMap<String, String>) -> Result<Vec<PushSpec>, Error> {
+        let local = protocol::advertise_refs(repo)?;
+
+        let mut specs: Vec<PushSpec> = local.iter()
+            .filter(|(name, _)| name.starts_with("refs/"))
+            .map(|(name, oid)| PushSpec {
+                source: name.clone(),
+                new: Some(oid.clone()),
+                destination: name.clone(),
+                force: true,
+            })
+            .collect();
+        specs.extend(advertised.keys()
+            .filter(|name| name.starts_with("refs/") && !local.contains_key(*name))
+            .map(|name| PushSpec {
+                source: String::new(),
+                new: None,
+                destination: name.clone(),
+                force: true,
+            }));
+        specs.sort_by(|a, b| a.destination.cmp(&b.destination));
+        Ok(specs)
+    }
+
+    fn parse_lease(lease: &str) -> Lease {
+        match lease.split_once(':') {
+            Some((name, expect)) => Lease { name: Some(name.to_string()), expect: Some(expect.to_string()) },
+            None if lease.is_empty() => Lease { name: None, expect: None },
+            None => Lease { name: Some(lease.to_string()), expect: None },
+        }
+    }
+
+    // A lease naming the destination wins over one covering every ref
+    fn lease_for<'a>(leases: &'a [Lease], destination: &str) -> Option<&'a Lease> {
+        let named = leases.iter().rev().find(|lease| match &lease.name {
+            Some(name) => name == destination
+                || format!("refs/heads/{}", name) == destination
+                || format!("refs/tags/{}", name) == destination,
+            None => false,
+        });
+        named.or_else(|| leases.iter().rev().find(|lease| lease.name.is_none()))
+    }
+
+    // Where the remote ref should be, None for "must not exist": the given
+    // value, or else the remote-tracking ref of the destination
+    fn expected_value(repo: &mut Repository, remote: &Remote, lease: &Lease, destination: &str) -> Result<Option<String>, Error> {
+        let to = short_ref_name(destination);
+        match lease.expect.as_deref() {
+            Some("") => Ok(None),
+            Some(expect) => {
+                let mut revision = Revision::new(repo, expect);
+                match revision.resolve("commit") {
+                    Ok(oid) => Ok(Some(oid)),
+                    Err(_) if expect.len() == 40 && expect.chars().all(|c| c.is_ascii_hexdigit()) => Ok(Some(expect.to_string())),
+                    Err(_) => Err(Error::Generic(format!("cannot parse expected object name '{}'", expect))),
+                }
+            },
+            None => {
+                let tracking = remote.fetch_specs.iter()
+                    .find_map(|spec| spec.map(destination).flatten())
+                    .ok_or_else(|| Error::Generic(format!(
+                        "cannot force-with-lease {}: no remote-tracking branch, give the expected value with --force-with-lease={}:<expect>",
+                        to, to
+                    )))?;
+                repo.refs.read_ref(&tracking)
+            },
+        }
+    }
+
+    // `[+]<src>[:<dst>]`: an empty source deletes the destination, and a
+    // destination may be left out when the source is a branch
+    fn parse_spec(repo: &mut Repository, spec: &str, force: bool) -> Result<PushSpec, Error> {
+        let (spec_force, spec) = match spec.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+        let (source, destination) = match spec.split_once(':') {
+            Some((source, destination)) => (source, Some(destination)),
+            None => (spec, None),
+        };
+
+        let branch = source.strip_prefix("refs/heads/").unwrap_or(source);
+        let is_branch = repo.refs.read_ref(&format!("refs/heads/{}", branch))?.is_some();
+
+        let destination = match destination {
+            Some(destination) if destination.starts_with("refs/") => destination.to_string(),
+            Some(destination) if !destination.is_empty() => format!("refs/heads/{}", destination),
+            _ if is_branch => format!("refs/heads/{}", branch),
+            _ => {
+                return Err(Error::Generic(format!(
+                    "The destination you provided is not a full refname: '{}'", spec
+                )));
+            }
+        };
+
+        let new = if source.is_empty() {
+            None
+        } else {
+            let mut revision = Revision::new(repo, source);
+            match revision.resolve("commit") {
+                Ok(oid) => Some(oid),
+                Err(_) => return Err(Error::Generic(format!("src refspec {} does not match any", source))),
+            }
+        };
+
+        Ok(PushSpec {
+            source: if is_branch { format!("refs/heads/{}", branch) } else { source.to_string() },
+            new,
+            destination,
+            force: force || spec_force,
+        })
+    }
+
+    // Keep refs/remotes/<remote>/<branch> in step with what was pushed
+    fn update_tracking_ref(repo: &Repository, remote: &str, update: &RefUpdate) -> Result<(), Error> {
+        let branch = match update.name.strip_prefix("refs/heads/") {
+            Some(branch) => branch,
+            None => return Ok(()),
+        };
+        let tracking = format!("refs/remotes/{}/{}", remote, branch);
+        match &update.new {
+            Some(oid) => repo.refs.update_ref(&tracking, oid, "update by push"),
+            None => repo.refs.delete_ref(&tracking),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+// src/commands/read_tree.rs - read trees into the index
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+/// How `ash read-tree` reads its trees
+#[derive(Debug, Clone, Default)]
+pub struct ReadTreeOptions {
+    /// Merge the trees rather than reading one over the index: with two,
+    /// move the index from the first to the second; with three, merge the
+    /// base, ours and theirs, leaving what cannot be settled as conflicts
+    pub merge: bool,
+    /// Check the files out to the working tree too
+    pub update: bool,
+    /// Read the tree under this directory, keeping the rest of the index
+    pub prefix: Option<String>,
+    /// Empty the index instead of reading a tree
+    pub empty: bool,
+}
+
+// What a path comes to in the index
+enum Outcome {
+    Entry(DatabaseEntry),
+    // Base, ours and theirs
+    Conflict(Vec<Option<DatabaseEntry>>),
+}
+
+pub struct ReadTreeCommand;
+
+impl ReadTreeCommand {
+    /// Read `trees` into the index as `options` say. Entries that the
+    /// trees leave as the index had them keep their cached file data.
+    pub fn execute(trees: &[String], options: &ReadTreeOptions) -> Result<(), Error> {
+        match (trees.len(), options.merge, options.empty) {
+            (0, _, true) => {},
+            (_, _, true) => return Err(Error::Generic("passing trees as arguments contradicts --empty".to_string())),
+            (0, _, false) => return Err(Error::Generic("usage: ash read-tree [-m] [-u] [--prefix=<prefix>] [--empty] <tree-ish> [<tree-ish2> [<tree-ish3>]]".to_string())),
+            (1, _, false) => {},
+            (2 | 3, true, false) if options.prefix.is_none() => {},
+            (2 | 3, true, false) => return Err(Error::Generic("--prefix reads a single tree".to_string())),
+            (2 | 3, false, false) => return Err(Error::Generic("read-tree: more than one tree needs -m".to_string())),
+            _ => return Err(Error::Generic("I cannot read more than 3 trees".to_string())),
+        }
+        if options.update && !options.merge && options.prefix.is_none() {
+            return Err(Error::Generic("-u is meaningless without -m or --prefix".to_string()));
+        }
+
+        let mut repo = Repository::new(".")?;
+        let mut files = Vec::new();
+        for tree in trees {
+            files.push(Self::tree_files(&mut repo, tree)?);
+        }
+
+        repo.index.load_for_update()?;
+        let result = Self::read(&mut repo, &files, options);
+        if result.is_err() {
+            repo.index.rollback()?;
+            return result;
+        }
+        repo.index.write_updates()?;
+        Ok(())
+    }
+
+    fn read(repo: &mut Repository, files: &[HashMap<String, DatabaseEntry>], options: &ReadTreeOptions) -> Result<(), Error> {
+        if options.merge && repo.index.has_conflict() {
+            return Err(Error::Generic("you need to resolve your current index first".to_string()));
+        }
+        // What the index has now, merged paths only
+        let index: HashMap<String, DatabaseEntry> = repo.index.each_entry()
+            .filter(|entry| entry.stage == 0)
+            .map(|entry| (entry.get_path().to_string(), DatabaseEntry::new(entry.get_path().to_string(), entry.get_oid().to_string(), &entry.mode_octal())))
+            .collect();
+
+        let mut outcomes: BTreeMap<String, Option<Outcome>> = BTreeMap::new();
+        match files {
+            [] => {
+                for path in index.keys() {
+                    outcomes.insert(path.clone(), None);
+                }
+            },
+            [tree] => match &options.prefix {
+                Some(prefix) => {
+                    let prefix = prefix.trim_end_matches('/');
+                    if index.keys().any(|path| path == prefix || path.starts_with(&format!("{}/", prefix))) {
+                        return Err(Error::Generic(format!("subdirectory '{}' already exists.", prefix)));
+                    }
+                    for (path, entry) in tree {
+                        let path = format!("{}/{}", prefix, path);
+                        outcomes.insert(path.clone(), Some(Outcome::Entry(DatabaseEntry::new(path, entry.get_oid().to_string(), entry.get_mode()))));
+                    }
+                },
+                None => {
+                    for path in index.keys().chain(tree.keys()) {
+                        outcomes.insert(path.clone(), tree.get(path).cloned().map(Outcome::Entry));
+                    }
+                },
+            },
+            [old, new] => {
+                let paths: BTreeSet<&String> = index.keys().chain(old.keys()).chain(new.keys()).collect();
+                for path in paths {
+                    let (current, old, new) = (index.get(path), old.get(path), new.get(path));
+                    if Self::same(old, new) || Self::same(current, new) {
+                        outcomes.insert(path.clone(), current.cloned().map(Outcome::Entry));
+                    } else if Self::same(current, old) {
+                        outcomes.insert(path.clone(), new.cloned().map(Outcome::Entry));
+                    } else {
+                        return Err(Self::overwritten(path));
+                    }
+                }
+            },
+            [base, ours, theirs] => {
+                let paths: BTreeSet<&String> = index.keys().chain(base.keys()).chain(ours.keys()).chain(theirs.keys()).collect();
+                for path in paths {
+                    let (base, ours, theirs) = (base.get(path), ours.get(path), theirs.get(path));
+                    let outcome = if Self::same(ours, theirs) || Self::same(base, theirs) {
+                        ours.cloned().map(Outcome::Entry)
+                    } else if Self::same(base, ours) {
+                        theirs.cloned().map(Outcome::Entry)
+                    } else {
+                        Some(Outcome::Conflict(vec![base.cloned(), ours.cloned(), theirs.cloned()]))
+                    };
+                    // The index may only differ from ours where ours is
+                    // kept, and then it stays as it is
+                    let kept = matches!(&outcome, Some(Outcome::Entry(entry)) if Self::same(Some(entry), ours))
+                        || (outcome.is_none() && ours.is_none());
+                    if kept {
+                        outcomes.insert(path.clone(), index.get(path).cloned().map(Outcome::Entry));
+                    } else if Self::same(index.get(path), ours) {
+                        outcomes.insert(path.clone(), outcome);
+                    } else {
+                        return Err(Self::overwritten(path));
+                    }
+                }
+            },
+            _ => unreachable!("at most three trees are read"),
+        }
+
+        if options.update {
+            Self::check_worktree(repo, &index, &outcomes)?;
+        }
+        for (path, outcome) in outcomes {
+            let current = index.get(&path);
+            let file_path = Path::new(&path);
+            match outcome {
+                Some(Outcome::Entry(entry)) if Self::same(current, Some(&entry)) => {},
+                Some(Outcome::Entry(entry)) => {
+                    let data = repo.database.load(entry.get_oid())?.to_bytes();
+                    if options.update {
+                        repo.workspace.write_file(file_path, &data)?;
+                        let stat = repo.workspace.stat_file(file_path)?;
+                        repo.index.add(file_path, entry.get_oid(), &stat)?;
+                    } else {
+                        repo.index.add_blob(file_path, entry.get_oid(), entry.get_file_mode(), data.len());
+                    }
+                },
+                Some(Outcome::Conflict(entries)) => repo.index.add_conflict(file_path, entries),
+                None => {
+                    if current.is_some() {
+                        repo.index.remove(file_path)?;
+                        if options.update {
+                            repo.workspace.remove_file(file_path)?;
+                        }
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    // With -u, the files to be replaced or removed must be as the index
+    // has them, and no untracked file may be in the way of a new one
+    fn check_worktree(
+        repo: &mut Repository,
+        index: &HashMap<String, DatabaseEntry>,
+        outcomes: &BTreeMap<String, Option<Outcome>>,
+    ) -> Result<(), Error> {
+        for (path, outcome) in outcomes {
+            let current = index.get(path);
+            let changes = match outcome {
+                Some(Outcome::Entry(entry)) => !Self::same(current, Some(entry)),
+                Some(Outcome::Conflict(_)) => false,
+                None => current.is_some(),
+            };
+            let file_path = Path::new(path);
+            if !changes || !repo.workspace.path_exists(file_path)? {
+                continue;
+            }
+            match current {
+                Some(current) => {
+                    let data = repo.workspace.read_file(file_path)?;
+                    if repo.database.hash_file_data(&data) != current.get_oid() {
+                        return Err(Error::Generic(format!("Entry '{}' not uptodate. Cannot merge.", path)));
+                    }
+                },
+                None => return Err(Error::Generic(format!("Untracked working tree file '{}' would be overwritten by merge.", path))),
+            }
+        }
+        Ok(())
+    }
+
+    // Whether two entries, either of which may be missing, are the same
+    fn same(a: Option<&DatabaseEntry>, b: Option<&DatabaseEntry>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a.get_oid() == b.get_oid() && a.get_file_mode() == b.get_file_mode(),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn overwritten(path: &str) -> Error {
+        Error::Generic(format!("Entry '{}' would be overwritten by merge. Cannot merge.", path))
+    }
+
+    // Every file of the tree `name` names, directly or through a commit
+    fn tree_files(repo: &mut Repository, name: &str) -> Result<HashMap<String, DatabaseEntry>, Error> {
+        let oid = Revision::new(repo, name).resolve(COMMIT)
+            .or_else(|_| Revision::new(repo, name).resolve("tree"))
+            .map_err(|_| Error::Generic(format!("Not a valid object name {}", name)))?;
+        head_files(repo, &oid)
+    }
+}
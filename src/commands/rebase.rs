@@ -0,0 +1,773 @@
+// src/commands/rebase.rs - replay the commits of a branch onto another base
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::errors::error::Error;
+use crate::commands::commit::get_editor_command;
+use crate::commands::commit_writer::CommitWriter;
+use crate::commands::reset::{ResetCommand, ORIG_HEAD};
+use crate::core::advice;
+use crate::core::config::Config;
+use crate::core::database::author::Author;
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
+use crate::core::merge::bases::Bases;
+use crate::core::merge::inputs::CherryPick;
+use crate::core::merge::resolve::Resolve;
+use crate::core::refs::{Reference, Refs, HEAD};
+use crate::core::remote::refspec::short_ref_name;
+use crate::core::remote::upstream::Upstream;
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::git_dir;
+use crate::core::repository::inspector::Inspector;
+use crate::core::repository::repository::Repository;
+use crate::core::repository::sequencer::{Action, Sequencer, Step};
+use crate::core::revision::{Revision, COMMIT};
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::trace;
+
+// Under the repository's git dir, next to the cherry-pick sequencer's
+const REBASE_DIR: &str = "rebase-merge";
+const HEAD_NAME_OPTION: &str = "head-name";
+const ONTO_OPTION: &str = "onto";
+// The commit an `edit` stopped at, while it is stopped
+const AMEND_STATE: &str = "amend";
+// The command that failed, while the rebase is stopped after it
+const EXEC_STATE: &str = "exec-failed";
+// Where the labels of a rebase with merges are kept while it runs
+const LABELS_DIR: &str = "refs/rewritten";
+// The label for the commit the rebase starts from
+const ONTO_LABEL: &str = "onto";
+
+/// The variable naming the editor for the todo list, ahead of the ones
+/// for commit messages
+pub const SEQUENCE_EDITOR_VARIABLE: &str = "GIT_SEQUENCE_EDITOR";
+
+const CONFLICT_ADVICE: &str = "\
+Resolve all conflicts manually, mark them as resolved with
+'ash add <paths>', then run 'ash rebase --continue'.
+You can instead skip this commit: run 'ash rebase --skip'.
+To abort and get back to the state before 'ash rebase', run 'ash rebase --abort'.";
+
+const TODO_HELP: &str = "\
+Commands:
+p, pick <commit> = use commit
+r, reword <commit> = use commit, but edit the commit message
+e, edit <commit> = use commit, but stop for amending
+s, squash <commit> = use commit, but meld into previous commit
+f, fixup <commit> = like \"squash\", but discard this commit's log message
+d, drop <commit> = remove commit
+x, exec <command> = run command (the rest of the line) using shell
+l, label <label> = label current HEAD with a name
+t, reset <label> = reset HEAD to a label
+m, merge [-C <commit>] <label> [# <oneline>] = create a merge commit, with
+        the message of the original merge commit if one is given
+
+These lines can be re-ordered; they are executed from top to bottom.
+
+If you remove a line here THAT COMMIT WILL BE LOST.
+
+However, if you remove everything, the rebase will be aborted.";
+
+#[derive(Debug, Clone, Default)]
+pub struct RebaseOptions {
+    pub onto: Option<String>,
+    pub interactive: bool,
+    /// Shell commands to run after each commit is made
+    pub exec: Vec<String>,
+    /// Recreate merge commits rather than flatten the history they join
+    pub rebase_merges: bool,
+}
+
+pub struct RebaseCommand;
+
+impl RebaseCommand {
+    /// Replay the commits of the current branch that are not in `upstream`
+    /// (the branch's upstream by default) onto `options.onto`, by default
+    /// `upstream` itself. Stops at the first commit that does not apply
+    /// cleanly, to be resumed with `--continue` or `--skip`, or undone with
+    /// `--abort`.
+    pub fn execute(upstream: Option<&str>, options: &RebaseOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        if Self::sequencer(&git_path).in_progress() {
+            return Err(Error::Generic(format!(
+                "It seems that there is already a {} directory.\n\
+                 Try 'ash rebase (--continue | --abort | --skip)' to finish the rebase in progress.",
+                REBASE_DIR
+            )));
+        }
+
+        let branch = match repo.refs.current_ref()? {
+            Reference::Symbolic(path) if path != HEAD => short_ref_name(&path).to_string(),
+            _ => return Err(Error::Generic("You are not currently on a branch.".to_string())),
+        };
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+
+        let upstream = match upstream {
+            Some(upstream) => upstream.to_string(),
+            None => Self::tracked_upstream(&git_path, &branch)?,
+        };
+        let upstream_oid = Self::resolve(&mut repo, &upstream)?;
+        let onto_oid = match &options.onto {
+            Some(onto) => Self::resolve(&mut repo, onto)?,
+            None => upstream_oid.clone(),
+        };
+
+        Self::start(repo, &branch, &head_oid, &upstream_oid, &onto_oid, options)
+    }
+
+    /// Rebase `branch`, checked out at `head_oid`: reset it to `onto` and
+    /// re-create the commits it has that `upstream` does not on top, one by
+    /// one. Merge commits are dropped, their changes come from the others,
+    /// unless `options.rebase_merges` asks for the branches they join to be
+    /// rebuilt and merged again. With `options.interactive` the list of commits is first handed to
+    /// the user to rearrange. The `options.exec` commands are run after
+    /// each commit, the rebase stopping at the first that fails.
+    /// ORIG_HEAD keeps where the branch was.
+    pub fn start(mut repo: Repository, branch: &str, head_oid: &str, upstream: &str, onto: &str, options: &RebaseOptions) -> Result<(), Error> {
+        // The worktree is rewritten from scratch, so it must be clean
+        if Self::has_local_changes(&mut repo, head_oid)? {
+            return Err(Error::Generic(
+                "cannot rebase: You have unstaged or uncommitted changes.\nPlease commit or stash them.".to_string()
+            ));
+        }
+
+        let bases = Bases::new(&mut repo.database)?.find(head_oid, upstream)?;
+        if bases.is_empty() {
+            return Err(Error::Generic("refusing to rebase onto unrelated history".to_string()));
+        }
+        if !options.interactive && options.exec.is_empty() && upstream == onto && bases == [onto] {
+            println!("Current branch {} is up to date.", branch);
+            return Ok(());
+        }
+
+        let mut walked = Vec::new();
+        if options.rebase_merges {
+            // Every side of the merges, not only the first parents
+            let range = [format!("^{}", upstream), head_oid.to_string()];
+            walked = RevList::new(&mut repo.database, &repo.refs, &range, false)?
+                .commits(&WalkOptions::default())?
+                .into_iter()
+                .map(|walked| walked.commit)
+                .collect();
+        } else {
+            let mut revlist = RevList::new(&mut repo.database, &repo.refs, &[format!("{}..{}", upstream, head_oid)], true)?;
+            while let Some(commit) = revlist.next() {
+                walked.push(commit?);
+            }
+        }
+        let commits = Self::parents_first(walked);
+
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Self::sequencer(&git_path);
+        let options_file = HashMap::from([
+            (HEAD_NAME_OPTION.to_string(), format!("refs/heads/{}", branch)),
+            (ONTO_OPTION.to_string(), onto.to_string()),
+        ]);
+        sequencer.start(&options_file)?;
+        if options.rebase_merges {
+            Self::plan_merges(&mut sequencer, commits, head_oid, &options.exec);
+        } else {
+            for commit in commits.into_iter().filter(|commit| commit.get_parents().len() == 1) {
+                sequencer.add_pick(commit);
+                for command in &options.exec {
+                    sequencer.add_exec(command);
+                }
+            }
+        }
+
+        if options.interactive {
+            let help = format!(
+                "Rebase {}..{} onto {} ({} commands)\n\n{}",
+                repo.database.short_oid(upstream),
+                repo.database.short_oid(head_oid),
+                repo.database.short_oid(onto),
+                sequencer.commands().len(),
+                TODO_HELP
+            );
+            let planned = sequencer.edit_todo(Self::sequence_editor(), &help)
+                .and_then(|_| Self::check_todo(&sequencer));
+            if let Err(e) = planned {
+                sequencer.quit()?;
+                return Err(e);
+            }
+            if sequencer.commands().is_empty() {
+                sequencer.quit()?;
+                return Err(Error::Generic("Nothing to do".to_string()));
+            }
+        }
+
+        Self::reset_hard(&git_path, onto, head_oid)?;
+        Self::run(&mut sequencer)
+    }
+
+    /// Go on with a rebase stopped at a conflict, an `edit` or a failed
+    /// `exec`. After a conflict, the resolution in the index is committed
+    /// as the command that stopped would have, unless it was committed
+    /// already or resolved to nothing; a merge is committed unless it was
+    /// already. At an `edit`, changes left staged amend the commit it
+    /// stopped at.
+    pub fn continue_rebase() -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        repo.index.load()?;
+        if repo.index.has_conflict() {
+            return Err(Error::Generic(
+                "You must edit all merge conflicts and then\nmark them as resolved using 'ash add'".to_string()
+            ));
+        }
+
+        let mut sequencer = Self::load(&git_path)?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+
+        let resumed = if sequencer.read_state(EXEC_STATE).is_some() {
+            sequencer.remove_state(EXEC_STATE)
+        } else if let Some(amended) = sequencer.read_state(AMEND_STATE) {
+            sequencer.remove_state(AMEND_STATE)?;
+            if amended == head_oid && !Self::index_matches(&mut repo, &head_oid)? {
+                let head = Self::load_commit(&mut repo, &head_oid)?;
+                Self::commit(&mut repo, &git_path, head.get_parents(), head.get_message(), head.get_author().cloned(), &Action::Edit.to_string(), false)
+            } else {
+                Ok(())
+            }
+        } else if let Some((action, commit)) = sequencer.next_command() {
+            Self::record(&mut repo, &git_path, &head_oid, action, &commit)
+                .and_then(|_| sequencer.drop_command())
+        } else if let Some(Step::Merge(commit, parents)) = sequencer.next_step() {
+            Self::continue_merge(&mut repo, &git_path, &head_oid, commit.as_deref(), &parents)
+                .and_then(|_| sequencer.drop_command())
+        } else {
+            Ok(())
+        };
+        if let Err(e) = resumed {
+            sequencer.dump()?;
+            return Err(e);
+        }
+
+        Self::run(&mut sequencer)
+    }
+
+    /// Go on with a rebase stopped at a conflict, leaving out the commit
+    /// that did not apply
+    pub fn skip() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let mut sequencer = Self::load(&git_path)?;
+
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+        Self::reset_hard(&git_path, &head_oid, &sequencer.original_head()?)?;
+        // At an `edit` or after an `exec` the commit is in already, there is
+        // nothing to leave out
+        if sequencer.read_state(AMEND_STATE).is_some() {
+            sequencer.remove_state(AMEND_STATE)?;
+        } else if sequencer.read_state(EXEC_STATE).is_some() {
+            sequencer.remove_state(EXEC_STATE)?;
+        } else {
+            sequencer.drop_command()?;
+        }
+        Self::run(&mut sequencer)
+    }
+
+    /// Give up on a rebase, putting the branch back where it was
+    pub fn abort() -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let sequencer = Self::load(&git_path)?;
+
+        let original = sequencer.original_head()?;
+        Self::reset_hard(&git_path, &original, &original)?;
+        Self::remove_labels(&repo.refs)?;
+        sequencer.quit()
+    }
+
+    // Carry out the commands left, stopping at the first conflict, `edit`
+    // or failed `exec` with the rest saved for --continue
+    fn run(sequencer: &mut Sequencer) -> Result<(), Error> {
+        let head_name = sequencer.get_option(HEAD_NAME_OPTION)?.unwrap_or_else(|| HEAD.to_string());
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        while let Some(step) = sequencer.next_step() {
+            let (action, commit) = match step {
+                Step::Commit(action, commit) => (action, *commit),
+                Step::Exec(command) => {
+                    sequencer.drop_command()?;
+                    if !Self::exec(&repo.path, &command)? {
+                        sequencer.write_state(EXEC_STATE, &command)?;
+                        sequencer.dump()?;
+                        return Err(Error::Generic(format!(
+                            "execution failed: {}\nYou can fix the problem, and then run\n\n  ash rebase --continue",
+                            command
+                        )));
+                    }
+                    continue;
+                },
+                Step::Label(name) => {
+                    let labelled = repo.refs.read_head()?
+                        .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))
+                        .and_then(|oid| repo.refs.update_ref(&format!("{}/{}", LABELS_DIR, name), &oid, "rebase (label)"));
+                    if let Err(e) = labelled {
+                        sequencer.dump()?;
+                        return Err(e);
+                    }
+                    sequencer.drop_command()?;
+                    continue;
+                },
+                Step::Reset(name) => {
+                    let original = sequencer.original_head()?;
+                    let reset = Self::lookup(&mut repo, &name)
+                        .and_then(|target| Self::reset_hard(&git_path, &target, &original));
+                    if let Err(e) = reset {
+                        sequencer.dump()?;
+                        return Err(e);
+                    }
+                    sequencer.drop_command()?;
+                    continue;
+                },
+                Step::Merge(commit, parents) => {
+                    let original = sequencer.original_head()?;
+                    if let Err(e) = Self::merge(&mut repo, &git_path, &original, commit.as_deref(), &parents) {
+                        sequencer.dump()?;
+                        eprintln!("error: could not merge {}", parents.join(" "));
+                        advice::advise(advice::MERGE_CONFLICT, CONFLICT_ADVICE);
+                        return Err(e);
+                    }
+                    sequencer.drop_command()?;
+                    continue;
+                },
+            };
+            let oid = commit.get_oid().cloned().unwrap_or_default();
+            if let Err(e) = Self::perform(&mut repo, &git_path, action, &commit) {
+                sequencer.dump()?;
+                eprintln!("error: could not apply {}... {}", repo.database.short_oid(&oid), commit.title_line().trim());
+                advice::advise(advice::MERGE_CONFLICT, CONFLICT_ADVICE);
+                return Err(e);
+            }
+            sequencer.drop_command()?;
+
+            if action == Action::Edit {
+                let head_oid = repo.refs.read_head()?.unwrap_or_default();
+                sequencer.write_state(AMEND_STATE, &head_oid)?;
+                sequencer.dump()?;
+                println!("Stopped at {}...  {}", repo.database.short_oid(&oid), commit.title_line().trim());
+                println!("You can amend the commit now, with\n\n  ash commit --amend\n");
+                println!("Once you are satisfied with your changes, run\n\n  ash rebase --continue");
+                return Ok(());
+            }
+        }
+
+        Self::remove_labels(&repo.refs)?;
+        sequencer.quit()?;
+        println!("Successfully rebased and updated {}.", head_name);
+        Ok(())
+    }
+
+    fn perform(repo: &mut Repository, git_path: &Path, action: Action, commit: &Commit) -> Result<(), Error> {
+        if action == Action::Drop {
+            return Ok(());
+        }
+        let head_oid = Self::apply(repo, commit)?;
+        Self::record(repo, git_path, &head_oid, action, commit)
+    }
+
+    // Apply the changes `commit` made to its parent onto HEAD, in the
+    // index and worktree; the HEAD they were applied to is returned
+    fn apply(repo: &mut Repository, commit: &Commit) -> Result<String, Error> {
+        let oid = commit.get_oid().cloned().unwrap_or_default();
+        let parent = commit.get_parent().cloned()
+            .ok_or_else(|| Error::Generic(format!("{} has no parent", oid)))?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let inputs = CherryPick::new(
+            HEAD.to_string(),
+            format!("{} ({})", repo.database.short_oid(&oid), commit.title_line().trim()),
+            head_oid.clone(),
+            oid,
+            vec![parent],
+        );
+        let result = Resolve::new(&mut repo.database, &repo.workspace, &mut repo.index, &inputs).execute();
+        repo.index.write_updates()?;
+        result?;
+        Ok(head_oid)
+    }
+
+    // Commit the changes of `commit` applied onto `head_oid` as `action`
+    // says: with the original author and message, an edited message, or
+    // melded into `head_oid`. A commit whose parent is `head_oid` is taken
+    // as it is, and one whose changes are already there is dropped.
+    fn record(repo: &mut Repository, git_path: &Path, head_oid: &str, action: Action, commit: &Commit) -> Result<(), Error> {
+        let oid = commit.get_oid().cloned().unwrap_or_default();
+        match action {
+            Action::Squash | Action::Fixup => Self::meld(repo, git_path, head_oid, action, commit),
+            Action::Pick | Action::Edit if commit.get_parent().map(String::as_str) == Some(head_oid) => {
+                trace!("Fast-forwarding to {}", oid);
+                repo.refs.update_head(&oid, &format!("rebase ({}): fast-forward", action))
+            },
+            _ if Self::index_matches(repo, head_oid)? => {
+                trace!("Dropping {}: its changes are already upstream", oid);
+                Ok(())
+            },
+            _ => Self::commit(
+                repo, git_path, vec![head_oid.to_string()], commit.get_message(), commit.get_author().cloned(),
+                &action.to_string(), action == Action::Reword,
+            ),
+        }
+    }
+
+    // Replace `head_oid` by a commit with its changes and those of `commit`,
+    // with both messages to edit for a squash, or only its own for a fixup
+    fn meld(repo: &mut Repository, git_path: &Path, head_oid: &str, action: Action, commit: &Commit) -> Result<(), Error> {
+        let head = Self::load_commit(repo, head_oid)?;
+        let message = match action {
+            Action::Squash => format!(
+                "# This is a combination of 2 commits.\n# This is the 1st commit message:\n\n{}\n# This is the commit message #2:\n\n{}",
+                head.get_message().trim_end(),
+                commit.get_message().trim_end()
+            ),
+            _ => head.get_message().to_string(),
+        };
+        Self::commit(repo, git_path, head.get_parents(), &message, head.get_author().cloned(), &action.to_string(), action == Action::Squash)
+    }
+
+    // Merge the commit `parents` names into HEAD, recreating `commit` if it
+    // is given. When HEAD and the parent are still those it was made on,
+    // it is taken as it is.
+    fn merge(repo: &mut Repository, git_path: &Path, original: &str, commit: Option<&Commit>, parents: &[String]) -> Result<(), Error> {
+        let name = match parents {
+            [name] => name,
+            _ => return Err(Error::Generic(format!("cannot merge more than one branch at a time: {}", parents.join(" ")))),
+        };
+        let other = Self::lookup(repo, name)?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))?;
+
+        if let Some(oid) = commit.filter(|commit| commit.get_parents() == [head_oid.clone(), other.clone()]).and_then(Commit::get_oid) {
+            trace!("Fast-forwarding to {}", oid);
+            return Self::reset_hard(git_path, oid, original);
+        }
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+        let bases = Bases::new(&mut repo.database)?.find(&head_oid, &other)?;
+        let inputs = CherryPick::new(HEAD.to_string(), name.clone(), head_oid.clone(), other.clone(), bases);
+        let result = Resolve::new(&mut repo.database, &repo.workspace, &mut repo.index, &inputs).execute();
+        repo.index.write_updates()?;
+        result?;
+        Self::commit_merge(repo, git_path, &head_oid, &other, commit, name)
+    }
+
+    // Commit a merge stopped at a conflict with its resolution, unless it
+    // was committed already
+    fn continue_merge(repo: &mut Repository, git_path: &Path, head_oid: &str, commit: Option<&Commit>, parents: &[String]) -> Result<(), Error> {
+        let name = parents.first().ok_or_else(|| Error::Generic("nothing to merge".to_string()))?;
+        let other = Self::lookup(repo, name)?;
+        let head = Self::load_commit(repo, head_oid)?;
+        if head.get_parents().get(1) == Some(&other) {
+            return Ok(());
+        }
+        Self::commit_merge(repo, git_path, head_oid, &other, commit, name)
+    }
+
+    // The merge of `other`, labelled `name`, into `head_oid`, with the
+    // message and author of `commit` if there is one
+    fn commit_merge(repo: &mut Repository, git_path: &Path, head_oid: &str, other: &str, commit: Option<&Commit>, name: &str) -> Result<(), Error> {
+        let (message, author) = match commit {
+            // Its parents are recorded anew
+            Some(commit) => {
+                let message: Vec<&str> = commit.get_message().lines()
+                    .filter(|line| !line.starts_with(MERGE_PARENT_TRAILER))
+                    .collect();
+                (format!("{}\n", message.join("\n").trim_end()), commit.get_author().cloned())
+            },
+            None => (format!("Merge branch '{}'\n", name), None),
+        };
+        Self::commit(repo, git_path, vec![head_oid.to_string(), other.to_string()], &message, author, "merge", false)
+    }
+
+    fn commit(
+        repo: &mut Repository,
+        git_path: &Path,
+        parents: Vec<String>,
+        message: &str,
+        author: Option<Author>,
+        action: &str,
+        edit: bool,
+    ) -> Result<(), Error> {
+        let mut writer = CommitWriter::new(
+            Path::new("."),
+            git_path.to_path_buf(),
+            &mut repo.database,
+            &mut repo.index,
+            &repo.refs,
+        );
+        writer.reflog_action = format!("rebase ({})", action);
+        let message = if edit {
+            writer.compose_message(get_editor_command(), Some(message))?
+                .ok_or_else(|| Error::Generic("Aborting commit due to empty commit message".to_string()))?
+        } else {
+            message.to_string()
+        };
+        writer.write_commit(parents, &message, author)?;
+        Ok(())
+    }
+
+    // Run `command` with the shell at the top of the working tree; whether
+    // it succeeded
+    fn exec(root_path: &Path, command: &str) -> Result<bool, Error> {
+        println!("Executing: {}", command);
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(root_path)
+            .status()
+            .map_err(|e| Error::Generic(format!("cannot run '{}': {}", command, e)))?;
+        Ok(status.success())
+    }
+
+    // A squash or fixup needs a commit before it to meld into
+    fn check_todo(sequencer: &Sequencer) -> Result<(), Error> {
+        let first = sequencer.commands().iter().find_map(|step| match step {
+            Step::Commit(action, _) if *action != Action::Drop => Some(action),
+            _ => None,
+        });
+        match first {
+            Some(action @ (Action::Squash | Action::Fixup)) => {
+                Err(Error::Generic(format!("cannot '{}' without a previous commit", action)))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // Plan the todo list that rebuilds `commits`, merges and all: each line
+    // of history is picked from where it branched off, the side a merge
+    // joins before the line it is merged into, and merged back as it was.
+    // Commits that a later line starts from or merges are labelled.
+    fn plan_merges(sequencer: &mut Sequencer, commits: Vec<Commit>, head_oid: &str, exec: &[String]) {
+        let by_oid: HashMap<String, Commit> = commits.into_iter()
+            .filter_map(|commit| commit.get_oid().cloned().map(|oid| (oid, commit)))
+            .collect();
+
+        // The order to make the commits in, parents first: the other parents
+        // of a merge, then its first parent, so that it comes right after
+        // the line it is made on
+        let mut order = Vec::new();
+        let mut placed = HashSet::new();
+        let mut pending = vec![(head_oid.to_string(), false)];
+        while let Some((oid, expanded)) = pending.pop() {
+            if placed.contains(&oid) || !by_oid.contains_key(&oid) {
+                continue;
+            }
+            if expanded {
+                placed.insert(oid.clone());
+                order.push(oid);
+                continue;
+            }
+            pending.push((oid.clone(), true));
+            let parents = by_oid[&oid].get_parents();
+            pending.extend(parents.iter().take(1).cloned().map(|parent| (parent, false)));
+            pending.extend(parents.iter().skip(1).rev().cloned().map(|parent| (parent, false)));
+        }
+
+        // Where HEAD has to be moved to first, if anywhere, and which
+        // commits need a label; None stands for `onto`
+        let mut labels: HashMap<String, String> = HashMap::new();
+        let mut taken: HashSet<String> = HashSet::from([ONTO_LABEL.to_string()]);
+        let mut label = |oid: &str, name: &str, labels: &mut HashMap<String, String>| {
+            if labels.contains_key(oid) {
+                return;
+            }
+            let mut unique = name.to_string();
+            let mut n = 1;
+            while !taken.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}-{}", name, n);
+            }
+            labels.insert(oid.to_string(), unique);
+        };
+        let mut resets = Vec::with_capacity(order.len());
+        let mut current: Option<String> = None;
+        for oid in &order {
+            let commit = &by_oid[oid];
+            let parents = commit.get_parents();
+            let base = parents.first().filter(|parent| by_oid.contains_key(*parent)).cloned();
+            resets.push(if base != current { Some(base.clone()) } else { None });
+            if let Some(base) = base.as_ref().filter(|_| base != current) {
+                label(base, "branch-point", &mut labels);
+            }
+            for parent in parents.iter().skip(1).filter(|parent| by_oid.contains_key(*parent)) {
+                label(parent, &Self::merged_branch(commit), &mut labels);
+            }
+            current = Some(oid.clone());
+        }
+
+        sequencer.add_label(ONTO_LABEL);
+        for (oid, reset) in order.iter().zip(resets) {
+            match reset {
+                Some(Some(base)) => sequencer.add_reset(&labels[&base]),
+                Some(None) => sequencer.add_reset(ONTO_LABEL),
+                None => {},
+            }
+            let commit = by_oid[oid].clone();
+            let parents = commit.get_parents();
+            if parents.len() > 1 {
+                let merged = parents.iter().skip(1)
+                    .map(|parent| labels.get(parent).cloned().unwrap_or_else(|| parent.clone()))
+                    .collect();
+                sequencer.add_merge(Some(commit), merged);
+            } else {
+                sequencer.add_pick(commit);
+            }
+            for command in exec {
+                sequencer.add_exec(command);
+            }
+            if let Some(name) = labels.get(oid) {
+                sequencer.add_label(name);
+            }
+        }
+    }
+
+    // A label for the side a merge joined: the branch its message names,
+    // as in "Merge branch 'topic'", made fit for a ref name
+    fn merged_branch(merge: &Commit) -> String {
+        let title = merge.title_line();
+        let name = title.split('\'').nth(1).unwrap_or("branch");
+        let name: String = name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '-' })
+            .collect();
+        if name.trim_matches(['-', '.']).is_empty() { "branch".to_string() } else { name }
+    }
+
+    // The commit a label, or any revision, stands for
+    fn lookup(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        if let Some(oid) = repo.refs.read_ref(&format!("{}/{}", LABELS_DIR, name))? {
+            return Ok(oid);
+        }
+        Revision::new(repo, name).resolve(COMMIT)
+            .map_err(|_| Error::Generic(format!("could not resolve '{}'", name)))
+    }
+
+    // Remove the labels left by a rebase with merges
+    fn remove_labels(refs: &Refs) -> Result<(), Error> {
+        for reference in refs.list_refs_under(LABELS_DIR)? {
+            if let Reference::Symbolic(name) = reference {
+                refs.delete_ref(&name)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn load_commit(repo: &mut Repository, oid: &str) -> Result<Commit, Error> {
+        repo.database.load(oid)?.as_any().downcast_ref::<Commit>().cloned()
+            .ok_or_else(|| Error::Generic(format!("{} is not a commit", oid)))
+    }
+
+    // The editor for the todo list: GIT_SEQUENCE_EDITOR, or the one for
+    // commit messages
+    fn sequence_editor() -> Option<String> {
+        env::var(SEQUENCE_EDITOR_VARIABLE).ok().or_else(get_editor_command)
+    }
+
+    // Order commits so that each comes after its parents; commits made in
+    // the same second would otherwise come out of the walk in any order
+    fn parents_first(commits: Vec<Commit>) -> Vec<Commit> {
+        let mut by_oid: HashMap<String, Commit> = commits.iter()
+            .filter_map(|commit| commit.get_oid().map(|oid| (oid.clone(), commit.clone())))
+            .collect();
+        let mut ordered = Vec::new();
+
+        for commit in commits.iter().rev() {
+            let mut stack: Vec<(String, bool)> = commit.get_oid().map(|oid| (oid.clone(), false)).into_iter().collect();
+            while let Some((oid, parents_done)) = stack.pop() {
+                if parents_done {
+                    if let Some(commit) = by_oid.remove(&oid) {
+                        ordered.push(commit);
+                    }
+                    continue;
+                }
+                let parents = match by_oid.get(&oid) {
+                    Some(commit) => commit.get_parents(),
+                    None => continue,
+                };
+                stack.push((oid, true));
+                stack.extend(parents.into_iter().rev().filter(|p| by_oid.contains_key(p)).map(|p| (p, false)));
+            }
+        }
+        ordered
+    }
+
+    /// Staged changes, or tracked files that differ from the index
+    pub fn has_local_changes(repo: &mut Repository, head_oid: &str) -> Result<bool, Error> {
+        repo.index.load()?;
+        if !Self::index_matches(repo, head_oid)? {
+            return Ok(true);
+        }
+
+        let inspector = Inspector::new(&repo.workspace, &repo.index, &repo.database);
+        Ok(!inspector.analyze_workspace_changes()?.is_empty())
+    }
+
+    // Whether the index holds exactly the files of `head_oid`
+    pub fn index_matches(repo: &mut Repository, head_oid: &str) -> Result<bool, Error> {
+        let head = head_files(repo, head_oid)?;
+        Ok(repo.index.each_entry().count() == head.len()
+            && repo.index.each_entry().all(|entry| {
+                head.get(entry.get_path()).map(|e| e.get_oid()) == Some(entry.get_oid())
+            }))
+    }
+
+    // Reset the worktree, index and branch to `target`, keeping ORIG_HEAD
+    // at where the branch was before the rebase
+    fn reset_hard(git_path: &Path, target: &str, original: &str) -> Result<(), Error> {
+        ResetCommand::execute(&[target.to_string()], false, false, true, true, None)?;
+        fs::write(git_path.join(ORIG_HEAD), format!("{}\n", original))?;
+        Ok(())
+    }
+
+    // The branch's upstream, as its remote-tracking branch
+    fn tracked_upstream(git_path: &Path, branch: &str) -> Result<String, Error> {
+        match Upstream::of(&Config::load(git_path)?, branch) {
+            Some(upstream) => Ok(upstream.tracking_ref(git_path)),
+            None => Err(Error::Generic(
+                "There is no tracking information for the current branch.\nPlease specify which branch you want to rebase against.".to_string()
+            )),
+        }
+    }
+
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        let mut revision = Revision::new(repo, name);
+        match revision.resolve(COMMIT) {
+            Ok(oid) => Ok(oid),
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn sequencer(git_path: &Path) -> Sequencer {
+        Sequencer::at(PathBuf::from(git_path), REBASE_DIR)
+    }
+
+    fn load(git_path: &Path) -> Result<Sequencer, Error> {
+        let mut sequencer = Self::sequencer(git_path);
+        if !sequencer.in_progress() {
+            return Err(Error::Generic("No rebase in progress?".to_string()));
+        }
+        sequencer.load()?;
+        Ok(sequencer)
+    }
+}
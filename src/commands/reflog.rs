@@ -0,0 +1,44 @@
+// src/commands/reflog.rs - show where a ref has pointed
+use crate::errors::error::Error;
+use crate::core::color::Color;
+use crate::core::pager::Pager;
+use crate::core::reflog::Reflog;
+use crate::core::refs::HEAD;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+
+pub struct ReflogCommand;
+
+impl ReflogCommand {
+    /// List the updates to `reference` (HEAD by default), newest first, as
+    /// `<commit> <ref>@{<n>}: <message>`; `<ref>@{<n>}` names that commit
+    /// in any revision argument
+    pub fn show(reference: Option<&str>) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let reflog = Reflog::new(git_dir::resolve(&repo.path));
+
+        let name = reference.unwrap_or(HEAD);
+        let entries = match reflog.resolve_name(name) {
+            Some(full_name) => reflog.read(&full_name)?,
+            None if repo.refs.read_ref(name)?.is_some() => Vec::new(),
+            None => return Err(Error::Generic(format!("ambiguous argument '{}': unknown revision or path not in the working tree.", name))),
+        };
+
+        let mut pager = Pager::new();
+        pager.start()?;
+        for (n, entry) in entries.iter().rev().enumerate() {
+            if pager.exited_early() {
+                break;
+            }
+            pager.write(&format!(
+                "{} {}@{{{}}}: {}\n",
+                Color::yellow(&repo.database.short_oid(&entry.new)),
+                name,
+                n,
+                entry.message
+            ))?;
+        }
+        pager.close()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,276 @@
+// src/commands/remote.rs - manage the set of remotes kept in .ash/config
+use std::fs;
+use std::path::Path;
+
+use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::refs::Reference;
+use crate::core::remote::refspec::{short_ref_name, Refspec};
+use crate::core::remote::transport;
+use crate::core::remote::{self, Remote};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+
+pub struct RemoteCommand;
+
+impl RemoteCommand {
+    /// Print the configured remotes, with their URLs when `verbose`
+    pub fn list(verbose: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let config = Config::load(&git_dir::resolve(&repo.path))?;
+
+        for name in config.subsections("remote") {
+            if verbose {
+                let url = config.get(&format!("remote.{}.url", name)).unwrap_or_default();
+                let push_url = config.get(&format!("remote.{}.pushurl", name)).unwrap_or_else(|| url.clone());
+                println!("{}\t{} (fetch)", name, url);
+                println!("{}\t{} (push)", name, push_url);
+            } else {
+                println!("{}", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a new remote with the default fetch refspec
+    pub fn add(name: &str, url: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let mut config = Config::load(&git_dir::resolve(&repo.path))?;
+
+        if !repo.refs.is_valid_branch_name(name) {
+            return Err(Error::Generic(format!("'{}' is not a valid remote name", name)));
+        }
+        if Self::exists(&config, name) {
+            return Err(Error::Generic(format!("remote {} already exists.", name)));
+        }
+
+        config.add(&format!("remote.{}.url", name), url)?;
+        config.add(&format!("remote.{}.fetch", name), &Self::default_fetch(name))?;
+        Ok(())
+    }
+
+    /// Forget a remote along with its remote-tracking refs
+    pub fn remove(name: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let mut config = Config::load(&git_dir::resolve(&repo.path))?;
+
+        if !config.remove_section(&format!("remote.{}", name))? {
+            return Err(Error::Generic(format!("No such remote: '{}'", name)));
+        }
+
+        for tracking in Self::tracking_refs(&repo, name)? {
+            repo.refs.delete_ref(&tracking)?;
+        }
+        Self::remove_empty_dirs(&git_dir::resolve(&repo.path).join("refs/remotes").join(name));
+        Ok(())
+    }
+
+    /// Rename a remote, moving its remote-tracking refs and updating fetch
+    /// refspecs that store into them
+    pub fn rename(old: &str, new: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let mut config = Config::load(&git_dir::resolve(&repo.path))?;
+
+        if !Self::exists(&config, old) {
+            return Err(Error::Generic(format!("No such remote: '{}'", old)));
+        }
+        if !repo.refs.is_valid_branch_name(new) {
+            return Err(Error::Generic(format!("'{}' is not a valid remote name", new)));
+        }
+        if Self::exists(&config, new) {
+            return Err(Error::Generic(format!("remote {} already exists.", new)));
+        }
+
+        let old_prefix = format!("refs/remotes/{}/", old);
+        let new_prefix = format!("refs/remotes/{}/", new);
+
+        let fetch_key = format!("remote.{}.fetch", old);
+        let specs = config.get_all(&fetch_key);
+        config.rename_section(&format!("remote.{}", old), &format!("remote.{}", new))?;
+
+        let fetch_key = format!("remote.{}.fetch", new);
+        config.unset_all(&fetch_key)?;
+        for spec in specs {
+            config.add(&fetch_key, &spec.replace(&old_prefix, &new_prefix))?;
+        }
+
+        for tracking in Self::tracking_refs(&repo, old)? {
+            // refs/remotes/<old>/HEAD moves along with the branch it names
+            if let Some(target) = repo.refs.symbolic_target(&tracking)? {
+                let renamed = tracking.replacen(&old_prefix, &new_prefix, 1);
+                repo.refs.set_symbolic_ref(&renamed, &target.replacen(&old_prefix, &new_prefix, 1))?;
+                repo.refs.delete_ref(&tracking)?;
+            } else if let Some(oid) = repo.refs.read_ref(&tracking)? {
+                let renamed = tracking.replacen(&old_prefix, &new_prefix, 1);
+                repo.refs.update_ref(&renamed, &oid, &format!("remote: renamed {} to {}", tracking, renamed))?;
+                repo.refs.delete_ref(&tracking)?;
+            }
+        }
+        Self::remove_empty_dirs(&git_dir::resolve(&repo.path).join("refs/remotes").join(old));
+        Ok(())
+    }
+
+    /// Describe a remote, asking it for its branches
+    pub fn show(name: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        if !Self::exists(&config, name) {
+            return Err(Error::Generic(format!("No such remote: '{}'", name)));
+        }
+        let remote = Remote::resolve(&git_path, name)?;
+        let push_url = config.get(&format!("remote.{}.pushurl", name)).unwrap_or_else(|| remote.url.clone());
+
+        println!("* remote {}", name);
+        println!("  Fetch URL: {}", remote.url);
+        println!("  Push  URL: {}", push_url);
+
+        let advertised = transport::connect(&remote.url).and_then(|mut t| t.advertised_refs())?;
+
+        // Branches that point where HEAD does; more than one is ambiguous
+        match remote::head_branches(&advertised).as_slice() {
+            [] => println!("  HEAD branch: (unknown)"),
+            [branch] => println!("  HEAD branch: {}", branch),
+            branches => {
+                println!("  HEAD branch (remote HEAD is ambiguous, may be one of the following):");
+                for branch in branches {
+                    println!("    {}", branch);
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        for ref_name in advertised.keys().filter(|r| r.starts_with("refs/heads/")) {
+            let branch = &ref_name["refs/heads/".len()..];
+            let tracking = remote.fetch_specs.iter().find_map(|spec: &Refspec| spec.map(ref_name).flatten());
+            let state = match tracking {
+                Some(local) if repo.refs.read_ref(&local)?.is_some() => "tracked".to_string(),
+                Some(local) => format!("new (next fetch will store in {})", local.trim_start_matches("refs/")),
+                None => "skipped".to_string(),
+            };
+            lines.push((branch.to_string(), state));
+        }
+        for tracking in Self::tracking_refs(&repo, name)? {
+            let branch = &tracking[format!("refs/remotes/{}/", name).len()..];
+            if !advertised.contains_key(&format!("refs/heads/{}", branch)) {
+                lines.push((branch.to_string(), "stale (use 'ash remote prune' to remove)".to_string()));
+            }
+        }
+
+        if !lines.is_empty() {
+            println!("  Remote branch{}:", if lines.len() == 1 { "" } else { "es" });
+            let width = lines.iter().map(|(branch, _)| branch.len()).max().unwrap_or(0);
+            for (branch, state) in lines {
+                println!("    {:<width$} {}", branch, state, width = width);
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the remote-tracking refs whose branches the remote no longer
+    /// has, or with `dry_run` just list them
+    pub fn prune(name: &str, dry_run: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        if !Self::exists(&config, name) {
+            return Err(Error::Generic(format!("No such remote: '{}'", name)));
+        }
+        let remote = Remote::resolve(&git_path, name)?;
+        let advertised = transport::connect(&remote.url).and_then(|mut t| t.advertised_refs())?;
+
+        let stale = remote::stale_refs(&repo.refs, &remote.fetch_specs, &advertised)?;
+        if stale.is_empty() {
+            return Ok(());
+        }
+
+        println!("Pruning {}", name);
+        println!("URL: {}", remote.url);
+        for tracking in stale {
+            if dry_run {
+                println!(" * [would prune] {}", short_ref_name(&tracking));
+            } else {
+                repo.refs.delete_ref(&tracking)?;
+                println!(" * [pruned] {}", short_ref_name(&tracking));
+            }
+        }
+        Ok(())
+    }
+
+    /// Set refs/remotes/<name>/HEAD, the remote's default branch, to
+    /// `branch`, or with `auto` to the branch the remote HEAD is on, or
+    /// with `delete` remove it. The remote-tracking branch must exist.
+    pub fn set_head(name: &str, branch: Option<&str>, auto: bool, delete: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        if !Self::exists(&config, name) {
+            return Err(Error::Generic(format!("No such remote: '{}'", name)));
+        }
+        let head_ref = format!("refs/remotes/{}/HEAD", name);
+
+        if delete {
+            if repo.refs.symbolic_target(&head_ref)?.is_none() {
+                return Err(Error::Generic(format!("Could not delete {}", head_ref)));
+            }
+            return repo.refs.delete_ref(&head_ref);
+        }
+
+        let branch = match (branch, auto) {
+            (Some(branch), false) => branch.to_string(),
+            (None, true) => {
+                let remote = Remote::resolve(&git_path, name)?;
+                let advertised = transport::connect(&remote.url).and_then(|mut t| t.advertised_refs())?;
+                remote::guess_head(&advertised)
+                    .map(|(branch, _)| branch)
+                    .ok_or_else(|| Error::Generic("Cannot determine remote HEAD".to_string()))?
+            },
+            _ => return Err(Error::Generic("usage: ash remote set-head <name> (-a | -d | <branch>)".to_string())),
+        };
+
+        let tracking = format!("refs/remotes/{}/{}", name, branch);
+        if repo.refs.read_ref(&tracking)?.is_none() {
+            return Err(Error::Generic(format!("Not a valid ref: {}", tracking)));
+        }
+        repo.refs.set_symbolic_ref(&head_ref, &tracking)?;
+        if auto {
+            println!("{}/HEAD set to {}", name, branch);
+        }
+        Ok(())
+    }
+
+    fn exists(config: &Config, name: &str) -> bool {
+        config.subsections("remote").iter().any(|remote| remote == name)
+    }
+
+    fn default_fetch(name: &str) -> String {
+        format!("+refs/heads/*:refs/remotes/{}/*", name)
+    }
+
+    // Full names of the refs under refs/remotes/<name>/
+    fn tracking_refs(repo: &Repository, name: &str) -> Result<Vec<String>, Error> {
+        Ok(repo.refs.list_refs_under(&format!("refs/remotes/{}", name))?
+            .into_iter()
+            .filter_map(|reference| match reference {
+                Reference::Symbolic(path) => Some(path),
+                _ => None,
+            })
+            .collect())
+    }
+
+    // Directories left behind once a remote's refs are gone; anything still
+    // holding a ref is kept
+    fn remove_empty_dirs(dir: &Path) {
+        if let Ok(entries) = fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    Self::remove_empty_dirs(&entry.path());
+                }
+            }
+        }
+        let _ = fs::remove_dir(dir);
+    }
+}
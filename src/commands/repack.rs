@@ -0,0 +1,165 @@
+// src/commands/repack.rs - gather objects into a pack
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::gc::GC_LOCK;
+use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::database::{bitmap, commit_graph};
+use crate::core::lockfile::Lockfile;
+use crate::core::refs::Reference;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::trace;
+
+const WRITE_BITMAPS_KEY: &str = "repack.writeBitmaps";
+
+/// Which indexes besides the pack get rebuilt; None leaves it to config
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IndexOptions {
+    /// `--[no-]write-commit-graph`
+    pub write_commit_graph: Option<bool>,
+    /// `--[no-]write-bitmaps`, else `repack.writeBitmaps`
+    pub write_bitmaps: Option<bool>,
+}
+
+pub struct RepackCommand;
+
+impl RepackCommand {
+    /// Pack the loose objects into a new pack. With `all`, objects already
+    /// in packs go into it too, so the result is a single pack; with
+    /// `delete`, whatever the new pack makes redundant is removed.
+    ///
+    /// `options` say whether the commit-graph is rewritten and whether the
+    /// new pack gets a bitmap, which needs `all` since only a pack holding
+    /// everything can have one. Only one repack or gc runs at a time.
+    pub fn execute(all: bool, delete: bool, options: IndexOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path)?;
+
+        let write_commit_graph = options.write_commit_graph.unwrap_or(false);
+        let mut write_bitmaps = options.write_bitmaps.or_else(|| config.get_bool(WRITE_BITMAPS_KEY)).unwrap_or(false);
+        if write_bitmaps && !all {
+            eprintln!("warning: disabling bitmap writing, as some objects are not being packed");
+            write_bitmaps = false;
+        }
+
+        let mut lock = Lockfile::new(git_path.join(GC_LOCK));
+        if lock.hold_for_update().is_err() {
+            return Err(Error::Lock(
+                "gc or repack is already running in this repository (see ash verify-repo-locks)".to_string()
+            ));
+        }
+        let result = Self::repack(&mut repo, all, delete, write_commit_graph, write_bitmaps);
+        let _ = lock.rollback();
+        result
+    }
+
+    fn repack(
+        repo: &mut Repository,
+        all: bool,
+        delete: bool,
+        write_commit_graph: bool,
+        write_bitmaps: bool,
+    ) -> Result<(), Error> {
+        let loose = repo.database.loose_oids()?;
+        let old_packs: Vec<_> = repo.database.packs().iter().map(|pack| pack.path().to_path_buf()).collect();
+
+        let mut oids: BTreeSet<String> = loose.iter().cloned().collect();
+        if all {
+            for pack in repo.database.packs() {
+                oids.extend(pack.oids().iter().cloned());
+            }
+        }
+        if oids.is_empty() || (all && loose.is_empty() && old_packs.len() == 1) {
+            println!("Nothing new to pack.");
+            // The indexes may still be asked for over the pack there is
+            let pack = old_packs.first().filter(|_| all).cloned();
+            return Self::write_indexes(repo, pack.as_deref(), write_commit_graph, write_bitmaps);
+        }
+
+        let oids: Vec<String> = oids.into_iter().collect();
+        let summary = repo.database.write_pack(&oids)?;
+        let name = summary.path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        println!("Packed {} objects ({} as deltas) into {}", summary.objects, summary.deltas, name);
+
+        if delete {
+            for oid in &loose {
+                repo.database.remove_loose(oid)?;
+            }
+            trace!("Removed {} loose objects", loose.len());
+
+            if all {
+                for pack in old_packs.iter().filter(|path| **path != summary.path) {
+                    Self::remove_pack(pack)?;
+                    trace!("Removed {}", pack.display());
+                }
+                repo.database.reload_packs();
+            }
+        }
+        Self::write_indexes(repo, Some(&summary.path), write_commit_graph, write_bitmaps)
+    }
+
+    /// Write the commit-graph of everything reachable from refs and HEAD,
+    /// and with `write_bitmaps` a bitmap for `pack`, which must hold every
+    /// object those reach
+    pub fn write_indexes(
+        repo: &mut Repository,
+        pack: Option<&Path>,
+        write_commit_graph: bool,
+        write_bitmaps: bool,
+    ) -> Result<(), Error> {
+        if !write_commit_graph && !write_bitmaps {
+            return Ok(());
+        }
+        let tips = Self::tips(repo)?;
+
+        if write_commit_graph {
+            trace!("Writing commit-graph from {} tips", tips.len());
+            let commits = commit_graph::write(&repo.database, &tips)?;
+            println!("Wrote commit-graph with {} commits", commits);
+        }
+
+        let pack = match pack.and_then(|path| repo.database.packs().iter().find(|pack| pack.path() == path)) {
+            Some(pack) if write_bitmaps => pack.clone(),
+            _ => return Ok(()),
+        };
+        let mut commits = Vec::new();
+        for tip in &tips {
+            let oid = repo.database.peel(tip)?;
+            if repo.database.load_raw(&oid)?.0 == "commit" {
+                commits.push(oid);
+            }
+        }
+        trace!("Selecting {} commits for bitmaps", commits.len());
+        let selected = bitmap::write(&repo.database, &pack, &commits)?;
+        println!("Wrote bitmap index for {} commits", selected);
+        Ok(())
+    }
+
+    /// Remove a pack with its index and bitmap
+    pub fn remove_pack(pack: &Path) -> Result<(), Error> {
+        let bitmap = pack.with_extension("bitmap");
+        if bitmap.exists() {
+            fs::remove_file(bitmap)?;
+        }
+        fs::remove_file(pack.with_extension("idx"))?;
+        fs::remove_file(pack)?;
+        Ok(())
+    }
+
+    // What refs and HEAD point at
+    fn tips(repo: &Repository) -> Result<Vec<String>, Error> {
+        let mut tips: Vec<String> = repo.refs.read_head()?.into_iter().collect();
+        for reference in repo.refs.list_refs_under("refs")? {
+            if let Reference::Symbolic(name) = reference {
+                tips.extend(repo.refs.read_ref(&name)?);
+            }
+        }
+        tips.sort();
+        tips.dedup();
+        Ok(tips)
+    }
+}
@@ -0,0 +1,106 @@
+// src/commands/request_pull.rs - summarize changes for someone to pull
+use crate::errors::error::Error;
+use crate::core::database::commit::Commit;
+use crate::core::diff::stat::{format_stat, stat_trees};
+use crate::core::merge::bases::Bases;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::{Reference, HEAD};
+use crate::core::remote::{transport, Remote};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::core::revlist::RevList;
+use crate::core::shortlog::format_shortlog;
+
+const SEPARATOR: &str = "----------------------------------------------------------------";
+
+pub struct RequestPullCommand;
+
+impl RequestPullCommand {
+    /// Print a summary of the changes between `start` and `end` (HEAD by
+    /// default) for asking the owner of another repository to pull them
+    /// from `url`: where they start and end, a shortlog of the commits and
+    /// a diffstat. `url` may also name a remote. The commit must already be
+    /// on a ref at `url`; when it is not, the summary is still printed but
+    /// the command fails.
+    pub fn execute(start: &str, url: &str, end: Option<&str>) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let remote = Remote::resolve(&git_dir::resolve(&repo.path), url)?;
+
+        let start_oid = Revision::new(&mut repo, start).resolve(COMMIT)?;
+        let end_oid = Revision::new(&mut repo, end.unwrap_or(HEAD)).resolve(COMMIT)?;
+        let end_branch = match end {
+            Some(end) if repo.refs.read_ref(&format!("refs/heads/{}", end))?.is_some() => Some(end.to_string()),
+            Some(_) => None,
+            None => match repo.refs.current_ref()? {
+                Reference::Symbolic(path) => Some(repo.refs.short_name(&path)),
+                Reference::Direct(_) => None,
+            },
+        };
+
+        let base_oid = Bases::new(&mut repo.database)?.find(&start_oid, &end_oid)?
+            .into_iter().next()
+            .ok_or_else(|| Error::Generic(format!("No commits in common between {} and {}", start, end.unwrap_or(HEAD))))?;
+
+        let mut commits = Vec::new();
+        let range = format!("{}..{}", base_oid, end_oid);
+        let mut revlist = RevList::new(&mut repo.database, &repo.refs, &[range], true)?;
+        while let Some(commit) = revlist.next() {
+            commits.push(commit?);
+        }
+        commits.reverse();
+        let stats = stat_trees(&mut repo.database, Some(&base_oid), Some(&end_oid), &PathFilter::new())?;
+
+        let remote_ref = Self::remote_ref(&remote.url, &end_oid, end_branch.as_deref());
+        let shown_ref = remote_ref.clone().or(end_branch.clone()).unwrap_or_default();
+
+        let base = Self::load_commit(&mut repo, &base_oid)?;
+        let tip = Self::load_commit(&mut repo, &end_oid)?;
+        println!("The following changes since commit {}:\n", base_oid);
+        println!("  {}\n", Self::describe(&base));
+        println!("are available in the repository at:\n");
+        println!("  {} {}\n", remote.url, shown_ref);
+        println!("for you to fetch changes up to {}:\n", end_oid);
+        println!("  {}\n", Self::describe(&tip));
+        println!("{}", SEPARATOR);
+        print!("{}", format_shortlog(&commits));
+        print!("{}", format_stat(&stats));
+
+        if remote_ref.is_none() {
+            eprintln!("warn: No match for commit {} found at {}", end_oid, remote.url);
+            eprintln!("warn: Are you sure you pushed '{}' there?", end_branch.as_deref().unwrap_or(HEAD));
+            return Err(Error::Generic("the requested changes are not available at the given URL".to_string()));
+        }
+        Ok(())
+    }
+
+    // The ref at `url` that holds `oid`, preferring the branch of the
+    // same name, shortened the way it is pulled: `topic`, `tags/v1.0`
+    fn remote_ref(url: &str, oid: &str, branch: Option<&str>) -> Option<String> {
+        let advertised = transport::connect(url).and_then(|mut transport| transport.advertised_refs()).ok()?;
+        let matching: Vec<&String> = advertised.iter()
+            .filter(|(name, ref_oid)| *ref_oid == oid && name.starts_with("refs/"))
+            .map(|(name, _)| name)
+            .collect();
+
+        let preferred = branch.map(|branch| format!("refs/heads/{}", branch));
+        let name = matching.iter()
+            .find(|name| Some(name.as_str()) == preferred.as_deref())
+            .or_else(|| matching.iter().find(|name| name.starts_with("refs/heads/")))
+            .or_else(|| matching.first())?;
+        Some(name.strip_prefix("refs/heads/").or_else(|| name.strip_prefix("refs/")).unwrap_or(name).to_string())
+    }
+
+    fn load_commit(repo: &mut Repository, oid: &str) -> Result<Commit, Error> {
+        repo.database.load(oid)?.as_any().downcast_ref::<Commit>().cloned()
+            .ok_or_else(|| Error::Generic(format!("{} is not a commit", oid)))
+    }
+
+    // "<title> (<committer date>)"
+    fn describe(commit: &Commit) -> String {
+        let date = commit.get_committer().or(commit.get_author())
+            .map(|author| author.timestamp.format("%Y-%m-%d %H:%M:%S +0000").to_string())
+            .unwrap_or_default();
+        format!("{} ({})", commit.title_line().trim(), date)
+    }
+}
@@ -1,7 +1,7 @@
 // src/commands/reset.rs
 use std::path::{Path, PathBuf};
 use std::time::Instant;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
 use crate::errors::error::Error;
@@ -15,6 +15,10 @@ use crate::core::database::tree::TreeEntry;
 use crate::core::database::commit::Commit;
 use crate::core::file_mode::FileMode;
 use crate::core::database::entry::DatabaseEntry;
+use crate::core::repository::autostash::head_files;
+use crate::core::repository::git_dir;
+use crate::core::repository::inspector::Inspector;
+use crate::trace;
 
 // Constanta pentru ORIG_HEAD
 pub const ORIG_HEAD: &str = "ORIG_HEAD";
@@ -32,7 +36,7 @@ pub struct ResetCommand;
 impl ResetCommand {
     pub fn execute(paths: &[String], soft: bool, mixed: bool, hard: bool, force: bool, reuse_message: Option<&str>) -> Result<(), Error> {
         let start_time = Instant::now();
-        println!("Reset started...");
+        trace!("Reset started...");
         
         // Inițializare repository
         let mut repo = Repository::new(".")?;
@@ -55,6 +59,7 @@ impl ResetCommand {
         // Stabilim commit-ul de resetare
         let mut commit_oid = head_oid.clone();
         let mut remaining_paths = paths.to_vec();
+        let mut reflog_message = "reset: moving to HEAD".to_string();
         
         // Verificăm primul argument pentru a vedea dacă este o revizie
         if let Some(first_arg) = paths.get(0) {
@@ -62,6 +67,7 @@ impl ResetCommand {
             match revision.resolve("commit") {
                 Ok(oid) => {
                     commit_oid = oid;
+                    reflog_message = format!("reset: moving to {}", first_arg);
                     remaining_paths.remove(0); // Îndepărtăm primul argument, rămân doar căile
                 },
                 Err(_) => {
@@ -80,7 +86,7 @@ impl ResetCommand {
                 if remaining_paths.is_empty() {
                     // Salvăm HEAD curent în ORIG_HEAD
                     if let Some(old_oid) = repo.refs.read_head()? {
-                        let orig_head_path = repo.path.join(".ash").join(ORIG_HEAD);
+                        let orig_head_path = git_dir::resolve(&repo.path).join(ORIG_HEAD);
                         std::fs::write(orig_head_path, format!("{}\n", old_oid))
                             .map_err(|e| Error::Generic(format!("Could not write ORIG_HEAD: {}", e)))?;
                         
@@ -94,9 +100,9 @@ impl ResetCommand {
                     }
                     
                     // Actualizăm HEAD
-                    repo.refs.update_head(&commit_oid)?;
+                    repo.refs.update_head(&commit_oid, &reflog_message)?;
                     println!("HEAD is now at {}", Self::short_oid(&commit_oid));
-                    println!("Commit message saved for reuse");
+                    trace!("Commit message saved for reuse");
                 } else {
                     return Err(Error::Generic("Cannot do path reset with --soft".to_string()));
                 }
@@ -106,7 +112,7 @@ impl ResetCommand {
                 if remaining_paths.is_empty() {
                     // Salvăm HEAD curent în ORIG_HEAD
                     if let Some(old_oid) = repo.refs.read_head()? {
-                        let orig_head_path = repo.path.join(".ash").join(ORIG_HEAD);
+                        let orig_head_path = git_dir::resolve(&repo.path).join(ORIG_HEAD);
                         std::fs::write(orig_head_path, format!("{}\n", old_oid))
                             .map_err(|e| Error::Generic(format!("Could not write ORIG_HEAD: {}", e)))?;
                     }
@@ -116,16 +122,16 @@ impl ResetCommand {
                     Self::reset_tree(&mut repo, &commit_oid, None)?;
                     
                     // Actualizează HEAD
-                    repo.refs.update_head(&commit_oid)?;
+                    repo.refs.update_head(&commit_oid, &reflog_message)?;
                     println!("HEAD is now at {}", Self::short_oid(&commit_oid));
-                    println!("Index reset to {}", Self::short_oid(&commit_oid));
+                    trace!("Index reset to {}", Self::short_oid(&commit_oid));
                 } else {
                     // Resetează doar căile specificate
                     for path_str in &remaining_paths {
                         let path = PathBuf::from(path_str);
                         Self::reset_path(&mut repo, &commit_oid, &path)?;
                     }
-                    println!("Paths have been reset in the index");
+                    trace!("Paths have been reset in the index");
                 }
             },
             Mode::Hard => {
@@ -133,18 +139,21 @@ impl ResetCommand {
                 if remaining_paths.is_empty() {
                     // Salvăm HEAD curent în ORIG_HEAD
                     if let Some(old_oid) = repo.refs.read_head()? {
-                        let orig_head_path = repo.path.join(".ash").join(ORIG_HEAD);
+                        let orig_head_path = git_dir::resolve(&repo.path).join(ORIG_HEAD);
                         std::fs::write(orig_head_path, format!("{}\n", old_oid))
                             .map_err(|e| Error::Generic(format!("Could not write ORIG_HEAD: {}", e)))?;
                     }
                     
                     // Facem hard reset utilizând tree diff, folosind parametrul force
-                    Self::hard_reset(&mut repo, &commit_oid, force)?;
+                    if let Err(e) = Self::hard_reset(&mut repo, &commit_oid, force) {
+                        repo.index.rollback()?;
+                        return Err(e);
+                    }
                     
                     // Actualizează HEAD
-                    repo.refs.update_head(&commit_oid)?;
+                    repo.refs.update_head(&commit_oid, &reflog_message)?;
                     println!("HEAD is now at {}", Self::short_oid(&commit_oid));
-                    println!("Index and workspace reset to {}", Self::short_oid(&commit_oid));
+                    trace!("Index and workspace reset to {}", Self::short_oid(&commit_oid));
                 } else {
                     return Err(Error::Generic("Cannot do path reset with --hard".to_string()));
                 }
@@ -155,7 +164,7 @@ impl ResetCommand {
         repo.index.write_updates()?;
         
         let elapsed = start_time.elapsed();
-        println!("Reset completed in {:.2}s", elapsed.as_secs_f32());
+        trace!("Reset completed in {:.2}s", elapsed.as_secs_f32());
         
         Ok(())
     }
@@ -170,7 +179,7 @@ impl ResetCommand {
             }
         } else if revision == "ORIG_HEAD" {
             // Try to read from ORIG_HEAD file
-            let orig_head_path = repo.path.join(".ash").join(ORIG_HEAD);
+            let orig_head_path = git_dir::resolve(&repo.path).join(ORIG_HEAD);
             if !orig_head_path.exists() {
                 return Err(Error::Generic("ORIG_HEAD not found".to_string()));
             }
@@ -195,7 +204,7 @@ impl ResetCommand {
         };
         
         // Save the message to COMMIT_EDITMSG file
-        let edit_msg_path = repo.path.join(".ash").join(COMMIT_EDITMSG);
+        let edit_msg_path = git_dir::resolve(&repo.path).join(COMMIT_EDITMSG);
         fs::write(&edit_msg_path, message)
             .map_err(|e| Error::Generic(format!("Failed to write commit message: {}", e)))?;
         
@@ -303,13 +312,44 @@ impl ResetCommand {
         if force {
             // Dacă avem force, ștergem toate conflictele potențiale înainte de aplicare
             migration.remove_all_conflicts();
-            println!("Force flag aplicat - ignorând conflictele potențiale");
-            println!("Notă: Fișierele modificate în workspace pot necesita actualizare manuală");
+            trace!("Force flag aplicat - ignorând conflictele potențiale");
+            trace!("Notă: Fișierele modificate în workspace pot necesita actualizare manuală");
         }
         
         // Aplicăm schimbările
         migration.apply_changes()?;
-        
+
+        // The migration only covers what differs between the two commits;
+        // staged and unstaged changes and conflicts go back too
+        Self::restore_changes(repo, commit_oid)
+    }
+
+    // Put every path whose index entry or working tree file differs from
+    // `commit_oid` back as the commit has it. Untracked files are left.
+    fn restore_changes(repo: &mut Repository, commit_oid: &str) -> Result<(), Error> {
+        let target = head_files(repo, commit_oid)?;
+        let mut paths: HashSet<String> = repo.index.each_entry()
+            .filter(|entry| entry.stage > 0 || target.get(entry.get_path()).map(|e| e.get_oid()) != Some(entry.get_oid()))
+            .map(|entry| entry.get_path().to_string())
+            .collect();
+        paths.extend(target.keys().filter(|path| !repo.index.tracked(path)).cloned());
+        let inspector = Inspector::new(&repo.workspace, &repo.index, &repo.database);
+        paths.extend(inspector.analyze_workspace_changes()?.into_keys());
+
+        for path in paths {
+            let file_path = Path::new(&path);
+            repo.index.remove(file_path)?;
+            match target.get(&path) {
+                Some(entry) => {
+                    let data = repo.database.load(entry.get_oid())?.to_bytes();
+                    repo.workspace.write_file(file_path, &data)?;
+                    let stat = repo.workspace.stat_file(file_path)?;
+                    repo.index.add(file_path, entry.get_oid(), &stat)?;
+                },
+                None if repo.workspace.root_path.join(file_path).is_file() => repo.workspace.remove_file(file_path)?,
+                None => {},
+            }
+        }
         Ok(())
     }
     
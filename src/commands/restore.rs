@@ -0,0 +1,153 @@
+// src/commands/restore.rs - copy file versions from a commit or the index into the index and/or worktree
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::errors::error::Error;
+use crate::core::file_mode::FileMode;
+use crate::core::path_filter::PathFilter;
+use crate::core::revision::Revision;
+use crate::core::repository::repository::Repository;
+
+pub struct RestoreCommand;
+
+impl RestoreCommand {
+    /// Restore `paths` without moving HEAD.
+    ///
+    /// `staged` writes the index, `worktree` writes the working tree; with
+    /// neither flag only the worktree is restored. The default source is the
+    /// index for worktree-only restores and HEAD whenever the index is written.
+    pub fn execute(paths: &[String], source: Option<&str>, staged: bool, worktree: bool) -> Result<(), Error> {
+        if paths.is_empty() {
+            return Err(Error::Generic("you must specify path(s) to restore".to_string()));
+        }
+
+        let worktree = worktree || !staged;
+        let mut repo = Repository::new(".")?;
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let result = Self::restore(&mut repo, paths, source, staged, worktree);
+        match result {
+            Ok(()) => {
+                repo.index.write_updates()?;
+                Ok(())
+            },
+            Err(e) => {
+                repo.index.rollback()?;
+                Err(e)
+            }
+        }
+    }
+
+    fn restore(repo: &mut Repository, paths: &[String], source: Option<&str>, staged: bool, worktree: bool) -> Result<(), Error> {
+        let source = match source {
+            Some(rev) => Some(rev.to_string()),
+            None if staged => Some("HEAD".to_string()),
+            None => None,
+        };
+
+        // path -> (oid, mode) of every file in the source
+        let source_files = match &source {
+            Some(rev) => Self::commit_files(repo, rev)?,
+            None => repo.index.each_entry()
+                .map(|entry| (entry.get_path().to_string(), (entry.get_oid().to_string(), entry.mode_octal())))
+                .collect(),
+        };
+
+        // Paths the pathspecs cover: whatever is in the source, plus tracked
+        // files absent from it, which get removed
+        let mut targets = Vec::new();
+        for spec in paths {
+            let spec = spec.trim_end_matches('/');
+            let matched: Vec<String> = source_files.keys()
+                .chain(repo.index.get_keys().iter())
+                .filter(|path| Self::matches(path, spec))
+                .cloned()
+                .collect();
+
+            if matched.is_empty() {
+                return Err(Error::Generic(format!("pathspec '{}' did not match any file(s) known to ash", spec)));
+            }
+            targets.extend(matched);
+        }
+        targets.sort();
+        targets.dedup();
+
+        // An intent-to-add entry has no content yet, so the index has
+        // nothing to give back for it and the file is left as it is; an
+        // unmerged entry is no version to restore from
+        if source.is_none() {
+            targets.retain(|path| !repo.index.get_entry(path).is_some_and(|e| e.intent_to_add));
+            if let Some(path) = targets.iter().find(|path| repo.index.get_entry(path).is_some_and(|e| e.stage > 0)) {
+                return Err(Error::Generic(format!("path '{}' is unmerged", path)));
+            }
+        }
+
+        for path in targets {
+            let file = Path::new(&path);
+
+            match source_files.get(&path) {
+                Some((oid, mode)) => {
+                    let data = repo.database.load(oid)?.to_bytes();
+
+                    if worktree {
+                        if let Some(parent) = file.parent() {
+                            if !parent.as_os_str().is_empty() {
+                                repo.workspace.make_directory(parent)?;
+                            }
+                        }
+                        repo.workspace.write_file(file, &data)?;
+                    }
+
+                    if staged && worktree {
+                        // The worktree now holds exactly this blob
+                        let stat = repo.workspace.stat_file(file)?;
+                        repo.index.add(file, oid, &stat)?;
+                    } else if staged {
+                        repo.index.add_blob(file, oid, FileMode::parse(mode), data.len());
+                    }
+                },
+                None => {
+                    if worktree && repo.workspace.path_exists(file)? {
+                        repo.workspace.remove_file(file)?;
+                    }
+                    if staged {
+                        repo.index.remove(file)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Every file in the tree of `rev`, keyed by path
+    fn commit_files(repo: &mut Repository, rev: &str) -> Result<BTreeMap<String, (String, String)>, Error> {
+        let mut revision = Revision::new(repo, rev);
+        let oid = match revision.resolve("commit") {
+            Ok(oid) => oid,
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                    for hint in &err.hint {
+                        eprintln!("hint: {}", hint);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        let diff = repo.database.tree_diff(None, Some(&oid), &PathFilter::new())?;
+        Ok(diff.into_iter()
+            .filter_map(|(path, (_, new))| new.map(|entry| {
+                (path.to_string_lossy().to_string(), (entry.get_oid().to_string(), entry.get_mode().to_string()))
+            }))
+            .collect())
+    }
+
+    fn matches(path: &str, spec: &str) -> bool {
+        spec == "." || spec.is_empty() || path == spec || path.starts_with(&format!("{}/", spec))
+    }
+}
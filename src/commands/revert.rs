@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::core::advice;
 use crate::core::database::commit::Commit;
 use crate::core::database::database::Database;
 use crate::core::editor::Editor;
@@ -16,12 +17,15 @@ use crate::commands::commit_writer::{CommitWriter, COMMIT_NOTES};
 use crate::core::workspace::Workspace;
 use crate::core::revision::Revision;
 use crate::core::repository::repository::Repository;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 // Shared constants and utilities
 const CONFLICT_NOTES: &str = "\
 after resolving the conflicts, mark the corrected paths
 with 'ash add <paths>' or 'ash rm <paths>'
-and commit the result with 'ash commit'";
+and commit the result with 'ash commit'.
+To abort and get back to the state before 'ash revert', run 'ash revert --abort'.";
 
 pub struct RevertCommand;
 
@@ -34,7 +38,7 @@ impl RevertCommand {
         mainline: Option<u32>,
     ) -> Result<(), Error> {
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         let repo_path = git_path.clone();
 
         // Verify repository exists
@@ -55,25 +59,25 @@ impl RevertCommand {
         let mut sequencer = Sequencer::new(repo_path.clone());
 
         if continue_op {
-            println!("Continuing revert operation...");
+            trace!("Continuing revert operation...");
             handle_continue(root_path, repo_path, &mut repo.database, &mut repo.index, &repo.refs, &mut sequencer)?;
             return Ok(());
         } else if abort {
-            println!("Aborting revert operation...");
+            trace!("Aborting revert operation...");
             handle_abort(root_path, repo_path, &mut repo.database, &mut repo.index, &repo.refs, &mut sequencer, PendingCommitType::Revert)?;
             return Ok(());
         } else if quit {
-            println!("Quitting revert operation without aborting...");
+            trace!("Quitting revert operation without aborting...");
             handle_quit(root_path, repo_path, &mut repo.database, &mut repo.index, &repo.refs, &mut sequencer, PendingCommitType::Revert)?;
             return Ok(());
         } else {
-            println!("Starting revert operation for {} commits...", args.len());
+            trace!("Starting revert operation for {} commits...", args.len());
             sequencer.start(&options)?;
             
             // Get the commits to revert and add them to the sequencer
             store_commit_sequence(&mut sequencer, &mut repo, args)?;
             
-            println!("Added {} commits to revert", args.len());
+            trace!("Added {} commits to revert", args.len());
         }
         
         // Process the first commit
@@ -90,7 +94,7 @@ impl RevertCommand {
             match action {
                 Action::Revert => {
                     let commit_oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
-                    println!("Reverting commit: {}", commit_oid);
+                    trace!("Reverting commit: {}", commit_oid);
                     
                     // Create a message for the revert
                     let message = format!(
@@ -106,16 +110,17 @@ This reverts commit {}.",
                     
                     // Use CommitWriter to handle the commit creation
                     let parents = vec![head_ref];
+                    commit_writer.reflog_action = "revert".to_string();
                     let new_commit = commit_writer.write_commit(parents, &message, None)?;
                     
                     // Print commit info
                     commit_writer.print_commit(&new_commit)?;
                     
                     sequencer.drop_command()?;
-                    println!("Successfully reverted commit");
+                    trace!("Successfully reverted commit");
                 },
-                Action::Pick => {
-                    return Err(Error::Generic("Pick action not supported in revert".into()));
+                action => {
+                    return Err(Error::Generic(format!("{} action not supported in revert", action)));
                 }
             }
         }
@@ -196,7 +201,7 @@ fn revert(
 
     // Create commit writer
     let root_path = Path::new(".");
-    let git_path = root_path.join(".ash");
+    let git_path = git_dir::resolve(root_path);
     let mut commit_writer = CommitWriter::new(
         root_path,
         git_path,
@@ -232,6 +237,7 @@ fn revert(
     
     // Use CommitWriter to create the commit
     let parents = vec![head_ref];
+    commit_writer.reflog_action = "revert".to_string();
     let new_commit = commit_writer.write_commit(parents, &message, Some(author))?;
     
     // Print commit info
@@ -245,7 +251,7 @@ fn revert_merge_inputs(
     commit: &Commit,
     refs: &Refs,
 ) -> Result<inputs::CherryPick, Error> {
-    let db_path = Path::new(".").join(".ash").join("objects");
+    let db_path = git_dir::objects(&git_dir::resolve(Path::new(".")));
     let database = Database::new(db_path);
     let commit_oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
     let short = database.short_oid(&commit_oid);
@@ -368,8 +374,8 @@ fn resume_sequencer(
 ) -> Result<(), Error> {
     while let Some((action, commit)) = sequencer.next_command() {
         match action {
-            Action::Pick => return Err(Error::Generic("Pick action not supported in revert".into())),
             Action::Revert => revert(sequencer, &commit, database, index, refs)?,
+            action => return Err(Error::Generic(format!("{} action not supported in revert", action))),
         }
         sequencer.drop_command()?;
     }
@@ -407,9 +413,7 @@ fn fail_on_conflict(
     })?;
 
     println!("error: could not apply {}", inputs.right_name);
-    for line in CONFLICT_NOTES.lines() {
-        println!("hint: {}", line);
-    }
+    advice::advise(advice::MERGE_CONFLICT, CONFLICT_NOTES);
 
     Err(Error::Generic("Revert failed due to conflicts".into()))
 }
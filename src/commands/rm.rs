@@ -5,6 +5,8 @@ use crate::core::workspace::Workspace;
 use crate::core::index::index::Index;
 use crate::core::database::database::Database;
 use crate::core::color::Color;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 // Enum pentru statusul verificărilor de ștergere
 #[derive(Debug)]
@@ -20,8 +22,8 @@ pub struct RmCommand;
 impl RmCommand {
     pub fn execute(paths: &[String], cached: bool, force: bool, recursive: bool) -> Result<(), Error> {
         let workspace = Workspace::new(Path::new("."));
-        let git_path = workspace.root_path.join(".ash");
-        let mut database = Database::new(git_path.join("objects"));
+        let git_path = git_dir::resolve(&workspace.root_path);
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = Index::new(git_path.join("index"));
         
         // Try to acquire the lock on the index
@@ -95,7 +97,7 @@ impl RmCommand {
         // Remove all files
         for path in expanded_paths {
             Self::remove_file(&workspace, &mut index, &path, cached)?;
-            println!("rm '{}'", path.display());
+            trace!("rm '{}'", path.display());
         }
         
         // Write index updates
@@ -0,0 +1,135 @@
+// src/commands/show.rs - pretty-print objects of any type
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::commands::log::{show_combined_patch, show_commit_medium, show_patch};
+use crate::core::attributes::Attributes;
+use crate::core::color::Color;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::tag::Tag;
+use crate::core::database::tree::{Tree, TreeEntry};
+use crate::core::gpg;
+use crate::core::pager::Pager;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::Reference;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT, HEAD};
+use crate::errors::error::Error;
+
+pub struct ShowCommand;
+
+impl ShowCommand {
+    /// Show each of `objects` (HEAD by default): a commit with its header,
+    /// message and, unless `patch` is false, its diff; a tree as a listing;
+    /// a blob as its raw content; an annotated tag as its annotation, with
+    /// who signed it if it is signed, followed by the object it names. Output goes through the pager.
+    pub fn execute(objects: &[String], patch: bool) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let names: Vec<String> = if objects.is_empty() { vec![HEAD.to_string()] } else { objects.to_vec() };
+
+        // Every name is checked before anything is shown
+        let mut resolved = Vec::new();
+        for name in &names {
+            let oid = Self::resolve(&mut repo, name)?;
+            resolved.push((name.clone(), oid));
+        }
+
+        let git_path = git_dir::resolve(&repo.path);
+        let mut pager = Pager::new();
+        pager.start()?;
+        for (i, (name, oid)) in resolved.iter().enumerate() {
+            if pager.exited_early() {
+                break;
+            }
+            if i > 0 {
+                pager.write("\n")?;
+            }
+            Self::show_object(&mut pager, &mut repo.database, repo.workspace.attributes(), &git_path, name, oid, patch)?;
+        }
+        pager.close()?;
+        Ok(())
+    }
+
+    // What `name` stands for, without peeling annotated tags. A blob in the
+    // index, :<n>:<path>, says what is wrong when it is not there.
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        for expected_type in ["tag", COMMIT, "tree", "blob"] {
+            match Revision::new(repo, name).resolve(expected_type) {
+                Ok(oid) => return Ok(oid),
+                Err(e) if name.starts_with(':') && expected_type == "blob" => return Err(e),
+                Err(_) => {},
+            }
+        }
+        Err(Error::Generic(format!("ambiguous argument '{}': unknown revision or path not in the working tree.", name)))
+    }
+
+    fn show_object(
+        pager: &mut Pager,
+        database: &mut Database,
+        attributes: &Attributes,
+        git_path: &Path,
+        name: &str,
+        oid: &str,
+        patch: bool,
+    ) -> Result<(), Error> {
+        let object = database.load(oid)?;
+
+        if let Some(tag) = object.as_any().downcast_ref::<Tag>() {
+            pager.write(&format!("{} {}\n", Color::yellow("tag"), tag.name))?;
+            pager.write(&format!("Tagger: {} <{}>\n", tag.tagger.name, tag.tagger.email))?;
+            pager.write(&format!("Date:   {}\n", tag.tagger.short_date()))?;
+            match tag.signature() {
+                // Who signed it rather than the signature itself
+                Some(signature) => {
+                    pager.write(&format!("\n{}\n", gpg::split_signature(&tag.message).0.trim_end()))?;
+                    match gpg::verify(git_path, &tag.payload(), signature) {
+                        Ok(verification) => pager.write(&verification.output)?,
+                        Err(_) => pager.write(signature)?,
+                    }
+                },
+                None => pager.write(&format!("\n{}\n", tag.message.trim_end()))?,
+            }
+            pager.write("\n")?;
+            // Then whatever the tag points at, itself possibly a tag
+            return Self::show_object(pager, database, attributes, git_path, &tag.object, &tag.object, patch);
+        }
+
+        if let Some(commit) = object.as_any().downcast_ref::<Commit>() {
+            let commit = commit.clone();
+            show_commit_medium(pager, &commit, false, "no", &HashMap::new(), &Reference::Direct(String::new()), false)?;
+            if !patch {
+                return Ok(());
+            }
+            pager.write("\n")?;
+            // Merges show only what differs from every parent, as with --cc
+            let parents = commit.get_parents();
+            if parents.len() > 1 {
+                show_combined_patch(pager, database, attributes, &parents, oid, &PathFilter::new())?;
+            } else {
+                show_patch(pager, database, attributes, parents.first().map(String::as_str), oid, &PathFilter::new())?;
+            }
+            return Ok(());
+        }
+
+        if let Some(tree) = object.as_any().downcast_ref::<Tree>() {
+            pager.write(&format!("{} {}\n\n", Color::yellow("tree"), name))?;
+            let mut entries: Vec<String> = tree.get_entries().iter()
+                .map(|(entry_name, entry)| match entry {
+                    TreeEntry::Tree(_) => format!("{}/", entry_name),
+                    TreeEntry::Blob(_, _) => entry_name.clone(),
+                })
+                .collect();
+            entries.sort();
+            for entry in entries {
+                pager.write(&format!("{}\n", entry))?;
+            }
+            return Ok(());
+        }
+
+        // A blob, shown as it is
+        pager.write(&String::from_utf8_lossy(&object.to_bytes()))?;
+        Ok(())
+    }
+}
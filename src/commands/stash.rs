@@ -0,0 +1,83 @@
+// src/commands/stash.rs - set local changes aside and bring them back later
+use crate::errors::error::Error;
+use crate::commands::log::show_patch;
+use crate::core::database::commit::Commit;
+use crate::core::diff::stat::{format_stat, stat_trees};
+use crate::core::pager::Pager;
+use crate::core::path_filter::PathFilter;
+use crate::core::repository::repository::Repository;
+use crate::core::repository::stash::Stash;
+
+/// What `ash stash` was asked to do besides the action itself
+#[derive(Debug, Clone, Default)]
+pub struct StashOptions {
+    /// The entry to act on, `stash@{<n>}` or `<n>`; the newest by default
+    pub entry: Option<String>,
+    pub message: Option<String>,
+    pub include_untracked: bool,
+    pub keep_index: bool,
+    /// Show the changes of an entry as a patch instead of a diffstat
+    pub patch: bool,
+}
+
+pub struct StashCommand;
+
+impl StashCommand {
+    /// Run `ash stash push` (the default), `pop`, `apply`, `drop`, `list`
+    /// or `show`.
+    pub fn execute(action: &str, options: &StashOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let entry = options.entry.as_deref();
+
+        match action {
+            "push" => {
+                let saved = Stash::save(&mut repo, options.message.as_deref(), options.include_untracked, options.keep_index)?;
+                if saved.is_none() {
+                    println!("No local changes to save");
+                }
+                Ok(())
+            },
+            "pop" => Stash::pop(&mut repo, entry),
+            "apply" => Stash::apply(&mut repo, entry),
+            "drop" => Stash::drop(&mut repo, entry),
+            "list" => Self::list(&repo),
+            "show" => Self::show(&mut repo, entry, options.patch),
+            _ => Err(Error::Generic(format!("unknown stash subcommand: {}", action))),
+        }
+    }
+
+    fn list(repo: &Repository) -> Result<(), Error> {
+        let mut pager = Pager::new();
+        pager.start()?;
+        for (n, entry) in Stash::list(repo)?.iter().enumerate() {
+            if pager.exited_early() {
+                break;
+            }
+            pager.write(&format!("stash@{{{}}}: {}\n", n, entry.message))?;
+        }
+        pager.close()?;
+        Ok(())
+    }
+
+    // The changes an entry records against the commit it was made on
+    fn show(repo: &mut Repository, entry: Option<&str>, patch: bool) -> Result<(), Error> {
+        let (_, stash_oid) = Stash::resolve(repo, entry)?;
+        let base_oid = repo.database.load(&stash_oid)?.as_any().downcast_ref::<Commit>()
+            .and_then(|commit| commit.get_parents().first().cloned())
+            .ok_or_else(|| Error::Generic(format!("{} is not a stash commit", stash_oid)))?;
+
+        if !patch {
+            let stats = stat_trees(&mut repo.database, Some(&base_oid), Some(&stash_oid), &PathFilter::new())?;
+            if !stats.is_empty() {
+                print!("{}", format_stat(&stats));
+            }
+            return Ok(());
+        }
+
+        let mut pager = Pager::new();
+        pager.start()?;
+        show_patch(&mut pager, &mut repo.database, repo.workspace.attributes(), Some(&base_oid), &stash_oid, &PathFilter::new())?;
+        pager.close()?;
+        Ok(())
+    }
+}
@@ -4,29 +4,43 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+use crate::core::advice;
 use crate::core::color::Color;
 use crate::core::database::database::Database;
 use crate::core::database::blob::Blob;
 use crate::core::database::entry::DatabaseEntry;
 use crate::core::database::tree::{Tree, TreeEntry};
 use crate::core::database::commit::Commit;
+use crate::core::diff::rename::{self, Pair, RenameDetection};
 use crate::core::file_mode::FileMode;
 use crate::core::index::entry::Entry;
 
 use crate::core::index::index::Index;
-use crate::core::refs::Refs;
+use crate::core::merge::conflict_summary::ConflictSummary;
+use crate::core::path_encoding;
+use crate::core::path_filter::PathFilter;
+use crate::core::config::Config;
+use crate::core::refs::{Reference, Refs};
+use crate::core::remote::upstream::{self, Upstream};
 use crate::core::workspace::Workspace;
 use crate::errors::error::Error;
 use crate::core::database::tree::TREE_MODE;
+use crate::core::diff::submodule::GITLINK_MODE;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 // Enum for change types
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum ChangeType {
     WorkspaceModified,
     WorkspaceDeleted,
+    // An intent-to-add path, new in the working tree only
+    WorkspaceAdded,
     IndexAdded,
     IndexModified,
     IndexDeleted,
+    // Added to the index where a file removed from it was
+    IndexRenamed,
 }
 
 pub struct StatusCommand;
@@ -55,9 +69,9 @@ impl StatusCommand {
             let stat_mtime_sec = stat.mtime() as u32;
             let stat_mtime_nsec = stat.mtime_nsec() as u32;
 
-            println!("Comparare timestamps pentru {}", entry.path);
-            println!("Index mtime: {}.{}", entry.get_mtime(), entry.get_mtime_nsec());
-            println!("File mtime: {}.{}", stat_mtime_sec, stat_mtime_nsec);
+            trace!("Comparare timestamps pentru {}", entry.path);
+            trace!("Index mtime: {}.{}", entry.get_mtime(), entry.get_mtime_nsec());
+            trace!("File mtime: {}.{}", stat_mtime_sec, stat_mtime_nsec);
             
             // Compare modification times
             entry.get_mtime() == stat_mtime_sec && entry.get_mtime_nsec() == stat_mtime_nsec
@@ -83,49 +97,6 @@ impl StatusCommand {
         FileMode::from_metadata(stat)
     }
     
-    /// Check if a directory contains trackable files (recursively)
-    fn is_trackable_dir(dir_path: &Path) -> Result<bool, Error> {
-        if !dir_path.is_dir() {
-            return Ok(false);
-        }
-        
-        // Check if directory contains non-hidden files
-        match std::fs::read_dir(dir_path) {
-            Ok(entries) => {
-                for entry_result in entries {
-                    match entry_result {
-                        Ok(entry) => {
-                            let path = entry.path();
-                            let file_name = entry.file_name();
-                            
-                            // Skip hidden files and directories
-                            if let Some(name) = file_name.to_str() {
-                                if name.starts_with('.') {
-                                    continue;
-                                }
-                            }
-                            
-                            if path.is_file() {
-                                // Found a trackable file
-                                return Ok(true);
-                            } else if path.is_dir() {
-                                // Recursively check subdirectories
-                                if Self::is_trackable_dir(&path)? {
-                                    return Ok(true);
-                                }
-                            }
-                        },
-                        Err(e) => return Err(Error::IO(e)),
-                    }
-                }
-                
-                // No trackable files found
-                Ok(false)
-            },
-            Err(e) => Err(Error::IO(e)),
-        }
-    }
-    
     /// Get status for a specific path based on change types
     fn status_for(path: &str, changes: &HashMap<String, HashSet<ChangeType>>) -> String {
         let mut left = " ";
@@ -135,6 +106,8 @@ impl StatusCommand {
             // Status for first column (HEAD -> Index)
             if change_set.contains(&ChangeType::IndexAdded) {
                 left = "A";
+            } else if change_set.contains(&ChangeType::IndexRenamed) {
+                left = "R";
             } else if change_set.contains(&ChangeType::IndexModified) {
                 left = "M";
             } else if change_set.contains(&ChangeType::IndexDeleted) {
@@ -144,6 +117,8 @@ impl StatusCommand {
             // Status for second column (Index -> Workspace)
             if change_set.contains(&ChangeType::WorkspaceDeleted) {
                 right = "D";
+            } else if change_set.contains(&ChangeType::WorkspaceAdded) {
+                right = "A";
             } else if change_set.contains(&ChangeType::WorkspaceModified) {
                 right = "M";
             }
@@ -168,72 +143,72 @@ impl StatusCommand {
 
     /// Diagnostic function to inspect objects in the database
     fn diagnose_object(database: &mut Database, oid: &str) -> Result<(), Error> {
-        println!("Diagnostic for object: {}", oid);
+        trace!("Diagnostic for object: {}", oid);
         
         // Try to load the object
         match database.load(oid) {
             Ok(obj) => {
-                println!("  Successfully loaded object");
-                println!("  Object type: {}", obj.get_type());
+                trace!("  Successfully loaded object");
+                trace!("  Object type: {}", obj.get_type());
                 
                 // Try to cast to different types
                 if let Some(tree) = obj.as_any().downcast_ref::<Tree>() {
-                    println!("  Object is a Tree with {} entries", tree.get_entries().len());
+                    trace!("  Object is a Tree with {} entries", tree.get_entries().len());
                     
                     // Print the entries
                     for (name, entry) in tree.get_entries() {
                         match entry {
                             TreeEntry::Blob(entry_oid, mode) => {
-                                println!("    Entry: {} (blob, mode {}) -> {}", name, mode, entry_oid);
+                                trace!("    Entry: {} (blob, mode {}) -> {}", name, mode, entry_oid);
                             },
                             TreeEntry::Tree(subtree) => {
                                 if let Some(subtree_oid) = subtree.get_oid() {
-                                    println!("    Entry: {} (tree) -> {}", name, subtree_oid);
+                                    trace!("    Entry: {} (tree) -> {}", name, subtree_oid);
                                 } else {
-                                    println!("    Entry: {} (tree) -> <no OID>", name);
+                                    trace!("    Entry: {} (tree) -> <no OID>", name);
                                 }
                             }
                         }
                     }
                 } else if let Some(_blob) = obj.as_any().downcast_ref::<Blob>() {
-                    println!("  Object is a Blob");
+                    trace!("  Object is a Blob");
                     
                     // Try to read and parse the blob as a tree
-                    println!("  Attempting to parse blob as tree...");
+                    trace!("  Attempting to parse blob as tree...");
                     let bytes = obj.to_bytes();
                     match Tree::parse(&bytes) {
                         Ok(tree) => {
-                            println!("  Successfully parsed blob as tree with {} entries", tree.get_entries().len());
+                            trace!("  Successfully parsed blob as tree with {} entries", tree.get_entries().len());
                             
                             // Print the entries
                             for (name, entry) in tree.get_entries() {
                                 match entry {
                                     TreeEntry::Blob(entry_oid, mode) => {
-                                        println!("    Entry: {} (blob, mode {}) -> {}", name, mode, entry_oid);
+                                        trace!("    Entry: {} (blob, mode {}) -> {}", name, mode, entry_oid);
                                     },
                                     TreeEntry::Tree(subtree) => {
                                         if let Some(subtree_oid) = subtree.get_oid() {
-                                            println!("    Entry: {} (tree) -> {}", name, subtree_oid);
+                                            trace!("    Entry: {} (tree) -> {}", name, subtree_oid);
                                         } else {
-                                            println!("    Entry: {} (tree) -> <no OID>", name);
+                                            trace!("    Entry: {} (tree) -> <no OID>", name);
                                         }
                                     }
                                 }
                             }
                         },
                         Err(e) => {
-                            println!("  Failed to parse blob as tree: {}", e);
+                            trace!("  Failed to parse blob as tree: {}", e);
                         }
                     }
                 } else if let Some(commit) = obj.as_any().downcast_ref::<Commit>() {
-                    println!("  Object is a Commit");
-                    println!("  Tree: {}", commit.get_tree());
+                    trace!("  Object is a Commit");
+                    trace!("  Tree: {}", commit.get_tree());
                 } else {
-                    println!("  Object is of unknown type");
+                    trace!("  Object is of unknown type");
                 }
             },
             Err(e) => {
-                println!("  Failed to load object: {}", e);
+                trace!("  Failed to load object: {}", e);
             }
         }
         
@@ -247,37 +222,37 @@ impl StatusCommand {
     ) -> Result<HashMap<String, DatabaseEntry>, Error> {
         let mut head_tree = HashMap::new();
         
-        println!("Loading HEAD tree");
+        trace!("Loading HEAD tree");
         
         // Read HEAD reference
         if let Some(head_oid) = refs.read_head()? {
-            println!("HEAD OID: {}", head_oid);
+            trace!("HEAD OID: {}", head_oid);
             
             // Load the commit
             let commit_obj = match database.load(&head_oid) {
                 Ok(obj) => {
-                    println!("DEBUG: Successfully loaded commit object");
+                    trace!("DEBUG: Successfully loaded commit object");
                     obj
                 },
                 Err(e) => {
-                    println!("DEBUG: Failed to load commit: {}", e);
+                    trace!("DEBUG: Failed to load commit: {}", e);
                     return Err(e);
                 }
             };
             
             let commit = match commit_obj.as_any().downcast_ref::<Commit>() {
                 Some(c) => {
-                    println!("DEBUG: Successfully cast to Commit");
+                    trace!("DEBUG: Successfully cast to Commit");
                     c
                 },
                 None => {
-                    println!("DEBUG: Object is not a Commit");
+                    trace!("DEBUG: Object is not a Commit");
                     return Err(Error::Generic("Object is not a commit".to_string()));
                 }
             };
             
             let root_tree_oid = commit.get_tree();
-            println!("Commit tree OID: {}", root_tree_oid);
+            trace!("Commit tree OID: {}", root_tree_oid);
             
             // Diagnose the root tree
             Self::diagnose_object(database, root_tree_oid)?;
@@ -289,12 +264,12 @@ impl StatusCommand {
                         if name == "src" {
                             match entry {
                                 TreeEntry::Blob(oid, _) => {
-                                    println!("Diagnosing src directory (blob):");
+                                    trace!("Diagnosing src directory (blob):");
                                     Self::diagnose_object(database, oid)?;
                                 },
                                 TreeEntry::Tree(subtree) => {
                                     if let Some(oid) = subtree.get_oid() {
-                                        println!("Diagnosing src directory (tree):");
+                                        trace!("Diagnosing src directory (tree):");
                                         Self::diagnose_object(database, oid)?;
                                     }
                                 }
@@ -307,12 +282,12 @@ impl StatusCommand {
             // Use a proper generic recursive traversal to build the complete head_tree
             Self::traverse_tree_structure(database, root_tree_oid, PathBuf::new(), &mut head_tree)?;
             
-            println!("Found {} entries in HEAD tree", head_tree.len());
+            trace!("Found {} entries in HEAD tree", head_tree.len());
             for (path, entry) in &head_tree {
-                println!("  {} -> {}", path, entry.get_oid());
+                trace!("  {} -> {}", path, entry.get_oid());
             }
         } else {
-            println!("No HEAD found, tree is empty");
+            trace!("No HEAD found, tree is empty");
         }
         
         Ok(head_tree)
@@ -325,7 +300,7 @@ impl StatusCommand {
         prefix: PathBuf,
         head_tree: &mut HashMap<String, DatabaseEntry>
     ) -> Result<(), Error> {
-        println!("Traversing tree: {} at path: {}", tree_oid, prefix.display());
+        trace!("Traversing tree: {} at path: {}", tree_oid, prefix.display());
         
         // Load the tree object
         let obj = database.load(tree_oid)?;
@@ -345,7 +320,7 @@ impl StatusCommand {
                 match entry {
                     TreeEntry::Blob(oid, mode) => {
                         // Store file entry in the head_tree
-                        println!("  Found file in HEAD: {} -> {}", path_str, oid);
+                        trace!("  Found file in HEAD: {} -> {}", path_str, oid);
                         head_tree.insert(
                             path_str.clone(),
                             DatabaseEntry::new(
@@ -357,7 +332,7 @@ impl StatusCommand {
                     },
                     TreeEntry::Tree(subtree) => {
                         if let Some(subtree_oid) = subtree.get_oid() {
-                            println!("  Found directory in HEAD: {} -> {}", path_str, subtree_oid);
+                            trace!("  Found directory in HEAD: {} -> {}", path_str, subtree_oid);
                             
                             // Store directory entry in the head_tree
                             head_tree.insert(
@@ -379,7 +354,7 @@ impl StatusCommand {
             // Sometimes blobs are used to store directories (special handling)
             let blob_data = obj.to_bytes();
             if let Ok(parsed_tree) = Tree::parse(&blob_data) {
-                println!("  Successfully parsed blob as tree with {} entries", parsed_tree.get_entries().len());
+                trace!("  Successfully parsed blob as tree with {} entries", parsed_tree.get_entries().len());
                 
                 // Process entries in the parsed tree
                 for (name, entry) in parsed_tree.get_entries() {
@@ -393,7 +368,7 @@ impl StatusCommand {
                     
                     match entry {
                         TreeEntry::Blob(blob_oid, mode) => {
-                            println!("  Found file in parsed tree: {} -> {}", path_str, blob_oid);
+                            trace!("  Found file in parsed tree: {} -> {}", path_str, blob_oid);
                             head_tree.insert(
                                 path_str.clone(),
                                 DatabaseEntry::new(
@@ -405,7 +380,7 @@ impl StatusCommand {
                         },
                         TreeEntry::Tree(subtree) => {
                             if let Some(subtree_oid) = subtree.get_oid() {
-                                println!("  Found directory in parsed tree: {} -> {}", path_str, subtree_oid);
+                                trace!("  Found directory in parsed tree: {} -> {}", path_str, subtree_oid);
                                 head_tree.insert(
                                     path_str.clone(),
                                     DatabaseEntry::new(
@@ -436,39 +411,39 @@ impl StatusCommand {
     ) {
         let path = index_entry.get_path();
         
-        println!("Comparing index with HEAD for {}", path);
-        println!("  Index OID: {}", index_entry.get_oid());
+        trace!("Comparing index with HEAD for {}", path);
+        trace!("  Index OID: {}", index_entry.get_oid());
         
         // If HEAD tree is empty (first commit case)
         if head_tree.is_empty() {
-            println!("  HEAD tree is empty, marking file as added: {}", path);
+            trace!("  HEAD tree is empty, marking file as added: {}", path);
             Self::record_change(changed, changes, path.to_string(), ChangeType::IndexAdded);
             return;
         }
         
         // Check if this file exists in HEAD
         if let Some(head_entry) = head_tree.get(path) {
-            println!("  HEAD OID: {}", head_entry.get_oid());
+            trace!("  HEAD OID: {}", head_entry.get_oid());
             
             // Skip if this is a directory entry
             if Self::is_directory_from_mode(head_entry.get_mode()) {
-                println!("  Skipping directory entry: {}", path);
+                trace!("  Skipping directory entry: {}", path);
                 return;
             }
             
             // Compare OIDs
             let oids_match = index_entry.get_oid() == head_entry.get_oid();
-            println!("  OIDs match: {}", oids_match);
+            trace!("  OIDs match: {}", oids_match);
             
             // Content comparison - if OIDs differ, file has been modified
             if !oids_match {
-                println!("  Content changed (different OIDs), marking as modified");
+                trace!("  Content changed (different OIDs), marking as modified");
                 Self::record_change(changed, changes, path.to_string(), ChangeType::IndexModified);
             } else {
-                println!("  File is unchanged in index");
+                trace!("  File is unchanged in index");
             }
         } else {
-            println!("  File not found in HEAD, marking as added: {}", path);
+            trace!("  File not found in HEAD, marking as added: {}", path);
             Self::record_change(changed, changes, path.to_string(), ChangeType::IndexAdded);
         }
     }
@@ -482,17 +457,17 @@ impl StatusCommand {
     ) {
         // Skip this check if HEAD is empty
         if head_tree.is_empty() {
-            println!("HEAD tree is empty, skipping deleted files check");
+            trace!("HEAD tree is empty, skipping deleted files check");
             return;
         }
         
-        println!("Checking for files in HEAD that are missing from index");
+        trace!("Checking for files in HEAD that are missing from index");
         
         // Find entries that are in HEAD but not in index
         for (path, head_entry) in head_tree {
             // Skip if this is a directory
             if Self::is_directory_from_mode(head_entry.get_mode()) {
-                println!("  Skipping directory entry: {}", path);
+                trace!("  Skipping directory entry: {}", path);
                 continue;
             }
             
@@ -500,16 +475,66 @@ impl StatusCommand {
             if !index.tracked(path) {
                 // Check if this file is part of a directory that might be tracked in a different way
                 if Self::is_parent_of_tracked_files(path, index) {
-                    println!("  Directory {} contains tracked files, not marking as deleted", path);
+                    trace!("  Directory {} contains tracked files, not marking as deleted", path);
                     continue;
                 }
                 
-                println!("  File in HEAD but not in index: {}", path);
+                trace!("  File in HEAD but not in index: {}", path);
                 Self::record_change(changed, changes, path.clone(), ChangeType::IndexDeleted);
             }
         }
     }
 
+    /// Pair the files removed from the index with those added to it that
+    /// hold much the same content, as `diff.renames` says, and record each
+    /// pair as one rename of the added path. Returns the pairs by that path.
+    fn pair_renames(
+        database: &mut Database,
+        git_path: &Path,
+        head_tree: &HashMap<String, DatabaseEntry>,
+        index: &Index,
+        changed: &mut HashSet<String>,
+        changes: &mut HashMap<String, HashSet<ChangeType>>
+    ) -> Result<HashMap<String, Pair>, Error> {
+        let detection = RenameDetection::resolve(&[], git_path)?;
+        let detection = RenameDetection { copies: false, copies_harder: false, ..detection };
+
+        let staged = |change_type: ChangeType| -> Vec<String> {
+            let mut paths: Vec<String> = changes.iter()
+                .filter(|(_, change_set)| change_set.contains(&change_type))
+                .map(|(path, _)| path.clone())
+                .collect();
+            paths.sort();
+            paths
+        };
+        let removed: Vec<(String, String)> = staged(ChangeType::IndexDeleted).into_iter()
+            .filter_map(|path| head_tree.get(&path).map(|entry| (path.clone(), entry.get_oid().to_string())))
+            .collect();
+        let added: Vec<(String, String)> = staged(ChangeType::IndexAdded).into_iter()
+            .filter_map(|path| index.get_entry(&path).map(|entry| (path.clone(), entry.get_oid().to_string())))
+            .collect();
+        if removed.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut renames = HashMap::new();
+        for pair in rename::detect(database, &removed, &added, &[], detection)? {
+            if let Some(change_set) = changes.get_mut(&pair.from) {
+                change_set.remove(&ChangeType::IndexDeleted);
+                if change_set.is_empty() {
+                    changes.remove(&pair.from);
+                    changed.remove(&pair.from);
+                }
+            }
+            if let Some(change_set) = changes.get_mut(&pair.to) {
+                change_set.remove(&ChangeType::IndexAdded);
+                change_set.insert(ChangeType::IndexRenamed);
+            }
+            renames.insert(pair.to.clone(), pair);
+        }
+        Ok(renames)
+    }
+
     // Helper method to determine if a mode string represents a directory
     fn is_directory_from_mode(mode_str: &str) -> bool {
         let mode = FileMode::parse(mode_str);
@@ -529,13 +554,34 @@ impl StatusCommand {
         index.entries.keys().any(|file_path| file_path.starts_with(&normalized_path))
     }
     
+    /// Print the unresolved conflicts grouped by kind, with what to do next.
+    /// Returns whether there are any.
+    pub fn conflicts() -> Result<bool, Error> {
+        let git_path = git_dir::resolve(Path::new("."));
+        if !git_path.exists() {
+            return Err(Error::Generic("Not an ash repository (or any of the parent directories): .ash directory not found".into()));
+        }
+
+        let mut index = Index::new(git_path.join("index"));
+        index.load()?;
+
+        let summary = ConflictSummary::load(&git_path, &index)?;
+        for line in summary.lines() {
+            println!("{}", line);
+        }
+        Ok(!summary.is_empty())
+    }
+
     /// Main execution method
-    pub fn execute(porcelain: bool) -> Result<(), Error> {
+    /// Print the working tree status. Returns whether the tree is dirty, i.e.
+    /// has staged, unstaged or untracked changes.
+    /// With `verbose`, renames say how alike the two files are.
+    pub fn execute(porcelain: bool, verbose: bool) -> Result<bool, Error> {
         let start_time = Instant::now();
         
         // Initialize paths and components
         let root_path = Path::new(".");
-        let git_path = root_path.join(".ash");
+        let git_path = git_dir::resolve(root_path);
         
         // Check if .ash directory exists
         if !git_path.exists() {
@@ -543,7 +589,7 @@ impl StatusCommand {
         }
         
         let workspace = Workspace::new(root_path);
-        let mut database = Database::new(git_path.join("objects"));
+        let mut database = Database::new(git_dir::objects(&git_path));
         let mut index = Index::new(git_path.join("index"));
         let refs = Refs::new(&git_path);
         
@@ -560,6 +606,11 @@ impl StatusCommand {
             .each_entry()
             .map(|entry| (entry.get_path().to_string(), entry.get_oid().to_string()))
             .collect();
+        let gitlinks: HashSet<String> = index
+            .each_entry()
+            .filter(|entry| entry.mode_octal() == GITLINK_MODE)
+            .map(|entry| entry.get_path().to_string())
+            .collect();
         
         // Prepare data structures for tracking changes
         let mut untracked = HashSet::new();  // Files in workspace but not in index
@@ -587,14 +638,14 @@ impl StatusCommand {
             &workspace,
             &mut untracked,
             &index_entries,
+            &gitlinks,
             &tracked_dirs,
-            root_path,
-            &PathBuf::new(),
             &mut stats_cache
         )?;
         
-        // Step 2: Compare index entries with HEAD
-        for entry in index.each_entry() {
+        // Step 2: Compare index entries with HEAD; an intent-to-add path has
+        // nothing staged
+        for entry in index.each_entry().filter(|entry| !entry.intent_to_add) {
             Self::check_index_against_head_tree(
                 entry,
                 &head_tree,
@@ -610,6 +661,16 @@ impl StatusCommand {
             &mut changed,
             &mut changes
         );
+
+        // Step 3b: Show a file removed and one added alike as a rename
+        let renames = Self::pair_renames(
+            &mut database,
+            &git_path,
+            &head_tree,
+            &index,
+            &mut changed,
+            &mut changes
+        )?;
         
         // Step 4: Compare index entries with workspace (working tree changes)
         for (path, oid) in &index_entries {
@@ -626,8 +687,25 @@ impl StatusCommand {
             if untracked.contains(path) {
                 continue;
             }
+
+            if index.get_entry(path).is_some_and(|entry| entry.intent_to_add) {
+                Self::record_change(&mut changed, &mut changes, path.clone(), ChangeType::WorkspaceAdded);
+                continue;
+            }
             
             // Check if file is modified using cached metadata
+            // Tracked files under an ignored directory are not walked
+            if !stats_cache.contains_key(path) {
+                if let Ok(metadata) = workspace.stat_file(&path_buf) {
+                    stats_cache.insert(path.clone(), metadata);
+                }
+            }
+            // A directory has taken the place of the file, which
+            // scan_workspace reported as untracked
+            if !gitlinks.contains(path) && stats_cache.get(path).is_some_and(|metadata| metadata.is_dir()) {
+                Self::record_change(&mut changed, &mut changes, path.clone(), ChangeType::WorkspaceDeleted);
+                continue;
+            }
             if let Some(metadata) = stats_cache.get(path) {
                 // Get index entry for comparison
                 let index_entry = index.get_entry(path).unwrap();
@@ -650,9 +728,9 @@ impl StatusCommand {
                         // Calculate hash using database
                         let computed_oid = database.hash_file_data(&data);
                         
-                        println!("Verifying file: {}", path);
-                        println!("  Index hash: {}", oid);
-                        println!("  Computed hash: {}", computed_oid);
+                        trace!("Verifying file: {}", path);
+                        trace!("  Index hash: {}", oid);
+                        trace!("  Computed hash: {}", computed_oid);
                         
                         if &computed_oid != oid {
                             // File has changed, mark as modified
@@ -685,97 +763,61 @@ impl StatusCommand {
         // Display results
         if porcelain {
             // Machine-readable output (--porcelain option)
-            Self::print_porcelain(&untracked, &changed, &changes);
+            Self::print_porcelain(&untracked, &changed, &changes, &renames);
         } else {
             // Human-readable output
-            Self::print_human_readable(&untracked, &changed, &changes);
+            let branch = Self::branch_status(&git_path, &refs, &mut database)?;
+            Self::print_human_readable(&branch, &untracked, &changed, &changes, &renames, verbose);
         }
         
         let elapsed = start_time.elapsed();
         if !porcelain {
-            println!("\n{} {:.2}s", Color::cyan("Status completed in"), elapsed.as_secs_f32());
+            trace!("\n{} {:.2}s", Color::cyan("Status completed in"), elapsed.as_secs_f32());
         }
         
-        Ok(())
+        Ok(!changed.is_empty() || !untracked.is_empty())
     }
 
+    // Sort the working tree into untracked paths and the metadata of the
+    // tracked files. An untracked directory is reported once, as the
+    // outermost directory holding no tracked files, and only for the files
+    // the walk finds in it: one holding nothing but ignored files or empty
+    // directories is left out. A directory where the index has a file is
+    // untracked whatever the directories around it hold, the file being
+    // gone, while the files of a submodule belong to its own repository.
     fn scan_workspace(
         workspace: &Workspace,
         untracked: &mut HashSet<String>,
         index_entries: &HashMap<String, String>,
+        gitlinks: &HashSet<String>,
         tracked_dirs: &HashSet<PathBuf>,
-        root_path: &Path,
-        prefix: &Path,
         stats_cache: &mut HashMap<String, fs::Metadata>,
     ) -> Result<(), Error> {
-        let current_path = if prefix.as_os_str().is_empty() {
-            root_path.to_path_buf()
-        } else {
-            root_path.join(prefix)
-        };
-        
-        // List files in current directory
-        match std::fs::read_dir(&current_path) {
-            Ok(entries) => {
-                for entry_result in entries {
-                    match entry_result {
-                        Ok(entry) => {
-                            let file_name = entry.file_name();
-                            let entry_path = entry.path();
-                            
-                            // Skip .ash directory
-                            if file_name == ".ash" {
-                                continue;
-                            }
-                            
-                            // Get relative path from root
-                            let rel_path = if prefix.as_os_str().is_empty() {
-                                PathBuf::from(file_name)
-                            } else {
-                                prefix.join(file_name)
-                            };
-                            
-                            let rel_path_str = rel_path.to_string_lossy().to_string();
-                            
-                            // Check if path is tracked in index
-                            let is_tracked = index_entries.contains_key(&rel_path_str);
-                            let is_in_tracked_dir = tracked_dirs.contains(&rel_path);
-                            
-                            if entry_path.is_dir() {
-                                if is_tracked || is_in_tracked_dir {
-                                    // If directory is tracked or contains tracked files, 
-                                    // scan it recursively
-                                    Self::scan_workspace(
-                                        workspace, 
-                                        untracked, 
-                                        index_entries, 
-                                        tracked_dirs,
-                                        root_path,
-                                        &rel_path,
-                                        stats_cache
-                                    )?;
-                                } else if Self::is_trackable_dir(&entry_path)? {
-                                    // If directory contains trackable files, mark it
-                                    untracked.insert(format!("{}/", rel_path_str));
-                                }
-                                // If directory is empty or contains only ignored files, skip it
-                            } else if !is_tracked {
-                                // File is not tracked in index
-                                untracked.insert(rel_path_str);
-                            } else {
-                                // File is tracked - cache metadata for later comparisons
-                                if let Ok(metadata) = entry_path.metadata() {
-                                    stats_cache.insert(rel_path_str, metadata);
-                                }
-                            }
-                        },
-                        Err(e) => return Err(Error::IO(e)),
-                    }
-                }
-            },
-            Err(e) => return Err(Error::IO(e)),
+        for entry in workspace.walk(&PathFilter::new())? {
+            if !entry.is_file() {
+                continue;
+            }
+            let rel_path_str = entry.path.to_string_lossy().to_string();
+            if index_entries.contains_key(&rel_path_str) {
+                stats_cache.insert(rel_path_str, entry.metadata);
+                continue;
+            }
+
+            let ancestors: Vec<&Path> = entry.path.ancestors()
+                .skip(1)
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .collect();
+            if ancestors.iter().any(|dir| gitlinks.contains(&*dir.to_string_lossy())) {
+                continue;
+            }
+            let untracked_dir = ancestors.iter().rev().find(|dir| {
+                !tracked_dirs.contains(**dir) || index_entries.contains_key(&*dir.to_string_lossy())
+            });
+            match untracked_dir {
+                Some(dir) => untracked.insert(format!("{}/", dir.to_string_lossy())),
+                None => untracked.insert(rel_path_str),
+            };
         }
-        
         Ok(())
     }
     
@@ -783,6 +825,7 @@ impl StatusCommand {
         untracked: &HashSet<String>,
         changed: &HashSet<String>,
         changes: &HashMap<String, HashSet<ChangeType>>,
+        renames: &HashMap<String, Pair>,
     ) {
         // Collect all files to sort them
         let mut all_files: Vec<String> = Vec::new();
@@ -801,29 +844,91 @@ impl StatusCommand {
         all_files.sort();
         
         // Display status for each file
+        let quote_path = path_encoding::quote_path();
         for path in &all_files {
+            let quoted = match renames.get(path) {
+                Some(pair) => format!("{} -> {}", path_encoding::quote(&pair.from, quote_path), path_encoding::quote(path, quote_path)),
+                None => path_encoding::quote(path, quote_path),
+            };
             if untracked.contains(path) {
-                println!("{} {}", Color::red("??"), Color::red(path));
+                println!("{} {}", Color::red("??"), Color::red(&quoted));
             } else {
                 let status = Self::status_for(path, changes);
                 let status_colored = if status.contains('M') {
                     Color::yellow(&status)
-                } else if status.contains('A') {
+                } else if status.contains('A') || status.contains('R') {
                     Color::green(&status)
                 } else if status.contains('D') {
                     Color::red(&status)
                 } else {
                     status.to_string()
                 };
-                println!("{} {}", status_colored, path);
+                println!("{} {}", status_colored, quoted);
             }
         }
     }
     
+    // The branch HEAD is on, or where it is detached, and how the branch
+    // stands against its upstream
+    fn branch_status(git_path: &Path, refs: &Refs, database: &mut Database) -> Result<Vec<String>, Error> {
+        let hints = advice::enabled(advice::STATUS_HINTS);
+        let branch = match refs.current_ref()? {
+            Reference::Symbolic(path) => refs.short_name(&path),
+            Reference::Direct(oid) => {
+                let short = oid.get(..7).unwrap_or(&oid).to_string();
+                return Ok(vec![format!("{} {}", Color::red("HEAD detached at"), short)]);
+            },
+        };
+        let mut lines = vec![format!("On branch {}", Color::green(&branch))];
+
+        let (upstream, head) = match (Upstream::of(&Config::load(git_path)?, &branch), refs.read_head()?) {
+            (Some(upstream), Some(head)) => (upstream, head),
+            _ => return Ok(lines),
+        };
+        let name = upstream.display(git_path);
+        let theirs = match refs.read_ref(&upstream.tracking_ref(git_path))? {
+            Some(theirs) => theirs,
+            None => {
+                lines.push(format!("Your branch is based on '{}', but the upstream is gone.", name));
+                if hints {
+                    lines.push("  (use \"ash branch --unset-upstream\" to fixup)".to_string());
+                }
+                return Ok(lines);
+            },
+        };
+        let commits = |n: usize| if n == 1 { "1 commit".to_string() } else { format!("{} commits", n) };
+        match upstream::ahead_behind(database, refs, &head, &theirs)? {
+            (0, 0) => lines.push(format!("Your branch is up to date with '{}'.", name)),
+            (ahead, 0) => {
+                lines.push(format!("Your branch is ahead of '{}' by {}.", name, commits(ahead)));
+                if hints {
+                    lines.push("  (use \"ash push\" to publish your local commits)".to_string());
+                }
+            },
+            (0, behind) => {
+                lines.push(format!("Your branch is behind '{}' by {}, and can be fast-forwarded.", name, commits(behind)));
+                if hints {
+                    lines.push("  (use \"ash pull\" to update your local branch)".to_string());
+                }
+            },
+            (ahead, behind) => {
+                lines.push(format!("Your branch and '{}' have diverged,", name));
+                lines.push(format!("and have {} and {} different commits each, respectively.", ahead, behind));
+                if hints {
+                    lines.push("  (use \"ash pull\" to merge the remote branch into yours)".to_string());
+                }
+            },
+        }
+        Ok(lines)
+    }
+
     fn print_human_readable(
+        branch: &[String],
         untracked: &HashSet<String>,
         changed: &HashSet<String>,
         changes: &HashMap<String, HashSet<ChangeType>>,
+        renames: &HashMap<String, Pair>,
+        verbose: bool,
     ) {
         // Group changes by type
         let mut changes_to_be_committed = Vec::new();
@@ -834,6 +939,8 @@ impl StatusCommand {
                 // Changes between HEAD and index
                 if change_set.contains(&ChangeType::IndexAdded) {
                     changes_to_be_committed.push((path, "new file"));
+                } else if change_set.contains(&ChangeType::IndexRenamed) {
+                    changes_to_be_committed.push((path, "renamed"));
                 } else if change_set.contains(&ChangeType::IndexModified) {
                     changes_to_be_committed.push((path, "modified"));
                 } else if change_set.contains(&ChangeType::IndexDeleted) {
@@ -845,16 +952,25 @@ impl StatusCommand {
                     changes_not_staged.push((path, "modified"));
                 } else if change_set.contains(&ChangeType::WorkspaceDeleted) {
                     changes_not_staged.push((path, "deleted"));
+                } else if change_set.contains(&ChangeType::WorkspaceAdded) {
+                    changes_not_staged.push((path, "new file"));
                 }
             }
         }
         
-        println!("On branch {}", Color::green("master"));
+        let quote_path = path_encoding::quote_path();
+        for line in branch {
+            println!("{}", line);
+        }
+        let hints = advice::enabled(advice::STATUS_HINTS);
+        Self::print_in_progress(hints);
         
         // Display changes in index (HEAD -> Index)
         if !changes_to_be_committed.is_empty() {
             println!("\n{}:", Color::green("Changes to be committed"));
-            println!("  (use \"{}\" to unstage)", Color::cyan("ash reset HEAD <file>..."));
+            if hints {
+                println!("  (use \"{}\" to unstage)", Color::cyan("ash reset HEAD <file>..."));
+            }
             
             // Sort for consistent output
             changes_to_be_committed.sort();
@@ -864,17 +980,25 @@ impl StatusCommand {
                     "new file" => Color::green("new file"),
                     "modified" => Color::green("modified"),
                     "deleted" => Color::green("deleted"),
+                    "renamed" => Color::green("renamed"),
                     _ => status.to_string()
                 };
-                println!("        {}: {}", colored_status, Color::green(path));
+                let shown = match renames.get(*path) {
+                    Some(pair) if verbose => format!("{} -> {} ({}%)", path_encoding::quote(&pair.from, quote_path), path_encoding::quote(path, quote_path), pair.similarity),
+                    Some(pair) => format!("{} -> {}", path_encoding::quote(&pair.from, quote_path), path_encoding::quote(path, quote_path)),
+                    None => path_encoding::quote(path, quote_path),
+                };
+                println!("        {}: {}", colored_status, Color::green(&shown));
             }
         }
         
         // Display changes in workspace (Index -> Workspace)
         if !changes_not_staged.is_empty() {
             println!("\n{}:", Color::red("Changes not staged for commit"));
-            println!("  (use \"{}\" to update what will be committed)", Color::cyan("ash add <file>..."));
-            println!("  (use \"{}\" to discard changes in working directory)", Color::cyan("ash checkout -- <file>..."));
+            if hints {
+                println!("  (use \"{}\" to update what will be committed)", Color::cyan("ash add <file>..."));
+                println!("  (use \"{}\" to discard changes in working directory)", Color::cyan("ash checkout -- <file>..."));
+            }
             
             // Sort for consistent output
             changes_not_staged.sort();
@@ -883,22 +1007,25 @@ impl StatusCommand {
                 let colored_status = match *status {
                     "modified" => Color::red("modified"),
                     "deleted" => Color::red("deleted"),
+                    "new file" => Color::red("new file"),
                     _ => status.to_string()
                 };
-                println!("        {}: {}", colored_status, Color::red(path));
+                println!("        {}: {}", colored_status, Color::red(&path_encoding::quote(path, quote_path)));
             }
         }
         
         // Display untracked files
         if !untracked.is_empty() {
             println!("\n{}:", Color::red("Untracked files"));
-            println!("  (use \"{}\" to include in what will be committed)", Color::cyan("ash add <file>..."));
+            if hints {
+                println!("  (use \"{}\" to include in what will be committed)", Color::cyan("ash add <file>..."));
+            }
             
             let mut sorted_untracked: Vec<&String> = untracked.iter().collect();
             sorted_untracked.sort();
             
             for path in sorted_untracked {
-                println!("        {}", Color::red(path));
+                println!("        {}", Color::red(&path_encoding::quote(path, quote_path)));
             }
         }
         
@@ -907,4 +1034,55 @@ impl StatusCommand {
             println!("{}", Color::green("nothing to commit, working tree clean"));
         }
     }
+
+    // Say which operation is waiting to be finished, and with `hints`, how
+    // to finish or abandon it
+    fn print_in_progress(hints: bool) {
+        let git_path = git_dir::resolve(Path::new("."));
+        let short = |file: &str| {
+            fs::read_to_string(git_path.join(file)).unwrap_or_default().trim().chars().take(7).collect::<String>()
+        };
+
+        let (state, advice): (String, &[&str]) = if git_path.join("MERGE_HEAD").exists() {
+            ("You have unmerged paths.".to_string(), &[
+                "fix conflicts and run \"ash commit\"",
+                "use \"ash merge --abort\" to abort the merge",
+            ])
+        } else if git_path.join("CHERRY_PICK_HEAD").exists() {
+            (format!("You are currently cherry-picking commit {}.", short("CHERRY_PICK_HEAD")), &[
+                "fix conflicts and run \"ash cherry-pick --continue\"",
+                "use \"ash cherry-pick --abort\" to cancel the cherry-pick operation",
+            ])
+        } else if git_path.join("REVERT_HEAD").exists() {
+            (format!("You are currently reverting commit {}.", short("REVERT_HEAD")), &[
+                "fix conflicts and run \"ash revert --continue\"",
+                "use \"ash revert --abort\" to cancel the revert operation",
+            ])
+        } else if git_path.join("rebase-merge").is_dir() {
+            ("You are currently rebasing.".to_string(), &[
+                "fix conflicts and then run \"ash rebase --continue\"",
+                "use \"ash rebase --skip\" to skip this patch",
+                "use \"ash rebase --abort\" to check out the original branch",
+            ])
+        } else if git_path.join("rebase-apply").is_dir() {
+            ("You are in the middle of an am session.".to_string(), &[
+                "fix conflicts and then run \"ash am --continue\"",
+                "use \"ash am --skip\" to skip this patch",
+                "use \"ash am --abort\" to restore the original branch",
+            ])
+        } else if git_path.join("BISECT_START").exists() {
+            (format!("You are currently bisecting, started from '{}'.", fs::read_to_string(git_path.join("BISECT_START")).unwrap_or_default().trim()), &[
+                "use \"ash bisect reset\" to get back to the original branch",
+            ])
+        } else {
+            return;
+        };
+
+        println!("{}", state);
+        if hints {
+            for line in advice {
+                println!("  ({})", line);
+            }
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,236 @@
+// src/commands/subtree.rs - keep another project in a subdirectory
+use std::collections::HashMap;
+
+use crate::commands::commit_writer::CommitWriter;
+use crate::commands::fetch::{FetchCommand, FetchOptions};
+use crate::commands::merge::{MergeCommand, MergeStrategy, Strategy};
+use crate::commands::read_tree::{ReadTreeCommand, ReadTreeOptions};
+use crate::commands::rebase::RebaseCommand;
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
+use crate::core::merge::bases::Bases;
+use crate::core::merge::subtree;
+use crate::core::repository::autostash::current_author;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::errors::error::Error;
+
+/// Trailers of the commit `ash subtree add` makes, naming the directory
+/// and the project's commit read into it, where a later split starts from
+pub const SUBTREE_DIR_TRAILER: &str = "Subtree-Dir:";
+pub const SUBTREE_MAINLINE_TRAILER: &str = "Subtree-Mainline:";
+pub const SUBTREE_SPLIT_TRAILER: &str = "Subtree-Split:";
+
+pub struct SubtreeCommand;
+
+impl SubtreeCommand {
+    /// Read the commit `args` name, or the `<repository> <ref>` they name,
+    /// into the new directory `prefix` and record it in a merge of that
+    /// commit's history.
+    pub fn add(prefix: &str, args: &[String], message: Option<&str>) -> Result<(), Error> {
+        let prefix = Self::normalize(prefix)?;
+        let mut repo = Repository::new(".")?;
+        if repo.workspace.root_path.join(&prefix).exists() {
+            return Err(Error::Generic(format!("prefix '{}' already exists.", prefix)));
+        }
+        let head_oid = repo.refs.read_head()?.ok_or_else(|| Error::Generic(
+            "You need to make an initial commit before you can add a subtree.".to_string()
+        ))?;
+        if RebaseCommand::has_local_changes(&mut repo, &head_oid)? {
+            return Err(Error::Generic("working tree has modifications.  Cannot add.".to_string()));
+        }
+
+        let oid = match args {
+            [commit] => Self::resolve(&mut repo, commit)?,
+            [repository, reference] => Self::fetch(repository, reference)?,
+            _ => return Err(Error::Generic("usage: ash subtree add --prefix=<prefix> <commit> | <repository> <ref>".to_string())),
+        };
+        drop(repo);
+        ReadTreeCommand::execute(std::slice::from_ref(&oid), &ReadTreeOptions {
+            update: true,
+            prefix: Some(prefix.clone()),
+            ..ReadTreeOptions::default()
+        })?;
+
+        let mut repo = Repository::new(".")?;
+        repo.index.load()?;
+        let git_path = git_dir::resolve(&repo.path);
+        let message = format!(
+            "{}\n\n{} {}\n{} {}\n{} {}",
+            message.map(str::to_string).unwrap_or_else(|| format!("Add '{}/' from commit '{}'", prefix, oid)),
+            SUBTREE_DIR_TRAILER, prefix,
+            SUBTREE_MAINLINE_TRAILER, head_oid,
+            SUBTREE_SPLIT_TRAILER, oid,
+        );
+        let mut writer = CommitWriter::new(&repo.workspace.root_path, git_path, &mut repo.database, &mut repo.index, &repo.refs);
+        writer.reflog_action = "subtree add".to_string();
+        writer.write_commit(vec![head_oid, oid], &message, None)?;
+        println!("Added dir '{}'", prefix);
+        Ok(())
+    }
+
+    /// Merge `commit` of the project kept in `prefix` into it with the
+    /// subtree strategy
+    pub fn merge(prefix: &str, commit: &str, message: Option<&str>) -> Result<(), Error> {
+        let prefix = Self::normalize(prefix)?;
+        let mut repo = Repository::new(".")?;
+        let oid = Self::resolve(&mut repo, commit)?;
+        Self::merge_oid(repo, &prefix, &oid, message)
+    }
+
+    /// Fetch `reference` from `repository` and merge it into `prefix`
+    pub fn pull(prefix: &str, repository: &str, reference: &str, message: Option<&str>) -> Result<(), Error> {
+        let prefix = Self::normalize(prefix)?;
+        let oid = Self::fetch(repository, reference)?;
+        Self::merge_oid(Repository::new(".")?, &prefix, &oid, message)
+    }
+
+    /// Print a history of `prefix` alone, from `revision` or HEAD: each
+    /// commit that touched it rewritten with its tree as the root tree.
+    /// Commits the directory was added or merged from are their own
+    /// rewrites, so splitting again gives the same commits. With `branch`,
+    /// a branch is made at the result.
+    pub fn split(prefix: &str, revision: Option<&str>, branch: Option<&str>) -> Result<(), Error> {
+        let prefix = Self::normalize(prefix)?;
+        let mut repo = Repository::new(".")?;
+        let tip = Self::resolve(&mut repo, revision.unwrap_or("HEAD"))?;
+
+        // Commits read in by `subtree add` are already the project's own
+        let mut rewritten: HashMap<String, String> = HashMap::new();
+        let walked = RevList::new(&mut repo.database, &repo.refs, std::slice::from_ref(&tip), false)?
+            .commits(&WalkOptions::default())?;
+        for commit in walked.iter().map(|walked| &walked.commit) {
+            let message = commit.get_message();
+            if Self::trailer(message, SUBTREE_DIR_TRAILER) == Some(prefix.as_str()) {
+                if let Some(split) = Self::trailer(message, SUBTREE_SPLIT_TRAILER) {
+                    rewritten.insert(split.to_string(), split.to_string());
+                }
+            }
+        }
+
+        let mut walk: Vec<String> = vec![tip.clone()];
+        walk.extend(rewritten.keys().map(|split| format!("^{}", split)));
+        let walk_options = WalkOptions { topo_order: true, reverse: true, ..WalkOptions::default() };
+        let walked = RevList::new(&mut repo.database, &repo.refs, &walk, false)?.commits(&walk_options)?;
+
+        for commit in walked.into_iter().map(|walked| walked.commit) {
+            let oid = commit.get_oid().cloned().unwrap_or_default();
+            let mut parents: Vec<String> = Vec::new();
+            for parent in commit.get_parents() {
+                if let Some(mapped) = rewritten.get(&parent).filter(|mapped| !parents.contains(mapped)) {
+                    parents.push(mapped.clone());
+                }
+            }
+
+            // A commit without the directory that follows the project's
+            // history is one of the project's own
+            let tree = match subtree::subtree_oid(&mut repo.database, &oid, &prefix)? {
+                Some(tree) => tree,
+                None => {
+                    if !parents.is_empty() {
+                        rewritten.insert(oid.clone(), oid);
+                    }
+                    continue;
+                },
+            };
+
+            let mut identical = None;
+            for parent in &parents {
+                if subtree::root_tree(&mut repo.database, parent)? == tree {
+                    identical = Some(parent.clone());
+                    break;
+                }
+            }
+            let reused = match identical {
+                Some(identical) if Self::descends_from_all(&mut repo, &identical, &parents)? => identical,
+                _ => Self::copy(&mut repo, &commit, tree, &parents)?,
+            };
+            rewritten.insert(oid, reused);
+        }
+
+        let split = rewritten.get(&tip).cloned()
+            .ok_or_else(|| Error::Generic(format!("No new revisions were found for '{}'", prefix)))?;
+        if let Some(branch) = branch {
+            repo.refs.create_branch(branch, &split, &format!("subtree split: {}", prefix))?;
+            eprintln!("Created branch '{}'", branch);
+        }
+        println!("{}", split);
+        Ok(())
+    }
+
+    fn merge_oid(mut repo: Repository, prefix: &str, oid: &str, message: Option<&str>) -> Result<(), Error> {
+        let head_oid = repo.refs.read_head()?.unwrap_or_default();
+        if subtree::subtree_oid(&mut repo.database, &head_oid, prefix)?.is_none() {
+            return Err(Error::Generic(format!("'{}' does not exist; use 'ash subtree add'", prefix)));
+        }
+        drop(repo);
+        let message = message.map(str::to_string).unwrap_or_else(|| format!("Merge commit '{}'", oid));
+        let strategy = MergeStrategy { strategy: Strategy::Subtree, favor: None, subtree: Some(prefix.to_string()) };
+        MergeCommand::execute(&[oid.to_string()], Some(&message), None, false, false, &strategy)
+    }
+
+    // A copy of `commit` with `tree` and the rewritten `parents`
+    fn copy(repo: &mut Repository, commit: &Commit, tree: String, parents: &[String]) -> Result<String, Error> {
+        let mut lines: Vec<&str> = commit.get_message().lines()
+            .filter(|line| !line.starts_with(MERGE_PARENT_TRAILER))
+            .collect();
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+        let mut message = lines.join("\n");
+        let extra: Vec<String> = parents.iter().skip(1)
+            .map(|oid| format!("{} {}", MERGE_PARENT_TRAILER, oid))
+            .collect();
+        if !extra.is_empty() {
+            message = format!("{}\n\n{}", message, extra.join("\n"));
+        }
+
+        let author = commit.get_author().cloned().unwrap_or_else(current_author);
+        let committer = commit.get_committer().cloned().unwrap_or_else(current_author);
+        let mut copy = Commit::new_with_committer(parents.first().cloned(), tree, author, committer, message);
+        repo.database.store(&mut copy)?;
+        Ok(copy.get_oid().cloned().unwrap_or_default())
+    }
+
+    // Whether every one of `others` is `oid` or one of its ancestors
+    fn descends_from_all(repo: &mut Repository, oid: &str, others: &[String]) -> Result<bool, Error> {
+        for other in others.iter().filter(|other| *other != oid) {
+            if Bases::new(&mut repo.database)?.find(other, oid)? != [other.clone()] {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // The commit fetched as `reference` from `repository`
+    fn fetch(repository: &str, reference: &str) -> Result<String, Error> {
+        let reference = if reference.starts_with("refs/") {
+            reference.to_string()
+        } else {
+            format!("refs/heads/{}", reference)
+        };
+        FetchCommand::execute(Some(repository), std::slice::from_ref(&reference), &FetchOptions::default())?
+            .into_iter()
+            .find(|(name, _)| *name == reference)
+            .map(|(_, oid)| oid)
+            .ok_or_else(|| Error::Generic(format!("couldn't find remote ref {}", reference)))
+    }
+
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        Revision::new(repo, name).resolve(COMMIT)
+            .map_err(|_| Error::Generic(format!("'{}' does not refer to a commit", name)))
+    }
+
+    fn trailer<'m>(message: &'m str, trailer: &str) -> Option<&'m str> {
+        message.lines().find_map(|line| line.strip_prefix(trailer)).map(str::trim)
+    }
+
+    fn normalize(prefix: &str) -> Result<String, Error> {
+        let prefix = prefix.trim_matches('/');
+        if prefix.is_empty() {
+            return Err(Error::Generic("you must provide the --prefix option.".to_string()));
+        }
+        Ok(prefix.to_string())
+    }
+}
@@ -0,0 +1,67 @@
+// src/commands/switch.rs - switch branches, and only branches
+use crate::commands::checkout::CheckoutCommand;
+use crate::core::advice;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+/// How `ash switch` moves HEAD
+#[derive(Debug, Clone, Default)]
+pub struct SwitchOptions {
+    /// `-c`/`-C`: the branch to create at the target and switch to
+    pub create: Option<String>,
+    /// `-C`: reset the branch if it exists
+    pub force_create: bool,
+    /// Detach HEAD at the target's commit
+    pub detach: bool,
+    /// Carry local changes over with a three-way merge
+    pub merge: bool,
+    pub autostash: Option<bool>,
+}
+
+pub struct SwitchCommand;
+
+impl SwitchCommand {
+    /// Switch to the branch `target`, or as `options` say: create a branch
+    /// at `target` (HEAD when missing), or detach at it. Unlike checkout,
+    /// anything other than a branch is refused unless HEAD is to be
+    /// detached, and no paths are ever restored.
+    pub fn execute(target: Option<&str>, options: &SwitchOptions) -> Result<(), Error> {
+        if options.create.is_some() && options.detach {
+            return Err(Error::Generic("'-c' and '--detach' cannot be used together".to_string()));
+        }
+        if let Some(name) = &options.create {
+            return CheckoutCommand::create_branch(name, target, options.force_create, options.autostash, options.merge);
+        }
+        if options.detach {
+            return CheckoutCommand::execute(target.unwrap_or("HEAD"), options.autostash, true, options.merge);
+        }
+
+        let target = target.ok_or_else(|| Error::Generic("missing branch or commit argument".to_string()))?;
+        Self::expect_branch(target)?;
+        CheckoutCommand::execute(target, options.autostash, false, options.merge)
+    }
+
+    // Refuse `target` unless it names a branch, saying what it names instead
+    fn expect_branch(target: &str) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        if repo.refs.read_ref(&format!("refs/heads/{}", target))?.is_some() {
+            return Ok(());
+        }
+
+        let kind = if repo.refs.read_ref(&format!("refs/tags/{}", target))?.is_some() {
+            "tag"
+        } else if repo.refs.read_ref(&format!("refs/remotes/{}", target))?.is_some() {
+            "remote branch"
+        } else if Revision::new(&mut repo, target).resolve(COMMIT).is_ok() {
+            "commit"
+        } else {
+            return Err(Error::Generic(format!("invalid reference: {}", target)));
+        };
+        advice::advise(
+            advice::SUGGEST_DETACHING_HEAD,
+            "If you want to detach HEAD at the commit, try again with the --detach option.",
+        );
+        Err(Error::Generic(format!("a branch is expected, got {} '{}'", kind, target)))
+    }
+}
@@ -0,0 +1,73 @@
+// src/commands/symbolic_ref.rs - read, point or delete a symbolic ref
+use crate::core::reflog::Reflog;
+use crate::core::refs::HEAD;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+pub struct SymbolicRefCommand;
+
+impl SymbolicRefCommand {
+    /// Print the ref `name` points to, shortened with `short` to how it
+    /// would be given on the command line. Returns false if `name` is not
+    /// symbolic and `quiet` says to only exit with 1 then.
+    pub fn read(name: &str, short: bool, quiet: bool) -> Result<bool, Error> {
+        let repo = Repository::new(".")?;
+        match repo.refs.symbolic_target(name)? {
+            Some(target) => {
+                println!("{}", if short { Self::shorten(&target) } else { &target });
+                Ok(true)
+            },
+            None if quiet => Ok(false),
+            None => Err(Error::Generic(format!("ref {} is not a symbolic ref", name))),
+        }
+    }
+
+    /// Point `name` at the ref `target`, logging `message` when the
+    /// commit it stands for changes
+    pub fn write(name: &str, target: &str, message: Option<&str>) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        if name == HEAD && !target.starts_with("refs/") {
+            return Err(Error::Generic(format!("Refusing to point {} outside of refs/", name)));
+        }
+        if target.split('/').any(|part| part.is_empty() || part.starts_with('.') || part.ends_with(".lock"))
+            || target.contains(['~', '^', ':', '?', '*', '[', '\\', ' '])
+            || target.contains("..")
+        {
+            return Err(Error::Generic(format!("Refusing to set '{}' to invalid ref '{}'", name, target)));
+        }
+
+        let old = repo.refs.read_ref(name)?;
+        repo.refs.set_symbolic_ref(name, target)?;
+        let new = repo.refs.read_ref(target)?;
+        if let (Some(message), Some(new)) = (message, &new) {
+            if old.as_ref() != Some(new) {
+                Reflog::new(git_dir::resolve(&repo.path)).append(name, old.as_deref(), new, message)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete the symbolic ref `name`, but nothing that is not one
+    pub fn delete(name: &str, quiet: bool) -> Result<bool, Error> {
+        let repo = Repository::new(".")?;
+        if name == HEAD {
+            return Err(Error::Generic("deleting 'HEAD' is not allowed".to_string()));
+        }
+        if repo.refs.symbolic_target(name)?.is_none() {
+            if quiet {
+                return Ok(false);
+            }
+            return Err(Error::Generic(format!("Cannot delete {}, not a symbolic ref", name)));
+        }
+        repo.refs.delete_ref(name)?;
+        Ok(true)
+    }
+
+    // A branch, tag or remote-tracking branch by its short name
+    fn shorten(target: &str) -> &str {
+        ["refs/heads/", "refs/tags/", "refs/remotes/", "refs/"].iter()
+            .find_map(|prefix| target.strip_prefix(prefix))
+            .unwrap_or(target)
+    }
+}
@@ -0,0 +1,310 @@
+// src/commands/tag.rs - create, list and delete tags
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use regex::Regex;
+
+use crate::errors::error::Error;
+use crate::commands::commit::get_editor_command;
+use crate::core::column::Columns;
+use crate::core::config::Config;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::tag::Tag;
+use crate::core::editor::Editor;
+use crate::core::gpg;
+use crate::core::refs::{Reference, HEAD};
+use crate::core::repository::autostash::current_author;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+
+const TAGS_PREFIX: &str = "refs/tags/";
+const TAG_EDITMSG: &str = "TAG_EDITMSG";
+const SORT_KEY: &str = "tag.sort";
+const GPG_SIGN_KEY: &str = "tag.gpgSign";
+
+/// How `ash tag <name>` makes the tag
+#[derive(Debug, Clone, Default)]
+pub struct TagOptions {
+    pub annotate: bool,
+    pub message: Option<String>,
+    /// Replace a tag of the same name
+    pub force: bool,
+    /// Whether to sign the tag; `tag.gpgSign` decides for annotated tags
+    /// when not given
+    pub sign: Option<bool>,
+    /// The key to sign with rather than `user.signingKey` or the tagger
+    pub local_user: Option<String>,
+}
+
+/// Which tags `ash tag --list` shows, and in what order
+#[derive(Debug, Clone, Default)]
+pub struct TagListOptions {
+    /// A glob such as `v1.*` the names must match
+    pub pattern: Option<String>,
+    /// Commits the tagged commits must contain
+    pub contains: Vec<String>,
+    /// Commits the tagged commits must not contain
+    pub no_contains: Vec<String>,
+    /// Objects the tags must point at
+    pub points_at: Vec<String>,
+    /// `refname` or `version:refname` (`v:refname`), `-` first to reverse;
+    /// `tag.sort` when not given
+    pub sort: Option<String>,
+    /// The `--column` words, over `column.ui` and `column.tag`
+    pub column: Option<String>,
+}
+
+pub struct TagCommand;
+
+impl TagCommand {
+    /// Print the names of the tags `options` select, in the order it asks
+    /// for (by name unless told otherwise)
+    pub fn list(options: &TagListOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let matcher = match &options.pattern {
+            Some(pattern) => Some(Self::glob(pattern)?),
+            None => None,
+        };
+        let config = Config::load(&git_dir::resolve(&repo.path))?;
+        let columns = Columns::load(&config, "tag", options.column.as_deref())?;
+        let sort = match &options.sort {
+            Some(sort) => Some(sort.clone()),
+            None => config.get(SORT_KEY),
+        };
+        let (descending, by_version) = Self::parse_sort(sort.as_deref().unwrap_or("refname"))?;
+
+        let contains = Self::resolve_all(&mut repo, &options.contains)?;
+        let no_contains = Self::resolve_all(&mut repo, &options.no_contains)?;
+        let points_at = Self::resolve_all(&mut repo, &options.points_at)?;
+
+        let mut names: Vec<String> = Vec::new();
+        for reference in repo.refs.list_refs_under("refs/tags")? {
+            let name = match reference {
+                Reference::Symbolic(path) => match path.strip_prefix(TAGS_PREFIX) {
+                    Some(name) => name.to_string(),
+                    None => continue,
+                },
+                Reference::Direct(_) => continue,
+            };
+            if matcher.as_ref().is_some_and(|matcher| !matcher.is_match(&name)) {
+                continue;
+            }
+            if !contains.is_empty() || !no_contains.is_empty() || !points_at.is_empty() {
+                let oid = match repo.refs.read_ref(&format!("{}{}", TAGS_PREFIX, name))? {
+                    Some(oid) => oid,
+                    None => continue,
+                };
+                let target = repo.database.peel(&oid)?;
+                if !points_at.is_empty() && !points_at.iter().any(|object| *object == oid || *object == target) {
+                    continue;
+                }
+                if !contains.is_empty() && !Self::reaches_any(&mut repo.database, &target, &contains)? {
+                    continue;
+                }
+                if !no_contains.is_empty() && Self::reaches_any(&mut repo.database, &target, &no_contains)? {
+                    continue;
+                }
+            }
+            names.push(name);
+        }
+
+        if by_version {
+            names.sort_by(|a, b| Self::compare_versions(a, b));
+        } else {
+            names.sort();
+        }
+        if descending {
+            names.reverse();
+        }
+
+        columns.print(&names);
+        Ok(())
+    }
+
+    /// Tag `target` (HEAD by default) as `name`. A lightweight tag is just
+    /// the ref; with `annotate`, a `message` or signing a tag object
+    /// recording the tagger and the message is created and the ref points
+    /// at it. Without a message for an annotated tag the editor is opened.
+    /// A signed tag has a GnuPG signature of the rest of it at the end of
+    /// its message. An existing tag is only replaced with `force`.
+    pub fn create(name: &str, target: Option<&str>, options: &TagOptions) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let config = Config::load(&git_path).ok();
+        let annotated = options.annotate || options.message.is_some();
+        let sign = options.sign.unwrap_or_else(|| {
+            options.local_user.is_some()
+                || (annotated && config.as_ref().and_then(|config| config.get_bool(GPG_SIGN_KEY)).unwrap_or(false))
+        });
+        let force = options.force;
+        if !repo.refs.is_valid_branch_name(name) {
+            return Err(Error::Generic(format!("'{}' is not a valid tag name.", name)));
+        }
+
+        let ref_name = format!("{}{}", TAGS_PREFIX, name);
+        let existing = repo.refs.read_ref(&ref_name)?;
+        if existing.is_some() && !force {
+            return Err(Error::Generic(format!("tag '{}' already exists", name)));
+        }
+
+        let target = target.unwrap_or(HEAD);
+        let mut revision = Revision::new(&mut repo, target);
+        let object_oid = match revision.resolve(COMMIT) {
+            Ok(oid) => oid,
+            Err(e) => {
+                for err in revision.errors {
+                    eprintln!("error: {}", err.message);
+                }
+                return Err(e);
+            }
+        };
+
+        let oid = if annotated || sign {
+            let message = match &options.message {
+                Some(message) => message.to_string(),
+                None => Self::compose_message(&repo, name)?
+                    .ok_or_else(|| Error::Generic("no tag message?".to_string()))?,
+            };
+            let mut tag = Tag::new(object_oid, COMMIT.to_string(), name.to_string(), current_author(), message);
+            if sign {
+                if !tag.message.ends_with('\n') {
+                    tag.message.push('\n');
+                }
+                let key = options.local_user.clone()
+                    .or_else(|| config.as_ref().and_then(|config| config.get(gpg::SIGNING_KEY_KEY)))
+                    .unwrap_or_else(|| format!("{} <{}>", tag.tagger.name, tag.tagger.email));
+                let signature = gpg::sign(&git_path, &tag.payload(), &key)?;
+                tag.message.push_str(&signature);
+            }
+            repo.database.store(&mut tag)?
+        } else {
+            object_oid
+        };
+
+        repo.refs.update_ref(&ref_name, &oid, &format!("tag: tagging {}", target))?;
+        if let Some(old) = existing.filter(|old| *old != oid) {
+            println!("Updated tag '{}' (was {})", name, repo.database.short_oid(&old));
+        }
+        Ok(())
+    }
+
+    /// Delete the given tags, reporting what each pointed at
+    pub fn delete(names: &[String]) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let mut failed = false;
+        for name in names {
+            let ref_name = format!("{}{}", TAGS_PREFIX, name);
+            match repo.refs.read_ref(&ref_name)? {
+                Some(oid) => {
+                    repo.refs.delete_ref(&ref_name)?;
+                    println!("Deleted tag '{}' (was {})", name, repo.database.short_oid(&oid));
+                },
+                None => {
+                    eprintln!("error: tag '{}' not found.", name);
+                    failed = true;
+                },
+            }
+        }
+
+        if failed {
+            return Err(Error::Generic("some tags could not be deleted".to_string()));
+        }
+        Ok(())
+    }
+
+    // Whether the sort is descending and by version
+    fn parse_sort(sort: &str) -> Result<(bool, bool), Error> {
+        let (descending, key) = match sort.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, sort),
+        };
+        match key {
+            "refname" => Ok((descending, false)),
+            "version:refname" | "v:refname" => Ok((descending, true)),
+            _ => Err(Error::Generic(format!("unsupported sort specification '{}'", sort))),
+        }
+    }
+
+    /// Names compared with runs of digits taken as numbers, so that v1.10
+    /// comes after v1.9
+    pub fn compare_versions(a: &str, b: &str) -> Ordering {
+        let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+        loop {
+            match (a.first(), b.first()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                    let a_digits = a.iter().take_while(|c| c.is_ascii_digit()).count();
+                    let b_digits = b.iter().take_while(|c| c.is_ascii_digit()).count();
+                    let a_number = std::str::from_utf8(&a[..a_digits]).unwrap_or("0").trim_start_matches('0');
+                    let b_number = std::str::from_utf8(&b[..b_digits]).unwrap_or("0").trim_start_matches('0');
+                    let order = a_number.len().cmp(&b_number.len()).then_with(|| a_number.cmp(b_number));
+                    if order != Ordering::Equal {
+                        return order;
+                    }
+                    a = &a[a_digits..];
+                    b = &b[b_digits..];
+                },
+                (Some(x), Some(y)) if x != y => return x.cmp(y),
+                _ => {
+                    a = &a[1..];
+                    b = &b[1..];
+                },
+            }
+        }
+    }
+
+    // The commits `revisions` name
+    fn resolve_all(repo: &mut Repository, revisions: &[String]) -> Result<Vec<String>, Error> {
+        let mut resolved = Vec::new();
+        for name in revisions {
+            resolved.push(Revision::new(repo, name).resolve(COMMIT)?);
+        }
+        Ok(resolved)
+    }
+
+    // Whether any of `commits` is `start` or one of its ancestors
+    fn reaches_any(database: &mut Database, start: &str, commits: &[String]) -> Result<bool, Error> {
+        let mut seen = HashSet::new();
+        let mut pending = vec![start.to_string()];
+        while let Some(oid) = pending.pop() {
+            if commits.contains(&oid) {
+                return Ok(true);
+            }
+            if !seen.insert(oid.clone()) {
+                continue;
+            }
+            // Tags of trees and blobs contain no commits
+            if let Some(commit) = database.load(&oid)?.as_any().downcast_ref::<Commit>() {
+                pending.extend(commit.get_parents());
+            }
+        }
+        Ok(false)
+    }
+
+    // `*` matches any run of characters and `?` any one character
+    fn glob(pattern: &str) -> Result<Regex, Error> {
+        let expression: String = pattern.chars()
+            .map(|c| match c {
+                '*' => ".*".to_string(),
+                '?' => ".".to_string(),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect();
+        Regex::new(&format!("^{}$", expression))
+            .map_err(|e| Error::Generic(format!("invalid pattern '{}': {}", pattern, e)))
+    }
+
+    fn compose_message(repo: &Repository, name: &str) -> Result<Option<String>, Error> {
+        let path = git_dir::resolve(&repo.path).join(TAG_EDITMSG);
+        Editor::edit(path, get_editor_command(), |editor| {
+            editor.write("")?;
+            editor.note(&format!(
+                "Write a message for tag:\n  {}\nLines starting with '#' will be ignored.", name
+            ))
+        })
+    }
+}
@@ -0,0 +1,70 @@
+// src/commands/update_ref.rs - set or delete a ref, checking what it held
+use crate::core::reflog::{Reflog, ZERO_OID};
+use crate::core::refs::HEAD;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+// Symbolic refs followed before giving up, as git does
+const MAX_SYMREF_DEPTH: usize = 5;
+
+pub struct UpdateRefCommand;
+
+impl UpdateRefCommand {
+    /// Point `name` at the object `new` names, or with None delete it,
+    /// logging `message`. With `old`, the ref must hold that object first,
+    /// or not exist when it is empty or the zero oid. Unless `no_deref`,
+    /// a symbolic ref such as HEAD has the ref it points to updated.
+    pub fn execute(name: &str, new: Option<&str>, old: Option<&str>, no_deref: bool, message: &str) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let new = new.map(|new| Self::resolve(&mut repo, new)).transpose()?;
+        let expected = match old {
+            Some(old) if old.is_empty() || old == ZERO_OID => Some(None),
+            Some(old) => Some(Some(Self::resolve(&mut repo, old)?)),
+            None => None,
+        };
+
+        let mut target = name.to_string();
+        if !no_deref {
+            for _ in 0..MAX_SYMREF_DEPTH {
+                match repo.refs.symbolic_target(&target)? {
+                    Some(next) => target = next,
+                    None => break,
+                }
+            }
+        }
+
+        let current = repo.refs.read_ref(&target)?.filter(|_| target == HEAD || target.starts_with("refs/"));
+        if let Some(expected) = expected.filter(|expected| *expected != current) {
+            let reason = match (&current, expected) {
+                (Some(current), Some(expected)) => format!("is at {} but expected {}", current, expected),
+                (Some(_), None) => "reference already exists".to_string(),
+                (None, _) => format!("unable to resolve reference '{}'", target),
+            };
+            return Err(Error::Generic(format!("cannot lock ref '{}': {}", name, reason)));
+        }
+
+        if !repo.refs.compare_and_swap(&target, current.as_deref(), new.as_deref(), message)? {
+            return Err(Error::Generic(format!("cannot lock ref '{}': it changed while being updated", name)));
+        }
+        // Moving the branch HEAD is on moves HEAD as well
+        if let Some(new) = &new {
+            if name == HEAD && target != HEAD {
+                Reflog::new(git_dir::resolve(&repo.path)).append(HEAD, current.as_deref(), new, message)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The object `name` stands for: a tag object itself rather than what it
+    // tags, any other revision's commit, or a full object id of any type
+    fn resolve(repo: &mut Repository, name: &str) -> Result<String, Error> {
+        if name.len() == 40 && name.chars().all(|c| c.is_ascii_hexdigit()) && repo.database.exists(name) {
+            return Ok(name.to_string());
+        }
+        Revision::new(repo, name).resolve("tag")
+            .or_else(|_| Revision::new(repo, name).resolve(COMMIT))
+            .map_err(|_| Error::Generic(format!("{}: not a valid SHA1", name)))
+    }
+}
@@ -0,0 +1,94 @@
+// src/commands/var.rs - print the settings commands end up using
+use std::env;
+
+use crate::commands::commit::EDITOR_VARIABLES;
+use crate::commands::init::DEFAULT_BRANCH;
+use crate::commands::rebase::SEQUENCE_EDITOR_VARIABLE;
+use crate::core::editor::DEFAULT_EDITOR;
+use crate::core::pager::{Pager, PAGER_VARIABLES};
+use crate::core::repository::autostash::current_author;
+use crate::errors::error::Error;
+
+const DEFAULT_ORIGIN: &str = "default";
+
+// The names `ash var` knows, in the order `-l` lists them
+const VARIABLES: [&str; 6] = [
+    "ASH_AUTHOR_IDENT",
+    "ASH_COMMITTER_IDENT",
+    "ASH_EDITOR",
+    "ASH_SEQUENCE_EDITOR",
+    "ASH_PAGER",
+    "ASH_DEFAULT_BRANCH",
+];
+
+/// A setting's value and where it came from: `env:<NAME>` for the
+/// environment variable that gave it, or `default`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub value: String,
+    pub origin: String,
+}
+
+pub struct VarCommand;
+
+impl VarCommand {
+    /// Print the value of the variable `name`, or with `list` every
+    /// variable as `NAME=value`. `show_origin` puts where each value came
+    /// from in front of it, to see which of several variables won.
+    pub fn execute(name: Option<&str>, list: bool, show_origin: bool) -> Result<(), Error> {
+        let names: Vec<&str> = match name {
+            Some(name) => vec![name],
+            None => VARIABLES.to_vec(),
+        };
+        for name in names {
+            let setting = Self::resolve(name)?;
+            let line = if list { format!("{}={}", name, setting.value) } else { setting.value };
+            if show_origin {
+                println!("{}\t{}", setting.origin, line);
+            } else {
+                println!("{}", line);
+            }
+        }
+        Ok(())
+    }
+
+    /// What the variable `name` comes to, looked up the way the commands
+    /// using it do
+    pub fn resolve(name: &str) -> Result<Setting, Error> {
+        match name {
+            // Commits are made with the author as committer too
+            "ASH_AUTHOR_IDENT" | "ASH_COMMITTER_IDENT" => Ok(Self::ident()),
+            "ASH_EDITOR" => Ok(Self::editor()),
+            "ASH_SEQUENCE_EDITOR" => Ok(Self::from_env(&[SEQUENCE_EDITOR_VARIABLE]).unwrap_or_else(Self::editor)),
+            "ASH_PAGER" => Ok(Self::from_env(&PAGER_VARIABLES)
+                .unwrap_or_else(|| Setting { value: Pager::default_command(), origin: DEFAULT_ORIGIN.to_string() })),
+            "ASH_DEFAULT_BRANCH" => Ok(Setting { value: DEFAULT_BRANCH.to_string(), origin: DEFAULT_ORIGIN.to_string() }),
+            _ => Err(Error::Generic(format!("unknown variable '{}'; known are {}", name, VARIABLES.join(", ")))),
+        }
+    }
+
+    fn editor() -> Setting {
+        Self::from_env(&EDITOR_VARIABLES)
+            .unwrap_or_else(|| Setting { value: DEFAULT_EDITOR.to_string(), origin: DEFAULT_ORIGIN.to_string() })
+    }
+
+    // The name falls back on USER and the email on one made from the name;
+    // the origin names where each part came from
+    fn ident() -> Setting {
+        let origin = |names: &[&str]| {
+            Self::from_env(names).map(|setting| setting.origin).unwrap_or_else(|| DEFAULT_ORIGIN.to_string())
+        };
+        let (name, email) = (origin(&["GIT_AUTHOR_NAME", "USER"]), origin(&["GIT_AUTHOR_EMAIL"]));
+        Setting {
+            value: current_author().to_string(),
+            origin: if name == email { name } else { format!("{},{}", name, email) },
+        }
+    }
+
+    // The first of `names` set in the environment
+    fn from_env(names: &[&str]) -> Option<Setting> {
+        names.iter().find_map(|name| {
+            env::var(name).ok().map(|value| Setting { value, origin: format!("env:{}", name) })
+        })
+    }
+}
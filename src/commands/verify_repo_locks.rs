@@ -0,0 +1,151 @@
+// src/commands/verify_repo_locks.rs - list the locks held in a repository
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::commands::gc::GC_LOCK;
+use crate::core::lockfile::{self, LOCK_EXTENSION};
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+// A lock nobody owns is taken to be left behind once it is this old
+const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(3600);
+// Objects are written through temp files, never locks, so there is no
+// need to look through them
+const OBJECTS_DIR: &str = "objects";
+
+// A lock file found in the git dir, and what is known about its owner
+struct HeldLock {
+    path: PathBuf,
+    owner: Option<u32>,
+    age: Duration,
+}
+
+pub struct VerifyRepoLocksCommand;
+
+impl VerifyRepoLocksCommand {
+    /// List every lock held in the repository with what it protects, the
+    /// process that took it and how long ago. A lock is stale when that
+    /// process is gone or, when no owner was recorded, once it is older
+    /// than `stale_after` seconds (an hour by default); with `break_stale`
+    /// stale locks are removed. Returns whether stale locks are left.
+    pub fn execute(break_stale: bool, stale_after: Option<u64>) -> Result<bool, Error> {
+        let git_path = git_dir::resolve(Path::new("."));
+        if !git_path.join("HEAD").exists() {
+            return Err(Error::Generic("not an ash repository".to_string()));
+        }
+        let stale_after = stale_after.map(Duration::from_secs).unwrap_or(DEFAULT_STALE_AFTER);
+
+        let mut locks = Vec::new();
+        Self::find_locks(&git_path, &git_path, &mut locks)?;
+        locks.sort_by(|a, b| a.path.cmp(&b.path));
+        if locks.is_empty() {
+            println!("No locks held");
+            return Ok(false);
+        }
+
+        let rows: Vec<(String, &str, String, String, Option<String>)> = locks.iter()
+            .map(|lock| {
+                let name = lock.path.strip_prefix(&git_path).unwrap_or(&lock.path).to_string_lossy().to_string();
+                let kind = Self::kind(&name);
+                let owner = lock.owner.map(|pid| format!("pid {}", pid)).unwrap_or_else(|| "pid unknown".to_string());
+                (name, kind, owner, Self::format_age(lock.age), Self::stale_reason(lock, stale_after))
+            })
+            .collect();
+        let name_width = rows.iter().map(|row| row.0.len()).max().unwrap_or(0);
+        let kind_width = rows.iter().map(|row| row.1.len()).max().unwrap_or(0);
+        let owner_width = rows.iter().map(|row| row.2.len()).max().unwrap_or(0);
+
+        let mut stale_left = false;
+        for ((name, kind, owner, age, stale), lock) in rows.iter().zip(&locks) {
+            let state = match stale {
+                None => "held".to_string(),
+                Some(reason) if break_stale => {
+                    fs::remove_file(&lock.path)?;
+                    let _ = fs::remove_file(lockfile::owner_path(&lock.path));
+                    format!("stale ({}), removed", reason)
+                },
+                Some(reason) => {
+                    stale_left = true;
+                    format!("stale ({})", reason)
+                },
+            };
+            println!("{:<name_width$}  {:<kind_width$}  {:<owner_width$}  {:>4}  {}",
+                name, kind, owner, age, state,
+                name_width = name_width, kind_width = kind_width, owner_width = owner_width);
+        }
+        if stale_left {
+            println!("\nRun with --break-stale to remove the stale locks");
+        }
+        Ok(stale_left)
+    }
+
+    fn find_locks(git_path: &Path, dir: &Path, locks: &mut Vec<HeldLock>) -> Result<(), Error> {
+        for entry in fs::read_dir(dir)?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if dir != git_path || entry.file_name() != OBJECTS_DIR {
+                    Self::find_locks(git_path, &path, locks)?;
+                }
+                continue;
+            }
+            if path.extension().is_none_or(|extension| extension != LOCK_EXTENSION) {
+                continue;
+            }
+            // A lock released since the directory was read is no lock
+            let age = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                Ok(modified) => SystemTime::now().duration_since(modified).unwrap_or_default(),
+                Err(_) => continue,
+            };
+            locks.push(HeldLock { owner: lockfile::read_owner(&path), path, age });
+        }
+        Ok(())
+    }
+
+    // What the lock at `name`, relative to the git dir, protects
+    fn kind(name: &str) -> &'static str {
+        let protected = name.strip_suffix(&format!(".{}", LOCK_EXTENSION)).unwrap_or(name);
+        match protected {
+            "index" => "index",
+            "config" => "config",
+            GC_LOCK => "maintenance",
+            "HEAD" | "packed-refs" => "ref",
+            _ if protected.starts_with("refs/") => "ref",
+            _ => "other",
+        }
+    }
+
+    fn stale_reason(lock: &HeldLock, stale_after: Duration) -> Option<String> {
+        match lock.owner {
+            Some(pid) => match Self::process_alive(pid) {
+                Some(false) => Some(format!("process {} is gone", pid)),
+                Some(true) => None,
+                None if lock.age > stale_after => Some(format!("older than {}", Self::format_age(stale_after))),
+                None => None,
+            },
+            None if lock.age > stale_after => Some(format!("no owner, older than {}", Self::format_age(stale_after))),
+            None => None,
+        }
+    }
+
+    // Whether process `pid` still runs, when that can be told
+    #[cfg(target_os = "linux")]
+    fn process_alive(pid: u32) -> Option<bool> {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn process_alive(_pid: u32) -> Option<bool> {
+        None
+    }
+
+    fn format_age(age: Duration) -> String {
+        let seconds = age.as_secs();
+        match seconds {
+            0..=59 => format!("{}s", seconds),
+            60..=3599 => format!("{}m", seconds / 60),
+            3600..=86399 => format!("{}h", seconds / 3600),
+            _ => format!("{}d", seconds / 86400),
+        }
+    }
+}
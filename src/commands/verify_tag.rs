@@ -0,0 +1,50 @@
+// src/commands/verify_tag.rs - check the signatures of tags
+use crate::core::database::tag::Tag;
+use crate::core::gpg;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::core::revision::Revision;
+use crate::errors::error::Error;
+
+pub struct VerifyTagCommand;
+
+impl VerifyTagCommand {
+    /// Check the GnuPG signature of each tag in `names`, printing what
+    /// GnuPG says about it and, with `verbose`, the tag itself first.
+    /// Returns whether every signature is good.
+    pub fn execute(names: &[String], verbose: bool) -> Result<bool, Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        let mut all_good = true;
+        for name in names {
+            let oid = Revision::new(&mut repo, name).resolve("tag")
+                .map_err(|_| Error::Generic(format!("tag '{}' not found.", name)))?;
+            let object = repo.database.load(&oid)?;
+            let tag = match object.as_any().downcast_ref::<Tag>() {
+                Some(tag) => tag.clone(),
+                None => {
+                    eprintln!("error: {}: cannot verify a non-tag object", name);
+                    all_good = false;
+                    continue;
+                },
+            };
+
+            if verbose {
+                print!("{}", String::from_utf8_lossy(&tag.payload()));
+            }
+            let signature = match tag.signature() {
+                Some(signature) => signature,
+                None => {
+                    eprintln!("error: no signature found");
+                    all_good = false;
+                    continue;
+                },
+            };
+            let verification = gpg::verify(&git_path, &tag.payload(), signature)?;
+            eprint!("{}", verification.output);
+            all_good &= verification.good;
+        }
+        Ok(all_good)
+    }
+}
@@ -0,0 +1,268 @@
+// src/commands/worktree.rs - add, list, lock, unlock, remove and prune
+// linked worktrees
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::commands::gc::GcCommand;
+use crate::commands::rebase::RebaseCommand;
+use crate::core::refs::{Reference, Refs};
+use crate::core::repository::git_dir::{self, GIT_DIR_NAME};
+use crate::core::repository::repository::Repository;
+use crate::core::repository::worktree::Worktree;
+use crate::core::revision::{Revision, COMMIT};
+use crate::errors::error::Error;
+
+/// The options of `ash worktree`, each used by some of its subcommands
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeOptions {
+    pub verbose: bool,
+    pub porcelain: bool,
+    pub dry_run: bool,
+    /// `add -b`: the branch to create
+    pub new_branch: Option<String>,
+    pub detach: bool,
+    pub force: bool,
+    pub reason: Option<String>,
+    pub expire: Option<String>,
+}
+
+pub struct WorktreeCommand;
+
+impl WorktreeCommand {
+    /// Print the main working tree and the linked ones, each with the
+    /// commit and branch checked out, and marked `locked` or `prunable`.
+    /// `verbose` gives the reasons on lines of their own; `porcelain` is
+    /// one attribute per line, for scripts.
+    pub fn list(verbose: bool, porcelain: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+
+        // The main working tree is around the shared metadata directory,
+        // wherever this is run from
+        let common = git_dir::common(&git_path);
+        let main_path = if common == git_path {
+            repo.path.clone()
+        } else if common.ends_with(GIT_DIR_NAME) {
+            common.parent().unwrap_or(&common).to_path_buf()
+        } else {
+            common.clone()
+        };
+        let main_path = fs::canonicalize(&main_path).unwrap_or(main_path);
+        let main_refs = Refs::new(&common);
+        let branch = match main_refs.current_ref()? {
+            Reference::Symbolic(branch) => Some(branch),
+            Reference::Direct(_) => None,
+        };
+        let mut rows = vec![(main_path.display().to_string(), main_refs.read_head()?, branch, None, None)];
+        for worktree in Worktree::list(&git_path)? {
+            let (oid, branch) = worktree.head(&git_path)?;
+            rows.push((worktree.display(), oid, branch, worktree.lock_reason(), worktree.prunable_reason()));
+        }
+
+        let width = rows.iter().map(|(path, ..)| path.len()).max().unwrap_or(0);
+        for (path, oid, branch, locked, prunable) in rows {
+            if porcelain {
+                println!("worktree {}", path);
+                println!("HEAD {}", oid.as_deref().unwrap_or("0000000000000000000000000000000000000000"));
+                match &branch {
+                    Some(branch) => println!("branch {}", branch),
+                    None => println!("detached"),
+                }
+                for (label, reason) in [("locked", &locked), ("prunable", &prunable)] {
+                    match reason.as_deref() {
+                        Some("") => println!("{}", label),
+                        Some(reason) => println!("{} {}", label, reason),
+                        None => {},
+                    }
+                }
+                println!();
+                continue;
+            }
+
+            let short = oid.as_deref().map_or("0000000", |oid| &oid[..7.min(oid.len())]);
+            let checked_out = match &branch {
+                Some(branch) => format!("[{}]", repo.refs.short_name(branch)),
+                None => "(detached HEAD)".to_string(),
+            };
+            let mut line = format!("{:<width$} {} {}", path, short, checked_out, width = width);
+            let mut reasons = Vec::new();
+            for (label, reason) in [("locked", &locked), ("prunable", &prunable)] {
+                if let Some(reason) = reason {
+                    if verbose && !reason.is_empty() {
+                        reasons.push(format!("\t{}: {}", label, reason));
+                    } else {
+                        line.push(' ');
+                        line.push_str(label);
+                    }
+                }
+            }
+            println!("{}", line);
+            for reason in reasons {
+                println!("{}", reason);
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a working tree at `path` sharing this repository's objects
+    /// and refs, with its own HEAD and index, and check out in it: the new
+    /// branch `new_branch` at `commit_ish` (HEAD by default); the branch
+    /// `commit_ish` names; with `detach` or any other commit, a detached
+    /// HEAD; or with nothing given, the branch named after the last
+    /// component of `path`, created at HEAD if it does not exist. A branch
+    /// is never checked out in two working trees at once.
+    pub fn add(path: &str, commit_ish: Option<&str>, new_branch: Option<&str>, detach: bool) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let target = Path::new(path);
+        if target.exists() && fs::read_dir(target).map_or(true, |mut entries| entries.next().is_some()) {
+            return Err(Error::Generic(format!("'{}' already exists", path)));
+        }
+
+        // The branch to check out, and whether it is to be created
+        let (branch, start) = match (new_branch, commit_ish) {
+            (Some(_), _) if detach => {
+                return Err(Error::Generic("'-b' and '--detach' cannot be used together".to_string()));
+            },
+            (Some(branch), start) => (Some((branch.to_string(), true)), start.unwrap_or("HEAD").to_string()),
+            (None, Some(name)) if !detach && repo.refs.read_ref(&format!("refs/heads/{}", name))?.is_some() => {
+                (Some((name.to_string(), false)), name.to_string())
+            },
+            (None, Some(name)) => (None, name.to_string()),
+            (None, None) if detach => (None, "HEAD".to_string()),
+            (None, None) => {
+                let name = target.file_name().map(|name| name.to_string_lossy().to_string())
+                    .ok_or_else(|| Error::Generic(format!("invalid path '{}'", path)))?;
+                let exists = repo.refs.read_ref(&format!("refs/heads/{}", name))?.is_some();
+                let start = if exists { name.clone() } else { "HEAD".to_string() };
+                (Some((name, !exists)), start)
+            },
+        };
+        let oid = Revision::new(&mut repo, &start).resolve(COMMIT)?;
+
+        let head = match &branch {
+            Some((name, true)) => {
+                repo.refs.create_branch(name, &oid, &format!("branch: Created from {}", start))?;
+                eprintln!("Preparing worktree (new branch '{}')", name);
+                format!("ref: refs/heads/{}", name)
+            },
+            Some((name, false)) => {
+                if let Some(location) = Worktree::checked_out(&git_path, &format!("refs/heads/{}", name))? {
+                    return Err(Error::Generic(format!("'{}' is already checked out at '{}'", name, location)));
+                }
+                eprintln!("Preparing worktree (checking out '{}')", name);
+                format!("ref: refs/heads/{}", name)
+            },
+            None => {
+                eprintln!("Preparing worktree (detached HEAD {})", &oid[..7]);
+                oid.clone()
+            },
+        };
+
+        fs::create_dir_all(target)?;
+        let target = fs::canonicalize(target)?;
+        let worktree = Worktree::create(&git_path, &target, &head)?;
+        git_dir::write_pointer(&target, &worktree.admin_path)?;
+
+        // Fill the new index and working tree from the commit
+        let result = Repository::new(&target.to_string_lossy()).and_then(|mut linked| {
+            let tree_diff = linked.tree_diff(None, Some(&oid))?;
+            linked.index.load_for_update()?;
+            let mut migration = linked.migration(tree_diff);
+            if let Err(e) = migration.apply_changes() {
+                linked.index.rollback()?;
+                return Err(e);
+            }
+            linked.index.write_updates()
+        });
+        if let Err(e) = result {
+            worktree.remove()?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Delete the worktree `name`, its id or path: its working tree and
+    /// administrative files. Unless `force` is given, one that is locked or
+    /// has changes or untracked files is kept.
+    pub fn remove(name: &str, force: bool) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let worktree = Worktree::find(&git_path, name)?;
+        if !force {
+            if let Some(reason) = worktree.lock_reason() {
+                return Err(Error::Generic(if reason.is_empty() {
+                    "cannot remove a locked working tree; use --force to override or unlock first".to_string()
+                } else {
+                    format!("cannot remove a locked working tree, lock reason: {}\nuse --force to override or unlock first", reason)
+                }));
+            }
+            if let Some(path) = worktree.path.as_ref().filter(|path| path.exists()) {
+                let mut linked = Repository::new(&path.to_string_lossy())?;
+                if Self::has_changes(&mut linked)? {
+                    return Err(Error::Generic(format!(
+                        "'{}' contains modified or untracked files, use --force to delete it", worktree.display()
+                    )));
+                }
+            }
+        }
+        worktree.remove()
+    }
+
+    // Local changes to tracked files, or files that are not tracked
+    fn has_changes(repo: &mut Repository) -> Result<bool, Error> {
+        let head = repo.refs.read_head()?.unwrap_or_default();
+        if RebaseCommand::has_local_changes(repo, &head)? {
+            return Ok(true);
+        }
+        Ok(repo.workspace.list_files()?.iter()
+            .any(|file| !repo.index.tracked(&file.to_string_lossy())))
+    }
+
+    /// Lock the worktree `name`, its id or path, so that prune leaves it
+    /// alone while its working tree cannot be seen, with an optional reason
+    pub fn lock(name: &str, reason: Option<&str>) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        Worktree::find(&git_dir::resolve(&repo.path), name)?.lock(reason)
+    }
+
+    pub fn unlock(name: &str) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        Worktree::find(&git_dir::resolve(&repo.path), name)?.unlock()
+    }
+
+    /// Delete the administrative files of worktrees that are prunable,
+    /// unless `expire` is given and they were in use since then. Locked
+    /// worktrees are kept. With `dry_run` only report what would go.
+    pub fn prune(dry_run: bool, verbose: bool, expire: Option<&str>) -> Result<(), Error> {
+        let repo = Repository::new(".")?;
+        let git_path = git_dir::resolve(&repo.path);
+        let now = SystemTime::now();
+        let cutoff = match expire {
+            Some(expire) => GcCommand::parse_expiry(expire, now)?,
+            None => Some(now),
+        };
+
+        for worktree in Worktree::list(&git_path)? {
+            let reason = match worktree.prunable_reason() {
+                Some(reason) => reason,
+                None => continue,
+            };
+            let expired = match cutoff {
+                Some(cutoff) => worktree.last_used()? <= cutoff,
+                None => false,
+            };
+            if !expired {
+                continue;
+            }
+            if verbose || dry_run {
+                println!("Removing worktrees/{}: {}", worktree.id, reason);
+            }
+            if !dry_run {
+                worktree.prune()?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+// src/commands/write_tree.rs - write the index out as tree objects
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::database::tree::Tree;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+pub struct WriteTreeCommand;
+
+impl WriteTreeCommand {
+    /// Store the trees of the index and print the ID of the root, or with
+    /// `prefix` the ID of the tree of that directory. An index with
+    /// conflicts has no tree to write; the unmerged paths are reported.
+    pub fn execute(prefix: Option<&str>) -> Result<(), Error> {
+        let mut repo = Repository::new(".")?;
+        repo.index.load()?;
+
+        let unmerged = repo.index.conflict_paths();
+        if !unmerged.is_empty() {
+            for path in &unmerged {
+                for stage in 1..=3 {
+                    if let Some(entry) = repo.index.get_stage_entry(path, stage) {
+                        eprintln!("{}: unmerged ({})", path, entry.get_oid());
+                    }
+                }
+            }
+            return Err(Error::Generic("write-tree: error building trees".to_string()));
+        }
+
+        let prefix = prefix.map(|prefix| prefix.trim_end_matches('/')).filter(|prefix| !prefix.is_empty());
+        let entries: Vec<DatabaseEntry> = repo.index.each_entry()
+            .filter(|entry| !entry.intent_to_add)
+            .filter_map(|entry| {
+                let path = match prefix {
+                    Some(prefix) => entry.get_path().strip_prefix(prefix)?.strip_prefix('/')?,
+                    None => entry.get_path(),
+                };
+                Some(DatabaseEntry::new(path.to_string(), entry.get_oid().to_string(), &entry.mode_octal()))
+            })
+            .collect();
+        if let Some(prefix) = prefix.filter(|_| entries.is_empty()) {
+            return Err(Error::Generic(format!("write-tree: prefix {} not found", prefix)));
+        }
+
+        println!("{}", Self::write(&mut repo.database, &entries)?);
+        Ok(())
+    }
+
+    /// Store the trees holding `entries` and return the ID of the root,
+    /// the empty tree when there are none
+    pub fn write(database: &mut Database, entries: &[DatabaseEntry]) -> Result<String, Error> {
+        let mut root = if entries.is_empty() { Tree::new() } else { Tree::build(entries.iter())? };
+        root.traverse(|tree| database.store(tree).map(|_| ()))?;
+        root.get_oid().cloned().ok_or_else(|| Error::Generic("Tree OID not set after storage".to_string()))
+    }
+}
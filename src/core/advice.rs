@@ -0,0 +1,47 @@
+// src/core/advice.rs - hints for newcomers that can be turned off one by one
+//
+// Each piece of advice is on unless `advice.<name>` is set to false in the
+// repository's config:
+//
+// ```text
+// [advice]
+//     statusHints = false
+//     detachedHead = false
+// ```
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+
+/// The `(use "ash ...")` lines in `ash status`
+pub const STATUS_HINTS: &str = "statusHints";
+/// What detached HEAD means, when checking out a commit
+pub const DETACHED_HEAD: &str = "detachedHead";
+/// Commit or stash first, when local changes stop a checkout
+pub const COMMIT_BEFORE_MERGE: &str = "commitBeforeMerge";
+/// How to resolve conflicts, when committing with unmerged paths
+pub const RESOLVE_CONFLICT: &str = "resolveConflict";
+/// How to go on, or back, when a merge, revert or rebase stops on conflicts
+pub const MERGE_CONFLICT: &str = "mergeConflict";
+/// Use --detach, when `ash switch` is given something other than a branch
+pub const SUGGEST_DETACHING_HEAD: &str = "suggestDetachingHead";
+
+/// Whether the advice called `name` is shown in the current repository
+pub fn enabled(name: &str) -> bool {
+    Config::load(&git_dir::resolve(Path::new(".")))
+        .ok()
+        .and_then(|config| config.get_bool(&format!("advice.{}", name)))
+        .unwrap_or(true)
+}
+
+/// Print `advice` as `hint:` lines on stderr, ending with how to turn it
+/// off, unless it has been turned off already
+pub fn advise(name: &str, advice: &str) {
+    if !enabled(name) {
+        return;
+    }
+    for line in advice.lines() {
+        eprintln!("hint: {}", line);
+    }
+    eprintln!("hint: Disable this message by setting advice.{} to false in .ash/config", name);
+}
@@ -0,0 +1,286 @@
+// src/core/attributes.rs - per-path attributes from .ashattributes files
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use regex::Regex;
+
+use crate::core::config::Config;
+use crate::core::diff::myers::is_binary_content;
+use crate::core::ignore::glob_to_regex;
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+/// The name of the attributes files read in every directory of the working tree
+pub const ATTRIBUTES_FILE: &str = ".ashattributes";
+pub const ATTRIBUTES_FILE_KEY: &str = "core.attributesFile";
+// Attributes for this repository only, above every other file
+const INFO_ATTRIBUTES: &str = "info/attributes";
+
+/// What an attribute is for a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttrValue {
+    /// `name`
+    Set,
+    /// `-name`
+    Unset,
+    /// `name=value`
+    Value(String),
+    /// Not mentioned, or `!name`
+    Unspecified,
+}
+
+impl AttrValue {
+    /// How `ash check-attr` would print it
+    pub fn describe(&self) -> &str {
+        match self {
+            AttrValue::Set => "set",
+            AttrValue::Unset => "unset",
+            AttrValue::Value(value) => value,
+            AttrValue::Unspecified => "unspecified",
+        }
+    }
+}
+
+// One line of an attributes file
+struct Rule {
+    regex: Regex,
+    // Matched against the path below the file's directory rather than the name
+    anchored: bool,
+    attributes: Vec<(String, AttrValue)>,
+}
+
+/// The attributes of the paths in a working tree. Files are read from the
+/// lowest precedence up: the global attributes file (`core.attributesFile`,
+/// or ~/.config/ash/attributes), the .ashattributes files from the root down
+/// to the path's directory, then the repository's info/attributes. The last
+/// line matching a path decides each attribute it mentions.
+pub struct Attributes {
+    root_path: PathBuf,
+    global: Vec<Rule>,
+    info: Vec<Rule>,
+    // The rules of each directory's file, read once
+    directories: RefCell<HashMap<String, Vec<Rule>>>,
+}
+
+impl Attributes {
+    pub fn new(root_path: &Path) -> Self {
+        let git_path = git_dir::resolve(root_path);
+        Attributes {
+            root_path: root_path.to_path_buf(),
+            global: Self::global_file(&git_path).map(|path| Self::read_file(&path)).unwrap_or_default(),
+            info: Self::read_file(&git_path.join(INFO_ATTRIBUTES)),
+            directories: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Every attribute specified for `path`, relative to the root, by name
+    pub fn check_all(&self, path: &Path) -> Vec<(String, AttrValue)> {
+        let path = Self::normalize(path);
+        let mut found: HashMap<String, AttrValue> = HashMap::new();
+        let mut apply = |rules: &[Rule], base: &str| {
+            let relative = if base.is_empty() { Some(path.as_str()) } else {
+                path.strip_prefix(base).and_then(|rest| rest.strip_prefix('/'))
+            };
+            let relative = match relative {
+                Some(relative) => relative,
+                None => return,
+            };
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            for rule in rules {
+                if !rule.regex.is_match(if rule.anchored { relative } else { name }) {
+                    continue;
+                }
+                for (attribute, value) in &rule.attributes {
+                    match value {
+                        AttrValue::Unspecified => found.remove(attribute),
+                        value => found.insert(attribute.clone(), value.clone()),
+                    };
+                }
+            }
+        };
+
+        apply(&self.global, "");
+        let mut directories = self.directories.borrow_mut();
+        let mut dir = String::new();
+        let mut parents: Vec<&str> = path.split('/').collect();
+        parents.pop();
+        for depth in 0..=parents.len() {
+            if depth > 0 {
+                dir = if dir.is_empty() { parents[depth - 1].to_string() } else { format!("{}/{}", dir, parents[depth - 1]) };
+            }
+            let rules = directories.entry(dir.clone())
+                .or_insert_with(|| Self::read_file(&self.root_path.join(&dir).join(ATTRIBUTES_FILE)));
+            apply(rules, &dir);
+        }
+        apply(&self.info, "");
+
+        let mut found: Vec<(String, AttrValue)> = found.into_iter().collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        found
+    }
+
+    /// The value of attribute `name` for `path`
+    pub fn get(&self, path: &Path, name: &str) -> AttrValue {
+        self.check_all(path).into_iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value)
+            .unwrap_or(AttrValue::Unspecified)
+    }
+
+    /// Whether `path` is diffed as binary: `-diff` (or `binary`) says so and
+    /// `diff` says not, whatever the contents; otherwise the contents decide
+    pub fn is_binary(&self, path: &Path, contents: &[&[u8]]) -> bool {
+        match self.get(path, "diff") {
+            AttrValue::Unset => true,
+            AttrValue::Set | AttrValue::Value(_) => false,
+            AttrValue::Unspecified => contents.iter().any(|content| is_binary_content(content)),
+        }
+    }
+
+    /// `data` as it is diffed: through the `diff.<driver>.textconv` command
+    /// when `path` has `diff=<driver>` and one is configured, which is given
+    /// the data in a temporary file and prints the text, as it is otherwise
+    pub fn textconv(&self, path: &Path, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let command = match self.get(path, "diff") {
+            AttrValue::Value(driver) => Config::load(&git_dir::resolve(&self.root_path)).ok()
+                .and_then(|config| config.get(&format!("diff.{}.textconv", driver))),
+            _ => None,
+        };
+        let command = match command {
+            Some(command) => command,
+            None => return Ok(data),
+        };
+
+        let file = env::temp_dir().join(format!("ash-textconv-{}", std::process::id()));
+        fs::write(&file, &data)?;
+        let output = Command::new("sh").arg("-c").arg(format!("{} \"$1\"", command))
+            .arg(&command)
+            .arg(&file)
+            .output();
+        let _ = fs::remove_file(&file);
+        match output {
+            Ok(output) if output.status.success() => Ok(output.stdout),
+            _ => Err(Error::Generic(format!("unable to read files to diff: textconv '{}' failed for {}", command, path.display()))),
+        }
+    }
+
+    /// `data` read from the working tree as it is stored: text files have
+    /// their CRLF line endings turned into LF
+    pub fn to_repository(&self, path: &Path, data: Vec<u8>) -> Vec<u8> {
+        if !self.is_text(path, &data) || !data.contains(&b'\r') {
+            return data;
+        }
+        let mut converted = Vec::with_capacity(data.len());
+        for (i, &byte) in data.iter().enumerate() {
+            if byte == b'\r' && data.get(i + 1) == Some(&b'\n') {
+                continue;
+            }
+            converted.push(byte);
+        }
+        converted
+    }
+
+    /// Stored `data` as it is written to the working tree: text files with
+    /// `eol=crlf` get CRLF line endings
+    pub fn to_workspace(&self, path: &Path, data: Vec<u8>) -> Vec<u8> {
+        if self.get(path, "eol") != AttrValue::Value("crlf".to_string()) || !self.is_text(path, &data) {
+            return data;
+        }
+        let mut converted = Vec::with_capacity(data.len() + data.len() / 16);
+        for (i, &byte) in data.iter().enumerate() {
+            if byte == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+                converted.push(b'\r');
+            }
+            converted.push(byte);
+        }
+        converted
+    }
+
+    // Whether line endings of `path` are converted: `text` always, `eol`
+    // implies it, and `text=auto` unless the content looks binary
+    fn is_text(&self, path: &Path, data: &[u8]) -> bool {
+        let attributes = self.check_all(path);
+        let value = |name: &str| attributes.iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.clone())
+            .unwrap_or(AttrValue::Unspecified);
+        match value("text") {
+            AttrValue::Set => true,
+            AttrValue::Unset => false,
+            AttrValue::Value(value) if value == "auto" => !is_binary_content(data),
+            _ => matches!(value("eol"), AttrValue::Value(_)),
+        }
+    }
+
+    fn global_file(git_path: &Path) -> Option<PathBuf> {
+        let home = env::var("HOME").ok();
+        if let Some(file) = Config::load(git_path).ok().and_then(|config| config.get(ATTRIBUTES_FILE_KEY)) {
+            return match (file.strip_prefix("~/"), home) {
+                (Some(rest), Some(home)) => Some(Path::new(&home).join(rest)),
+                _ => Some(PathBuf::from(file)),
+            };
+        }
+        match env::var("XDG_CONFIG_HOME") {
+            Ok(config_home) if !config_home.is_empty() => Some(Path::new(&config_home).join("ash/attributes")),
+            _ => home.map(|home| Path::new(&home).join(".config/ash/attributes")),
+        }
+    }
+
+    // A missing or unreadable file has no rules to give
+    fn read_file(path: &Path) -> Vec<Rule> {
+        fs::read_to_string(path)
+            .map(|content| content.lines().filter_map(Self::parse).collect())
+            .unwrap_or_default()
+    }
+
+    fn parse(line: &str) -> Option<Rule> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let mut fields = line.split_whitespace();
+        let pattern = fields.next()?;
+        // Negative patterns are not allowed, and directories have no attributes
+        if pattern.starts_with('!') || pattern.ends_with('/') {
+            return None;
+        }
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+        let mut attributes = Vec::new();
+        for field in fields {
+            if field == "binary" {
+                // The one built-in macro
+                attributes.push(("binary".to_string(), AttrValue::Set));
+                for name in ["diff", "merge", "text"] {
+                    attributes.push((name.to_string(), AttrValue::Unset));
+                }
+            } else if let Some(name) = field.strip_prefix('-') {
+                attributes.push((name.to_string(), AttrValue::Unset));
+            } else if let Some(name) = field.strip_prefix('!') {
+                attributes.push((name.to_string(), AttrValue::Unspecified));
+            } else if let Some((name, value)) = field.split_once('=') {
+                attributes.push((name.to_string(), AttrValue::Value(value.to_string())));
+            } else {
+                attributes.push((field.to_string(), AttrValue::Set));
+            }
+        }
+
+        let regex = Regex::new(&glob_to_regex(pattern)).ok()?;
+        Some(Rule { regex, anchored, attributes })
+    }
+
+    fn normalize(path: &Path) -> String {
+        path.components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
@@ -0,0 +1,425 @@
+// src/core/blame.rs - find the commit that last changed each line of a file
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::tree::{Tree, TreeEntry};
+use crate::core::diff::myers::{diff_lines, Edit};
+use crate::core::diff::rename::{self, RenameDetection};
+use crate::core::path_filter::PathFilter;
+use crate::errors::error::Error;
+
+/// A line of the blamed file and where it came from
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    pub commit: String,
+    pub content: String,
+}
+
+/// Consecutive lines of the blamed file that one commit introduced,
+/// consecutive in that commit's version of the file as well
+#[derive(Debug, Clone)]
+pub struct BlameRange {
+    pub commit: String,
+    /// Index of the first line in the blamed file
+    pub start: usize,
+    /// Index of the first line in the commit's version of the file
+    pub orig_start: usize,
+    pub lines: Vec<String>,
+}
+
+/// A commit that changed some of the lines `log -L` follows: the range
+/// as it was in the commit's first parent and as the commit left it
+#[derive(Debug, Clone)]
+pub struct LineChange {
+    pub commit: String,
+    /// The file's name in the parent and in the commit, which differ
+    /// when the commit renamed it
+    pub old_path: String,
+    pub path: String,
+    /// Index of the first line of the range in the parent's version;
+    /// `old_lines` is empty when the commit added the whole range
+    pub old_start: usize,
+    pub old_lines: Vec<String>,
+    pub new_start: usize,
+    pub new_lines: Vec<String>,
+}
+
+// A line still looking for its origin: its index in the final file and in
+// the version of the commit currently suspected of introducing it
+type Suspect = (usize, usize);
+
+/// Walks history from a starting commit, passing each line on to a parent
+/// for as long as the parent has it unchanged. The commit a line cannot be
+/// passed on from is the one that introduced it. A file a commit added by
+/// renaming another is followed to its old name, as `log --follow` does.
+///
+/// Ignored commits (e.g. mass reformatting) pass their changed lines on as
+/// well, matching each added line to the removed line at the same offset in
+/// its hunk; lines with no such counterpart stay with the ignored commit.
+pub struct Blame<'a> {
+    database: &'a mut Database,
+    ignored: HashSet<String>,
+}
+
+impl<'a> Blame<'a> {
+    pub fn new(database: &'a mut Database) -> Self {
+        Blame {
+            database,
+            ignored: HashSet::new(),
+        }
+    }
+
+    pub fn ignore(&mut self, oid: &str) {
+        self.ignored.insert(oid.to_string());
+    }
+
+    /// Blame every line of `path` as it is in commit `start`
+    pub fn run(&mut self, start: &str, path: &str) -> Result<Vec<BlameLine>, Error> {
+        let mut result: Vec<Option<BlameLine>> = Vec::new();
+        self.run_incremental(start, path, |range| {
+            let end = range.start + range.lines.len();
+            if result.len() < end {
+                result.resize(end, None);
+            }
+            for (slot, content) in result[range.start..end].iter_mut().zip(range.lines) {
+                *slot = Some(BlameLine { commit: range.commit.clone(), content });
+            }
+            Ok(())
+        })?;
+        result.into_iter()
+            .map(|line| line.ok_or_else(|| Error::Generic("blame left a line unassigned".to_string())))
+            .collect()
+    }
+
+    /// Blame `path` as it is in commit `start`, handing each range of lines
+    /// to `emit` as soon as the commit that introduced it is known, so large
+    /// files can be shown while the walk goes on. Ranges arrive newest
+    /// commit first, not in file order; together they cover every line once.
+    pub fn run_incremental<F>(&mut self, start: &str, path: &str, mut emit: F) -> Result<(), Error>
+    where
+        F: FnMut(BlameRange) -> Result<(), Error>,
+    {
+        let final_lines = self.file_lines(start, path)?
+            .ok_or_else(|| Error::Generic(format!("no such path '{}' in {}", path, start)))?;
+
+        let mut unassigned = final_lines.len();
+        let mut pending: HashMap<String, Vec<Suspect>> = HashMap::new();
+        // The file's name in each commit lines were passed to
+        let mut paths: HashMap<String, String> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        pending.insert(start.to_string(), (0..final_lines.len()).map(|i| (i, i)).collect());
+        paths.insert(start.to_string(), path.to_string());
+        queue.push((self.commit_time(start)?, start.to_string()));
+
+        // Newest first, so a commit reached through several children is only
+        // processed once all of them have passed their lines to it
+        while let Some((_, oid)) = queue.pop() {
+            let mut suspects = match pending.remove(&oid) {
+                Some(suspects) => suspects,
+                None => continue,
+            };
+            let commit = self.load_commit(&oid)?;
+            let path = paths.get(&oid).cloned().unwrap_or_else(|| path.to_string());
+            let lines = self.file_lines(&oid, &path)?.unwrap_or_default();
+
+            for (number, parent) in commit.get_parents().iter().enumerate() {
+                if suspects.is_empty() {
+                    break;
+                }
+                let (parent_path, parent_lines) = match self.parent_file(&oid, parent, &path)? {
+                    Some(file) => file,
+                    None => continue,
+                };
+
+                let edits = diff_lines(&parent_lines, &lines);
+                let mut mapping = Self::unchanged(&edits);
+                if number == 0 && self.ignored.contains(&oid) {
+                    mapping.extend(Self::same_offset(&edits));
+                }
+
+                let (passed, kept): (Vec<Suspect>, Vec<Suspect>) = suspects.into_iter()
+                    .partition(|(_, line)| mapping.contains_key(line));
+                suspects = kept;
+
+                if !passed.is_empty() {
+                    if !pending.contains_key(parent) {
+                        queue.push((self.commit_time(parent)?, parent.clone()));
+                    }
+                    pending.entry(parent.clone()).or_default()
+                        .extend(passed.into_iter().map(|(end, line)| (end, mapping[&line])));
+                    paths.entry(parent.clone()).or_insert(parent_path);
+                }
+            }
+
+            unassigned -= suspects.len();
+            suspects.sort_unstable();
+            for range in Self::ranges(&oid, &suspects, &final_lines) {
+                emit(range)?;
+            }
+        }
+
+        if unassigned > 0 {
+            return Err(Error::Generic("blame left a line unassigned".to_string()));
+        }
+        Ok(())
+    }
+
+    /// The commits that changed lines `first..=last` (from 0, `last` the
+    /// end of the file when None) of `path` as it is in `start`, newest
+    /// first. Blame in reverse: the range is mapped back to each first
+    /// parent through the lines it has unchanged, the lines a commit
+    /// replaced or deleted in between widening it, until the commit that
+    /// added all of it or the file. Renames are followed as in `run`.
+    pub fn line_history(&mut self, start: &str, path: &str, first: usize, last: Option<usize>) -> Result<Vec<LineChange>, Error> {
+        let mut lines = self.file_lines(start, path)?
+            .ok_or_else(|| Error::Generic(format!("There is no path {} in the commit", path)))?;
+        let last = last.unwrap_or(lines.len().saturating_sub(1));
+        if first >= lines.len() || last >= lines.len() || first > last {
+            return Err(Error::Generic(format!("file {} has only {} lines", path, lines.len())));
+        }
+
+        let mut changes = Vec::new();
+        let mut oid = start.to_string();
+        let mut path = path.to_string();
+        let mut range = (first, last);
+        loop {
+            let new_lines = lines[range.0..=range.1].to_vec();
+            let parent = self.load_commit(&oid)?.get_parents().first().cloned();
+            let parent_file = match &parent {
+                Some(parent) => self.parent_file(&oid, parent, &path)?,
+                None => None,
+            };
+            let (parent, (old_path, parent_lines)) = match (parent, parent_file) {
+                (Some(parent), Some(file)) => (parent, file),
+                _ => {
+                    changes.push(LineChange {
+                        commit: oid,
+                        old_path: path.clone(),
+                        path,
+                        old_start: 0,
+                        old_lines: Vec::new(),
+                        new_start: range.0,
+                        new_lines,
+                    });
+                    break;
+                },
+            };
+
+            let edits = diff_lines(&parent_lines, &lines);
+            match Self::map_range(&edits, range) {
+                Some(old) => {
+                    let old_lines = parent_lines[old.0..=old.1].to_vec();
+                    if old_lines != new_lines {
+                        changes.push(LineChange {
+                            commit: oid,
+                            old_path: old_path.clone(),
+                            path: path.clone(),
+                            old_start: old.0,
+                            old_lines,
+                            new_start: range.0,
+                            new_lines,
+                        });
+                    }
+                    range = old;
+                },
+                None => {
+                    // Where the parent would have had the lines, for the hunk header
+                    let old_start = range.0.checked_sub(1)
+                        .and_then(|before| Self::map_range(&edits, (0, before)))
+                        .map_or(0, |(_, end)| end + 1);
+                    changes.push(LineChange {
+                        commit: oid,
+                        old_path,
+                        path,
+                        old_start,
+                        old_lines: Vec::new(),
+                        new_start: range.0,
+                        new_lines,
+                    });
+                    break;
+                },
+            }
+            oid = parent;
+            path = old_path;
+            lines = parent_lines;
+        }
+        Ok(changes)
+    }
+
+    // The lines of the old version the new lines `first..=last` span: from
+    // the old line matching the first, or starting the change block it is
+    // in, to the one matching the last or ending its block. None when the
+    // range has no lines in the old version.
+    fn map_range(edits: &[Edit], (first, last): (usize, usize)) -> Option<(usize, usize)> {
+        let (mut start, mut end) = (None, None);
+        // First old line of the current change block, and old lines so far
+        let mut block_start: Option<usize> = None;
+        let mut old_position = 0;
+        let mut end_in_block = false;
+
+        for edit in edits {
+            match *edit {
+                Edit::Equal(old, new) => {
+                    if end_in_block {
+                        end = old.checked_sub(1);
+                        end_in_block = false;
+                    }
+                    block_start = None;
+                    if new == first {
+                        start = Some(old);
+                    }
+                    if new == last {
+                        end = Some(old);
+                    }
+                    old_position = old + 1;
+                },
+                Edit::Delete(old) => {
+                    block_start.get_or_insert(old);
+                    old_position = old + 1;
+                },
+                Edit::Insert(new) => {
+                    if new == first {
+                        start = Some(block_start.unwrap_or(old_position));
+                    }
+                    if new == last {
+                        end_in_block = true;
+                    }
+                },
+            }
+        }
+        if end_in_block {
+            end = old_position.checked_sub(1);
+        }
+
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => Some((start, end)),
+            _ => None,
+        }
+    }
+
+    // Group the sorted lines a commit introduced into runs that are
+    // consecutive both in the blamed file and in the commit's version
+    fn ranges(oid: &str, suspects: &[Suspect], final_lines: &[String]) -> Vec<BlameRange> {
+        let mut ranges: Vec<BlameRange> = Vec::new();
+        for &(end, line) in suspects {
+            match ranges.last_mut() {
+                Some(range) if range.start + range.lines.len() == end && range.orig_start + range.lines.len() == line => {
+                    range.lines.push(final_lines[end].clone());
+                },
+                _ => ranges.push(BlameRange {
+                    commit: oid.to_string(),
+                    start: end,
+                    orig_start: line,
+                    lines: vec![final_lines[end].clone()],
+                }),
+            }
+        }
+        ranges
+    }
+
+    // Line in the new version -> line in the old version, for unchanged lines
+    fn unchanged(edits: &[Edit]) -> HashMap<usize, usize> {
+        edits.iter()
+            .filter_map(|edit| match edit {
+                Edit::Equal(old, new) => Some((*new, *old)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Pair the k-th added line of each hunk with its k-th removed line
+    fn same_offset(edits: &[Edit]) -> HashMap<usize, usize> {
+        let mut mapping = HashMap::new();
+        let mut deleted = Vec::new();
+        let mut inserted = Vec::new();
+
+        let mut flush = |deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+            for (new, old) in inserted.iter().zip(deleted.iter()) {
+                mapping.insert(*new, *old);
+            }
+            deleted.clear();
+            inserted.clear();
+        };
+
+        for edit in edits {
+            match edit {
+                Edit::Delete(old) => deleted.push(*old),
+                Edit::Insert(new) => inserted.push(*new),
+                Edit::Equal(_, _) => flush(&mut deleted, &mut inserted),
+            }
+        }
+        flush(&mut deleted, &mut inserted);
+
+        mapping
+    }
+
+    // The name and lines `parent` has the file `path` of `commit` under:
+    // the same name, or the one it had before the commit renamed it. None
+    // when the commit added the file.
+    fn parent_file(&mut self, commit: &str, parent: &str, path: &str) -> Result<Option<(String, Vec<String>)>, Error> {
+        if let Some(lines) = self.file_lines(parent, path)? {
+            return Ok(Some((path.to_string(), lines)));
+        }
+        let changes = self.database.tree_diff(Some(parent), Some(commit), &PathFilter::new())?;
+        let added = match changes.get(Path::new(path)) {
+            Some((None, Some(new))) => new.get_oid().to_string(),
+            _ => return Ok(None),
+        };
+        let mut removed: Vec<(String, String)> = changes.iter()
+            .filter_map(|(removed, (old, new))| match (old, new) {
+                (Some(old), None) => Some((removed.to_string_lossy().to_string(), old.get_oid().to_string())),
+                _ => None,
+            })
+            .collect();
+        removed.sort();
+        let added = [(path.to_string(), added)];
+        let from = match rename::detect(self.database, &removed, &added, &[], RenameDetection::default())?.into_iter().next() {
+            Some(pair) => pair.from,
+            None => return Ok(None),
+        };
+        Ok(self.file_lines(parent, &from)?.map(|lines| (from, lines)))
+    }
+
+    // Lines of `path` in `commit`, or None when the commit has no such file
+    fn file_lines(&mut self, commit: &str, path: &str) -> Result<Option<Vec<String>>, Error> {
+        let mut tree_oid = self.load_commit(commit)?.get_tree().to_string();
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+        for (i, component) in components.iter().enumerate() {
+            let object = self.database.load(&tree_oid)?;
+            let tree = match object.as_any().downcast_ref::<Tree>() {
+                Some(tree) => tree,
+                None => return Ok(None),
+            };
+
+            match tree.get_entry(component) {
+                Some(TreeEntry::Tree(subtree)) if i + 1 < components.len() => {
+                    tree_oid = match subtree.get_oid() {
+                        Some(oid) => oid.clone(),
+                        None => return Ok(None),
+                    };
+                },
+                Some(TreeEntry::Blob(oid, _)) if i + 1 == components.len() => {
+                    let data = self.database.load(oid)?.to_bytes();
+                    return Ok(Some(String::from_utf8_lossy(&data).lines().map(str::to_string).collect()));
+                },
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn load_commit(&mut self, oid: &str) -> Result<Commit, Error> {
+        let object = self.database.load(oid)?;
+        object.as_any().downcast_ref::<Commit>().cloned()
+            .ok_or_else(|| Error::Generic(format!("Object {} is not a commit", oid)))
+    }
+
+    fn commit_time(&mut self, oid: &str) -> Result<i64, Error> {
+        Ok(self.load_commit(oid)?.committer.timestamp.timestamp())
+    }
+}
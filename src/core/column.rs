@@ -0,0 +1,249 @@
+// src/core/column.rs - lay listings out in columns, as `git column` does
+//
+// `column.ui` says how listings are laid out, and `column.<command>`
+// (`column.branch`, `column.tag`) changes that for one command; each is a
+// list of words, separated by commas or spaces, later words winning:
+//
+//     always | never | auto    use columns, or only when writing to a terminal
+//     column | row | plain     fill columns first, rows first, or one per line
+//     dense | nodense          size each column to its items, or all alike
+//
+//     [column]
+//         ui = auto
+//         tag = always,row
+//
+// `--column[=<words>]` goes on from there, turning columns on unless its
+// words say otherwise, and `--no-column` is `never`. Widths count what a terminal
+// shows: colors take no room and East Asian wide characters take two.
+use std::env;
+
+use crate::core::config::Config;
+use crate::errors::error::Error;
+
+// When $COLUMNS does not say how wide the terminal is
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_PADDING: usize = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum When {
+    Always,
+    Never,
+    Auto,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fill {
+    Column,
+    Row,
+    Plain,
+}
+
+#[derive(Debug, Clone)]
+pub struct Columns {
+    when: When,
+    fill: Fill,
+    dense: bool,
+    /// Spaces between columns
+    pub padding: usize,
+    /// Put in front of every line
+    pub indent: String,
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Columns {
+            when: When::Never,
+            fill: Fill::Column,
+            dense: false,
+            padding: DEFAULT_PADDING,
+            indent: String::new(),
+        }
+    }
+}
+
+impl Columns {
+    /// The layout for `command`: `column.ui`, then `column.<command>`,
+    /// then the `--column` words in `option`
+    pub fn load(config: &Config, command: &str, option: Option<&str>) -> Result<Self, Error> {
+        let mut columns = Columns::default();
+        for key in ["column.ui".to_string(), format!("column.{}", command)] {
+            if let Some(value) = config.get(&key) {
+                columns.parse(&value).map_err(|e| Error::Generic(format!("invalid value for '{}': {}", key, e)))?;
+            }
+        }
+        if let Some(option) = option {
+            columns.when = When::Always;
+            columns.parse(option)?;
+        }
+        Ok(columns)
+    }
+
+    /// Apply the words of `spec` over the layout so far
+    pub fn parse(&mut self, spec: &str) -> Result<(), Error> {
+        for word in spec.split([',', ' ']).filter(|word| !word.is_empty()) {
+            match word {
+                "always" => self.when = When::Always,
+                "never" => self.when = When::Never,
+                "auto" => self.when = When::Auto,
+                "column" => self.fill = Fill::Column,
+                "row" => self.fill = Fill::Row,
+                "plain" => self.fill = Fill::Plain,
+                "dense" => self.dense = true,
+                "nodense" => self.dense = false,
+                _ => return Err(Error::Generic(format!("unsupported column option '{}'", word))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether listings are laid out in columns, `auto` asking whether
+    /// they go to a terminal
+    pub fn is_active(&self) -> bool {
+        match self.when {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => atty::is(atty::Stream::Stdout),
+        }
+    }
+
+    /// Print `items` in columns as wide as the terminal, or one per line
+    /// when columns are not in use
+    pub fn print(&self, items: &[String]) {
+        if !self.is_active() {
+            for item in items {
+                println!("{}", item);
+            }
+            return;
+        }
+        for line in self.layout(items, terminal_width()) {
+            println!("{}", line);
+        }
+    }
+
+    /// The lines `items` take up laid out in `width` columns of text
+    pub fn layout(&self, items: &[String], width: usize) -> Vec<String> {
+        if items.is_empty() {
+            return Vec::new();
+        }
+        let widths: Vec<usize> = items.iter().map(|item| display_width(item)).collect();
+        let available = width.saturating_sub(display_width(&self.indent));
+        let rows = match self.fill {
+            Fill::Plain => items.len(),
+            _ => self.rows(&widths, available),
+        };
+        let cols = items.len().div_ceil(rows);
+        let column_widths = self.column_widths(&widths, rows, cols);
+
+        let mut lines = Vec::new();
+        for row in 0..rows {
+            let mut line = self.indent.clone();
+            let cells: Vec<usize> = (0..cols)
+                .map(|col| self.index(row, col, rows, cols))
+                .filter(|&i| i < items.len())
+                .collect();
+            for (position, &i) in cells.iter().enumerate() {
+                line.push_str(&items[i]);
+                if position + 1 < cells.len() {
+                    line.push_str(&" ".repeat(column_widths[position] - widths[i] + self.padding));
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    // How many rows the items need: as many columns of the widest item
+    // as fit, or with `dense` the fewest rows whose columns, each as wide
+    // as its own widest item, still fit
+    fn rows(&self, widths: &[usize], available: usize) -> usize {
+        let cell = widths.iter().max().copied().unwrap_or(0) + self.padding;
+        let cols = ((available + self.padding) / cell).max(1);
+        let mut rows = widths.len().div_ceil(cols);
+        if self.dense {
+            while rows > 1 {
+                let cols = widths.len().div_ceil(rows - 1);
+                let total: usize = self.column_widths(widths, rows - 1, cols).iter().sum::<usize>()
+                    + self.padding * (cols - 1);
+                if total > available {
+                    break;
+                }
+                rows -= 1;
+            }
+        }
+        rows
+    }
+
+    // The width of each column: its widest item, or the widest of all
+    // unless `dense`
+    fn column_widths(&self, widths: &[usize], rows: usize, cols: usize) -> Vec<usize> {
+        if !self.dense {
+            return vec![widths.iter().max().copied().unwrap_or(0); cols];
+        }
+        (0..cols)
+            .map(|col| {
+                (0..rows)
+                    .map(|row| self.index(row, col, rows, cols))
+                    .filter_map(|i| widths.get(i).copied())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    // The item in a cell, filling down each column or along each row
+    fn index(&self, row: usize, col: usize, rows: usize, cols: usize) -> usize {
+        match self.fill {
+            Fill::Row => row * cols + col,
+            _ => col * rows + row,
+        }
+    }
+}
+
+/// How wide the terminal is: $COLUMNS, else 80 columns
+pub fn terminal_width() -> usize {
+    env::var("COLUMNS").ok()
+        .and_then(|columns| columns.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// How many columns `text` takes on a terminal: color escapes take none,
+/// combining marks none and East Asian wide characters two
+pub fn display_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // An escape sequence runs to its final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            continue;
+        }
+        width += char_width(c);
+    }
+    width
+}
+
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
@@ -0,0 +1,75 @@
+// src/core/comments.rs - review comments anchored to a line of a file in a commit
+use crate::core::database::author::Author;
+use crate::errors::error::Error;
+
+/// Notes ref that review comments are kept under
+pub const COMMENTS_REF: &str = "refs/notes/comments";
+
+/// A comment on `line` of `path`. The commit it belongs to is the note it
+/// is stored in; one note holds every comment on that commit, with each
+/// body line indented by a tab (spaces below):
+///
+/// ```text
+/// comment src/main.rs:42
+/// author A U Thor <author@example.com> 1700000000 +0000
+///     First line of the comment
+///     Second line
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReviewComment {
+    pub path: String,
+    pub line: usize,
+    pub author: Author,
+    pub body: String,
+}
+
+impl ReviewComment {
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, Error> {
+        let mut comments: Vec<ReviewComment> = Vec::new();
+
+        for line in text.lines() {
+            if let Some(anchor) = line.strip_prefix("comment ") {
+                let (path, number) = anchor.rsplit_once(':')
+                    .ok_or_else(|| Error::Generic(format!("malformed comment anchor '{}'", anchor)))?;
+                let number = number.parse()
+                    .map_err(|_| Error::Generic(format!("malformed comment anchor '{}'", anchor)))?;
+                comments.push(ReviewComment {
+                    path: path.to_string(),
+                    line: number,
+                    author: Author::new(String::new(), String::new()),
+                    body: String::new(),
+                });
+                continue;
+            }
+
+            let comment = match comments.last_mut() {
+                Some(comment) => comment,
+                None if line.is_empty() => continue,
+                None => return Err(Error::Generic("comment note does not start with an anchor".to_string())),
+            };
+
+            if let Some(author) = line.strip_prefix("author ") {
+                comment.author = Author::parse(author).map_err(Error::Generic)?;
+            } else if let Some(body) = line.strip_prefix('\t') {
+                if !comment.body.is_empty() {
+                    comment.body.push('\n');
+                }
+                comment.body.push_str(body);
+            }
+        }
+
+        Ok(comments)
+    }
+
+    pub fn format_all(comments: &[Self]) -> String {
+        let mut text = String::new();
+        for comment in comments {
+            text.push_str(&format!("comment {}:{}\n", comment.path, comment.line));
+            text.push_str(&format!("author {}\n", comment.author));
+            for line in comment.body.lines() {
+                text.push_str(&format!("\t{}\n", line));
+            }
+        }
+        text
+    }
+}
@@ -0,0 +1,337 @@
+// src/core/config.rs - reading repository settings from .ash/config
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::lockfile::Lockfile;
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+/// Settings stored in git-style INI format:
+///
+/// ```text
+/// [merge]
+///     autostash = true
+/// [remote "origin"]
+///     url = ../upstream
+/// ```
+///
+/// Keys are addressed as `section.name` or `section.subsection.name`;
+/// section and variable names are case-insensitive, subsections are not.
+#[derive(Debug, Clone)]
+pub struct Config {
+    path: PathBuf,
+    values: HashMap<String, Vec<String>>,
+    // The file as read, so edits keep comments and layout
+    lines: Vec<String>,
+}
+
+impl Config {
+    pub fn load(git_path: &Path) -> Result<Self, Error> {
+        // Every worktree of a repository shares its configuration
+        let path = git_dir::common(git_path).join("config");
+        let mut config = Config {
+            path,
+            values: HashMap::new(),
+            lines: Vec::new(),
+        };
+
+        if config.path.exists() {
+            let content = fs::read_to_string(&config.path)?;
+            config.parse(&content)?;
+            config.lines = content.lines().map(str::to_string).collect();
+        }
+
+        Ok(config)
+    }
+
+    /// Last value set for `key`
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.values.get(&normalize_key(key)).and_then(|v| v.last().cloned())
+    }
+
+    /// Every value set for a multi-valued `key`, in file order
+    pub fn get_all(&self, key: &str) -> Vec<String> {
+        self.values.get(&normalize_key(key)).cloned().unwrap_or_default()
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|value| parse_bool(&value))
+    }
+
+    pub fn get_size(&self, key: &str) -> Option<usize> {
+        self.get(key).and_then(|value| parse_size(&value))
+    }
+
+    /// Variables set directly in `section`, outside its subsections, with
+    /// their last values, sorted by name
+    pub fn variables(&self, section: &str) -> Vec<(String, String)> {
+        let prefix = format!("{}.", section.to_lowercase());
+        let mut variables: Vec<(String, String)> = self.values.iter()
+            .filter_map(|(key, values)| {
+                let name = key.strip_prefix(&prefix).filter(|name| !name.contains('.'))?;
+                Some((name.to_string(), values.last()?.clone()))
+            })
+            .collect();
+        variables.sort();
+        variables
+    }
+
+    /// Every variable with all of its values, sorted by key
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<(String, String)> = self.values.iter()
+            .flat_map(|(key, values)| values.iter().map(move |value| (key.clone(), value.clone())))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries
+    }
+
+    /// Names of the subsections of `section` in file order, e.g. the
+    /// configured remotes for `remote`
+    pub fn subsections(&self, section: &str) -> Vec<String> {
+        let prefix = format!("{}.", section.to_lowercase());
+        let mut names: Vec<String> = Vec::new();
+        for (_, header) in self.headers() {
+            if let Some(name) = header.strip_prefix(&prefix) {
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names
+    }
+
+    /// Add a value for `key`, creating its section at the end of the file
+    /// if it does not exist yet
+    pub fn add(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let (section, name) = split_key(key)?;
+        let line = format!("\t{} = {}", name, quote(value));
+
+        match self.section_end(&section) {
+            Some(end) => self.lines.insert(end, line),
+            None => {
+                self.lines.push(format_section(&section));
+                self.lines.push(line);
+            }
+        }
+        self.save()
+    }
+
+    /// Remove every value of `key`
+    pub fn unset_all(&mut self, key: &str) -> Result<(), Error> {
+        let (section, name) = split_key(key)?;
+        let mut current: Option<String> = None;
+        self.lines.retain(|line| {
+            let trimmed = line.trim();
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                current = parse_section(header);
+                return true;
+            }
+            let variable = trimmed.split('=').next().unwrap_or_default().trim().to_lowercase();
+            !(current.as_deref() == Some(section.as_str()) && variable == name)
+        });
+        self.save()
+    }
+
+    /// Drop a section such as `remote.origin` with all its values; false
+    /// if there was no such section
+    pub fn remove_section(&mut self, section: &str) -> Result<bool, Error> {
+        let section = normalize_section(section);
+        let mut found = false;
+        let mut inside = false;
+        self.lines.retain(|line| {
+            let trimmed = line.trim();
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                inside = parse_section(header).as_deref() == Some(section.as_str());
+                found |= inside;
+            }
+            !inside
+        });
+        if found {
+            self.save()?;
+        }
+        Ok(found)
+    }
+
+    /// Rename a section, keeping its values; false if there was no such section
+    pub fn rename_section(&mut self, old: &str, new: &str) -> Result<bool, Error> {
+        let (old, new) = (normalize_section(old), normalize_section(new));
+        let positions: Vec<usize> = self.headers().into_iter()
+            .filter(|(_, header)| *header == old)
+            .map(|(i, _)| i)
+            .collect();
+        for &i in &positions {
+            self.lines[i] = format_section(&new);
+        }
+        if !positions.is_empty() {
+            self.save()?;
+        }
+        Ok(!positions.is_empty())
+    }
+
+    // Line index and normalised name of every section header
+    fn headers(&self) -> Vec<(usize, String)> {
+        self.lines.iter().enumerate()
+            .filter_map(|(i, line)| {
+                let header = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+                parse_section(header).map(|section| (i, section))
+            })
+            .collect()
+    }
+
+    // Index just past the last value of the last `section` block
+    fn section_end(&self, section: &str) -> Option<usize> {
+        let headers = self.headers();
+        let (position, start) = headers.iter().enumerate()
+            .filter(|(_, (_, header))| header == section)
+            .map(|(position, (i, _))| (position, *i))
+            .next_back()?;
+        let mut end = headers.get(position + 1).map_or(self.lines.len(), |(i, _)| *i);
+        // Keep blank lines and comments before the next section with it
+        while end > start + 1 && self.lines[end - 1].trim().is_empty() {
+            end -= 1;
+        }
+        Some(end)
+    }
+
+    fn save(&mut self) -> Result<(), Error> {
+        let mut content = self.lines.join("\n");
+        if !content.is_empty() {
+            content.push('\n');
+        }
+
+        let mut lockfile = Lockfile::new(&self.path);
+        if !lockfile.hold_for_update()? {
+            return Err(Error::Generic(format!("could not lock config file {}", self.path.display())));
+        }
+        lockfile.write(&content)?;
+        lockfile.commit_ref()?;
+
+        self.values.clear();
+        self.parse(&content)
+    }
+
+    fn parse(&mut self, content: &str) -> Result<(), Error> {
+        let mut section: Option<String> = None;
+
+        for (number, raw_line) in content.lines().enumerate() {
+            let line = raw_line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                let header = line.strip_prefix('[')
+                    .and_then(|l| l.strip_suffix(']'))
+                    .ok_or_else(|| self.bad_line(number))?;
+                section = Some(parse_section(header).ok_or_else(|| self.bad_line(number))?);
+                continue;
+            }
+
+            let section = section.as_ref().ok_or_else(|| self.bad_line(number))?;
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), unquote(value.trim())),
+                // A bare variable name means "true"
+                None => (line, "true".to_string()),
+            };
+
+            let key = format!("{}.{}", section, name.to_lowercase());
+            self.values.entry(key).or_default().push(value);
+        }
+
+        Ok(())
+    }
+
+    fn bad_line(&self, number: usize) -> Error {
+        Error::Generic(format!("bad config line {} in file {}", number + 1, self.path.display()))
+    }
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" | "" => Some(false),
+        _ => None,
+    }
+}
+
+/// A number of bytes, with an optional `k`, `m` or `g` suffix for units
+/// of 1024
+pub fn parse_size(value: &str) -> Option<usize> {
+    let value = value.trim().to_lowercase();
+    let (number, unit) = match value.chars().last()? {
+        'k' => (&value[..value.len() - 1], 1 << 10),
+        'm' => (&value[..value.len() - 1], 1 << 20),
+        'g' => (&value[..value.len() - 1], 1 << 30),
+        _ => (value.as_str(), 1),
+    };
+    number.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+// `section` or `section "subsection"` -> "section" or "section.subsection"
+fn parse_section(header: &str) -> Option<String> {
+    let header = header.trim();
+    match header.split_once(' ') {
+        Some((name, sub)) => {
+            let sub = sub.trim().strip_prefix('"')?.strip_suffix('"')?;
+            Some(format!("{}.{}", name.to_lowercase(), sub))
+        },
+        None => Some(header.to_lowercase()),
+    }
+}
+
+// Section and variable names are case-insensitive, the subsection is kept as is
+fn normalize_key(key: &str) -> String {
+    let first = key.find('.');
+    let last = key.rfind('.');
+    match (first, last) {
+        (Some(first), Some(last)) => format!(
+            "{}{}{}",
+            key[..first].to_lowercase(),
+            &key[first..last],
+            key[last..].to_lowercase()
+        ),
+        _ => key.to_lowercase(),
+    }
+}
+
+// `section.subsection.name` -> ("section.subsection", "name")
+fn split_key(key: &str) -> Result<(String, String), Error> {
+    match normalize_key(key).rsplit_once('.') {
+        Some((section, name)) if !section.is_empty() && !name.is_empty() => {
+            Ok((section.to_string(), name.to_string()))
+        },
+        _ => Err(Error::Generic(format!("key does not contain a section: {}", key))),
+    }
+}
+
+fn normalize_section(section: &str) -> String {
+    match section.split_once('.') {
+        Some((name, sub)) => format!("{}.{}", name.to_lowercase(), sub),
+        None => section.to_lowercase(),
+    }
+}
+
+// "section.subsection" -> `[section "subsection"]`
+fn format_section(section: &str) -> String {
+    match section.split_once('.') {
+        Some((name, sub)) => format!("[{} \"{}\"]", name, sub),
+        None => format!("[{}]", section),
+    }
+}
+
+// Values are trimmed when read, so keep surrounding spaces in quotes
+fn quote(value: &str) -> String {
+    if value.trim() != value {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
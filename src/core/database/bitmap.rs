@@ -0,0 +1,132 @@
+// src/core/database/bitmap.rs - write the .bitmap that goes with a pack
+//
+// For a pack holding everything reachable, a bitmap says which of its
+// objects a commit reaches, one bit per object in the order the pack
+// stores them, so that fetches and counts need not walk history. The file
+// is git's version 1 format: a header naming the pack by its checksum,
+// one bitmap per object type, then a bitmap for each selected commit, all
+// EWAH-compressed, and a SHA-1 of it all.
+use std::collections::HashMap;
+
+use sha1::{Digest, Sha1};
+
+use crate::core::database::database::Database;
+use crate::core::database::pack::Pack;
+use crate::core::database::pack_writer::write_file;
+use crate::errors::error::Error;
+
+const SIGNATURE: &[u8] = b"BITM";
+const VERSION: u16 = 1;
+// Every object reachable from a bitmapped commit is in the pack
+const OPTION_FULL_DAG: u16 = 1;
+
+const TYPES: [&str; 4] = ["commit", "tree", "blob", "tag"];
+
+// An EWAH word that says how many clean words follow, all 0s or all 1s,
+// then how many literal words come after them
+const RUN_BITS: u32 = 32;
+const MAX_RUN: u64 = (1 << RUN_BITS) - 1;
+const MAX_LITERALS: u64 = (1 << 31) - 1;
+
+/// Write `pack`'s bitmap, with an entry for each of `commits`, and
+/// return how many commits it covers. Fails when something a commit
+/// reaches is not in the pack.
+pub fn write(database: &Database, pack: &Pack, commits: &[String]) -> Result<usize, Error> {
+    // Bit positions follow the pack, entries name commits by their place
+    // in the sorted index
+    let mut order: Vec<(u64, &String)> = pack.oids().iter()
+        .filter_map(|oid| pack.offset(oid).map(|offset| (offset, oid)))
+        .collect();
+    order.sort();
+    let positions: HashMap<&str, usize> = order.iter().enumerate().map(|(i, (_, oid))| (oid.as_str(), i)).collect();
+
+    let mut types = vec![Vec::new(); TYPES.len()];
+    for (position, (_, oid)) in order.iter().enumerate() {
+        let (obj_type, _) = database.load_raw(oid)?;
+        if let Some(kind) = TYPES.iter().position(|name| *name == obj_type) {
+            types[kind].push(position);
+        }
+    }
+
+    let mut commits: Vec<&String> = commits.iter().collect();
+    commits.sort();
+    commits.dedup();
+
+    let mut entries = Vec::new();
+    for commit in &commits {
+        let mut bits = Vec::new();
+        for oid in database.connected_objects(&[commit.to_string()]) {
+            match positions.get(oid.as_str()) {
+                Some(&position) => bits.push(position),
+                None => return Err(Error::Generic(format!(
+                    "cannot write bitmap: {} reaches {}, which is not in {}", commit, oid, pack.path().display()
+                ))),
+            }
+        }
+        let index_position = pack.oids().binary_search(commit)
+            .map_err(|_| Error::Generic(format!("cannot write bitmap: {} is not in the pack", commit)))?;
+        entries.push((index_position as u32, bits));
+    }
+
+    let mut bitmap = Vec::new();
+    bitmap.extend_from_slice(SIGNATURE);
+    bitmap.extend_from_slice(&VERSION.to_be_bytes());
+    bitmap.extend_from_slice(&OPTION_FULL_DAG.to_be_bytes());
+    bitmap.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    bitmap.extend(pack.checksum()?);
+    for positions in &types {
+        bitmap.extend(ewah(positions, order.len()));
+    }
+    for (index_position, bits) in &entries {
+        bitmap.extend_from_slice(&index_position.to_be_bytes());
+        // Not stored as an xor against an earlier entry, no flags
+        bitmap.extend_from_slice(&[0, 0]);
+        bitmap.extend(ewah(bits, order.len()));
+    }
+    let checksum = Sha1::digest(&bitmap);
+    bitmap.extend_from_slice(&checksum);
+
+    write_file(&pack.path().with_extension("bitmap"), &bitmap)?;
+    Ok(entries.len())
+}
+
+// The bits at `positions` out of `size`, compressed: the size in bits,
+// the number of 64-bit words, the words, and where the last marker word is
+fn ewah(positions: &[usize], size: usize) -> Vec<u8> {
+    let mut words = vec![0u64; size.div_ceil(64)];
+    for &position in positions {
+        words[position / 64] |= 1 << (position % 64);
+    }
+
+    let mut buffer = vec![0u64];
+    let mut marker = 0;
+    for word in words {
+        let clean = word == 0 || word == u64::MAX;
+        let run_bit = buffer[marker] & 1;
+        let run = (buffer[marker] >> 1) & MAX_RUN;
+        let literals = buffer[marker] >> (1 + RUN_BITS);
+        if clean && literals == 0 && (run == 0 || run_bit == word & 1) && run < MAX_RUN {
+            buffer[marker] = (word & 1) | ((run + 1) << 1);
+        } else if clean {
+            buffer.push((word & 1) | (1 << 1));
+            marker = buffer.len() - 1;
+        } else {
+            if literals == MAX_LITERALS {
+                buffer.push(0);
+                marker = buffer.len() - 1;
+            }
+            let literals = buffer[marker] >> (1 + RUN_BITS);
+            buffer[marker] = (buffer[marker] & ((1 << (1 + RUN_BITS)) - 1)) | ((literals + 1) << (1 + RUN_BITS));
+            buffer.push(word);
+        }
+    }
+
+    let mut out = Vec::with_capacity(12 + buffer.len() * 8);
+    out.extend_from_slice(&(size as u32).to_be_bytes());
+    out.extend_from_slice(&(buffer.len() as u32).to_be_bytes());
+    for word in &buffer {
+        out.extend_from_slice(&word.to_be_bytes());
+    }
+    out.extend_from_slice(&(marker as u32).to_be_bytes());
+    out
+}
@@ -5,6 +5,9 @@ use std::any::Any;
 use std::str;
 use std::collections::HashMap;
 
+/// Message trailer recording an additional parent of a merge commit
+pub const MERGE_PARENT_TRAILER: &str = "Merge-Parent:";
+
 #[derive(Debug, Clone)]
 pub struct Commit {
     pub oid: Option<String>,
@@ -106,6 +109,26 @@ impl Commit {
         self.parent.as_ref()
     }
     
+    /// All parents of the commit. Commits store a single parent header, so
+    /// further parents of a merge are recorded as `Merge-Parent:` lines in
+    /// the message.
+    pub fn get_parents(&self) -> Vec<String> {
+        let mut parents: Vec<String> = self.parent.iter().cloned().collect();
+        for line in self.message.lines() {
+            if let Some(oid) = line.strip_prefix(MERGE_PARENT_TRAILER) {
+                let oid = oid.trim();
+                if !oid.is_empty() && !parents.iter().any(|p| p == oid) {
+                    parents.push(oid.to_string());
+                }
+            }
+        }
+        parents
+    }
+
+    pub fn is_merge(&self) -> bool {
+        self.get_parents().len() > 1
+    }
+    
     pub fn get_author(&self) -> Option<&Author> {
         Some(&self.author)
     }
@@ -170,21 +193,19 @@ impl Commit {
         
         let mut lines = content.lines();
         let mut headers = HashMap::new();
-        let mut message = String::new();
+        let mut message_lines: Vec<&str> = Vec::new();
         let mut reading_message = false;
         
         // Parsează headerele până la linia goală
         while let Some(line) = lines.next() {
-            if line.is_empty() {
-                reading_message = true;
+            // Blank lines within the message, between paragraphs, are kept
+            if reading_message {
+                message_lines.push(line);
                 continue;
             }
-            
-            if reading_message {
-                if !message.is_empty() {
-                    message.push('\n');
-                }
-                message.push_str(line);
+
+            if line.is_empty() {
+                reading_message = true;
                 continue;
             }
             
@@ -227,7 +248,7 @@ impl Commit {
             tree,
             author,
             committer,
-            message,
+            message: message_lines.join("\n"),
         })
     }
 }
\ No newline at end of file
@@ -0,0 +1,191 @@
+// src/core/database/commit_graph.rs - write objects/info/commit-graph
+//
+// The file git reads to walk history without opening commits: a header,
+// a table of chunks, then the chunks themselves and a SHA-1 of it all.
+//
+//     OIDF  256 counts, how many ids start with each byte or a smaller one
+//     OIDL  the commit ids, sorted
+//     CDAT  per commit: its tree, the positions of its first two parents,
+//           its generation number and commit time
+//     EDGE  the rest of the parents of octopus merges
+use std::collections::HashMap;
+use std::fs;
+
+use sha1::{Digest, Sha1};
+
+use crate::core::database::database::Database;
+use crate::core::database::pack_writer::write_file;
+use crate::errors::error::Error;
+
+pub const COMMIT_GRAPH_FILE: &str = "info/commit-graph";
+
+const SIGNATURE: &[u8] = b"CGPH";
+const VERSION: u8 = 1;
+const HASH_VERSION: u8 = 1;
+const CHUNK_LOOKUP_WIDTH: usize = 12;
+const CDAT_WIDTH: usize = 36;
+
+const PARENT_NONE: u32 = 0x7000_0000;
+const EXTRA_EDGES: u32 = 0x8000_0000;
+const LAST_EDGE: u32 = 0x8000_0000;
+const GENERATION_MAX: u32 = 0x3FFF_FFFF;
+
+struct GraphCommit {
+    tree: String,
+    parents: Vec<String>,
+    time: u64,
+}
+
+/// Write the commit-graph of every commit reachable from `tips`, which
+/// may also name tags and other objects, and return how many commits it
+/// holds. Parents are taken from the commits' own headers.
+pub fn write(database: &Database, tips: &[String]) -> Result<usize, Error> {
+    let commits = walk(database, tips)?;
+    let mut oids: Vec<&String> = commits.keys().collect();
+    oids.sort();
+    let positions: HashMap<&str, u32> = oids.iter().enumerate().map(|(i, oid)| (oid.as_str(), i as u32)).collect();
+    let generations = generations(&commits);
+
+    let mut counts = [0u32; 256];
+    for oid in &oids {
+        counts[first_byte(oid) as usize] += 1;
+    }
+    let mut fanout = Vec::with_capacity(256 * 4);
+    let mut total = 0;
+    for count in counts {
+        total += count;
+        fanout.extend_from_slice(&total.to_be_bytes());
+    }
+
+    let mut lookup = Vec::with_capacity(oids.len() * 20);
+    let mut data = Vec::with_capacity(oids.len() * CDAT_WIDTH);
+    let mut edges: Vec<u8> = Vec::new();
+    for oid in &oids {
+        let commit = &commits[oid.as_str()];
+        lookup.extend(hex::decode(oid).map_err(|e| Error::Generic(e.to_string()))?);
+        data.extend(hex::decode(&commit.tree).map_err(|e| Error::Generic(e.to_string()))?);
+
+        let position = |parent: &String| positions[parent.as_str()];
+        let first = commit.parents.first().map(position).unwrap_or(PARENT_NONE);
+        let second = match commit.parents.len() {
+            0 | 1 => PARENT_NONE,
+            2 => position(&commit.parents[1]),
+            _ => {
+                let start = (edges.len() / 4) as u32;
+                let rest = &commit.parents[1..];
+                for (i, parent) in rest.iter().enumerate() {
+                    let last = if i + 1 == rest.len() { LAST_EDGE } else { 0 };
+                    edges.extend_from_slice(&(position(parent) | last).to_be_bytes());
+                }
+                EXTRA_EDGES | start
+            },
+        };
+        data.extend_from_slice(&first.to_be_bytes());
+        data.extend_from_slice(&second.to_be_bytes());
+
+        let generation = generations[oid.as_str()].min(GENERATION_MAX);
+        data.extend_from_slice(&((generation << 2) | ((commit.time >> 32) as u32 & 0x3)).to_be_bytes());
+        data.extend_from_slice(&(commit.time as u32).to_be_bytes());
+    }
+
+    let mut chunks: Vec<(&[u8], Vec<u8>)> = vec![(b"OIDF", fanout), (b"OIDL", lookup), (b"CDAT", data)];
+    if !edges.is_empty() {
+        chunks.push((b"EDGE", edges));
+    }
+
+    let mut graph = Vec::new();
+    graph.extend_from_slice(SIGNATURE);
+    graph.extend_from_slice(&[VERSION, HASH_VERSION, chunks.len() as u8, 0]);
+    // Each chunk's id and where it starts, then a terminating entry
+    let mut offset = (graph.len() + (chunks.len() + 1) * CHUNK_LOOKUP_WIDTH) as u64;
+    for (id, chunk) in &chunks {
+        graph.extend_from_slice(id);
+        graph.extend_from_slice(&offset.to_be_bytes());
+        offset += chunk.len() as u64;
+    }
+    graph.extend_from_slice(&[0; 4]);
+    graph.extend_from_slice(&offset.to_be_bytes());
+    for (_, chunk) in &chunks {
+        graph.extend_from_slice(chunk);
+    }
+    let checksum = Sha1::digest(&graph);
+    graph.extend_from_slice(&checksum);
+
+    let path = database.pathname.join(COMMIT_GRAPH_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_file(&path, &graph)?;
+    Ok(oids.len())
+}
+
+// The commits reachable from `tips`, tags followed to what they name
+fn walk(database: &Database, tips: &[String]) -> Result<HashMap<String, GraphCommit>, Error> {
+    let mut commits = HashMap::new();
+    let mut stack: Vec<String> = tips.to_vec();
+    while let Some(oid) = stack.pop() {
+        if commits.contains_key(&oid) || !database.exists(&oid) {
+            continue;
+        }
+        let (obj_type, data) = database.load_raw(&oid)?;
+        let text = String::from_utf8_lossy(&data);
+        let headers = text.lines().take_while(|line| !line.is_empty()).filter_map(|line| line.split_once(' '));
+        match obj_type.as_str() {
+            "tag" => stack.extend(headers.filter(|(key, _)| *key == "object").map(|(_, value)| value.to_string())),
+            "commit" => {
+                let mut commit = GraphCommit { tree: String::new(), parents: Vec::new(), time: 0 };
+                for (key, value) in headers {
+                    match key {
+                        "tree" => commit.tree = value.to_string(),
+                        "parent" => commit.parents.push(value.to_string()),
+                        // `<name> <email> <seconds> <zone>`
+                        "committer" => {
+                            commit.time = value.rsplit(' ').nth(1).and_then(|time| time.parse().ok()).unwrap_or(0)
+                        },
+                        _ => {},
+                    }
+                }
+                if let Some(parent) = commit.parents.iter().find(|parent| !database.exists(parent)) {
+                    return Err(Error::Generic(format!(
+                        "cannot write commit-graph: parent {} of {} is missing", parent, oid
+                    )));
+                }
+                stack.extend(commit.parents.iter().cloned());
+                commits.insert(oid, commit);
+            },
+            _ => {},
+        }
+    }
+    Ok(commits)
+}
+
+// One more than the highest generation among the parents, 1 for roots
+fn generations(commits: &HashMap<String, GraphCommit>) -> HashMap<&str, u32> {
+    let mut generations: HashMap<&str, u32> = HashMap::new();
+    for oid in commits.keys() {
+        let mut stack = vec![oid.as_str()];
+        while let Some(&oid) = stack.last() {
+            if generations.contains_key(oid) {
+                stack.pop();
+                continue;
+            }
+            let parents = &commits[oid].parents;
+            let pending: Vec<&str> = parents.iter()
+                .map(String::as_str)
+                .filter(|parent| !generations.contains_key(parent))
+                .collect();
+            if pending.is_empty() {
+                let generation = parents.iter().map(|parent| generations[parent.as_str()]).max().unwrap_or(0) + 1;
+                generations.insert(oid, generation);
+                stack.pop();
+            } else {
+                stack.extend(pending);
+            }
+        }
+    }
+    generations
+}
+
+fn first_byte(oid: &str) -> u8 {
+    u8::from_str_radix(&oid[..2], 16).unwrap_or(0)
+}
@@ -1,27 +1,36 @@
 // src/core/database/database.rs
-use std::fs::{self, File};
-use std::io::Write;
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::io::Read;
-use std::collections::HashMap;
+use std::process;
+use std::collections::{HashMap, HashSet, VecDeque};
 use sha1::{Digest, Sha1};
 use flate2::write::ZlibEncoder;
 use flate2::read::ZlibDecoder;
 use flate2::Compression;
 use crate::core::path_filter::PathFilter;
+use crate::core::remote::transport;
 use crate::errors::error::Error;
 use crate::core::database::blob::Blob;
-use crate::core::database::tree::Tree;
+use crate::core::database::tree::{Tree, TreeEntry};
 use crate::core::database::commit::Commit;
+use crate::core::database::tag::Tag;
 use std::any::Any;
 
 use super::entry::DatabaseEntry;
+use super::pack::{self, Pack, RawObject};
+use super::pack_writer::{PackSummary, PackWriter};
 use super::tree_diff::TreeDiff;
+use crate::trace;
 
 pub struct Database {
     pub pathname: PathBuf,
     temp_chars: Vec<char>,
     objects: HashMap<String, Box<dyn GitObject>>,
+    packs: Vec<Pack>,
+    // URLs of remotes that may hold objects a partial fetch left out
+    promisors: Vec<String>,
 }
 
 impl Clone for Database {
@@ -30,10 +39,15 @@ impl Clone for Database {
             pathname: self.pathname.clone(),
             temp_chars: self.temp_chars.clone(),
             objects: HashMap::new(), // We don't clone the objects cache
+            packs: self.packs.clone(),
+            promisors: self.promisors.clone(),
         }
     }
 }
 
+/// An object ID and the path it was found at
+pub type PathedOid = (String, String);
+
 pub trait GitObject: Any {
     fn get_type(&self) -> &str;
     fn to_bytes(&self) -> Vec<u8>;
@@ -49,15 +63,87 @@ impl Database {
             .chain('0'..='9')
             .collect();
 
+        let packs = pack::load_packs(&pathname);
+
         Database {
             pathname,
             temp_chars,
             objects: HashMap::new(),
+            packs,
+            promisors: Vec::new(),
         }
     }
 
+    /// Remotes to ask for objects that are missing here
+    pub fn set_promisors(&mut self, urls: Vec<String>) {
+        self.promisors = urls;
+    }
+
     pub fn exists(&self, oid: &str) -> bool {
-        self.object_path(oid).exists()
+        self.object_path(oid).exists() || self.packs.iter().any(|pack| pack.contains(oid))
+    }
+
+    /// Pick up packs written since the database was opened
+    pub fn reload_packs(&mut self) {
+        self.packs = pack::load_packs(&self.pathname);
+    }
+
+    pub fn packs(&self) -> &[Pack] {
+        &self.packs
+    }
+
+    /// Every object stored as its own file
+    pub fn loose_oids(&self) -> Result<Vec<String>, Error> {
+        let mut oids = Vec::new();
+        for dir in fs::read_dir(&self.pathname)?.flatten() {
+            let prefix = dir.file_name().to_string_lossy().to_string();
+            if prefix.len() != 2 || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            for file in fs::read_dir(dir.path())?.flatten() {
+                let rest = file.file_name().to_string_lossy().to_string();
+                if rest.len() == 38 && rest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    oids.push(format!("{}{}", prefix, rest));
+                }
+            }
+        }
+        oids.sort();
+        Ok(oids)
+    }
+
+    /// Write `oids` into a new pack, which is read from right away
+    pub fn write_pack(&mut self, oids: &[String]) -> Result<PackSummary, Error> {
+        let mut writer = PackWriter::new();
+        for oid in oids {
+            writer.add(oid, self.load_raw(oid)?);
+        }
+        let summary = writer.write(&self.pathname.join("pack"))?;
+        self.reload_packs();
+        Ok(summary)
+    }
+
+    /// Follow annotated tags from `oid` to the object they finally name
+    pub fn peel(&mut self, oid: &str) -> Result<String, Error> {
+        let mut oid = oid.to_string();
+        loop {
+            let object = self.load(&oid)?;
+            match object.as_any().downcast_ref::<Tag>() {
+                Some(tag) => oid = tag.object.clone(),
+                None => return Ok(oid),
+            }
+        }
+    }
+
+    /// Delete the loose copy of an object, e.g. once it is packed
+    pub fn remove_loose(&self, oid: &str) -> Result<(), Error> {
+        let path = self.object_path(oid);
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        if let Some(dir) = path.parent() {
+            let _ = fs::remove_dir(dir);
+        }
+        Ok(())
     }
 
     /// Încarcă un obiect din baza de date folosind OID-ul său
@@ -84,21 +170,21 @@ impl Database {
 
     /// Stochează un obiect git în baza de date
     pub fn store(&mut self, object: &mut impl GitObject) -> Result<String, Error> {
-        println!("Storing object of type: {}", object.get_type());
+        trace!("Storing object of type: {}", object.get_type());
         
         // Serialize object
         let content = self.serialize_object(object)?;
         
         // Calculate OID (hash)
         let oid = self.hash_content(&content);
-        println!("Calculated OID: {}", oid);
+        trace!("Calculated OID: {}", oid);
         
         // Write only if object doesn't already exist
         if !self.exists(&oid) {
-            println!("Object {} doesn't exist, writing to database", oid);
+            trace!("Object {} doesn't exist, writing to database", oid);
             self.write_object(&oid, &content)?;
         } else {
-            println!("Object {} already exists in database", oid);
+            trace!("Object {} already exists in database", oid);
         }
     
         // Set OID on object
@@ -117,10 +203,192 @@ impl Database {
         Ok(oid)
     }
 
+    /// Store an object from its type and raw content, e.g. one received from a remote
+    pub fn store_raw(&mut self, obj_type: &str, content: &[u8]) -> Result<String, Error> {
+        let mut full_content = format!("{} {}\0", obj_type, content.len()).into_bytes();
+        full_content.extend_from_slice(content);
+
+        let oid = self.hash_content(&full_content);
+        self.write_object(&oid, &full_content)?;
+        Ok(oid)
+    }
+
+    /// Every object reachable from the `tips` commits that is not reachable
+    /// from the `exclude` commits, assuming whoever holds `exclude` also holds
+    /// their trees and blobs. Commits in `exclude` missing here are ignored.
+    pub fn reachable_objects(&mut self, tips: &[String], exclude: &[String]) -> Result<Vec<String>, Error> {
+        let (objects, _) = self.reachable_objects_with_paths(tips, exclude)?;
+        Ok(objects.into_iter().map(|(oid, _)| oid).collect())
+    }
+
+    /// Like `reachable_objects`, with the path each tree or blob was first
+    /// seen at (empty for commits and root trees). Also returns the trees
+    /// and blobs of the boundary commits, which the other side has, by path;
+    /// these make good delta bases for new versions of the same files.
+    pub fn reachable_objects_with_paths(&mut self, tips: &[String], exclude: &[String]) -> Result<(Vec<PathedOid>, Vec<PathedOid>), Error> {
+        // Annotated tags travel with the commits they name
+        let mut tags = Vec::new();
+        let mut tip_commits = Vec::with_capacity(tips.len());
+        for tip in tips {
+            let mut oid = tip.clone();
+            while let Some(tag) = self.load(&oid)?.as_any().downcast_ref::<Tag>() {
+                if !exclude.contains(&oid) && !tags.contains(&oid) {
+                    tags.push(oid.clone());
+                }
+                oid = tag.object.clone();
+            }
+            tip_commits.push(oid);
+        }
+
+        // Commits the other side already has
+        let mut uninteresting = HashSet::new();
+        let mut queue = VecDeque::new();
+        for oid in exclude {
+            if self.exists(oid) {
+                queue.push_back(self.peel(oid)?);
+            }
+        }
+        while let Some(oid) = queue.pop_front() {
+            if uninteresting.insert(oid.clone()) {
+                queue.extend(self.load_commit(&oid)?.get_parents());
+            }
+        }
+
+        // Commits it is missing, and the trees at the boundary between the two
+        let mut commits = Vec::new();
+        let mut seen = HashSet::new();
+        let mut boundary = HashSet::new();
+        let mut queue: VecDeque<String> = tip_commits.into_iter().collect();
+        while let Some(oid) = queue.pop_front() {
+            if uninteresting.contains(&oid) {
+                boundary.insert(oid);
+                continue;
+            }
+            if !seen.insert(oid.clone()) {
+                continue;
+            }
+            let commit = self.load_commit(&oid)?;
+            queue.extend(commit.get_parents());
+            commits.push((oid, commit.get_tree().to_string()));
+        }
+
+        // In a stable order, so the same request always gets the same batch
+        let mut boundary: Vec<String> = boundary.into_iter().collect();
+        boundary.sort();
+        let mut known = HashSet::new();
+        let mut boundary_objects = Vec::new();
+        for oid in &boundary {
+            let tree = self.load_commit(oid)?.get_tree().to_string();
+            self.collect_tree(&tree, "", &mut known, &mut boundary_objects)?;
+        }
+
+        let mut objects: Vec<PathedOid> = tags.into_iter().map(|oid| (oid, String::new())).collect();
+        for (oid, tree) in commits {
+            objects.push((oid, String::new()));
+            self.collect_tree(&tree, "", &mut known, &mut objects)?;
+        }
+        Ok((objects, boundary_objects))
+    }
+
+    /// Every object reachable from `roots`, whatever their types: commits
+    /// lead to their trees and parents, trees to their entries and tags to
+    /// the object they name. Missing objects are left out.
+    pub fn connected_objects(&self, roots: &[String]) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut queue: VecDeque<String> = roots.iter().cloned().collect();
+
+        while let Some(oid) = queue.pop_front() {
+            // What a partial fetch left out stays with the remote
+            if seen.contains(&oid) || !self.exists(&oid) {
+                continue;
+            }
+            let (obj_type, data) = match self.load_raw(&oid) {
+                Ok(object) => object,
+                Err(e) => {
+                    trace!("Skipping {}: {}", oid, e);
+                    continue;
+                }
+            };
+            seen.insert(oid);
+
+            match obj_type.as_str() {
+                "commit" | "tag" => {
+                    // Header lines up to the first blank one
+                    let text = String::from_utf8_lossy(&data);
+                    for line in text.lines().take_while(|line| !line.is_empty()) {
+                        if let Some((key, value)) = line.split_once(' ') {
+                            if matches!(key, "tree" | "parent" | "object") {
+                                queue.push_back(value.trim().to_string());
+                            }
+                        }
+                    }
+                },
+                "tree" => {
+                    // `<mode> <name>\0` then the 20-byte ID, for each entry
+                    let mut pos = 0;
+                    while let Some(nul) = data[pos..].iter().position(|&b| b == 0) {
+                        let header = &data[pos..pos + nul];
+                        let start = pos + nul + 1;
+                        let raw = match data.get(start..start + 20) {
+                            Some(raw) => raw,
+                            None => break,
+                        };
+                        // Submodule commits live in other repositories
+                        if !header.starts_with(b"160000") {
+                            queue.push_back(hex::encode(raw));
+                        }
+                        pos = start + 20;
+                    }
+                },
+                _ => {},
+            }
+        }
+        seen
+    }
+
+    /// When a loose object was last written
+    pub fn loose_modified(&self, oid: &str) -> Result<std::time::SystemTime, Error> {
+        Ok(fs::metadata(self.object_path(oid))?.modified()?)
+    }
+
+    // Add a tree and everything below it to `objects`, skipping anything in `known`
+    fn collect_tree(&mut self, oid: &str, path: &str, known: &mut HashSet<String>, objects: &mut Vec<PathedOid>) -> Result<(), Error> {
+        if !known.insert(oid.to_string()) {
+            return Ok(());
+        }
+        objects.push((oid.to_string(), path.to_string()));
+
+        let object = self.load(oid)?;
+        let tree = object.as_any().downcast_ref::<Tree>()
+            .ok_or_else(|| Error::Generic(format!("Object {} is not a tree", oid)))?;
+        for (name, entry) in tree.get_entries() {
+            let entry_path = if path.is_empty() { name.clone() } else { format!("{}/{}", path, name) };
+            match entry {
+                TreeEntry::Tree(subtree) => {
+                    if let Some(sub_oid) = subtree.get_oid() {
+                        self.collect_tree(sub_oid, &entry_path, known, objects)?;
+                    }
+                },
+                TreeEntry::Blob(blob_oid, _) => {
+                    if known.insert(blob_oid.clone()) {
+                        objects.push((blob_oid.clone(), entry_path));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn load_commit(&mut self, oid: &str) -> Result<Commit, Error> {
+        let object = self.load(oid)?;
+        object.as_any().downcast_ref::<Commit>().cloned()
+            .ok_or_else(|| Error::Generic(format!("Object {} is not a commit", oid)))
+    }
+
     pub fn serialize_object(&self, object: &impl GitObject) -> Result<Vec<u8>, Error> {
         let obj_type = object.get_type();
         let content = object.to_bytes();
-        println!("Serializing {} object, content size: {} bytes", obj_type, content.len());
+        trace!("Serializing {} object, content size: {} bytes", obj_type, content.len());
         
         // Format: "<type> <size>\0<content>"
         let header = format!("{} {}\0", obj_type, content.len());
@@ -138,6 +406,32 @@ impl Database {
         format!("{:x}", result)
     }
 
+    // An object a partial fetch left out, fetched from a remote that has it
+    // and kept from then on
+    fn fetch_promised(&self, oid: &str) -> Result<Option<RawObject>, Error> {
+        for url in &self.promisors {
+            let objects = match transport::connect(url).and_then(|mut remote| remote.fetch_by_id(&[oid.to_string()])) {
+                Ok(objects) => objects,
+                Err(e) => {
+                    trace!("Could not fetch {} from {}: {}", oid, url, e);
+                    continue;
+                }
+            };
+
+            if let Some(object) = objects.into_iter().find(|object| object.oid == oid) {
+                let mut full_content = format!("{} {}\0", object.obj_type, object.data.len()).into_bytes();
+                full_content.extend_from_slice(&object.data);
+                if self.hash_content(&full_content) != oid {
+                    return Err(Error::Generic(format!("object {} was corrupted in transfer", oid)));
+                }
+                self.write_object(oid, &full_content)?;
+                trace!("Fetched missing object {} from {}", oid, url);
+                return Ok(Some((object.obj_type, object.data)));
+            }
+        }
+        Ok(None)
+    }
+
     /// Scrie un obiect în baza de date
     fn write_object(&self, oid: &str, content: &[u8]) -> Result<(), Error> {
         let object_path = self.object_path(oid);
@@ -155,33 +449,60 @@ impl Database {
             fs::create_dir_all(dirname)?;
         }
 
-        let temp_name = self.generate_temp_name();
-        let temp_path = dirname.join(temp_name);
-
-        let mut file = File::create(&temp_path)?;
-
         // Comprimă și scrie
         let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
         encoder.write_all(content)?;
         let compressed = encoder.finish()?;
 
-        file.write_all(&compressed)?;
-        fs::rename(temp_path, object_path)?;
+        // Other processes may be writing the same object at the same time:
+        // each writes its own temp file, and the rename puts one complete
+        // copy in place whichever comes last
+        let (temp_path, mut file) = self.create_temp_file(dirname)?;
+        let written = file.write_all(&compressed);
+        drop(file);
+        if let Err(e) = written.and_then(|_| fs::rename(&temp_path, &object_path)) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e.into());
+        }
 
         Ok(())
     }
 
+    // A new temp file in `dirname`, created exclusively so that it cannot be
+    // one another process is writing
+    fn create_temp_file(&self, dirname: &Path) -> Result<(PathBuf, File), Error> {
+        const ATTEMPTS: usize = 16;
+        for _ in 0..ATTEMPTS {
+            let temp_path = dirname.join(self.generate_temp_name());
+            match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+                Ok(file) => return Ok((temp_path, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(Error::Generic(format!("unable to create temporary object file in {}", dirname.display())))
+    }
+
     /// Obține calea către un obiect bazat pe OID
     fn object_path(&self, oid: &str) -> PathBuf {
         self.pathname.join(&oid[0..2]).join(&oid[2..])
     }
 
-    /// Citește un obiect din baza de date și îl parsează
-    /// Read and parse an object from the database
-    fn read_object(&self, oid: &str) -> Result<Box<dyn GitObject>, Error> {
+    /// Read an object's type and content without parsing it
+    pub fn load_raw(&self, oid: &str) -> Result<(String, Vec<u8>), Error> {
         let path = self.object_path(oid);
         
         if !path.exists() {
+            // Not loose, so it may be packed
+            let load_base = |base: &str| self.load_raw(base);
+            for pack in &self.packs {
+                if let Some(object) = pack.read(oid, &load_base)? {
+                    return Ok(object);
+                }
+            }
+            if let Some(object) = self.fetch_promised(oid)? {
+                return Ok(object);
+            }
             return Err(Error::Generic(format!("Object not found: {}", oid)));
         }
         
@@ -218,7 +539,44 @@ impl Database {
         }
         
         // Extract content (after null byte)
-        let content = &data[null_pos + 1..];
+        Ok((obj_type.to_string(), data[null_pos + 1..].to_vec()))
+    }
+
+    /// The size of an object's content, read from its header without
+    /// inflating the rest of it
+    pub fn object_size(&self, oid: &str) -> Result<usize, Error> {
+        let path = self.object_path(oid);
+        if !path.exists() {
+            for pack in &self.packs {
+                if let Some(size) = pack.size(oid)? {
+                    return Ok(size);
+                }
+            }
+            return Ok(self.load_raw(oid)?.1.len());
+        }
+
+        let mut decoder = ZlibDecoder::new(File::open(&path)?);
+        let mut header = Vec::new();
+        let mut byte = [0u8; 1];
+        loop {
+            decoder.read_exact(&mut byte)?;
+            if byte[0] == 0 {
+                break;
+            }
+            header.push(byte[0]);
+        }
+        let header = String::from_utf8_lossy(&header);
+        header.split_once(' ')
+            .and_then(|(_, size)| size.parse().ok())
+            .ok_or_else(|| Error::Generic(format!("Invalid header format: {}", header)))
+    }
+
+    /// Citește un obiect din baza de date și îl parsează
+    /// Read and parse an object from the database
+    fn read_object(&self, oid: &str) -> Result<Box<dyn GitObject>, Error> {
+        let (obj_type, content) = self.load_raw(oid)?;
+        let obj_type = obj_type.as_str();
+        let content = &content[..];
         
         // Parse object based on type
         let mut object: Box<dyn GitObject> = match obj_type {
@@ -235,7 +593,7 @@ impl Database {
             }
         },
         "tree" => {
-            println!("Parsing tree object: {}", oid);
+            trace!("Parsing tree object: {}", oid);
             match Tree::parse(content) {
                 Ok(tree) => Box::new(tree),
                 Err(e) => {
@@ -248,6 +606,7 @@ impl Database {
                 Ok(commit) => Box::new(commit),
                 Err(e) => return Err(e),
             },
+            "tag" => Box::new(Tag::parse(content)?),
             _ => return Err(Error::Generic(format!("Unknown object type: {}", obj_type))),
         };
         
@@ -263,7 +622,7 @@ impl Database {
         let name: String = (0..6)
             .map(|_| self.temp_chars.choose(&mut rng).unwrap())
             .collect();
-        format!("tmp_obj_{}", name)
+        format!("tmp_obj_{}_{}", process::id(), name)
     }
     
     /// Helper method to calculate hash for raw data (useful for status command)
@@ -286,19 +645,23 @@ impl Database {
             return Ok(Vec::new());
         }
         
+        // Packed objects first
+        let mut matches: Vec<String> = self.packs.iter()
+            .flat_map(|pack| pack.oids().iter().filter(|oid| oid.starts_with(prefix)).cloned())
+            .collect();
+        
         // Get the directory path for this prefix
         let dir_name = &prefix[0..2];
         let dir_path = self.pathname.join(dir_name);
         
         if !dir_path.exists() || !dir_path.is_dir() {
-            return Ok(Vec::new());
+            return Ok(matches);
         }
         
         // Read all files in the directory
         let entries = std::fs::read_dir(&dir_path).map_err(|e| Error::IO(e))?;
         
         // Filter files that match our prefix
-        let mut matches = Vec::new();
         for entry_result in entries {
             match entry_result {
                 Ok(entry) => {
@@ -306,7 +669,7 @@ impl Database {
                     let full_id = format!("{}{}", dir_name, file_name);
                     
                     // Check if this ID starts with our prefix
-                    if full_id.starts_with(prefix) {
+                    if full_id.starts_with(prefix) && !matches.contains(&full_id) {
                         matches.push(full_id);
                     }
                 },
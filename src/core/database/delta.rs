@@ -0,0 +1,183 @@
+// src/core/database/delta.rs - git delta instructions
+use std::collections::HashMap;
+
+use crate::errors::error::Error;
+
+// Matches shorter than a block are not worth a copy instruction
+const BLOCK: usize = 16;
+const MAX_INSERT: usize = 0x7f;
+const MAX_COPY: usize = 0xff_ffff;
+
+/// Describe `target` as copies from `base` plus literal inserts. Every
+/// block-aligned chunk of the base is indexed; the target is scanned byte by
+/// byte for those chunks and each hit is extended as far as it goes.
+pub fn compress(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut index: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    for start in (0..base.len().saturating_sub(BLOCK - 1)).step_by(BLOCK) {
+        index.entry(&base[start..start + BLOCK]).or_default().push(start);
+    }
+
+    let mut delta = Vec::new();
+    write_size(&mut delta, base.len());
+    write_size(&mut delta, target.len());
+
+    let mut pending: Vec<u8> = Vec::new();
+    let mut pos = 0;
+    while pos < target.len() {
+        let best = target.get(pos..pos + BLOCK)
+            .and_then(|chunk| index.get(chunk))
+            .and_then(|starts| {
+                starts.iter()
+                    .map(|&start| (start, common_length(&base[start..], &target[pos..])))
+                    .max_by_key(|&(_, length)| length)
+            });
+
+        match best {
+            Some((start, length)) => {
+                flush_insert(&mut delta, &mut pending);
+                write_copy(&mut delta, start, length);
+                pos += length;
+            },
+            None => {
+                pending.push(target[pos]);
+                pos += 1;
+            },
+        }
+    }
+    flush_insert(&mut delta, &mut pending);
+    delta
+}
+
+/// Rebuild an object from its `base` and a delta against it: two size
+/// headers, then copy (from the base) and insert (literal) instructions
+pub fn apply(base: &[u8], delta: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+    let base_size = read_size(delta, &mut pos)?;
+    let result_size = read_size(delta, &mut pos)?;
+
+    if base_size != base.len() {
+        return Err(Error::Generic(format!(
+            "delta base size mismatch: expected {} bytes, got {}", base_size, base.len()
+        )));
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+
+        if op & 0x80 != 0 {
+            // Copy: bits 0-3 say which offset bytes follow, bits 4-6 which size bytes
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos).ok_or_else(truncated)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (1 << (4 + i)) != 0 {
+                    size |= (*delta.get(pos).ok_or_else(truncated)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            let chunk = base.get(offset..offset + size)
+                .ok_or_else(|| Error::Generic("delta copies past the end of its base".to_string()))?;
+            result.extend_from_slice(chunk);
+        } else if op != 0 {
+            // Insert the next `op` bytes as they are
+            let chunk = delta.get(pos..pos + op as usize).ok_or_else(truncated)?;
+            result.extend_from_slice(chunk);
+            pos += op as usize;
+        } else {
+            return Err(Error::Generic("invalid delta instruction 0".to_string()));
+        }
+    }
+
+    if result.len() != result_size {
+        return Err(Error::Generic(format!(
+            "delta result size mismatch: expected {} bytes, got {}", result_size, result.len()
+        )));
+    }
+    Ok(result)
+}
+
+/// The size of the object `delta` builds, from its header
+pub fn target_size(delta: &[u8]) -> Result<usize, Error> {
+    let mut pos = 0;
+    read_size(delta, &mut pos)?;
+    read_size(delta, &mut pos)
+}
+
+// Little-endian base-128 size from a delta header
+fn read_size(data: &[u8], pos: &mut usize) -> Result<usize, Error> {
+    let mut size = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(size);
+        }
+    }
+}
+
+fn write_size(out: &mut Vec<u8>, mut size: usize) {
+    loop {
+        let byte = (size & 0x7f) as u8;
+        size >>= 7;
+        if size == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_copy(out: &mut Vec<u8>, mut offset: usize, mut length: usize) {
+    while length > 0 {
+        let size = length.min(MAX_COPY);
+        let mut op = 0x80u8;
+        let mut args = Vec::new();
+        for i in 0..4 {
+            let byte = ((offset >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                op |= 1 << i;
+                args.push(byte);
+            }
+        }
+        for i in 0..3 {
+            let byte = ((size >> (8 * i)) & 0xff) as u8;
+            if byte != 0 {
+                op |= 1 << (4 + i);
+                args.push(byte);
+            }
+        }
+        out.push(op);
+        out.extend(args);
+        offset += size;
+        length -= size;
+    }
+}
+
+fn flush_insert(out: &mut Vec<u8>, pending: &mut Vec<u8>) {
+    for chunk in pending.chunks(MAX_INSERT) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    pending.clear();
+}
+
+fn common_length(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+fn truncated() -> Error {
+    Error::Generic("truncated delta".to_string())
+}
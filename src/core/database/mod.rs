@@ -4,4 +4,10 @@ pub mod commit;
 pub mod tree;
 pub mod author;
 pub mod entry;
-pub mod tree_diff;
\ No newline at end of file
+pub mod tree_diff;
+pub mod delta;
+pub mod pack;
+pub mod pack_writer;
+pub mod commit_graph;
+pub mod bitmap;
+pub mod tag;
@@ -0,0 +1,300 @@
+// src/core/database/pack.rs - read objects out of .pack files through their .idx
+use std::fs::{self, File};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+
+use crate::core::database::delta;
+use crate::errors::error::Error;
+use crate::trace;
+
+const IDX_MAGIC: &[u8] = b"\xfftOc";
+const PACK_MAGIC: &[u8] = b"PACK";
+
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_TAG: u8 = 4;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+/// An object's type name and content
+pub type RawObject = (String, Vec<u8>);
+/// Finds a delta base that is not in the same pack
+pub type LoadBase<'a> = dyn Fn(&str) -> Result<RawObject, Error> + 'a;
+
+/// The object ids in a pack, sorted, and where each one starts in the pack
+#[derive(Debug, Clone)]
+pub struct PackIndex {
+    oids: Vec<String>,
+    offsets: Vec<u64>,
+}
+
+impl PackIndex {
+    /// Parse a version 1 or version 2 .idx file
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        if data.starts_with(IDX_MAGIC) {
+            if read_u32(data, 4)? != 2 {
+                return Err(Error::Generic(format!("unsupported pack index version {}", read_u32(data, 4)?)));
+            }
+            Self::parse_v2(data)
+        } else {
+            Self::parse_v1(data)
+        }
+    }
+
+    // Fan-out table, then (offset, oid) pairs
+    fn parse_v1(data: &[u8]) -> Result<Self, Error> {
+        let count = read_u32(data, 255 * 4)? as usize;
+        let mut oids = Vec::with_capacity(count);
+        let mut offsets = Vec::with_capacity(count);
+
+        for i in 0..count {
+            let entry = 256 * 4 + i * 24;
+            offsets.push(read_u32(data, entry)? as u64);
+            oids.push(hex::encode(slice(data, entry + 4, 20)?));
+        }
+        Ok(PackIndex { oids, offsets })
+    }
+
+    // Header, fan-out table, oids, CRCs, 31-bit offsets, then 64-bit
+    // offsets for the entries whose 31-bit offset has its top bit set
+    fn parse_v2(data: &[u8]) -> Result<Self, Error> {
+        let fanout = 8;
+        let count = read_u32(data, fanout + 255 * 4)? as usize;
+        let oid_table = fanout + 256 * 4;
+        let offset_table = oid_table + count * 20 + count * 4;
+        let large_table = offset_table + count * 4;
+
+        let mut oids = Vec::with_capacity(count);
+        let mut offsets = Vec::with_capacity(count);
+        for i in 0..count {
+            oids.push(hex::encode(slice(data, oid_table + i * 20, 20)?));
+
+            let offset = read_u32(data, offset_table + i * 4)?;
+            if offset & 0x8000_0000 == 0 {
+                offsets.push(offset as u64);
+            } else {
+                let large = large_table + (offset & 0x7fff_ffff) as usize * 8;
+                offsets.push(((read_u32(data, large)? as u64) << 32) | read_u32(data, large + 4)? as u64);
+            }
+        }
+        Ok(PackIndex { oids, offsets })
+    }
+
+    pub fn offset(&self, oid: &str) -> Option<u64> {
+        self.oids.binary_search_by(|probe| probe.as_str().cmp(oid))
+            .ok()
+            .map(|i| self.offsets[i])
+    }
+
+    pub fn oids(&self) -> &[String] {
+        &self.oids
+    }
+}
+
+/// A pack file together with its index
+#[derive(Debug, Clone)]
+pub struct Pack {
+    path: PathBuf,
+    index: PackIndex,
+}
+
+impl Pack {
+    /// Open the pack that goes with `idx_path`
+    pub fn open(idx_path: &Path) -> Result<Self, Error> {
+        let index = PackIndex::parse(&fs::read(idx_path)?)?;
+        let path = idx_path.with_extension("pack");
+
+        let mut header = [0u8; 12];
+        File::open(&path)?.read_exact(&mut header)?;
+        if &header[0..4] != PACK_MAGIC {
+            return Err(Error::Generic(format!("{} is not a pack file", path.display())));
+        }
+
+        Ok(Pack { path, index })
+    }
+
+    pub fn contains(&self, oid: &str) -> bool {
+        self.index.offset(oid).is_some()
+    }
+
+    pub fn oids(&self) -> &[String] {
+        self.index.oids()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Where `oid` starts in the pack, if the pack has it
+    pub fn offset(&self, oid: &str) -> Option<u64> {
+        self.index.offset(oid)
+    }
+
+    /// The SHA-1 the pack ends with, over everything before it
+    pub fn checksum(&self) -> Result<Vec<u8>, Error> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::End(-20))?;
+        let mut checksum = vec![0u8; 20];
+        file.read_exact(&mut checksum)?;
+        Ok(checksum)
+    }
+
+    /// The type and content of `oid`, or None when the pack does not have
+    /// it. `load_base` finds the bases of ref deltas, which may live outside
+    /// this pack.
+    pub fn read(
+        &self,
+        oid: &str,
+        load_base: &LoadBase<'_>,
+    ) -> Result<Option<RawObject>, Error> {
+        match self.index.offset(oid) {
+            Some(offset) => {
+                let mut file = BufReader::new(File::open(&self.path)?);
+                self.read_at(&mut file, offset, load_base).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// The size of `oid` once read, or None when the pack does not have
+    /// it. Only the header is read, and for a delta the delta itself.
+    pub fn size(&self, oid: &str) -> Result<Option<usize>, Error> {
+        let offset = match self.index.offset(oid) {
+            Some(offset) => offset,
+            None => return Ok(None),
+        };
+        let mut file = BufReader::new(File::open(&self.path)?);
+        file.seek(SeekFrom::Start(offset))?;
+        let (obj_type, size) = read_header(&mut file)?;
+
+        match obj_type {
+            OBJ_OFS_DELTA => {
+                while read_byte(&mut file)? & 0x80 != 0 {}
+            },
+            OBJ_REF_DELTA => {
+                file.read_exact(&mut [0u8; 20])?;
+            },
+            _ => return Ok(Some(size)),
+        }
+        delta::target_size(&inflate(&mut file, size)?).map(Some)
+    }
+
+    fn read_at(
+        &self,
+        file: &mut BufReader<File>,
+        offset: u64,
+        load_base: &LoadBase<'_>,
+    ) -> Result<RawObject, Error> {
+        file.seek(SeekFrom::Start(offset))?;
+        let (obj_type, size) = read_header(file)?;
+
+        match obj_type {
+            OBJ_COMMIT | OBJ_TREE | OBJ_BLOB | OBJ_TAG => {
+                let data = inflate(file, size)?;
+                Ok((type_name(obj_type).to_string(), data))
+            },
+            OBJ_OFS_DELTA => {
+                // Big-endian base-128 distance back to the base, with each
+                // continuation adding one so no two encodings mean the same
+                let mut byte = read_byte(file)?;
+                let mut distance = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_byte(file)?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+                }
+                let base_offset = offset.checked_sub(distance)
+                    .ok_or_else(|| Error::Generic(format!("bad delta base offset in {}", self.path.display())))?;
+
+                let delta_data = inflate(file, size)?;
+                let (base_type, base) = self.read_at(file, base_offset, load_base)?;
+                Ok((base_type, delta::apply(&base, &delta_data)?))
+            },
+            OBJ_REF_DELTA => {
+                let mut base_oid = [0u8; 20];
+                file.read_exact(&mut base_oid)?;
+                let base_oid = hex::encode(base_oid);
+
+                let delta_data = inflate(file, size)?;
+                let (base_type, base) = match self.index.offset(&base_oid) {
+                    Some(base_offset) => self.read_at(file, base_offset, load_base)?,
+                    None => load_base(&base_oid)?,
+                };
+                Ok((base_type, delta::apply(&base, &delta_data)?))
+            },
+            other => Err(Error::Generic(format!("unknown object type {} in {}", other, self.path.display()))),
+        }
+    }
+}
+
+/// Every readable pack under `<objects>/pack`
+pub fn load_packs(objects: &Path) -> Vec<Pack> {
+    let mut idx_paths: Vec<PathBuf> = match fs::read_dir(objects.join("pack")) {
+        Ok(entries) => entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    idx_paths.sort();
+
+    idx_paths.iter()
+        .filter_map(|path| match Pack::open(path) {
+            Ok(pack) => Some(pack),
+            Err(e) => {
+                trace!("Skipping pack {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn type_name(obj_type: u8) -> &'static str {
+    match obj_type {
+        OBJ_COMMIT => "commit",
+        OBJ_TREE => "tree",
+        OBJ_BLOB => "blob",
+        _ => "tag",
+    }
+}
+
+fn inflate(file: &mut BufReader<File>, size: usize) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::with_capacity(size);
+    ZlibDecoder::new(file).read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(Error::Generic(format!("packed object is {} bytes, expected {}", data.len(), size)));
+    }
+    Ok(data)
+}
+
+// Type in bits 4-6 of the first byte, then the inflated size in
+// little-endian base-128
+fn read_header(file: &mut BufReader<File>) -> Result<(u8, usize), Error> {
+    let mut byte = read_byte(file)?;
+    let obj_type = (byte >> 4) & 0x7;
+    let mut size = (byte & 0x0f) as usize;
+    let mut shift = 4;
+    while byte & 0x80 != 0 {
+        byte = read_byte(file)?;
+        size |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+    }
+    Ok((obj_type, size))
+}
+
+fn read_byte(file: &mut BufReader<File>) -> Result<u8, Error> {
+    let mut byte = [0u8; 1];
+    file.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8], Error> {
+    data.get(start..start + len).ok_or_else(|| Error::Generic("truncated pack index".to_string()))
+}
+
+fn read_u32(data: &[u8], start: usize) -> Result<u32, Error> {
+    let bytes = slice(data, start, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
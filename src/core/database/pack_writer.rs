@@ -0,0 +1,258 @@
+// src/core/database/pack_writer.rs - write objects into a .pack with its .idx
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+use sha1::{Digest, Sha1};
+
+use crate::core::database::delta;
+use crate::core::database::pack::RawObject;
+use crate::errors::error::Error;
+
+// How many earlier objects of the same type each object is tried against
+const WINDOW: usize = 10;
+// Longest chain of deltas a reader has to follow
+const MAX_DEPTH: usize = 50;
+
+/// What a written pack holds
+#[derive(Debug)]
+pub struct PackSummary {
+    pub path: PathBuf,
+    pub objects: usize,
+    pub deltas: usize,
+}
+
+struct PackEntry {
+    oid: String,
+    obj_type: String,
+    data: Vec<u8>,
+    depth: usize,
+    // Index of the entry this one is stored as a delta against
+    base: Option<usize>,
+}
+
+/// Collects objects and writes them out as `pack-<checksum>.pack` plus a
+/// version 2 `.idx`. Objects are sorted by type and size so that similar
+/// ones sit close together, then each is stored as a delta against one of
+/// the few before it when that is at most half its size.
+pub struct PackWriter {
+    entries: Vec<PackEntry>,
+}
+
+impl Default for PackWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PackWriter {
+    pub fn new() -> Self {
+        PackWriter { entries: Vec::new() }
+    }
+
+    pub fn add(&mut self, oid: &str, object: RawObject) {
+        let (obj_type, data) = object;
+        self.entries.push(PackEntry {
+            oid: oid.to_string(),
+            obj_type,
+            data,
+            depth: 0,
+            base: None,
+        });
+    }
+
+    /// Write the pack and its index into `pack_dir`
+    pub fn write(mut self, pack_dir: &Path) -> Result<PackSummary, Error> {
+        self.entries.sort_by(|a, b| {
+            a.obj_type.cmp(&b.obj_type).then(b.data.len().cmp(&a.data.len()))
+        });
+        let deltas = self.find_deltas();
+
+        let mut pack = Vec::new();
+        pack.extend_from_slice(b"PACK");
+        pack.extend_from_slice(&2u32.to_be_bytes());
+        pack.extend_from_slice(&(self.entries.len() as u32).to_be_bytes());
+
+        let mut offsets = Vec::with_capacity(self.entries.len());
+        let mut crcs = Vec::with_capacity(self.entries.len());
+        for (i, entry) in self.entries.iter().enumerate() {
+            let offset = pack.len();
+            let mut record = Vec::new();
+
+            match (entry.base, &deltas[i]) {
+                (Some(base), Some(delta_data)) => {
+                    write_header(&mut record, 6, delta_data.len());
+                    write_offset(&mut record, offset - offsets[base]);
+                    record.extend(deflate(delta_data)?);
+                },
+                _ => {
+                    write_header(&mut record, type_code(&entry.obj_type)?, entry.data.len());
+                    record.extend(deflate(&entry.data)?);
+                },
+            }
+
+            let mut crc = Crc::new();
+            crc.update(&record);
+            crcs.push(crc.sum());
+            offsets.push(offset);
+            pack.extend(record);
+        }
+        let checksum = Sha1::digest(&pack);
+        pack.extend_from_slice(&checksum);
+
+        let index = self.build_index(&offsets, &crcs, &checksum);
+
+        fs::create_dir_all(pack_dir)?;
+        let name = format!("pack-{}", hex::encode(checksum));
+        let pack_path = pack_dir.join(format!("{}.pack", name));
+        let idx_path = pack_dir.join(format!("{}.idx", name));
+        // The index goes last: readers only look for packs through it
+        write_file(&pack_path, &pack)?;
+        write_file(&idx_path, &index)?;
+
+        Ok(PackSummary {
+            path: pack_path,
+            objects: self.entries.len(),
+            deltas: deltas.iter().filter(|d| d.is_some()).count(),
+        })
+    }
+
+    // Pick a base for each entry among the previous few of the same type,
+    // keeping the smallest delta that saves at least half the object
+    fn find_deltas(&mut self) -> Vec<Option<Vec<u8>>> {
+        let mut deltas: Vec<Option<Vec<u8>>> = Vec::with_capacity(self.entries.len());
+        for i in 0..self.entries.len() {
+            let mut best: Option<(usize, Vec<u8>)> = None;
+            let limit = self.entries[i].data.len() / 2;
+
+            for j in (i.saturating_sub(WINDOW)..i).rev() {
+                let (base, target) = (&self.entries[j], &self.entries[i]);
+                if base.obj_type != target.obj_type || base.depth >= MAX_DEPTH || base.data.is_empty() {
+                    continue;
+                }
+                let candidate = delta::compress(&base.data, &target.data);
+                let smallest = best.as_ref().map_or(limit, |(_, d)| d.len());
+                if candidate.len() < smallest {
+                    best = Some((j, candidate));
+                }
+            }
+
+            match best {
+                Some((base, delta_data)) => {
+                    self.entries[i].base = Some(base);
+                    self.entries[i].depth = self.entries[base].depth + 1;
+                    deltas.push(Some(delta_data));
+                },
+                None => deltas.push(None),
+            }
+        }
+        deltas
+    }
+
+    fn build_index(&self, offsets: &[usize], crcs: &[u32], checksum: &[u8]) -> Vec<u8> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        order.sort_by(|&a, &b| self.entries[a].oid.cmp(&self.entries[b].oid));
+        let raw_oids: Vec<Vec<u8>> = order.iter()
+            .map(|&i| hex::decode(&self.entries[i].oid).unwrap_or_else(|_| vec![0; 20]))
+            .collect();
+
+        let mut index = Vec::new();
+        index.extend_from_slice(b"\xfftOc");
+        index.extend_from_slice(&2u32.to_be_bytes());
+
+        // How many oids start with a byte up to each value
+        let mut fanout = [0u32; 256];
+        for oid in &raw_oids {
+            fanout[oid[0] as usize] += 1;
+        }
+        let mut total = 0;
+        for count in fanout.iter_mut() {
+            total += *count;
+            *count = total;
+        }
+        for count in fanout {
+            index.extend_from_slice(&count.to_be_bytes());
+        }
+
+        for oid in &raw_oids {
+            index.extend_from_slice(oid);
+        }
+        for &i in &order {
+            index.extend_from_slice(&crcs[i].to_be_bytes());
+        }
+
+        let mut large = Vec::new();
+        for &i in &order {
+            let offset = offsets[i] as u64;
+            if offset < 0x8000_0000 {
+                index.extend_from_slice(&(offset as u32).to_be_bytes());
+            } else {
+                index.extend_from_slice(&(0x8000_0000 | large.len() as u32).to_be_bytes());
+                large.push(offset);
+            }
+        }
+        for offset in large {
+            index.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        index.extend_from_slice(checksum);
+        let index_checksum = Sha1::digest(&index);
+        index.extend_from_slice(&index_checksum);
+        index
+    }
+}
+
+fn type_code(obj_type: &str) -> Result<u8, Error> {
+    match obj_type {
+        "commit" => Ok(1),
+        "tree" => Ok(2),
+        "blob" => Ok(3),
+        "tag" => Ok(4),
+        other => Err(Error::Generic(format!("cannot pack object of type {}", other))),
+    }
+}
+
+// Type and size: 3 type bits and 4 size bits, then 7 size bits per byte
+fn write_header(out: &mut Vec<u8>, obj_type: u8, size: usize) {
+    let mut byte = (obj_type << 4) | (size & 0x0f) as u8;
+    let mut rest = size >> 4;
+    while rest > 0 {
+        out.push(byte | 0x80);
+        byte = (rest & 0x7f) as u8;
+        rest >>= 7;
+    }
+    out.push(byte);
+}
+
+// The inverse of the ofs-delta distance encoding in pack.rs
+fn write_offset(out: &mut Vec<u8>, mut distance: usize) {
+    let mut bytes = vec![(distance & 0x7f) as u8];
+    distance >>= 7;
+    while distance > 0 {
+        distance -= 1;
+        bytes.push(0x80 | (distance & 0x7f) as u8);
+        distance >>= 7;
+    }
+    bytes.reverse();
+    out.extend(bytes);
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+pub(crate) fn write_file(path: &Path, data: &[u8]) -> Result<(), Error> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    // Two repacks may produce the same pack at once; each writes its own copy
+    let temp = path.with_file_name(format!("tmp_{}_{}", std::process::id(), name));
+    fs::write(&temp, data)?;
+    if let Err(e) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
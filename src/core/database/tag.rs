@@ -0,0 +1,111 @@
+// src/core/database/tag.rs - annotated tag objects
+use std::any::Any;
+use std::str;
+
+use super::{author::Author, database::GitObject};
+use crate::core::gpg;
+use crate::errors::error::Error;
+
+/// An annotated tag: names an object (usually a commit) and records who
+/// tagged it, when, and why
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub oid: Option<String>,
+    pub object: String,
+    pub object_type: String,
+    pub name: String,
+    pub tagger: Author,
+    pub message: String,
+}
+
+impl GitObject for Tag {
+    fn get_type(&self) -> &str {
+        "tag"
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut message = self.message.clone();
+        if !message.ends_with('\n') {
+            message.push('\n');
+        }
+        format!(
+            "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+            self.object, self.object_type, self.name, self.tagger, message
+        ).into_bytes()
+    }
+
+    fn set_oid(&mut self, oid: String) {
+        self.oid = Some(oid);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn GitObject> {
+        Box::new(self.clone())
+    }
+}
+
+impl Tag {
+    pub fn new(object: String, object_type: String, name: String, tagger: Author, message: String) -> Self {
+        Tag {
+            oid: None,
+            object,
+            object_type,
+            name,
+            tagger,
+            message,
+        }
+    }
+
+    /// The signature at the end of the message, if the tag is signed
+    pub fn signature(&self) -> Option<&str> {
+        gpg::split_signature(&self.message).1
+    }
+
+    /// The tag as it was before it was signed, which is what the signature
+    /// is of
+    pub fn payload(&self) -> Vec<u8> {
+        let mut unsigned = self.clone();
+        unsigned.message = gpg::split_signature(&self.message).0.to_string();
+        unsigned.to_bytes()
+    }
+
+    /// Parse the headers and message of a tag object
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let content = str::from_utf8(data)
+            .map_err(|_| Error::Generic("Invalid UTF-8 in tag".to_string()))?;
+        let (headers, message) = content.split_once("\n\n").unwrap_or((content, ""));
+
+        let mut object = None;
+        let mut object_type = None;
+        let mut name = None;
+        let mut tagger = None;
+        for line in headers.lines() {
+            match line.split_once(' ') {
+                Some(("object", value)) => object = Some(value.to_string()),
+                Some(("type", value)) => object_type = Some(value.to_string()),
+                Some(("tag", value)) => name = Some(value.to_string()),
+                Some(("tagger", value)) => tagger = Author::parse(value).ok(),
+                _ => {},
+            }
+        }
+
+        let missing = |header: &str| Error::Generic(format!("Missing {} in tag", header));
+        let object = object.ok_or_else(|| missing("object"))?;
+        let object_type = object_type.ok_or_else(|| missing("type"))?;
+        let name = name.ok_or_else(|| missing("tag"))?;
+        // Very old tags have no tagger
+        let tagger = tagger.unwrap_or_else(|| Author::new(String::new(), String::new()));
+
+        Ok(Tag {
+            oid: None,
+            object,
+            object_type,
+            name,
+            tagger,
+            message: message.to_string(),
+        })
+    }
+}
@@ -8,6 +8,7 @@ use itertools::Itertools;
 use std::collections::HashMap;
 use std::any::Any;
 use std::path::PathBuf;
+use crate::trace;
 
 #[derive(Debug)]
 #[derive(Clone)]
@@ -146,7 +147,7 @@ impl Tree {
                 continue;
             }
             
-            println!("Processing entry: {}", path_str);
+            trace!("Processing entry: {}", path_str);
             
             // Handle top-level file
             if components.len() == 1 {
@@ -157,7 +158,7 @@ impl Tree {
                     TreeEntry::Blob(entry.get_oid().to_string(), mode)
                 );
                 
-                println!("Added top-level file: {}", components[0]);
+                trace!("Added top-level file: {}", components[0]);
                 continue;
             }
             
@@ -172,7 +173,7 @@ impl Tree {
             for dir in dir_components {
                 current_path.push(dir.clone());
                 let dir_str = current_path.join("/");
-                println!("Creating/navigating directory: {}", dir_str);
+                trace!("Creating/navigating directory: {}", dir_str);
                 
                 // Check if we need to create a directory
                 let need_new_dir = match current.entries.get(dir) {
@@ -188,7 +189,7 @@ impl Tree {
                 };
                 
                 if need_new_dir {
-                    println!("Creating new directory: {}", dir);
+                    trace!("Creating new directory: {}", dir);
                     current.entries.insert(
                         dir.clone(),
                         TreeEntry::Tree(Box::new(Tree::new()))
@@ -208,7 +209,7 @@ impl Tree {
             // Add file at current position
             let mode = FileMode::parse(entry.get_mode());
             
-            println!("Adding file: {} to directory: {}", filename, current_path.join("/"));
+            trace!("Adding file: {} to directory: {}", filename, current_path.join("/"));
             current.entries.insert(
                 filename.clone(),
                 TreeEntry::Blob(entry.get_oid().to_string(), mode)
@@ -216,7 +217,7 @@ impl Tree {
         }
         
         // Print final tree structure for debugging
-        println!("Final tree structure:");
+        trace!("Final tree structure:");
         root.dump_structure("  ");
         
         Ok(root)
@@ -293,7 +294,7 @@ impl Tree {
         // Process each entry
         for name in names_to_process {
             if let Some(TreeEntry::Tree(subtree)) = self.entries.get_mut(&name) {
-                println!("Traversing subtree: {}", name);
+                trace!("Traversing subtree: {}", name);
                 // Process subtree recursively - using traverse_internal
                 subtree.traverse_internal(&mut func)?;
                 
@@ -301,20 +302,20 @@ impl Tree {
                 if subtree.oid.is_none() {
                     println!("Warning: Subtree {} has no OID after traversal", name);
                 } else {
-                    println!("Subtree {} has OID {} after traversal", name, subtree.oid.as_ref().unwrap());
+                    trace!("Subtree {} has OID {} after traversal", name, subtree.oid.as_ref().unwrap());
                 }
             }
         }
         
         // Finally, process this tree
-        println!("Processing tree with {} entries", self.entries.len());
+        trace!("Processing tree with {} entries", self.entries.len());
         func(self)?;
         
         // Verify this tree has OID set
         if self.oid.is_none() {
             println!("Warning: Tree has no OID after processing");
         } else {
-            println!("Tree has OID {} after processing", self.oid.as_ref().unwrap());
+            trace!("Tree has OID {} after processing", self.oid.as_ref().unwrap());
         }
         
         Ok(())
@@ -335,7 +336,7 @@ impl Tree {
         // Process each entry
         for name in names_to_process {
             if let Some(TreeEntry::Tree(subtree)) = self.entries.get_mut(&name) {
-                println!("Traversing internal subtree: {}", name);
+                trace!("Traversing internal subtree: {}", name);
                 // Process subtree recursively
                 subtree.traverse_internal(func)?;
                 
@@ -343,21 +344,21 @@ impl Tree {
                 if subtree.oid.is_none() {
                     println!("Warning: Internal subtree {} has no OID after traversal", name);
                 } else {
-                    println!("Internal subtree {} has OID {} after traversal", 
+                    trace!("Internal subtree {} has OID {} after traversal", 
                             name, subtree.oid.as_ref().unwrap());
                 }
             }
         }
         
         // Finally, process this tree
-        println!("Processing internal tree with {} entries", self.entries.len());
+        trace!("Processing internal tree with {} entries", self.entries.len());
         let result = func(self);
         
         // Verify OID is set after processing
         if self.oid.is_none() {
             println!("Warning: Internal tree has no OID after processing");
         } else {
-            println!("Internal tree has OID {} after processing", self.oid.as_ref().unwrap());
+            trace!("Internal tree has OID {} after processing", self.oid.as_ref().unwrap());
         }
         
         result
@@ -407,13 +408,13 @@ impl Tree {
                     // MODIFICAREA CRUCIALĂ - verifică modul pentru a determina tipul intrării
                     if mode.is_directory() {
                         // Aceasta este o intrare de director
-                        println!("Tree parse: Found directory entry: {} -> {} (mode {})", name, oid, mode);
+                        trace!("Tree parse: Found directory entry: {} -> {} (mode {})", name, oid, mode);
                         let mut subtree = Tree::new();
                         subtree.set_oid(oid);
                         tree.entries.insert(name.to_string(), TreeEntry::Tree(Box::new(subtree)));
                     } else {
                         // Aceasta este o intrare normală de fișier
-                        println!("Tree parse: Found file entry: {} -> {} (mode {})", name, oid, mode);
+                        trace!("Tree parse: Found file entry: {} -> {} (mode {})", name, oid, mode);
                         tree.entries.insert(name.to_string(), TreeEntry::Blob(oid, mode));
                     }
                 } else {
@@ -442,7 +443,7 @@ impl Tree {
     }
     
     pub fn dump_structure(&self, prefix: &str) {
-        println!("{}Tree Structure:", prefix);
+        trace!("{}Tree Structure:", prefix);
         self.dump_entries(prefix, "");
     }
     
@@ -456,14 +457,14 @@ impl Tree {
             
             match entry {
                 TreeEntry::Blob(oid, mode) => {
-                    println!("{}{} (blob, mode {}) -> {}", prefix, entry_path, mode, oid);
+                    trace!("{}{} (blob, mode {}) -> {}", prefix, entry_path, mode, oid);
                 },
                 TreeEntry::Tree(subtree) => {
                     if let Some(oid) = subtree.get_oid() {
-                        println!("{}{} (tree) -> {}", prefix, entry_path, oid);
+                        trace!("{}{} (tree) -> {}", prefix, entry_path, oid);
                         subtree.dump_entries(prefix, &entry_path);
                     } else {
-                        println!("{}{} (tree) -> <no OID>", prefix, entry_path);
+                        trace!("{}{} (tree) -> <no OID>", prefix, entry_path);
                         subtree.dump_entries(prefix, &entry_path);
                     }
                 }
@@ -473,36 +474,36 @@ impl Tree {
     
     pub fn inspect_tree_structure(database: &mut Database, tree_oid: &str, depth: usize) -> Result<(), Error> {
         let indent = "  ".repeat(depth);
-        println!("{}Inspecting tree: {}", indent, tree_oid);
+        trace!("{}Inspecting tree: {}", indent, tree_oid);
         
         // Load the object
         let obj = database.load(tree_oid)?;
-        println!("{}Object type: {}", indent, obj.get_type());
+        trace!("{}Object type: {}", indent, obj.get_type());
         
         // If it's a tree, process it directly
         if let Some(tree) = obj.as_any().downcast_ref::<Tree>() {
-            println!("{}Tree has {} entries:", indent, tree.get_entries().len());
+            trace!("{}Tree has {} entries:", indent, tree.get_entries().len());
             
             for (name, entry) in tree.get_entries() {
                 match entry {
                     TreeEntry::Blob(blob_oid, mode) => {
                         if mode.is_directory() {
-                            println!("{}+ {} (directory stored as blob) -> {}", 
+                            trace!("{}+ {} (directory stored as blob) -> {}", 
                                     indent, name, blob_oid);
                             // Recursively inspect this directory
                             Self::inspect_tree_structure(database, blob_oid, depth + 1)?;
                         } else {
-                            println!("{}+ {} (file, mode {}) -> {}", 
+                            trace!("{}+ {} (file, mode {}) -> {}", 
                                     indent, name, mode, blob_oid);
                         }
                     },
                     TreeEntry::Tree(subtree) => {
                         if let Some(subtree_oid) = subtree.get_oid() {
-                            println!("{}+ {} (directory) -> {}", indent, name, subtree_oid);
+                            trace!("{}+ {} (directory) -> {}", indent, name, subtree_oid);
                             // Recursively inspect this directory
                             Self::inspect_tree_structure(database, subtree_oid, depth + 1)?;
                         } else {
-                            println!("{}+ {} (directory without OID)", indent, name);
+                            trace!("{}+ {} (directory without OID)", indent, name);
                         }
                     }
                 }
@@ -513,48 +514,48 @@ impl Tree {
         
         // If it's a blob, try to parse it as a tree
         if obj.get_type() == "blob" {
-            println!("{}Blob, attempting to parse as tree...", indent);
+            trace!("{}Blob, attempting to parse as tree...", indent);
             
             let blob_data = obj.to_bytes();
             match Tree::parse(&blob_data) {
                 Ok(tree) => {
-                    println!("{}Successfully parsed as tree with {} entries:", 
+                    trace!("{}Successfully parsed as tree with {} entries:", 
                             indent, tree.get_entries().len());
                     
                     for (name, entry) in tree.get_entries() {
                         match entry {
                             TreeEntry::Blob(blob_oid, mode) => {
                                 if mode.is_directory() {
-                                    println!("{}+ {} (directory stored as blob) -> {}", 
+                                    trace!("{}+ {} (directory stored as blob) -> {}", 
                                             indent, name, blob_oid);
                                     // Recursively inspect this directory
                                     Self::inspect_tree_structure(database, blob_oid, depth + 1)?;
                                 } else {
-                                    println!("{}+ {} (file, mode {}) -> {}", 
+                                    trace!("{}+ {} (file, mode {}) -> {}", 
                                             indent, name, mode, blob_oid);
                                 }
                             },
                             TreeEntry::Tree(subtree) => {
                                 if let Some(subtree_oid) = subtree.get_oid() {
-                                    println!("{}+ {} (directory) -> {}", indent, name, subtree_oid);
+                                    trace!("{}+ {} (directory) -> {}", indent, name, subtree_oid);
                                     // Recursively inspect this directory
                                     Self::inspect_tree_structure(database, subtree_oid, depth + 1)?;
                                 } else {
-                                    println!("{}+ {} (directory without OID)", indent, name);
+                                    trace!("{}+ {} (directory without OID)", indent, name);
                                 }
                             }
                         }
                     }
                 },
                 Err(e) => {
-                    println!("{}Failed to parse as tree: {}", indent, e);
+                    trace!("{}Failed to parse as tree: {}", indent, e);
                 }
             }
             
             return Ok(());
         }
         
-        println!("{}Neither a tree nor a parseable blob", indent);
+        trace!("{}Neither a tree nor a parseable blob", indent);
         Ok(())
     }
 }
\ No newline at end of file
@@ -132,8 +132,9 @@ impl<'a> TreeDiff<'a> {
             }
             
             // Check if both entries are trees
-            let a_is_tree = a_entry.get_mode() == "040000";
-            let b_is_tree = b_entry.map_or(false, |e| e.get_mode() == "040000");
+            // Modes are normalised without the leading zero, so compare parsed modes
+            let a_is_tree = a_entry.get_file_mode().is_directory();
+            let b_is_tree = b_entry.is_some_and(|e| e.get_file_mode().is_directory());
             
             // Create a new filter for this path
             let sub_filter = filter.join(name);
@@ -0,0 +1,147 @@
+// src/core/diff/combined.rs - combined (`diff --cc`) output against several parents
+use super::myers::{diff_lines, Edit};
+use crate::core::color::Color;
+
+// One output row: either a result line or a line deleted from one parent
+struct Row {
+    text: String,
+    // One marker per parent: ' ', '+' or '-'
+    marks: Vec<char>,
+    // Whether the row consumes a line of the result
+    in_result: bool,
+}
+
+impl Row {
+    fn is_change(&self) -> bool {
+        self.marks.iter().any(|m| *m != ' ')
+    }
+
+    // Whether the row consumes a line of the given parent
+    fn in_parent(&self, parent: usize) -> bool {
+        match self.marks[parent] {
+            '-' => true,
+            ' ' => self.in_result,
+            _ => false,
+        }
+    }
+}
+
+/// Produce a combined diff of `result` against every version in `parents`.
+///
+/// Each output line carries one marker column per parent: `+` when the line
+/// is not present in that parent, `-` when it was removed from it. Only the
+/// hunk bodies are returned; callers print the file header.
+pub fn combined_diff(parents: &[Vec<String>], result: &[String], context_lines: usize) -> String {
+    let rows = build_rows(parents, result);
+    let mut output = String::new();
+
+    for (start, end) in hunk_ranges(&rows, context_lines) {
+        output.push_str(&hunk_header(&rows, parents.len(), start, end));
+        output.push('\n');
+        for row in &rows[start..end] {
+            let marks: String = row.marks.iter().collect();
+            output.push_str(&format!("{}{}\n", marks, row.text));
+        }
+    }
+
+    output
+}
+
+fn build_rows(parents: &[Vec<String>], result: &[String]) -> Vec<Row> {
+    let count = parents.len();
+
+    // For every parent: which result lines it contains, and the lines it
+    // loses before each result position
+    let mut present = vec![vec![false; result.len()]; count];
+    let mut deleted: Vec<Vec<Vec<String>>> = vec![vec![Vec::new(); result.len() + 1]; count];
+
+    for (p, parent) in parents.iter().enumerate() {
+        let mut pending = Vec::new();
+        for edit in diff_lines(parent, result) {
+            match edit {
+                Edit::Delete(a) => pending.push(parent[a].clone()),
+                Edit::Insert(b) => deleted[p][b].append(&mut pending),
+                Edit::Equal(_, b) => {
+                    deleted[p][b].append(&mut pending);
+                    present[p][b] = true;
+                }
+            }
+        }
+        deleted[p][result.len()].append(&mut pending);
+    }
+
+    let mut rows = Vec::new();
+    for r in 0..=result.len() {
+        for p in 0..count {
+            for text in &deleted[p][r] {
+                let mut marks = vec![' '; count];
+                marks[p] = '-';
+                rows.push(Row { text: text.clone(), marks, in_result: false });
+            }
+        }
+        if r < result.len() {
+            let marks = (0..count).map(|p| if present[p][r] { ' ' } else { '+' }).collect();
+            rows.push(Row { text: result[r].clone(), marks, in_result: true });
+        }
+    }
+
+    rows
+}
+
+// Row ranges to print, merging changes whose context overlaps
+fn hunk_ranges(rows: &[Row], context_lines: usize) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (i, row) in rows.iter().enumerate() {
+        if !row.is_change() {
+            continue;
+        }
+        let start = i.saturating_sub(context_lines);
+        let end = (i + 1 + context_lines).min(rows.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+
+    ranges
+}
+
+fn hunk_header(rows: &[Row], count: usize, start: usize, end: usize) -> String {
+    let marker = "@".repeat(count + 1);
+    let mut header = marker.clone();
+
+    for p in 0..count {
+        let before = rows[..start].iter().filter(|r| r.in_parent(p)).count();
+        let len = rows[start..end].iter().filter(|r| r.in_parent(p)).count();
+        header.push_str(&format!(" -{},{}", if len == 0 { before } else { before + 1 }, len));
+    }
+
+    let before = rows[..start].iter().filter(|r| r.in_result).count();
+    let len = rows[start..end].iter().filter(|r| r.in_result).count();
+    header.push_str(&format!(" +{},{} {}", if len == 0 { before } else { before + 1 }, len, marker));
+
+    header
+}
+
+/// Color a combined diff: hunk headers in cyan, lines with any `+` marker in
+/// green and lines with a `-` marker in red
+pub fn colorize(diff: &str, parent_count: usize) -> String {
+    let mut result = String::new();
+
+    for line in diff.lines() {
+        let marks: String = line.chars().take(parent_count).collect();
+        if line.starts_with("@@") {
+            result.push_str(&Color::cyan(line));
+        } else if marks.contains('+') {
+            result.push_str(&Color::green(line));
+        } else if marks.contains('-') {
+            result.push_str(&Color::red(line));
+        } else {
+            result.push_str(line);
+        }
+        result.push('\n');
+    }
+
+    result
+}
@@ -7,6 +7,7 @@ use crate::core::database::blob::Blob;
 use crate::errors::error::Error;
 use crate::core::color::Color;
 use super::myers;
+use crate::core::path_encoding;
 
 /// Dimensiunea maximă a unui fișier pentru diff (pentru a evita probleme de performanță)
 const MAX_DIFF_SIZE: usize = 10 * 1024 * 1024; // 10 MB
@@ -81,8 +82,8 @@ pub fn diff_files(file1_path: &Path, file2_path: &Path, context_lines: usize) ->
     // Creează antetul în stil git
     let mut result = String::new();
     result.push_str(&format!("index {}..{} 100644\n", hash1_short, hash2_short));
-    result.push_str(&format!("--- a/{}\n", file1_name));
-    result.push_str(&format!("+++ b/{}\n", file2_name));
+    result.push_str(&format!("--- {}\n", header_path("a/", &file1_name)));
+    result.push_str(&format!("+++ {}\n", header_path("b/", &file2_name)));
     result.push_str(&diff_content);
     
     Ok(result)
@@ -109,10 +110,7 @@ pub fn diff_with_database(
     let db_content = blob.to_bytes();
     
     // Verifică dacă conținutul este binar
-    let working_is_binary = myers::is_binary_content(&working_content);
-    let db_is_binary = myers::is_binary_content(&db_content);
-    
-    if working_is_binary || db_is_binary {
+    if workspace.attributes().is_binary(file_path, &[&working_content, &db_content]) {
         return Ok(format!("Binary files differ"));
     }
     
@@ -123,6 +121,8 @@ pub fn diff_with_database(
     
     // Calculează hash-ul pentru conținutul fișierului de lucru
     let working_hash = database.hash_file_data(&working_content);
+    let working_content = workspace.attributes().textconv(file_path, working_content)?;
+    let db_content = workspace.attributes().textconv(file_path, db_content)?;
     
     // Convertește conținutul în text și calculează diff-ul
     let diff_content = match (String::from_utf8(working_content.to_vec()), String::from_utf8(db_content.to_vec())) {
@@ -163,8 +163,8 @@ pub fn diff_with_database(
     // Creează antetul în stil git
     let mut result = String::new();
     result.push_str(&format!("index {}..{} 100644\n", db_hash_short, working_hash_short));
-    result.push_str(&format!("--- a/{}\n", path_str));
-    result.push_str(&format!("+++ b/{}\n", path_str));
+    result.push_str(&format!("--- {}\n", header_path("a/", &path_str)));
+    result.push_str(&format!("+++ {}\n", header_path("b/", &path_str)));
     result.push_str(&diff_content);
     
     Ok(result)
@@ -192,7 +192,7 @@ pub fn incremental_diff_with_database(
     let db_content = blob.to_bytes();
     
     // Verifică dacă conținutul este binar
-    if myers::is_binary_content(&working_content) || myers::is_binary_content(&db_content) {
+    if workspace.attributes().is_binary(file_path, &[&working_content, &db_content]) {
         return Ok(format!("Binary files differ"));
     }
     
@@ -248,8 +248,8 @@ pub fn incremental_diff_with_database(
         // Creează antetul în stil git
         let mut result = String::new();
         result.push_str(&format!("index {}..{} 100644\n", db_hash_short, working_hash_short));
-        result.push_str(&format!("--- a/{}\n", path_str));
-        result.push_str(&format!("+++ b/{}\n", path_str));
+        result.push_str(&format!("--- {}\n", header_path("a/", &path_str)));
+        result.push_str(&format!("+++ {}\n", header_path("b/", &path_str)));
         result.push_str(&diff_content);
         
         return Ok(result);
@@ -291,8 +291,8 @@ pub fn incremental_diff_with_database(
     // Creează antetul în stil git
     let mut result = String::new();
     result.push_str(&format!("index {}..{} 100644\n", db_hash_short, working_hash_short));
-    result.push_str(&format!("--- a/{}\n", path_str));
-    result.push_str(&format!("+++ b/{}\n", path_str));
+    result.push_str(&format!("--- {}\n", header_path("a/", &path_str)));
+    result.push_str(&format!("+++ {}\n", header_path("b/", &path_str)));
     result.push_str(&diff_content);
     
     Ok(result)
@@ -336,4 +336,10 @@ pub fn colorize_diff(diff_output: &str) -> String {
     }
     
     result
-}
\ No newline at end of file
+}
+
+/// `path` under `prefix` as a header line writes it, quoted as a whole when
+/// `core.quotePath` and the characters in it ask for that
+pub fn header_path(prefix: &str, path: &str) -> String {
+    path_encoding::quote(&format!("{}{}", prefix, path), path_encoding::quote_path())
+}
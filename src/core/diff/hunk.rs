@@ -0,0 +1,243 @@
+// src/core/diff/hunk.rs - hunk splitting and selective application of edits
+use super::myers::Edit;
+
+/// A contiguous block of changes between two versions of a file, together
+/// with the surrounding context lines used when displaying it.
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    /// Position of the first edit of the change block in the edit script
+    pub first_edit: usize,
+    /// Position after the last edit of the change block
+    pub last_edit: usize,
+    pub a_start: usize,
+    pub a_len: usize,
+    pub b_start: usize,
+    pub b_len: usize,
+    /// Display lines, each prefixed with ' ', '-' or '+'
+    pub lines: Vec<String>,
+}
+
+impl Hunk {
+    /// The `@@ -a,b +c,d @@` header line for this hunk
+    pub fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            if self.a_len == 0 { self.a_start } else { self.a_start + 1 },
+            self.a_len,
+            if self.b_len == 0 { self.b_start } else { self.b_start + 1 },
+            self.b_len
+        )
+    }
+}
+
+/// Split an edit script into one hunk per contiguous block of changes.
+///
+/// Unlike `format_diff`, nearby blocks are never merged, so every hunk can be
+/// accepted or rejected on its own.
+pub fn build_hunks(a: &[String], b: &[String], edits: &[Edit], context_lines: usize) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut i = 0;
+
+    while i < edits.len() {
+        if let Edit::Equal(_, _) = edits[i] {
+            i += 1;
+            continue;
+        }
+
+        let first_edit = i;
+        while i < edits.len() && !matches!(edits[i], Edit::Equal(_, _)) {
+            i += 1;
+        }
+        let last_edit = i;
+
+        // Positions in a and b at which the change block starts
+        let (a_pos, b_pos) = position_before(edits, first_edit);
+
+        let before = context_lines.min(a_pos).min(b_pos);
+        let mut after = 0;
+        while after < context_lines && last_edit + after < edits.len() {
+            if let Edit::Equal(_, _) = edits[last_edit + after] {
+                after += 1;
+            } else {
+                break;
+            }
+        }
+
+        let mut lines = Vec::new();
+        for line in &a[(a_pos - before)..a_pos] {
+            lines.push(format!(" {}", line));
+        }
+
+        let mut a_len = before;
+        let mut b_len = before;
+        for edit in &edits[first_edit..last_edit] {
+            match edit {
+                Edit::Delete(ai) => {
+                    lines.push(format!("-{}", a[*ai]));
+                    a_len += 1;
+                },
+                Edit::Insert(bi) => {
+                    lines.push(format!("+{}", b[*bi]));
+                    b_len += 1;
+                },
+                Edit::Equal(_, _) => {}
+            }
+        }
+
+        for edit in &edits[last_edit..last_edit + after] {
+            if let Edit::Equal(ai, _) = edit {
+                lines.push(format!(" {}", a[*ai]));
+                a_len += 1;
+                b_len += 1;
+            }
+        }
+
+        hunks.push(Hunk {
+            first_edit,
+            last_edit,
+            a_start: a_pos - before,
+            a_len,
+            b_start: b_pos - before,
+            b_len,
+            lines,
+        });
+    }
+
+    hunks
+}
+
+/// Split an edit script into the hunks of a unified diff.
+///
+/// Unlike `build_hunks`, blocks of changes whose context would meet or
+/// overlap share a hunk, as `diff -u` writes them.
+pub fn build_unified_hunks(a: &[String], b: &[String], edits: &[Edit], context_lines: usize) -> Vec<Hunk> {
+    let is_change = |edit: &Edit| !matches!(edit, Edit::Equal(_, _));
+    let mut hunks = Vec::new();
+    let mut i = 0;
+
+    while i < edits.len() {
+        if !is_change(&edits[i]) {
+            i += 1;
+            continue;
+        }
+
+        // Take in the next blocks while the unchanged lines between are
+        // no more than the context on both sides
+        let first_edit = i;
+        let mut last_edit = i;
+        loop {
+            while last_edit < edits.len() && is_change(&edits[last_edit]) {
+                last_edit += 1;
+            }
+            let mut gap = 0;
+            while last_edit + gap < edits.len() && !is_change(&edits[last_edit + gap]) {
+                gap += 1;
+            }
+            if last_edit + gap < edits.len() && gap <= 2 * context_lines {
+                last_edit += gap;
+            } else {
+                break;
+            }
+        }
+
+        let (a_pos, b_pos) = position_before(edits, first_edit);
+        let before = context_lines.min(a_pos).min(b_pos);
+        let mut after = 0;
+        while after < context_lines && last_edit + after < edits.len() && !is_change(&edits[last_edit + after]) {
+            after += 1;
+        }
+
+        let mut lines = Vec::new();
+        let (mut a_len, mut b_len) = (0, 0);
+        for edit in &edits[first_edit - before..last_edit + after] {
+            match edit {
+                Edit::Equal(ai, _) => {
+                    lines.push(format!(" {}", a[*ai]));
+                    a_len += 1;
+                    b_len += 1;
+                },
+                Edit::Delete(ai) => {
+                    lines.push(format!("-{}", a[*ai]));
+                    a_len += 1;
+                },
+                Edit::Insert(bi) => {
+                    lines.push(format!("+{}", b[*bi]));
+                    b_len += 1;
+                },
+            }
+        }
+
+        hunks.push(Hunk {
+            first_edit,
+            last_edit,
+            a_start: a_pos - before,
+            a_len,
+            b_start: b_pos - before,
+            b_len,
+            lines,
+        });
+        i = last_edit;
+    }
+
+    hunks
+}
+
+/// Produce the lines obtained by applying only the accepted hunks to `a`.
+///
+/// `accepted` is indexed like the result of `build_hunks`.
+pub fn apply_hunks(a: &[String], b: &[String], edits: &[Edit], hunks: &[Hunk], accepted: &[bool]) -> Vec<String> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()));
+    let mut hunk_idx = 0;
+
+    for (pos, edit) in edits.iter().enumerate() {
+        while hunk_idx < hunks.len() && pos >= hunks[hunk_idx].last_edit {
+            hunk_idx += 1;
+        }
+
+        let take = hunk_idx < hunks.len()
+            && pos >= hunks[hunk_idx].first_edit
+            && accepted.get(hunk_idx).copied().unwrap_or(false);
+
+        match edit {
+            Edit::Equal(ai, _) => result.push(a[*ai].clone()),
+            Edit::Delete(ai) => {
+                if !take {
+                    result.push(a[*ai].clone());
+                }
+            },
+            Edit::Insert(bi) => {
+                if take {
+                    result.push(b[*bi].clone());
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// Join lines back into file content, keeping a trailing newline if requested
+pub fn join_lines(lines: &[String], trailing_newline: bool) -> String {
+    let mut content = lines.join("\n");
+    if trailing_newline && !lines.is_empty() {
+        content.push('\n');
+    }
+    content
+}
+
+// Number of lines of a and b consumed before the given edit
+fn position_before(edits: &[Edit], index: usize) -> (usize, usize) {
+    let mut a_pos = 0;
+    let mut b_pos = 0;
+    for edit in &edits[..index] {
+        match edit {
+            Edit::Equal(_, _) => {
+                a_pos += 1;
+                b_pos += 1;
+            },
+            Edit::Delete(_) => a_pos += 1,
+            Edit::Insert(_) => b_pos += 1,
+        }
+    }
+    (a_pos, b_pos)
+}
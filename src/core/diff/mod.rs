@@ -1,2 +1,9 @@
 pub mod myers;
-pub mod diff;
\ No newline at end of file
+pub mod diff;
+pub mod hunk;
+pub mod combined;
+pub mod stat;
+pub mod submodule;
+pub mod rename;
+pub mod patch;
+pub mod patch_id;
@@ -0,0 +1,382 @@
+// src/core/diff/patch.rs - reading unified diffs back and applying them
+//
+// A patch is read as `ash diff` and `ash format-patch` write it, and as git
+// and `diff -u` do: for each file an optional `diff --ash` (or `--git`) line
+// and extended headers for modes, renames and abbreviated blob ids, then the
+// `---`/`+++` names and the hunks. Whatever is around the diffs, such as the
+// headers of a mail or a diffstat, is skipped.
+use regex::Regex;
+
+use crate::errors::error::Error;
+
+pub const DEV_NULL: &str = "/dev/null";
+const REGULAR_MODE: &str = "100644";
+
+/// One hunk of a patch: where it goes in the old and new versions of the
+/// file, and its lines, each prefixed with ' ', '-' or '+' and ending with
+/// a newline unless the file does not
+#[derive(Debug, Clone)]
+pub struct PatchHunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<String>,
+}
+
+/// The changes a patch makes to one file
+#[derive(Debug, Clone, Default)]
+pub struct FilePatch {
+    /// The path before the change, None when the file is created
+    pub old_path: Option<String>,
+    /// The path after the change, None when the file is deleted
+    pub new_path: Option<String>,
+    pub old_mode: Option<String>,
+    pub new_mode: Option<String>,
+    /// The abbreviated blob ids of the `index` line, if there is one
+    pub old_oid: Option<String>,
+    pub new_oid: Option<String>,
+    /// Only says that binary contents differ, so cannot be applied
+    pub binary: bool,
+    pub hunks: Vec<PatchHunk>,
+}
+
+/// Read the file patches in `text`, taking `strip` leading components off
+/// every path, as `-p<n>` does
+pub fn parse(text: &str, strip: usize) -> Result<Vec<FilePatch>, Error> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let mut patches = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = header(lines[i]);
+        let extended = line.starts_with("diff --ash ") || line.starts_with("diff --git ");
+        let mut patch = if let Some(names) = line.strip_prefix("diff --ash ").or_else(|| line.strip_prefix("diff --git ")) {
+            let (old, new) = split_names(names, strip);
+            i += 1;
+            let mut patch = FilePatch { old_path: Some(old), new_path: Some(new), ..FilePatch::default() };
+            while i < lines.len() && extended_header(&mut patch, header(lines[i])) {
+                i += 1;
+            }
+            patch
+        } else if line.starts_with("--- ") && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ ")) {
+            FilePatch::default()
+        } else {
+            i += 1;
+            continue;
+        };
+
+        if i + 1 < lines.len() && lines[i].starts_with("--- ") && lines[i + 1].starts_with("+++ ") {
+            patch.old_path = file_name(&header(lines[i])[4..], strip);
+            patch.new_path = file_name(&header(lines[i + 1])[4..], strip);
+            i += 2;
+            // Without the headers of a diff --ash, two names are one file
+            // under two names, such as f.orig and f; the shorter is taken
+            if let (false, Some(old), Some(new)) = (extended, &patch.old_path, &patch.new_path) {
+                let name = if new.len() < old.len() { new.clone() } else { old.clone() };
+                patch.old_path = Some(name.clone());
+                patch.new_path = Some(name);
+            }
+        }
+        if patch.old_path.is_none() && patch.new_path.is_none() {
+            return Err(Error::Generic(format!("patch with no file names at line {}", i)));
+        }
+        while i < lines.len() && lines[i].starts_with("@@ ") {
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            patch.hunks.push(hunk);
+            i = next;
+        }
+        patches.push(patch);
+    }
+    Ok(patches)
+}
+
+// A line of a header without its line ending
+fn header(line: &str) -> &str {
+    line.trim_end_matches(['\n', '\r'])
+}
+
+// Take in an extended header line of the patch; false when the line is not
+// one, and the headers are over
+fn extended_header(patch: &mut FilePatch, line: &str) -> bool {
+    if let Some(mode) = line.strip_prefix("new file mode ") {
+        patch.old_path = None;
+        patch.new_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("deleted file mode ") {
+        patch.new_path = None;
+        patch.old_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("old mode ") {
+        patch.old_mode = Some(mode.to_string());
+    } else if let Some(mode) = line.strip_prefix("new mode ") {
+        patch.new_mode = Some(mode.to_string());
+    } else if let Some(ids) = line.strip_prefix("index ") {
+        let mut parts = ids.split(' ');
+        if let Some((old, new)) = parts.next().and_then(|range| range.split_once("..")) {
+            patch.old_oid = Some(old.to_string()).filter(|oid| oid.chars().any(|c| c != '0'));
+            patch.new_oid = Some(new.to_string()).filter(|oid| oid.chars().any(|c| c != '0'));
+        }
+        if let Some(mode) = parts.next() {
+            patch.old_mode = Some(mode.to_string());
+            patch.new_mode = Some(mode.to_string());
+        }
+    } else if let Some(name) = line.strip_prefix("rename from ").or_else(|| line.strip_prefix("copy from ")) {
+        patch.old_path = Some(name.to_string());
+    } else if let Some(name) = line.strip_prefix("rename to ").or_else(|| line.strip_prefix("copy to ")) {
+        patch.new_path = Some(name.to_string());
+    } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+        patch.binary = true;
+    } else {
+        // Renames and copies are reported with how similar the files are
+        return line.starts_with("similarity index ") || line.starts_with("dissimilarity index ");
+    }
+    true
+}
+
+// The two names on a `diff --ash` line. They are the same unless the file
+// was renamed, and then the rename headers give them, so the split that
+// makes them equal is the one wanted.
+fn split_names(names: &str, strip: usize) -> (String, String) {
+    for (at, _) in names.match_indices(' ') {
+        let (old, new) = (strip_components(&names[..at], strip), strip_components(&names[at + 1..], strip));
+        if old == new {
+            return (old, new);
+        }
+    }
+    match names.split_once(" b/").or_else(|| names.split_once(' ')) {
+        Some((old, new)) if names.contains(" b/") => (strip_components(old, strip), strip_components(&format!("b/{}", new), strip)),
+        Some((old, new)) => (strip_components(old, strip), strip_components(new, strip)),
+        None => (strip_components(names, strip), strip_components(names, strip)),
+    }
+}
+
+// The name on a `---` or `+++` line, None for /dev/null. A tab starts the
+// timestamp `diff -u` puts after it.
+fn file_name(name: &str, strip: usize) -> Option<String> {
+    let name = name.split('\t').next().unwrap_or_default().trim_end();
+    if name == DEV_NULL {
+        None
+    } else {
+        Some(strip_components(name, strip))
+    }
+}
+
+fn strip_components(name: &str, strip: usize) -> String {
+    let parts: Vec<&str> = name.split('/').collect();
+    if parts.len() > strip {
+        parts[strip..].join("/")
+    } else {
+        name.to_string()
+    }
+}
+
+// The hunk whose header is at `lines[start]`, and the line after it
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(PatchHunk, usize), Error> {
+    let re = Regex::new(r"^@@ -(\d+)(?:,(\d+))? \+(\d+)(?:,(\d+))? @@").unwrap();
+    let caps = re.captures(lines[start])
+        .ok_or_else(|| Error::Generic(format!("corrupt patch at line {}", start + 1)))?;
+    let number = |i: usize| caps.get(i).map_or(Ok(1), |m| m.as_str().parse::<usize>())
+        .map_err(|_| Error::Generic(format!("corrupt patch at line {}", start + 1)));
+    let mut hunk = PatchHunk {
+        old_start: number(1)?,
+        old_len: number(2)?,
+        new_start: number(3)?,
+        new_len: number(4)?,
+        lines: Vec::new(),
+    };
+
+    let (mut old_seen, mut new_seen) = (0, 0);
+    let mut i = start + 1;
+    while i < lines.len() && (old_seen < hunk.old_len || new_seen < hunk.new_len) {
+        let line = lines[i];
+        match line.chars().next() {
+            Some(' ') => {
+                old_seen += 1;
+                new_seen += 1;
+            },
+            Some('-') => old_seen += 1,
+            Some('+') => new_seen += 1,
+            Some('\\') => {},
+            // An empty context line whose space got lost on the way
+            Some('\n') | Some('\r') => {
+                old_seen += 1;
+                new_seen += 1;
+                hunk.lines.push(format!(" {}", line));
+                i += 1;
+                continue;
+            },
+            _ => return Err(Error::Generic(format!("corrupt patch at line {}", i + 1))),
+        }
+        if line.starts_with('\\') {
+            no_newline(&mut hunk);
+        } else {
+            hunk.lines.push(line.to_string());
+        }
+        i += 1;
+    }
+    if old_seen < hunk.old_len || new_seen < hunk.new_len {
+        return Err(Error::Generic(format!("corrupt patch at line {}", i)));
+    }
+    if i < lines.len() && lines[i].starts_with('\\') {
+        no_newline(&mut hunk);
+        i += 1;
+    }
+    Ok((hunk, i))
+}
+
+// The line before a `\ No newline at end of file` marker has none
+fn no_newline(hunk: &mut PatchHunk) {
+    if let Some(last) = hunk.lines.last_mut() {
+        if last.ends_with('\n') {
+            last.pop();
+            if last.ends_with('\r') {
+                last.pop();
+            }
+        }
+    }
+}
+
+impl FilePatch {
+    /// The path the patch is reported under: the new one, or the old one
+    /// for a deletion
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+
+    pub fn is_creation(&self) -> bool {
+        self.old_path.is_none()
+    }
+
+    pub fn is_deletion(&self) -> bool {
+        self.new_path.is_none()
+    }
+
+    pub fn is_rename(&self) -> bool {
+        matches!((&self.old_path, &self.new_path), (Some(old), Some(new)) if old != new)
+    }
+
+    /// The mode the file has after the change
+    pub fn mode(&self) -> &str {
+        self.new_mode.as_deref().or(self.old_mode.as_deref()).unwrap_or(REGULAR_MODE)
+    }
+
+    /// The patch that undoes this one
+    pub fn reverse(&self) -> FilePatch {
+        let hunks = self.hunks.iter()
+            .map(|hunk| PatchHunk {
+                old_start: hunk.new_start,
+                old_len: hunk.new_len,
+                new_start: hunk.old_start,
+                new_len: hunk.old_len,
+                lines: hunk.lines.iter()
+                    .map(|line| match line.strip_prefix('-') {
+                        Some(rest) => format!("+{}", rest),
+                        None => match line.strip_prefix('+') {
+                            Some(rest) => format!("-{}", rest),
+                            None => line.clone(),
+                        },
+                    })
+                    .collect(),
+            })
+            .collect();
+        FilePatch {
+            old_path: self.new_path.clone(),
+            new_path: self.old_path.clone(),
+            old_mode: self.new_mode.clone(),
+            new_mode: self.old_mode.clone(),
+            old_oid: self.new_oid.clone(),
+            new_oid: self.old_oid.clone(),
+            binary: self.binary,
+            hunks,
+        }
+    }
+
+    /// Apply the hunks to `content`, the old version of the file. A hunk
+    /// is looked for at the line it names and then further and further
+    /// away from it, past where the one before it went. Its context must
+    /// match in full, unless `min_context` allows that many lines of it,
+    /// fewer if it has fewer, to be given up on either side.
+    pub fn apply(&self, content: &str, min_context: Option<usize>) -> Result<String, Error> {
+        if self.binary {
+            return Err(Error::Generic(format!("cannot apply binary patch to '{}' without full index line", self.path())));
+        }
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let mut output = String::new();
+        let mut cursor = 0;
+        let mut offset: isize = 0;
+
+        for hunk in &self.hunks {
+            let (position, expected, kept) = Self::locate(&lines, hunk, cursor, offset, min_context)
+                .ok_or_else(|| Error::Generic(format!("patch failed: {}:{}", self.path(), hunk.old_start)))?;
+            output.extend(lines[cursor..position].iter().copied());
+            let mut old_lines = 0;
+            for line in kept {
+                match line.split_at(1) {
+                    (" ", text) => {
+                        output.push_str(text);
+                        old_lines += 1;
+                    },
+                    ("+", text) => output.push_str(text),
+                    _ => old_lines += 1,
+                }
+            }
+            cursor = position + old_lines;
+            offset = position as isize - expected as isize;
+        }
+        output.extend(lines[cursor..].iter().copied());
+        Ok(output)
+    }
+
+    // Where in `lines` the hunk goes, where it was expected before any
+    // offset, and the lines of it that are kept once context is given up
+    fn locate<'h>(
+        lines: &[&str],
+        hunk: &'h PatchHunk,
+        cursor: usize,
+        offset: isize,
+        min_context: Option<usize>,
+    ) -> Option<(usize, usize, &'h [String])> {
+        let leading = hunk.lines.iter().take_while(|line| line.starts_with(' ')).count();
+        let trailing = hunk.lines.iter().rev().take_while(|line| line.starts_with(' ')).count()
+            .min(hunk.lines.len() - leading);
+        let most = match min_context {
+            Some(_) => leading.max(trailing),
+            None => 0,
+        };
+
+        for fuzz in 0..=most {
+            let front = fuzz.min(leading);
+            let back = fuzz.min(trailing);
+            if let Some(min_context) = min_context {
+                if leading - front < min_context.min(leading) || trailing - back < min_context.min(trailing) {
+                    break;
+                }
+            }
+            let kept = &hunk.lines[front..hunk.lines.len() - back];
+            let old: Vec<&str> = kept.iter()
+                .filter(|line| !line.starts_with('+'))
+                .map(|line| &line[1..])
+                .collect();
+
+            let start = if hunk.old_len == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+            let expected = start + front;
+            let wanted = (expected as isize + offset).max(0) as usize;
+            let last = match lines.len().checked_sub(old.len()) {
+                Some(last) if last >= cursor => last,
+                _ => continue,
+            };
+            let matches = |at: usize| lines[at..at + old.len()] == old[..];
+            let found = (0..=last.max(wanted) - cursor.min(wanted)).find_map(|distance| {
+                let after = wanted + distance;
+                if after >= cursor && after <= last && matches(after) {
+                    return Some(after);
+                }
+                let before = wanted.checked_sub(distance)?;
+                (before >= cursor && before <= last && matches(before)).then_some(before)
+            });
+            if let Some(position) = found {
+                return Some((position, expected, kept));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,65 @@
+// src/core/diff/patch_id.rs - recognizing the same change made twice
+//
+// Two commits have the same patch ID when they change the same files in
+// the same way, whatever they were made on. As with git, line numbers and
+// whitespace are left out of the hash, so that a change picked or applied
+// elsewhere, with its context moved about, is still recognized.
+use std::path::PathBuf;
+
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::diff::myers::{self, Edit};
+use crate::core::path_filter::PathFilter;
+use crate::errors::error::Error;
+
+/// The patch ID of the changes between the trees of `parent`, nothing for
+/// a root commit, and `oid`, or None when there are none
+pub fn patch_id(database: &mut Database, parent: Option<&str>, oid: &str) -> Result<Option<String>, Error> {
+    let changes = database.tree_diff(parent, Some(oid), &PathFilter::new())?;
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    let mut paths: Vec<&PathBuf> = changes.keys().collect();
+    paths.sort();
+
+    let mut text = String::new();
+    for path in paths {
+        let (old, new) = &changes[path];
+        let mode = |entry: &Option<DatabaseEntry>| entry.as_ref().map_or_else(String::new, |entry| entry.get_mode().to_string());
+        text.push_str(&format!("{} {} {}\n", path.display(), mode(old), mode(new)));
+
+        let old_content = content(database, old.as_ref())?;
+        let new_content = content(database, new.as_ref())?;
+        if myers::is_binary_content(&old_content) || myers::is_binary_content(&new_content) {
+            let oid = |entry: &Option<DatabaseEntry>| entry.as_ref().map_or_else(String::new, |entry| entry.get_oid().to_string());
+            text.push_str(&format!("{} {}\n", oid(old), oid(new)));
+            continue;
+        }
+
+        let old_lines = lines(&old_content);
+        let new_lines = lines(&new_content);
+        for edit in myers::diff_lines(&old_lines, &new_lines) {
+            match edit {
+                Edit::Delete(i) => text.push_str(&format!("-{}\n", old_lines[i])),
+                Edit::Insert(j) => text.push_str(&format!("+{}\n", new_lines[j])),
+                Edit::Equal(..) => {},
+            }
+        }
+    }
+    Ok(Some(database.hash_content(text.as_bytes())))
+}
+
+// The lines of `content` with their whitespace taken out
+fn lines(content: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(content)
+        .lines()
+        .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+        .collect()
+}
+
+fn content(database: &mut Database, entry: Option<&DatabaseEntry>) -> Result<Vec<u8>, Error> {
+    match entry {
+        Some(entry) => Ok(database.load(entry.get_oid())?.to_bytes()),
+        None => Ok(Vec::new()),
+    }
+}
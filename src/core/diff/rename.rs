@@ -0,0 +1,239 @@
+// src/core/diff/rename.rs - pairing removed and added files that hold the
+// same content
+//
+// A change seen path by path is a set of files removed and a set added. A
+// removed file whose content turns up, much the same, under an added path
+// was renamed; an added file much like one that stays was copied. How alike
+// two files are is the share of the larger one made of lines they have in
+// common.
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::database::database::Database;
+use crate::errors::error::Error;
+
+const RENAMES_KEY: &str = "diff.renames";
+/// How alike two files must be, in percent, when no threshold is given
+pub const DEFAULT_THRESHOLD: u8 = 50;
+
+/// Which pairs `detect` looks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenameDetection {
+    pub renames: bool,
+    /// Added files copied from modified ones
+    pub copies: bool,
+    /// Added files copied from any file, changed or not
+    pub copies_harder: bool,
+    pub threshold: u8,
+}
+
+impl Default for RenameDetection {
+    fn default() -> Self {
+        RenameDetection { renames: true, copies: false, copies_harder: false, threshold: DEFAULT_THRESHOLD }
+    }
+}
+
+impl RenameDetection {
+    /// What `diff.renames` (true, false or copies) asks for, then each of
+    /// `options` in turn: `-M[<n>]` / `--find-renames[=<n>]`, `-C[<n>]` /
+    /// `--find-copies[=<n>]`, `--find-copies-harder` and `--no-renames`
+    pub fn resolve(options: &[String], git_path: &Path) -> Result<Self, Error> {
+        let mut detection = Self::default();
+        if let Some(value) = Config::load(git_path).ok().and_then(|config| config.get(RENAMES_KEY)) {
+            match value.as_str() {
+                "true" | "yes" | "on" | "1" => detection.renames = true,
+                "false" | "no" | "off" | "0" => detection.renames = false,
+                "copies" | "copy" => {
+                    detection.renames = true;
+                    detection.copies = true;
+                },
+                _ => return Err(Error::Generic(format!("bad config value '{}' for '{}'", value, RENAMES_KEY))),
+            }
+        }
+
+        for option in options {
+            if option == "--no-renames" {
+                detection = RenameDetection { renames: false, copies: false, copies_harder: false, ..detection };
+            } else if option == "--find-copies-harder" {
+                detection.renames = true;
+                detection.copies = true;
+                detection.copies_harder = true;
+            } else if let Some(threshold) = option.strip_prefix("--find-renames").or_else(|| option.strip_prefix("-M")) {
+                detection.renames = true;
+                detection.threshold = Self::parse_threshold(option, threshold.trim_start_matches('='), detection.threshold)?;
+            } else if let Some(threshold) = option.strip_prefix("--find-copies").or_else(|| option.strip_prefix("-C")) {
+                detection.renames = true;
+                detection.copies = true;
+                detection.threshold = Self::parse_threshold(option, threshold.trim_start_matches('='), detection.threshold)?;
+            }
+        }
+        Ok(detection)
+    }
+
+    // "" keeps the threshold, "70" or "70%" is a percentage and "7" is read
+    // as the fraction 0.7, as -M7 is
+    fn parse_threshold(option: &str, value: &str, current: u8) -> Result<u8, Error> {
+        if value.is_empty() {
+            return Ok(current);
+        }
+        let invalid = || Error::Generic(format!("invalid similarity threshold in '{}'", option));
+        let (digits, percent) = match value.strip_suffix('%') {
+            Some(digits) => (digits, true),
+            None => (value, false),
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let threshold = if percent {
+            digits.parse::<u32>().map_err(|_| invalid())?
+        } else {
+            // The digits are what follows "0." in a fraction
+            let scaled: f64 = format!("0.{}", digits).parse().map_err(|_| invalid())?;
+            (scaled * 100.0).round() as u32
+        };
+        if threshold > 100 {
+            return Err(invalid());
+        }
+        Ok(threshold as u8)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairKind {
+    Rename,
+    Copy,
+}
+
+/// An added file matched with the file it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pair {
+    pub kind: PairKind,
+    pub from: String,
+    pub to: String,
+    /// How alike the two are, in percent
+    pub similarity: u8,
+}
+
+/// The renames and copies among files `removed` and `added`, each given as
+/// path and blob id. Copies are looked for in `unchanged` too; callers pass
+/// the modified files there for `copies` and every kept file for
+/// `copies_harder`. Every removed and added file is in one pair at most,
+/// except that a removed file may also be the source of copies; the most
+/// alike files are paired first.
+pub fn detect(
+    database: &mut Database,
+    removed: &[(String, String)],
+    added: &[(String, String)],
+    unchanged: &[(String, String)],
+    detection: RenameDetection,
+) -> Result<Vec<Pair>, Error> {
+    let mut pairs = Vec::new();
+    if !detection.renames || added.is_empty() {
+        return Ok(pairs);
+    }
+    let mut taken_from: Vec<bool> = vec![false; removed.len()];
+    let mut taken_to: Vec<bool> = vec![false; added.len()];
+
+    // Files stored as the same blob are renames whatever the threshold
+    for (to_index, (to, to_oid)) in added.iter().enumerate() {
+        let from_index = removed.iter().enumerate()
+            .position(|(i, (_, from_oid))| !taken_from[i] && from_oid == to_oid);
+        if let Some(from_index) = from_index {
+            taken_from[from_index] = true;
+            taken_to[to_index] = true;
+            pairs.push(Pair { kind: PairKind::Rename, from: removed[from_index].0.clone(), to: to.clone(), similarity: 100 });
+        }
+    }
+
+    let mut contents: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut load = |database: &mut Database, oid: &str| -> Result<Vec<u8>, Error> {
+        if let Some(content) = contents.get(oid) {
+            return Ok(content.clone());
+        }
+        let content = database.load(oid)?.to_bytes();
+        contents.insert(oid.to_string(), content.clone());
+        Ok(content)
+    };
+
+    // Then the most alike of what is left, above the threshold
+    let mut candidates: Vec<(u8, usize, usize)> = Vec::new();
+    for (to_index, (_, to_oid)) in added.iter().enumerate() {
+        if taken_to[to_index] {
+            continue;
+        }
+        let to_content = load(database, to_oid)?;
+        for (from_index, (_, from_oid)) in removed.iter().enumerate() {
+            if taken_from[from_index] {
+                continue;
+            }
+            let score = similarity(&load(database, from_oid)?, &to_content);
+            if score >= detection.threshold {
+                candidates.push((score, from_index, to_index));
+            }
+        }
+    }
+    // Best first; the sort is stable, so ties keep the order of the paths
+    candidates.sort_by_key(|(score, _, _)| Reverse(*score));
+    for (score, from_index, to_index) in candidates {
+        if taken_from[from_index] || taken_to[to_index] {
+            continue;
+        }
+        taken_from[from_index] = true;
+        taken_to[to_index] = true;
+        pairs.push(Pair { kind: PairKind::Rename, from: removed[from_index].0.clone(), to: added[to_index].0.clone(), similarity: score });
+    }
+
+    if detection.copies {
+        // A file can be copied from anything that was there before,
+        // including one renamed or removed in the same change
+        let sources: Vec<&(String, String)> = unchanged.iter().chain(removed.iter()).collect();
+        for (to_index, (to, to_oid)) in added.iter().enumerate() {
+            if taken_to[to_index] {
+                continue;
+            }
+            let to_content = load(database, to_oid)?;
+            let mut best: Option<(u8, &String)> = None;
+            for (from, from_oid) in &sources {
+                let score = if from_oid == to_oid { 100 } else { similarity(&load(database, from_oid)?, &to_content) };
+                if score >= detection.threshold && best.is_none_or(|(best_score, _)| score > best_score) {
+                    best = Some((score, from));
+                }
+            }
+            if let Some((score, from)) = best {
+                taken_to[to_index] = true;
+                pairs.push(Pair { kind: PairKind::Copy, from: from.clone(), to: to.clone(), similarity: score });
+            }
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// How alike `a` and `b` are, in percent: the bytes of the lines found in
+/// both (each line counted as often as it is in both) over the size of the
+/// larger. Empty files are alike to nothing, not even each other.
+pub fn similarity(a: &[u8], b: &[u8]) -> u8 {
+    let larger = a.len().max(b.len());
+    if a.is_empty() || b.is_empty() {
+        return 0;
+    }
+    if a == b {
+        return 100;
+    }
+
+    let mut lines: HashMap<&[u8], usize> = HashMap::new();
+    for line in a.split_inclusive(|&byte| byte == b'\n') {
+        *lines.entry(line).or_insert(0) += 1;
+    }
+    let mut common = 0;
+    for line in b.split_inclusive(|&byte| byte == b'\n') {
+        if let Some(count) = lines.get_mut(line).filter(|count| **count > 0) {
+            *count -= 1;
+            common += line.len();
+        }
+    }
+    // Only equal files are alike in full
+    ((common * 100 / larger) as u8).min(99)
+}
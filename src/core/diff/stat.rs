@@ -0,0 +1,117 @@
+// src/core/diff/stat.rs - how many lines each changed file gained and lost
+use std::path::PathBuf;
+
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::path_filter::PathFilter;
+use crate::errors::error::Error;
+use super::diff::split_lines;
+use super::myers::{self, Edit};
+
+const GITLINK_MODE: &str = "160000";
+// Widest the +/- graph of a file may get
+const GRAPH_WIDTH: usize = 40;
+
+/// The change to one file
+#[derive(Debug, Clone)]
+pub struct FileStat {
+    pub path: String,
+    pub added: usize,
+    pub removed: usize,
+    // Sizes before and after, for binary files, which have no lines
+    pub binary: Option<(usize, usize)>,
+}
+
+/// Count the lines added and removed per file between two trees or
+/// commits, sorted by path, for the files `filter` lets through
+pub fn stat_trees(database: &mut Database, old: Option<&str>, new: Option<&str>, filter: &PathFilter) -> Result<Vec<FileStat>, Error> {
+    let changes = database.tree_diff(old, new, filter)?;
+    let mut paths: Vec<&PathBuf> = changes.keys().collect();
+    paths.sort();
+
+    let mut stats = Vec::with_capacity(paths.len());
+    for path in paths {
+        let (old_entry, new_entry) = &changes[path];
+        let old_data = content(database, old_entry.as_ref())?;
+        let new_data = content(database, new_entry.as_ref())?;
+        let path = path.to_string_lossy().to_string();
+
+        if myers::is_binary_content(&old_data) || myers::is_binary_content(&new_data) {
+            stats.push(FileStat { path, added: 0, removed: 0, binary: Some((old_data.len(), new_data.len())) });
+            continue;
+        }
+
+        let old_lines = split_lines(&String::from_utf8_lossy(&old_data));
+        let new_lines = split_lines(&String::from_utf8_lossy(&new_data));
+        let edits = myers::diff_lines(&old_lines, &new_lines);
+        stats.push(FileStat {
+            path,
+            added: edits.iter().filter(|edit| matches!(edit, Edit::Insert(_))).count(),
+            removed: edits.iter().filter(|edit| matches!(edit, Edit::Delete(_))).count(),
+            binary: None,
+        });
+    }
+    Ok(stats)
+}
+
+/// The summary `diff --stat` prints: a line per file with its number of
+/// changed lines and a graph of them, scaled down when the largest change
+/// would not fit, then the totals
+pub fn format_stat(stats: &[FileStat]) -> String {
+    let name_width = stats.iter().map(|stat| stat.path.chars().count()).max().unwrap_or(0);
+    let most = stats.iter().map(|stat| stat.added + stat.removed).max().unwrap_or(0);
+    let count_width = most.to_string().len();
+    let scale = |n: usize| if most > GRAPH_WIDTH {
+        // Never scale a change away entirely
+        (n * GRAPH_WIDTH).div_ceil(most)
+    } else {
+        n
+    };
+
+    let mut output = String::new();
+    for stat in stats {
+        match stat.binary {
+            Some((before, after)) => output.push_str(&format!(
+                " {:<name_width$} | Bin {} -> {} bytes\n", stat.path, before, after
+            )),
+            None => output.push_str(&format!(
+                " {:<name_width$} | {:>count_width$} {}{}\n",
+                stat.path,
+                stat.added + stat.removed,
+                "+".repeat(scale(stat.added)),
+                "-".repeat(scale(stat.removed))
+            )),
+        }
+    }
+    output.push_str(&summary_line(stats));
+    output.push('\n');
+    output
+}
+
+/// "N files changed, X insertions(+), Y deletions(-)", leaving out a kind
+/// of change that did not happen
+pub fn summary_line(stats: &[FileStat]) -> String {
+    let added: usize = stats.iter().map(|stat| stat.added).sum();
+    let removed: usize = stats.iter().map(|stat| stat.removed).sum();
+    let plural = |n: usize, one: &str, many: &str| format!("{} {}", n, if n == 1 { one } else { many });
+
+    let mut line = format!(" {} changed", plural(stats.len(), "file", "files"));
+    if added > 0 || removed == 0 {
+        line.push_str(&format!(", {}", plural(added, "insertion(+)", "insertions(+)")));
+    }
+    if removed > 0 || added == 0 {
+        line.push_str(&format!(", {}", plural(removed, "deletion(-)", "deletions(-)")));
+    }
+    line
+}
+
+// What a side of a change holds; a submodule is the commit it points at
+fn content(database: &mut Database, entry: Option<&DatabaseEntry>) -> Result<Vec<u8>, Error> {
+    match entry {
+        None => Ok(Vec::new()),
+        Some(entry) if entry.get_mode() == GITLINK_MODE => {
+            Ok(format!("Subproject commit {}\n", entry.get_oid()).into_bytes())
+        },
+        Some(entry) => Ok(database.load(entry.get_oid())?.to_bytes()),
+    }
+}
@@ -0,0 +1,141 @@
+// src/core/diff/submodule.rs - describing a change to a submodule
+//
+// A submodule is recorded in the superproject as a gitlink: a tree entry
+// naming a commit of another repository. When that repository is checked
+// out next to the superproject (or kept under `.ash/modules/<path>`), its
+// objects are there to say which commits the change brings in or takes out.
+use std::path::{Path, PathBuf};
+
+use crate::core::config::Config;
+use crate::core::database::database::Database;
+use crate::core::database::commit::Commit;
+use crate::core::refs::Refs;
+use crate::core::repository::git_dir;
+use crate::core::revlist::RevList;
+use crate::errors::error::Error;
+
+pub const GITLINK_MODE: &str = "160000";
+const MODULES_DIR: &str = "modules";
+const SUBMODULE_KEY: &str = "diff.submodule";
+
+/// How `diff` shows a submodule that points at another commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubmoduleFormat {
+    /// As the two commit ids, like the content of a one-line file
+    #[default]
+    Short,
+    /// As the titles of the commits added and removed
+    Log,
+}
+
+impl SubmoduleFormat {
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        match value {
+            "short" => Ok(SubmoduleFormat::Short),
+            "log" => Ok(SubmoduleFormat::Log),
+            _ => Err(Error::Generic(format!("failed to parse --submodule option parameter: '{}'", value))),
+        }
+    }
+
+    /// `option` if given, otherwise `diff.submodule`
+    pub fn resolve(option: Option<&str>, git_path: &Path) -> Result<Self, Error> {
+        match option.map(str::to_string).or_else(|| Config::load(git_path).ok()?.get(SUBMODULE_KEY)) {
+            Some(value) => Self::parse(&value),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// The metadata directory of the submodule at `path` of the worktree at
+/// `root_path`: the checked out one, or the one kept in the superproject's
+pub fn repository(root_path: &Path, path: &str) -> Option<PathBuf> {
+    git_dir::find(&root_path.join(path)).or_else(|| {
+        let kept = git_dir::resolve(root_path).join(MODULES_DIR).join(path);
+        kept.is_dir().then_some(kept)
+    })
+}
+
+/// The commit the submodule at `path` has checked out, if it is there
+pub fn checked_out(root_path: &Path, path: &str) -> Option<String> {
+    let git_path = git_dir::find(&root_path.join(path))?;
+    Refs::new(&git_path).read_head().ok().flatten()
+}
+
+/// Describe the submodule at `path` moving from `old` to `new` (None when it
+/// is added or removed) the way `format` asks for
+pub fn describe(root_path: &Path, path: &str, old: Option<&str>, new: Option<&str>, format: SubmoduleFormat) -> String {
+    match format {
+        SubmoduleFormat::Short => short(path, old, new),
+        SubmoduleFormat::Log => log(root_path, path, old, new),
+    }
+}
+
+fn short(path: &str, old: Option<&str>, new: Option<&str>) -> String {
+    let abbrev = |oid: Option<&str>| oid.map_or("0000000".to_string(), |oid| oid[..7.min(oid.len())].to_string());
+    let mut output = format!("diff --ash a/{} b/{}\n", path, path);
+    output.push_str(&format!("index {}..{} {}\n", abbrev(old), abbrev(new), GITLINK_MODE));
+    output.push_str(&format!("--- {}\n", old.map_or("/dev/null".to_string(), |_| format!("a/{}", path))));
+    output.push_str(&format!("+++ {}\n", new.map_or("/dev/null".to_string(), |_| format!("b/{}", path))));
+    match (old, new) {
+        (Some(old), Some(new)) => output.push_str(&format!("@@ -1 +1 @@\n-Subproject commit {}\n+Subproject commit {}\n", old, new)),
+        (Some(old), None) => output.push_str(&format!("@@ -1 +0,0 @@\n-Subproject commit {}\n", old)),
+        (None, Some(new)) => output.push_str(&format!("@@ -0,0 +1 @@\n+Subproject commit {}\n", new)),
+        (None, None) => {},
+    }
+    output
+}
+
+// "Submodule <path> <old>..<new>:" and a line per commit, `>` for those
+// only `new` has and `<` for those only `old` has
+fn log(root_path: &Path, path: &str, old: Option<&str>, new: Option<&str>) -> String {
+    let abbrev = |oid: Option<&str>| oid.map_or("0000000".to_string(), |oid| oid[..7.min(oid.len())].to_string());
+    let range = |separator: &str| format!("{}{}{}", abbrev(old), separator, abbrev(new));
+    match (old, new) {
+        (None, _) => return format!("Submodule {} {} (new submodule)\n", path, range("...")),
+        (_, None) => return format!("Submodule {} {} (submodule deleted)\n", path, range("...")),
+        _ => {},
+    }
+    let (old, new) = (old.unwrap_or_default(), new.unwrap_or_default());
+
+    let git_path = match repository(root_path, path) {
+        Some(git_path) => git_path,
+        None => return format!("Submodule {} {} (not checked out)\n", path, range("...")),
+    };
+    let mut database = Database::new(git_dir::objects(&git_path));
+    if !database.exists(old) || !database.exists(new) {
+        return format!("Submodule {} {} (commits not present)\n", path, range("..."));
+    }
+    let refs = Refs::new(&git_path);
+
+    let added = titles(&mut database, &refs, new, old);
+    let removed = titles(&mut database, &refs, old, new);
+    let (added, removed) = match (added, removed) {
+        (Ok(added), Ok(removed)) => (added, removed),
+        _ => return format!("Submodule {} {} (commits not present)\n", path, range("...")),
+    };
+
+    // Two dots when `new` only adds to `old`, three when they diverged
+    let mut output = if removed.is_empty() {
+        format!("Submodule {} {}:\n", path, range(".."))
+    } else {
+        format!("Submodule {} {}{}:\n", path, range("..."), if added.is_empty() { " (rewind)" } else { "" })
+    };
+    for title in removed {
+        output.push_str(&format!("  < {}\n", title));
+    }
+    for title in added {
+        output.push_str(&format!("  > {}\n", title));
+    }
+    output
+}
+
+// Titles of the commits reachable from `tip` but not from `exclude`
+fn titles(database: &mut Database, refs: &Refs, tip: &str, exclude: &str) -> Result<Vec<String>, Error> {
+    let mut revlist = RevList::new(database, refs, &[tip.to_string(), format!("^{}", exclude)], true)?;
+    let mut titles = Vec::new();
+    while let Some(commit) = revlist.next() {
+        let commit: Commit = commit?;
+        titles.push(commit.title_line().trim().to_string());
+    }
+    Ok(titles)
+}
@@ -1,19 +1,24 @@
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::errors::error::Error;
 
-const DEFAULT_EDITOR: &str = "vi";
+/// The editor run when no variable names one
+pub const DEFAULT_EDITOR: &str = "vi";
+
+/// Everything below this line is dropped from the edited message
+pub const SCISSORS: &str = "# ------------------------ >8 ------------------------";
 
 #[derive(Debug)]
 pub struct Editor {
     path: PathBuf,
     command: String,
     closed: bool,
-    file: File,
+    // Closed, and None, once the editor runs
+    file: Option<File>,
 }
 
 impl Editor {
@@ -29,7 +34,7 @@ impl Editor {
             path,
             command: command.unwrap_or_else(|| DEFAULT_EDITOR.to_owned()),
             closed: false,
-            file,
+            file: Some(file),
         })
     }
 
@@ -46,9 +51,10 @@ impl Editor {
         if self.closed {
             return Ok(());
         }
-        self.file.write_all(string.as_bytes())
+        let file = self.file()?;
+        file.write_all(string.as_bytes())
             .map_err(|e| Error::Generic(format!("Failed to write to file: {}", e)))?;
-        self.file.write_all(b"\n")
+        file.write_all(b"\n")
             .map_err(|e| Error::Generic(format!("Failed to write newline to file: {}", e)))?;
 
         Ok(())
@@ -58,21 +64,37 @@ impl Editor {
         if self.closed {
             return Ok(());
         }
+        let file = self.file()?;
         for line in string.lines() {
-            write!(self.file, "# {}\n", line)
+            writeln!(file, "# {}", line)
                 .map_err(|e| Error::Generic(format!("Failed to write note to file: {}", e)))?;
         }
 
         Ok(())
     }
 
+    pub fn scissors(&mut self) -> Result<(), Error> {
+        if self.closed {
+            return Ok(());
+        }
+        writeln!(self.file()?, "{}", SCISSORS)
+            .map_err(|e| Error::Generic(format!("Failed to write scissors line to file: {}", e)))?;
+        self.note("Do not modify or remove the line above.\nEverything below it will be ignored.")
+    }
+
+    /// The file being edited; what was written is on disk already, so a
+    /// hook may rewrite it before the editor runs
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn close(&mut self) {
         self.closed = true;
     }
 
     pub fn edit_file(&mut self) -> Result<Option<String>, Error> {
         // Close the file before launching the editor
-        drop(std::mem::replace(&mut self.file, unsafe { std::mem::zeroed() }));
+        drop(self.file.take());
 
         if self.closed {
             return Ok(None);
@@ -101,11 +123,18 @@ impl Editor {
         let content = fs::read_to_string(&self.path)
             .map_err(|e| Error::Generic(format!("Failed to read edited file: {}", e)))?;
 
-        Ok(self.remove_notes(content))
+        Ok(Self::remove_notes(&content))
+    }
+
+    fn file(&mut self) -> Result<&mut File, Error> {
+        self.file.as_mut().ok_or_else(|| Error::Generic(format!("{} is no longer open for writing", self.path.display())))
     }
 
-    fn remove_notes(&self, content: String) -> Option<String> {
+    /// The message in `content` without its notes and what follows the
+    /// scissors line, or None if nothing is left
+    pub fn remove_notes(content: &str) -> Option<String> {
         let lines: Vec<String> = content.lines()
+            .take_while(|line| *line != SCISSORS)
             .filter(|line| !line.starts_with('#'))
             .map(String::from)
             .collect();
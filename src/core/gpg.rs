@@ -0,0 +1,103 @@
+// src/core/gpg.rs - sign and verify objects with GnuPG
+//
+// A signed object carries an ASCII-armored detached signature of everything
+// else in it. For tags the signature ends the message, so the object
+// without it is what was signed.
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::core::config::Config;
+use crate::errors::error::Error;
+
+const PROGRAM_KEY: &str = "gpg.program";
+pub const SIGNING_KEY_KEY: &str = "user.signingKey";
+const DEFAULT_PROGRAM: &str = "gpg";
+const SIGNATURE_BEGIN: &str = "-----BEGIN PGP SIGNATURE-----";
+
+/// What GnuPG made of a signature
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub good: bool,
+    /// What GnuPG printed for people to read, naming who signed
+    pub output: String,
+}
+
+/// `message` without the signature at its end, and the signature
+pub fn split_signature(message: &str) -> (&str, Option<&str>) {
+    let start = if message.starts_with(SIGNATURE_BEGIN) {
+        Some(0)
+    } else {
+        message.find(&format!("\n{}", SIGNATURE_BEGIN)).map(|at| at + 1)
+    };
+    match start {
+        Some(start) => (&message[..start], Some(&message[start..])),
+        None => (message, None),
+    }
+}
+
+/// An armored detached signature of `payload` made with `key`, a key id or
+/// user id that GnuPG knows
+pub fn sign(git_path: &Path, payload: &[u8], key: &str) -> Result<String, Error> {
+    let program = program(git_path);
+    let mut child = Command::new(&program)
+        .args(["--status-fd=2", "-bsau", key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Generic(format!("cannot run {}: {}", program, e)))?;
+    child.stdin.take()
+        .ok_or_else(|| Error::Generic(format!("cannot write to {}", program)))?
+        .write_all(payload)?;
+    let output = child.wait_with_output()?;
+
+    let signature = String::from_utf8_lossy(&output.stdout).to_string();
+    let status = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !status.lines().any(|line| line.starts_with("[GNUPG:] SIG_CREATED ")) {
+        let reasons: Vec<&str> = status.lines().filter(|line| !line.starts_with("[GNUPG:]")).collect();
+        return Err(Error::Generic(format!("gpg failed to sign the data\n{}", reasons.join("\n")).trim_end().to_string()));
+    }
+    Ok(signature)
+}
+
+/// Check that `signature` was made over `payload` by a key GnuPG trusts
+pub fn verify(git_path: &Path, payload: &[u8], signature: &str) -> Result<Verification, Error> {
+    // GnuPG takes a detached signature only from a file
+    let signature_path = std::env::temp_dir().join(format!("ash-signature-{}", std::process::id()));
+    fs::write(&signature_path, signature)?;
+
+    let program = program(git_path);
+    let child = Command::new(&program)
+        .args(["--keyid-format=long", "--status-fd=1", "--verify"])
+        .arg(&signature_path)
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let output = child.and_then(|mut child| {
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(payload)?;
+        }
+        child.wait_with_output()
+    });
+    let _ = fs::remove_file(&signature_path);
+    let output = output.map_err(|e| Error::Generic(format!("cannot run {}: {}", program, e)))?;
+
+    let status = String::from_utf8_lossy(&output.stdout);
+    let good = status.lines().any(|line| line.starts_with("[GNUPG:] GOODSIG "));
+    let bad = status.lines().any(|line| line.starts_with("[GNUPG:] BADSIG ") || line.starts_with("[GNUPG:] ERRSIG "));
+
+    Ok(Verification {
+        good: good && !bad && output.status.success(),
+        output: String::from_utf8_lossy(&output.stderr).to_string(),
+    })
+}
+
+fn program(git_path: &Path) -> String {
+    Config::load(git_path).ok()
+        .and_then(|config| config.get(PROGRAM_KEY))
+        .unwrap_or_else(|| DEFAULT_PROGRAM.to_string())
+}
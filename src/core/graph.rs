@@ -0,0 +1,171 @@
+// src/core/graph.rs - drawing the commit graph in the left margin of a log
+//
+// Each line of history being followed is a lane, a column of `|` waiting
+// for the next commit on it. A commit is drawn as `*` in its lane; its
+// first parent takes the lane over and any other parents open new lanes to
+// the right, drawn branching off with `\`. Lanes that come to wait for the
+// same commit join the leftmost of them, drawn with `/`.
+use std::collections::VecDeque;
+
+use crate::core::color::Color;
+
+// The colors lanes take in turn
+const LANE_COLORS: [&str; 6] = [Color::RED, Color::GREEN, Color::YELLOW, Color::BLUE, Color::MAGENTA, Color::CYAN];
+
+#[derive(Debug, Clone)]
+struct Lane {
+    // The commit the lane leads to
+    oid: String,
+    color: usize,
+}
+
+// One line of the graph, one cell per column: the character and the lane
+// whose color it takes
+type Row = Vec<(char, Option<usize>)>;
+
+/// The graph drawn next to the commits of a log, given newest first with
+/// no commit before any of its children
+#[derive(Debug, Default)]
+pub struct Graph {
+    lanes: Vec<Lane>,
+    next_color: usize,
+    // The lines of the graph for the commit taken in last, still to be
+    // written, all padded to `width`
+    rows: VecDeque<String>,
+    width: usize,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take in the next commit and the parents of it that are shown too.
+    /// `boundary` commits are drawn as `o` and end their lane.
+    pub fn commit(&mut self, oid: &str, parents: &[String], boundary: bool) {
+        let col = match self.lanes.iter().position(|lane| lane.oid == oid) {
+            Some(col) => col,
+            None => {
+                let lane = self.new_lane(oid);
+                self.lanes.push(lane);
+                self.lanes.len() - 1
+            },
+        };
+
+        let mut rows: Vec<Row> = Vec::new();
+        let mut row = self.lane_row();
+        row[2 * col] = (if boundary { 'o' } else { '*' }, None);
+        rows.push(row);
+
+        let parents: &[String] = if boundary { &[] } else { parents };
+        match parents.split_first() {
+            None => {
+                self.lanes.remove(col);
+                // The lanes to the right move over into the gap
+                if col < self.lanes.len() {
+                    let mut row = self.lane_row();
+                    for i in col..self.lanes.len() {
+                        row[2 * i] = (' ', None);
+                        row[2 * i + 1] = ('/', Some(self.lanes[i].color));
+                    }
+                    rows.push(row);
+                }
+            },
+            Some((first, others)) => {
+                self.lanes[col].oid = first.clone();
+                for (k, parent) in others.iter().enumerate() {
+                    rows.push(self.open_lane(col + k + 1, parent));
+                }
+            },
+        }
+        while let Some(row) = self.join_lanes() {
+            rows.push(row);
+        }
+
+        // Room for the lanes there are now, even when the commit ended one
+        self.width = rows.iter().map(Vec::len).chain(Some(2 * self.lanes.len())).max().unwrap_or(0);
+        self.rows = rows.into_iter().map(|row| self.render(&row)).collect();
+    }
+
+    /// The graph to write before the next line of the commit's output
+    pub fn next_prefix(&mut self) -> String {
+        match self.rows.pop_front() {
+            Some(row) => row,
+            None => {
+                let row = self.lane_row();
+                self.render(&row)
+            },
+        }
+    }
+
+    /// The lines of the graph left over once the commit's output is all
+    /// written, each to go on a line of its own
+    pub fn remaining(&mut self) -> Vec<String> {
+        self.rows.drain(..).map(|row| row.trim_end().to_string()).collect()
+    }
+
+    fn new_lane(&mut self, oid: &str) -> Lane {
+        let lane = Lane { oid: oid.to_string(), color: self.next_color % LANE_COLORS.len() };
+        self.next_color += 1;
+        lane
+    }
+
+    // Every lane as a `|`
+    fn lane_row(&self) -> Row {
+        let mut row = Vec::with_capacity(2 * self.lanes.len());
+        for lane in &self.lanes {
+            row.push(('|', Some(lane.color)));
+            row.push((' ', None));
+        }
+        row
+    }
+
+    // Open a lane for `parent` at column `at`, branching off the one before
+    // it; the lanes from there on move one column right
+    fn open_lane(&mut self, at: usize, parent: &str) -> Row {
+        let lane = self.new_lane(parent);
+        let mut row = self.lane_row();
+        row.extend([(' ', None), (' ', None)]);
+        row[2 * at - 1] = ('\\', Some(lane.color));
+        for i in at..self.lanes.len() {
+            row[2 * i] = (' ', None);
+            row[2 * i + 1] = ('\\', Some(self.lanes[i].color));
+        }
+        self.lanes.insert(at, lane);
+        row
+    }
+
+    // Join the first lane waiting for the same commit as one to its left
+    // into that one, if there is such a lane; the lanes after it move one
+    // column left
+    fn join_lanes(&mut self) -> Option<Row> {
+        let (into, from) = (1..self.lanes.len()).find_map(|j| {
+            self.lanes[..j].iter().position(|lane| lane.oid == self.lanes[j].oid).map(|i| (i, j))
+        })?;
+        let color = Some(self.lanes[from].color);
+        let mut row = self.lane_row();
+        for m in into..from.saturating_sub(1) {
+            row[2 * m + 1] = ('_', color);
+        }
+        row[2 * from] = (' ', None);
+        row[2 * from - 1] = ('/', color);
+        for m in from + 1..self.lanes.len() {
+            row[2 * m] = (' ', None);
+            row[2 * m - 1] = ('/', Some(self.lanes[m].color));
+        }
+        self.lanes.remove(from);
+        Some(row)
+    }
+
+    fn render(&self, row: &Row) -> String {
+        let mut text = String::new();
+        for &(c, color) in row {
+            match color {
+                Some(color) if c != ' ' => text.push_str(&Color::colorize(&c.to_string(), LANE_COLORS[color])),
+                _ => text.push(c),
+            }
+        }
+        text.push_str(&" ".repeat(self.width.saturating_sub(row.len())));
+        text
+    }
+}
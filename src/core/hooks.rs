@@ -0,0 +1,86 @@
+// src/core/hooks.rs - run the scripts users install as hooks
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+/// Where hooks live in the git dir, unless `core.hooksPath` says otherwise
+pub const HOOKS_DIR: &str = "hooks";
+pub const HOOKS_PATH_KEY: &str = "core.hooksPath";
+
+pub const PREPARE_COMMIT_MSG: &str = "prepare-commit-msg";
+/// Run by a repository being fetched from, before it sends any objects,
+/// with the wanted commits on standard input; failing refuses the fetch
+pub const PRE_UPLOAD_PACK: &str = "pre-upload-pack";
+/// Run by a repository pushed to once its refs are updated, with a line
+/// `<old> <new> <ref>` per updated ref on standard input
+pub const POST_RECEIVE: &str = "post-receive";
+/// Run after `post-receive`, with the updated refs as arguments
+pub const POST_UPDATE: &str = "post-update";
+
+/// The hook `name` of the repository at `git_path`, if one is installed
+/// and executable
+pub fn find(git_path: &Path, name: &str) -> Option<PathBuf> {
+    let dir = Config::load(git_path).ok()
+        .and_then(|config| config.get(HOOKS_PATH_KEY))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| git_dir::common(git_path).join(HOOKS_DIR));
+    let path = dir.join(name);
+    let metadata = fs::metadata(&path).ok()?;
+    if !metadata.is_file() || !is_executable(&metadata) {
+        return None;
+    }
+    Some(path)
+}
+
+/// Run the hook `name` with `args` from the top of the working tree.
+/// Returns whether it succeeded; a hook that is not installed always does.
+pub fn run(git_path: &Path, name: &str, args: &[&str]) -> Result<bool, Error> {
+    let path = match find(git_path, name) {
+        Some(path) => path,
+        None => return Ok(true),
+    };
+    let status = Command::new(&path)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Generic(format!("cannot run {} hook: {}", name, e)))?;
+    Ok(status.success())
+}
+
+/// Run the hook `name` of the repository at `git_path` in `dir`, as a
+/// server does for the repository it serves, with `input` on its standard
+/// input. Returns whether it succeeded, as `run` does.
+pub fn run_with_input(git_path: &Path, dir: &Path, name: &str, args: &[&str], input: &str) -> Result<bool, Error> {
+    let path = match find(git_path, name) {
+        Some(path) => path,
+        None => return Ok(true),
+    };
+    let mut child = Command::new(&path)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Generic(format!("cannot run {} hook: {}", name, e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that does not read its input may exit before taking it all
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let status = child.wait()
+        .map_err(|e| Error::Generic(format!("cannot run {} hook: {}", name, e)))?;
+    Ok(status.success())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    true
+}
@@ -0,0 +1,199 @@
+// src/core/ignore.rs - decide which untracked paths are left alone
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+
+/// The name of the ignore files read in every directory of the working tree
+pub const IGNORE_FILE: &str = ".ashignore";
+pub const EXCLUDES_FILE_KEY: &str = "core.excludesFile";
+// Patterns kept out of the history of this repository only
+const INFO_EXCLUDE: &str = "info/exclude";
+// Repository directories are never part of the working tree, whatever the
+// patterns say
+pub const ALWAYS_IGNORED: [&str; 2] = [".ash", ".git"];
+
+// One line of an ignore file
+struct Pattern {
+    regex: Regex,
+    // The directory of the file it came from, relative to the root; the
+    // pattern only applies below it
+    base: String,
+    negated: bool,
+    dir_only: bool,
+    // Matched against the path below `base` rather than against the name
+    anchored: bool,
+}
+
+/// The ignore patterns in effect, from the lowest precedence up: the global
+/// excludes file (`core.excludesFile`, or ~/.config/ash/ignore), the
+/// repository's info/exclude, then the .ashignore files from the root down
+/// to the directory being looked at. The last pattern matching a path
+/// decides whether it is ignored, so `!pattern` can take a path back.
+pub struct Ignore {
+    root_path: PathBuf,
+    patterns: Vec<Pattern>,
+}
+
+impl Ignore {
+    pub fn new(root_path: &Path) -> Self {
+        let mut ignore = Ignore { root_path: root_path.to_path_buf(), patterns: Vec::new() };
+        let git_path = git_dir::resolve(root_path);
+
+        if let Some(path) = Self::global_excludes_file(&git_path) {
+            ignore.read_file(&path, "");
+        }
+        ignore.read_file(&git_path.join(INFO_EXCLUDE), "");
+        ignore.read_file(&root_path.join(IGNORE_FILE), "");
+        ignore
+    }
+
+    /// Take in the .ashignore file of `dir`, relative to the root, on the way
+    /// into it. Returns the mark to pass to `leave` on the way out.
+    pub fn enter(&mut self, dir: &str) -> usize {
+        let mark = self.patterns.len();
+        // The root's file is always read
+        if !dir.is_empty() {
+            let path = self.root_path.join(dir).join(IGNORE_FILE);
+            self.read_file(&path, dir);
+        }
+        mark
+    }
+
+    /// Drop the patterns read since `enter` returned `mark`
+    pub fn leave(&mut self, mark: usize) {
+        self.patterns.truncate(mark);
+    }
+
+    /// Whether `path`, relative to the root, is ignored by the patterns read
+    /// so far. The directories above it are not looked at; anything inside an
+    /// ignored directory is ignored too, but that is for the caller to know.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        if ALWAYS_IGNORED.contains(&name) {
+            return true;
+        }
+
+        for pattern in self.patterns.iter().rev() {
+            if pattern.dir_only && !is_dir {
+                continue;
+            }
+            let relative = if pattern.base.is_empty() {
+                path
+            } else {
+                match path.strip_prefix(pattern.base.as_str()).and_then(|rest| rest.strip_prefix('/')) {
+                    Some(relative) => relative,
+                    None => continue,
+                }
+            };
+            if pattern.regex.is_match(if pattern.anchored { relative } else { name }) {
+                return !pattern.negated;
+            }
+        }
+        false
+    }
+
+    fn global_excludes_file(git_path: &Path) -> Option<PathBuf> {
+        let home = env::var("HOME").ok();
+        if let Some(file) = Config::load(git_path).ok().and_then(|config| config.get(EXCLUDES_FILE_KEY)) {
+            return match (file.strip_prefix("~/"), home) {
+                (Some(rest), Some(home)) => Some(Path::new(&home).join(rest)),
+                _ => Some(PathBuf::from(file)),
+            };
+        }
+        match env::var("XDG_CONFIG_HOME") {
+            Ok(config_home) if !config_home.is_empty() => Some(Path::new(&config_home).join("ash/ignore")),
+            _ => home.map(|home| Path::new(&home).join(".config/ash/ignore")),
+        }
+    }
+
+    // A missing or unreadable file has no patterns to give
+    fn read_file(&mut self, path: &Path, base: &str) {
+        if let Ok(content) = fs::read_to_string(path) {
+            self.patterns.extend(content.lines().filter_map(|line| Self::parse(line, base)));
+        }
+    }
+
+    fn parse(line: &str, base: &str) -> Option<Pattern> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        // "\#" and "\!" start patterns with those characters
+        let (negated, line) = if line.starts_with("\\#") || line.starts_with("\\!") {
+            (false, &line[1..])
+        } else if let Some(rest) = line.strip_prefix('!') {
+            (true, rest)
+        } else {
+            (false, line)
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        // A slash anywhere but at the end ties the pattern to its directory
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        let regex = Regex::new(&glob_to_regex(line)).ok()?;
+        Some(Pattern { regex, base: base.to_string(), negated, dir_only, anchored })
+    }
+}
+
+/// The regex matching what `glob` does in ignore and attributes files: "*"
+/// and "?" stay within one path component, "**" crosses them, and "[...]"
+/// is a character class as in the shell
+pub fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::from("^");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                if chars.get(i + 2) == Some(&'/') {
+                    // Any number of directories, none included
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+                continue;
+            },
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(length) if length > 0 => {
+                    regex.push('[');
+                    for (j, &c) in chars[i + 1..i + 1 + length].iter().enumerate() {
+                        match c {
+                            '!' if j == 0 => regex.push('^'),
+                            '-' => regex.push('-'),
+                            c => regex.push_str(&regex::escape(&c.to_string())),
+                        }
+                    }
+                    regex.push(']');
+                    i += length + 2;
+                    continue;
+                },
+                _ => regex.push_str("\\["),
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+                continue;
+            },
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        i += 1;
+    }
+    regex.push('$');
+    regex
+}
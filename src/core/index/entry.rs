@@ -5,6 +5,11 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::core::file_mode::FileMode;
 const MAX_PATH_SIZE: u16 = 0xfff;
+// Set in the flags of an entry followed by a second, extended flags field,
+// which only version 3 indexes have
+const EXTENDED_FLAG: u16 = 0x4000;
+// In the extended flags: the path is recorded by `add -N` without content
+const INTENT_TO_ADD_FLAG: u16 = 0x2000;
 
 #[derive(Debug, Clone)]
 pub struct Entry {
@@ -24,6 +29,8 @@ pub struct Entry {
     pub path: String,
     // Add this field:
     pub stage: u8,  // 0 = normal, 1 = base, 2 = ours, 3 = theirs
+    // Added with `add -N`: tracked, but with nothing staged yet
+    pub intent_to_add: bool,
 }
 
 impl Entry {
@@ -60,8 +67,14 @@ impl Entry {
             flags,
             path,
             stage: 0,  // Default stage is 0 (normal entry)
+            intent_to_add: false,
         }
     }
+
+    /// Whether the entry needs the extended flags of a version 3 index
+    pub fn is_extended(&self) -> bool {
+        self.intent_to_add
+    }
     pub fn mode_octal(&self) -> String {
         self.mode.to_octal_string()
     }
@@ -148,6 +161,11 @@ impl Entry {
         self.oid = oid;
     }
 
+    pub fn set_path(&mut self, path: &str) {
+        self.path = path.to_string();
+        self.flags = path.len().min(MAX_PATH_SIZE as usize) as u16;
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut result = Vec::new();
         
@@ -173,8 +191,15 @@ impl Entry {
         
         // Add flags with stage bits
         // Stage is stored in the high bits of the flags field
-        let flags_with_stage = self.flags | ((self.stage as u16) << 12);
+        let mut flags_with_stage = self.flags | ((self.stage as u16) << 12);
+        if self.is_extended() {
+            flags_with_stage |= EXTENDED_FLAG;
+        }
         result.extend_from_slice(&flags_with_stage.to_be_bytes());
+        if self.is_extended() {
+            let extended = if self.intent_to_add { INTENT_TO_ADD_FLAG } else { 0 };
+            result.extend_from_slice(&extended.to_be_bytes());
+        }
         
         // Add path
         result.extend_from_slice(self.path.as_bytes());
@@ -214,9 +239,15 @@ impl Entry {
         let flags_with_stage = u16::from_be_bytes([data[60], data[61]]);
         let flags = flags_with_stage & 0x0FFF; // Lower 12 bits
         let stage = ((flags_with_stage >> 12) & 0x3) as u8; // Upper 2 bits (stage 0-3)
+        let extended = if flags_with_stage & EXTENDED_FLAG != 0 {
+            u16::from_be_bytes([data[62], data[63]])
+        } else {
+            0
+        };
         
-        // Path starts at byte 62 and continues until null byte
-        let mut path_end = 62;
+        // Path starts after the flags and continues until null byte
+        let path_start = Self::path_offset(data);
+        let mut path_end = path_start;
         while path_end < data.len() && data[path_end] != 0 {
             path_end += 1;
         }
@@ -225,7 +256,7 @@ impl Entry {
             return Err(crate::errors::error::Error::Generic("No null terminator for path".to_string()));
         }
         
-        let path = match std::str::from_utf8(&data[62..path_end]) {
+        let path = match std::str::from_utf8(&data[path_start..path_end]) {
             Ok(s) => s.to_string(),
             Err(_) => return Err(crate::errors::error::Error::Generic("Invalid UTF-8 in path".to_string())),
         };
@@ -245,8 +276,18 @@ impl Entry {
             flags,
             path,
             stage,
+            intent_to_add: extended & INTENT_TO_ADD_FLAG != 0,
         })
     }
+
+    /// Where the path of the entry starting at `data` begins: after the
+    /// flags, and the extended flags when it has them
+    pub fn path_offset(data: &[u8]) -> usize {
+        match data.get(60..62) {
+            Some(flags) if u16::from_be_bytes([flags[0], flags[1]]) & EXTENDED_FLAG != 0 => 64,
+            _ => 62,
+        }
+    }
     
     // Update stat information for an entry
     pub fn update_stat(&mut self, stat: &std::fs::Metadata) {
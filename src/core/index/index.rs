@@ -3,6 +3,7 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::{self, File};
 use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use crate::core::database::entry::DatabaseEntry;
 use crate::errors::error::Error;
 use crate::core::lockfile::Lockfile;
@@ -10,15 +11,22 @@ use crate::core::index::entry::Entry;
 use crate::core::index::checksum::Checksum;
 use crate::core::index::checksum::CHECKSUM_SIZE;
 use crate::core::file_mode::FileMode;
+use crate::trace;
 
 const HEADER_FORMAT: &str = "DIRC";
 const VERSION: u32 = 2;
+// Written instead of VERSION when an entry has extended flags
+const EXTENDED_VERSION: u32 = 3;
 const HEADER_SIZE: usize = 12;
+// How long to wait for another process to release the index
+const LOCK_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub struct Index {
     pathname: PathBuf,
     pub entries: HashMap<String, Entry>,
     pub keys: BTreeSet<String>,
+    // Every stage of each conflicted path, in order; `entries` has the last
+    stages: HashMap<String, Vec<Entry>>,
     lockfile: Lockfile,
     pub changed: bool,
 }
@@ -29,6 +37,7 @@ impl Index {
             pathname: pathname.as_ref().to_path_buf(),
             entries: HashMap::new(),
             keys: BTreeSet::new(),
+            stages: HashMap::new(),
             lockfile: Lockfile::new(pathname),
             changed: false,
         };
@@ -46,6 +55,16 @@ impl Index {
         self.entries.get(key)
     }
     
+    /// The entry for `path` at `stage`: 0 for a merged path, 1 to 3 for the
+    /// base, ours and theirs of a conflicted one
+    pub fn get_stage_entry(&self, path: &str, stage: u8) -> Option<&Entry> {
+        match self.entries.get(path) {
+            Some(entry) if entry.stage == 0 => Some(entry).filter(|_| stage == 0),
+            Some(_) => self.stages.get(path)?.iter().find(|entry| entry.stage == stage),
+            None => None,
+        }
+    }
+
     pub fn get_entry_mut(&mut self, key: &str) -> Option<&mut Entry> {
         self.entries.get_mut(key)
     }
@@ -75,6 +94,7 @@ impl Index {
     pub fn clear(&mut self) {
         self.entries.clear();
         self.keys.clear();
+        self.stages.clear();
         self.changed = false;
     }
 
@@ -84,9 +104,51 @@ impl Index {
         self.changed = true;
         Ok(())
     }
-    
+
+    // Add an entry whose blob differs from the file on disk (e.g. a partially
+    // staged file). The cached size and mtime are taken from the blob so that
+    // status re-examines the workspace file instead of trusting its stat.
+    pub fn add_partial(&mut self, pathname: &Path, oid: &str, size: usize, stat: &fs::Metadata) -> Result<(), Error> {
+        let mut entry = Entry::create(pathname, oid, stat);
+        entry.set_size(size as u32);
+        entry.set_mtime(0);
+        entry.set_mtime_nsec(0);
+        self.store_entry(entry);
+        self.changed = true;
+        Ok(())
+    }
+
+    /// Record `pathname` as intent-to-add: tracked with the empty blob
+    /// `oid`, so that diff shows all of it as new and commits leave it out
+    /// until its content is added
+    pub fn add_intent_to_add(&mut self, pathname: &Path, oid: &str, stat: &fs::Metadata) {
+        let mut entry = create_stage_entry(pathname, oid, 0);
+        entry.set_mode(FileMode::from_metadata(stat));
+        entry.intent_to_add = true;
+        self.store_entry(entry);
+        self.changed = true;
+    }
+
+    // Add an entry for a blob without looking at the workspace file, which may
+    // differ or be missing. Having no cached stat data, it is always re-examined.
+    pub fn add_blob(&mut self, pathname: &Path, oid: &str, mode: FileMode, size: usize) {
+        let mut entry = create_stage_entry(pathname, oid, 0);
+        entry.set_mode(mode);
+        entry.set_size(size as u32);
+        self.store_entry(entry);
+        self.changed = true;
+    }
+
     fn store_entry(&mut self, entry: Entry) {
         let key = entry.get_path().to_string();
+        if entry.stage == 0 {
+            self.stages.remove(&key);
+        } else {
+            let stages = self.stages.entry(key.clone()).or_default();
+            stages.retain(|staged| staged.stage != entry.stage);
+            stages.push(entry.clone());
+            stages.sort_by_key(|staged| staged.stage);
+        }
         self.keys.insert(key.clone());
         self.entries.insert(key, entry);
     }
@@ -96,7 +158,8 @@ impl Index {
     }
     
     pub fn load_for_update(&mut self) -> Result<bool, Error> {
-        let acquired = self.lockfile.hold_for_update()
+        // Concurrent commands each hold the lock only briefly, so wait for it
+        let acquired = self.lockfile.hold_for_update_retrying(LOCK_TIMEOUT)
             .map_err(|e| Error::Generic(format!("Lock error: {:?}", e)))?;
         
         if acquired {
@@ -154,7 +217,7 @@ impl Index {
             )));
         }
         
-        if version != VERSION {
+        if version != VERSION && version != EXTENDED_VERSION {
             return Err(Error::Generic(format!(
                 "Version: expected '{}' but found '{}'",
                 VERSION, version
@@ -188,9 +251,11 @@ impl Index {
             }
             checksum.update(&entry_data);
             
-            // Keep reading 8-byte blocks until we find a null terminator or EOF
+            // Keep reading 8-byte blocks until we find a null terminator or EOF,
+            // past the extended flags, whose last byte may be zero
             let mut reached_end = false;
-            while !reached_end && entry_data[entry_data.len() - 1] != 0 {
+            let path_offset = Entry::path_offset(&entry_data);
+            while !reached_end && (entry_data.len() <= path_offset + 1 || entry_data[entry_data.len() - 1] != 0) {
                 let mut block = vec![0; ENTRY_BLOCK];
                 match reader.read_exact(&mut block) {
                     Ok(_) => {
@@ -229,10 +294,11 @@ impl Index {
         let mut checksum = Checksum::new();
         
         // Generate header
-        let entry_count = self.entries.len() as u32;
+        let entry_count = self.keys.iter().map(|key| self.stored_entries(key).len()).sum::<usize>() as u32;
+        let version = if self.entries.values().any(Entry::is_extended) { EXTENDED_VERSION } else { VERSION };
         let mut header = Vec::with_capacity(HEADER_SIZE);
         header.extend_from_slice(HEADER_FORMAT.as_bytes());
-        header.extend_from_slice(&VERSION.to_be_bytes());
+        header.extend_from_slice(&version.to_be_bytes());
         header.extend_from_slice(&entry_count.to_be_bytes());
         
         
@@ -244,13 +310,14 @@ impl Index {
             .map_err(|e| Error::Generic(format!("Write error: {:?}", e)))?;
         
         // Write entries in sorted order
-        for key in &self.keys {
-            let entry = &self.entries[key];
-            let bytes = entry.to_bytes();
-            
+        let entries: Vec<Vec<u8>> = self.keys.iter()
+            .flat_map(|key| self.stored_entries(key))
+            .map(|entry| entry.to_bytes())
+            .collect();
+        for bytes in entries {
             // Update checksum with entry data
             checksum.update(&bytes);
-            
+
             // Write entry data to lockfile
             self.lockfile.write_bytes(&bytes)
                 .map_err(|e| Error::Generic(format!("Write error: {:?}", e)))?;
@@ -273,6 +340,15 @@ impl Index {
         Ok(true)
     }
 
+    // The entries written for `key`: all its stages when it is conflicted
+    fn stored_entries(&self, key: &str) -> Vec<&Entry> {
+        let entry = &self.entries[key];
+        match self.stages.get(key) {
+            Some(stages) if entry.stage > 0 => stages.iter().collect(),
+            _ => vec![entry],
+        }
+    }
+
     pub fn rollback(&mut self) -> Result<(), Error> {
         self.changed = false;
         self.lockfile.rollback()
@@ -312,7 +388,7 @@ impl Index {
         }
         
         let version = u32::from_be_bytes([header_data[4], header_data[5], header_data[6], header_data[7]]);
-        if version != VERSION {
+        if version != VERSION && version != EXTENDED_VERSION {
             return Err(Error::Generic(format!(
                 "Unsupported index version: expected {}, got {}",
                 VERSION, version
@@ -398,7 +474,7 @@ impl Index {
                             // Lock is more than an hour old, probably stale
                             match fs::remove_file(&lock_path) {
                                 Ok(_) => {
-                                    println!("Removed stale lock file: {}", lock_path.display());
+                                    trace!("Removed stale lock file: {}", lock_path.display());
                                     return Ok(true);
                                 },
                                 Err(e) => {
@@ -465,11 +541,23 @@ impl Index {
         Ok(())
     }
     
+    /// Move the entry at `from` to `to`, keeping its blob and cached stat data
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(mut entry) = self.entries.remove(from) {
+            self.keys.remove(from);
+            self.stages.remove(from);
+            entry.set_path(to);
+            self.store_entry(entry);
+            self.changed = true;
+        }
+    }
+
     /// Remove a specific entry from the index
     fn remove_entry(&mut self, path: &str) {
         if self.entries.remove(path).is_some() {
             self.keys.remove(path);
         }
+        self.stages.remove(path);
     }
     
     /// Remove all entries that are children of the given path
@@ -486,6 +574,7 @@ impl Index {
         for key in keys_to_remove {
             self.entries.remove(&key);
             self.keys.remove(&key);
+            self.stages.remove(&key);
         }
     }
 
@@ -499,6 +588,7 @@ impl Index {
             self.entries.remove(&path_str);
             self.keys.remove(&path_str);
         }
+        self.stages.remove(&path_str);
         
         // Add each conflict stage entry
         // Stage 1: Base version
@@ -561,7 +651,7 @@ impl Index {
     
     // Remove conflict entries for a path
     fn remove_conflict(&mut self, path_str: &str) {
-        println!("Removing conflict for path: {}", path_str);
+        trace!("Removing conflict for path: {}", path_str);
         
         // Get all entries for this path with their stages
         let entries_to_remove: Vec<(String, u8)> = self.entries.iter()
@@ -571,16 +661,17 @@ impl Index {
         
         // Remove each conflict entry
         for (key, stage) in entries_to_remove {
-            println!("  Removing stage {} entry for {}", stage, key);
+            trace!("  Removing stage {} entry for {}", stage, key);
             self.entries.remove(&key);
+            self.stages.remove(&key);
         }
         
         // Check if there are any entries left for this path
         if !self.entries.iter().any(|(k, _)| k == path_str) {
-            println!("  No entries left for path {}, removing from keys collection", path_str);
+            trace!("  No entries left for path {}, removing from keys collection", path_str);
             self.keys.remove(path_str);
         } else {
-            println!("  Regular (non-conflict) entry remains for {}", path_str);
+            trace!("  Regular (non-conflict) entry remains for {}", path_str);
         }
     }
     
@@ -607,7 +698,7 @@ impl Index {
         
         // Remove each conflict entry
         for path in conflict_paths {
-            println!("Removing conflict entry for file in directory: {}", path);
+            trace!("Removing conflict entry for file in directory: {}", path);
             self.remove_conflict(&path);
         }
         
@@ -635,6 +726,7 @@ fn create_stage_entry(path: &Path, oid: &str, stage: u8) -> Entry {
         flags: 0,
         path: path.to_string_lossy().to_string(),
         stage,
+        intent_to_add: false,
     };
     
     // Set stage in flags
@@ -1,6 +1,37 @@
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+// Longest wait between attempts at a contended lock
+const MAX_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Extension of a lock file, and of the file next to it naming its owner
+pub const LOCK_EXTENSION: &str = "lock";
+pub const OWNER_EXTENSION: &str = "pid";
+
+/// The file recording which process holds `lock_path`, e.g. `index.lock.pid`
+pub fn owner_path(lock_path: &Path) -> PathBuf {
+    let mut name = lock_path.as_os_str().to_os_string();
+    name.push(format!(".{}", OWNER_EXTENSION));
+    PathBuf::from(name)
+}
+
+/// The process that took the lock at `lock_path`, if it recorded itself
+pub fn read_owner(lock_path: &Path) -> Option<u32> {
+    fs::read_to_string(owner_path(lock_path)).ok()?.trim().parse().ok()
+}
+
+/// Whether `name` is a lock file or the record of a lock's owner, rather
+/// than anything the lock protects
+pub fn is_lock_artifact(name: &str) -> bool {
+    name.ends_with(&format!(".{}", LOCK_EXTENSION))
+        || name.ends_with(&format!(".{}.{}", LOCK_EXTENSION, OWNER_EXTENSION))
+}
 
 #[derive(Debug)]
 pub enum LockError {
@@ -20,7 +51,7 @@ pub struct Lockfile {
 impl Lockfile {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
         let file_path = path.as_ref().to_path_buf();
-        let lock_path = file_path.with_extension("lock");
+        let lock_path = file_path.with_extension(LOCK_EXTENSION);
         Lockfile {
             file_path,
             lock_path,
@@ -55,6 +86,9 @@ impl Lockfile {
         {
             Ok(file) => {
                 self.lock = Some(file);
+                // Only a help for finding out who left a lock behind, so
+                // failing to write it does not fail the lock
+                let _ = fs::write(owner_path(&self.lock_path), format!("{}\n", process::id()));
                 Ok(true)
             }
             Err(e) => match e.kind() {
@@ -73,6 +107,29 @@ impl Lockfile {
         }
     }
 
+    /// Like `hold_for_update`, but while another process holds the lock keep
+    /// trying for up to `timeout`, waiting a little longer after each attempt
+    pub fn hold_for_update_retrying(&mut self, timeout: Duration) -> Result<bool, LockError> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Duration::from_millis(1);
+        loop {
+            match self.hold_for_update() {
+                Err(LockError::LockDenied(message)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(LockError::LockDenied(message));
+                    }
+                    // Jitter, so processes that failed together do not all
+                    // try again at the same moment
+                    let jitter = Duration::from_micros(rand::thread_rng().gen_range(0..=backoff.as_micros() as u64));
+                    thread::sleep((backoff + jitter).min(deadline - now));
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                },
+                result => return result,
+            }
+        }
+    }
+
     pub fn write(&mut self, data: &str) -> Result<(), LockError> {
         let lock = self.lock.as_mut().ok_or_else(|| {
             LockError::StaleLock(format!(
@@ -95,6 +152,7 @@ impl Lockfile {
         
         // Drop the file handle
         self.lock.take();
+        let _ = fs::remove_file(owner_path(&self.lock_path));
         
         // Remove the lock file
         match fs::remove_file(&self.lock_path) {
@@ -118,6 +176,7 @@ impl Lockfile {
 
         // Close the file before rename (necessary on Windows)
         drop(lock);
+        let _ = fs::remove_file(owner_path(&self.lock_path));
         
         fs::rename(&self.lock_path, &self.file_path)
             .map_err(|e| LockError::StaleLock(format!(
@@ -0,0 +1,133 @@
+// src/core/mailbox.rs - reading patches mailed as format-patch writes them
+//
+// A mailbox holds one mail after another, each starting with a "From "
+// line. The headers of a mail give the author and the subject; its body is
+// the rest of the commit message up to a `---` line, and the patch follows.
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::core::database::author::Author;
+use crate::errors::error::Error;
+
+/// A patch read from a mail
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub author: Author,
+    /// The subject with the [PATCH n/m] tag and any "Re:" taken off
+    pub subject: String,
+    /// The message after the subject, without the blank line between
+    pub body: String,
+    /// The diffstat and diff
+    pub patch: String,
+}
+
+impl Mail {
+    /// The mails in the mailbox `text`, one string each. Text with no
+    /// "From " line is taken as a single mail.
+    pub fn split(text: &str) -> Vec<String> {
+        let separator = Regex::new(r"^From \S+ ").unwrap();
+        let mut mails: Vec<String> = Vec::new();
+        let mut current = String::new();
+        for line in text.split_inclusive('\n') {
+            if separator.is_match(line) && !current.trim().is_empty() {
+                mails.push(std::mem::take(&mut current));
+            }
+            current.push_str(line);
+        }
+        if !current.trim().is_empty() {
+            mails.push(current);
+        }
+        mails
+    }
+
+    /// Read one mail; it must say who wrote it
+    pub fn parse(text: &str) -> Result<Mail, Error> {
+        let mut lines = text.split_inclusive('\n').peekable();
+        if lines.peek().is_some_and(|line| line.starts_with("From ")) {
+            lines.next();
+        }
+
+        // Headers may go on over lines that start with whitespace
+        let mut headers: Vec<(String, String)> = Vec::new();
+        for line in lines.by_ref() {
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                break;
+            }
+            match headers.last_mut() {
+                Some((_, value)) if line.starts_with([' ', '\t']) => {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                },
+                _ => if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+                },
+            }
+        }
+        let header = |name: &str| headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+        let from = header("from")
+            .ok_or_else(|| Error::Generic("patch does not have a valid e-mail address".to_string()))?;
+        let mut author = Self::parse_address(from)
+            .ok_or_else(|| Error::Generic(format!("invalid ident line: {}", from)))?;
+        if let Some(date) = header("date") {
+            author.timestamp = DateTime::parse_from_rfc2822(date)
+                .map_err(|_| Error::Generic(format!("invalid date line: {}", date)))?
+                .with_timezone(&Utc);
+        }
+        let subject = Self::clean_subject(header("subject").unwrap_or_default());
+
+        // The message ends where the patch starts, at the `---` before the
+        // diffstat or at the diff itself when there is none
+        let mut body = String::new();
+        let mut patch = String::new();
+        for line in lines.by_ref() {
+            let bare = line.trim_end_matches(['\n', '\r']);
+            if bare == "---" || bare.starts_with("diff -") || bare.starts_with("Index: ") {
+                patch.push_str(line);
+                break;
+            }
+            body.push_str(line);
+        }
+        patch.extend(lines);
+
+        Ok(Mail { author, subject, body: body.trim().to_string(), patch })
+    }
+
+    /// The commit message the mail carries
+    pub fn message(&self) -> String {
+        if self.body.is_empty() {
+            format!("{}\n", self.subject)
+        } else {
+            format!("{}\n\n{}\n", self.subject, self.body)
+        }
+    }
+
+    // "A U Thor <author@example.com>", or a bare address
+    fn parse_address(from: &str) -> Option<Author> {
+        let (name, email) = match from.rsplit_once('<') {
+            Some((name, email)) => (name.trim().trim_matches('"').to_string(), email.trim_end_matches('>').trim().to_string()),
+            None => (String::new(), from.trim().to_string()),
+        };
+        if !email.contains('@') {
+            return None;
+        }
+        let name = if name.is_empty() { email.split('@').next().unwrap_or_default().to_string() } else { name };
+        Some(Author::new(name, email))
+    }
+
+    // Take off what mailers and format-patch put before the subject proper:
+    // "Re:" and bracketed tags such as "[PATCH 2/3]"
+    fn clean_subject(subject: &str) -> String {
+        let mut subject = subject.trim();
+        loop {
+            if subject.len() >= 3 && subject[..3].eq_ignore_ascii_case("re:") {
+                subject = subject[3..].trim_start();
+            } else if let Some(end) = subject.strip_prefix('[').and_then(|rest| rest.find(']')) {
+                subject = subject[end + 2..].trim_start();
+            } else {
+                return subject.to_string();
+            }
+        }
+    }
+}
@@ -127,8 +127,9 @@ impl<'a> CommonAncestors<'a> {
         commit_oid: &str,
         flags: &HashSet<Flag>,
     ) -> Result<(), Error> {
-        // If commit has a parent, add it to the queue with the same flags
-        if let Some(parent_oid) = commit.get_parent() {
+        // Every parent, those a merge brings in too, goes on the queue with
+        // the same flags
+        for parent_oid in commit.get_parents() {
             // Get or create flags entry for parent
             let current_flags = self.flags.entry(parent_oid.to_string()).or_insert_with(HashSet::new);
             
@@ -0,0 +1,142 @@
+// src/core/merge/conflict_summary.rs - group unresolved conflicts by kind
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::index::index::Index;
+use crate::errors::error::Error;
+
+const CONFLICTS_FILE: &str = "MERGE_CONFLICTS";
+
+/// The shape of a conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConflictKind {
+    Content,
+    AddAdd,
+    ModifyDelete,
+    FileDirectory,
+    Binary,
+}
+
+impl ConflictKind {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "content" => Some(ConflictKind::Content),
+            "add/add" => Some(ConflictKind::AddAdd),
+            "modify/delete" => Some(ConflictKind::ModifyDelete),
+            "file/directory" => Some(ConflictKind::FileDirectory),
+            "binary" => Some(ConflictKind::Binary),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ConflictKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConflictKind::Content => "content",
+            ConflictKind::AddAdd => "add/add",
+            ConflictKind::ModifyDelete => "modify/delete",
+            ConflictKind::FileDirectory => "file/directory",
+            ConflictKind::Binary => "binary",
+        })
+    }
+}
+
+/// Conflicts grouped by kind. The index keeps a single entry per path, so
+/// the kinds are recorded in .ash/MERGE_CONFLICTS when the merge stops and
+/// read back from there later.
+pub struct ConflictSummary {
+    groups: BTreeMap<ConflictKind, Vec<String>>,
+}
+
+impl ConflictSummary {
+    pub fn new(conflicts: impl IntoIterator<Item = (String, ConflictKind)>) -> Self {
+        let mut groups: BTreeMap<ConflictKind, Vec<String>> = BTreeMap::new();
+        for (path, kind) in conflicts {
+            groups.entry(kind).or_default().push(path);
+        }
+        for paths in groups.values_mut() {
+            paths.sort();
+        }
+        ConflictSummary { groups }
+    }
+
+    /// The conflicts recorded by the last merge that the index still has;
+    /// anything unrecorded counts as a content conflict
+    pub fn load(git_path: &Path, index: &Index) -> Result<Self, Error> {
+        let recorded: BTreeMap<String, ConflictKind> = match fs::read_to_string(Self::path(git_path)) {
+            Ok(content) => content.lines()
+                .filter_map(|line| line.split_once('\t'))
+                .filter_map(|(kind, path)| ConflictKind::parse(kind).map(|kind| (path.to_string(), kind)))
+                .collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(Error::Generic(format!("Failed to read {}: {}", CONFLICTS_FILE, e))),
+        };
+
+        Ok(Self::new(index.conflict_paths().into_iter().map(|path| {
+            let kind = recorded.get(&path).copied().unwrap_or(ConflictKind::Content);
+            (path, kind)
+        })))
+    }
+
+    pub fn save(&self, git_path: &Path) -> Result<(), Error> {
+        let content: String = self.groups.iter()
+            .flat_map(|(kind, paths)| paths.iter().map(move |path| format!("{}\t{}\n", kind, path)))
+            .collect();
+        fs::write(Self::path(git_path), content)
+            .map_err(|e| Error::Generic(format!("Failed to write {}: {}", CONFLICTS_FILE, e)))
+    }
+
+    pub fn clear(git_path: &Path) {
+        let _ = fs::remove_file(Self::path(git_path));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.groups.values().map(Vec::len).sum()
+    }
+
+    /// The report: the total, one section per kind, then the commands that
+    /// move the merge along
+    pub fn lines(&self) -> Vec<String> {
+        if self.is_empty() {
+            return vec!["No unresolved conflicts.".to_string()];
+        }
+
+        let total = self.len();
+        let mut lines = vec![format!("{} unresolved conflict{}:", total, if total == 1 { "" } else { "s" })];
+        for (kind, paths) in &self.groups {
+            lines.push(format!("  {} ({}):", kind, paths.len()));
+            for path in paths {
+                lines.push(format!("    {}", path));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push("Next steps:".to_string());
+        if self.groups.contains_key(&ConflictKind::Content) || self.groups.contains_key(&ConflictKind::AddAdd) {
+            lines.push("  edit the files to remove the conflict markers, or run 'ash merge --tool'".to_string());
+        }
+        if self.groups.contains_key(&ConflictKind::ModifyDelete) {
+            lines.push("  keep a modified file with 'ash add <path>' or drop it with 'ash rm <path>'".to_string());
+        }
+        if self.groups.contains_key(&ConflictKind::FileDirectory) {
+            lines.push("  the file version was left in the tree as <path>~<branch>; move it where it belongs".to_string());
+        }
+        if self.groups.contains_key(&ConflictKind::Binary) {
+            lines.push("  keep one side of a binary file with 'ash checkout --ours <path>' or 'ash checkout --theirs <path>'".to_string());
+        }
+        lines.push("  mark each path resolved with 'ash add <path>', then run 'ash merge --continue'".to_string());
+        lines.push("  or go back to where you started with 'ash merge --abort'".to_string());
+        lines
+    }
+
+    fn path(git_path: &Path) -> PathBuf {
+        git_path.join(CONFLICTS_FILE)
+    }
+}
@@ -1,6 +1,12 @@
 use std::collections::HashMap;
 use std::fmt::Write;
+use std::path::Path;
 use crate::errors::error::Error;
+use crate::core::config::Config;
+use crate::core::diff::myers::{diff_lines, Edit};
+
+// How conflicts are written out; `merge` when not set
+const CONFLICT_STYLE_KEY: &str = "merge.conflictStyle";
 
 // Helper to convert a string into a vector of lines with their endings preserved
 struct LinesWithEndings<'a> {
@@ -38,68 +44,6 @@ impl<'a> Iterator for LinesWithEndings<'a> {
     }
 }
 
-// Simplified representation of edits for our diff3 purposes
-#[derive(Debug, PartialEq)]
-enum EditType {
-    Eql,
-    Add,
-    Del,
-}
-
-#[derive(Debug)]
-struct Edit {
-    r#type: EditType,
-    a_line: Option<LineInfo>,
-    b_line: Option<LineInfo>,
-}
-
-#[derive(Debug)]
-struct LineInfo {
-    number: usize,
-    content: String,
-}
-
-// Function to calculate simple line-by-line diff between two texts
-fn diff(a: &str, b: &str) -> Vec<Edit> {
-    let a_lines: Vec<_> = a.lines().collect();
-    let b_lines: Vec<_> = b.lines().collect();
-    
-    let mut result = Vec::new();
-    let mut i = 0;
-    let mut j = 0;
-    
-    while i < a_lines.len() || j < b_lines.len() {
-        if i < a_lines.len() && j < b_lines.len() && a_lines[i] == b_lines[j] {
-            // Equal lines
-            result.push(Edit {
-                r#type: EditType::Eql,
-                a_line: Some(LineInfo { number: i, content: a_lines[i].to_string() }),
-                b_line: Some(LineInfo { number: j, content: b_lines[j].to_string() }),
-            });
-            i += 1;
-            j += 1;
-        } else if j < b_lines.len() && (i >= a_lines.len() || i < a_lines.len() && a_lines[i] != b_lines[j]) {
-            // Line added in b
-            result.push(Edit {
-                r#type: EditType::Add,
-                a_line: None,
-                b_line: Some(LineInfo { number: j, content: b_lines[j].to_string() }),
-            });
-            j += 1;
-        } else if i < a_lines.len() {
-            // Line deleted from a
-            result.push(Edit {
-                r#type: EditType::Del,
-                a_line: Some(LineInfo { number: i, content: a_lines[i].to_string() }),
-                b_line: None,
-            });
-            i += 1;
-        }
-    }
-    
-    result
-}
-
 /// Performs a three-way merge between original (o), ours (a), and theirs (b) content
 pub fn merge(o: &str, a: &str, b: &str) -> Result<MergeResult, Error> {
     let o: Vec<_> = LinesWithEndings::new(o).map(|l| l.to_string()).collect();
@@ -112,6 +56,48 @@ pub fn merge(o: &str, a: &str, b: &str) -> Result<MergeResult, Error> {
 
 type MatchSet = HashMap<usize, usize>;
 
+/// The side whose lines settle a conflicting chunk, as `-X ours` and
+/// `-X theirs` ask
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Favor {
+    Ours,
+    Theirs,
+}
+
+/// How a conflicting chunk is written out, as `merge.conflictStyle` says:
+/// ours and theirs only, with the base between them, or with the base and
+/// the lines both sides share moved out of the conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConflictStyle {
+    #[default]
+    Merge,
+    Diff3,
+    Zdiff3,
+}
+
+impl ConflictStyle {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "merge" => Some(ConflictStyle::Merge),
+            "diff3" => Some(ConflictStyle::Diff3),
+            "zdiff3" => Some(ConflictStyle::Zdiff3),
+            _ => None,
+        }
+    }
+
+    /// The style the repository at `git_path` is configured with
+    pub fn load(git_path: &Path) -> Self {
+        let name = match Config::load(git_path).ok().and_then(|config| config.get(CONFLICT_STYLE_KEY)) {
+            Some(name) => name,
+            None => return ConflictStyle::default(),
+        };
+        Self::parse(&name).unwrap_or_else(|| {
+            eprintln!("warning: unknown style '{}' given for '{}'", name, CONFLICT_STYLE_KEY);
+            ConflictStyle::default()
+        })
+    }
+}
+
 #[derive(Debug)]
 struct Diff3 {
     o: Vec<String>,
@@ -159,19 +145,10 @@ impl Diff3 {
     fn match_set(&self, file: &[String]) -> MatchSet {
         let mut matches = HashMap::new();
 
-        // Generate diff between original and this file
-        let o_content = self.o.join("");
-        let file_content = file.join("");
-        
-        // Generate diff using our custom diff function
-        for edit in diff(&o_content, &file_content) {
-            match edit.r#type {
-                EditType::Eql => {
-                    if let (Some(a_line), Some(b_line)) = (edit.a_line, edit.b_line) {
-                        matches.insert(a_line.number, b_line.number);
-                    }
-                },
-                _ => {}
+        // Map 1-based line numbers of the original to those of this file
+        for edit in diff_lines(&self.o, file) {
+            if let Edit::Equal(o_line, file_line) = edit {
+                matches.insert(o_line + 1, file_line + 1);
             }
         }
 
@@ -301,9 +278,15 @@ pub enum Chunk {
 
 impl Chunk {
     pub fn to_string(&self, a_name: Option<&str>, b_name: Option<&str>) -> String {
+        self.to_styled_string(a_name, None, b_name, ConflictStyle::Merge)
+    }
+
+    /// The chunk with a conflict written in `style`, the base labelled
+    /// `o_name`
+    pub fn to_styled_string(&self, a_name: Option<&str>, o_name: Option<&str>, b_name: Option<&str>, style: ConflictStyle) -> String {
         match self {
             Chunk::Clean { lines } => lines.join(""),
-            Chunk::Conflict { o_lines: _, a_lines, b_lines } => {
+            Chunk::Conflict { o_lines, a_lines, b_lines } => {
                 fn separator(text: &mut String, r#char: &str, name: Option<&str>) {
                     text.push_str(&r#char.repeat(7));
                     if let Some(name) = name {
@@ -312,16 +295,37 @@ impl Chunk {
                     text.push('\n');
                 }
 
-                let mut text = String::new();
+                // zdiff3 leaves the lines both sides begin and end with
+                // outside the markers
+                let (mut prefix, mut suffix) = (0, 0);
+                if style == ConflictStyle::Zdiff3 {
+                    while prefix < a_lines.len().min(b_lines.len()) && a_lines[prefix] == b_lines[prefix] {
+                        prefix += 1;
+                    }
+                    while suffix < (a_lines.len() - prefix).min(b_lines.len() - prefix)
+                        && a_lines[a_lines.len() - 1 - suffix] == b_lines[b_lines.len() - 1 - suffix]
+                    {
+                        suffix += 1;
+                    }
+                }
+
+                let mut text = a_lines[..prefix].join("");
                 separator(&mut text, "<", a_name);
-                for line in a_lines {
+                for line in &a_lines[prefix..a_lines.len() - suffix] {
                     text.push_str(line);
                 }
+                if style != ConflictStyle::Merge {
+                    separator(&mut text, "|", o_name);
+                    for line in o_lines {
+                        text.push_str(line);
+                    }
+                }
                 separator(&mut text, "=", None);
-                for line in b_lines {
+                for line in &b_lines[prefix..b_lines.len() - suffix] {
                     text.push_str(line);
                 }
                 separator(&mut text, ">", b_name);
+                text.push_str(&a_lines[a_lines.len() - suffix..].join(""));
 
                 text
             }
@@ -349,9 +353,42 @@ impl MergeResult {
     }
 
     pub fn to_string(&self, a_name: Option<&str>, b_name: Option<&str>) -> String {
+        self.to_styled_string(a_name, None, b_name, ConflictStyle::Merge)
+    }
+
+    /// The merge with conflicts written in `style`, the base labelled
+    /// `o_name`
+    pub fn to_styled_string(&self, a_name: Option<&str>, o_name: Option<&str>, b_name: Option<&str>, style: ConflictStyle) -> String {
+        self.chunks
+            .iter()
+            .map(|chunk| chunk.to_styled_string(a_name, o_name, b_name, style))
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// The merge with every conflict settled by taking the lines of the
+    /// `favor`ed side
+    pub fn to_favored_string(&self, favor: Favor) -> String {
+        self.chunks
+            .iter()
+            .map(|chunk| match (chunk, favor) {
+                (Chunk::Clean { lines }, _) => lines.join(""),
+                (Chunk::Conflict { a_lines, .. }, Favor::Ours) => a_lines.join(""),
+                (Chunk::Conflict { b_lines, .. }, Favor::Theirs) => b_lines.join(""),
+            })
+            .collect::<Vec<_>>()
+            .join("")
+    }
+
+    /// The merge with both sides of every conflict kept, ours first and
+    /// without markers, as the union merge driver leaves it
+    pub fn to_union_string(&self) -> String {
         self.chunks
             .iter()
-            .map(|chunk| chunk.to_string(a_name, b_name))
+            .map(|chunk| match chunk {
+                Chunk::Clean { lines } => lines.join(""),
+                Chunk::Conflict { a_lines, b_lines, .. } => format!("{}{}", a_lines.join(""), b_lines.join("")),
+            })
             .collect::<Vec<_>>()
             .join("")
     }
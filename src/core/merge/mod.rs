@@ -1,5 +1,7 @@
 pub mod bases;
 pub mod common_ancestors;
+pub mod conflict_summary;
 pub mod diff3;
 pub mod inputs;
-pub mod resolve;
\ No newline at end of file
+pub mod resolve;
+pub mod subtree;
\ No newline at end of file
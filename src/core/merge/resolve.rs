@@ -1,8 +1,13 @@
 // src/core/merge/resolve.rs
 use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use crate::core::attributes::AttrValue;
+use crate::core::config::Config;
 use crate::core::database::blob::Blob;
+use crate::core::database::commit::Commit;
 use crate::core::database::database::{Database, GitObject};
 use crate::core::database::entry::DatabaseEntry;
 use crate::core::database::tree::{Tree, TreeEntry};
@@ -10,9 +15,21 @@ use crate::core::file_mode::FileMode;
 use crate::core::index::index::Index;
 use crate::core::workspace::Workspace;
 use crate::errors::error::Error;
-use crate::core::merge::diff3;
+use crate::core::diff::myers;
+use crate::core::diff::rename::{self, RenameDetection};
+use crate::core::merge::diff3::{self, ConflictStyle, Favor};
+use crate::core::merge::conflict_summary::{ConflictKind, ConflictSummary};
 use crate::core::merge::inputs::MergeInputs;
 use crate::core::path_filter::PathFilter;
+use crate::core::repository::git_dir;
+use crate::trace;
+
+// Whether merges follow renamed files; `diff.renames` when not set
+const MERGE_RENAMES_KEY: &str = "merge.renames";
+// Blobs larger than this many bytes are taken as binary without being
+// read in to be merged
+const BIG_FILE_THRESHOLD_KEY: &str = "core.bigFileThreshold";
+const DEFAULT_BIG_FILE_THRESHOLD: usize = 512 * 1024 * 1024;
 
 pub struct Resolve<'a, T: MergeInputs> {
     database: &'a mut Database,
@@ -24,7 +41,10 @@ pub struct Resolve<'a, T: MergeInputs> {
     clean_diff: HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
     conflicts: HashMap<String, Vec<Option<DatabaseEntry>>>,
     untracked: HashMap<String, DatabaseEntry>, // For renamed files in conflicts
+    binary: HashSet<String>, // Conflicts left unmerged as binary files
     pub on_progress: fn(String),
+    // The side that wins what both sides changed, for `-X ours|theirs`
+    pub favor: Option<Favor>,
 }
 
 impl<'a, T: MergeInputs> Resolve<'a, T> {
@@ -44,13 +64,15 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
             clean_diff: HashMap::new(),
             conflicts: HashMap::new(),
             untracked: HashMap::new(),
+            binary: HashSet::new(),
             on_progress: |_info| (),
+            favor: None,
         }
     }
 
      // Main execution logic for recursive merge
      pub fn execute(&mut self) -> Result<(), Error> {
-         println!("Executing merge resolution");
+         trace!("Executing merge resolution");
 
          // Prepare the tree differences and identify conflicts
          self.prepare_tree_diffs()?; // Populates self.conflicts and self.untracked
@@ -66,14 +88,14 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
 
          // Check if conflicts were detected
          if !self.conflicts.is_empty() {
-             println!("Found {} conflicts.", self.conflicts.len());
+             trace!("Found {} conflicts.", self.conflicts.len());
              // Return error indicating conflicts, index lock is kept by caller (main.rs)
              // because index.write_updates() will be called there to save conflict state.
              return Err(Error::Generic("Automatic merge failed; fix conflicts and then commit the result.".into()));
          }
 
          // No conflicts were found during preparation and resolution
-         println!("Merge resolved successfully with no conflicts.");
+         trace!("Merge resolved successfully with no conflicts.");
          Ok(()) // Index lock released by caller (main.rs) via index.write_updates()
      }
 
@@ -89,7 +111,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         // parent-based file/dir conflicts need different handling than direct ones.
 
         let path_str = path.to_string_lossy().to_string();
-        println!("Checking legacy file/dir parent conflict for: {}", path_str);
+        trace!("Checking legacy file/dir parent conflict for: {}", path_str);
 
         // Consider if this loop logic is still needed or if the direct check + parent check in prepare_tree_diffs is sufficient.
         // For now, let's keep it but be aware it might double-log or conflict with other checks.
@@ -102,7 +124,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                       // If parent is a FILE in the other diff map
                       if !new_item.get_file_mode().is_directory() {
                            let parent_path = parent.to_string_lossy().to_string();
-                           println!("Found parent file/dir conflict at: {}", parent_path);
+                           trace!("Found parent file/dir conflict at: {}", parent_path);
                            // ... rest of conflict recording logic ...
                            break; // Stop checking higher parents
                       }
@@ -114,17 +136,17 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
 
 
     fn apply_clean_changes(&mut self) -> Result<(), Error> {
-        println!("Applying {} clean changes...", self.clean_diff.len());
+        trace!("Applying {} clean changes...", self.clean_diff.len());
         let clean_diff_clone = self.clean_diff.clone(); // Clone to allow mutable borrow of self later
         for (path, (_, new_entry_opt)) in clean_diff_clone { // Iterate over the clone
-            println!("  Applying change for: {}", path.display());
+            trace!("  Applying change for: {}", path.display());
             if let Some(new_entry) = new_entry_opt {
                 if !new_entry.get_file_mode().is_directory() {
-                    println!("    Updating file...");
+                    trace!("    Updating file...");
                     // Call helper method using self
                     self.update_workspace_file(&path, new_entry.get_oid(), &new_entry.get_file_mode())?;
                 } else {
-                    println!("    Ensuring directory exists...");
+                    trace!("    Ensuring directory exists...");
                     self.workspace.make_directory(&path)?;
                     // Optionally add directory to index if needed
                     // let stat = self.workspace.stat_file(&path)?;
@@ -132,7 +154,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                 }
             } else {
                 // Entry is None, meaning deletion
-                println!("    Deleting path...");
+                trace!("    Deleting path...");
                 let path_str = path.to_string_lossy().to_string();
                 let full_path = self.workspace.root_path.join(&path); // Use full path for checks
                 if full_path.exists() {
@@ -142,21 +164,21 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                           self.workspace.remove_file(&path)?;
                      }
                 } else {
-                    println!("    Path {} already removed.", path.display());
+                    trace!("    Path {} already removed.", path.display());
                 }
                 self.index.remove(&PathBuf::from(&path_str))?;
             }
         }
-        println!("Finished applying clean changes.");
+        trace!("Finished applying clean changes.");
         Ok(())
     }
 
 
     fn add_conflicts_to_index(&mut self) {
          if self.conflicts.is_empty() { return; }
-         println!("Adding {} conflict entries to index...", self.conflicts.len());
+         trace!("Adding {} conflict entries to index...", self.conflicts.len());
         for (path, entries) in &self.conflicts {
-             println!("  Adding conflict for: {}", path);
+             trace!("  Adding conflict for: {}", path);
             let path_obj = Path::new(path);
             self.index.add_conflict(path_obj, entries.clone()); // Clones Option<DatabaseEntry>
         }
@@ -164,9 +186,9 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
 
     fn write_untracked_files(&mut self) -> Result<(), Error> {
         if self.untracked.is_empty() { return Ok(()); }
-        println!("Writing {} untracked files resulting from conflicts...", self.untracked.len());
+        trace!("Writing {} untracked files resulting from conflicts...", self.untracked.len());
         for (path_str, entry) in &self.untracked {
-             println!("  Writing untracked file: {} (OID: {})", path_str, entry.get_oid());
+             trace!("  Writing untracked file: {} (OID: {})", path_str, entry.get_oid());
              let blob_obj = self.database.load(entry.get_oid())?;
              let content = blob_obj.to_bytes();
              let path_obj = Path::new(path_str);
@@ -177,11 +199,27 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
              }
             self.workspace.write_file(path_obj, &content)?;
         }
-        println!("Successfully wrote all untracked files.");
+        trace!("Successfully wrote all untracked files.");
         Ok(())
     }
 
 
+    /// The conflicts found by `execute`, grouped the way they were reported
+    pub fn conflict_summary(&self) -> ConflictSummary {
+        ConflictSummary::new(self.conflicts.iter().map(|(path, entries)| {
+            let renamed = self.untracked.keys().any(|key| key.starts_with(&format!("{}~", path)));
+            let kind = match (&entries[0], &entries[1], &entries[2]) {
+                _ if renamed => ConflictKind::FileDirectory,
+                _ if self.binary.contains(path) => ConflictKind::Binary,
+                (Some(_), Some(_), Some(_)) => ConflictKind::Content,
+                (None, Some(_), Some(_)) => ConflictKind::AddAdd,
+                (Some(_), _, _) => ConflictKind::ModifyDelete,
+                _ => ConflictKind::FileDirectory,
+            };
+            (path.clone(), kind)
+        }))
+    }
+
     fn parent_directories(&self, path: &Path) -> Vec<PathBuf> {
         let mut result = Vec::new();
         let mut current = PathBuf::from(path);
@@ -214,7 +252,10 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         }
     }
     fn log_left_right_conflict(&self, path: &str) {
-         if let Some(conflict) = self.conflicts.get(path) {
+         if self.binary.contains(path) {
+             self.log(format!("warning: Cannot merge binary files: {} ({} vs. {})", path, self.inputs.left_name(), self.inputs.right_name()));
+             self.log(format!("CONFLICT (binary): Merge conflict in {}", path));
+         } else if let Some(conflict) = self.conflicts.get(path) {
              let base = conflict[0].clone();
             let conflict_type = if base.is_some() { "content" } else { "add/add" };
              self.log(format!("CONFLICT ({}): Merge conflict in {}", conflict_type, path));
@@ -241,8 +282,16 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
     // --- End Logging Functions ---
 
 
+    // Merge the contents of `path` with the driver its `merge` attribute
+    // names: `-merge` (or `binary`) keeps our side and leaves the path
+    // conflicted, `union` keeps the lines of both sides, a name configured
+    // as `merge.<name>.driver` runs that command, and anything else merges
+    // the text line by line. Binary files, and blobs past
+    // `core.bigFileThreshold`, are conflicted as `-merge` ones are,
+    // without markers written over them.
     fn merge_blobs(
         &mut self,
+        path: &Path,
         base_oid: Option<&str>,
         left_oid: Option<&str>,
         right_oid: Option<&str>,
@@ -250,25 +299,119 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         if let Some(result) = Resolve::<T>::merge3_oid(base_oid, left_oid, right_oid) {
             return Ok((true, result.to_string()));
         }
-        let blobs: Vec<String> = vec![base_oid, left_oid, right_oid]
-            .into_iter()
-            .map(|oid| -> Result<String, Error> {
-                if let Some(oid_str) = oid {
-                     if oid_str.len() == 40 && oid_str.chars().all(|c| c.is_ascii_hexdigit()) {
-                         let blob_obj = self.database.load(oid_str)?;
-                         let content = blob_obj.to_bytes();
-                         Ok(String::from_utf8_lossy(&content).to_string())
-                     } else { Ok(String::new()) }
-                } else { Ok(String::new()) }
-            })
-            .collect::<Result<Vec<String>, Error>>()?;
+        let driver = self.workspace.attributes().get(path, "merge");
+        if driver == AttrValue::Unset || driver == AttrValue::Value("binary".to_string()) {
+            return Ok(self.binary_conflict(path, left_oid, right_oid));
+        }
 
-        let merge_result = diff3::merge(&blobs[0], &blobs[1], &blobs[2])?;
-        let result_text = merge_result.to_string( Some(&self.inputs.left_name()), Some(&self.inputs.right_name()), );
+        let oids: Vec<&str> = [base_oid, left_oid, right_oid].into_iter()
+            .flatten()
+            .filter(|oid| oid.len() == 40 && oid.chars().all(|c| c.is_ascii_hexdigit()))
+            .collect();
+        let threshold = self.big_file_threshold();
+        for oid in &oids {
+            if self.database.object_size(oid)? > threshold {
+                return Ok(self.binary_conflict(path, left_oid, right_oid));
+            }
+        }
+        let mut contents: HashMap<&str, Vec<u8>> = HashMap::new();
+        for oid in oids {
+            let content = self.database.load(oid)?.to_bytes();
+            if myers::is_binary_content(&content) {
+                return Ok(self.binary_conflict(path, left_oid, right_oid));
+            }
+            contents.insert(oid, content);
+        }
+        let blobs: Vec<String> = [base_oid, left_oid, right_oid].into_iter()
+            .map(|oid| oid.and_then(|oid| contents.get(oid))
+                .map(|content| String::from_utf8_lossy(content).to_string())
+                .unwrap_or_default())
+            .collect();
+
+        let command = match &driver {
+            AttrValue::Value(name) => self.merge_driver_command(name),
+            _ => None,
+        };
+        let (clean, result_text) = match (&driver, command) {
+            (_, Some(command)) => self.run_merge_driver(&command, path, &blobs)?,
+            (AttrValue::Value(name), None) if name == "union" => {
+                (true, diff3::merge(&blobs[0], &blobs[1], &blobs[2])?.to_union_string())
+            },
+            _ => {
+                let merge_result = diff3::merge(&blobs[0], &blobs[1], &blobs[2])?;
+                // A file deleted on one side is left to be resolved by hand
+                if let Some(favor) = self.favor.filter(|_| left_oid.is_some() && right_oid.is_some()) {
+                    (true, merge_result.to_favored_string(favor))
+                } else {
+                    let style = ConflictStyle::load(&git_dir::resolve(&self.workspace.root_path));
+                    let text = merge_result.to_styled_string(
+                        Some(&self.inputs.left_name()), Some(&self.base_name()), Some(&self.inputs.right_name()), style,
+                    );
+                    (merge_result.is_clean(), text)
+                }
+            },
+        };
         let mut blob = Blob::new(result_text.as_bytes().to_vec());
         self.database.store(&mut blob)?;
         let blob_oid = blob.get_oid().map(|s| s.to_string()).unwrap_or_default();
-        Ok((merge_result.is_clean(), blob_oid))
+        Ok((clean, blob_oid))
+    }
+
+    // Leave `path` conflicted with our side in the working tree as it is,
+    // unless a side is favored and both have the file
+    fn binary_conflict(&mut self, path: &Path, left_oid: Option<&str>, right_oid: Option<&str>) -> (bool, String) {
+        match (self.favor, left_oid, right_oid) {
+            (Some(Favor::Ours), Some(oid), Some(_)) | (Some(Favor::Theirs), Some(_), Some(oid)) => return (true, oid.to_string()),
+            _ => {},
+        }
+        self.binary.insert(path.to_string_lossy().to_string());
+        (false, left_oid.unwrap_or("").to_string())
+    }
+
+    // The label of the base in diff3-style conflicts
+    fn base_name(&self) -> String {
+        match self.inputs.base_oids().as_slice() {
+            [] => "empty tree".to_string(),
+            [oid] => oid.chars().take(7).collect(),
+            _ => "merged common ancestors".to_string(),
+        }
+    }
+
+    fn big_file_threshold(&self) -> usize {
+        Config::load(&git_dir::resolve(&self.workspace.root_path)).ok()
+            .and_then(|config| config.get_size(BIG_FILE_THRESHOLD_KEY))
+            .unwrap_or(DEFAULT_BIG_FILE_THRESHOLD)
+    }
+
+    fn merge_driver_command(&self, name: &str) -> Option<String> {
+        let config = Config::load(&git_dir::resolve(&self.workspace.root_path)).ok()?;
+        config.get(&format!("merge.{}.driver", name))
+    }
+
+    // Run a configured merge driver on the base, ours and theirs written to
+    // temporary files: %O, %A and %B name them, %P is the path and %L the
+    // conflict marker size. The result is what the driver leaves in %A,
+    // clean if it exits with 0.
+    fn run_merge_driver(&self, command: &str, path: &Path, blobs: &[String]) -> Result<(bool, String), Error> {
+        let files: Vec<PathBuf> = ["O", "A", "B"].iter()
+            .map(|stage| std::env::temp_dir().join(format!("ash-merge-{}-{}", std::process::id(), stage)))
+            .collect();
+        for (file, content) in files.iter().zip(blobs) {
+            fs::write(file, content)?;
+        }
+        let command = command
+            .replace("%O", &files[0].to_string_lossy())
+            .replace("%A", &files[1].to_string_lossy())
+            .replace("%B", &files[2].to_string_lossy())
+            .replace("%P", &path.to_string_lossy())
+            .replace("%L", "7");
+        let status = Command::new("sh").arg("-c").arg(&command).status();
+        let result = fs::read_to_string(&files[1]);
+        for file in &files {
+            let _ = fs::remove_file(file);
+        }
+        let status = status.map_err(|e| Error::Generic(format!("cannot run merge driver for {}: {}", path.display(), e)))?;
+        Ok((status.success(), result?))
     }
 
     fn merge_modes(
@@ -281,7 +424,11 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         if right_mode == base_mode { return (true, left_mode.unwrap_or(FileMode::REGULAR)); }
         if left_mode.is_none() { return (right_mode.is_none(), right_mode.unwrap_or(FileMode::REGULAR)); }
         if right_mode.is_none() { return (false, left_mode.unwrap_or(FileMode::REGULAR)); }
-        (false, left_mode.unwrap_or(FileMode::REGULAR))
+        match self.favor {
+            Some(Favor::Ours) => (true, left_mode.unwrap_or(FileMode::REGULAR)),
+            Some(Favor::Theirs) => (true, right_mode.unwrap_or(FileMode::REGULAR)),
+            None => (false, left_mode.unwrap_or(FileMode::REGULAR)),
+        }
     }
 
     // Associated function, no `self`
@@ -294,36 +441,48 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
 
 
     fn prepare_tree_diffs(&mut self) -> Result<(), Error> {
-        println!("Preparing tree diffs for merge");
+        trace!("Preparing tree diffs for merge");
         let base_oids = self.inputs.base_oids();
         let base_oid_opt = base_oids.first().map(String::as_str);
         let path_filter = PathFilter::new();
 
         self.left_diff = self.database.tree_diff( base_oid_opt, Some(&self.inputs.left_oid()), &path_filter, )?;
-        println!("Left diff ({} vs Base) has {} entries", self.inputs.left_name(), self.left_diff.len());
+        trace!("Left diff ({} vs Base) has {} entries", self.inputs.left_name(), self.left_diff.len());
 
         self.right_diff = self.database.tree_diff( base_oid_opt, Some(&self.inputs.right_oid()), &path_filter, )?;
-        println!("Right diff ({} vs Base) has {} entries", self.inputs.right_name(), self.right_diff.len());
+        trace!("Right diff ({} vs Base) has {} entries", self.inputs.right_name(), self.right_diff.len());
 
         self.clean_diff = HashMap::new();
         self.conflicts = HashMap::new();
         self.untracked = HashMap::new();
 
+        if let Some(base_oid) = base_oid_opt {
+            self.apply_directory_renames(base_oid)?;
+            self.apply_file_renames()?;
+        }
+
         let mut all_paths = HashSet::new();
         all_paths.extend(self.left_diff.keys().cloned());
         all_paths.extend(self.right_diff.keys().cloned());
 
         let paths_to_process: Vec<PathBuf> = all_paths.into_iter().collect();
-        println!("Processing {} unique paths", paths_to_process.len());
+        trace!("Processing {} unique paths", paths_to_process.len());
 
         for path in paths_to_process {
-             println!("Processing path: {}", path.display());
+             trace!("Processing path: {}", path.display());
 
              // Clone entries needed for same_path_conflict and potential later use
              let base_entry = self.left_diff.get(&path).and_then(|(old, _)| old.clone())
                  .or_else(|| self.right_diff.get(&path).and_then(|(old, _)| old.clone()));
-             let left_entry = self.left_diff.get(&path).and_then(|(_, new)| new.clone());
-             let right_entry = self.right_diff.get(&path).and_then(|(_, new)| new.clone());
+             // A path missing from one side's diff is unchanged there, i.e. still the base version
+             let left_entry = match self.left_diff.get(&path) {
+                 Some((_, new)) => new.clone(),
+                 None => base_entry.clone(),
+             };
+             let right_entry = match self.right_diff.get(&path) {
+                 Some((_, new)) => new.clone(),
+                 None => base_entry.clone(),
+             };
 
              // Extract booleans needed for parent checks *before* potentially moving entries
              let left_new_is_some = left_entry.is_some();
@@ -349,17 +508,253 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                      }
                  }
               } else {
-                   println!("  Skipping parent conflict check for already conflicted path: {}", path.display());
+                   trace!("  Skipping parent conflict check for already conflicted path: {}", path.display());
               }
         }
 
-        println!("Tree diff processing complete:");
-        println!("  Clean changes: {}", self.clean_diff.len());
-        println!("  Conflicts: {}", self.conflicts.len());
-        println!("  Untracked files: {}", self.untracked.len());
+        trace!("Tree diff processing complete:");
+        trace!("  Clean changes: {}", self.clean_diff.len());
+        trace!("  Conflicts: {}", self.conflicts.len());
+        trace!("  Untracked files: {}", self.untracked.len());
+        Ok(())
+    }
+
+    // When one side renamed a directory, files the other side added to the
+    // old directory are moved into the new one instead of bringing it back
+    fn apply_directory_renames(&mut self, base_oid: &str) -> Result<(), Error> {
+        let removes_files = |diff: &HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>| {
+            diff.values().any(|(old, new)| old.is_some() && new.is_none())
+        };
+        let left_removes = removes_files(&self.left_diff);
+        let right_removes = removes_files(&self.right_diff);
+        if !left_removes && !right_removes {
+            return Ok(());
+        }
+
+        let base_files = self.commit_files(base_oid)?;
+        let (left_name, right_name) = (self.inputs.left_name(), self.inputs.right_name());
+
+        if left_removes {
+            let left_files = self.commit_files(&self.inputs.left_oid())?;
+            let renames = Self::directory_renames(&base_files, &left_files);
+            for (old, new) in Self::relocate_additions(&mut self.right_diff, &renames, &left_files) {
+                self.log_directory_rename(&old, &new, &right_name, &left_name);
+            }
+        }
+
+        if right_removes {
+            let right_files = self.commit_files(&self.inputs.right_oid())?;
+            let renames = Self::directory_renames(&base_files, &right_files);
+            for (old, new) in Self::relocate_additions(&mut self.left_diff, &renames, &right_files) {
+                // The file is already in our tree at its old location
+                if let Some((_, Some(entry))) = self.left_diff.get(&new) {
+                    self.clean_diff.insert(old.clone(), (Some(entry.clone()), None));
+                }
+                self.log_directory_rename(&old, &new, &left_name, &right_name);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Old directory -> new directory, for directories that are gone from
+    // `side` and whose files mostly reappear, unchanged, under one new
+    // directory. Directories whose files scattered evenly are left alone.
+    fn directory_renames(
+        base_files: &HashMap<PathBuf, DatabaseEntry>,
+        side_files: &HashMap<PathBuf, DatabaseEntry>,
+    ) -> HashMap<PathBuf, PathBuf> {
+        let mut side_dirs = HashSet::new();
+        let mut side_by_oid: HashMap<&str, Vec<&PathBuf>> = HashMap::new();
+        for (path, entry) in side_files {
+            side_dirs.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+            side_by_oid.entry(entry.get_oid()).or_default().push(path);
+        }
+
+        let mut votes: HashMap<PathBuf, HashMap<PathBuf, usize>> = HashMap::new();
+        for (path, entry) in base_files {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() && !side_dirs.contains(dir) => dir,
+                _ => continue,
+            };
+            for new_path in side_by_oid.get(entry.get_oid()).into_iter().flatten() {
+                if base_files.contains_key(*new_path) {
+                    continue;
+                }
+                if let Some(new_dir) = new_path.parent() {
+                    *votes.entry(dir.to_path_buf()).or_default().entry(new_dir.to_path_buf()).or_default() += 1;
+                }
+            }
+        }
+
+        let mut renames = HashMap::new();
+        for (dir, targets) in votes {
+            let best = targets.values().copied().max().unwrap_or(0);
+            let mut winners = targets.into_iter().filter(|(_, count)| *count == best);
+            if let (Some((target, _)), None) = (winners.next(), winners.next()) {
+                trace!("Directory {} was renamed to {}", dir.display(), target.display());
+                renames.insert(dir, target);
+            }
+        }
+        renames
+    }
+
+    // Move files added under a renamed directory to the same place under its
+    // new name, unless something already occupies that path
+    fn relocate_additions(
+        diff: &mut HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
+        renames: &HashMap<PathBuf, PathBuf>,
+        renamed_side_files: &HashMap<PathBuf, DatabaseEntry>,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        if renames.is_empty() {
+            return Vec::new();
+        }
+
+        let additions: Vec<PathBuf> = diff.iter()
+            .filter(|(_, (old, new))| {
+                old.is_none() && new.as_ref().is_some_and(|e| !e.get_file_mode().is_directory())
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut moved = Vec::new();
+        for path in additions {
+            // The closest renamed ancestor decides where the file goes
+            let target = path.ancestors().skip(1)
+                .find_map(|dir| renames.get(dir).map(|new_dir| (dir, new_dir)))
+                .and_then(|(dir, new_dir)| path.strip_prefix(dir).ok().map(|rest| new_dir.join(rest)));
+            let target = match target {
+                Some(target) if !diff.contains_key(&target) && !renamed_side_files.contains_key(&target) => target,
+                _ => continue,
+            };
+
+            if let Some((_, Some(entry))) = diff.remove(&path) {
+                let relocated = DatabaseEntry::new(
+                    target.to_string_lossy().to_string(),
+                    entry.get_oid().to_string(),
+                    entry.get_mode(),
+                );
+                diff.insert(target.clone(), (None, Some(relocated)));
+                moved.push((path, target));
+            }
+        }
+        moved
+    }
+
+    // When one side renamed a file, what the other side did to it is merged
+    // at its new path rather than being taken for a change to a file that
+    // is gone
+    fn apply_file_renames(&mut self) -> Result<(), Error> {
+        let git_path = git_dir::resolve(&self.workspace.root_path);
+        let mut detection = RenameDetection::resolve(&[], &git_path)?;
+        if let Some(renames) = Config::load(&git_path).ok().and_then(|config| config.get_bool(MERGE_RENAMES_KEY)) {
+            detection.renames = renames;
+        }
+        if !detection.renames {
+            return Ok(());
+        }
+        detection.copies = false;
+
+        let left_renames = self.file_renames(&self.left_diff.clone(), detection)?;
+        let right_renames = self.file_renames(&self.right_diff.clone(), detection)?;
+        let (left_name, right_name) = (self.inputs.left_name(), self.inputs.right_name());
+        for (from, to) in left_renames {
+            if Self::follow_rename(&mut self.left_diff, &mut self.right_diff, &from, &to, &right_renames) {
+                trace!("{} was renamed to {} in {}", from.display(), to.display(), left_name);
+            }
+        }
+        for (from, to) in right_renames {
+            if Self::follow_rename(&mut self.right_diff, &mut self.left_diff, &from, &to, &[]) {
+                trace!("{} was renamed to {} in {}", from.display(), to.display(), right_name);
+            }
+        }
         Ok(())
     }
 
+    // The files `diff` removed and added again elsewhere, alike enough
+    fn file_renames(
+        &mut self,
+        diff: &HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
+        detection: RenameDetection,
+    ) -> Result<Vec<(PathBuf, PathBuf)>, Error> {
+        let is_file = |entry: &DatabaseEntry| !entry.get_file_mode().is_directory();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for (path, (old, new)) in diff {
+            match (old, new) {
+                (Some(old), None) if is_file(old) => removed.push((path.to_string_lossy().to_string(), old.get_oid().to_string())),
+                (None, Some(new)) if is_file(new) => added.push((path.to_string_lossy().to_string(), new.get_oid().to_string())),
+                _ => {},
+            }
+        }
+        if removed.is_empty() || added.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Sorted so that equally good matches pair the same way every time
+        removed.sort();
+        added.sort();
+
+        Ok(rename::detect(self.database, &removed, &added, &[], detection)?
+            .into_iter()
+            .map(|pair| (PathBuf::from(pair.from), PathBuf::from(pair.to)))
+            .collect())
+    }
+
+    // Make the rename of `from` to `to` on one side a change of `to` on
+    // both, from the base version of `from`: the other side's version of
+    // `from` (changed or not) moves to `to`, and `from` is removed.
+    // A path the other side also renamed (`other_renames`) or already has
+    // something at `to` is left alone. Returns whether the rename was
+    // followed.
+    fn follow_rename(
+        this: &mut HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
+        other: &mut HashMap<PathBuf, (Option<DatabaseEntry>, Option<DatabaseEntry>)>,
+        from: &Path,
+        to: &Path,
+        other_renames: &[(PathBuf, PathBuf)],
+    ) -> bool {
+        let moved = |entry: &DatabaseEntry| DatabaseEntry::new(to.to_string_lossy().to_string(), entry.get_oid().to_string(), entry.get_mode());
+        let base = match this.get(from) {
+            Some((Some(base), None)) => base.clone(),
+            _ => return false,
+        };
+        let renamed = match this.get(to) {
+            Some((None, Some(renamed))) => renamed.clone(),
+            _ => return false,
+        };
+        if other.contains_key(to) || other_renames.iter().any(|(other_from, _)| other_from == from) {
+            return false;
+        }
+
+        let other_version = match other.get(from) {
+            Some((_, new)) => new.as_ref().map(moved),
+            None => Some(moved(&base)),
+        };
+        this.insert(to.to_path_buf(), (Some(moved(&base)), Some(renamed)));
+        other.insert(to.to_path_buf(), (Some(moved(&base)), other_version));
+        // Left as it was on the other side, `from` goes with this side's
+        // removal of it
+        other.remove(from);
+        true
+    }
+
+    fn log_directory_rename(&self, old: &Path, new: &Path, added_in: &str, renamed_in: &str) {
+        self.log(format!(
+            "Path updated: {} added in {} inside a directory that was renamed in {}; moving it to {}.",
+            old.display(), added_in, renamed_in, new.display(),
+        ));
+    }
+
+    // Every file in the tree of `commit_oid`, keyed by path
+    fn commit_files(&mut self, commit_oid: &str) -> Result<HashMap<PathBuf, DatabaseEntry>, Error> {
+        let object = self.database.load(commit_oid)?;
+        let tree_oid = match object.as_any().downcast_ref::<Commit>() {
+            Some(commit) => commit.get_tree().to_string(),
+            None => return Err(Error::Generic(format!("Object {} is not a commit", commit_oid))),
+        };
+        self.gather_files_from_tree(&tree_oid, Path::new(""))
+    }
+
     // Takes &self because logging helpers might need self.inputs
      fn check_parent_dir_conflict(
          &self,
@@ -435,7 +830,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         branch_with_file: &str // The name of the branch where path is a file
     ) -> Result<(), Error> {
         let path_str = path.to_string_lossy().to_string();
-        println!("Handling direct file/directory conflict for {}", path_str);
+        trace!("Handling direct file/directory conflict for {}", path_str);
 
         if self.conflicts.contains_key(&path_str) { return Ok(()); } // Avoid double recording
 
@@ -455,7 +850,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         self.clean_diff.remove(path);
 
         let rename_path = format!("{}~{}", path_str, branch_with_file);
-        println!("  Creating renamed file: {}", rename_path);
+        trace!("  Creating renamed file: {}", rename_path);
         self.untracked.insert(rename_path.clone(), file_entry);
 
         self.log(format!( "CONFLICT (file/directory): '{}' is a file in branch '{}' and a directory in the other.", path_str, branch_with_file ));
@@ -516,6 +911,16 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
              return Ok(());
          }
 
+         // One side left the path alone, so the other side's version wins,
+         // deletions included (merge3_oid cannot report a deletion)
+         if left == base || right == base {
+              let merged = if left == base { right } else { left };
+              if merged != base {
+                  self.clean_diff.insert(path.to_path_buf(), (base, merged));
+              }
+              return Ok(());
+         }
+
          let base_oid_str = base.as_ref().map(|b| b.get_oid());
          let left_oid_str = left.as_ref().map(|l| l.get_oid());
          let right_oid_str = right.as_ref().map(|r| r.get_oid());
@@ -536,7 +941,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
               if let Some(oid) = merged_oid { (true, oid.to_string()) }
               else { (false, left_oid_str.unwrap_or("").to_string()) } // Conflict
          } else {
-              self.merge_blobs(base_oid_str, left_oid_str, right_oid_str)?
+              self.merge_blobs(path, base_oid_str, left_oid_str, right_oid_str)?
          };
 
          let merged_entry = if left.is_some() || right.is_some() {
@@ -570,7 +975,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         left: Option<DatabaseEntry>,
         right: Option<DatabaseEntry>
     ) -> Result<(), Error> {
-        println!("Looking for conflicts in directory: {}", dir_path.display());
+        trace!("Looking for conflicts in directory: {}", dir_path.display());
         
         // Only continue if at least one of the entries is a directory
         let left_is_dir = left.as_ref().map_or(false, |e| e.get_file_mode().is_directory());
@@ -584,18 +989,18 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         let left_dir_oid = left.as_ref().map(|e| e.get_oid());
         let right_dir_oid = right.as_ref().map(|e| e.get_oid());
         
-        println!("Left directory OID: {:?}", left_dir_oid);
-        println!("Right directory OID: {:?}", right_dir_oid);
+        trace!("Left directory OID: {:?}", left_dir_oid);
+        trace!("Right directory OID: {:?}", right_dir_oid);
         
         // Gather files from both left and right directories
         let mut left_files = HashMap::new(); 
         let mut right_files = HashMap::new();
         
         if let Some(oid) = left_dir_oid {
-            println!("Gathering files from left directory OID: {}", oid);
+            trace!("Gathering files from left directory OID: {}", oid);
             match self.gather_files_from_tree(oid, dir_path) {
                 Ok(files) => {
-                    println!("Found {} files in left directory", files.len());
+                    trace!("Found {} files in left directory", files.len());
                     left_files = files;
                 },
                 Err(e) => println!("Error gathering left files: {}", e)
@@ -603,10 +1008,10 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         }
         
         if let Some(oid) = right_dir_oid {
-            println!("Gathering files from right directory OID: {}", oid);
+            trace!("Gathering files from right directory OID: {}", oid);
             match self.gather_files_from_tree(oid, dir_path) {
                 Ok(files) => {
-                    println!("Found {} files in right directory", files.len());
+                    trace!("Found {} files in right directory", files.len());
                     right_files = files;
                 },
                 Err(e) => println!("Error gathering right files: {}", e)
@@ -622,7 +1027,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
             all_paths.insert(path.clone());
         }
         
-        println!("Total unique paths from both directories: {}", all_paths.len());
+        trace!("Total unique paths from both directories: {}", all_paths.len());
         
         // Check each path for conflicts
         let mut found_conflicts = false;
@@ -633,17 +1038,17 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
             let left_oid = left_entry.as_ref().map(|e| e.get_oid());
             let right_oid = right_entry.as_ref().map(|e| e.get_oid());
             
-            println!("Checking path: {} (left OID: {:?}, right OID: {:?})", 
+            trace!("Checking path: {} (left OID: {:?}, right OID: {:?})", 
                      path.display(), left_oid, right_oid);
             
             // Skip if entries match (same OID)
             if left_oid == right_oid && left_oid.is_some() {
-                println!("  Entries match, skipping");
+                trace!("  Entries match, skipping");
                 continue;
             }
             
             // Record conflict for this file
-            println!("Found conflict for file: {}", path.display());
+            trace!("Found conflict for file: {}", path.display());
             found_conflicts = true;
             
             // Create a conflict entry for this file
@@ -662,7 +1067,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         if found_conflicts {
             let dir_path_str = dir_path.to_string_lossy().to_string();
             if self.conflicts.contains_key(&dir_path_str) {
-                println!("Removing directory conflict entry for {} as individual file conflicts were found", dir_path_str);
+                trace!("Removing directory conflict entry for {} as individual file conflicts were found", dir_path_str);
                 self.conflicts.remove(&dir_path_str);
             }
         }
@@ -680,10 +1085,10 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
         
         let obj = self.database.load(oid)?;
         if let Some(tree) = obj.as_any().downcast_ref::<Tree>() {
-            println!("Loaded tree for {}: {}", prefix.display(), tree.get_oid().map_or("unknown".to_string(), |s| s.to_string()));
+            trace!("Loaded tree for {}: {}", prefix.display(), tree.get_oid().map_or("unknown".to_string(), |s| s.to_string()));
             for (name, entry) in tree.get_entries() {
                 let entry_path = prefix.join(name);
-                println!("  Found tree entry: {} ({})", entry_path.display(), 
+                trace!("  Found tree entry: {} ({})", entry_path.display(), 
                          if let TreeEntry::Blob(_, mode) = &entry { 
                              if mode.is_directory() { "directory" } else { "file" } 
                          } else { "tree" });
@@ -694,7 +1099,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                             // For directories, we need to get the Tree object and process it
                             let subtree_obj = self.database.load(&blob_oid)?;
                             if let Some(subtree) = subtree_obj.as_any().downcast_ref::<Tree>() {
-                                println!("    Processing subtree: {}", subtree.get_oid().map_or("unknown".to_string(), |s| s.to_string()));
+                                trace!("    Processing subtree: {}", subtree.get_oid().map_or("unknown".to_string(), |s| s.to_string()));
                                 let subtree_oid = subtree.get_oid().map_or("".to_string(), |s| s.to_string());
                                 if !subtree_oid.is_empty() {
                                     let subtree_files = self.gather_files_from_tree(&subtree_oid, &entry_path)?;
@@ -703,7 +1108,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                             }
                         } else {
                             // Regular file
-                            println!("    Adding file: {} ({})", entry_path.display(), blob_oid);
+                            trace!("    Adding file: {} ({})", entry_path.display(), blob_oid);
                             let entry = DatabaseEntry::new(
                                 entry_path.to_string_lossy().to_string(),
                                 blob_oid.clone(),
@@ -714,7 +1119,7 @@ impl<'a, T: MergeInputs> Resolve<'a, T> {
                     },
                     TreeEntry::Tree(subtree) => {
                         if let Some(subtree_oid) = subtree.get_oid() {
-                            println!("    Processing direct tree: {}", subtree_oid);
+                            trace!("    Processing direct tree: {}", subtree_oid);
                             let subtree_files = self.gather_files_from_tree(subtree_oid, &entry_path)?;
                             files.extend(subtree_files);
                         }
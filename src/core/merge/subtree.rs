@@ -0,0 +1,100 @@
+// src/core/merge/subtree.rs - merging a project kept in a subdirectory
+//
+// A subtree merge merges another project's history into the directory its
+// files were read into. Their side, and the base, are shifted under that
+// prefix first, onto our tree, so that the merge only sees the changes
+// each side made to the project's files.
+use std::collections::{BTreeMap, HashMap};
+
+use crate::commands::write_tree::WriteTreeCommand;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::database::tree::{Tree, TreeEntry};
+use crate::core::path_filter::PathFilter;
+use crate::core::repository::autostash::current_author;
+use crate::errors::error::Error;
+
+/// The tree of `commit`, or of a tree itself
+pub fn root_tree(database: &mut Database, oid: &str) -> Result<String, Error> {
+    let object = database.load(oid)?;
+    Ok(match object.as_any().downcast_ref::<Commit>() {
+        Some(commit) => commit.get_tree().to_string(),
+        None => oid.to_string(),
+    })
+}
+
+/// The tree at `prefix` in the tree of `oid`, if there is a directory there
+pub fn subtree_oid(database: &mut Database, oid: &str, prefix: &str) -> Result<Option<String>, Error> {
+    let mut tree_oid = root_tree(database, oid)?;
+    for name in prefix.split('/').filter(|name| !name.is_empty()) {
+        let object = database.load(&tree_oid)?;
+        let tree = match object.as_any().downcast_ref::<Tree>() {
+            Some(tree) => tree,
+            None => return Ok(None),
+        };
+        tree_oid = match tree.get_entry(name) {
+            Some(TreeEntry::Tree(subtree)) => match subtree.get_oid() {
+                Some(oid) => oid.clone(),
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+    }
+    Ok(Some(tree_oid))
+}
+
+/// The directory of `ours` that the files of `theirs` are most alike, by
+/// how many of them it has with the same contents
+pub fn find_prefix(database: &mut Database, ours: &str, theirs: &[String]) -> Result<Option<String>, Error> {
+    let our_files = files(database, ours)?;
+    let mut their_files = Vec::new();
+    for oid in theirs {
+        their_files.push(files(database, oid)?);
+    }
+
+    let mut scores: BTreeMap<String, usize> = BTreeMap::new();
+    for (path, entry) in &our_files {
+        let mut rest = path.as_str();
+        while let Some((dir, _)) = rest.rsplit_once('/') {
+            let relative = &path[dir.len() + 1..];
+            let matches = their_files.iter()
+                .filter(|files| files.get(relative).is_some_and(|their| their.get_oid() == entry.get_oid()))
+                .count();
+            *scores.entry(dir.to_string()).or_default() += matches;
+            rest = dir;
+        }
+    }
+    let best = scores.into_iter().filter(|(_, score)| *score > 0).max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+    Ok(best.map(|(dir, _)| dir))
+}
+
+/// A commit of our tree with `theirs`, when given, read in at `prefix`
+/// in place of what is there, for the merge to take as their side or the
+/// base
+pub fn shift(database: &mut Database, ours: &str, theirs: Option<&str>, prefix: &str) -> Result<String, Error> {
+    let under = format!("{}/", prefix);
+    let mut entries: Vec<DatabaseEntry> = files(database, ours)?.into_values()
+        .filter(|entry| !entry.get_name().starts_with(&under))
+        .collect();
+    if let Some(theirs) = theirs {
+        entries.extend(files(database, theirs)?.into_values()
+            .map(|entry| DatabaseEntry::new(format!("{}{}", under, entry.get_name()), entry.get_oid().to_string(), entry.get_mode())));
+    }
+
+    let tree_oid = WriteTreeCommand::write(database, &entries)?;
+    let mut commit = Commit::new(None, tree_oid, current_author(), format!("subtree shifted into {}", prefix));
+    database.store(&mut commit)?;
+    commit.get_oid().cloned().ok_or_else(|| Error::Generic("Commit OID not set after storage".to_string()))
+}
+
+// Every file of the tree of `oid`, by path, each entry named by its path
+fn files(database: &mut Database, oid: &str) -> Result<HashMap<String, DatabaseEntry>, Error> {
+    let diff = database.tree_diff(None, Some(oid), &PathFilter::new())?;
+    Ok(diff.into_iter()
+        .filter_map(|(path, (_, new))| new.map(|entry| {
+            let path = path.to_string_lossy().to_string();
+            (path.clone(), DatabaseEntry::new(path, entry.get_oid().to_string(), entry.get_mode()))
+        }))
+        .collect())
+}
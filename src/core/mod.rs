@@ -13,4 +13,21 @@ pub mod path_filter;
 pub mod revlist;
 pub mod merge;
 pub mod metadata;
-pub mod editor;
\ No newline at end of file
+pub mod editor;
+pub mod config;
+pub mod trace;
+pub mod protocol;
+pub mod remote;
+pub mod notes;
+pub mod comments;
+pub mod blame;pub mod reflog;
+pub mod shortlog;
+pub mod advice;
+pub mod hooks;
+pub mod ignore;
+pub mod attributes;
+pub mod gpg;
+pub mod graph;
+pub mod mailbox;
+pub mod path_encoding;
+pub mod column;
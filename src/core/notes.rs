@@ -0,0 +1,106 @@
+// src/core/notes.rs - text attached to commits without changing them
+use std::collections::BTreeMap;
+
+use crate::core::database::author::Author;
+use crate::core::database::blob::Blob;
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::database::tree::{Tree, TreeEntry};
+use crate::core::file_mode::FileMode;
+use crate::core::refs::Refs;
+use crate::errors::error::Error;
+
+/// A notes ref such as `refs/notes/comments` points at a commit whose tree
+/// holds one blob per annotated commit, named by that commit's ID. Every
+/// change to the notes is a new commit, so the notes have their own history
+/// and can be fetched like any other ref.
+pub struct Notes<'a> {
+    database: &'a mut Database,
+    refs: &'a Refs,
+    ref_name: String,
+}
+
+impl<'a> Notes<'a> {
+    pub fn new(database: &'a mut Database, refs: &'a Refs, ref_name: &str) -> Self {
+        Notes {
+            database,
+            refs,
+            ref_name: ref_name.to_string(),
+        }
+    }
+
+    /// The note on `commit_oid`, if any
+    pub fn read(&mut self, commit_oid: &str) -> Result<Option<String>, Error> {
+        let notes = self.load()?;
+        match notes.get(commit_oid) {
+            Some(blob_oid) => {
+                let data = self.database.load(blob_oid)?.to_bytes();
+                Ok(Some(String::from_utf8_lossy(&data).to_string()))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Every annotated commit with its note
+    pub fn list(&mut self) -> Result<Vec<(String, String)>, Error> {
+        let notes = self.load()?;
+        let mut result = Vec::new();
+        for (commit_oid, blob_oid) in notes {
+            let data = self.database.load(&blob_oid)?.to_bytes();
+            result.push((commit_oid, String::from_utf8_lossy(&data).to_string()));
+        }
+        Ok(result)
+    }
+
+    /// Replace the note on `commit_oid`, recording the change as a new notes commit
+    pub fn write(&mut self, commit_oid: &str, text: &str, author: Author, message: &str) -> Result<(), Error> {
+        let mut notes = self.load()?;
+
+        let mut blob = Blob::new(text.as_bytes().to_vec());
+        let blob_oid = self.database.store(&mut blob)?;
+        notes.insert(commit_oid.to_string(), blob_oid);
+
+        let entries: Vec<DatabaseEntry> = notes.into_iter()
+            .map(|(name, oid)| DatabaseEntry::new(name, oid, &FileMode::REGULAR.to_octal_string()))
+            .collect();
+        let mut tree = Tree::build(entries.iter())?;
+        tree.traverse(|t| {
+            self.database.store(t)?;
+            Ok(())
+        })?;
+        let tree_oid = tree.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Tree OID not set after storage".into()))?;
+
+        let parent = self.refs.read_ref(&self.ref_name)?;
+        let mut commit = Commit::new(parent, tree_oid, author, message.to_string());
+        let oid = self.database.store(&mut commit)?;
+        self.refs.update_ref(&self.ref_name, &oid, &format!("notes: {}", message))
+    }
+
+    // Annotated commit -> note blob, from the tip of the notes ref
+    fn load(&mut self) -> Result<BTreeMap<String, String>, Error> {
+        let mut notes = BTreeMap::new();
+
+        let tip = match self.refs.read_ref(&self.ref_name)? {
+            Some(oid) => oid,
+            None => return Ok(notes),
+        };
+        let commit_obj = self.database.load(&tip)?;
+        let tree_oid = match commit_obj.as_any().downcast_ref::<Commit>() {
+            Some(commit) => commit.get_tree().to_string(),
+            None => return Err(Error::Generic(format!("{} does not point to a commit", self.ref_name))),
+        };
+
+        let tree_obj = self.database.load(&tree_oid)?;
+        if let Some(tree) = tree_obj.as_any().downcast_ref::<Tree>() {
+            for (name, entry) in tree.get_entries() {
+                if let TreeEntry::Blob(oid, _) = entry {
+                    notes.insert(name.clone(), oid.clone());
+                }
+            }
+        }
+
+        Ok(notes)
+    }
+}
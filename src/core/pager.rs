@@ -4,12 +4,17 @@ use std::io::{self, Write};
 use std::process::{Command, Stdio};
 use crate::errors::error::Error;
 
+/// The variables that name the pager, the first one set winning
+pub const PAGER_VARIABLES: [&str; 2] = ["ASH_PAGER", "PAGER"];
+
 pub struct Pager {
     enabled: bool,
     command: String,
     process: Option<std::process::Child>,
     stdout: Option<std::process::ChildStdin>,
     early_exit: bool,  // Flag to track if user exited pager early
+    // What is written while capturing, kept back instead of shown
+    captured: Option<String>,
 }
 
 impl Pager {
@@ -28,40 +33,14 @@ impl Pager {
                 process: None,
                 stdout: None,
                 early_exit: false,
+                captured: None,
             };
         }
         
         // Check if there's an explicitly set pager command
-        let command = if let Ok(pager) = env::var("ASH_PAGER") {
-            pager
-        } else if let Ok(pager) = env::var("PAGER") {
-            pager
-        } else {
-            // Auto-detect available pager
-            let candidates = ["less", "more", "cat", "pager"];
-            for cmd in candidates {
-                if Self::command_exists(cmd) {
-                    if cmd == "less" {
-                        return Pager {
-                            enabled: true,
-                            command: "less -FRX".to_string(), // -F: quit if one screen, -R: preserve ANSI colors, -X: don't clear screen
-                            process: None,
-                            stdout: None,
-                            early_exit: false,
-                        };
-                    }
-                    return Pager {
-                        enabled: true,
-                        command: cmd.to_string(),
-                        process: None,
-                        stdout: None,
-                        early_exit: false,
-                    };
-                }
-            }
-            // If no pager is found, we'll use stdout directly
-            "cat".to_string()
-        };
+        let command = PAGER_VARIABLES.iter()
+            .find_map(|name| env::var(name).ok())
+            .unwrap_or_else(Self::default_command);
         
         Pager {
             enabled: true,
@@ -69,9 +48,27 @@ impl Pager {
             process: None,
             stdout: None,
             early_exit: false,
+            captured: None,
         }
     }
     
+    /// The pager used when no variable names one: the first of less, more,
+    /// cat and pager installed, else cat
+    pub fn default_command() -> String {
+        let candidates = ["less", "more", "cat", "pager"];
+        for cmd in candidates {
+            if Self::command_exists(cmd) {
+                if cmd == "less" {
+                    // -F: quit if one screen, -R: preserve ANSI colors, -X: don't clear screen
+                    return "less -FRX".to_string();
+                }
+                return cmd.to_string();
+            }
+        }
+        // If no pager is found, we'll use stdout directly
+        "cat".to_string()
+    }
+    
     /// Check if a command exists in the system
     fn command_exists(cmd: &str) -> bool {
         let check_cmd = if cfg!(target_os = "windows") {
@@ -145,15 +142,27 @@ impl Pager {
     
     /// Write text to the pager
     pub fn write(&mut self, text: &str) -> Result<(), Error> {
-        // If pager is not enabled or user exited, don't write anything
-        if !self.enabled || self.early_exit {
+        if let Some(captured) = &mut self.captured {
+            captured.push_str(text);
+            return Ok(());
+        }
+
+        // If user exited, don't write anything
+        if self.early_exit {
             return Ok(());
         }
         
-        // If no stdout handle, write directly
-        if self.stdout.is_none() {
-            print!("{}", text);
-            io::stdout().flush().map_err(|e| Error::IO(e))?;
+        // Without a pager (output is not a terminal), write directly
+        if !self.enabled || self.stdout.is_none() {
+            let mut stdout = io::stdout();
+            match stdout.write_all(text.as_bytes()).and_then(|_| stdout.flush()) {
+                Ok(_) => {},
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                    // Whatever reads the output stopped, as `head` does
+                    self.early_exit = true;
+                },
+                Err(e) => return Err(Error::IO(e)),
+            }
             return Ok(());
         }
         
@@ -186,6 +195,17 @@ impl Pager {
         Ok(())
     }
     
+    /// Keep back what is written from now on, for `end_capture` to hand
+    /// over, so that it can be reworked before it is shown
+    pub fn begin_capture(&mut self) {
+        self.captured = Some(String::new());
+    }
+
+    /// What was written since `begin_capture`; writing shows text again
+    pub fn end_capture(&mut self) -> String {
+        self.captured.take().unwrap_or_default()
+    }
+
     /// Close the pager and wait for the process to terminate
     pub fn close(&mut self) -> Result<(), Error> {
         // If user already exited pager, just clean up
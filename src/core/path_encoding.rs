@@ -0,0 +1,264 @@
+// src/core/path_encoding.rs - how file names are read in and written out
+//
+// Two settings in the repository's config decide it:
+//
+// ```text
+// [core]
+//     quotePath = false
+//     precomposeUnicode = true
+// ```
+//
+// `core.quotePath`, on by default, writes names with bytes past ASCII in
+// double quotes with octal escapes, as `"\303\251t\303\251"`; off, they are
+// written as they are, and only names with control characters, quotes or
+// backslashes are quoted. `core.precomposeUnicode`, off by default, turns
+// names read from the working tree in decomposed form, as macOS file
+// systems hand them out, into the composed form other systems use, so
+// that the same name is tracked whichever system added it.
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+
+const QUOTE_PATH_KEY: &str = "core.quotePath";
+const PRECOMPOSE_UNICODE_KEY: &str = "core.precomposeUnicode";
+
+/// Whether names with bytes past ASCII are quoted in the current repository
+pub fn quote_path() -> bool {
+    static QUOTE_PATH: OnceLock<bool> = OnceLock::new();
+    *QUOTE_PATH.get_or_init(|| {
+        Config::load(&git_dir::resolve(Path::new("."))).ok()
+            .and_then(|config| config.get_bool(QUOTE_PATH_KEY))
+            .unwrap_or(true)
+    })
+}
+
+/// Whether names read from the working tree under `root_path` are composed
+pub fn precompose_unicode(root_path: &Path) -> bool {
+    Config::load(&git_dir::resolve(root_path)).ok()
+        .and_then(|config| config.get_bool(PRECOMPOSE_UNICODE_KEY))
+        .unwrap_or(false)
+}
+
+/// `path` as it is written in output: as it is, or in double quotes with
+/// C escapes when it has characters that would be lost or misread, bytes
+/// past ASCII among them when `quote_path` is set
+pub fn quote(path: &str, quote_path: bool) -> String {
+    quote_bytes(path.as_bytes(), quote_path)
+}
+
+/// `quote` for a name that need not be UTF-8
+pub fn quote_bytes(name: &[u8], quote_path: bool) -> String {
+    let needs_quoting = |byte: u8| byte < 0x20 || byte == b'"' || byte == b'\\' || byte == 0x7f || (quote_path && byte >= 0x80);
+    if !name.iter().any(|&byte| needs_quoting(byte)) {
+        return String::from_utf8_lossy(name).to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    let mut rest = name;
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b'\x07' => quoted.push_str("\\a"),
+            b'\x08' => quoted.push_str("\\b"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\n' => quoted.push_str("\\n"),
+            b'\x0b' => quoted.push_str("\\v"),
+            b'\x0c' => quoted.push_str("\\f"),
+            b'\r' => quoted.push_str("\\r"),
+            _ if needs_quoting(byte) => quoted.push_str(&format!("\\{:03o}", byte)),
+            _ if byte < 0x80 => quoted.push(byte as char),
+            _ => {
+                // A whole character left unquoted, or a stray byte escaped
+                let length = match byte { 0xc0..=0xdf => 2, 0xe0..=0xef => 3, _ => 4 };
+                match rest.get(..length).and_then(|bytes| std::str::from_utf8(bytes).ok()) {
+                    Some(character) => {
+                        quoted.push_str(character);
+                        rest = &rest[length..];
+                        continue;
+                    },
+                    None => quoted.push_str(&format!("\\{:03o}", byte)),
+                }
+            },
+        }
+        rest = tail;
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `name` with each letter and the combining marks after it composed into
+/// one character where Unicode has one, as NFC does for Latin letters and
+/// Hangul syllables
+pub fn precompose(name: &str) -> String {
+    let mut composed = String::with_capacity(name.len());
+    let mut last: Option<char> = None;
+    for c in name.chars() {
+        if let Some(base) = last {
+            if let Some(pair) = compose_pair(base, c) {
+                last = Some(pair);
+                continue;
+            }
+            composed.push(base);
+        }
+        last = Some(c);
+    }
+    composed.extend(last);
+    composed
+}
+
+// Hangul syllables are composed by arithmetic rather than by table
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+const HANGUL_S_COUNT: u32 = 19 * HANGUL_V_COUNT * HANGUL_T_COUNT;
+
+fn compose_pair(base: char, mark: char) -> Option<char> {
+    let (b, m) = (base as u32, mark as u32);
+    if (HANGUL_L_BASE..HANGUL_L_BASE + 19).contains(&b) && (HANGUL_V_BASE..HANGUL_V_BASE + HANGUL_V_COUNT).contains(&m) {
+        let syllable = HANGUL_S_BASE + ((b - HANGUL_L_BASE) * HANGUL_V_COUNT + (m - HANGUL_V_BASE)) * HANGUL_T_COUNT;
+        return char::from_u32(syllable);
+    }
+    if (HANGUL_S_BASE..HANGUL_S_BASE + HANGUL_S_COUNT).contains(&b) && (b - HANGUL_S_BASE).is_multiple_of(HANGUL_T_COUNT)
+        && (HANGUL_T_BASE + 1..HANGUL_T_BASE + HANGUL_T_COUNT).contains(&m) {
+        return char::from_u32(b + m - HANGUL_T_BASE);
+    }
+    COMPOSITIONS.binary_search_by(|&(b, m, _)| (b, m).cmp(&(base, mark)))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+// The canonical compositions of a letter and one combining mark into the
+// Latin-1, Latin Extended and Latin Extended Additional blocks, sorted
+const COMPOSITIONS: &[(char, char, char)] = &[
+    ('\u{0041}', '\u{0300}', '\u{00C0}'), ('\u{0041}', '\u{0301}', '\u{00C1}'), ('\u{0041}', '\u{0302}', '\u{00C2}'), ('\u{0041}', '\u{0303}', '\u{00C3}'),
+    ('\u{0041}', '\u{0304}', '\u{0100}'), ('\u{0041}', '\u{0306}', '\u{0102}'), ('\u{0041}', '\u{0307}', '\u{0226}'), ('\u{0041}', '\u{0308}', '\u{00C4}'),
+    ('\u{0041}', '\u{0309}', '\u{1EA2}'), ('\u{0041}', '\u{030A}', '\u{00C5}'), ('\u{0041}', '\u{030C}', '\u{01CD}'), ('\u{0041}', '\u{030F}', '\u{0200}'),
+    ('\u{0041}', '\u{0311}', '\u{0202}'), ('\u{0041}', '\u{0323}', '\u{1EA0}'), ('\u{0041}', '\u{0325}', '\u{1E00}'), ('\u{0041}', '\u{0328}', '\u{0104}'),
+    ('\u{0042}', '\u{0307}', '\u{1E02}'), ('\u{0042}', '\u{0323}', '\u{1E04}'), ('\u{0042}', '\u{0331}', '\u{1E06}'), ('\u{0043}', '\u{0301}', '\u{0106}'),
+    ('\u{0043}', '\u{0302}', '\u{0108}'), ('\u{0043}', '\u{0307}', '\u{010A}'), ('\u{0043}', '\u{030C}', '\u{010C}'), ('\u{0043}', '\u{0327}', '\u{00C7}'),
+    ('\u{0044}', '\u{0307}', '\u{1E0A}'), ('\u{0044}', '\u{030C}', '\u{010E}'), ('\u{0044}', '\u{0323}', '\u{1E0C}'), ('\u{0044}', '\u{0327}', '\u{1E10}'),
+    ('\u{0044}', '\u{032D}', '\u{1E12}'), ('\u{0044}', '\u{0331}', '\u{1E0E}'), ('\u{0045}', '\u{0300}', '\u{00C8}'), ('\u{0045}', '\u{0301}', '\u{00C9}'),
+    ('\u{0045}', '\u{0302}', '\u{00CA}'), ('\u{0045}', '\u{0303}', '\u{1EBC}'), ('\u{0045}', '\u{0304}', '\u{0112}'), ('\u{0045}', '\u{0306}', '\u{0114}'),
+    ('\u{0045}', '\u{0307}', '\u{0116}'), ('\u{0045}', '\u{0308}', '\u{00CB}'), ('\u{0045}', '\u{0309}', '\u{1EBA}'), ('\u{0045}', '\u{030C}', '\u{011A}'),
+    ('\u{0045}', '\u{030F}', '\u{0204}'), ('\u{0045}', '\u{0311}', '\u{0206}'), ('\u{0045}', '\u{0323}', '\u{1EB8}'), ('\u{0045}', '\u{0327}', '\u{0228}'),
+    ('\u{0045}', '\u{0328}', '\u{0118}'), ('\u{0045}', '\u{032D}', '\u{1E18}'), ('\u{0045}', '\u{0330}', '\u{1E1A}'), ('\u{0046}', '\u{0307}', '\u{1E1E}'),
+    ('\u{0047}', '\u{0301}', '\u{01F4}'), ('\u{0047}', '\u{0302}', '\u{011C}'), ('\u{0047}', '\u{0304}', '\u{1E20}'), ('\u{0047}', '\u{0306}', '\u{011E}'),
+    ('\u{0047}', '\u{0307}', '\u{0120}'), ('\u{0047}', '\u{030C}', '\u{01E6}'), ('\u{0047}', '\u{0327}', '\u{0122}'), ('\u{0048}', '\u{0302}', '\u{0124}'),
+    ('\u{0048}', '\u{0307}', '\u{1E22}'), ('\u{0048}', '\u{0308}', '\u{1E26}'), ('\u{0048}', '\u{030C}', '\u{021E}'), ('\u{0048}', '\u{0323}', '\u{1E24}'),
+    ('\u{0048}', '\u{0327}', '\u{1E28}'), ('\u{0048}', '\u{032E}', '\u{1E2A}'), ('\u{0049}', '\u{0300}', '\u{00CC}'), ('\u{0049}', '\u{0301}', '\u{00CD}'),
+    ('\u{0049}', '\u{0302}', '\u{00CE}'), ('\u{0049}', '\u{0303}', '\u{0128}'), ('\u{0049}', '\u{0304}', '\u{012A}'), ('\u{0049}', '\u{0306}', '\u{012C}'),
+    ('\u{0049}', '\u{0307}', '\u{0130}'), ('\u{0049}', '\u{0308}', '\u{00CF}'), ('\u{0049}', '\u{0309}', '\u{1EC8}'), ('\u{0049}', '\u{030C}', '\u{01CF}'),
+    ('\u{0049}', '\u{030F}', '\u{0208}'), ('\u{0049}', '\u{0311}', '\u{020A}'), ('\u{0049}', '\u{0323}', '\u{1ECA}'), ('\u{0049}', '\u{0328}', '\u{012E}'),
+    ('\u{0049}', '\u{0330}', '\u{1E2C}'), ('\u{004A}', '\u{0302}', '\u{0134}'), ('\u{004B}', '\u{0301}', '\u{1E30}'), ('\u{004B}', '\u{030C}', '\u{01E8}'),
+    ('\u{004B}', '\u{0323}', '\u{1E32}'), ('\u{004B}', '\u{0327}', '\u{0136}'), ('\u{004B}', '\u{0331}', '\u{1E34}'), ('\u{004C}', '\u{0301}', '\u{0139}'),
+    ('\u{004C}', '\u{030C}', '\u{013D}'), ('\u{004C}', '\u{0323}', '\u{1E36}'), ('\u{004C}', '\u{0327}', '\u{013B}'), ('\u{004C}', '\u{032D}', '\u{1E3C}'),
+    ('\u{004C}', '\u{0331}', '\u{1E3A}'), ('\u{004D}', '\u{0301}', '\u{1E3E}'), ('\u{004D}', '\u{0307}', '\u{1E40}'), ('\u{004D}', '\u{0323}', '\u{1E42}'),
+    ('\u{004E}', '\u{0300}', '\u{01F8}'), ('\u{004E}', '\u{0301}', '\u{0143}'), ('\u{004E}', '\u{0303}', '\u{00D1}'), ('\u{004E}', '\u{0307}', '\u{1E44}'),
+    ('\u{004E}', '\u{030C}', '\u{0147}'), ('\u{004E}', '\u{0323}', '\u{1E46}'), ('\u{004E}', '\u{0327}', '\u{0145}'), ('\u{004E}', '\u{032D}', '\u{1E4A}'),
+    ('\u{004E}', '\u{0331}', '\u{1E48}'), ('\u{004F}', '\u{0300}', '\u{00D2}'), ('\u{004F}', '\u{0301}', '\u{00D3}'), ('\u{004F}', '\u{0302}', '\u{00D4}'),
+    ('\u{004F}', '\u{0303}', '\u{00D5}'), ('\u{004F}', '\u{0304}', '\u{014C}'), ('\u{004F}', '\u{0306}', '\u{014E}'), ('\u{004F}', '\u{0307}', '\u{022E}'),
+    ('\u{004F}', '\u{0308}', '\u{00D6}'), ('\u{004F}', '\u{0309}', '\u{1ECE}'), ('\u{004F}', '\u{030B}', '\u{0150}'), ('\u{004F}', '\u{030C}', '\u{01D1}'),
+    ('\u{004F}', '\u{030F}', '\u{020C}'), ('\u{004F}', '\u{0311}', '\u{020E}'), ('\u{004F}', '\u{031B}', '\u{01A0}'), ('\u{004F}', '\u{0323}', '\u{1ECC}'),
+    ('\u{004F}', '\u{0328}', '\u{01EA}'), ('\u{0050}', '\u{0301}', '\u{1E54}'), ('\u{0050}', '\u{0307}', '\u{1E56}'), ('\u{0052}', '\u{0301}', '\u{0154}'),
+    ('\u{0052}', '\u{0307}', '\u{1E58}'), ('\u{0052}', '\u{030C}', '\u{0158}'), ('\u{0052}', '\u{030F}', '\u{0210}'), ('\u{0052}', '\u{0311}', '\u{0212}'),
+    ('\u{0052}', '\u{0323}', '\u{1E5A}'), ('\u{0052}', '\u{0327}', '\u{0156}'), ('\u{0052}', '\u{0331}', '\u{1E5E}'), ('\u{0053}', '\u{0301}', '\u{015A}'),
+    ('\u{0053}', '\u{0302}', '\u{015C}'), ('\u{0053}', '\u{0307}', '\u{1E60}'), ('\u{0053}', '\u{030C}', '\u{0160}'), ('\u{0053}', '\u{0323}', '\u{1E62}'),
+    ('\u{0053}', '\u{0326}', '\u{0218}'), ('\u{0053}', '\u{0327}', '\u{015E}'), ('\u{0054}', '\u{0307}', '\u{1E6A}'), ('\u{0054}', '\u{030C}', '\u{0164}'),
+    ('\u{0054}', '\u{0323}', '\u{1E6C}'), ('\u{0054}', '\u{0326}', '\u{021A}'), ('\u{0054}', '\u{0327}', '\u{0162}'), ('\u{0054}', '\u{032D}', '\u{1E70}'),
+    ('\u{0054}', '\u{0331}', '\u{1E6E}'), ('\u{0055}', '\u{0300}', '\u{00D9}'), ('\u{0055}', '\u{0301}', '\u{00DA}'), ('\u{0055}', '\u{0302}', '\u{00DB}'),
+    ('\u{0055}', '\u{0303}', '\u{0168}'), ('\u{0055}', '\u{0304}', '\u{016A}'), ('\u{0055}', '\u{0306}', '\u{016C}'), ('\u{0055}', '\u{0308}', '\u{00DC}'),
+    ('\u{0055}', '\u{0309}', '\u{1EE6}'), ('\u{0055}', '\u{030A}', '\u{016E}'), ('\u{0055}', '\u{030B}', '\u{0170}'), ('\u{0055}', '\u{030C}', '\u{01D3}'),
+    ('\u{0055}', '\u{030F}', '\u{0214}'), ('\u{0055}', '\u{0311}', '\u{0216}'), ('\u{0055}', '\u{031B}', '\u{01AF}'), ('\u{0055}', '\u{0323}', '\u{1EE4}'),
+    ('\u{0055}', '\u{0324}', '\u{1E72}'), ('\u{0055}', '\u{0328}', '\u{0172}'), ('\u{0055}', '\u{032D}', '\u{1E76}'), ('\u{0055}', '\u{0330}', '\u{1E74}'),
+    ('\u{0056}', '\u{0303}', '\u{1E7C}'), ('\u{0056}', '\u{0323}', '\u{1E7E}'), ('\u{0057}', '\u{0300}', '\u{1E80}'), ('\u{0057}', '\u{0301}', '\u{1E82}'),
+    ('\u{0057}', '\u{0302}', '\u{0174}'), ('\u{0057}', '\u{0307}', '\u{1E86}'), ('\u{0057}', '\u{0308}', '\u{1E84}'), ('\u{0057}', '\u{0323}', '\u{1E88}'),
+    ('\u{0058}', '\u{0307}', '\u{1E8A}'), ('\u{0058}', '\u{0308}', '\u{1E8C}'), ('\u{0059}', '\u{0300}', '\u{1EF2}'), ('\u{0059}', '\u{0301}', '\u{00DD}'),
+    ('\u{0059}', '\u{0302}', '\u{0176}'), ('\u{0059}', '\u{0303}', '\u{1EF8}'), ('\u{0059}', '\u{0304}', '\u{0232}'), ('\u{0059}', '\u{0307}', '\u{1E8E}'),
+    ('\u{0059}', '\u{0308}', '\u{0178}'), ('\u{0059}', '\u{0309}', '\u{1EF6}'), ('\u{0059}', '\u{0323}', '\u{1EF4}'), ('\u{005A}', '\u{0301}', '\u{0179}'),
+    ('\u{005A}', '\u{0302}', '\u{1E90}'), ('\u{005A}', '\u{0307}', '\u{017B}'), ('\u{005A}', '\u{030C}', '\u{017D}'), ('\u{005A}', '\u{0323}', '\u{1E92}'),
+    ('\u{005A}', '\u{0331}', '\u{1E94}'), ('\u{0061}', '\u{0300}', '\u{00E0}'), ('\u{0061}', '\u{0301}', '\u{00E1}'), ('\u{0061}', '\u{0302}', '\u{00E2}'),
+    ('\u{0061}', '\u{0303}', '\u{00E3}'), ('\u{0061}', '\u{0304}', '\u{0101}'), ('\u{0061}', '\u{0306}', '\u{0103}'), ('\u{0061}', '\u{0307}', '\u{0227}'),
+    ('\u{0061}', '\u{0308}', '\u{00E4}'), ('\u{0061}', '\u{0309}', '\u{1EA3}'), ('\u{0061}', '\u{030A}', '\u{00E5}'), ('\u{0061}', '\u{030C}', '\u{01CE}'),
+    ('\u{0061}', '\u{030F}', '\u{0201}'), ('\u{0061}', '\u{0311}', '\u{0203}'), ('\u{0061}', '\u{0323}', '\u{1EA1}'), ('\u{0061}', '\u{0325}', '\u{1E01}'),
+    ('\u{0061}', '\u{0328}', '\u{0105}'), ('\u{0062}', '\u{0307}', '\u{1E03}'), ('\u{0062}', '\u{0323}', '\u{1E05}'), ('\u{0062}', '\u{0331}', '\u{1E07}'),
+    ('\u{0063}', '\u{0301}', '\u{0107}'), ('\u{0063}', '\u{0302}', '\u{0109}'), ('\u{0063}', '\u{0307}', '\u{010B}'), ('\u{0063}', '\u{030C}', '\u{010D}'),
+    ('\u{0063}', '\u{0327}', '\u{00E7}'), ('\u{0064}', '\u{0307}', '\u{1E0B}'), ('\u{0064}', '\u{030C}', '\u{010F}'), ('\u{0064}', '\u{0323}', '\u{1E0D}'),
+    ('\u{0064}', '\u{0327}', '\u{1E11}'), ('\u{0064}', '\u{032D}', '\u{1E13}'), ('\u{0064}', '\u{0331}', '\u{1E0F}'), ('\u{0065}', '\u{0300}', '\u{00E8}'),
+    ('\u{0065}', '\u{0301}', '\u{00E9}'), ('\u{0065}', '\u{0302}', '\u{00EA}'), ('\u{0065}', '\u{0303}', '\u{1EBD}'), ('\u{0065}', '\u{0304}', '\u{0113}'),
+    ('\u{0065}', '\u{0306}', '\u{0115}'), ('\u{0065}', '\u{0307}', '\u{0117}'), ('\u{0065}', '\u{0308}', '\u{00EB}'), ('\u{0065}', '\u{0309}', '\u{1EBB}'),
+    ('\u{0065}', '\u{030C}', '\u{011B}'), ('\u{0065}', '\u{030F}', '\u{0205}'), ('\u{0065}', '\u{0311}', '\u{0207}'), ('\u{0065}', '\u{0323}', '\u{1EB9}'),
+    ('\u{0065}', '\u{0327}', '\u{0229}'), ('\u{0065}', '\u{0328}', '\u{0119}'), ('\u{0065}', '\u{032D}', '\u{1E19}'), ('\u{0065}', '\u{0330}', '\u{1E1B}'),
+    ('\u{0066}', '\u{0307}', '\u{1E1F}'), ('\u{0067}', '\u{0301}', '\u{01F5}'), ('\u{0067}', '\u{0302}', '\u{011D}'), ('\u{0067}', '\u{0304}', '\u{1E21}'),
+    ('\u{0067}', '\u{0306}', '\u{011F}'), ('\u{0067}', '\u{0307}', '\u{0121}'), ('\u{0067}', '\u{030C}', '\u{01E7}'), ('\u{0067}', '\u{0327}', '\u{0123}'),
+    ('\u{0068}', '\u{0302}', '\u{0125}'), ('\u{0068}', '\u{0307}', '\u{1E23}'), ('\u{0068}', '\u{0308}', '\u{1E27}'), ('\u{0068}', '\u{030C}', '\u{021F}'),
+    ('\u{0068}', '\u{0323}', '\u{1E25}'), ('\u{0068}', '\u{0327}', '\u{1E29}'), ('\u{0068}', '\u{032E}', '\u{1E2B}'), ('\u{0068}', '\u{0331}', '\u{1E96}'),
+    ('\u{0069}', '\u{0300}', '\u{00EC}'), ('\u{0069}', '\u{0301}', '\u{00ED}'), ('\u{0069}', '\u{0302}', '\u{00EE}'), ('\u{0069}', '\u{0303}', '\u{0129}'),
+    ('\u{0069}', '\u{0304}', '\u{012B}'), ('\u{0069}', '\u{0306}', '\u{012D}'), ('\u{0069}', '\u{0308}', '\u{00EF}'), ('\u{0069}', '\u{0309}', '\u{1EC9}'),
+    ('\u{0069}', '\u{030C}', '\u{01D0}'), ('\u{0069}', '\u{030F}', '\u{0209}'), ('\u{0069}', '\u{0311}', '\u{020B}'), ('\u{0069}', '\u{0323}', '\u{1ECB}'),
+    ('\u{0069}', '\u{0328}', '\u{012F}'), ('\u{0069}', '\u{0330}', '\u{1E2D}'), ('\u{006A}', '\u{0302}', '\u{0135}'), ('\u{006A}', '\u{030C}', '\u{01F0}'),
+    ('\u{006B}', '\u{0301}', '\u{1E31}'), ('\u{006B}', '\u{030C}', '\u{01E9}'), ('\u{006B}', '\u{0323}', '\u{1E33}'), ('\u{006B}', '\u{0327}', '\u{0137}'),
+    ('\u{006B}', '\u{0331}', '\u{1E35}'), ('\u{006C}', '\u{0301}', '\u{013A}'), ('\u{006C}', '\u{030C}', '\u{013E}'), ('\u{006C}', '\u{0323}', '\u{1E37}'),
+    ('\u{006C}', '\u{0327}', '\u{013C}'), ('\u{006C}', '\u{032D}', '\u{1E3D}'), ('\u{006C}', '\u{0331}', '\u{1E3B}'), ('\u{006D}', '\u{0301}', '\u{1E3F}'),
+    ('\u{006D}', '\u{0307}', '\u{1E41}'), ('\u{006D}', '\u{0323}', '\u{1E43}'), ('\u{006E}', '\u{0300}', '\u{01F9}'), ('\u{006E}', '\u{0301}', '\u{0144}'),
+    ('\u{006E}', '\u{0303}', '\u{00F1}'), ('\u{006E}', '\u{0307}', '\u{1E45}'), ('\u{006E}', '\u{030C}', '\u{0148}'), ('\u{006E}', '\u{0323}', '\u{1E47}'),
+    ('\u{006E}', '\u{0327}', '\u{0146}'), ('\u{006E}', '\u{032D}', '\u{1E4B}'), ('\u{006E}', '\u{0331}', '\u{1E49}'), ('\u{006F}', '\u{0300}', '\u{00F2}'),
+    ('\u{006F}', '\u{0301}', '\u{00F3}'), ('\u{006F}', '\u{0302}', '\u{00F4}'), ('\u{006F}', '\u{0303}', '\u{00F5}'), ('\u{006F}', '\u{0304}', '\u{014D}'),
+    ('\u{006F}', '\u{0306}', '\u{014F}'), ('\u{006F}', '\u{0307}', '\u{022F}'), ('\u{006F}', '\u{0308}', '\u{00F6}'), ('\u{006F}', '\u{0309}', '\u{1ECF}'),
+    ('\u{006F}', '\u{030B}', '\u{0151}'), ('\u{006F}', '\u{030C}', '\u{01D2}'), ('\u{006F}', '\u{030F}', '\u{020D}'), ('\u{006F}', '\u{0311}', '\u{020F}'),
+    ('\u{006F}', '\u{031B}', '\u{01A1}'), ('\u{006F}', '\u{0323}', '\u{1ECD}'), ('\u{006F}', '\u{0328}', '\u{01EB}'), ('\u{0070}', '\u{0301}', '\u{1E55}'),
+    ('\u{0070}', '\u{0307}', '\u{1E57}'), ('\u{0072}', '\u{0301}', '\u{0155}'), ('\u{0072}', '\u{0307}', '\u{1E59}'), ('\u{0072}', '\u{030C}', '\u{0159}'),
+    ('\u{0072}', '\u{030F}', '\u{0211}'), ('\u{0072}', '\u{0311}', '\u{0213}'), ('\u{0072}', '\u{0323}', '\u{1E5B}'), ('\u{0072}', '\u{0327}', '\u{0157}'),
+    ('\u{0072}', '\u{0331}', '\u{1E5F}'), ('\u{0073}', '\u{0301}', '\u{015B}'), ('\u{0073}', '\u{0302}', '\u{015D}'), ('\u{0073}', '\u{0307}', '\u{1E61}'),
+    ('\u{0073}', '\u{030C}', '\u{0161}'), ('\u{0073}', '\u{0323}', '\u{1E63}'), ('\u{0073}', '\u{0326}', '\u{0219}'), ('\u{0073}', '\u{0327}', '\u{015F}'),
+    ('\u{0074}', '\u{0307}', '\u{1E6B}'), ('\u{0074}', '\u{0308}', '\u{1E97}'), ('\u{0074}', '\u{030C}', '\u{0165}'), ('\u{0074}', '\u{0323}', '\u{1E6D}'),
+    ('\u{0074}', '\u{0326}', '\u{021B}'), ('\u{0074}', '\u{0327}', '\u{0163}'), ('\u{0074}', '\u{032D}', '\u{1E71}'), ('\u{0074}', '\u{0331}', '\u{1E6F}'),
+    ('\u{0075}', '\u{0300}', '\u{00F9}'), ('\u{0075}', '\u{0301}', '\u{00FA}'), ('\u{0075}', '\u{0302}', '\u{00FB}'), ('\u{0075}', '\u{0303}', '\u{0169}'),
+    ('\u{0075}', '\u{0304}', '\u{016B}'), ('\u{0075}', '\u{0306}', '\u{016D}'), ('\u{0075}', '\u{0308}', '\u{00FC}'), ('\u{0075}', '\u{0309}', '\u{1EE7}'),
+    ('\u{0075}', '\u{030A}', '\u{016F}'), ('\u{0075}', '\u{030B}', '\u{0171}'), ('\u{0075}', '\u{030C}', '\u{01D4}'), ('\u{0075}', '\u{030F}', '\u{0215}'),
+    ('\u{0075}', '\u{0311}', '\u{0217}'), ('\u{0075}', '\u{031B}', '\u{01B0}'), ('\u{0075}', '\u{0323}', '\u{1EE5}'), ('\u{0075}', '\u{0324}', '\u{1E73}'),
+    ('\u{0075}', '\u{0328}', '\u{0173}'), ('\u{0075}', '\u{032D}', '\u{1E77}'), ('\u{0075}', '\u{0330}', '\u{1E75}'), ('\u{0076}', '\u{0303}', '\u{1E7D}'),
+    ('\u{0076}', '\u{0323}', '\u{1E7F}'), ('\u{0077}', '\u{0300}', '\u{1E81}'), ('\u{0077}', '\u{0301}', '\u{1E83}'), ('\u{0077}', '\u{0302}', '\u{0175}'),
+    ('\u{0077}', '\u{0307}', '\u{1E87}'), ('\u{0077}', '\u{0308}', '\u{1E85}'), ('\u{0077}', '\u{030A}', '\u{1E98}'), ('\u{0077}', '\u{0323}', '\u{1E89}'),
+    ('\u{0078}', '\u{0307}', '\u{1E8B}'), ('\u{0078}', '\u{0308}', '\u{1E8D}'), ('\u{0079}', '\u{0300}', '\u{1EF3}'), ('\u{0079}', '\u{0301}', '\u{00FD}'),
+    ('\u{0079}', '\u{0302}', '\u{0177}'), ('\u{0079}', '\u{0303}', '\u{1EF9}'), ('\u{0079}', '\u{0304}', '\u{0233}'), ('\u{0079}', '\u{0307}', '\u{1E8F}'),
+    ('\u{0079}', '\u{0308}', '\u{00FF}'), ('\u{0079}', '\u{0309}', '\u{1EF7}'), ('\u{0079}', '\u{030A}', '\u{1E99}'), ('\u{0079}', '\u{0323}', '\u{1EF5}'),
+    ('\u{007A}', '\u{0301}', '\u{017A}'), ('\u{007A}', '\u{0302}', '\u{1E91}'), ('\u{007A}', '\u{0307}', '\u{017C}'), ('\u{007A}', '\u{030C}', '\u{017E}'),
+    ('\u{007A}', '\u{0323}', '\u{1E93}'), ('\u{007A}', '\u{0331}', '\u{1E95}'), ('\u{00C2}', '\u{0300}', '\u{1EA6}'), ('\u{00C2}', '\u{0301}', '\u{1EA4}'),
+    ('\u{00C2}', '\u{0303}', '\u{1EAA}'), ('\u{00C2}', '\u{0309}', '\u{1EA8}'), ('\u{00C4}', '\u{0304}', '\u{01DE}'), ('\u{00C5}', '\u{0301}', '\u{01FA}'),
+    ('\u{00C6}', '\u{0301}', '\u{01FC}'), ('\u{00C6}', '\u{0304}', '\u{01E2}'), ('\u{00C7}', '\u{0301}', '\u{1E08}'), ('\u{00CA}', '\u{0300}', '\u{1EC0}'),
+    ('\u{00CA}', '\u{0301}', '\u{1EBE}'), ('\u{00CA}', '\u{0303}', '\u{1EC4}'), ('\u{00CA}', '\u{0309}', '\u{1EC2}'), ('\u{00CF}', '\u{0301}', '\u{1E2E}'),
+    ('\u{00D4}', '\u{0300}', '\u{1ED2}'), ('\u{00D4}', '\u{0301}', '\u{1ED0}'), ('\u{00D4}', '\u{0303}', '\u{1ED6}'), ('\u{00D4}', '\u{0309}', '\u{1ED4}'),
+    ('\u{00D5}', '\u{0301}', '\u{1E4C}'), ('\u{00D5}', '\u{0304}', '\u{022C}'), ('\u{00D5}', '\u{0308}', '\u{1E4E}'), ('\u{00D6}', '\u{0304}', '\u{022A}'),
+    ('\u{00D8}', '\u{0301}', '\u{01FE}'), ('\u{00DC}', '\u{0300}', '\u{01DB}'), ('\u{00DC}', '\u{0301}', '\u{01D7}'), ('\u{00DC}', '\u{0304}', '\u{01D5}'),
+    ('\u{00DC}', '\u{030C}', '\u{01D9}'), ('\u{00E2}', '\u{0300}', '\u{1EA7}'), ('\u{00E2}', '\u{0301}', '\u{1EA5}'), ('\u{00E2}', '\u{0303}', '\u{1EAB}'),
+    ('\u{00E2}', '\u{0309}', '\u{1EA9}'), ('\u{00E4}', '\u{0304}', '\u{01DF}'), ('\u{00E5}', '\u{0301}', '\u{01FB}'), ('\u{00E6}', '\u{0301}', '\u{01FD}'),
+    ('\u{00E6}', '\u{0304}', '\u{01E3}'), ('\u{00E7}', '\u{0301}', '\u{1E09}'), ('\u{00EA}', '\u{0300}', '\u{1EC1}'), ('\u{00EA}', '\u{0301}', '\u{1EBF}'),
+    ('\u{00EA}', '\u{0303}', '\u{1EC5}'), ('\u{00EA}', '\u{0309}', '\u{1EC3}'), ('\u{00EF}', '\u{0301}', '\u{1E2F}'), ('\u{00F4}', '\u{0300}', '\u{1ED3}'),
+    ('\u{00F4}', '\u{0301}', '\u{1ED1}'), ('\u{00F4}', '\u{0303}', '\u{1ED7}'), ('\u{00F4}', '\u{0309}', '\u{1ED5}'), ('\u{00F5}', '\u{0301}', '\u{1E4D}'),
+    ('\u{00F5}', '\u{0304}', '\u{022D}'), ('\u{00F5}', '\u{0308}', '\u{1E4F}'), ('\u{00F6}', '\u{0304}', '\u{022B}'), ('\u{00F8}', '\u{0301}', '\u{01FF}'),
+    ('\u{00FC}', '\u{0300}', '\u{01DC}'), ('\u{00FC}', '\u{0301}', '\u{01D8}'), ('\u{00FC}', '\u{0304}', '\u{01D6}'), ('\u{00FC}', '\u{030C}', '\u{01DA}'),
+    ('\u{0102}', '\u{0300}', '\u{1EB0}'), ('\u{0102}', '\u{0301}', '\u{1EAE}'), ('\u{0102}', '\u{0303}', '\u{1EB4}'), ('\u{0102}', '\u{0309}', '\u{1EB2}'),
+    ('\u{0103}', '\u{0300}', '\u{1EB1}'), ('\u{0103}', '\u{0301}', '\u{1EAF}'), ('\u{0103}', '\u{0303}', '\u{1EB5}'), ('\u{0103}', '\u{0309}', '\u{1EB3}'),
+    ('\u{0112}', '\u{0300}', '\u{1E14}'), ('\u{0112}', '\u{0301}', '\u{1E16}'), ('\u{0113}', '\u{0300}', '\u{1E15}'), ('\u{0113}', '\u{0301}', '\u{1E17}'),
+    ('\u{014C}', '\u{0300}', '\u{1E50}'), ('\u{014C}', '\u{0301}', '\u{1E52}'), ('\u{014D}', '\u{0300}', '\u{1E51}'), ('\u{014D}', '\u{0301}', '\u{1E53}'),
+    ('\u{015A}', '\u{0307}', '\u{1E64}'), ('\u{015B}', '\u{0307}', '\u{1E65}'), ('\u{0160}', '\u{0307}', '\u{1E66}'), ('\u{0161}', '\u{0307}', '\u{1E67}'),
+    ('\u{0168}', '\u{0301}', '\u{1E78}'), ('\u{0169}', '\u{0301}', '\u{1E79}'), ('\u{016A}', '\u{0308}', '\u{1E7A}'), ('\u{016B}', '\u{0308}', '\u{1E7B}'),
+    ('\u{017F}', '\u{0307}', '\u{1E9B}'), ('\u{01A0}', '\u{0300}', '\u{1EDC}'), ('\u{01A0}', '\u{0301}', '\u{1EDA}'), ('\u{01A0}', '\u{0303}', '\u{1EE0}'),
+    ('\u{01A0}', '\u{0309}', '\u{1EDE}'), ('\u{01A0}', '\u{0323}', '\u{1EE2}'), ('\u{01A1}', '\u{0300}', '\u{1EDD}'), ('\u{01A1}', '\u{0301}', '\u{1EDB}'),
+    ('\u{01A1}', '\u{0303}', '\u{1EE1}'), ('\u{01A1}', '\u{0309}', '\u{1EDF}'), ('\u{01A1}', '\u{0323}', '\u{1EE3}'), ('\u{01AF}', '\u{0300}', '\u{1EEA}'),
+    ('\u{01AF}', '\u{0301}', '\u{1EE8}'), ('\u{01AF}', '\u{0303}', '\u{1EEE}'), ('\u{01AF}', '\u{0309}', '\u{1EEC}'), ('\u{01AF}', '\u{0323}', '\u{1EF0}'),
+    ('\u{01B0}', '\u{0300}', '\u{1EEB}'), ('\u{01B0}', '\u{0301}', '\u{1EE9}'), ('\u{01B0}', '\u{0303}', '\u{1EEF}'), ('\u{01B0}', '\u{0309}', '\u{1EED}'),
+    ('\u{01B0}', '\u{0323}', '\u{1EF1}'), ('\u{01B7}', '\u{030C}', '\u{01EE}'), ('\u{01EA}', '\u{0304}', '\u{01EC}'), ('\u{01EB}', '\u{0304}', '\u{01ED}'),
+    ('\u{0226}', '\u{0304}', '\u{01E0}'), ('\u{0227}', '\u{0304}', '\u{01E1}'), ('\u{0228}', '\u{0306}', '\u{1E1C}'), ('\u{0229}', '\u{0306}', '\u{1E1D}'),
+    ('\u{022E}', '\u{0304}', '\u{0230}'), ('\u{022F}', '\u{0304}', '\u{0231}'), ('\u{0292}', '\u{030C}', '\u{01EF}'), ('\u{1E36}', '\u{0304}', '\u{1E38}'),
+    ('\u{1E37}', '\u{0304}', '\u{1E39}'), ('\u{1E5A}', '\u{0304}', '\u{1E5C}'), ('\u{1E5B}', '\u{0304}', '\u{1E5D}'), ('\u{1E62}', '\u{0307}', '\u{1E68}'),
+    ('\u{1E63}', '\u{0307}', '\u{1E69}'), ('\u{1EA0}', '\u{0302}', '\u{1EAC}'), ('\u{1EA0}', '\u{0306}', '\u{1EB6}'), ('\u{1EA1}', '\u{0302}', '\u{1EAD}'),
+    ('\u{1EA1}', '\u{0306}', '\u{1EB7}'), ('\u{1EB8}', '\u{0302}', '\u{1EC6}'), ('\u{1EB9}', '\u{0302}', '\u{1EC7}'), ('\u{1ECC}', '\u{0302}', '\u{1ED8}'),
+    ('\u{1ECD}', '\u{0302}', '\u{1ED9}'),
+];
@@ -70,6 +70,16 @@ impl PathFilter {
         &self.path
     }
     
+    /// Whether only some paths are let through
+    pub fn is_filtering(&self) -> bool {
+        !self.routes.matched
+    }
+    
+    /// Whether the entry `name` below the current path can match
+    pub fn allows(&self, name: &str) -> bool {
+        self.routes.matched || self.routes.children.contains_key(name)
+    }
+    
     /// Filter a set of entries, yielding only those that match the criteria
     pub fn filter_entries<'a, T>(&self, entries: &'a HashMap<String, T>) -> Vec<(&'a String, &'a T)> {
         let mut result = Vec::new();
@@ -0,0 +1,267 @@
+// src/core/protocol/daemon.rs - serving repositories read-only over TCP
+//
+// The conversation is line based. The client first names the repository
+//
+//     ash-upload-pack /path/to/repo
+//
+// and the daemon answers `ok` or `ERR <reason>`. Then, as often as needed:
+//
+//     ls-refs              `<oid> <name>` lines, ended by an empty line
+//     have <oid>...        `ack <oid>...` for the ones the daemon has too
+//     fetch <filter|-> <skip>
+//                          then `want <oid>` and `common <oid>` lines and
+//                          `done`; answered with the objects, less the first
+//                          `skip` a resumed fetch already has
+//     fetch-by-id <oid>... objects
+//
+// Objects are sent as `objects <count>` and then, per object, a header
+// `<oid> <type> <base|-> <size>` followed by `size` bytes of data, and
+// finally `checksum <sha1>` of the whole batch. Any request may be
+// answered with `ERR <reason>` instead.
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::thread;
+
+use crate::core::protocol::upload_pack::UploadPack;
+use crate::core::protocol::{checksum, decode_object, encode_object, ObjectFilter, TransferObject};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+pub const DEFAULT_PORT: u16 = 9418;
+pub const UPLOAD_PACK: &str = "ash-upload-pack";
+// Without --export-all, only repositories holding this file are served
+pub const EXPORT_OK: &str = "ash-daemon-export-ok";
+
+// Wants, common commits, filter and objects to skip of a fetch request
+type FetchRequest = (Vec<String>, Vec<String>, Option<ObjectFilter>, usize);
+
+/// Which repositories the daemon may serve: those at or below one of the
+/// `whitelist` directories, with requested paths taken relative to
+/// `base_path` when there is one
+#[derive(Debug, Clone)]
+pub struct Daemon {
+    pub base_path: Option<PathBuf>,
+    pub whitelist: Vec<PathBuf>,
+    pub export_all: bool,
+}
+
+impl Daemon {
+    pub fn new(base_path: Option<&str>, whitelist: &[String], export_all: bool) -> Result<Self, Error> {
+        let canonical = |dir: &str| Path::new(dir).canonicalize()
+            .map_err(|e| Error::Generic(format!("cannot export '{}': {}", dir, e)));
+
+        let base_path = base_path.map(canonical).transpose()?;
+        let whitelist = whitelist.iter().map(|dir| canonical(dir)).collect::<Result<Vec<_>, _>>()?;
+        if base_path.is_none() && whitelist.is_empty() {
+            return Err(Error::Generic("nothing to export: give a --base-path or directories to serve".to_string()));
+        }
+        Ok(Daemon { base_path, whitelist, export_all })
+    }
+
+    /// Accept connections on `address` until killed, serving each one on
+    /// its own thread
+    pub fn listen(&self, address: &str) -> Result<(), Error> {
+        let listener = TcpListener::bind(address)
+            .map_err(|e| Error::Generic(format!("unable to listen on {}: {}", address, e)))?;
+        eprintln!("Listening on {}", listener.local_addr()?);
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("error: accept failed: {}", e);
+                    continue;
+                },
+            };
+            let daemon = self.clone();
+            thread::spawn(move || {
+                let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+                if let Err(e) = daemon.serve(stream) {
+                    eprintln!("[{}] {}", peer, e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn serve(&self, stream: TcpStream) -> Result<(), Error> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let request = match read_line(&mut reader)? {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        let repo = match request.split_once(' ') {
+            Some((UPLOAD_PACK, path)) => self.open(path),
+            Some((service, _)) if service.starts_with("ash-") => {
+                Err(Error::Generic(format!("service '{}' not enabled: this daemon is read-only", service)))
+            },
+            _ => Err(Error::Generic("bad request".to_string())),
+        };
+        let mut upload_pack = match repo.and_then(UploadPack::anonymous) {
+            Ok(upload_pack) => upload_pack,
+            Err(e) => {
+                writeln!(writer, "ERR {}", message(&e))?;
+                return Err(e);
+            },
+        };
+        writeln!(writer, "ok")?;
+
+        while let Some(line) = read_line(&mut reader)? {
+            let (command, args) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+            let result = match command {
+                "ls-refs" => upload_pack.advertise().map(|refs| {
+                    let mut output = String::new();
+                    for (name, oid) in refs {
+                        output.push_str(&format!("{} {}\n", oid, name));
+                    }
+                    output.push('\n');
+                    output.into_bytes()
+                }),
+                "have" => {
+                    let haves: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+                    Ok(format!("ack {}\n", upload_pack.acknowledge(&haves).join(" ")).into_bytes())
+                },
+                "fetch" => Self::read_fetch(&mut reader, args)
+                    .and_then(|(wants, common, filter, skip)| {
+                        let objects = upload_pack.send(&wants, &common, filter)?;
+                        Ok(encode_objects(objects.get(skip..).unwrap_or_default(), &objects))
+                    }),
+                "fetch-by-id" => {
+                    let oids: Vec<String> = args.split_whitespace().map(str::to_string).collect();
+                    upload_pack.send_by_id(&oids).map(|objects| encode_objects(&objects, &objects))
+                },
+                _ => Err(Error::Generic(format!("unknown request '{}'", command))),
+            };
+
+            match result {
+                Ok(response) => writer.write_all(&response)?,
+                Err(e) => writeln!(writer, "ERR {}", message(&e))?,
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // The repository a client asked for, if it may be served
+    fn open(&self, requested: &str) -> Result<Repository, Error> {
+        let denied = || Error::Generic(format!("access denied or repository not exported: {}", requested));
+
+        let requested_path = Path::new(requested);
+        if requested_path.components().any(|component| component == Component::ParentDir) {
+            return Err(denied());
+        }
+        let path = match &self.base_path {
+            Some(base) => base.join(requested.trim_start_matches('/')),
+            None if requested_path.is_absolute() => requested_path.to_path_buf(),
+            None => return Err(denied()),
+        };
+        // Symlinks are followed before checking, so none can lead outside
+        let path = path.canonicalize().map_err(|_| denied())?;
+        let path = match path.file_name() {
+            Some(name) if name == git_dir::GIT_DIR_NAME => path.parent().map(Path::to_path_buf).unwrap_or(path),
+            _ => path,
+        };
+
+        let within = |dir: &PathBuf| path.starts_with(dir);
+        let allowed = if self.whitelist.is_empty() {
+            self.base_path.as_ref().is_some_and(within)
+        } else {
+            self.whitelist.iter().any(within)
+        };
+        let git_path = git_dir::resolve(&path);
+        if !allowed || !git_dir::objects(&git_path).is_dir() {
+            return Err(denied());
+        }
+        if !self.export_all && !git_path.join(EXPORT_OK).exists() {
+            return Err(denied());
+        }
+
+        Repository::new(&path.to_string_lossy())
+    }
+
+    // The `want` and `common` lines that follow a `fetch`
+    fn read_fetch(reader: &mut impl BufRead, args: &str) -> Result<FetchRequest, Error> {
+        let (filter, skip) = args.split_once(' ').unwrap_or((args, "0"));
+        let filter = match filter {
+            "-" | "" => None,
+            spec => Some(ObjectFilter::parse(spec)?),
+        };
+        let skip = skip.parse().map_err(|_| Error::Generic(format!("bad fetch request '{}'", args)))?;
+
+        let mut wants = Vec::new();
+        let mut common = Vec::new();
+        loop {
+            let line = read_line(reader)?.ok_or_else(closed)?;
+            match line.split_once(' ') {
+                Some(("want", oid)) => wants.push(oid.to_string()),
+                Some(("common", oid)) => common.push(oid.to_string()),
+                _ if line == "done" => break,
+                _ => return Err(Error::Generic(format!("unexpected line in fetch: '{}'", line))),
+            }
+        }
+        Ok((wants, common, filter, skip))
+    }
+}
+
+/// The next line without its newline, or None at the end of the stream
+pub fn read_line(reader: &mut impl BufRead) -> Result<Option<String>, Error> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+/// The next line, failing on `ERR` and at the end of the stream
+pub fn expect_line(reader: &mut impl BufRead) -> Result<String, Error> {
+    let line = read_line(reader)?.ok_or_else(closed)?;
+    match line.strip_prefix("ERR ") {
+        Some(reason) => Err(Error::Generic(format!("remote error: {}", reason))),
+        None => Ok(line),
+    }
+}
+
+/// Objects as they go over the wire: their count, the objects and the
+/// checksum of `batch`, the whole batch they are the rest of
+pub fn encode_objects(objects: &[TransferObject], batch: &[TransferObject]) -> Vec<u8> {
+    let mut output = format!("objects {}\n", objects.len()).into_bytes();
+    for object in objects {
+        output.extend(encode_object(object));
+    }
+    output.extend(format!("checksum {}\n", checksum(batch)).into_bytes());
+    output
+}
+
+/// Read back what `encode_objects` wrote, handing each object to
+/// `receive` as it arrives, and return the checksum
+pub fn decode_objects(reader: &mut impl BufRead, mut receive: impl FnMut(TransferObject) -> Result<(), Error>) -> Result<String, Error> {
+    let line = expect_line(reader)?;
+    let count: usize = line.strip_prefix("objects ")
+        .and_then(|count| count.parse().ok())
+        .ok_or_else(|| Error::Generic(format!("unexpected response: '{}'", line)))?;
+
+    for _ in 0..count {
+        receive(decode_object(reader)?.ok_or_else(closed)?)?;
+    }
+
+    let line = expect_line(reader)?;
+    line.strip_prefix("checksum ")
+        .map(str::to_string)
+        .ok_or_else(|| Error::Generic(format!("unexpected response: '{}'", line)))
+}
+
+fn closed() -> Error {
+    Error::Generic("the remote end hung up unexpectedly".to_string())
+}
+
+// Errors go over the wire on one line, without the "Error: " prefix
+fn message(error: &Error) -> String {
+    match error {
+        Error::Generic(message) => message.replace('\n', " "),
+        other => other.to_string().replace('\n', " "),
+    }
+}
@@ -0,0 +1,60 @@
+// src/core/protocol/hide_refs.rs - refs a repository keeps from anonymous clients
+//
+// `transfer.hideRefs`, and `uploadpack.hideRefs` for fetches only, each
+// given as often as needed, name ref prefixes the daemon leaves out of
+// what it advertises; a `!` in front shows a prefix again, the last match
+// deciding:
+//
+//     [transfer]
+//         hideRefs = refs/tasks
+//     [uploadpack]
+//         hideRefs = refs/reviews
+//         hideRefs = !refs/reviews/published
+//
+// Clients reading the repository from its path have access to its files
+// anyway, so they are served every ref; hiding is for the daemon, which
+// serves whoever connects.
+use crate::core::config::Config;
+
+pub const TRANSFER_SECTION: &str = "transfer";
+pub const UPLOAD_PACK_SECTION: &str = "uploadpack";
+
+#[derive(Debug, Clone, Default)]
+pub struct HideRefs {
+    // In config order, `!` still in front of the ones that show refs
+    patterns: Vec<String>,
+}
+
+impl HideRefs {
+    /// The refs `transfer.hideRefs` and then `<section>.hideRefs` hide
+    pub fn load(config: &Config, section: &str) -> Self {
+        let patterns = [TRANSFER_SECTION, section].iter()
+            .flat_map(|section| config.get_all(&format!("{}.hideRefs", section)))
+            .map(|pattern| pattern.trim().trim_end_matches('/').to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+        HideRefs { patterns }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Whether the ref `name` (`HEAD`, `refs/...`) is hidden: the last
+    /// pattern that is `name` or one of its leading directories decides
+    pub fn is_hidden(&self, name: &str) -> bool {
+        self.patterns.iter().rev()
+            .find_map(|pattern| {
+                let (shown, prefix) = match pattern.strip_prefix('!') {
+                    Some(prefix) => (true, prefix),
+                    None => (false, pattern.as_str()),
+                };
+                // `^` matches the full name; without namespaces it always is
+                let prefix = prefix.strip_prefix('^').unwrap_or(prefix);
+                let matches = name == prefix
+                    || name.strip_prefix(prefix).is_some_and(|rest| rest.starts_with('/'));
+                matches.then_some(!shown)
+            })
+            .unwrap_or(false)
+    }
+}
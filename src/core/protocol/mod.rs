@@ -0,0 +1,252 @@
+// src/core/protocol/mod.rs - the have/want exchange that lets two repositories
+// work out which objects one of them is missing
+pub mod daemon;
+pub mod hide_refs;
+pub mod negotiator;
+pub mod receive_pack;
+pub mod resume;
+pub mod throttle;
+pub mod upload_pack;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io::BufRead;
+
+use sha1::{Digest, Sha1};
+
+use crate::core::database::database::Database;
+use crate::core::database::delta;
+use crate::core::refs::{Reference, HEAD};
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+// How many earlier objects of the same type each object is tried against
+const DELTA_WINDOW: usize = 10;
+// Longest chain of deltas the receiver has to follow
+const MAX_DELTA_DEPTH: usize = 50;
+
+/// An object in transit between repositories. With a `base`, `data` is a
+/// delta against that object, which the receiver either has already or
+/// gets earlier in the same batch.
+#[derive(Debug, Clone)]
+pub struct TransferObject {
+    pub oid: String,
+    pub obj_type: String,
+    pub data: Vec<u8>,
+    pub base: Option<String>,
+}
+
+/// An object as it is written to the wire or to a partially received
+/// pack: a header `<oid> <type> <base|-> <size>` and `size` bytes of data
+pub fn encode_object(object: &TransferObject) -> Vec<u8> {
+    let base = object.base.as_deref().unwrap_or("-");
+    let mut record = format!("{} {} {} {}\n", object.oid, object.obj_type, base, object.data.len()).into_bytes();
+    record.extend_from_slice(&object.data);
+    record
+}
+
+/// Read back what `encode_object` wrote; None at the end of the stream
+pub fn decode_object(reader: &mut impl BufRead) -> Result<Option<TransferObject>, Error> {
+    let mut header = String::new();
+    if reader.read_line(&mut header)? == 0 {
+        return Ok(None);
+    }
+    let malformed = || Error::Generic(format!("malformed object header: '{}'", header.trim_end()));
+    let fields: Vec<&str> = header.trim_end_matches('\n').split(' ').collect();
+    let size: usize = match fields.as_slice() {
+        [_, _, _, size] => size.parse().map_err(|_| malformed())?,
+        _ => return Err(malformed()),
+    };
+
+    let mut data = vec![0; size];
+    reader.read_exact(&mut data)?;
+    Ok(Some(TransferObject {
+        oid: fields[0].to_string(),
+        obj_type: fields[1].to_string(),
+        data,
+        base: Some(fields[2]).filter(|base| *base != "-").map(str::to_string),
+    }))
+}
+
+/// SHA-1 over a batch of objects as encoded, which the sender reports and
+/// the receiver checks before storing anything
+pub fn checksum(objects: &[TransferObject]) -> String {
+    let mut hasher = Sha1::new();
+    for object in objects {
+        hasher.update(encode_object(object));
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Fail unless `objects` match the checksum the sender gave for them
+pub fn verify_checksum(objects: &[TransferObject], expected: &str) -> Result<(), Error> {
+    let actual = checksum(objects);
+    if actual != expected {
+        return Err(Error::Generic(format!("pack checksum mismatch: expected {}, got {}", expected, actual)));
+    }
+    Ok(())
+}
+
+/// Objects a partial fetch leaves out, to be fetched when first needed:
+/// `blob:none` for every blob, `blob:limit=<n>[k|m|g]` for blobs of at
+/// least n bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectFilter {
+    BlobNone,
+    BlobLimit(usize),
+}
+
+impl ObjectFilter {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        if spec == "blob:none" {
+            return Ok(ObjectFilter::BlobNone);
+        }
+        let limit = spec.strip_prefix("blob:limit=")
+            .ok_or_else(|| Error::Generic(format!("invalid filter-spec '{}'", spec)))?;
+
+        let (digits, scale) = match limit.char_indices().last() {
+            Some((i, 'k')) => (&limit[..i], 1 << 10),
+            Some((i, 'm')) => (&limit[..i], 1 << 20),
+            Some((i, 'g')) => (&limit[..i], 1 << 30),
+            _ => (limit, 1),
+        };
+        let size: usize = digits.parse()
+            .map_err(|_| Error::Generic(format!("invalid filter-spec '{}'", spec)))?;
+        Ok(ObjectFilter::BlobLimit(size * scale))
+    }
+
+    /// Whether an object of this type and size gets sent
+    pub fn allows(&self, obj_type: &str, size: usize) -> bool {
+        match self {
+            _ if obj_type != "blob" => true,
+            ObjectFilter::BlobNone => false,
+            ObjectFilter::BlobLimit(limit) => size < *limit,
+        }
+    }
+}
+
+impl fmt::Display for ObjectFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectFilter::BlobNone => write!(f, "blob:none"),
+            ObjectFilter::BlobLimit(limit) => write!(f, "blob:limit={}", limit),
+        }
+    }
+}
+
+/// Everything reachable from `tips` but not from `exclude`, ready to send,
+/// except what `filter` leaves out. Trees and blobs go as deltas where that
+/// at least halves them: against an earlier version of the same path in the
+/// batch, or against the version the receiver has from the `exclude` commits.
+pub fn pack_objects(database: &mut Database, tips: &[String], exclude: &[String], filter: Option<ObjectFilter>) -> Result<Vec<TransferObject>, Error> {
+    let (objects, boundary) = database.reachable_objects_with_paths(tips, exclude)?;
+    let theirs: HashMap<String, String> = boundary.into_iter()
+        .map(|(oid, path)| (path, oid))
+        .collect();
+
+    let mut loaded = Vec::with_capacity(objects.len());
+    for (oid, path) in objects {
+        let (obj_type, data) = database.load_raw(&oid)?;
+        if filter.is_some_and(|filter| !filter.allows(&obj_type, data.len())) {
+            continue;
+        }
+        loaded.push((oid, path, obj_type, data));
+    }
+    // Versions of the same file next to each other, largest first
+    loaded.sort_by(|a, b| a.2.cmp(&b.2).then(a.1.cmp(&b.1)).then(b.3.len().cmp(&a.3.len())));
+
+    let mut depths: HashMap<String, usize> = HashMap::new();
+    let mut sent = Vec::with_capacity(loaded.len());
+    for i in 0..loaded.len() {
+        let (oid, path, obj_type, data) = &loaded[i];
+        let mut best: Option<(String, Vec<u8>)> = None;
+
+        if obj_type != "commit" && !data.is_empty() {
+            let mut candidates: Vec<(String, Vec<u8>)> = Vec::new();
+            if let Some(base) = theirs.get(path).filter(|base| *base != oid) {
+                if let Ok((base_type, base_data)) = database.load_raw(base) {
+                    if base_type == *obj_type {
+                        candidates.push((base.clone(), base_data));
+                    }
+                }
+            }
+            for (base, _, base_type, base_data) in loaded[i.saturating_sub(DELTA_WINDOW)..i].iter().rev() {
+                if base_type == obj_type && depths.get(base).copied().unwrap_or(0) < MAX_DELTA_DEPTH {
+                    candidates.push((base.clone(), base_data.clone()));
+                }
+            }
+
+            for (base, base_data) in candidates {
+                let candidate = delta::compress(&base_data, data);
+                let limit = best.as_ref().map_or(data.len() / 2, |(_, d)| d.len());
+                if candidate.len() < limit {
+                    best = Some((base, candidate));
+                }
+            }
+        }
+
+        sent.push(match best {
+            Some((base, delta_data)) => {
+                let depth = depths.get(&base).copied().unwrap_or(0) + 1;
+                depths.insert(oid.clone(), depth);
+                TransferObject { oid: oid.clone(), obj_type: obj_type.clone(), data: delta_data, base: Some(base) }
+            },
+            None => TransferObject { oid: oid.clone(), obj_type: obj_type.clone(), data: data.clone(), base: None },
+        });
+    }
+    Ok(sent)
+}
+
+/// Store objects received from another repository, expanding deltas and
+/// checking each object against its ID. Returns how many came as deltas.
+pub fn unpack_objects(database: &mut Database, objects: &[TransferObject]) -> Result<usize, Error> {
+    let mut deltas = 0;
+    for object in objects {
+        let oid = match &object.base {
+            Some(base) => {
+                let (_, base_data) = database.load_raw(base)
+                    .map_err(|_| Error::Generic(format!("missing delta base {} for {}", base, object.oid)))?;
+                deltas += 1;
+                database.store_raw(&object.obj_type, &delta::apply(&base_data, &object.data)?)?
+            },
+            None => database.store_raw(&object.obj_type, &object.data)?,
+        };
+        if oid != object.oid {
+            return Err(Error::Generic(format!("object {} was corrupted in transfer", object.oid)));
+        }
+    }
+    Ok(deltas)
+}
+
+/// A request to move a remote ref from `old` to `new`; None on either side
+/// means the ref does not exist
+#[derive(Debug, Clone)]
+pub struct RefUpdate {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// HEAD plus every ref under refs/ of `repo` (branches, tags, notes and any
+/// other namespace), by full ref name
+pub fn advertise_refs(repo: &Repository) -> Result<BTreeMap<String, String>, Error> {
+    let mut refs = BTreeMap::new();
+
+    if let Some(oid) = repo.refs.read_head()? {
+        refs.insert(HEAD.to_string(), oid);
+    }
+
+    for reference in repo.refs.list_refs_under("refs")? {
+        if let Reference::Symbolic(name) = reference {
+            // A ref being written right now
+            if name.ends_with(".lock") {
+                continue;
+            }
+            if let Some(oid) = repo.refs.read_ref(&name)? {
+                refs.insert(name, oid);
+            }
+        }
+    }
+
+    Ok(refs)
+}
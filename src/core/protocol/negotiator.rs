@@ -0,0 +1,83 @@
+// src/core/protocol/negotiator.rs - the fetching side of have/want negotiation
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::core::database::commit::Commit;
+use crate::core::database::database::Database;
+use crate::core::remote::transport::Transport;
+use crate::errors::error::Error;
+
+// Haves offered per round trip
+const HAVES_PER_ROUND: usize = 32;
+
+// Stop offering history after this many haves in a row go unacknowledged
+const MAX_UNACKED_HAVES: usize = 256;
+
+/// Finds commits the local and remote repositories have in common by
+/// offering local history newest first. Once the remote acknowledges a
+/// commit its ancestors are known to be common and are no longer offered.
+pub struct Negotiator<'a> {
+    database: &'a mut Database,
+}
+
+impl<'a> Negotiator<'a> {
+    pub fn new(database: &'a mut Database) -> Self {
+        Negotiator { database }
+    }
+
+    /// Negotiate starting from the local `tips`; returns the common commits
+    pub fn negotiate(&mut self, transport: &mut dyn Transport, tips: &[String]) -> Result<Vec<String>, Error> {
+        let mut queue = BinaryHeap::new();
+        let mut seen = HashSet::new();
+        for oid in tips {
+            self.enqueue(&mut queue, &mut seen, oid)?;
+        }
+
+        let mut common = Vec::new();
+        let mut unacked = 0;
+
+        while !queue.is_empty() && unacked < MAX_UNACKED_HAVES {
+            let mut round = Vec::new();
+            while round.len() < HAVES_PER_ROUND {
+                match queue.pop() {
+                    Some((_, oid, parents)) => round.push((oid, parents)),
+                    None => break,
+                }
+            }
+
+            let haves: Vec<String> = round.iter().map(|(oid, _)| oid.clone()).collect();
+            let acked: HashSet<String> = transport.acknowledge(&haves)?.into_iter().collect();
+
+            for (oid, parents) in round {
+                if acked.contains(&oid) {
+                    unacked = 0;
+                    common.push(oid);
+                } else {
+                    unacked += 1;
+                    for parent in parents {
+                        self.enqueue(&mut queue, &mut seen, &parent)?;
+                    }
+                }
+            }
+        }
+
+        Ok(common)
+    }
+
+    // Queue a commit by commit time, with its parents, unless already seen
+    fn enqueue(
+        &mut self,
+        queue: &mut BinaryHeap<(i64, String, Vec<String>)>,
+        seen: &mut HashSet<String>,
+        oid: &str,
+    ) -> Result<(), Error> {
+        if !seen.insert(oid.to_string()) || !self.database.exists(oid) {
+            return Ok(());
+        }
+
+        let object = self.database.load(oid)?;
+        if let Some(commit) = object.as_any().downcast_ref::<Commit>() {
+            queue.push((commit.committer.timestamp.timestamp(), oid.to_string(), commit.get_parents()));
+        }
+        Ok(())
+    }
+}
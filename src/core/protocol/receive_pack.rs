@@ -0,0 +1,82 @@
+// src/core/protocol/receive_pack.rs - the receiving side of a push
+use crate::core::config::Config;
+use crate::core::hooks::{self, POST_RECEIVE, POST_UPDATE};
+use crate::core::protocol::{unpack_objects, verify_checksum, RefUpdate, TransferObject};
+use crate::core::reflog::ZERO_OID;
+use crate::core::refs::Reference;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+/// Accepts objects from a pushing client and moves refs to point at them.
+/// A ref is only moved if it still has the value the client last saw, so
+/// concurrent pushes cannot silently overwrite each other.
+pub struct ReceivePack {
+    repo: Repository,
+}
+
+impl ReceivePack {
+    pub fn new(repo: Repository) -> Self {
+        ReceivePack { repo }
+    }
+
+    /// Store objects sent by the client once they match the checksum it
+    /// gave, checking each one against its ID
+    pub fn receive(&mut self, objects: &[TransferObject], checksum: &str) -> Result<(), Error> {
+        verify_checksum(objects, checksum)?;
+        unpack_objects(&mut self.repo.database, objects)?;
+        Ok(())
+    }
+
+    /// Apply `updates` one by one; each gets Ok or the reason it was
+    /// refused. The post-receive and post-update hooks are then told of
+    /// the refs that moved, e.g. to update mirrors or start a build.
+    pub fn update_refs(&mut self, updates: &[RefUpdate]) -> Result<Vec<Result<(), String>>, Error> {
+        let git_path = git_dir::resolve(&self.repo.path);
+        let config = Config::load(&git_path)?;
+        // A bare repository has no worktree to get out of step with its HEAD
+        let bare = config.get_bool("core.bare").unwrap_or(false);
+        let deny_current = !bare && config.get_bool("receive.denyCurrentBranch").unwrap_or(true);
+        let current = match self.repo.refs.current_ref()? {
+            Reference::Symbolic(path) => Some(path),
+            Reference::Direct(_) => None,
+        };
+
+        let mut results = Vec::new();
+        for update in updates {
+            if deny_current && current.as_deref() == Some(update.name.as_str()) {
+                results.push(Err("branch is currently checked out".to_string()));
+                continue;
+            }
+            if let Some(new) = &update.new {
+                if !self.repo.database.exists(new) {
+                    results.push(Err("missing necessary objects".to_string()));
+                    continue;
+                }
+            }
+
+            let swapped = self.repo.refs.compare_and_swap(&update.name, update.old.as_deref(), update.new.as_deref(), "push")?;
+            results.push(if swapped { Ok(()) } else { Err("stale info".to_string()) });
+        }
+
+        let updated: Vec<&RefUpdate> = updates.iter().zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(update, _)| update)
+            .collect();
+        if !updated.is_empty() {
+            // The updates are done; a failing hook cannot undo them
+            let input: String = updated.iter()
+                .map(|update| format!(
+                    "{} {} {}\n",
+                    update.old.as_deref().unwrap_or(ZERO_OID),
+                    update.new.as_deref().unwrap_or(ZERO_OID),
+                    update.name,
+                ))
+                .collect();
+            hooks::run_with_input(&git_path, &self.repo.path, POST_RECEIVE, &[], &input)?;
+            let names: Vec<&str> = updated.iter().map(|update| update.name.as_str()).collect();
+            hooks::run_with_input(&git_path, &self.repo.path, POST_UPDATE, &names, "")?;
+        }
+        Ok(results)
+    }
+}
@@ -0,0 +1,171 @@
+// src/core/protocol/resume.rs - transfers that pick up where an interrupted one stopped
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use sha1::{Digest, Sha1};
+
+use crate::core::protocol::throttle::Throttle;
+use crate::core::protocol::{decode_object, encode_object, verify_checksum, ObjectFilter, TransferObject};
+use crate::errors::error::Error;
+use crate::trace;
+
+// Under the repository's git dir
+const TRANSFER_DIR: &str = "transfer";
+
+/// The objects of a fetch received so far. They are appended to
+/// `transfer/<id>.pack` as they arrive, and the commits negotiated as
+/// common are kept in `transfer/<id>.state`, so the same fetch run again
+/// after an interruption asks the remote for the same batch, minus what
+/// already arrived.
+pub struct PartialPack {
+    pack_path: PathBuf,
+    state_path: PathBuf,
+    objects: Vec<TransferObject>,
+    common: Option<Vec<String>>,
+    throttle: Throttle,
+}
+
+impl PartialPack {
+    /// The partial pack for fetching `wants` from `url`, holding whatever
+    /// an interrupted attempt received
+    pub fn open(git_path: &Path, url: &str, wants: &[String], filter: Option<ObjectFilter>, throttle: Throttle) -> Result<Self, Error> {
+        let mut key = vec![url.to_string(), filter.map(|filter| filter.to_string()).unwrap_or_default()];
+        key.extend(wants.iter().cloned());
+        let id = transfer_id(&key);
+        let dir = git_path.join(TRANSFER_DIR);
+        let state_path = dir.join(format!("{}.state", id));
+        let pack_path = dir.join(format!("{}.pack", id));
+
+        let common = fs::read_to_string(&state_path).ok()
+            .map(|state| state.lines().filter(|line| !line.is_empty()).map(str::to_string).collect::<Vec<_>>());
+        let objects = match common {
+            Some(_) => read_partial(&pack_path)?,
+            None => Vec::new(),
+        };
+        if !objects.is_empty() {
+            trace!("Resuming fetch after {} objects", objects.len());
+        }
+
+        Ok(PartialPack { pack_path, state_path, objects, common, throttle })
+    }
+
+    /// The commits an interrupted attempt negotiated as common, which must
+    /// be asked with again for the remote to send the same batch
+    pub fn common(&self) -> Option<&[String]> {
+        self.common.as_deref()
+    }
+
+    /// Start receiving the objects for `common`, dropping anything earlier
+    pub fn begin(&mut self, common: &[String]) -> Result<(), Error> {
+        if let Some(dir) = self.state_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut state = common.join("\n");
+        state.push('\n');
+        fs::write(&self.state_path, state)?;
+        File::create(&self.pack_path)?;
+
+        self.common = Some(common.to_vec());
+        self.objects.clear();
+        Ok(())
+    }
+
+    /// How many objects of the batch have arrived
+    pub fn received(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Keep an object that arrived, first on disk
+    pub fn push(&mut self, object: TransferObject) -> Result<(), Error> {
+        let record = encode_object(&object);
+        self.throttle.consume(record.len());
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.pack_path)?;
+        file.write_all(&record)?;
+        self.objects.push(object);
+        Ok(())
+    }
+
+    /// Check the whole batch against the checksum the remote gave for it
+    /// and hand it over. Either way the partial pack is gone afterwards: a
+    /// batch that does not match is not worth resuming.
+    pub fn finish(self, checksum: &str) -> Result<Vec<TransferObject>, Error> {
+        let verified = verify_checksum(&self.objects, checksum);
+        let _ = fs::remove_file(&self.pack_path);
+        let _ = fs::remove_file(&self.state_path);
+        verified?;
+        Ok(self.objects)
+    }
+}
+
+/// How many of the objects of a push the remote has stored. They are sent
+/// in batches it stores on arrival, so a push run again after an
+/// interruption starts after the last batch it acknowledged.
+pub struct PushProgress {
+    path: PathBuf,
+    sent: usize,
+}
+
+impl PushProgress {
+    /// Progress of pushing `tips` to `url`, where it already has `known`
+    pub fn open(git_path: &Path, url: &str, tips: &[String], known: &[String]) -> Result<Self, Error> {
+        let mut known = known.to_vec();
+        known.sort();
+        let mut key = vec![url.to_string()];
+        key.extend(tips.iter().cloned());
+        key.push(String::new());
+        key.extend(known);
+
+        let path = git_path.join(TRANSFER_DIR).join(format!("{}.push", transfer_id(&key)));
+        let sent = fs::read_to_string(&path).ok()
+            .and_then(|sent| sent.trim().parse().ok())
+            .unwrap_or(0);
+        if sent > 0 {
+            trace!("Resuming push after {} objects", sent);
+        }
+        Ok(PushProgress { path, sent })
+    }
+
+    pub fn sent(&self) -> usize {
+        self.sent
+    }
+
+    /// Note that `n` more objects were stored by the remote
+    pub fn record(&mut self, n: usize) -> Result<(), Error> {
+        self.sent += n;
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, format!("{}\n", self.sent))?;
+        Ok(())
+    }
+
+    pub fn finish(self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Name for the state of a transfer, from what identifies it
+fn transfer_id(key: &[String]) -> String {
+    format!("{:x}", Sha1::digest(key.join("\n").as_bytes()))
+}
+
+// Objects written to a partial pack before it was interrupted. A record
+// cut off halfway is dropped, and the file shortened to match.
+fn read_partial(path: &Path) -> Result<Vec<TransferObject>, Error> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut reader = BufReader::new(file);
+
+    let mut objects = Vec::new();
+    let mut complete = 0;
+    while let Ok(Some(object)) = decode_object(&mut reader) {
+        complete += encode_object(&object).len() as u64;
+        objects.push(object);
+    }
+    OpenOptions::new().write(true).open(path)?.set_len(complete)?;
+    Ok(objects)
+}
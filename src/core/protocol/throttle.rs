@@ -0,0 +1,62 @@
+// src/core/protocol/throttle.rs - keep transfers under a bandwidth limit
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::core::config::Config;
+use crate::errors::error::Error;
+
+const MAX_RATE_KEY: &str = "transfer.maxRate";
+
+/// Slows a transfer down to at most `rate` bytes per second, averaged
+/// since its first bytes, by sleeping whenever it gets ahead
+#[derive(Debug)]
+pub struct Throttle {
+    rate: Option<u64>,
+    start: Option<Instant>,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub fn new(rate: Option<u64>) -> Self {
+        Throttle { rate, start: None, bytes: 0 }
+    }
+
+    /// Limited by `transfer.maxRate`, in bytes per second with an optional
+    /// `k`, `m` or `g` suffix; unlimited when it is unset or 0
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        let rate = match config.get(MAX_RATE_KEY) {
+            Some(value) => Self::parse_rate(&value)?,
+            None => None,
+        };
+        Ok(Self::new(rate))
+    }
+
+    /// Account for `n` more bytes, waiting until they fit under the limit
+    pub fn consume(&mut self, n: usize) {
+        let rate = match self.rate {
+            Some(rate) => rate,
+            None => return,
+        };
+        let start = *self.start.get_or_insert_with(Instant::now);
+        self.bytes += n as u64;
+
+        let due = Duration::from_secs_f64(self.bytes as f64 / rate as f64);
+        let elapsed = start.elapsed();
+        if due > elapsed {
+            thread::sleep(due - elapsed);
+        }
+    }
+
+    fn parse_rate(value: &str) -> Result<Option<u64>, Error> {
+        let value = value.trim().to_ascii_lowercase();
+        let (digits, scale) = match value.char_indices().last() {
+            Some((i, 'k')) => (&value[..i], 1 << 10),
+            Some((i, 'm')) => (&value[..i], 1 << 20),
+            Some((i, 'g')) => (&value[..i], 1 << 30),
+            _ => (value.as_str(), 1),
+        };
+        let rate: u64 = digits.parse()
+            .map_err(|_| Error::Generic(format!("invalid {} '{}'", MAX_RATE_KEY, value)))?;
+        Ok(Some(rate * scale).filter(|rate| *rate > 0))
+    }
+}
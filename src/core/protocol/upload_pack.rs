@@ -0,0 +1,111 @@
+// src/core/protocol/upload_pack.rs - the serving side of a fetch
+use std::collections::{BTreeMap, HashSet};
+
+use crate::core::config::Config;
+use crate::core::hooks::{self, PRE_UPLOAD_PACK};
+use crate::core::protocol::hide_refs::{HideRefs, UPLOAD_PACK_SECTION};
+use crate::core::protocol::{advertise_refs, pack_objects, ObjectFilter, TransferObject};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+/// Answers a fetching client: advertises refs, acknowledges the commits
+/// both sides have, and sends the objects the client is missing.
+pub struct UploadPack {
+    repo: Repository,
+    hidden: HideRefs,
+    // What only the hidden refs reach, worked out on the first request
+    hidden_objects: Option<HashSet<String>>,
+}
+
+impl UploadPack {
+    pub fn new(repo: Repository) -> Self {
+        UploadPack { repo, hidden: HideRefs::default(), hidden_objects: None }
+    }
+
+    /// For a client that could be anyone: the refs the repository's
+    /// hideRefs settings name are neither advertised nor can what only
+    /// they reach be fetched
+    pub fn anonymous(repo: Repository) -> Result<Self, Error> {
+        let config = Config::load(&git_dir::resolve(&repo.path))?;
+        let hidden = HideRefs::load(&config, UPLOAD_PACK_SECTION);
+        Ok(UploadPack { repo, hidden, hidden_objects: None })
+    }
+
+    pub fn advertise(&self) -> Result<BTreeMap<String, String>, Error> {
+        let mut refs = advertise_refs(&self.repo)?;
+        refs.retain(|name, _| !self.hidden.is_hidden(name));
+        Ok(refs)
+    }
+
+    /// The subset of `haves` this repository also has
+    pub fn acknowledge(&self, haves: &[String]) -> Vec<String> {
+        haves.iter().filter(|oid| self.repo.database.exists(oid)).cloned().collect()
+    }
+
+    /// Everything reachable from `wants` that is not reachable from
+    /// `common`, less what `filter` leaves out, once the pre-upload-pack
+    /// hook allows it
+    pub fn send(&mut self, wants: &[String], common: &[String], filter: Option<ObjectFilter>) -> Result<Vec<TransferObject>, Error> {
+        for oid in wants {
+            if !self.repo.database.exists(oid) {
+                return Err(Error::Generic(format!("remote does not have object {}", oid)));
+            }
+        }
+        self.check_visible(wants)?;
+        let input: String = wants.iter().map(|oid| format!("{}\n", oid)).collect();
+        if !hooks::run_with_input(&git_dir::resolve(&self.repo.path), &self.repo.path, PRE_UPLOAD_PACK, &[], &input)? {
+            return Err(Error::Generic(format!("{} hook declined the fetch", PRE_UPLOAD_PACK)));
+        }
+
+        pack_objects(&mut self.repo.database, wants, common, filter)
+    }
+
+    /// Exactly the objects asked for, as a partial clone needs them
+    pub fn send_by_id(&mut self, oids: &[String]) -> Result<Vec<TransferObject>, Error> {
+        self.check_visible(oids)?;
+        oids.iter()
+            .map(|oid| {
+                let (obj_type, data) = self.repo.database.load_raw(oid)
+                    .map_err(|_| Error::Generic(format!("remote does not have object {}", oid)))?;
+                Ok(TransferObject { oid: oid.clone(), obj_type, data, base: None })
+            })
+            .collect()
+    }
+
+    // Refuse objects the client could only know of through hidden refs
+    fn check_visible(&mut self, oids: &[String]) -> Result<(), Error> {
+        if self.hidden.is_empty() {
+            return Ok(());
+        }
+        if self.hidden_objects.is_none() {
+            let (mut hidden_tips, mut visible_tips) = (Vec::new(), Vec::new());
+            for (name, oid) in advertise_refs(&self.repo)? {
+                if self.hidden.is_hidden(&name) {
+                    hidden_tips.push(oid);
+                } else {
+                    visible_tips.push(oid);
+                }
+            }
+            // A hidden ref may point at a tree or blob; only commits and
+            // tags can be walked
+            let database = &self.repo.database;
+            let walkable = |oid: &String| database.load_raw(oid)
+                .is_ok_and(|(obj_type, _)| obj_type == "commit" || obj_type == "tag");
+            let mut objects: HashSet<String> = hidden_tips.iter()
+                .filter(|oid| !visible_tips.contains(oid))
+                .cloned()
+                .collect();
+            let hidden_tips: Vec<String> = hidden_tips.into_iter().filter(walkable).collect();
+            let visible_tips: Vec<String> = visible_tips.into_iter().filter(walkable).collect();
+            objects.extend(self.repo.database.reachable_objects(&hidden_tips, &visible_tips)?);
+            self.hidden_objects = Some(objects);
+        }
+
+        let hidden = self.hidden_objects.as_ref().expect("worked out above");
+        match oids.iter().find(|oid| hidden.contains(*oid)) {
+            Some(oid) => Err(Error::Generic(format!("not our ref {}", oid))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+// src/core/reflog.rs - the history of where each ref has pointed
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::core::config::Config;
+use crate::core::repository::autostash::current_author;
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+const LOGS_DIR: &str = "logs";
+pub const ZERO_OID: &str = "0000000000000000000000000000000000000000";
+
+/// One movement of a ref: from `old` (the zero oid when it did not exist)
+/// to `new`, and why
+#[derive(Debug, Clone)]
+pub struct ReflogEntry {
+    pub old: String,
+    pub new: String,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    fn parse(line: &str) -> Option<Self> {
+        let (head, message) = line.split_once('\t').unwrap_or((line, ""));
+        // Who made the change and when follows the two oids
+        let mut parts = head.splitn(3, ' ');
+        let old = parts.next()?.to_string();
+        let new = parts.next()?.to_string();
+        if !is_oid(&old) || !is_oid(&new) {
+            return None;
+        }
+        Some(ReflogEntry { old, new, message: message.to_string() })
+    }
+}
+
+/// The logs under `.ash/logs/`, one file per ref named like the ref itself
+/// (`logs/HEAD`, `logs/refs/heads/master`), one line appended per update in
+/// the same format as git's, so that lost commits can still be found.
+pub struct Reflog {
+    // HEAD's log is the worktree's own, those of refs/ are shared
+    logs_path: PathBuf,
+    common_logs_path: PathBuf,
+    git_path: PathBuf,
+}
+
+impl Reflog {
+    pub fn new<P: AsRef<Path>>(git_path: P) -> Self {
+        let git_path = git_path.as_ref().to_path_buf();
+        Reflog {
+            logs_path: git_path.join(LOGS_DIR),
+            common_logs_path: git_dir::common(&git_path).join(LOGS_DIR),
+            git_path,
+        }
+    }
+
+    /// Record that `name` moved from `old` to `new`, if updates to it are
+    /// logged at all
+    pub fn append(&self, name: &str, old: Option<&str>, new: &str, message: &str) -> Result<(), Error> {
+        if !is_oid(new) || !self.should_log(name) {
+            return Ok(());
+        }
+
+        let path = self.path_for(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let old = old.filter(|old| is_oid(old)).unwrap_or(ZERO_OID);
+        // One entry per line, so only the first line of a message is kept
+        let message = message.lines().next().unwrap_or("").trim();
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{} {} {}\t{}", old, new, current_author(), message)?;
+        Ok(())
+    }
+
+    /// The entries of `name`'s log, oldest first
+    pub fn read(&self, name: &str) -> Result<Vec<ReflogEntry>, Error> {
+        let path = self.path_for(name);
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(&path)?
+            .lines()
+            .filter_map(ReflogEntry::parse)
+            .collect())
+    }
+
+    /// Remove the `n`th newest entry of `name`'s log, returning it
+    pub fn remove(&self, name: &str, n: usize) -> Result<Option<ReflogEntry>, Error> {
+        let path = self.path_for(name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)?;
+        let mut lines: Vec<&str> = content.lines().filter(|line| ReflogEntry::parse(line).is_some()).collect();
+        if n >= lines.len() {
+            return Ok(None);
+        }
+
+        let removed = lines.remove(lines.len() - 1 - n);
+        let mut rest = lines.join("\n");
+        if !rest.is_empty() {
+            rest.push('\n');
+        }
+        fs::write(&path, rest)?;
+        Ok(ReflogEntry::parse(removed))
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.path_for(name).is_file()
+    }
+
+    /// The full name of the log a short name such as `master` refers to,
+    /// looked up the way refs are
+    pub fn resolve_name(&self, name: &str) -> Option<String> {
+        [
+            name.to_string(),
+            format!("refs/{}", name),
+            format!("refs/heads/{}", name),
+            format!("refs/tags/{}", name),
+            format!("refs/remotes/{}", name),
+        ]
+        .into_iter()
+        .find(|candidate| self.exists(candidate))
+    }
+
+    /// Drop the log of a deleted ref, with any directories it leaves empty
+    pub fn delete(&self, name: &str) -> Result<(), Error> {
+        let path = self.path_for(name);
+        if !path.is_file() {
+            return Ok(());
+        }
+        fs::remove_file(&path)?;
+
+        let mut dir = path.parent().map(Path::to_path_buf);
+        while let Some(current) = dir {
+            if current == self.logs_path || current == self.common_logs_path || fs::remove_dir(&current).is_err() {
+                break;
+            }
+            dir = current.parent().map(Path::to_path_buf);
+        }
+        Ok(())
+    }
+
+    /// Carry the log of `old` over to `new`, a ref it was renamed to,
+    /// replacing any log `new` had
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), Error> {
+        let (from, to) = (self.path_for(old), self.path_for(new));
+        if !from.is_file() {
+            return Ok(());
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&from, &to)?;
+        Ok(())
+    }
+
+    fn path_for(&self, name: &str) -> PathBuf {
+        if git_dir::is_shared_ref(name) {
+            self.common_logs_path.join(name)
+        } else {
+            self.logs_path.join(name)
+        }
+    }
+
+    // `core.logAllRefUpdates` decides, true by default unless the
+    // repository is bare: HEAD, branches, remote-tracking branches and
+    // notes are logged, or every ref with "always". The stash is its log,
+    // and a ref that already has one keeps it.
+    fn should_log(&self, name: &str) -> bool {
+        if name == "refs/stash" || self.exists(name) {
+            return true;
+        }
+
+        let config = Config::load(&self.git_path).ok();
+        let setting = config.as_ref().and_then(|config| config.get("core.logAllRefUpdates"));
+        let bare = config.as_ref().and_then(|config| config.get_bool("core.bare")).unwrap_or(false);
+        match setting.as_deref().map(str::to_lowercase).as_deref() {
+            Some("always") => true,
+            Some("false") | Some("no") | Some("off") | Some("0") => false,
+            None if bare => false,
+            _ => name == "HEAD"
+                || ["refs/heads/", "refs/remotes/", "refs/notes/"].iter().any(|prefix| name.starts_with(prefix)),
+        }
+    }
+}
+
+fn is_oid(value: &str) -> bool {
+    value.len() == 40 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
@@ -1,15 +1,20 @@
 // src/core/refs.rs
+use std::collections::BTreeMap;
 use std::fs::{self, File};
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use regex::Regex;
 use crate::errors::error::Error;
-use crate::core::lockfile::Lockfile;
+use crate::core::lockfile::{self, Lockfile};
+use crate::core::reflog::{Reflog, ReflogEntry};
+use crate::core::repository::git_dir;
 
 // Constants
 pub const HEAD: &str = "HEAD";
 const DEFAULT_BRANCH: &str = "master";
 const SYMREF_PREFIX: &str = "ref: ";
+const PACKED_REFS: &str = "packed-refs";
+const PACKED_REFS_HEADER: &str = "# pack-refs with: sorted";
 lazy_static::lazy_static! {
     static ref SYMREF_REGEX: Regex = Regex::new(r"^ref: (.+)$").unwrap();
 }
@@ -29,19 +34,27 @@ pub enum RefError {
 }
 
 pub struct Refs {
+    // The worktree's own metadata directory, with HEAD, and the one all
+    // worktrees share, with refs/ and packed-refs; the same directory
+    // unless this is a linked worktree
     pathname: PathBuf,
+    common_path: PathBuf,
     refs_path: PathBuf,
     heads_path: PathBuf,
+    reflog: Reflog,
 }
 
 impl Refs {
     pub fn new<P: AsRef<Path>>(pathname: P) -> Self {
         let path = pathname.as_ref().to_path_buf();
-        let refs_path = path.join("refs");
+        let common_path = git_dir::common(&path);
+        let refs_path = common_path.join("refs");
         let heads_path = refs_path.join("heads");
         
         Refs {
+            reflog: Reflog::new(&path),
             pathname: path,
+            common_path,
             refs_path,
             heads_path,
         }
@@ -57,33 +70,45 @@ impl Refs {
         self.read_symref(&head_path)
     }
 
-    // Set HEAD to point to a branch or commit
-    pub fn set_head(&self, revision: &str, oid: &str) -> Result<(), Error> {
+    // Set HEAD to point to a branch or commit, logging `message` in HEAD's reflog
+    pub fn set_head(&self, revision: &str, oid: &str, message: &str) -> Result<(), Error> {
         let head_path = self.pathname.join(HEAD);
         let branch_path = self.heads_path.join(revision);
+        let old = self.read_head().unwrap_or(None);
         
-        if File::open(&branch_path).is_ok() {
+        let result = if self.ref_exists(&branch_path) {
             // If the revision is a valid branch name, create a symbolic ref
-            let relative = branch_path.strip_prefix(&self.pathname)
+            let relative = branch_path.strip_prefix(&self.common_path)
                 .map_err(|_| Error::PathResolution(format!(
                     "Failed to create relative path from '{}' to '{}'",
-                    self.pathname.display(), branch_path.display()
+                    self.common_path.display(), branch_path.display()
                 )))?;
                 
             self.update_ref_file(&head_path, &format!("{}{}", SYMREF_PREFIX, relative.display()))
         } else {
             // Otherwise, store the commit ID directly
             self.update_ref_file(&head_path, oid)
-        }
+        };
+        result?;
+        self.reflog.append(HEAD, old.as_deref(), oid, message)
     }
 
-    // Update HEAD, following symbolic references
-    pub fn update_head(&self, oid: &str) -> Result<(), Error> {
-        self.update_symref(&self.pathname.join(HEAD), oid)
+    // Update HEAD, following symbolic references; both HEAD and the branch
+    // it is on log the move
+    pub fn update_head(&self, oid: &str, message: &str) -> Result<(), Error> {
+        let old = self.read_head()?;
+        let current = self.current_ref()?;
+        self.update_symref(&self.pathname.join(HEAD), oid)?;
+
+        self.reflog.append(HEAD, old.as_deref(), oid, message)?;
+        if let Reference::Symbolic(branch) = current {
+            self.reflog.append(&branch, old.as_deref(), oid, message)?;
+        }
+        Ok(())
     }
     
     // Create a new branch pointing to the specified commit OID
-    pub fn create_branch(&self, branch_name: &str, oid: &str) -> Result<(), Error> {
+    pub fn create_branch(&self, branch_name: &str, oid: &str, message: &str) -> Result<(), Error> {
         // Validate branch name using regex pattern for invalid names
         if !self.is_valid_branch_name(branch_name) {
             return Err(Error::Generic(format!(
@@ -93,16 +118,50 @@ impl Refs {
         
         // Check if branch already exists
         let branch_path = self.heads_path.join(branch_name);
-        if branch_path.exists() {
+        if self.ref_exists(&branch_path) {
             return Err(Error::Generic(format!(
                 "A branch named '{}' already exists.", branch_name
             )));
         }
         
         // Create the branch reference file
-        self.update_ref_file(&branch_path, oid)
+        self.update_ref_file(&branch_path, oid)?;
+        self.reflog.append(&format!("refs/heads/{}", branch_name), None, oid, message)
     }
     
+    // Rename a branch, its reflog going along with it, and return its OID.
+    // An existing branch `new_name` is only replaced when `force` is set.
+    pub fn rename_branch(&self, old_name: &str, new_name: &str, force: bool, message: &str) -> Result<String, Error> {
+        if !self.is_valid_branch_name(new_name) {
+            return Err(Error::Generic(format!(
+                "'{}' is not a valid branch name.", new_name
+            )));
+        }
+
+        let (old_ref, new_ref) = (format!("refs/heads/{}", old_name), format!("refs/heads/{}", new_name));
+        let oid = match self.read_symref(&self.heads_path.join(old_name))? {
+            Some(oid) => oid,
+            None => return Err(Error::Generic(format!("No branch named '{}'.", old_name))),
+        };
+        if old_name == new_name {
+            return Ok(oid);
+        }
+        if self.ref_exists(&self.heads_path.join(new_name)) && !force {
+            return Err(Error::Generic(format!(
+                "A branch named '{}' already exists.", new_name
+            )));
+        }
+
+        self.update_ref_file(&self.heads_path.join(new_name), &oid)?;
+        self.remove_packed_ref(&new_ref)?;
+        self.reflog.delete(&new_ref)?;
+        self.reflog.rename(&old_ref, &new_ref)?;
+        self.remove_loose_ref(&old_ref)?;
+        self.remove_packed_ref(&old_ref)?;
+        self.reflog.append(&new_ref, Some(&oid), &oid, message)?;
+        Ok(oid)
+    }
+
     // Read a reference by name (branch, HEAD, etc.)
     pub fn read_ref(&self, name: &str) -> Result<Option<String>, Error> {
         // Check for HEAD alias
@@ -114,14 +173,19 @@ impl Refs {
         // 1. Direct under .ash directory
         // 2. Under .ash/refs
         // 3. Under .ash/refs/heads (branches)
+        // 4. Under .ash/refs/tags
+        // 5. Under .ash/refs/remotes (remote-tracking branches)
         let paths = [
-            self.pathname.join(name),
+            self.path_for(name),
             self.refs_path.join(name),
             self.heads_path.join(name),
+            self.refs_path.join("tags").join(name),
+            self.refs_path.join("remotes").join(name),
+            self.refs_path.join("remotes").join(name).join(HEAD),
         ];
         
         for path in &paths {
-            if path.exists() {
+            if self.ref_exists(path) {
                 return self.read_symref(path);
             }
         }
@@ -130,8 +194,240 @@ impl Refs {
         Ok(None)
     }
     
-    // Read a reference file and parse as OID or symref
+    // Point a ref named relative to the metadata directory (e.g. "refs/stash")
+    // at an OID, logging `message` in its reflog
+    pub fn update_ref(&self, name: &str, oid: &str, message: &str) -> Result<(), Error> {
+        let path = self.path_for(name);
+        let old = if self.ref_exists(&path) { self.read_symref(&path)? } else { None };
+        self.update_ref_file(&path, oid)?;
+        self.reflog.append(name, old.as_deref(), oid, message)
+    }
+
+    // Move a ref from `expected` to `new` under its lock, failing with false if
+    // it no longer points at `expected` (None: the ref must not exist). A `new`
+    // of None deletes the ref.
+    pub fn compare_and_swap(&self, name: &str, expected: Option<&str>, new: Option<&str>, message: &str) -> Result<bool, Error> {
+        let path = self.path_for(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                Error::DirectoryCreation(format!(
+                    "Failed to create directory '{}': {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+
+        let mut lockfile = Lockfile::new(&path);
+        let acquired = lockfile.hold_for_update()
+            .map_err(|e| Error::Generic(format!("Lock error: {:?}", e)))?;
+        if !acquired {
+            return Err(Error::Generic(format!(
+                "Could not acquire lock on '{}'", path.display()
+            )));
+        }
+
+        if self.read_symref(&path)?.as_deref() != expected {
+            lockfile.rollback()?;
+            return Ok(false);
+        }
+
+        match new {
+            Some(oid) => {
+                lockfile.write(&format!("{}\n", oid))
+                    .map_err(|e| Error::Generic(format!("Write error: {:?}", e)))?;
+                lockfile.commit_ref()
+                    .map_err(|e| Error::Generic(format!("Commit error: {:?}", e)))?;
+                self.reflog.append(name, expected, oid, message)?;
+            },
+            None => {
+                if path.exists() {
+                    fs::remove_file(&path).map_err(Error::IO)?;
+                }
+                self.remove_packed_ref(name)?;
+                self.reflog.delete(name)?;
+                lockfile.rollback()?;
+            }
+        }
+        Ok(true)
+    }
+
+    // Drop the `n`th newest entry of a ref's reflog. Dropping the newest
+    // moves the ref back to the entry before it, and dropping the last one
+    // deletes the ref. Returns the dropped entry.
+    pub fn drop_reflog_entry(&self, name: &str, n: usize) -> Result<Option<ReflogEntry>, Error> {
+        let dropped = self.reflog.remove(name, n)?;
+        if dropped.is_some() && n == 0 {
+            match self.reflog.read(name)?.last() {
+                Some(entry) => self.update_ref_file(&self.path_for(name), &entry.new)?,
+                None => self.delete_ref(name)?,
+            }
+        }
+        Ok(dropped)
+    }
+
+    // Point a ref named relative to the metadata directory at the ref
+    // `target`, e.g. refs/remotes/origin/HEAD at refs/remotes/origin/master
+    pub fn set_symbolic_ref(&self, name: &str, target: &str) -> Result<(), Error> {
+        self.update_ref_file(&self.path_for(name), &format!("{}{}", SYMREF_PREFIX, target))
+    }
+
+    // The ref a symbolic ref named relative to the metadata directory
+    // points at; None if it is missing or holds a commit ID
+    pub fn symbolic_target(&self, name: &str) -> Result<Option<String>, Error> {
+        match self.read_oid_or_symref(&self.path_for(name))? {
+            Some(Reference::Symbolic(target)) => Ok(Some(target)),
+            _ => Ok(None),
+        }
+    }
+
+    // Remove a ref named relative to the metadata directory, if it exists
+    pub fn delete_ref(&self, name: &str) -> Result<(), Error> {
+        self.remove_loose_ref(name)?;
+        self.remove_packed_ref(name)?;
+        self.reflog.delete(name)
+    }
+
+    // Move every loose ref under refs/ into the packed-refs file, leaving
+    // symbolic refs such as refs/remotes/origin/HEAD as they are. A ref
+    // that changes while this runs keeps its loose file, which wins over
+    // the packed value. Returns the names of the refs that were packed.
+    pub fn pack_refs(&self) -> Result<Vec<String>, Error> {
+        let mut lockfile = Lockfile::new(self.common_path.join(PACKED_REFS));
+        let acquired = lockfile.hold_for_update()
+            .map_err(|e| Error::Generic(format!("Lock error: {:?}", e)))?;
+        if !acquired {
+            return Err(Error::Generic("Could not acquire lock on packed-refs".to_string()));
+        }
+
+        let mut packed = self.packed_refs();
+        let mut loose = Vec::new();
+        for reference in self.list_refs(&self.refs_path)? {
+            let name = match reference {
+                Reference::Symbolic(name) => name,
+                Reference::Direct(_) => continue,
+            };
+            let path = self.path_for(&name);
+            if let Some(Reference::Direct(oid)) = self.read_loose(&path)? {
+                packed.insert(name.clone(), oid.clone());
+                loose.push((name, oid));
+            }
+        }
+
+        lockfile.write(&Self::format_packed(&packed))
+            .map_err(|e| Error::Generic(format!("Write error: {:?}", e)))?;
+        lockfile.commit_ref()
+            .map_err(|e| Error::Generic(format!("Commit error: {:?}", e)))?;
+
+        let mut names = Vec::new();
+        for (name, oid) in loose {
+            if self.read_loose(&self.path_for(&name))? == Some(Reference::Direct(oid)) {
+                self.remove_loose_ref(&name)?;
+                names.push(name);
+            }
+        }
+        Ok(names)
+    }
+
+    // Refs kept in the packed-refs file, by full name
+    fn packed_refs(&self) -> BTreeMap<String, String> {
+        let content = fs::read_to_string(self.common_path.join(PACKED_REFS)).unwrap_or_default();
+        content.lines()
+            // Comments, and the peeled values of annotated tags
+            .filter(|line| !line.starts_with('#') && !line.starts_with('^'))
+            .filter_map(|line| line.split_once(' '))
+            .map(|(oid, name)| (name.trim().to_string(), oid.to_string()))
+            .collect()
+    }
+
+    fn format_packed(packed: &BTreeMap<String, String>) -> String {
+        let mut content = format!("{}\n", PACKED_REFS_HEADER);
+        for (name, oid) in packed {
+            content.push_str(&format!("{} {}\n", oid, name));
+        }
+        content
+    }
+
+    // Drop `name` from packed-refs, rewriting the file only if it is there
+    fn remove_packed_ref(&self, name: &str) -> Result<(), Error> {
+        if !self.packed_refs().contains_key(name) {
+            return Ok(());
+        }
+
+        let mut lockfile = Lockfile::new(self.common_path.join(PACKED_REFS));
+        let acquired = lockfile.hold_for_update()
+            .map_err(|e| Error::Generic(format!("Lock error: {:?}", e)))?;
+        if !acquired {
+            return Err(Error::Generic("Could not acquire lock on packed-refs".to_string()));
+        }
+
+        let mut packed = self.packed_refs();
+        packed.remove(name);
+        lockfile.write(&Self::format_packed(&packed))
+            .map_err(|e| Error::Generic(format!("Write error: {:?}", e)))?;
+        lockfile.commit_ref()
+            .map_err(|e| Error::Generic(format!("Commit error: {:?}", e)))?;
+        Ok(())
+    }
+
+    // Where the ref `name`, relative to the metadata directory, is kept;
+    // the `refs` root is the shared one, in a linked worktree too
+    fn path_for(&self, name: &str) -> PathBuf {
+        if name == "refs" || git_dir::is_shared_ref(name) {
+            self.common_path.join(name)
+        } else {
+            self.pathname.join(name)
+        }
+    }
+
+    // Whether a ref exists at `path`, as a file or in packed-refs
+    fn ref_exists(&self, path: &Path) -> bool {
+        path.exists() || self.packed_name(path).is_some_and(|name| self.packed_refs().contains_key(&name))
+    }
+
+    // The full ref name a path under the metadata directory stands for,
+    // or `refs` for the root all of them are under
+    fn packed_name(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.common_path).ok()?;
+        let name = relative.components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        (name == "refs" || name.starts_with("refs/")).then_some(name)
+    }
+
+    // Delete the file of a ref, not its packed value
+    fn remove_loose_ref(&self, name: &str) -> Result<(), Error> {
+        let path = self.path_for(name);
+        if path.exists() {
+            fs::remove_file(&path).map_err(Error::IO)?;
+        }
+
+        // Drop directories the ref leaves empty, keeping refs/heads and the like
+        let mut dir = path.parent();
+        while let Some(parent) = dir {
+            let depth = parent.strip_prefix(&self.common_path).map(|rel| rel.components().count()).unwrap_or(0);
+            if depth <= 2 || fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+        Ok(())
+    }
+
+    // Read a reference file and parse as OID or symref, falling back to
+    // packed-refs for a ref that has no file
     fn read_oid_or_symref(&self, path: &Path) -> Result<Option<Reference>, Error> {
+        if !path.exists() {
+            return Ok(self.packed_name(path)
+                .and_then(|name| self.packed_refs().get(&name).cloned())
+                .map(Reference::Direct));
+        }
+        self.read_loose(path)
+    }
+
+    // Read a reference file only
+    fn read_loose(&self, path: &Path) -> Result<Option<Reference>, Error> {
         if !path.exists() {
             return Ok(None);
         }
@@ -152,8 +448,10 @@ impl Refs {
                     }
                 }
                 
-                // It's a direct reference (OID)
-                Ok(Some(Reference::Direct(trimmed.to_string())))
+                // It's a direct reference (OID); files like FETCH_HEAD
+                // carry more after it on the first line
+                let oid = trimmed.split_whitespace().next().unwrap_or_default();
+                Ok(Some(Reference::Direct(oid.to_string())))
             },
             Err(_) => Ok(None),
         }
@@ -166,7 +464,7 @@ impl Refs {
         match ref_result {
             Some(Reference::Symbolic(target)) => {
                 // Follow the symbolic reference
-                self.read_symref(&self.pathname.join(target))
+                self.read_symref(&self.path_for(&target))
             },
             Some(Reference::Direct(oid)) => {
                 // Return the OID directly
@@ -235,7 +533,7 @@ impl Refs {
             Some(Reference::Symbolic(target)) => {
                 // Release this lock and follow the symref
                 lockfile.rollback()?;
-                self.update_symref(&self.pathname.join(target), oid)
+                self.update_symref(&self.path_for(&target), oid)
             },
             Some(Reference::Direct(_)) | None => {
                 // Write directly to this file
@@ -249,7 +547,7 @@ impl Refs {
     }
     
     // Check if a branch name is valid (not matching the invalid patterns)
-    fn is_valid_branch_name(&self, name: &str) -> bool {
+    pub(crate) fn is_valid_branch_name(&self, name: &str) -> bool {
         // Define invalid patterns for branch names
         lazy_static::lazy_static! {
             static ref INVALID_NAME: Regex = Regex::new(r"(?x)
@@ -314,8 +612,27 @@ impl Refs {
         self.list_refs(&self.heads_path)
     }
     
-    // List all refs in a directory, recursively
+    // List refs below a path relative to the metadata directory, e.g. "refs/remotes/origin"
+    pub fn list_refs_under(&self, prefix: &str) -> Result<Vec<Reference>, Error> {
+        self.list_refs(&self.path_for(prefix))
+    }
+    
+    // List all refs in a directory, recursively, packed ones included
     fn list_refs(&self, dir: &Path) -> Result<Vec<Reference>, Error> {
+        let mut refs = self.list_loose_refs(dir)?;
+        if let Some(prefix) = self.packed_name(dir) {
+            let prefix = format!("{}/", prefix);
+            for name in self.packed_refs().into_keys().filter(|name| name.starts_with(&prefix)) {
+                let reference = Reference::Symbolic(name);
+                if !refs.contains(&reference) {
+                    refs.push(reference);
+                }
+            }
+        }
+        Ok(refs)
+    }
+
+    fn list_loose_refs(&self, dir: &Path) -> Result<Vec<Reference>, Error> {
         if !dir.exists() {
             return Ok(Vec::new());
         }
@@ -330,11 +647,14 @@ impl Refs {
                         
                         if path.is_dir() {
                             // Recursively list refs in subdirectories
-                            let mut subrefs = self.list_refs(&path)?;
+                            let mut subrefs = self.list_loose_refs(&path)?;
                             refs.append(&mut subrefs);
+                        } else if lockfile::is_lock_artifact(&entry.file_name().to_string_lossy()) {
+                            // A ref being updated right now, not a ref
+                            continue;
                         } else {
                             // Add this file as a reference
-                            if let Some(relative) = path.strip_prefix(&self.pathname).ok() {
+                            if let Ok(relative) = path.strip_prefix(&self.common_path) {
                                 refs.push(Reference::Symbolic(relative.to_string_lossy().to_string()));
                             }
                         }
@@ -374,12 +694,16 @@ impl Refs {
             }
         };
         
-        // Delete the branch file
-        fs::remove_file(&branch_path)
-            .map_err(|e| Error::IO(e))?;
-            
-        // Clean up empty parent directories
-        self.delete_parent_directories(&branch_path)?;
+        // Delete the branch file and any packed value
+        if branch_path.exists() {
+            fs::remove_file(&branch_path)
+                .map_err(Error::IO)?;
+
+            // Clean up empty parent directories
+            self.delete_parent_directories(&branch_path)?;
+        }
+        self.remove_packed_ref(&format!("refs/heads/{}", branch_name))?;
+        self.reflog.delete(&format!("refs/heads/{}", branch_name))?;
         
         // Release the lock
         lockfile.rollback()?;
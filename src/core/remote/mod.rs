@@ -0,0 +1,137 @@
+// src/core/remote/mod.rs - named remote repositories and how to reach them
+pub mod connection;
+pub mod refspec;
+pub mod transport;
+pub mod upstream;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::database::database::Database;
+use crate::core::merge::bases::Bases;
+use crate::core::refs::{Reference, Refs, HEAD};
+use crate::errors::error::Error;
+use refspec::Refspec;
+
+pub const DEFAULT_REMOTE: &str = "origin";
+// The branch taken for the remote HEAD when several are at its commit
+const DEFAULT_BRANCH: &str = "master";
+
+/// A remote repository, either named in the config as
+///
+/// ```text
+/// [remote "origin"]
+///     url = ../upstream
+///     fetch = +refs/heads/*:refs/remotes/origin/*
+/// ```
+///
+/// or given directly by its URL, in which case it has no name and no
+/// fetch refspecs. A remote with `mirror = true` is kept an exact copy:
+/// fetching prunes and pushing sends every ref.
+#[derive(Debug, Clone)]
+pub struct Remote {
+    pub name: Option<String>,
+    pub url: String,
+    pub fetch_specs: Vec<Refspec>,
+    pub mirror: bool,
+}
+
+impl Remote {
+    /// Look `name` up in the config, falling back to treating it as a URL
+    pub fn resolve(git_path: &Path, name: &str) -> Result<Self, Error> {
+        let config = Config::load(git_path)?;
+
+        if let Some(url) = config.get(&format!("remote.{}.url", name)) {
+            let mut fetch_specs = config.get_all(&format!("remote.{}.fetch", name)).iter()
+                .map(|spec| Refspec::parse(spec))
+                .collect::<Result<Vec<_>, _>>()?;
+            if fetch_specs.is_empty() {
+                fetch_specs.push(Refspec::default_fetch(name));
+            }
+
+            return Ok(Remote {
+                name: Some(name.to_string()),
+                url,
+                fetch_specs,
+                mirror: config.get_bool(&format!("remote.{}.mirror", name)).unwrap_or(false),
+            });
+        }
+
+        if name.contains('/') || name.contains("://") || Path::new(name).exists() {
+            return Ok(Remote {
+                name: None,
+                url: name.to_string(),
+                fetch_specs: Vec::new(),
+                mirror: false,
+            });
+        }
+
+        Err(Error::Generic(format!("'{}' does not appear to be an ash repository", name)))
+    }
+}
+
+/// The branches the remote HEAD may be on. Only its commit is advertised,
+/// so these are the advertised branches at that commit.
+pub fn head_branches(advertised: &BTreeMap<String, String>) -> Vec<String> {
+    let head = match advertised.get(HEAD) {
+        Some(head) => head,
+        None => return Vec::new(),
+    };
+    advertised.iter()
+        .filter(|(name, oid)| name.starts_with("refs/heads/") && *oid == head)
+        .map(|(name, _)| name["refs/heads/".len()..].to_string())
+        .collect()
+}
+
+/// The branch the remote HEAD is taken to be on, the default branch if it
+/// is one of those it may be on, with its commit
+pub fn guess_head(advertised: &BTreeMap<String, String>) -> Option<(String, String)> {
+    let branches = head_branches(advertised);
+    let branch = branches.iter()
+        .find(|branch| *branch == DEFAULT_BRANCH)
+        .or_else(|| branches.first())?;
+    Some((branch.clone(), advertised.get(HEAD)?.clone()))
+}
+
+/// Local refs that `specs` fill from remote refs no longer in `advertised`.
+/// Symbolic refs such as refs/remotes/origin/HEAD are left alone.
+pub fn stale_refs(refs: &Refs, specs: &[Refspec], advertised: &BTreeMap<String, String>) -> Result<Vec<String>, Error> {
+    let mut stale = BTreeSet::new();
+    for spec in specs {
+        let destination = match &spec.destination {
+            Some(destination) => destination,
+            None => continue,
+        };
+        let dir = match destination.split_once('*') {
+            Some((prefix, _)) => prefix.trim_end_matches('/'),
+            None => destination.rsplit_once('/').map_or(destination.as_str(), |(dir, _)| dir),
+        };
+
+        for reference in refs.list_refs_under(dir)? {
+            let local = match reference {
+                Reference::Symbolic(local) => local,
+                _ => continue,
+            };
+            if local.ends_with("/HEAD") {
+                continue;
+            }
+            if let Some(source) = spec.map_back(&local) {
+                if !advertised.contains_key(&source) {
+                    stale.insert(local);
+                }
+            }
+        }
+    }
+    Ok(stale.into_iter().collect())
+}
+
+/// Whether moving a ref from `old` to `new` keeps all of its history,
+/// i.e. `old` is an ancestor of `new`. An `old` we do not have is not.
+pub fn is_fast_forward(database: &mut Database, old: &str, new: &str) -> Result<bool, Error> {
+    if !database.exists(old) {
+        return Ok(false);
+    }
+    let bases = Bases::new(database)?.find(old, new)?;
+    Ok(bases.len() == 1 && bases[0] == old)
+}
@@ -0,0 +1,91 @@
+// src/core/remote/refspec.rs - mapping remote ref names to local ones
+use crate::errors::error::Error;
+
+/// A `[+]<src>[:<dst>]` pattern. Either side may contain a single `*`,
+/// which matches any suffix in `src` and is substituted into `dst`. A
+/// leading `+` allows non-fast-forward updates of the destination.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refspec {
+    pub source: String,
+    pub destination: Option<String>,
+    pub force: bool,
+}
+
+impl Refspec {
+    pub fn parse(spec: &str) -> Result<Self, Error> {
+        let (force, spec) = match spec.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, spec),
+        };
+
+        let (source, destination) = match spec.split_once(':') {
+            Some((source, destination)) => (source, Some(destination)),
+            None => (spec, None),
+        };
+
+        let wildcards = |s: &str| s.matches('*').count();
+        let source_wild = wildcards(source);
+        let dest_wild = destination.map(wildcards).unwrap_or(source_wild);
+        if source.is_empty() || source_wild > 1 || source_wild != dest_wild {
+            return Err(Error::Generic(format!("invalid refspec '{}'", spec)));
+        }
+
+        Ok(Refspec {
+            source: expand(source, "refs/heads/"),
+            destination: destination.filter(|d| !d.is_empty()).map(|d| expand(d, "refs/heads/")),
+            force,
+        })
+    }
+
+    /// The default fetch refspec for a remote called `name`
+    pub fn default_fetch(name: &str) -> Self {
+        Refspec {
+            source: "refs/heads/*".to_string(),
+            destination: Some(format!("refs/remotes/{}/*", name)),
+            force: true,
+        }
+    }
+
+    /// If `name` matches the source side, the local ref it maps to; the
+    /// inner value is None for a spec without a destination
+    pub fn map(&self, name: &str) -> Option<Option<String>> {
+        match self.source.split_once('*') {
+            Some((prefix, suffix)) => {
+                let middle = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                Some(self.destination.as_ref().map(|d| d.replacen('*', middle, 1)))
+            },
+            None if name == self.source => Some(self.destination.clone()),
+            None => None,
+        }
+    }
+
+    /// The remote ref that maps to the local ref `name`, if any
+    pub fn map_back(&self, name: &str) -> Option<String> {
+        let destination = self.destination.as_ref()?;
+        match destination.split_once('*') {
+            Some((prefix, suffix)) => {
+                let middle = name.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                Some(self.source.replacen('*', middle, 1))
+            },
+            None if name == destination => Some(self.source.clone()),
+            None => None,
+        }
+    }
+}
+
+// Qualify a short name like `master` as `refs/heads/master`
+fn expand(name: &str, prefix: &str) -> String {
+    if name.starts_with("refs/") || name == "HEAD" {
+        name.to_string()
+    } else {
+        format!("{}{}", prefix, name)
+    }
+}
+
+/// The name of a ref as shown to users: without `refs/heads/`,
+/// `refs/tags/` or `refs/remotes/`
+pub fn short_ref_name(name: &str) -> &str {
+    ["refs/heads/", "refs/tags/", "refs/remotes/"].iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name)
+}
@@ -0,0 +1,205 @@
+// src/core/remote/transport.rs - connections to remote repositories
+use std::collections::BTreeMap;
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::path::Path;
+
+use crate::core::protocol::daemon::{decode_objects, expect_line, DEFAULT_PORT, UPLOAD_PACK};
+use crate::core::protocol::receive_pack::ReceivePack;
+use crate::core::protocol::resume::PartialPack;
+use crate::core::protocol::upload_pack::UploadPack;
+use crate::core::protocol::{self, ObjectFilter, RefUpdate, TransferObject};
+use crate::core::config::Config;
+use crate::core::remote::connection::{ConnectionSettings, LowSpeed};
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+
+const FILE_SCHEME: &str = "file://";
+const DAEMON_SCHEME: &str = "ash://";
+
+/// One side of a conversation with a remote repository
+pub trait Transport {
+    /// Refs the remote offers, by full name
+    fn advertised_refs(&mut self) -> Result<BTreeMap<String, String>, Error>;
+
+    /// Which of `haves` the remote also has
+    fn acknowledge(&mut self, haves: &[String]) -> Result<Vec<String>, Error>;
+
+    /// Objects needed to complete `wants`, given the commits in `common`,
+    /// less those `filter` leaves out, into `pack`. Only the part of the
+    /// batch `pack` has not received yet is sent. Returns the checksum of
+    /// the whole batch.
+    fn fetch_objects(&mut self, wants: &[String], common: &[String], filter: Option<ObjectFilter>, pack: &mut PartialPack) -> Result<String, Error>;
+
+    /// Just the objects `oids`, e.g. blobs a partial fetch left out
+    fn fetch_by_id(&mut self, oids: &[String]) -> Result<Vec<TransferObject>, Error>;
+
+    /// Hand objects to the remote for storage, with their checksum
+    fn send_objects(&mut self, objects: &[TransferObject], checksum: &str) -> Result<(), Error>;
+
+    /// Ask the remote to move refs; one result per update, Err holding the reason it refused
+    fn update_refs(&mut self, updates: &[RefUpdate]) -> Result<Vec<Result<(), String>>, Error>;
+}
+
+/// A remote on the local filesystem, served in-process
+pub struct LocalTransport {
+    upload_pack: UploadPack,
+    receive_pack: ReceivePack,
+}
+
+impl LocalTransport {
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let root = Path::new(path);
+        if !git_dir::objects(&git_dir::resolve(root)).is_dir() {
+            return Err(Error::Generic(format!("'{}' does not appear to be an ash repository", path)));
+        }
+
+        Ok(LocalTransport {
+            upload_pack: UploadPack::new(Repository::new(path)?),
+            receive_pack: ReceivePack::new(Repository::new(path)?),
+        })
+    }
+}
+
+impl Transport for LocalTransport {
+    fn advertised_refs(&mut self) -> Result<BTreeMap<String, String>, Error> {
+        self.upload_pack.advertise()
+    }
+
+    fn acknowledge(&mut self, haves: &[String]) -> Result<Vec<String>, Error> {
+        Ok(self.upload_pack.acknowledge(haves))
+    }
+
+    fn fetch_objects(&mut self, wants: &[String], common: &[String], filter: Option<ObjectFilter>, pack: &mut PartialPack) -> Result<String, Error> {
+        let objects = self.upload_pack.send(wants, common, filter)?;
+        for object in objects.iter().skip(pack.received()) {
+            pack.push(object.clone())?;
+        }
+        Ok(protocol::checksum(&objects))
+    }
+
+    fn fetch_by_id(&mut self, oids: &[String]) -> Result<Vec<TransferObject>, Error> {
+        self.upload_pack.send_by_id(oids)
+    }
+
+    fn send_objects(&mut self, objects: &[TransferObject], checksum: &str) -> Result<(), Error> {
+        self.receive_pack.receive(objects, checksum)
+    }
+
+    fn update_refs(&mut self, updates: &[RefUpdate]) -> Result<Vec<Result<(), String>>, Error> {
+        self.receive_pack.update_refs(updates)
+    }
+}
+
+/// A repository served by `ash daemon`, which only allows fetching
+pub struct DaemonTransport {
+    reader: BufReader<LowSpeed<TcpStream>>,
+    writer: TcpStream,
+}
+
+impl DaemonTransport {
+    /// Connect to `host[:port]/path`, the part of an `ash://` URL after the
+    /// scheme, as `settings` say
+    pub fn open(address: &str, settings: &ConnectionSettings) -> Result<Self, Error> {
+        let (host, path) = match address.find('/') {
+            Some(i) => address.split_at(i),
+            None => return Err(Error::Generic(format!("no repository path in '{}{}'", DAEMON_SCHEME, address))),
+        };
+        let host = if host.contains(':') { host.to_string() } else { format!("{}:{}", host, DEFAULT_PORT) };
+
+        let stream = settings.open(&host)?;
+        let mut transport = DaemonTransport {
+            writer: stream.get_ref().try_clone()?,
+            reader: BufReader::new(stream),
+        };
+        transport.request(&format!("{} {}\n", UPLOAD_PACK, path))?;
+        expect_line(&mut transport.reader)?;
+        Ok(transport)
+    }
+
+    fn request(&mut self, request: &str) -> Result<(), Error> {
+        self.writer.write_all(request.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn read_only() -> Error {
+        Error::Generic("the daemon serves repositories read-only; cannot push".to_string())
+    }
+}
+
+impl Transport for DaemonTransport {
+    fn advertised_refs(&mut self) -> Result<BTreeMap<String, String>, Error> {
+        self.request("ls-refs\n")?;
+        let mut refs = BTreeMap::new();
+        loop {
+            let line = expect_line(&mut self.reader)?;
+            if line.is_empty() {
+                return Ok(refs);
+            }
+            let (oid, name) = line.split_once(' ')
+                .ok_or_else(|| Error::Generic(format!("malformed ref line: '{}'", line)))?;
+            refs.insert(name.to_string(), oid.to_string());
+        }
+    }
+
+    fn acknowledge(&mut self, haves: &[String]) -> Result<Vec<String>, Error> {
+        self.request(&format!("have {}\n", haves.join(" ")))?;
+        let line = expect_line(&mut self.reader)?;
+        let acks = line.strip_prefix("ack")
+            .ok_or_else(|| Error::Generic(format!("unexpected response: '{}'", line)))?;
+        Ok(acks.split_whitespace().map(str::to_string).collect())
+    }
+
+    fn fetch_objects(&mut self, wants: &[String], common: &[String], filter: Option<ObjectFilter>, pack: &mut PartialPack) -> Result<String, Error> {
+        let filter = filter.map_or("-".to_string(), |filter| filter.to_string());
+        let mut request = format!("fetch {} {}\n", filter, pack.received());
+        for oid in wants {
+            request.push_str(&format!("want {}\n", oid));
+        }
+        for oid in common {
+            request.push_str(&format!("common {}\n", oid));
+        }
+        request.push_str("done\n");
+        self.request(&request)?;
+        decode_objects(&mut self.reader, |object| pack.push(object))
+    }
+
+    fn fetch_by_id(&mut self, oids: &[String]) -> Result<Vec<TransferObject>, Error> {
+        self.request(&format!("fetch-by-id {}\n", oids.join(" ")))?;
+        let mut objects = Vec::new();
+        let checksum = decode_objects(&mut self.reader, |object| {
+            objects.push(object);
+            Ok(())
+        })?;
+        protocol::verify_checksum(&objects, &checksum)?;
+        Ok(objects)
+    }
+
+    fn send_objects(&mut self, _objects: &[TransferObject], _checksum: &str) -> Result<(), Error> {
+        Err(Self::read_only())
+    }
+
+    fn update_refs(&mut self, _updates: &[RefUpdate]) -> Result<Vec<Result<(), String>>, Error> {
+        Err(Self::read_only())
+    }
+}
+
+/// Open a transport for `url`: a path, a `file://` URL or an `ash://`
+/// URL naming a repository served by `ash daemon`, reached as the current
+/// repository's `http` settings say
+pub fn connect(url: &str) -> Result<Box<dyn Transport>, Error> {
+    if let Some(address) = url.strip_prefix(DAEMON_SCHEME) {
+        let config = Config::load(&git_dir::resolve(Path::new(".")))?;
+        let settings = ConnectionSettings::for_url(&config, url)?;
+        return Ok(Box::new(DaemonTransport::open(address, &settings)?));
+    }
+    let path = url.strip_prefix(FILE_SCHEME).unwrap_or(url);
+
+    if let Some((scheme, _)) = path.split_once("://") {
+        return Err(Error::Generic(format!("Unable to find remote helper for '{}'", scheme)));
+    }
+
+    Ok(Box::new(LocalTransport::open(path)?))
+}
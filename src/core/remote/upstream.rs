@@ -0,0 +1,144 @@
+// src/core/remote/upstream.rs - the branch a local branch tracks
+//
+// A branch's upstream is kept in its section of the config, as the remote
+// and the branch there that pull merges from and push sends to by default:
+//
+//     [branch "topic"]
+//         remote = origin
+//         merge = refs/heads/topic
+//
+// The remote "." is the repository itself, for a branch that tracks
+// another local branch. Locally the upstream is followed through the
+// remote-tracking branch the remote's fetch refspecs map it to.
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::database::database::Database;
+use crate::core::refs::Refs;
+use crate::core::remote::refspec::{short_ref_name, Refspec};
+use crate::core::remote::Remote;
+use crate::core::revlist::{RevList, WalkOptions};
+use crate::errors::error::Error;
+
+// The remote standing for the repository itself
+const LOCAL_REMOTE: &str = ".";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upstream {
+    pub remote: String,
+    /// The full name of the branch on the remote
+    pub merge: String,
+}
+
+impl Upstream {
+    /// The upstream configured for the local branch `branch`, if any
+    pub fn of(config: &Config, branch: &str) -> Option<Self> {
+        let remote = config.get(&format!("branch.{}.remote", branch))?;
+        let merge = config.get(&format!("branch.{}.merge", branch))?;
+        Some(Upstream { remote, merge })
+    }
+
+    /// Find what `name` (`origin/topic`, `refs/remotes/origin/topic` or a
+    /// local branch) stands for as an upstream: a remote-tracking branch of
+    /// a configured remote, mapped back through its fetch refspecs, or a
+    /// local branch
+    pub fn find(git_path: &Path, refs: &Refs, name: &str) -> Result<Self, Error> {
+        let config = Config::load(git_path)?;
+        let tracking = match name.strip_prefix("refs/remotes/") {
+            Some(_) => name.to_string(),
+            None => format!("refs/remotes/{}", name.strip_prefix("remotes/").unwrap_or(name)),
+        };
+        if refs.read_ref(&tracking)?.is_some() {
+            for remote in config.subsections("remote") {
+                let specs = Remote::resolve(git_path, &remote)?.fetch_specs;
+                if let Some(merge) = specs.iter().find_map(|spec| spec.map_back(&tracking)) {
+                    return Ok(Upstream { remote, merge });
+                }
+            }
+        }
+
+        let branch = format!("refs/heads/{}", name.strip_prefix("refs/heads/").unwrap_or(name));
+        if refs.read_ref(&branch)?.is_some() {
+            return Ok(Upstream { remote: LOCAL_REMOTE.to_string(), merge: branch });
+        }
+        Err(Error::Generic(format!("the requested upstream branch '{}' does not exist", name)))
+    }
+
+    /// Record this as the upstream of the local branch `branch`
+    pub fn set(&self, git_path: &Path, branch: &str) -> Result<(), Error> {
+        let mut config = Config::load(git_path)?;
+        for (key, value) in [("remote", &self.remote), ("merge", &self.merge)] {
+            let key = format!("branch.{}.{}", branch, key);
+            config.unset_all(&key)?;
+            config.add(&key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Forget the upstream of the local branch `branch`, and its config
+    /// section with it if nothing else is set there; false if it had none
+    pub fn unset(git_path: &Path, branch: &str) -> Result<bool, Error> {
+        let mut config = Config::load(git_path)?;
+        if Self::of(&config, branch).is_none() {
+            return Ok(false);
+        }
+        config.unset_all(&format!("branch.{}.remote", branch))?;
+        config.unset_all(&format!("branch.{}.merge", branch))?;
+        let section = format!("branch.{}", branch);
+        if !config.entries().iter().any(|(key, _)| key.starts_with(&format!("{}.", section))) {
+            config.remove_section(&section)?;
+        }
+        Ok(true)
+    }
+
+    pub fn is_local(&self) -> bool {
+        self.remote == LOCAL_REMOTE
+    }
+
+    /// The ref that has the upstream's commit here: the branch itself for
+    /// a local upstream, else the remote-tracking branch it is fetched into
+    pub fn tracking_ref(&self, git_path: &Path) -> String {
+        if self.is_local() {
+            return self.merge.clone();
+        }
+        let specs = Remote::resolve(git_path, &self.remote)
+            .map(|remote| remote.fetch_specs)
+            .unwrap_or_else(|_| vec![Refspec::default_fetch(&self.remote)]);
+        specs.iter()
+            .find_map(|spec| spec.map(&self.merge).flatten())
+            .unwrap_or_else(|| format!("refs/remotes/{}/{}", self.remote, short_ref_name(&self.merge)))
+    }
+
+    /// The upstream as users name it, such as `origin/topic`
+    pub fn display(&self, git_path: &Path) -> String {
+        short_ref_name(&self.tracking_ref(git_path)).to_string()
+    }
+}
+
+/// How many commits `ours` has that `theirs` does not, and the other way
+/// round
+pub fn ahead_behind(database: &mut Database, refs: &Refs, ours: &str, theirs: &str) -> Result<(usize, usize), Error> {
+    let mut count = |from: &str, to: &str| -> Result<usize, Error> {
+        let range = [format!("^{}", from), to.to_string()];
+        Ok(RevList::new(database, refs, &range, false)?.commits(&WalkOptions::default())?.len())
+    };
+    Ok((count(theirs, ours)?, count(ours, theirs)?))
+}
+
+/// What `branch -v` shows of a branch's upstream: `[origin/topic: ahead 1,
+/// behind 2]`, `[origin/topic: gone]` when the upstream is not there, and
+/// just the name when the two are level
+pub fn track(database: &mut Database, refs: &Refs, git_path: &Path, upstream: &Upstream, oid: &str) -> Result<String, Error> {
+    let name = upstream.display(git_path);
+    let theirs = match refs.read_ref(&upstream.tracking_ref(git_path))? {
+        Some(theirs) => theirs,
+        None => return Ok(format!("[{}: gone]", name)),
+    };
+    let counts = match ahead_behind(database, refs, oid, &theirs)? {
+        (0, 0) => return Ok(format!("[{}]", name)),
+        (ahead, 0) => format!("ahead {}", ahead),
+        (0, behind) => format!("behind {}", behind),
+        (ahead, behind) => format!("ahead {}, behind {}", ahead, behind),
+    };
+    Ok(format!("[{}: {}]", name, counts))
+}
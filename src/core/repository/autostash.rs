@@ -0,0 +1,242 @@
+// src/core/repository/autostash.rs - stash local changes around checkout and merge
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::config::Config;
+use crate::core::database::author::Author;
+use crate::core::database::blob::Blob;
+use crate::core::database::commit::Commit;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::database::tree::Tree;
+use crate::core::merge::inputs::CherryPick;
+use crate::core::merge::resolve::Resolve;
+use crate::core::path_filter::PathFilter;
+use crate::core::repository::git_dir;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+use crate::trace;
+
+const AUTOSTASH_FILE: &str = "AUTOSTASH";
+const AUTOSTASH_MESSAGE: &str = "autostash";
+
+/// Saves uncommitted changes to tracked files as a commit on top of HEAD,
+/// resets the worktree and index to HEAD, and later reapplies the changes
+/// with a three-way merge onto whatever HEAD has become.
+///
+/// The pending stash is recorded in `.ash/AUTOSTASH` between the two steps.
+pub struct Autostash {
+    path: PathBuf,
+}
+
+impl Autostash {
+    pub fn new(git_path: &Path) -> Self {
+        Autostash {
+            path: git_path.join(AUTOSTASH_FILE),
+        }
+    }
+
+    pub fn for_repository(repo: &Repository) -> Self {
+        Self::new(&git_dir::resolve(&repo.path))
+    }
+
+    pub fn in_progress(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Stash local changes if there are any. Returns true when something was saved.
+    pub fn save(&self, repo: &mut Repository) -> Result<bool, Error> {
+        if self.in_progress() {
+            return Err(Error::Generic(format!(
+                "An autostash is already pending in {}; apply or remove it first",
+                self.path.display()
+            )));
+        }
+
+        let head_oid = match repo.refs.read_head()? {
+            Some(oid) => oid,
+            None => return Ok(false),
+        };
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let result = self.save_locked(repo, &head_oid);
+        if !matches!(result, Ok(true)) {
+            repo.index.rollback()?;
+        }
+        result
+    }
+
+    fn save_locked(&self, repo: &mut Repository, head_oid: &str) -> Result<bool, Error> {
+        if repo.index.has_conflict() {
+            return Err(Error::Generic("Cannot autostash while there are unmerged paths".to_string()));
+        }
+
+        let head_files = head_files(repo, head_oid)?;
+        let mut paths: BTreeSet<String> = repo.index.each_entry()
+            .map(|entry| entry.get_path().to_string())
+            .collect();
+        paths.extend(head_files.keys().cloned());
+
+        // Capture the worktree state of every tracked file
+        let mut entries = Vec::new();
+        let mut dirty = false;
+
+        for path in &paths {
+            let head_entry = head_files.get(path);
+            let index_entry = repo.index.get_entry(path).map(|e| (e.get_oid().to_string(), e.mode_octal()));
+
+            if index_entry.as_ref().map(|(oid, _)| oid.as_str()) != head_entry.map(|e| e.get_oid()) {
+                dirty = true;
+            }
+
+            let (_, mode) = match &index_entry {
+                Some(entry) => entry.clone(),
+                None => continue,
+            };
+
+            let file = Path::new(path);
+            if !repo.workspace.path_exists(file)? {
+                dirty = true;
+                continue;
+            }
+
+            let data = repo.workspace.read_file(file)?;
+            let oid = repo.database.hash_file_data(&data);
+            if head_entry.map(|e| e.get_oid()) != Some(oid.as_str()) {
+                dirty = true;
+                let mut blob = Blob::new(data);
+                repo.database.store(&mut blob)?;
+            }
+            entries.push(DatabaseEntry::new(path.clone(), oid, &mode));
+        }
+
+        if !dirty {
+            return Ok(false);
+        }
+
+        let mut tree = Tree::build(entries.iter())?;
+        tree.traverse(|t| {
+            repo.database.store(t)?;
+            Ok(())
+        })?;
+        let tree_oid = tree.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Tree OID not set after storage".into()))?;
+
+        let mut commit = Commit::new(Some(head_oid.to_string()), tree_oid, current_author(), AUTOSTASH_MESSAGE.to_string());
+        repo.database.store(&mut commit)?;
+        let stash_oid = commit.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Commit OID not set after storage".into()))?;
+
+        // Bring worktree and index back to HEAD
+        for path in &paths {
+            let file = Path::new(path);
+            match head_files.get(path) {
+                Some(entry) => {
+                    let content = repo.database.load(entry.get_oid())?.to_bytes();
+                    repo.workspace.write_file(file, &content)?;
+                    let stat = repo.workspace.stat_file(file)?;
+                    repo.index.add(file, entry.get_oid(), &stat)?;
+                },
+                None => {
+                    if repo.workspace.path_exists(file)? {
+                        fs::remove_file(repo.workspace.root_path.join(file))?;
+                    }
+                    repo.index.remove(file)?;
+                }
+            }
+        }
+
+        repo.index.write_updates()?;
+        fs::write(&self.path, format!("{}\n", stash_oid))?;
+
+        trace!("Created autostash: {}", repo.database.short_oid(&stash_oid));
+        Ok(true)
+    }
+
+    /// Reapply a pending autostash onto the current HEAD. Conflicting
+    /// changes are left in the index and worktree as after a merge.
+    pub fn apply(&self, repo: &mut Repository) -> Result<(), Error> {
+        if !self.in_progress() {
+            return Ok(());
+        }
+
+        let stash_oid = fs::read_to_string(&self.path)?.trim().to_string();
+        let commit_obj = repo.database.load(&stash_oid)?;
+        let stash = match commit_obj.as_any().downcast_ref::<Commit>() {
+            Some(commit) => commit.clone(),
+            None => return Err(Error::Generic(format!("Autostash {} is not a commit", stash_oid))),
+        };
+        let base_oid = stash.get_parent().cloned()
+            .ok_or_else(|| Error::Generic(format!("Autostash {} has no parent", stash_oid)))?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("Cannot apply autostash without a HEAD commit".to_string()))?;
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let inputs = CherryPick::new(
+            "HEAD".to_string(),
+            AUTOSTASH_MESSAGE.to_string(),
+            head_oid.clone(),
+            stash_oid.clone(),
+            vec![base_oid],
+        );
+        let result = Resolve::new(&mut repo.database, &repo.workspace, &mut repo.index, &inputs).execute();
+
+        // The stash is consumed either way; on conflicts it stays reachable by its id
+        fs::remove_file(&self.path)?;
+
+        match result {
+            Ok(()) => {
+                // Changes come back unstaged, except for newly added files
+                let head_files = head_files(repo, &head_oid)?;
+                for (path, entry) in &head_files {
+                    let unchanged = repo.index.get_entry(path).map(|e| e.get_oid() == entry.get_oid());
+                    if unchanged == Some(false) {
+                        let size = repo.database.load(entry.get_oid())?.to_bytes().len();
+                        let stat = repo.workspace.stat_file(Path::new(path))?;
+                        repo.index.add_partial(Path::new(path), entry.get_oid(), size, &stat)?;
+                    }
+                }
+                repo.index.write_updates()?;
+                trace!("Applied autostash.");
+                Ok(())
+            },
+            Err(e) => {
+                repo.index.write_updates()?;
+                println!("Applying autostash resulted in conflicts.");
+                println!("Your changes are safe in commit {}.", stash_oid);
+                Err(e)
+            }
+        }
+    }
+}
+
+// Whether autostash is enabled for `command`, from its flag or `<command>.autostash`
+pub fn enabled(repo: &Repository, command: &str, flag: Option<bool>) -> Result<bool, Error> {
+    if let Some(flag) = flag {
+        return Ok(flag);
+    }
+    let config = Config::load(&git_dir::resolve(&repo.path))?;
+    Ok(config.get_bool(&format!("{}.autostash", command)).unwrap_or(false))
+}
+
+pub(crate) fn head_files(repo: &mut Repository, head_oid: &str) -> Result<HashMap<String, DatabaseEntry>, Error> {
+    let diff = repo.database.tree_diff(None, Some(head_oid), &PathFilter::new())?;
+    Ok(diff.into_iter()
+        .filter_map(|(path, (_, new))| new.map(|entry| (path.to_string_lossy().to_string(), entry)))
+        .collect())
+}
+
+pub(crate) fn current_author() -> Author {
+    let name = std::env::var("GIT_AUTHOR_NAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "Unknown".to_string());
+    let email = std::env::var("GIT_AUTHOR_EMAIL")
+        .unwrap_or_else(|_| format!("{}@localhost", name));
+    Author::new(name, email)
+}
@@ -0,0 +1,103 @@
+// src/core/repository/format.rs - which repositories this version of ash can work on
+//
+// `core.repositoryformatversion` says how to read the rest of the
+// repository. Version 0 is the original layout. Version 1 adds the
+// `[extensions]` section, in which a repository lists what it needs beyond
+// that layout; a version that does not understand every extension listed
+// must not touch the repository at all, since it would misread or damage
+// it. Any later version is unknown.
+use std::path::Path;
+
+use crate::core::config::Config;
+use crate::core::repository::git_dir;
+use crate::errors::error::Error;
+
+pub const FORMAT_VERSION_KEY: &str = "core.repositoryformatversion";
+pub const FORMAT_VERSION: u32 = 1;
+const EXTENSIONS_SECTION: &str = "extensions";
+
+// The extensions ash understands, with the values it supports for them
+// (any value when None)
+const EXTENSIONS: &[(&str, Option<&[&str]>)] = &[
+    ("noop", None),
+    ("noop-v1", None),
+    ("objectformat", Some(&["sha1"])),
+    ("refstorage", Some(&["files"])),
+    ("partialclone", None),
+];
+
+/// Record the format of a new repository, unless it has one already
+pub fn initialize(config: &mut Config) -> Result<(), Error> {
+    if config.get(FORMAT_VERSION_KEY).is_some() {
+        return Ok(());
+    }
+    config.add(FORMAT_VERSION_KEY, &FORMAT_VERSION.to_string())?;
+    config.add(&format!("{}.objectformat", EXTENSIONS_SECTION), "sha1")
+}
+
+/// Fail unless ash can work on a repository with this config. Extensions
+/// only count from version 1 on; older repositories without a version are
+/// version 0.
+pub fn check(config: &Config) -> Result<(), Error> {
+    let version = match config.get(FORMAT_VERSION_KEY) {
+        Some(value) => value.trim().parse::<u32>()
+            .map_err(|_| Error::Generic(format!("bad {} '{}'", FORMAT_VERSION_KEY, value)))?,
+        None => 0,
+    };
+    if version > FORMAT_VERSION {
+        return Err(Error::Generic(format!(
+            "Expected ash repo version <= {}, found {}\n\
+             hint: this repository was created by a newer version of ash; upgrade ash to use it",
+            FORMAT_VERSION, version
+        )));
+    }
+    if version == 0 {
+        return Ok(());
+    }
+
+    let mut unknown = Vec::new();
+    for (name, value) in config.variables(EXTENSIONS_SECTION) {
+        let supported = EXTENSIONS.iter()
+            .find(|(known, _)| *known == name)
+            .map(|(_, values)| values.is_none_or(|values| values.contains(&value.to_lowercase().as_str())));
+        match supported {
+            Some(true) => {},
+            Some(false) => unknown.push(format!("{} = {}", name, value)),
+            None => unknown.push(name),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(Error::Generic(format!(
+            "unknown repository extension found:\n\t{}\n\
+             hint: this repository relies on features this version of ash does not support;\n\
+             hint: upgrade ash, or remove the extension from the [extensions] section of the\n\
+             hint: repository's config only if you are sure nothing depends on it",
+            unknown.join("\n\t")
+        )));
+    }
+    Ok(())
+}
+
+/// `check` the repository at `path`, if there is one
+pub fn check_repository(path: &Path) -> Result<(), Error> {
+    let git_path = git_dir::resolve(path);
+    if !git_dir::common(&git_path).join("config").exists() {
+        return Ok(());
+    }
+    check(&Config::load(&git_path)?)
+}
+
+/// Declare that the repository depends on extension `name`, moving it to
+/// the version that has extensions if needed
+pub fn require_extension(config: &mut Config, name: &str, value: &str) -> Result<(), Error> {
+    let key = format!("{}.{}", EXTENSIONS_SECTION, name);
+    if config.get(&key).as_deref() == Some(value) {
+        return Ok(());
+    }
+    if config.get(FORMAT_VERSION_KEY).and_then(|v| v.trim().parse::<u32>().ok()).unwrap_or(0) < FORMAT_VERSION {
+        config.unset_all(FORMAT_VERSION_KEY)?;
+        config.add(FORMAT_VERSION_KEY, &FORMAT_VERSION.to_string())?;
+    }
+    config.unset_all(&key)?;
+    config.add(&key, value)
+}
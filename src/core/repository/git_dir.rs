@@ -0,0 +1,115 @@
+// src/core/repository/git_dir.rs - locating the repository metadata directory
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::error::Error;
+
+/// Name of the metadata directory (or pointer file) inside a worktree
+pub const GIT_DIR_NAME: &str = ".ash";
+
+/// Environment variable overriding the metadata directory
+pub const GIT_DIR_ENV: &str = "ASH_DIR";
+
+const GIT_DIR_PREFIX: &str = "gitdir:";
+
+/// File in a linked worktree's metadata directory naming the directory it
+/// shares with the other worktrees, relative to it
+pub const COMMON_DIR_FILE: &str = "commondir";
+const OBJECTS_DIR: &str = "objects";
+
+/// Resolve the metadata directory for the worktree at `root_path`.
+///
+/// In order of precedence: the `ASH_DIR` environment variable, a `.ash`
+/// file containing a `gitdir: <path>` pointer, a bare repository at
+/// `root_path` itself, and finally the plain `.ash` directory. Relative
+/// pointers are interpreted from the worktree root.
+pub fn resolve(root_path: &Path) -> PathBuf {
+    if let Ok(dir) = env::var(GIT_DIR_ENV) {
+        if !dir.is_empty() {
+            return PathBuf::from(dir);
+        }
+    }
+
+    let dot_git = root_path.join(GIT_DIR_NAME);
+    if dot_git.is_file() {
+        if let Some(target) = read_pointer(&dot_git) {
+            return if target.is_absolute() { target } else { root_path.join(target) };
+        }
+    }
+
+    if !dot_git.exists() && is_bare(root_path) {
+        return root_path.to_path_buf();
+    }
+
+    dot_git
+}
+
+/// The metadata directory every worktree of the repository shares, given
+/// the one `resolve` found: for a linked worktree, the main repository's
+/// named by its `commondir` file; otherwise `git_path` itself. Objects,
+/// refs under refs/, config, hooks and the list of worktrees are kept
+/// there, HEAD, the index and operations in progress in `git_path`.
+pub fn common(git_path: &Path) -> PathBuf {
+    match fs::read_to_string(git_path.join(COMMON_DIR_FILE)) {
+        Ok(common) if !common.trim().is_empty() => {
+            let common = Path::new(common.trim());
+            if common.is_absolute() { common.to_path_buf() } else { git_path.join(common) }
+        },
+        _ => git_path.to_path_buf(),
+    }
+}
+
+/// The object database of the repository at `git_path`
+pub fn objects(git_path: &Path) -> PathBuf {
+    common(git_path).join(OBJECTS_DIR)
+}
+
+/// Whether the ref or reflog `name`, relative to the metadata directory,
+/// is shared by all worktrees rather than each having its own, as HEAD
+pub fn is_shared_ref(name: &str) -> bool {
+    name.starts_with("refs/") && !name.starts_with("refs/bisect/") && !name.starts_with("refs/worktree/")
+}
+
+/// The metadata directory of a worktree nested at `root_path`, such as a
+/// submodule's, if it has one; `ASH_DIR` is about the outer repository, so
+/// it does not count here
+pub fn find(root_path: &Path) -> Option<PathBuf> {
+    let dot_git = root_path.join(GIT_DIR_NAME);
+    if dot_git.is_file() {
+        let target = read_pointer(&dot_git)?;
+        return Some(if target.is_absolute() { target } else { root_path.join(target) });
+    }
+    dot_git.is_dir().then_some(dot_git)
+}
+
+/// Whether `path` holds the metadata directly, with no worktree around it,
+/// as `init --bare` and `clone --mirror` leave it
+pub fn is_bare(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
+
+/// Write a `.ash` pointer file in `root_path` referring to `git_dir`
+pub fn write_pointer(root_path: &Path, git_dir: &Path) -> Result<(), Error> {
+    let pointer = root_path.join(GIT_DIR_NAME);
+    if pointer.is_dir() {
+        return Err(Error::Generic(format!(
+            "'{}' already exists and is a directory", pointer.display()
+        )));
+    }
+
+    fs::write(&pointer, format!("{} {}\n", GIT_DIR_PREFIX, git_dir.display()))
+        .map_err(|e| Error::Generic(format!("Could not write '{}': {}", pointer.display(), e)))
+}
+
+// Parse the `gitdir: <path>` line of a pointer file
+fn read_pointer(path: &Path) -> Option<PathBuf> {
+    let content = fs::read_to_string(path).ok()?;
+    let line = content.lines().next()?;
+    let target = line.strip_prefix(GIT_DIR_PREFIX)?.trim();
+    if target.is_empty() {
+        None
+    } else {
+        Some(PathBuf::from(target))
+    }
+}
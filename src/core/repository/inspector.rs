@@ -8,6 +8,7 @@ use crate::core::index::entry::Entry;
 use crate::core::workspace::Workspace;
 use crate::core::index::index::Index;
 use crate::core::database::database::{Database, GitObject};
+use crate::trace;
 
 // Enum for change types in the repository
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -62,7 +63,7 @@ impl<'a> Inspector<'a> {
             return Ok(false);
         }
         
-        println!("DEBUG: Checking if directory contains untracked files: {}", dir_path.display());
+        trace!("DEBUG: Checking if directory contains untracked files: {}", dir_path.display());
         
         // Get all entries in the directory
         match std::fs::read_dir(dir_path) {
@@ -89,7 +90,7 @@ impl<'a> Inspector<'a> {
                             
                             // If it's a file not in the index, it's untracked
                             if path.is_file() && !self.index.tracked(&rel_path_str) {
-                                println!("DEBUG: Found untracked file: {}", rel_path_str);
+                                trace!("DEBUG: Found untracked file: {}", rel_path_str);
                                 return Ok(true);
                             } else if path.is_dir() {
                                 // Recursively check subdirectories
@@ -144,9 +145,9 @@ impl<'a> Inspector<'a> {
         
         // Debug output to help diagnose issues
         if content_changed {
-            println!("DEBUG: File {} content differs", path.display());
-            println!("  Index OID:   {}", entry.oid);
-            println!("  Content OID: {}", actual_oid);
+            trace!("DEBUG: File {} content differs", path.display());
+            trace!("  Index OID:   {}", entry.oid);
+            trace!("  Content OID: {}", actual_oid);
             return Ok(Some(ChangeType::Modified));
         }
         
@@ -179,9 +180,9 @@ impl<'a> Inspector<'a> {
         let oid_match = item.get_oid() == entry.oid;
         
         if !mode_match || !oid_match {
-            println!("DEBUG: Entry differs - mode match: {}, oid match: {}", mode_match, oid_match);
-            println!("  Tree mode: {}, Index mode: {}", item.get_mode(), entry.mode_octal());
-            println!("  Tree OID:  {}, Index OID:  {}", item.get_oid(), entry.oid);
+            trace!("DEBUG: Entry differs - mode match: {}, oid match: {}", mode_match, oid_match);
+            trace!("  Tree mode: {}, Index mode: {}", item.get_mode(), entry.mode_octal());
+            trace!("  Tree OID:  {}, Index OID:  {}", item.get_oid(), entry.oid);
             Some(ChangeType::Modified)
         } else {
             None
@@ -203,9 +204,9 @@ impl<'a> Inspector<'a> {
         let matches = workspace_oid == oid;
         
         if !matches {
-            println!("DEBUG: File {} differs from blob {}", path.display(), oid);
-            println!("  Blob OID:      {}", oid);
-            println!("  Workspace OID: {}", workspace_oid);
+            trace!("DEBUG: File {} differs from blob {}", path.display(), oid);
+            trace!("  Blob OID:      {}", oid);
+            trace!("  Workspace OID: {}", workspace_oid);
         }
         
         Ok(!matches) // Return true if they differ
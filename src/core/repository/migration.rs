@@ -1,13 +1,16 @@
 // src/core/repository/migration.rs
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use crate::core::advice;
 use crate::core::database::blob::Blob;
 use crate::core::database::tree::{Tree, TreeEntry};
 use crate::core::file_mode::FileMode;
 use crate::errors::error::Error;
 use crate::core::repository::repository::Repository;
 use crate::core::database::entry::DatabaseEntry;
-use crate::core::repository::inspector::{Inspector, ChangeType};
+use crate::core::repository::inspector::Inspector;
+use crate::core::path_filter::PathFilter;
+use crate::trace;
 
 // Define conflict types for different error scenarios
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
@@ -16,7 +19,6 @@ pub enum ConflictType {
     StaleDirectory,      // Directory contains modified files
     UntrackedOverwritten, // Untracked file would be overwritten
     UntrackedRemoved,    // Untracked file would be removed
-    UncommittedChanges,  // Added a new type for uncommitted changes
 }
 
 pub struct Migration<'a> {
@@ -25,6 +27,7 @@ pub struct Migration<'a> {
     pub errors: Vec<String>,
     conflicts: HashMap<ConflictType, HashSet<String>>,
     changes_to_make: Vec<Change>,
+    force: bool,
 }
 
 #[derive(Clone)]
@@ -42,7 +45,6 @@ impl<'a> Migration<'a> {
         conflicts.insert(ConflictType::StaleDirectory, HashSet::new());
         conflicts.insert(ConflictType::UntrackedOverwritten, HashSet::new());
         conflicts.insert(ConflictType::UntrackedRemoved, HashSet::new());
-        conflicts.insert(ConflictType::UncommittedChanges, HashSet::new()); // Add the new conflict type
         
         Migration {
             repo,
@@ -50,6 +52,7 @@ impl<'a> Migration<'a> {
             errors: Vec::new(),
             conflicts,
             changes_to_make: Vec::new(),
+            force: false,
         }
     }
     
@@ -62,19 +65,18 @@ impl<'a> Migration<'a> {
         
         // Golim și lista de erori
         self.errors.clear();
+        self.force = true;
         
-        println!("Force flag applied - ignoring potential conflicts");
+        trace!("Force flag applied - ignoring potential conflicts");
     }
 
     pub fn apply_changes(&mut self) -> Result<(), Error> {
         // Analyze changes using Inspector to detect conflicts
         self.analyze_changes()?;
         
-        // Check if there are any conflicts that would prevent checkout, doar dacă erori nu e goală
-        // Dacă am aplicat force flag, vom avea erori și conflicts goale
-        if !self.errors.is_empty() {
-            self.check_conflicts()?;
-        }
+        // Check if there are any conflicts that would prevent checkout
+        // Dacă am aplicat force flag, conflicts sunt goale
+        self.check_conflicts()?;
         
         // Apply the planned changes
         self.execute_changes()?;
@@ -87,7 +89,7 @@ impl<'a> Migration<'a> {
     
     // New method to perform more comprehensive directory cleanup
     fn cleanup_empty_directories(&mut self) -> Result<(), Error> {
-        println!("Performing final empty directory cleanup");
+        trace!("Performing final empty directory cleanup");
         
         // First get all directories that exist in the workspace
         let workspace_dirs = self.find_all_workspace_directories()?;
@@ -128,16 +130,16 @@ impl<'a> Migration<'a> {
             };
             
             if is_effectively_empty {
-                println!("Removing empty directory in final cleanup: {}", dir.display());
+                trace!("Removing empty directory in final cleanup: {}", dir.display());
                 
                 // First try normal directory removal
                 match std::fs::remove_dir(&full_path) {
                     Ok(_) => {
-                        println!("Successfully removed empty directory: {}", dir.display());
+                        trace!("Successfully removed empty directory: {}", dir.display());
                     },
                     Err(e) => {
                         // If that fails, try force removal for directories that might have hidden files
-                        println!("Standard removal failed, trying force removal: {} - {}", dir.display(), e);
+                        trace!("Standard removal failed, trying force removal: {} - {}", dir.display(), e);
                         
                         // First remove any hidden files
                         if let Ok(entries) = std::fs::read_dir(&full_path) {
@@ -158,7 +160,7 @@ impl<'a> Migration<'a> {
                         if let Err(e) = std::fs::remove_dir(&full_path) {
                             println!("Warning: Still could not remove directory: {} - {}", dir.display(), e);
                         } else {
-                            println!("Successfully removed directory after clearing hidden files: {}", dir.display());
+                            trace!("Successfully removed directory after clearing hidden files: {}", dir.display());
                         }
                     }
                 }
@@ -169,67 +171,16 @@ impl<'a> Migration<'a> {
     }
 
     fn find_all_workspace_directories(&self) -> Result<HashSet<PathBuf>, Error> {
-        let mut dirs = HashSet::new();
-        let root_path = &self.repo.workspace.root_path;
-        
-        // Skip .ash directory
-        let git_dir = root_path.join(".ash");
-        
-        self.collect_directories_recursive(root_path, &PathBuf::new(), &mut dirs, &git_dir)?;
-        
-        Ok(dirs)
+        Ok(self.repo.workspace.walk(&PathFilter::new())?
+            .into_iter()
+            .filter(|entry| entry.is_dir() && !Self::is_hidden(&entry.path))
+            .map(|entry| entry.path)
+            .collect())
     }
-    
-    // Helper to recursively collect directories
-    fn collect_directories_recursive(
-        &self, 
-        full_path: &Path, 
-        rel_path: &Path, 
-        dirs: &mut HashSet<PathBuf>,
-        git_dir: &Path
-    ) -> Result<(), Error> {
-        // Skip if this is the .ash directory
-        if full_path == git_dir {
-            return Ok(());
-        }
-        
-        // Skip if path doesn't exist or isn't a directory
-        if !full_path.exists() || !full_path.is_dir() {
-            return Ok(());
-        }
-        
-        // Add this directory
-        dirs.insert(rel_path.to_path_buf());
-        
-        // Process subdirectories
-        if let Ok(entries) = std::fs::read_dir(full_path) {
-            for entry_result in entries {
-                if let Ok(entry) = entry_result {
-                    let entry_path = entry.path();
-                    let entry_name = entry.file_name();
-                    
-                    // Skip hidden directories
-                    if entry_name.to_string_lossy().starts_with('.') {
-                        continue;
-                    }
-                    
-                    // Only process directories
-                    if entry_path.is_dir() {
-                        // Get relative path
-                        let entry_rel_path = if rel_path.as_os_str().is_empty() {
-                            PathBuf::from(entry_name)
-                        } else {
-                            rel_path.join(entry_name)
-                        };
-                        
-                        // Recursively collect this directory
-                        self.collect_directories_recursive(&entry_path, &entry_rel_path, dirs, git_dir)?;
-                    }
-                }
-            }
-        }
-        
-        Ok(())
+
+    // Hidden files and directories are left alone by the cleanup
+    fn is_hidden(path: &Path) -> bool {
+        path.components().any(|component| component.as_os_str().to_string_lossy().starts_with('.'))
     }
     
     // Helper method to find all potentially empty directories in the workspace
@@ -255,7 +206,7 @@ impl<'a> Migration<'a> {
     }
     
     fn analyze_changes(&mut self) -> Result<(), Error> {
-        println!("Analyzing changes for migration");
+        trace!("Analyzing changes for migration");
         
         // Create Inspector to help analyze the repository state
         let inspector = Inspector::new(
@@ -264,30 +215,10 @@ impl<'a> Migration<'a> {
             &self.repo.database
         );
         
-        // First, check if there are uncommitted changes in the workspace
-        // This is the key improvement - using the analyze_workspace_changes method
-        let workspace_changes = inspector.analyze_workspace_changes()?;
-        
-        // If there are any uncommitted changes, record them as conflicts
-        if !workspace_changes.is_empty() {
-            println!("Found uncommitted changes in workspace:");
-            for (path, change_type) in &workspace_changes {
-                match change_type {
-                    ChangeType::Modified | ChangeType::Added | ChangeType::Deleted => {
-                        println!("  {} - {:?}", path, change_type);
-                        self.conflicts.get_mut(&ConflictType::UncommittedChanges).unwrap().insert(path.clone());
-                    },
-                    _ => {} // Ignore untracked files here
-                }
-            }
-            
-            // If we found uncommitted changes, we can exit early
-            if !self.conflicts.get(&ConflictType::UncommittedChanges).unwrap().is_empty() {
-                return Ok(());
-            }
-        }
-        
-        // Next, find all files in current state that should be deleted
+        // Local changes are carried over as long as the paths they touch are
+        // not changed between the two trees; only those paths are checked below
+
+        // Find all files in current state that should be deleted
         let mut current_paths = HashSet::new();
         let mut target_paths = HashSet::new();
         
@@ -309,7 +240,7 @@ impl<'a> Migration<'a> {
         
         // Add deletions to our change list
         for path in deleted_files {
-            println!("Planning deletion for file: {}", path.display());
+            trace!("Planning deletion for file: {}", path.display());
             self.changes_to_make.push(Change::Delete { path });
         }
         
@@ -326,7 +257,8 @@ impl<'a> Migration<'a> {
                 e.get_mode() == "040000" || FileMode::parse(e.get_mode()).is_directory()
             });
             
-            if !is_directory {
+            // With force, local changes and untracked files are simply overwritten
+            if !is_directory && !self.force {
                 // Check for conflicts using Inspector
                 let path_str = path.to_string_lossy().to_string();
                 let entry = self.repo.index.get_entry(&path_str);
@@ -339,7 +271,7 @@ impl<'a> Migration<'a> {
                     
                     if changed_from_old.is_some() && changed_from_new.is_some() {
                         // Index has changes compared to both old and new - conflict
-                        println!("Index entry for {} differs from both old and new trees", path_str);
+                        trace!("Index entry for {} differs from both old and new trees", path_str);
                         self.conflicts.get_mut(&ConflictType::StaleFile).unwrap().insert(path_str.clone());
                         continue;
                     }
@@ -347,7 +279,7 @@ impl<'a> Migration<'a> {
                     // Use compare_workspace_vs_blob to check if workspace content matches the indexed content
                     if let Ok(has_changes) = inspector.compare_workspace_vs_blob(&path, index_entry.get_oid()) {
                         if has_changes {
-                            println!("Uncommitted changes in workspace file: {}", path_str);
+                            trace!("Uncommitted changes in workspace file: {}", path_str);
                             self.conflicts.get_mut(&ConflictType::StaleFile).unwrap().insert(path_str.clone());
                             continue;
                         }
@@ -359,14 +291,14 @@ impl<'a> Migration<'a> {
                     if stat.is_file() {
                         if new_entry.is_some() {
                             // Would overwrite untracked file
-                            println!("Untracked file would be overwritten: {}", path_str);
+                            trace!("Untracked file would be overwritten: {}", path_str);
                             self.conflicts.get_mut(&ConflictType::UntrackedOverwritten).unwrap().insert(path_str.clone());
                             continue;
                         }
                     } else if stat.is_dir() {
                         // Check for untracked files in directory using Inspector
                         if inspector.trackable_file(&path, &stat)? {
-                            println!("Directory contains untracked files: {}", path_str);
+                            trace!("Directory contains untracked files: {}", path_str);
                             self.conflicts.get_mut(&ConflictType::StaleDirectory).unwrap().insert(path_str.clone());
                             continue;
                         }
@@ -423,12 +355,11 @@ impl<'a> Migration<'a> {
                 "The following untracked working tree files would be removed by checkout:",
                 "Please move or remove them before you switch branches."
             )),
-            (ConflictType::UncommittedChanges, (
-                "You have uncommitted changes in your working tree:",
-                "Please commit your changes or stash them before you switch branches."
-            ))
         ]);
         
+        // What to do about them is advice, which can be turned off
+        let advise = advice::enabled(advice::COMMIT_BEFORE_MERGE);
+
         // Check each conflict type
         for (conflict_type, paths) in &self.conflicts {
             if paths.is_empty() {
@@ -455,7 +386,11 @@ impl<'a> Migration<'a> {
                 error_message.push_str(&line);
                 error_message.push('\n');
             }
-            error_message.push_str(footer);
+            if advise {
+                error_message.push_str(footer);
+            } else {
+                error_message.pop();
+            }
             
             self.errors.push(error_message);
         }
@@ -470,7 +405,7 @@ impl<'a> Migration<'a> {
     
     // Execute all planned changes
     fn execute_changes(&mut self) -> Result<(), Error> {
-        println!("Executing {} changes", self.changes_to_make.len());
+        trace!("Executing {} changes", self.changes_to_make.len());
         
         // Clone the changes to avoid borrowing issues
         let changes_clone = self.changes_to_make.clone();
@@ -481,8 +416,13 @@ impl<'a> Migration<'a> {
         // First, handle deletions
         for change in &changes_clone {
             if let Change::Delete { path } = change {
-                println!("Removing file: {}", path.display());
-                self.repo.workspace.remove_file(path)?;
+                trace!("Removing file: {}", path.display());
+                // A deleted tree entry removes the whole directory
+                if self.repo.workspace.root_path.join(path).is_dir() {
+                    self.repo.workspace.force_remove_directory(path)?;
+                } else {
+                    self.repo.workspace.remove_file(path)?;
+                }
                 
                 // Also remove from index
                 let path_str = path.to_string_lossy().to_string();
@@ -522,7 +462,7 @@ impl<'a> Migration<'a> {
         
         // Create all needed directories
         for dir in dir_list {
-            println!("Creating directory: {}", dir.display());
+            trace!("Creating directory: {}", dir.display());
             self.repo.workspace.make_directory(&dir)?;
         }
         
@@ -532,14 +472,14 @@ impl<'a> Migration<'a> {
                 Change::Create { path, entry } | Change::Update { path, entry } => {
                     // Check if this is a directory entry
                     if entry.get_mode() == "040000" || FileMode::parse(entry.get_mode()).is_directory() {
-                        println!("Creating directory: {}", path.display());
+                        trace!("Creating directory: {}", path.display());
                         self.repo.workspace.make_directory(&path)?;
                         
                         // Process directory contents
                         self.process_directory_contents(&path, &entry.get_oid())?;
                     } else {
                         // Write the file and update index
-                        println!("Writing file: {}", path.display());
+                        trace!("Writing file: {}", path.display());
                         self.write_file(&path, &entry)?;
                     }
                 },
@@ -555,7 +495,7 @@ impl<'a> Migration<'a> {
                 continue;
             }
             
-            println!("Checking if directory is empty: {}", dir.display());
+            trace!("Checking if directory is empty: {}", dir.display());
             self.repo.workspace.remove_directory(&dir)?;
         }
         
@@ -581,7 +521,7 @@ impl<'a> Migration<'a> {
     
     // Process a directory's contents recursively
     fn process_directory_contents(&mut self, directory_path: &Path, directory_oid: &str) -> Result<(), Error> {
-        println!("Processing directory contents: {}", directory_path.display());
+        trace!("Processing directory contents: {}", directory_path.display());
         
         // Load the tree object
         let obj = self.repo.database.load(directory_oid)?;
@@ -598,14 +538,14 @@ impl<'a> Migration<'a> {
             let current_files = self.get_all_workspace_files(directory_path)?;
             
             // Debug output
-            println!("Target files for {}: {}", directory_path.display(), target_files.len());
+            trace!("Target files for {}: {}", directory_path.display(), target_files.len());
             for (path, (oid, _)) in &target_files {
-                println!("  Target file: {} -> {}", path.display(), oid);
+                trace!("  Target file: {} -> {}", path.display(), oid);
             }
             
-            println!("Current files for {}: {}", directory_path.display(), current_files.len());
+            trace!("Current files for {}: {}", directory_path.display(), current_files.len());
             for path in &current_files {
-                println!("  Current file: {}", path.display());
+                trace!("  Current file: {}", path.display());
             }
             
             // First ensure all directories exist
@@ -625,7 +565,7 @@ impl<'a> Migration<'a> {
             
             // Create all necessary directories
             for dir in dir_list {
-                println!("Creating directory: {}", dir.display());
+                trace!("Creating directory: {}", dir.display());
                 self.repo.workspace.make_directory(&dir)?;
             }
             
@@ -634,13 +574,13 @@ impl<'a> Migration<'a> {
                 // Create parent directories if needed
                 if let Some(parent) = path.parent() {
                     if parent != directory_path && !parent.exists() {
-                        println!("Creating parent directory: {}", parent.display());
+                        trace!("Creating parent directory: {}", parent.display());
                         self.repo.workspace.make_directory(parent)?;
                     }
                 }
                 
                 // Write the file content
-                println!("Writing file: {}", path.display());
+                trace!("Writing file: {}", path.display());
                 
                 // Get and write the blob content
                 let blob_obj = self.repo.database.load(oid)?;
@@ -669,7 +609,7 @@ impl<'a> Migration<'a> {
             
             // Delete files that exist in current state but not in target state
             for file_path in sorted_files_to_remove {
-                println!("Removing file that doesn't exist in target: {}", file_path.display());
+                trace!("Removing file that doesn't exist in target: {}", file_path.display());
                 self.repo.workspace.remove_file(&file_path)?;
                 
                 // Also remove from index
@@ -681,58 +621,6 @@ impl<'a> Migration<'a> {
         Ok(())
     }
 
-    fn get_current_entries_in_dir(&self, dir_path: &Path) -> Result<HashSet<PathBuf>, Error> {
-        let mut entries = HashSet::new();
-        let full_dir_path = self.repo.workspace.root_path.join(dir_path);
-        
-        // Skip if directory doesn't exist
-        if !full_dir_path.exists() || !full_dir_path.is_dir() {
-            return Ok(entries);
-        }
-        
-        // Add this directory's files and subdirectories recursively
-        self.collect_entries_recursive(&full_dir_path, dir_path, &mut entries)?;
-        
-        Ok(entries)
-    }
-
-    fn collect_entries_recursive(&self, full_path: &Path, rel_path: &Path, entries: &mut HashSet<PathBuf>) -> Result<(), Error> {
-        // Skip if path doesn't exist
-        if !full_path.exists() {
-            return Ok(());
-        }
-        
-        // Add this entry if it's not the root directory we're checking
-        if rel_path.as_os_str().len() > 0 {
-            entries.insert(rel_path.to_path_buf());
-        }
-        
-        // If it's a directory, process contents
-        if full_path.is_dir() {
-            if let Ok(dir_entries) = std::fs::read_dir(full_path) {
-                for entry_result in dir_entries {
-                    if let Ok(entry) = entry_result {
-                        let entry_path = entry.path();
-                        let entry_name = entry.file_name();
-                        
-                        // Skip hidden files and directories
-                        if entry_name.to_string_lossy().starts_with('.') {
-                            continue;
-                        }
-                        
-                        // Get relative path
-                        let entry_rel_path = rel_path.join(entry_name);
-                        
-                        // Recursively collect this entry
-                        self.collect_entries_recursive(&entry_path, &entry_rel_path, entries)?;
-                    }
-                }
-            }
-        }
-        
-        Ok(())
-    }
-
     // Get all current files in a specific directory
     fn get_current_files_in_dir(&self, dir_path: &Path) -> Result<HashSet<PathBuf>, Error> {
         let mut files = HashSet::new();
@@ -753,56 +641,19 @@ impl<'a> Migration<'a> {
     }
 
     fn get_all_workspace_files(&mut self, dir_path: &Path) -> Result<HashSet<PathBuf>, Error> {
-        let mut files = HashSet::new();
         let full_dir_path = self.repo.workspace.root_path.join(dir_path);
         
         // Skip if directory doesn't exist
         if !full_dir_path.exists() || !full_dir_path.is_dir() {
-            return Ok(files);
-        }
-        
-        self.collect_files_recursive(&full_dir_path, dir_path, &mut files)?;
-        
-        Ok(files)
-    }
-
-    fn collect_files_recursive(&mut self, full_path: &Path, rel_path: &Path, files: &mut HashSet<PathBuf>) -> Result<(), Error> {
-        // Skip if path doesn't exist
-        if !full_path.exists() {
-            return Ok(());
-        }
-        
-        // If it's a file, add it
-        if full_path.is_file() {
-            files.insert(rel_path.to_path_buf());
-            return Ok(());
+            return Ok(HashSet::new());
         }
         
-        // If it's a directory, process contents
-        if full_path.is_dir() {
-            if let Ok(dir_entries) = std::fs::read_dir(full_path) {
-                for entry_result in dir_entries {
-                    if let Ok(entry) = entry_result {
-                        let entry_path = entry.path();
-                        let entry_name = entry.file_name();
-                        
-                        // Skip .ash directory and hidden files
-                        if entry_name.to_string_lossy() == ".ash" || 
-                        entry_name.to_string_lossy().starts_with('.') {
-                            continue;
-                        }
-                        
-                        // Get relative path
-                        let entry_rel_path = rel_path.join(entry_name);
-                        
-                        // Process this entry
-                        self.collect_files_recursive(&entry_path, &entry_rel_path, files)?;
-                    }
-                }
-            }
-        }
-        
-        Ok(())
+        Ok(self.repo.workspace.walk(&PathFilter::build(&[dir_path.to_path_buf()]))?
+            .into_iter()
+            .filter(|entry| entry.is_file())
+            .filter(|entry| !Self::is_hidden(entry.path.strip_prefix(dir_path).unwrap_or(&entry.path)))
+            .map(|entry| entry.path)
+            .collect())
     }
 
     fn collect_all_target_files(
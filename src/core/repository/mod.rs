@@ -2,4 +2,9 @@ pub mod repository;
 pub mod migration;
 pub mod inspector;
 pub mod pending_commit;
-pub mod sequencer;
\ No newline at end of file
+pub mod sequencer;
+pub mod git_dir;
+pub mod autostash;
+pub mod stash;
+pub mod format;
+pub mod worktree;
@@ -3,6 +3,7 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::{fs as fs_std, io};
 
+use crate::core::merge::conflict_summary::ConflictSummary;
 use crate::errors::error::Error;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
@@ -16,6 +17,8 @@ pub enum PendingCommitType {
 pub struct PendingCommit {
     pathname: PathBuf,
     pub message_path: PathBuf,
+    // Left by `merge --squash` for the commit that follows it
+    pub squash_message_path: PathBuf,
 }
 
 impl PendingCommit {
@@ -23,6 +26,7 @@ impl PendingCommit {
         Self {
             pathname: pathname.to_owned(),
             message_path: pathname.join("MERGE_MSG"),
+            squash_message_path: pathname.join("SQUASH_MSG"),
         }
     }
 
@@ -70,8 +74,9 @@ impl PendingCommit {
             PendingCommitType::Revert => self.pathname.join("REVERT_HEAD"),
         };
 
+        // An octopus merge lists a head a line; the last is the one merged last
         match fs::read_to_string(&head_path) {
-            Ok(oid) => Ok(oid.trim().to_string()),
+            Ok(oids) => Ok(oids.lines().last().unwrap_or_default().trim().to_string()),
             Err(err) => {
                 if err.kind() == io::ErrorKind::NotFound {
                     let name = head_path.file_name().unwrap().to_string_lossy().to_string();
@@ -84,6 +89,14 @@ impl PendingCommit {
         }
     }
 
+    /// Every head a merge in progress is merging, the one of a simple merge
+    /// or several for an octopus
+    pub fn merge_oids(&self) -> Result<Vec<String>, Error> {
+        self.merge_oid(PendingCommitType::Merge)?;
+        let oids = fs::read_to_string(self.pathname.join("MERGE_HEAD"))?;
+        Ok(oids.lines().map(str::trim).filter(|oid| !oid.is_empty()).map(str::to_string).collect())
+    }
+
     pub fn merge_message(&self) -> Result<String, Error> {
         match fs::read_to_string(&self.message_path) {
             Ok(message) => Ok(message),
@@ -110,6 +123,8 @@ impl PendingCommit {
             Err(err) => return self.handle_no_merge_to_abort(&head_path, err),
         }
         
+        ConflictSummary::clear(&self.pathname);
+
         // Also remove the message file if it exists
         if self.message_path.exists() {
             fs::remove_file(&self.message_path)
@@ -119,6 +134,22 @@ impl PendingCommit {
         Ok(())
     }
 
+    /// The message `merge --squash` prepared, if one is waiting
+    pub fn squash_message(&self) -> Result<Option<String>, Error> {
+        match fs::read_to_string(&self.squash_message_path) {
+            Ok(message) => Ok(Some(message)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(Error::Generic(format!("Failed to read squash message: {}", e))),
+        }
+    }
+
+    pub fn clear_squash(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.squash_message_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::Generic(format!("Failed to remove squash message file: {}", e))),
+            _ => Ok(()),
+        }
+    }
+
     fn handle_no_merge_to_abort(&self, head_path: &Path, err: io::Error) -> Result<(), Error> {
         if err.kind() == io::ErrorKind::NotFound {
             let name = head_path.file_name().unwrap().to_string_lossy().to_string();
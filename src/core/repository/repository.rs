@@ -8,6 +8,8 @@ use crate::core::index::index::Index;
 use std::collections::HashMap;
 use crate::core::database::entry::DatabaseEntry;
 use super::migration::Migration;
+use crate::core::repository::{format, git_dir};
+use crate::core::config::Config;
 
 pub struct Repository {
     pub path: PathBuf,
@@ -23,22 +25,31 @@ impl Repository {
             Error::PathResolution(format!("Failed to resolve path '{}': {}", path, e))
         })?;
         
-        let git_path = path_buf.join(".ash");
+        let git_path = git_dir::resolve(&path_buf);
         
-        let db_path = git_path.join("objects");
+        let db_path = git_dir::objects(&git_path);
         let index_path = git_path.join("index");
+
+        // Remotes a partial fetch came from still hold what it left out
+        let mut database = Database::new(db_path);
+        let config = Config::load(&git_path)?;
+        format::check(&config)?;
+        database.set_promisors(config.subsections("remote").iter()
+            .filter(|name| config.get_bool(&format!("remote.{}.promisor", name)).unwrap_or(false))
+            .filter_map(|name| config.get(&format!("remote.{}.url", name)))
+            .collect());
         
         Ok(Repository {
             workspace: Workspace::new(&path_buf),
             index: Index::new(index_path),
-            database: Database::new(db_path),
+            database,
             refs: Refs::new(&git_path),
             path: path_buf,
         })
     }
 
     pub fn create_git_directory(&self) -> Result<PathBuf, Error> {
-        let git_path = self.path.join(".ash");
+        let git_path = git_dir::resolve(&self.path);
         self.create_directory(&git_path)?;
         Ok(git_path)
     }
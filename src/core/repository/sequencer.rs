@@ -9,19 +9,29 @@ use regex::Regex;
 
 use crate::core::database::commit::Commit;
 use crate::core::database::database::Database;
+use crate::core::editor::Editor;
 use crate::core::lockfile::Lockfile;
 use crate::core::refs::{Refs, HEAD};
 use crate::errors::error::Error;
+use crate::core::repository::git_dir;
+
+// The todo list as handed to the editor, under the sequencer's directory
+const EDIT_TODO_FILE: &str = "git-rebase-todo";
 
 fn get_line_regex() -> Regex {
-    Regex::new(r"^(\S+) (\S+) (.*)$").unwrap()
+    Regex::new(r"^(\S+)\s+(\S+)(?:\s+(.*))?$").unwrap()
 }
 
 /// Actions that can be performed during sequencing
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Action {
     Pick,
     Revert,
+    Reword,
+    Edit,
+    Squash,
+    Fixup,
+    Drop,
 }
 
 impl fmt::Display for Action {
@@ -29,6 +39,11 @@ impl fmt::Display for Action {
         let action = match self {
             Action::Pick => "pick",
             Action::Revert => "revert",
+            Action::Reword => "reword",
+            Action::Edit => "edit",
+            Action::Squash => "squash",
+            Action::Fixup => "fixup",
+            Action::Drop => "drop",
         };
         write!(f, "{}", action)
     }
@@ -39,13 +54,34 @@ impl FromStr for Action {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "pick" => Ok(Action::Pick),
+            "pick" | "p" => Ok(Action::Pick),
             "revert" => Ok(Action::Revert),
+            "reword" | "r" => Ok(Action::Reword),
+            "edit" | "e" => Ok(Action::Edit),
+            "squash" | "s" => Ok(Action::Squash),
+            "fixup" | "f" => Ok(Action::Fixup),
+            "drop" | "d" => Ok(Action::Drop),
             _ => Err(Error::Generic(format!("Unknown sequencer action: {}", s))),
         }
     }
 }
 
+/// One line of the todo list
+#[derive(Debug, Clone)]
+pub enum Step {
+    /// Do `Action` with a commit
+    Commit(Action, Box<Commit>),
+    /// Run a shell command
+    Exec(String),
+    /// Name the commit at HEAD, for a later `Reset` or `Merge`
+    Label(String),
+    /// Move HEAD to a label, or a commit
+    Reset(String),
+    /// Merge the labels, or commits, into HEAD, with the message of the
+    /// original merge commit if there is one
+    Merge(Option<Box<Commit>>, Vec<String>),
+}
+
 /// The Sequencer handles sequenced operations like cherry-pick and revert
 #[derive(Debug)]
 pub struct Sequencer {
@@ -59,13 +95,19 @@ pub struct Sequencer {
     options_path: PathBuf,
     
     // Sequencing state
-    commands: Vec<(Action, Commit)>,
+    commands: Vec<Step>,
 }
 
 impl Sequencer {
     /// Create a new Sequencer for the given repository
     pub fn new(repo_path: PathBuf) -> Self {
-        let pathname = repo_path.join("sequencer");
+        Self::at(repo_path, "sequencer")
+    }
+
+    /// A sequencer keeping its state in `dir` under the repository, so that
+    /// a rebase and a cherry-pick do not trip over each other's state
+    pub fn at(repo_path: PathBuf, dir: &str) -> Self {
+        let pathname = repo_path.join(dir);
         let abort_path = pathname.join("abort-safety");
         let head_path = pathname.join("head");
         let todo_path = pathname.join("todo");
@@ -110,6 +152,16 @@ impl Sequencer {
         Ok(())
     }
 
+    /// Whether an operation has been started and not finished or quit
+    pub fn in_progress(&self) -> bool {
+        self.pathname.exists()
+    }
+
+    /// HEAD as it was when the operation started
+    pub fn original_head(&self) -> Result<String, Error> {
+        Ok(fs::read_to_string(&self.head_path)?.trim().to_owned())
+    }
+
     /// Get an option value from the options file
     pub fn get_option(&self, name: &str) -> Result<Option<String>, Error> {
         if !self.options_path.exists() {
@@ -133,17 +185,78 @@ impl Sequencer {
 
     /// Add a cherry-pick command to the sequencer
     pub fn add_pick(&mut self, commit: Commit) {
-        self.commands.push((Action::Pick, commit));
+        self.commands.push(Step::Commit(Action::Pick, Box::new(commit)));
     }
 
     /// Add a revert command to the sequencer
     pub fn add_revert(&mut self, commit: Commit) {
-        self.commands.push((Action::Revert, commit));
+        self.commands.push(Step::Commit(Action::Revert, Box::new(commit)));
+    }
+
+    /// Add a shell command to run at this point
+    pub fn add_exec(&mut self, command: &str) {
+        self.commands.push(Step::Exec(command.to_string()));
+    }
+
+    pub fn add_label(&mut self, name: &str) {
+        self.commands.push(Step::Label(name.to_string()));
+    }
+
+    pub fn add_reset(&mut self, name: &str) {
+        self.commands.push(Step::Reset(name.to_string()));
+    }
+
+    /// Add a merge of `parents` into HEAD, recreating `commit`
+    pub fn add_merge(&mut self, commit: Option<Commit>, parents: Vec<String>) {
+        self.commands.push(Step::Merge(commit.map(Box::new), parents));
+    }
+
+    /// The commands left, in order
+    pub fn commands(&self) -> &[Step] {
+        &self.commands
+    }
+
+    /// Let the user rearrange the commands left in `command`, the editor,
+    /// with `help` below them as comments. What comes back replaces them.
+    pub fn edit_todo(&mut self, command: Option<String>, help: &str) -> Result<(), Error> {
+        let todo = self.format_todo();
+        let edited = Editor::edit(self.pathname.join(EDIT_TODO_FILE), command, |editor| {
+            editor.write(todo.trim_end())?;
+            editor.write("")?;
+            editor.note(help)
+        })?;
+        self.commands = self.parse_todo(edited.as_deref().unwrap_or_default())?;
+        Ok(())
+    }
+
+    /// Keep `content` under `name` with the operation's state, until it quits
+    pub fn write_state(&self, name: &str, content: &str) -> Result<(), Error> {
+        self.write_file(&self.pathname.join(name), content)
+    }
+
+    pub fn read_state(&self, name: &str) -> Option<String> {
+        fs::read_to_string(self.pathname.join(name)).ok()
+            .map(|content| content.trim_end().to_string())
+    }
+
+    pub fn remove_state(&self, name: &str) -> Result<(), Error> {
+        match fs::remove_file(self.pathname.join(name)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
     }
 
-    /// Get the next command from the sequencer
+    /// Get the next command from the sequencer, if it is one on a commit
     pub fn next_command(&self) -> Option<(Action, Commit)> {
-        self.commands.first().map(|(action, commit)| (action.to_owned(), commit.to_owned()))
+        match self.commands.first() {
+            Some(Step::Commit(action, commit)) => Some((action.to_owned(), commit.as_ref().clone())),
+            _ => None,
+        }
+    }
+
+    /// The next step, whatever it is
+    pub fn next_step(&self) -> Option<Step> {
+        self.commands.first().cloned()
     }
 
     /// Drop the current command from the sequencer
@@ -174,41 +287,103 @@ impl Sequencer {
         let mut file = File::open(&self.todo_path)?;
         file.read_to_string(&mut content)?;
 
-        self.commands.clear();
-        let mut database = Database::new(self.repo_path.join("objects"));
+        self.commands = self.parse_todo(&content)?;
+
+        Ok(())
+    }
+
+    // Commands from the lines of a todo file; blank lines and `#` comments
+    // are skipped. `exec` (`x`) takes the rest of its line as the command;
+    // what follows `#` on `label`, `reset` and `merge` lines is a comment.
+    fn parse_todo(&self, content: &str) -> Result<Vec<Step>, Error> {
+        let mut database = Database::new(git_dir::objects(&self.repo_path));
         let line_regex = get_line_regex();
+        let mut commands = Vec::new();
 
-        for line in content.lines() {
-            if let Some(captures) = line_regex.captures(line) {
-                let action = &captures[1];
-                let oid = &captures[2];
-                
-                // Load the commit object
-                let obj = database.load(oid)?;
-                let commit = match obj.as_any().downcast_ref::<Commit>() {
-                    Some(commit) => commit.clone(),
-                    None => return Err(Error::Generic(format!("Invalid commit object: {}", oid)))
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((word, rest)) = line.split_once(char::is_whitespace) {
+                let args: Vec<&str> = rest.split('#').next().unwrap_or_default().split_whitespace().collect();
+                let step = match (word, args.as_slice()) {
+                    ("exec" | "x", _) => Some(Step::Exec(rest.trim().to_string())),
+                    ("label" | "l", [name]) => Some(Step::Label(name.to_string())),
+                    ("reset" | "t", [name]) => Some(Step::Reset(name.to_string())),
+                    ("merge" | "m", ["-C" | "-c", oid, parents @ ..]) if !parents.is_empty() => {
+                        let commit = Self::load_commit(&mut database, oid, line)?;
+                        Some(Step::Merge(Some(Box::new(commit)), parents.iter().map(|p| p.to_string()).collect()))
+                    },
+                    ("merge" | "m", parents) if !parents.is_empty() && !parents[0].starts_with('-') => {
+                        Some(Step::Merge(None, parents.iter().map(|p| p.to_string()).collect()))
+                    },
+                    ("label" | "l" | "reset" | "t" | "merge" | "m", _) => {
+                        return Err(Error::Generic(format!("invalid line in todo list: '{}'", line)));
+                    },
+                    _ => None,
                 };
-                
-                // Add the command to the queue
-                self.commands.push((Action::from_str(action)?, commit));
+                if let Some(step) = step {
+                    commands.push(step);
+                    continue;
+                }
             }
+            let captures = line_regex.captures(line)
+                .ok_or_else(|| Error::Generic(format!("invalid line in todo list: '{}'", line)))?;
+            let action = &captures[1];
+            let commit = Self::load_commit(&mut database, &captures[2], line)?;
+
+            // Add the command to the queue
+            commands.push(Step::Commit(Action::from_str(action)?, Box::new(commit)));
         }
 
-        Ok(())
+        Ok(commands)
+    }
+
+    // The commit a todo `line` names, by its short ID
+    fn load_commit(database: &mut Database, name: &str, line: &str) -> Result<Commit, Error> {
+        let oid = match database.prefix_match(name)?.as_slice() {
+            [oid] => oid.clone(),
+            _ => name.to_string(),
+        };
+        let obj = database.load(&oid)
+            .map_err(|_| Error::Generic(format!("invalid line in todo list: '{}': no such commit", line)))?;
+        match obj.as_any().downcast_ref::<Commit>() {
+            Some(commit) => Ok(commit.clone()),
+            None => Err(Error::Generic(format!("Invalid commit object: {}", oid))),
+        }
+    }
+
+    // One `<action> <short oid> <title>` line per command
+    fn format_todo(&self) -> String {
+        let database = Database::new(git_dir::objects(&self.repo_path));
+        let mut todo = String::new();
+        for step in &self.commands {
+            match step {
+                Step::Commit(action, commit) => {
+                    let oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
+                    todo.push_str(&format!("{} {} {}\n", action, database.short_oid(&oid), commit.title_line().trim()));
+                },
+                Step::Exec(command) => todo.push_str(&format!("exec {}\n", command)),
+                Step::Label(name) => todo.push_str(&format!("label {}\n", name)),
+                Step::Reset(name) => todo.push_str(&format!("reset {}\n", name)),
+                Step::Merge(Some(commit), parents) => {
+                    let oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
+                    todo.push_str(&format!(
+                        "merge -C {} {} # {}\n", database.short_oid(&oid), parents.join(" "), commit.title_line().trim()
+                    ));
+                },
+                Step::Merge(None, parents) => todo.push_str(&format!("merge {}\n", parents.join(" "))),
+            }
+        }
+        todo
     }
 
     /// Save the current sequencer state to disk
     pub fn dump(&mut self) -> Result<(), Error> {
+        let todo = self.format_todo();
         if let Some(todo_file) = &mut self.todo_file {
-            let mut database = Database::new(self.repo_path.join("objects"));
-            
-            for (action, commit) in &self.commands {
-                let oid = commit.get_oid().map_or_else(String::new, |s| s.clone());
-                let short = database.short_oid(&oid);
-                todo_file.write(&format!("{} {} {}\n", action, short, commit.title_line()))
-                    .map_err(|e| Error::Generic(format!("Failed to write to todo file: {:?}", e)))?;
-            }
+            todo_file.write(&todo)
+                .map_err(|e| Error::Generic(format!("Failed to write to todo file: {:?}", e)))?;
 
             todo_file.commit_ref()
                 .map_err(|e| Error::Generic(format!("Failed to commit todo file: {:?}", e)))?;
@@ -247,7 +422,7 @@ impl Sequencer {
     fn hard_reset(&self, commit_oid: &str) -> Result<(), Error> {
         // Reset HEAD
         let refs = Refs::new(&self.repo_path);
-        refs.update_head(commit_oid)?;
+        refs.update_head(commit_oid, &format!("reset: moving to {}", commit_oid))?;
         
         // TODO: Reset working directory and index
         // This would need to be implemented
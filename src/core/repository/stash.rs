@@ -0,0 +1,390 @@
+// src/core/repository/stash.rs - save local changes as commits under refs/stash and restore them
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::core::database::commit::{Commit, MERGE_PARENT_TRAILER};
+use crate::core::database::blob::Blob;
+use crate::core::database::entry::DatabaseEntry;
+use crate::core::database::tree::Tree;
+use crate::core::file_mode::FileMode;
+use crate::core::merge::inputs::CherryPick;
+use crate::core::merge::resolve::Resolve;
+use crate::core::path_filter::PathFilter;
+use crate::core::reflog::{Reflog, ReflogEntry};
+use crate::core::refs::Reference;
+use crate::core::repository::autostash::{head_files, current_author};
+use crate::core::repository::git_dir;
+use crate::core::repository::inspector::Inspector;
+use crate::core::repository::repository::Repository;
+use crate::errors::error::Error;
+use crate::trace;
+
+pub const STASH_REF: &str = "refs/stash";
+
+/// A stash entry is a commit `W` holding the worktree state of tracked
+/// files. Its first parent is HEAD at the time of stashing, its second a
+/// commit `I` recording the index, and with `--include-untracked` a third,
+/// parentless commit `U` holds the untracked files.
+///
+/// `refs/stash` points at the newest entry; the older ones live on in its
+/// reflog, so `stash@{0}` is the newest, `stash@{1}` the one before it and
+/// so on.
+pub struct Stash;
+
+impl Stash {
+    /// Record local changes and clean them from the index and worktree.
+    /// Returns the stash commit, or None when there was nothing to save.
+    pub fn save(
+        repo: &mut Repository,
+        message: Option<&str>,
+        include_untracked: bool,
+        keep_index: bool,
+    ) -> Result<Option<String>, Error> {
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("You do not have the initial commit yet".to_string()))?;
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let result = Self::save_locked(repo, &head_oid, message, include_untracked, keep_index);
+        if !matches!(result, Ok(Some(_))) {
+            repo.index.rollback()?;
+        }
+        result
+    }
+
+    fn save_locked(
+        repo: &mut Repository,
+        head_oid: &str,
+        message: Option<&str>,
+        include_untracked: bool,
+        keep_index: bool,
+    ) -> Result<Option<String>, Error> {
+        if repo.index.has_conflict() {
+            return Err(Error::Generic("Cannot stash while there are unmerged paths".to_string()));
+        }
+
+        let head_files = head_files(repo, head_oid)?;
+        let index_entries: Vec<DatabaseEntry> = repo.index.each_entry()
+            .filter(|entry| !entry.intent_to_add)
+            .map(|entry| DatabaseEntry::new(entry.get_path().to_string(), entry.get_oid().to_string(), &entry.mode_octal()))
+            .collect();
+
+        let mut dirty = index_entries.len() != head_files.len()
+            || index_entries.iter().any(|entry| head_files.get(&entry.name).map(|e| e.get_oid()) != Some(entry.get_oid()));
+
+        // Worktree versions of tracked files
+        let mut worktree_entries = Vec::new();
+        for entry in &index_entries {
+            let file = Path::new(&entry.name);
+            if !repo.workspace.path_exists(file)? {
+                dirty = true;
+                continue;
+            }
+
+            let data = repo.workspace.read_file(file)?;
+            let oid = repo.database.hash_file_data(&data);
+            if oid != entry.get_oid() {
+                dirty = true;
+                repo.database.store(&mut Blob::new(data))?;
+            }
+            worktree_entries.push(DatabaseEntry::new(entry.name.clone(), oid, entry.get_mode()));
+        }
+
+        let mut untracked_entries = Vec::new();
+        if include_untracked {
+            for file in repo.workspace.list_files()? {
+                let path = file.to_string_lossy().replace('\\', "/");
+                if repo.index.tracked(&path) {
+                    continue;
+                }
+                let data = repo.workspace.read_file(&file)?;
+                let mode = FileMode::from_metadata(&repo.workspace.stat_file(&file)?);
+                let mut blob = Blob::new(data);
+                repo.database.store(&mut blob)?;
+                let oid = blob.get_oid().cloned()
+                    .ok_or_else(|| Error::Generic("Blob OID not set after storage".into()))?;
+                untracked_entries.push(DatabaseEntry::new(path, oid, &mode.to_octal_string()));
+            }
+        }
+
+        if !dirty && untracked_entries.is_empty() {
+            return Ok(None);
+        }
+
+        let on = Self::describe_head(repo, head_oid)?;
+        let author = current_author();
+
+        let index_tree = Self::store_tree(repo, &index_entries)?;
+        let index_oid = Self::store_commit(repo, Commit::new(
+            Some(head_oid.to_string()), index_tree, author.clone(), format!("index on {}", on),
+        ))?;
+
+        let mut parents = vec![index_oid];
+        if !untracked_entries.is_empty() {
+            let untracked_tree = Self::store_tree(repo, &untracked_entries)?;
+            parents.push(Self::store_commit(repo, Commit::new(
+                None, untracked_tree, author.clone(), format!("untracked files on {}", on),
+            ))?);
+        }
+
+        let title = match message {
+            Some(message) => format!("On {}: {}", on.split(':').next().unwrap_or_default(), message),
+            None => format!("WIP on {}", on),
+        };
+        let trailers: Vec<String> = parents.iter()
+            .map(|oid| format!("{} {}", MERGE_PARENT_TRAILER, oid))
+            .collect();
+        let worktree_tree = Self::store_tree(repo, &worktree_entries)?;
+        let stash_oid = Self::store_commit(repo, Commit::new(
+            Some(head_oid.to_string()), worktree_tree, author, format!("{}\n\n{}", title, trailers.join("\n")),
+        ))?;
+
+        // Clean the worktree back to HEAD, or to the index with --keep-index
+        let paths: BTreeSet<String> = head_files.keys().cloned()
+            .chain(index_entries.iter().map(|entry| entry.name.clone()))
+            .collect();
+        for path in &paths {
+            let file = Path::new(path);
+            let target = if keep_index {
+                index_entries.iter().find(|entry| &entry.name == path).map(|entry| entry.get_oid().to_string())
+            } else {
+                head_files.get(path).map(|entry| entry.get_oid().to_string())
+            };
+
+            match target {
+                Some(oid) => {
+                    let content = repo.database.load(&oid)?.to_bytes();
+                    if let Some(parent) = file.parent() {
+                        if !parent.as_os_str().is_empty() {
+                            repo.workspace.make_directory(parent)?;
+                        }
+                    }
+                    repo.workspace.write_file(file, &content)?;
+                    let stat = repo.workspace.stat_file(file)?;
+                    repo.index.add(file, &oid, &stat)?;
+                },
+                None => {
+                    if repo.workspace.path_exists(file)? {
+                        repo.workspace.remove_file(file)?;
+                    }
+                    repo.index.remove(file)?;
+                }
+            }
+        }
+
+        for entry in &untracked_entries {
+            let file = Path::new(&entry.name);
+            repo.workspace.remove_file(file)?;
+            if let Some(parent) = file.parent() {
+                if !parent.as_os_str().is_empty() {
+                    repo.workspace.remove_directory(parent)?;
+                }
+            }
+        }
+
+        repo.index.write_updates()?;
+        repo.refs.update_ref(STASH_REF, &stash_oid, &title)?;
+
+        println!("Saved working directory and index state {}", title);
+        Ok(Some(stash_oid))
+    }
+
+    /// The entries, newest first: `stash@{n}` is the nth
+    pub fn list(repo: &Repository) -> Result<Vec<ReflogEntry>, Error> {
+        let mut entries = Reflog::new(git_dir::resolve(&repo.path)).read(STASH_REF)?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// The position and commit of the entry `name` (`stash@{<n>}` or just
+    /// `<n>`) names, or of the newest entry
+    pub fn resolve(repo: &Repository, name: Option<&str>) -> Result<(usize, String), Error> {
+        let entries = Self::list(repo)?;
+        if entries.is_empty() {
+            return Err(Error::Generic("No stash entries found.".to_string()));
+        }
+
+        let n = match name {
+            None => 0,
+            Some(name) => {
+                let index = name.strip_prefix("stash@{")
+                    .and_then(|rest| rest.strip_suffix('}'))
+                    .unwrap_or(name);
+                index.parse::<usize>()
+                    .map_err(|_| Error::Generic(format!("'{}' is not a stash-like commit", name)))?
+            },
+        };
+        let entry = entries.get(n)
+            .ok_or_else(|| Error::Generic(format!("stash@{{{}}} is not a valid reference", n)))?;
+        Ok((n, entry.new.clone()))
+    }
+
+    /// Reapply an entry onto HEAD and drop it. On conflicts the entry is
+    /// kept and the conflicts are left in the index and worktree.
+    pub fn pop(repo: &mut Repository, name: Option<&str>) -> Result<(), Error> {
+        let (n, stash_oid) = Self::resolve(repo, name)?;
+        if let Err(e) = Self::apply_commit(repo, &stash_oid) {
+            println!("The stash entry is kept in case you need it again.");
+            return Err(e);
+        }
+        Self::drop_entry(repo, n, &stash_oid)
+    }
+
+    /// Reapply an entry onto HEAD, keeping it
+    pub fn apply(repo: &mut Repository, name: Option<&str>) -> Result<(), Error> {
+        let (_, stash_oid) = Self::resolve(repo, name)?;
+        Self::apply_commit(repo, &stash_oid)
+    }
+
+    /// Forget an entry; the ones older than it move up by one
+    pub fn drop(repo: &mut Repository, name: Option<&str>) -> Result<(), Error> {
+        let (n, stash_oid) = Self::resolve(repo, name)?;
+        Self::drop_entry(repo, n, &stash_oid)
+    }
+
+    fn drop_entry(repo: &mut Repository, n: usize, stash_oid: &str) -> Result<(), Error> {
+        repo.refs.drop_reflog_entry(STASH_REF, n)?;
+        println!("Dropped stash@{{{}}} ({})", n, stash_oid);
+        Ok(())
+    }
+
+    // Merge the changes of a stash commit into the index and worktree.
+    // Changes come back unstaged, untracked files as they were.
+    fn apply_commit(repo: &mut Repository, stash_oid: &str) -> Result<(), Error> {
+        let stash = match repo.database.load(stash_oid)?.as_any().downcast_ref::<Commit>() {
+            Some(commit) => commit.clone(),
+            None => return Err(Error::Generic(format!("{} is not a stash commit", stash_oid))),
+        };
+        let parents = stash.get_parents();
+        let base_oid = parents.first().cloned()
+            .ok_or_else(|| Error::Generic(format!("{} is not a stash commit", stash_oid)))?;
+        let head_oid = repo.refs.read_head()?
+            .ok_or_else(|| Error::Generic("Cannot apply a stash without a HEAD commit".to_string()))?;
+
+        // Untracked files come back as they were, but never over existing files
+        let untracked = match parents.get(2) {
+            Some(oid) => head_files(repo, oid)?,
+            None => HashMap::new(),
+        };
+        for path in untracked.keys() {
+            if repo.workspace.path_exists(Path::new(path))? {
+                eprintln!("error: {} already exists, no checkout", path);
+                return Err(Error::Generic("could not restore untracked files from stash".to_string()));
+            }
+        }
+
+        Self::check_local_changes(repo, &base_oid, stash_oid, &head_oid)?;
+
+        if !repo.index.load_for_update()? {
+            return Err(Error::Lock("Unable to acquire lock on index".to_string()));
+        }
+
+        let inputs = CherryPick::new(
+            "Updated upstream".to_string(),
+            "Stashed changes".to_string(),
+            head_oid.clone(),
+            stash_oid.to_string(),
+            vec![base_oid],
+        );
+        let result = Resolve::new(&mut repo.database, &repo.workspace, &mut repo.index, &inputs).execute();
+
+        for (path, entry) in &untracked {
+            let file = Path::new(path);
+            if let Some(parent) = file.parent() {
+                if !parent.as_os_str().is_empty() {
+                    repo.workspace.make_directory(parent)?;
+                }
+            }
+            let content = repo.database.load(entry.get_oid())?.to_bytes();
+            repo.workspace.write_file(file, &content)?;
+        }
+
+        match result {
+            Ok(()) => {
+                // Changes come back unstaged, except for newly added files
+                let head_files = head_files(repo, &head_oid)?;
+                for (path, entry) in &head_files {
+                    let unchanged = repo.index.get_entry(path).map(|e| e.get_oid() == entry.get_oid());
+                    if unchanged == Some(false) {
+                        let size = repo.database.load(entry.get_oid())?.to_bytes().len();
+                        let stat = repo.workspace.stat_file(Path::new(path))?;
+                        repo.index.add_partial(Path::new(path), entry.get_oid(), size, &stat)?;
+                    }
+                }
+                repo.index.write_updates()?;
+                trace!("Applied {}", stash_oid);
+                Ok(())
+            },
+            Err(e) => {
+                repo.index.write_updates()?;
+                Err(e)
+            }
+        }
+    }
+
+    // Refuse to apply over local changes to a path the stash changes: a
+    // file that differs from the index, or whose index entry differs from
+    // HEAD, would be overwritten by the merge without being saved anywhere
+    fn check_local_changes(repo: &mut Repository, base_oid: &str, stash_oid: &str, head_oid: &str) -> Result<(), Error> {
+        let touched = repo.database.tree_diff(Some(base_oid), Some(stash_oid), &PathFilter::new())?;
+        let head = head_files(repo, head_oid)?;
+        repo.index.load()?;
+        let inspector = Inspector::new(&repo.workspace, &repo.index, &repo.database);
+
+        let mut changed = BTreeSet::new();
+        for path in touched.keys() {
+            let name = path.to_string_lossy().to_string();
+            let entry = repo.index.get_entry(&name);
+            let stat = repo.workspace.stat_file(path).ok();
+            let staged = entry.map(|entry| entry.get_oid()) != head.get(&name).map(|entry| entry.get_oid());
+            let unstaged = match (entry, &stat) {
+                (None, None) => false,
+                _ => inspector.compare_index_to_workspace(entry, stat.as_ref())?.is_some(),
+            };
+            if staged || unstaged {
+                changed.insert(name);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        eprintln!("error: Your local changes to the following files would be overwritten by merge:");
+        for path in &changed {
+            eprintln!("\t{}", path);
+        }
+        eprintln!("Please commit your changes or stash them before you merge.");
+        Err(Error::Generic("Your local changes would be overwritten".to_string()))
+    }
+
+    // "<branch>: <short oid> <title>" describing the commit changes were stashed on
+    fn describe_head(repo: &mut Repository, head_oid: &str) -> Result<String, Error> {
+        let branch = match repo.refs.current_ref()? {
+            Reference::Symbolic(path) if path != "HEAD" => repo.refs.short_name(&path),
+            _ => "(no branch)".to_string(),
+        };
+        let title = match repo.database.load(head_oid)?.as_any().downcast_ref::<Commit>() {
+            Some(commit) => commit.title_line(),
+            None => String::new(),
+        };
+        Ok(format!("{}: {} {}", branch, repo.database.short_oid(head_oid), title))
+    }
+
+    fn store_tree(repo: &mut Repository, entries: &[DatabaseEntry]) -> Result<String, Error> {
+        let mut tree = Tree::build(entries.iter())?;
+        tree.traverse(|t| {
+            repo.database.store(t)?;
+            Ok(())
+        })?;
+        tree.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Tree OID not set after storage".into()))
+    }
+
+    fn store_commit(repo: &mut Repository, mut commit: Commit) -> Result<String, Error> {
+        repo.database.store(&mut commit)?;
+        commit.get_oid().cloned()
+            .ok_or_else(|| Error::Generic("Commit OID not set after storage".into()))
+    }
+}
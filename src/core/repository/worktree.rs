@@ -0,0 +1,182 @@
+// src/core/repository/worktree.rs - the linked worktrees of a repository
+//
+// Each working tree linked to the repository besides the main one has an
+// administrative directory `worktrees/<id>` in the metadata directory,
+// laid out as git lays it out: `gitdir` holds the path of the `.ash`
+// pointer file in the working tree, `commondir` the way back to the
+// metadata directory shared with the main tree, `HEAD` and `index` its
+// own, and `locked`, if there, keeps the tree from being pruned and gives
+// the reason why.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::core::refs::{Reference, Refs};
+use crate::core::repository::git_dir::{self, COMMON_DIR_FILE, GIT_DIR_NAME};
+use crate::errors::error::Error;
+
+pub const WORKTREES_DIR: &str = "worktrees";
+const GITDIR_FILE: &str = "gitdir";
+const LOCKED_FILE: &str = "locked";
+
+/// A working tree linked to the repository
+#[derive(Debug, Clone)]
+pub struct Worktree {
+    /// The name of its administrative directory
+    pub id: String,
+    pub admin_path: PathBuf,
+    /// Where the working tree is, as recorded; None if that is unreadable
+    pub path: Option<PathBuf>,
+}
+
+impl Worktree {
+    /// The worktrees linked to the repository at `git_path`, by id
+    pub fn list(git_path: &Path) -> Result<Vec<Worktree>, Error> {
+        let mut worktrees = Vec::new();
+        for entry in fs::read_dir(git_dir::common(git_path).join(WORKTREES_DIR)).into_iter().flatten().flatten() {
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let admin_path = entry.path();
+            let path = fs::read_to_string(admin_path.join(GITDIR_FILE)).ok()
+                .map(|gitdir| PathBuf::from(gitdir.trim()))
+                .and_then(|gitdir| gitdir.parent().map(Path::to_path_buf));
+            worktrees.push(Worktree { id: entry.file_name().to_string_lossy().to_string(), admin_path, path });
+        }
+        worktrees.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(worktrees)
+    }
+
+    /// Set up the administrative directory of a new worktree at `path`, an
+    /// absolute path, with `head` as its HEAD: `ref: refs/heads/<branch>`
+    /// or a commit. Its id is the last component of the path, numbered if
+    /// another worktree has that id already.
+    pub fn create(git_path: &Path, path: &Path, head: &str) -> Result<Worktree, Error> {
+        let worktrees_path = git_dir::common(git_path).join(WORKTREES_DIR);
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| Error::Generic(format!("invalid path '{}'", path.display())))?;
+        let id = (0..).map(|n| if n == 0 { name.clone() } else { format!("{}{}", name, n) })
+            .find(|id| !worktrees_path.join(id).exists())
+            .unwrap_or(name);
+
+        let admin_path = worktrees_path.join(&id);
+        fs::create_dir_all(&admin_path)?;
+        fs::write(admin_path.join(GITDIR_FILE), format!("{}\n", path.join(GIT_DIR_NAME).display()))?;
+        fs::write(admin_path.join(COMMON_DIR_FILE), "../..\n")?;
+        fs::write(admin_path.join("HEAD"), format!("{}\n", head))?;
+        Ok(Worktree { id, admin_path, path: Some(path.to_path_buf()) })
+    }
+
+    /// Where the branch `name` (`refs/heads/...`) is checked out, in the
+    /// main working tree of the repository at `git_path` or a linked one
+    pub fn checked_out(git_path: &Path, name: &str) -> Result<Option<String>, Error> {
+        let common = git_dir::common(git_path);
+        if Refs::new(&common).current_ref()? == Reference::Symbolic(name.to_string()) {
+            let main = if common.ends_with(GIT_DIR_NAME) { common.parent().unwrap_or(&common) } else { &common };
+            return Ok(Some(main.display().to_string()));
+        }
+        for worktree in Self::list(git_path)? {
+            if worktree.head(git_path)?.1.as_deref() == Some(name) {
+                return Ok(Some(worktree.display()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The linked worktree `name` stands for: its id, or the path of its
+    /// working tree
+    pub fn find(git_path: &Path, name: &str) -> Result<Worktree, Error> {
+        let wanted = fs::canonicalize(name).ok();
+        Self::list(git_path)?.into_iter()
+            .find(|worktree| {
+                worktree.id == name || worktree.path.as_ref().is_some_and(|path| {
+                    path == Path::new(name) || wanted.is_some() && fs::canonicalize(path).ok() == wanted
+                })
+            })
+            .ok_or_else(|| Error::Generic(format!("'{}' is not a working tree", name)))
+    }
+
+    /// Why the worktree is locked, if it is; the reason may be empty
+    pub fn lock_reason(&self) -> Option<String> {
+        fs::read_to_string(self.admin_path.join(LOCKED_FILE)).ok()
+            .map(|reason| reason.trim_end().to_string())
+    }
+
+    /// Keep the worktree from being pruned, for example while it is on
+    /// removable media that is not mounted
+    pub fn lock(&self, reason: Option<&str>) -> Result<(), Error> {
+        if let Some(existing) = self.lock_reason() {
+            return if existing.is_empty() {
+                Err(Error::Generic(format!("'{}' is already locked", self.display())))
+            } else {
+                Err(Error::Generic(format!("'{}' is already locked, reason: {}", self.display(), existing)))
+            };
+        }
+        fs::write(self.admin_path.join(LOCKED_FILE), reason.unwrap_or_default())?;
+        Ok(())
+    }
+
+    pub fn unlock(&self) -> Result<(), Error> {
+        if self.lock_reason().is_none() {
+            return Err(Error::Generic(format!("'{}' is not locked", self.display())));
+        }
+        fs::remove_file(self.admin_path.join(LOCKED_FILE))?;
+        Ok(())
+    }
+
+    /// Why the worktree can be pruned, if it can: its administrative files
+    /// are broken or its working tree is gone. Locked worktrees never can.
+    pub fn prunable_reason(&self) -> Option<String> {
+        if self.lock_reason().is_some() {
+            return None;
+        }
+        if !self.admin_path.join(GITDIR_FILE).is_file() {
+            return Some("gitdir file does not exist".to_string());
+        }
+        match &self.path {
+            None => Some("invalid gitdir file".to_string()),
+            Some(path) if !path.exists() => Some("gitdir file points to non-existent location".to_string()),
+            Some(_) => None,
+        }
+    }
+
+    /// When the worktree was last known to be in use: when its gitdir file
+    /// was written, or its administrative directory if that file is gone
+    pub fn last_used(&self) -> Result<SystemTime, Error> {
+        let metadata = fs::metadata(self.admin_path.join(GITDIR_FILE))
+            .or_else(|_| fs::metadata(&self.admin_path))?;
+        Ok(metadata.modified()?)
+    }
+
+    /// Delete the working tree and then its administrative files
+    pub fn remove(&self) -> Result<(), Error> {
+        if let Some(path) = self.path.as_ref().filter(|path| path.exists()) {
+            fs::remove_dir_all(path)?;
+        }
+        self.prune()
+    }
+
+    /// Delete the administrative files of the worktree
+    pub fn prune(&self) -> Result<(), Error> {
+        fs::remove_dir_all(&self.admin_path)?;
+        Ok(())
+    }
+
+    /// The commit checked out in the worktree and the branch, if any, read
+    /// from its own HEAD against the refs of the repository at `git_path`
+    pub fn head(&self, git_path: &Path) -> Result<(Option<String>, Option<String>), Error> {
+        let refs = Refs::new(git_path);
+        match Refs::new(&self.admin_path).current_ref()? {
+            Reference::Symbolic(branch) => Ok((refs.read_ref(&branch)?, Some(branch))),
+            Reference::Direct(oid) if oid.is_empty() => Ok((None, None)),
+            Reference::Direct(oid) => Ok((Some(oid), None)),
+        }
+    }
+
+    /// The path of the working tree, or the administrative directory when
+    /// that is not known
+    pub fn display(&self) -> String {
+        self.path.as_ref().unwrap_or(&self.admin_path).display().to_string()
+    }
+}
@@ -4,6 +4,8 @@ use regex::Regex;
 use crate::errors::error::Error;
 use crate::core::repository::repository::Repository;
 use crate::core::database::commit::Commit;
+use crate::core::repository::git_dir;
+use crate::core::reflog::{Reflog, ZERO_OID};
 
 // Constants for revision types
 pub const HEAD: &str = "HEAD";
@@ -17,6 +19,10 @@ enum RevisionNode {
     Ancestor(Box<RevisionNode>, usize),
     Range(Box<RevisionNode>, Box<RevisionNode>),
     Exclude(Box<RevisionNode>),
+    // <ref>@{n}: where the ref was n moves ago
+    Reflog(String, usize),
+    // :<n>:<path>: the blob the index has for a path at stage n
+    Index(u8, String),
 }
 
 // Structure to hold errors with hints
@@ -54,6 +60,8 @@ impl<'a> Revision<'a> {
             static ref ANCESTOR_PATTERN: Regex = Regex::new(r"^(.+)~(\d+)$").unwrap();
             static ref RANGE_PATTERN: Regex = Regex::new(r"^(.*)\.\.(.*)$").unwrap();
             static ref EXCLUDE_PATTERN: Regex = Regex::new(r"^\^(.+)$").unwrap();
+            static ref REFLOG_PATTERN: Regex = Regex::new(r"^(.*)@\{(\d+)\}$").unwrap();
+            static ref INDEX_PATTERN: Regex = Regex::new(r"^:(?:([0-3]):)?(.+)$").unwrap();
             static ref INVALID_NAME: Regex = Regex::new(r"(?x)
                 ^\.|
                 /\.|
@@ -72,6 +80,14 @@ impl<'a> Revision<'a> {
             };
         }
         
+        // Check for index notation (:path, :n:path) before anything else,
+        // as paths may have any of the operators in them
+        if let Some(captures) = INDEX_PATTERN.captures(revision) {
+            let stage = captures.get(1).map_or(Ok(0), |n| n.as_str().parse::<u8>()).ok()?;
+            let path = captures.get(2).unwrap().as_str().trim_start_matches("./");
+            return Some(RevisionNode::Index(stage, path.to_string()));
+        }
+
         // Check for range notation (A..B)
         if let Some(captures) = RANGE_PATTERN.captures(revision) {
             let start = captures.get(1).unwrap().as_str();
@@ -114,6 +130,17 @@ impl<'a> Revision<'a> {
             return Self::parse(rev).map(|node| RevisionNode::Ancestor(Box::new(node), n));
         }
         
+        // Check for reflog notation (rev@{n}); a bare @{n} is HEAD's
+        if let Some(captures) = REFLOG_PATTERN.captures(revision) {
+            let name = captures.get(1).unwrap().as_str();
+            let n = captures.get(2).unwrap().as_str().parse::<usize>().ok()?;
+            let name = if name.is_empty() || name == "@" { HEAD } else { name };
+            if INVALID_NAME.is_match(name) {
+                return None;
+            }
+            return Some(RevisionNode::Reflog(name.to_string(), n));
+        }
+        
         // Check if it's a valid reference name
         if !INVALID_NAME.is_match(revision) {
             let name = REF_ALIASES.get(revision).unwrap_or(&revision);
@@ -135,7 +162,13 @@ impl<'a> Revision<'a> {
             let node_clone = node.clone();
             
             // Resolve the AST to an object ID
-            match self.resolve_node(&node_clone) {
+            // Annotated tags stand for the object they name, unless a
+            // tag is what was asked for
+            let resolved = self.resolve_node(&node_clone).and_then(|oid| match expected_type {
+                "tag" => Ok(oid),
+                _ => self.repo.database.peel(&oid),
+            });
+            match resolved {
                 Ok(oid) => {
                     // Verify the object type if specified
                     if self.verify_object_type(&oid, expected_type)? {
@@ -144,6 +177,8 @@ impl<'a> Revision<'a> {
                         Err(Error::Generic(format!("Not a valid object name: '{}'", self.expr)))
                     }
                 },
+                // What is wrong with an index path is worth saying
+                Err(e) if matches!(node_clone, RevisionNode::Index(..)) => Err(e),
                 Err(_) => Err(Error::Generic(format!("Not a valid object name: '{}'", self.expr))),
             }
         } else {
@@ -181,42 +216,85 @@ impl<'a> Revision<'a> {
                 // This is handled by the RevList structure
                 self.resolve_node(rev)
             },
+            RevisionNode::Reflog(name, n) => self.read_reflog(name, *n),
+            RevisionNode::Index(stage, path) => self.read_index(path, *stage),
+        }
+    }
+
+    // The blob the index has for `path` at `stage`
+    fn read_index(&mut self, path: &str, stage: u8) -> Result<String, Error> {
+        self.repo.index.load()?;
+        if let Some(entry) = self.repo.index.get_stage_entry(path, stage) {
+            return Ok(entry.get_oid().to_string());
+        }
+        if (0..=3).any(|other| self.repo.index.get_stage_entry(path, other).is_some()) {
+            return Err(Error::Generic(format!("path '{}' is in the index, but not at stage {}", path, stage)));
+        }
+        if self.repo.workspace.path_exists(std::path::Path::new(path))? {
+            return Err(Error::Generic(format!("path '{}' exists on disk, but not in the index", path)));
+        }
+        Err(Error::Generic(format!("path '{}' does not exist (neither on disk nor in the index)", path)))
+    }
+
+    // The value `name` had `n` updates ago, from its reflog
+    fn read_reflog(&mut self, name: &str, n: usize) -> Result<String, Error> {
+        let reflog = Reflog::new(git_dir::resolve(&self.repo.path));
+        let full_name = reflog.resolve_name(name)
+            .ok_or_else(|| Error::Generic(format!("no reflog for '{}'", name)))?;
+        let entries = reflog.read(&full_name)?;
+        if let Some(entry) = entries.iter().rev().nth(n) {
+            return Ok(entry.new.clone());
+        }
+        // One past the oldest entry is where that entry moved the ref from
+        match entries.first() {
+            Some(oldest) if n == entries.len() && oldest.old != ZERO_OID => Ok(oldest.old.clone()),
+            _ => Err(Error::Generic(format!("log for '{}' only has {} entries", name, entries.len()))),
         }
     }
     
     // Get a reference value or try to match an abbreviated object ID
     fn read_ref(&mut self, name: &str) -> Result<String, Error> {
-        // First try to read as a reference
-        // In a real implementation, this would use a refs object to look up references
-        // For now, we'll just handle HEAD specially
+        // HEAD goes through the refs, as the branch it names may be packed
         if name == HEAD {
-            // Return HEAD reference (would normally be implemented via refs system)
-            // For our example, we'll try to load HEAD from an expected location
-            let head_file = std::path::Path::new(".ash/HEAD");
-            if head_file.exists() {
-                if let Ok(content) = std::fs::read_to_string(head_file) {
-                    let content = content.trim();
+            if let Some(oid) = self.repo.refs.read_head()? {
+                return Ok(oid);
+            }
+        }
+        
+        // Try as a full ref name or a special ref like FETCH_HEAD, then as a
+        // branch, tag or remote-tracking branch, and last as a remote, whose
+        // HEAD names its default branch
+        let git_path = git_dir::resolve(std::path::Path::new("."));
+        let common = git_dir::common(&git_path);
+        let mut ref_paths = Vec::new();
+        if git_dir::is_shared_ref(name) {
+            ref_paths.push(common.join(name));
+        } else if name.starts_with("refs/") || (name.ends_with(HEAD) && name.chars().all(|c| c.is_ascii_uppercase() || c == '_')) {
+            ref_paths.push(git_path.join(name));
+        }
+        for prefix in ["refs/heads", "refs/tags", "refs/remotes"] {
+            ref_paths.push(common.join(prefix).join(name));
+        }
+        ref_paths.push(common.join("refs/remotes").join(name).join(HEAD));
+        for ref_file in &ref_paths {
+            if ref_file.is_file() {
+                if let Ok(content) = std::fs::read_to_string(ref_file) {
+                    // Symbolic refs are followed through the refs below
                     if content.starts_with("ref: ") {
-                        let ref_path = content.strip_prefix("ref: ").unwrap();
-                        let ref_file = std::path::Path::new(".ash").join(ref_path);
-                        if ref_file.exists() {
-                            if let Ok(oid) = std::fs::read_to_string(ref_file) {
-                                return Ok(oid.trim().to_string());
-                            }
-                        }
-                    } else {
-                        return Ok(content.to_string());
+                        continue;
                     }
+                    // FETCH_HEAD lists more after the object ID
+                    return Ok(content.split_whitespace().next().unwrap_or_default().to_string());
                 }
             }
         }
-        
-        // Try as a branch reference
-        let ref_path = format!(".ash/refs/heads/{}", name);
-        let ref_file = std::path::Path::new(&ref_path);
-        if ref_file.exists() {
-            if let Ok(oid) = std::fs::read_to_string(ref_file) {
-                return Ok(oid.trim().to_string());
+        // Refs without a file of their own are in packed-refs, and symbolic
+        // refs point at another
+        for ref_file in &ref_paths {
+            if let Ok(full_name) = ref_file.strip_prefix(&git_path) {
+                if let Some(oid) = self.repo.refs.read_ref(&full_name.to_string_lossy())? {
+                    return Ok(oid);
+                }
             }
         }
         
@@ -236,7 +314,8 @@ impl<'a> Revision<'a> {
     
     // Get the parent of a commit
     fn commit_parent(&mut self, oid: &str) -> Result<String, Error> {
-        // Ensure it's a commit
+        // Ensure it's a commit, looking through tags
+        let oid = &self.repo.database.peel(oid)?;
         let commit = self.load_typed_object(oid, COMMIT)?;
         
         // Get its parent
@@ -1,15 +1,21 @@
 // src/core/revlist.rs with all clone_box fixes
 
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::PathBuf;
+use regex::Regex;
 use crate::core::database::database::{Database, GitObject};
+use crate::core::database::author::Author;
 use crate::core::database::commit::Commit;
 use crate::core::revision::{HEAD, COMMIT};
 use crate::core::path_filter::PathFilter;
+use crate::core::diff::rename::{self, RenameDetection};
 use crate::errors::error::Error;
 use crate::core::refs::Refs;
 use std::iter::Iterator;
 use std::path::Path;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 /// RevList handles traversing commit history and filtering commits
 /// based on various criteria (date, path, etc.)
@@ -26,12 +32,82 @@ pub struct RevList<'a> {
     
     // Path filtering
     path_filter: PathFilter,
+    // The paths the filter was built from
+    paths: Vec<PathBuf>,
     
     // Limitation flags
     limited: bool,
     
     // Diffs cache
     diffs: HashMap<(Option<String>, String), HashMap<PathBuf, (Option<String>, Option<String>)>>,
+
+    // The commits given as excluded (^A, or A in A..B), where ancestry
+    // paths start
+    bottoms: Vec<String>,
+}
+
+/// Traversal controls for `RevList::commits`
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Oldest first rather than newest first
+    pub reverse: bool,
+    /// Also give the excluded commits right below the range, marked
+    pub boundary: bool,
+    /// Only commits that descend from an excluded commit and lead to an
+    /// included one
+    pub ancestry_path: bool,
+    /// At most this many commits, after skipping `skip` of them
+    pub max_count: Option<usize>,
+    pub skip: usize,
+    /// Only commits made at or after / at or before these times, in
+    /// seconds since the epoch
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    /// Only commits whose author (committer) matches one of these, as
+    /// `Name <email>`
+    pub authors: Vec<Regex>,
+    pub committers: Vec<Regex>,
+    /// Only commits whose message matches one of these, or all of them
+    /// with `all_match`
+    pub grep: Vec<Regex>,
+    pub all_match: bool,
+    /// Keep to the one filtered path across renames: from the commit that
+    /// renamed it on, older commits are filtered by its old name
+    pub follow: bool,
+    /// No commit before all of its children, each line of history shown
+    /// through before the next rather than interleaved by date
+    pub topo_order: bool,
+}
+
+impl WalkOptions {
+    // Whether `commit` passes the date, author, committer and message limits
+    fn accepts(&self, commit: &Commit) -> bool {
+        let person = |author: Option<&Author>| author.map(|a| format!("{} <{}>", a.name, a.email)).unwrap_or_default();
+        let date = commit.get_committer().or(commit.get_author()).map(|a| a.timestamp.timestamp()).unwrap_or(0);
+        if self.since.is_some_and(|since| date < since) || self.until.is_some_and(|until| date > until) {
+            return false;
+        }
+        if !self.authors.is_empty() && !self.authors.iter().any(|re| re.is_match(&person(commit.get_author()))) {
+            return false;
+        }
+        if !self.committers.is_empty() && !self.committers.iter().any(|re| re.is_match(&person(commit.get_committer()))) {
+            return false;
+        }
+        let message = commit.get_message();
+        if self.all_match {
+            self.grep.iter().all(|re| re.is_match(message))
+        } else {
+            self.grep.is_empty() || self.grep.iter().any(|re| re.is_match(message))
+        }
+    }
+}
+
+/// A commit given by `RevList::commits`
+#[derive(Debug, Clone)]
+pub struct WalkedCommit {
+    pub commit: Commit,
+    /// Excluded from the range, but the parent of a commit in it
+    pub boundary: bool,
 }
 
 /// Flags that can be associated with commits during traversal
@@ -44,7 +120,9 @@ enum Flag {
 }
 
 impl<'a> RevList<'a> {
-    /// Create a new RevList with the given revisions
+    /// Create a new RevList with the given revisions. Arguments naming
+    /// files in the workspace, and all of those after `--`, are paths that
+    /// limit the commits to those changing them.
     pub fn new(database: &'a mut Database, refs: &Refs, revisions: &[String], walk: bool) -> Result<Self, Error> {
         let mut revlist = RevList {
             database,
@@ -53,18 +131,25 @@ impl<'a> RevList<'a> {
             queue: VecDeque::new(),
             output: Vec::new(),
             path_filter: PathFilter::new(),
+            paths: Vec::new(),
             limited: false,
             diffs: HashMap::new(),
+            bottoms: Vec::new(),
         };
         
         let mut has_revisions = false;
         let mut path_filters = Vec::new();
+        let mut only_paths = false;
         
         // Process all revisions
         for rev in revisions {
+            if rev == "--" && !only_paths {
+                only_paths = true;
+                continue;
+            }
             // Check if this is a path that exists in the workspace
             let path = PathBuf::from(rev);
-            if path.exists() {
+            if only_paths || path.exists() {
                 path_filters.push(path);
                 continue;
             }
@@ -77,6 +162,7 @@ impl<'a> RevList<'a> {
         // Initialize path filter if any paths were specified
         if !path_filters.is_empty() {
             revlist.path_filter = PathFilter::build(&path_filters);
+            revlist.paths = path_filters.clone();
         }
         
         // If no revisions were given, use HEAD
@@ -133,7 +219,7 @@ impl<'a> RevList<'a> {
         // Resolve the revision to a commit OID
         let oid = if rev == HEAD {
             // For HEAD, resolve through refs
-            let refs = Refs::new(Path::new(".").join(".ash"));
+            let refs = Refs::new(git_dir::resolve(Path::new(".")));
             refs.read_head()?.ok_or_else(|| Error::Generic("Could not resolve HEAD".to_string()))?
         } else if rev.len() >= 4 && rev.chars().all(|c| c.is_ascii_hexdigit()) {
             // It's likely a commit hash (full or abbreviated)
@@ -141,11 +227,12 @@ impl<'a> RevList<'a> {
             self.database.resolve_oid(rev)?
         } else {
             // Try to resolve as a branch or tag reference
-            let refs = Refs::new(Path::new(".").join(".ash"));
+            let refs = Refs::new(git_dir::resolve(Path::new(".")));
             refs.read_ref(rev)?.ok_or_else(|| Error::Generic(format!("Reference not found: {}", rev)))?
         };
         
-        // Load the commit
+        // Load the commit, which an annotated tag points at
+        let oid = self.database.peel(&oid)?;
         let commit = self.load_commit(&oid)?;
         
         // Add to the queue
@@ -153,6 +240,7 @@ impl<'a> RevList<'a> {
         
         // If not interesting, mark as uninteresting and propagate to parents
         if !interesting {
+            self.bottoms.push(oid.clone());
             self.limited = true;
             self.mark(&oid, Flag::Uninteresting);
             self.mark_parents_uninteresting(&oid)?;
@@ -368,6 +456,200 @@ impl<'a> RevList<'a> {
         Ok(commit)
     }
     
+    /// Every commit in the range, following all parents of merges, newest
+    /// first by date unless `options` say otherwise. Commits that do not
+    /// change the filtered paths are left out, merges when they match any
+    /// of their parents. Meant for a RevList made without `walk`.
+    pub fn commits(&mut self, options: &WalkOptions) -> Result<Vec<WalkedCommit>, Error> {
+        // Everything the excluded commits reach is out of the range
+        let mut excluded: HashSet<String> = HashSet::new();
+        let mut pending: Vec<String> = self.bottoms.clone();
+        while let Some(oid) = pending.pop() {
+            if excluded.insert(oid.clone()) {
+                pending.extend(self.parents_of(&oid)?);
+            }
+        }
+
+        let mut included: Vec<(String, i64)> = Vec::new();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut pending: Vec<String> = self.queue.iter()
+            .map(|commit| self.get_oid(commit))
+            .filter(|oid| !excluded.contains(oid))
+            .collect();
+        self.queue.clear();
+        while let Some(oid) = pending.pop() {
+            if excluded.contains(&oid) || !seen.insert(oid.clone()) {
+                continue;
+            }
+            let commit = self.load_commit(&oid)?;
+            included.push((oid.clone(), self.get_commit_date(&commit)?));
+            pending.extend(self.parents_of(&oid)?);
+        }
+        // Newest first; a stable sort keeps the order commits were found in
+        // for those made in the same second
+        included.sort_by_key(|(_, date)| Reverse(*date));
+
+        if options.ancestry_path && !self.bottoms.is_empty() {
+            let mut on_path: HashSet<String> = self.bottoms.iter().cloned().collect();
+            // Oldest first, repeated in case dates go against the history
+            loop {
+                let mut changed = false;
+                for (oid, _) in included.iter().rev() {
+                    if !on_path.contains(oid) && self.parents_of(oid)?.iter().any(|parent| on_path.contains(parent)) {
+                        on_path.insert(oid.clone());
+                        changed = true;
+                    }
+                }
+                if !changed {
+                    break;
+                }
+            }
+            included.retain(|(oid, _)| on_path.contains(oid));
+        }
+        if options.topo_order {
+            included = self.topo_sort(included)?;
+        }
+
+        let mut followed = match self.paths.as_slice() {
+            [path] if options.follow => Some(path.clone()),
+            _ => None,
+        };
+        let mut walked = Vec::new();
+        let mut skipped = 0;
+        for (oid, _) in &included {
+            if options.max_count.is_some_and(|max| walked.len() >= max) {
+                break;
+            }
+            if self.path_filter.is_filtering() && self.tree_same(oid)? {
+                continue;
+            }
+            if let Some(path) = &followed {
+                if let Some(from) = self.renamed_from(oid, path)? {
+                    trace!("{} renamed {} from {}", oid, path.display(), from.display());
+                    self.path_filter = PathFilter::build(std::slice::from_ref(&from));
+                    // The cached diffs were filtered by the name it has now
+                    self.diffs.clear();
+                    followed = Some(from);
+                }
+            }
+            let commit = self.commit_of(oid)?;
+            if !options.accepts(&commit) {
+                continue;
+            }
+            if skipped < options.skip {
+                skipped += 1;
+                continue;
+            }
+            walked.push(WalkedCommit { commit, boundary: false });
+        }
+
+        if options.boundary {
+            let mut boundary: Vec<(String, i64)> = Vec::new();
+            for shown in walked.clone() {
+                for parent in shown.commit.get_parents() {
+                    if excluded.contains(&parent) && !boundary.iter().any(|(oid, _)| *oid == parent) {
+                        let commit = self.load_commit(&parent)?;
+                        boundary.push((parent, self.get_commit_date(&commit)?));
+                    }
+                }
+            }
+            boundary.sort_by_key(|(_, date)| Reverse(*date));
+            for (oid, _) in boundary {
+                walked.push(WalkedCommit { commit: self.commit_of(&oid)?, boundary: true });
+            }
+        }
+
+        if options.reverse {
+            walked.reverse();
+        }
+        Ok(walked)
+    }
+
+    // `commits`, newest first, reordered so that each comes after all of
+    // its children. A commit whose children are all out follows straight
+    // on, the last parent of a merge first, so each line of history is gone
+    // through before the next.
+    fn topo_sort(&mut self, commits: Vec<(String, i64)>) -> Result<Vec<(String, i64)>, Error> {
+        let dates: HashMap<String, i64> = commits.iter().cloned().collect();
+        let mut children: HashMap<String, usize> = HashMap::new();
+        for (oid, _) in &commits {
+            for parent in self.parents_of(oid)? {
+                if dates.contains_key(&parent) {
+                    *children.entry(parent).or_insert(0) += 1;
+                }
+            }
+        }
+
+        // The newest tip on top
+        let mut ready: Vec<String> = commits.iter().rev()
+            .filter(|(oid, _)| !children.contains_key(oid))
+            .map(|(oid, _)| oid.clone())
+            .collect();
+        let mut sorted = Vec::with_capacity(commits.len());
+        while let Some(oid) = ready.pop() {
+            for parent in self.parents_of(&oid)? {
+                if let Some(count) = children.get_mut(&parent) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(parent);
+                    }
+                }
+            }
+            sorted.push((oid.clone(), dates[&oid]));
+        }
+        Ok(sorted)
+    }
+
+    // The path `path` had before commit `oid` renamed it, if the commit
+    // added it as a file much like one it removed from its first parent
+    fn renamed_from(&mut self, oid: &str, path: &Path) -> Result<Option<PathBuf>, Error> {
+        let parent = match self.parents_of(oid)?.into_iter().next() {
+            Some(parent) => parent,
+            None => return Ok(None),
+        };
+        let added = match self.tree_diff(Some(&parent), oid)?.get(path) {
+            Some((None, Some(new))) => new.clone(),
+            _ => return Ok(None),
+        };
+
+        let changes = self.database.tree_diff(Some(&parent), Some(oid), &PathFilter::new())?;
+        let mut removed: Vec<(String, String)> = changes.iter()
+            .filter_map(|(removed, (old, new))| match (old, new) {
+                (Some(old), None) => Some((removed.to_string_lossy().to_string(), old.get_oid().to_string())),
+                _ => None,
+            })
+            .collect();
+        removed.sort();
+        let added = [(path.to_string_lossy().to_string(), added)];
+        let pairs = rename::detect(self.database, &removed, &added, &[], RenameDetection::default())?;
+        Ok(pairs.into_iter().next().map(|pair| PathBuf::from(pair.from)))
+    }
+
+    // Whether commit `oid` leaves the filtered paths as a parent had them
+    fn tree_same(&mut self, oid: &str) -> Result<bool, Error> {
+        let parents = self.parents_of(oid)?;
+        if parents.is_empty() {
+            return Ok(self.tree_diff(None, oid)?.is_empty());
+        }
+        for parent in parents {
+            if self.tree_diff(Some(&parent), oid)?.is_empty() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn parents_of(&mut self, oid: &str) -> Result<Vec<String>, Error> {
+        Ok(self.commit_of(oid)?.get_parents())
+    }
+
+    fn commit_of(&mut self, oid: &str) -> Result<Commit, Error> {
+        let commit = self.load_commit(oid)?;
+        commit.as_any().downcast_ref::<Commit>()
+            .cloned()
+            .ok_or_else(|| Error::Generic(format!("Object {} is not a commit", oid)))
+    }
+
     /// Helper to get OID from a commit
     fn get_oid(&self, commit: &Box<dyn GitObject>) -> String {
         if let Some(commit) = commit.as_any().downcast_ref::<Commit>() {
@@ -0,0 +1,30 @@
+// src/core/shortlog.rs - commit titles grouped by author
+use std::collections::BTreeMap;
+
+use crate::core::database::commit::Commit;
+
+/// Group `commits` by author name, keeping each author's titles in the
+/// order given, and format them the way `git shortlog` does:
+///
+/// ```text
+/// Author Name (2):
+///       First title
+///       Second title
+/// ```
+pub fn format_shortlog(commits: &[Commit]) -> String {
+    let mut authors: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for commit in commits {
+        let name = commit.get_author().map(|author| author.name.clone()).unwrap_or_default();
+        authors.entry(name).or_default().push(commit.title_line().trim().to_string());
+    }
+
+    let mut output = String::new();
+    for (name, titles) in authors {
+        output.push_str(&format!("{} ({}):\n", name, titles.len()));
+        for title in titles {
+            output.push_str(&format!("      {}\n", title));
+        }
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,87 @@
+// src/core/trace.rs - informational output that `--quiet` silences, and
+// the log of commands run in a repository
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use chrono::Local;
+
+use crate::core::repository::git_dir;
+
+pub const QUIET_ENV: &str = "ASH_QUIET";
+
+// The latest commands run in a repository and how they failed, under its
+// git dir, for `ash bugreport`; cut back to the newest entries once it
+// grows past the limit
+pub const COMMAND_LOG: &str = "command-log";
+const COMMAND_LOG_LIMIT: usize = 200;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Print an informational line to stdout unless quiet mode is on.
+///
+/// Progress narration goes through this macro; command results, warnings
+/// and errors keep using `println!`/`eprintln!` so they are never lost.
+/// A closed stdout, as when piped to `head`, is not an error here.
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if !$crate::core::trace::is_quiet() {
+            use std::io::Write as _;
+            let _ = writeln!(std::io::stdout(), $($arg)*);
+        }
+    };
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Enable quiet mode from `ASH_QUIET` or a `-q`/`--quiet` flag given
+/// before the command name, which is removed from `args`. A `-q` after the
+/// command name belongs to that command, which parses it itself.
+pub fn init(args: &mut Vec<String>) {
+    let mut quiet = env::var(QUIET_ENV).map(|v| v == "1").unwrap_or(false);
+
+    while args.get(1).is_some_and(|arg| arg == "-q" || arg == "--quiet") {
+        args.remove(1);
+        quiet = true;
+    }
+
+    set_quiet(quiet);
+}
+
+/// Append `line` to the command log of the repository at `git_path`, if
+/// there is one. Losing an entry is never worth failing a command over.
+pub fn log_command(git_path: &Path, line: &str) {
+    if !git_dir::common(git_path).join("config").is_file() {
+        return;
+    }
+    let path = git_path.join(COMMAND_LOG);
+    let entry = format!("{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), line.lines().next().unwrap_or(""));
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&path) {
+        let _ = writeln!(file, "{}", entry);
+    }
+
+    let lines = read_command_log(git_path);
+    if lines.len() > 2 * COMMAND_LOG_LIMIT {
+        let mut kept = lines[lines.len() - COMMAND_LOG_LIMIT..].join("\n");
+        kept.push('\n');
+        let _ = fs::write(&path, kept);
+    }
+}
+
+/// The entries of the command log, oldest first
+pub fn read_command_log(git_path: &Path) -> Vec<String> {
+    fs::read_to_string(git_path.join(COMMAND_LOG))
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
@@ -1,127 +1,130 @@
 // src/core/workspace.rs
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::collections::{HashMap, HashSet};
-use regex::Regex; // Asigură-te că ai adăugat `regex = "1"` în Cargo.toml
+use std::cell::OnceCell;
 use crate::errors::error::Error;
+use crate::core::attributes::Attributes;
+use crate::core::ignore::Ignore;
+use crate::core::path_encoding;
+use crate::core::path_filter::PathFilter;
+use crate::core::refs::Refs;
+use crate::core::repository::git_dir;
+use crate::trace;
 
 pub struct Workspace {
     pub root_path: PathBuf,
+    // Read on first use, as most commands never convert anything
+    attributes: OnceCell<Attributes>,
+    // `core.precomposeUnicode`, read on the first walk
+    precompose: OnceCell<bool>,
+}
+
+/// A file or directory found by `Workspace::walk`, relative to the root
+#[derive(Debug, Clone)]
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub metadata: fs::Metadata,
+}
+
+impl WalkEntry {
+    pub fn is_file(&self) -> bool {
+        self.metadata.is_file()
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
 }
 
 impl Workspace {
     pub fn new(root_path: &Path) -> Self {
         Workspace {
             root_path: root_path.to_path_buf(),
+            attributes: OnceCell::new(),
+            precompose: OnceCell::new(),
         }
     }
 
-    // Load ignore patterns from .ashignore
-    fn load_ignore_patterns(&self) -> HashSet<String> {
-        let mut patterns = HashSet::new();
-        let ignore_path = self.root_path.join(".ashignore");
-
-        // Always ignore .ash directory and .git directory
-        patterns.insert(".ash".to_string());
-        patterns.insert(".ash/".to_string()); // More explicit directory ignore
-        patterns.insert(".git".to_string());
-        patterns.insert(".git/".to_string());
-
-        if ignore_path.exists() {
-            if let Ok(content) = fs::read_to_string(ignore_path) {
-                for line in content.lines() {
-                    let line = line.trim();
-                    if !line.is_empty() && !line.starts_with('#') {
-                        patterns.insert(line.to_string());
-                    }
-                }
-            }
-        }
-
-        patterns
-    }
-
     // List files recursively, applying ignore patterns
     pub fn list_files(&self) -> Result<Vec<PathBuf>, Error> {
-        let ignore_patterns = self.load_ignore_patterns();
-        let mut files = Vec::new();
-        self.list_files_recursive(&self.root_path, PathBuf::new(), &mut files, &ignore_patterns)?;
-        Ok(files)
+        Ok(self.walk(&PathFilter::new())?
+            .into_iter()
+            .filter(WalkEntry::is_file)
+            .map(|entry| entry.path)
+            .collect())
     }
 
+    /// Every file and directory under the root that `filter` selects and the
+    /// ignore patterns do not exclude, sorted by path as the index is, with
+    /// the metadata read on the way. Ignored directories are not descended
+    /// into, and symlinks to directories are not followed.
+    pub fn walk(&self, filter: &PathFilter) -> Result<Vec<WalkEntry>, Error> {
+        let mut ignore = Ignore::new(&self.root_path);
+        let mut entries = Vec::new();
+        self.walk_directory(filter, &mut ignore, &mut entries)?;
+        entries.sort_by(|a, b| a.path.as_os_str().cmp(b.path.as_os_str()));
+        Ok(entries)
+    }
 
-     // Helper for recursive listing
-     fn list_files_recursive(
-         &self,
-         abs_dir_path: &Path,
-         rel_dir_path: PathBuf, // Pass relative path for checking ignores
-         files: &mut Vec<PathBuf>,
-         ignore_patterns: &HashSet<String>,
-     ) -> Result<(), Error> {
-         match fs::read_dir(abs_dir_path) {
-             Ok(entries) => {
-                 for entry_result in entries {
-                     match entry_result {
-                         Ok(entry) => {
-                             let entry_abs_path = entry.path();
-                             let file_name = entry.file_name();
-
-                             // Construct relative path for ignore checking
-                             let entry_rel_path = rel_dir_path.join(&file_name);
-                             let rel_path_str = entry_rel_path.to_string_lossy().to_string().replace("\\", "/"); // Normalize
-
-                             // --- Ignore Check ---
-                             if self.matches_any_pattern(&rel_path_str, ignore_patterns) {
-                                 // If the pattern specifically targets a directory (ends with /), ignore it and don't recurse
-                                 // Also ignore if it's an exact match for a non-directory pattern
-                                  if entry_abs_path.is_dir() {
-                                       // Check if any pattern matches this directory specifically
-                                       let dir_pattern_match = ignore_patterns.iter().any(|p| {
-                                            let norm_p = p.replace("\\", "/");
-                                            (norm_p.ends_with('/') && rel_path_str.starts_with(&norm_p[..norm_p.len()-1])) || norm_p == rel_path_str
-                                       });
-                                       if dir_pattern_match {
-                                            //println!("Ignoring directory and contents: {}", rel_path_str);
-                                            continue; // Skip recursion
-                                       }
-                                  } else {
-                                       // If it's a file and matches any pattern, ignore it
-                                       //println!("Ignoring file: {}", rel_path_str);
-                                       continue;
-                                  }
-                             }
-                             // --- End Ignore Check ---
+    fn walk_directory(&self, filter: &PathFilter, ignore: &mut Ignore, entries: &mut Vec<WalkEntry>) -> Result<(), Error> {
+        let mark = ignore.enter(&filter.path().to_string_lossy().replace("\\", "/"));
+        let abs_dir_path = self.root_path.join(filter.path());
+        let dir_entries = match fs::read_dir(&abs_dir_path) {
+            Ok(dir_entries) => dir_entries,
+            Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                eprintln!("Warning: Permission denied reading directory {}", abs_dir_path.display());
+                return Ok(());
+            },
+            Err(e) => return Err(Error::IO(e)),
+        };
 
-                             if entry_abs_path.is_dir() {
-                                 // Recursively scan subdirectories
-                                 self.list_files_recursive(&entry_abs_path, entry_rel_path, files, ignore_patterns)?;
-                             } else if entry_abs_path.is_file() {
-                                 // Add file if it's not ignored
-                                 files.push(entry_rel_path);
-                             }
-                         },
-                         Err(e) => {
-                              if e.kind() == std::io::ErrorKind::PermissionDenied {
-                                   eprintln!("Warning: Permission denied reading entry in {}", abs_dir_path.display());
-                                   continue;
-                              } else {
-                                   return Err(Error::IO(e));
-                              }
-                         }
-                     }
-                 }
-                 Ok(())
-             },
-             Err(e) => {
-                 if e.kind() == std::io::ErrorKind::PermissionDenied {
-                     eprintln!("Warning: Permission denied reading directory {}", abs_dir_path.display());
-                     Ok(())
-                 } else {
-                     Err(Error::IO(e))
-                 }
-             }
-         }
-     }
+        for entry_result in dir_entries {
+            let entry = match entry_result {
+                Ok(entry) => entry,
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => {
+                    eprintln!("Warning: Permission denied reading entry in {}", abs_dir_path.display());
+                    continue;
+                },
+                Err(e) => return Err(Error::IO(e)),
+            };
+            // Paths are kept as UTF-8, so other names could not be added back
+            let name = match entry.file_name().to_str() {
+                Some(name) if *self.precompose.get_or_init(|| path_encoding::precompose_unicode(&self.root_path)) => path_encoding::precompose(name),
+                Some(name) => name.to_string(),
+                None => {
+                    eprintln!("warning: skipping {}: file name is not valid UTF-8",
+                        path_encoding::quote_bytes(filter.path().join(entry.file_name()).as_os_str().as_encoded_bytes(), true));
+                    continue;
+                },
+            };
+            if !filter.allows(&name) {
+                continue;
+            }
+            let child = filter.join(&name);
+            let is_symlink = entry.file_type().map(|file_type| file_type.is_symlink()).unwrap_or(false);
+            // A dangling symlink has nothing to offer
+            let metadata = match fs::metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if metadata.is_dir() && is_symlink {
+                continue;
+            }
+            let is_dir = metadata.is_dir();
+            if ignore.is_ignored(&child.path().to_string_lossy().replace("\\", "/"), is_dir) {
+                continue;
+            }
+            if is_dir || metadata.is_file() {
+                entries.push(WalkEntry { path: child.path().to_path_buf(), metadata });
+            }
+            if is_dir {
+                self.walk_directory(&child, ignore, entries)?;
+            }
+        }
+        ignore.leave(mark);
+        Ok(())
+    }
 
     // List files starting from a specific path (for add command)
     pub fn list_files_from(&self, start_path: &Path, index_entries: &HashMap<String, String>) -> Result<(Vec<PathBuf>, Vec<String>), Error> {
@@ -174,17 +177,22 @@ impl Workspace {
         }
 
         if abs_start_path.is_dir() {
-            let ignore_patterns = self.load_ignore_patterns();
-            self.process_directory( &abs_start_path, &rel_start_path, &ignore_patterns, &mut files_found, &mut expected_files )?;
+            let filter = if path_prefix.is_empty() { PathFilter::new() } else { PathFilter::build(std::slice::from_ref(&rel_start_path)) };
+            for entry in self.walk(&filter)?.into_iter().filter(WalkEntry::is_file) {
+                expected_files.remove(&entry.path.to_string_lossy().to_string());
+                files_found.push(entry.path);
+            }
              for missing_path in expected_files {
-                  if missing_path == path_prefix || missing_path.starts_with(&format!("{}/", path_prefix)) || path_prefix.is_empty() {
+                  // Tracked files stay tracked when they match an ignore pattern
+                  if self.root_path.join(&missing_path).is_file() {
+                      files_found.push(PathBuf::from(missing_path));
+                  } else if missing_path == path_prefix || missing_path.starts_with(&format!("{}/", path_prefix)) || path_prefix.is_empty() {
                      files_missing.push(missing_path);
                   }
              }
         } else {
             let rel_path_str = rel_start_path.to_string_lossy().to_string();
-            let ignore_patterns = self.load_ignore_patterns();
-            if !self.matches_any_pattern(&rel_path_str, &ignore_patterns) {
+            if index_entries.contains_key(&rel_path_str) || !self.is_ignored(&rel_start_path, false) {
                 files_found.push(rel_start_path);
             }
             expected_files.remove(&rel_path_str);
@@ -193,132 +201,39 @@ impl Workspace {
         Ok((files_found, files_missing))
     }
 
-    // Helper for processing directories recursively
-    fn process_directory(
-        &self,
-        abs_path: &Path,
-        rel_path: &Path,
-        ignore_patterns: &HashSet<String>,
-        files: &mut Vec<PathBuf>,
-        expected_files: &mut HashSet<String>
-    ) -> Result<(), Error> {
-        match fs::read_dir(abs_path) {
-            Ok(entries) => {
-                for entry_result in entries {
-                    match entry_result {
-                        Ok(entry) => {
-                            let entry_path = entry.path();
-                            let file_name = entry.file_name();
-                            let entry_rel_path = rel_path.join(&file_name);
-                            let rel_path_str = entry_rel_path.to_string_lossy().to_string().replace("\\", "/");
-
-                            if self.matches_any_pattern(&rel_path_str, ignore_patterns) {
-                                 // Skip ignored paths entirely
-                                continue;
-                            }
-
-                            if entry_path.is_dir() {
-                                self.process_directory( &entry_path, &entry_rel_path, ignore_patterns, files, expected_files )?;
-                            } else if entry_path.is_file() {
-                                files.push(entry_rel_path.clone());
-                                expected_files.remove(&rel_path_str);
-                            }
-                        },
-                        Err(e) => {
-                            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                                 eprintln!("Warning: Permission denied reading entry in {}", abs_path.display());
-                                 continue;
-                            } else { return Err(Error::IO(e)); }
-                        }
-                    }
-                }
-                Ok(())
-            },
-            Err(e) => {
-                 if e.kind() == std::io::ErrorKind::PermissionDenied {
-                      eprintln!("Warning: Permission denied accessing directory {}", abs_path.display());
-                      Ok(())
-                 } else { Err(Error::IO(e)) }
-            }
-        }
-    }
-
-
-    // Check if a path matches any ignore pattern
-    fn matches_any_pattern(&self, path_str: &str, patterns: &HashSet<String>) -> bool {
-         let normalized_path = path_str.replace("\\", "/");
-         let path_to_match = if Path::new(&normalized_path).is_absolute() {
-              match Path::new(&normalized_path).strip_prefix(&self.root_path) {
-                   Ok(p) => p.to_string_lossy().to_string(),
-                   Err(_) => normalized_path,
-              }
-         } else { normalized_path };
-
-        for pattern in patterns {
-             let normalized_pattern = pattern.replace("\\", "/");
-            if self.matches_pattern(&path_to_match, &normalized_pattern) {
+    /// Whether `path`, relative to the root, is ignored, by itself or
+    /// because a directory above it is
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignore = Ignore::new(&self.root_path);
+        let components: Vec<String> = path.components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let mut current = String::new();
+        for (i, component) in components.iter().enumerate() {
+            current = if current.is_empty() { component.clone() } else { format!("{}/{}", current, component) };
+            let last = i + 1 == components.len();
+            if ignore.is_ignored(&current, is_dir || !last) {
                 return true;
             }
-        }
-        false
-    }
-
-    // Simple pattern matching logic
-    fn matches_pattern(&self, path: &str, pattern: &str) -> bool {
-        if pattern.is_empty() { return false; }
-
-        // Handle directory patterns (ending with /)
-        if pattern.ends_with('/') {
-            let dir_pattern = &pattern[0..pattern.len() - 1];
-            // Match directory itself or anything inside it
-            return path == dir_pattern || path.starts_with(&format!("{}/", dir_pattern));
-        }
-
-        // Handle file patterns (no slashes or specific file match)
-        if !pattern.contains('/') {
-            // Match filename anywhere in the path
-            if let Some(file_name) = Path::new(path).file_name() {
-                if file_name == std::ffi::OsStr::new(pattern) {
-                    return true;
-                }
-            }
-             // Basic wildcard matching for filename
-             if pattern.contains('*') {
-                  if let Some(filename) = Path::new(path).file_name().and_then(|s| s.to_str()) {
-                       let regex_pattern_str = pattern.replace(".", "\\.").replace("*", ".*");
-                       let filename_regex = format!("^{}$", regex_pattern_str);
-                       if let Ok(re_fn) = Regex::new(&filename_regex) {
-                           if re_fn.is_match(filename) { return true; }
-                       }
-                  }
-             }
-
-        } else { // Pattern contains slashes (specific path)
-            // Basic wildcard matching for path
-            if pattern.contains('*') {
-                let regex_pattern_str = pattern.replace(".", "\\.").replace("*", ".*");
-                 // Anchor the pattern to the beginning for path match
-                let final_regex_str = format!("^{}", regex_pattern_str);
-                 if let Ok(re) = Regex::new(&final_regex_str) {
-                     if re.is_match(path) { return true; }
-                 }
-            } else {
-                 // Exact path match or prefix match if pattern represents a directory
-                 if path == pattern || path.starts_with(&format!("{}/", pattern)) {
-                    return true;
-                 }
+            if !last {
+                ignore.enter(&current);
             }
         }
-
-
         false
     }
 
+    /// The attributes `.ashattributes` files give the paths of the tree
+    pub fn attributes(&self) -> &Attributes {
+        self.attributes.get_or_init(|| Attributes::new(&self.root_path))
+    }
 
+    /// The content of the file at `path` as it is stored, with line endings
+    /// converted as its attributes ask
     pub fn read_file(&self, path: &Path) -> Result<Vec<u8>, Error> {
         let file_path = self.root_path.join(path);
         match fs::read(&file_path) {
-            Ok(data) => Ok(data),
+            Ok(data) => Ok(self.attributes().to_repository(self.relative(path), data)),
             Err(e) => Err(Error::IO(e)), // Simplify error handling for now
         }
     }
@@ -331,6 +246,11 @@ impl Workspace {
         }
     }
 
+    // `path` relative to the root, as attributes are looked up
+    fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(&self.root_path).unwrap_or(path)
+    }
+
     pub fn path_exists(&self, path: &Path) -> Result<bool, Error> {
         let file_path = self.root_path.join(path);
         Ok(file_path.exists())
@@ -340,23 +260,24 @@ impl Workspace {
         let full_path = self.root_path.join(path);
         if let Some(parent) = full_path.parent() {
              if !parent.exists() {
-                //println!("Creating parent directory for write: {}", parent.display());
+                //trace!("Creating parent directory for write: {}", parent.display());
                 std::fs::create_dir_all(parent).map_err(Error::IO)?;
              }
         }
-        //println!("Writing file: {} ({} bytes)", full_path.display(), data.len());
+        //trace!("Writing file: {} ({} bytes)", full_path.display(), data.len());
+        let data = self.attributes().to_workspace(self.relative(path), data.to_vec());
         std::fs::write(&full_path, data).map_err(Error::IO)
     }
 
     // Includes logging added previously
     pub fn remove_file(&self, path: &Path) -> Result<(), Error> {
         let full_path = self.root_path.join(path);
-        println!("  Attempting to remove file/dir at: {}", full_path.display());
+        trace!("  Attempting to remove file/dir at: {}", full_path.display());
         if full_path.exists() {
             if full_path.is_file() {
-                println!("    Path is a file, calling std::fs::remove_file");
+                trace!("    Path is a file, calling std::fs::remove_file");
                 match std::fs::remove_file(&full_path) {
-                    Ok(_) => println!("    std::fs::remove_file succeeded for file."),
+                    Ok(_) => trace!("    std::fs::remove_file succeeded for file."),
                     Err(e) => {
                         println!("    Error removing file: {}", e);
                         return Err(Error::IO(e));
@@ -366,9 +287,9 @@ impl Workspace {
                  println!("    Warning: remove_file called on a directory: {}. Use force_remove_directory instead.", full_path.display());
                  return Err(Error::Generic(format!("Attempted to use remove_file on directory: {}", full_path.display())));
             } else {
-                println!("    Path exists but is not a file or directory (e.g., symlink?): {}", full_path.display());
+                trace!("    Path exists but is not a file or directory (e.g., symlink?): {}", full_path.display());
                   match std::fs::remove_file(&full_path) { // Try removing anyway
-                     Ok(_) => println!("    Successfully removed non-file/non-dir path."),
+                     Ok(_) => trace!("    Successfully removed non-file/non-dir path."),
                      Err(e) => {
                           println!("    Error removing non-file/non-dir path: {}", e);
                           return Err(Error::IO(e));
@@ -376,12 +297,12 @@ impl Workspace {
                   }
             }
         } else {
-             println!("    Path does not exist, nothing to remove: {}", full_path.display());
+             trace!("    Path does not exist, nothing to remove: {}", full_path.display());
         }
         if self.root_path.join(path).exists() { // Re-check using relative path construction logic
              println!("    Warning: Path still exists after removal attempt: {}", full_path.display());
         } else {
-             println!("    Path confirmed removed or did not exist initially: {}", full_path.display());
+             trace!("    Path confirmed removed or did not exist initially: {}", full_path.display());
         }
         Ok(())
     }
@@ -394,11 +315,11 @@ impl Workspace {
             Err(_) => false,
         };
         if is_effectively_empty {
-            println!("Attempting to remove empty/effectively empty directory: {}", full_path.display());
+            trace!("Attempting to remove empty/effectively empty directory: {}", full_path.display());
             if let Err(e) = std::fs::remove_dir(&full_path) {
                 eprintln!("Warning: Failed to remove directory {} with std::fs::remove_dir: {}", full_path.display(), e);
             } else {
-                 println!("Successfully removed empty directory: {}", full_path.display());
+                 trace!("Successfully removed empty directory: {}", full_path.display());
             }
             if let Some(parent) = path.parent() {
                 if !parent.as_os_str().is_empty() && parent.to_string_lossy() != "." {
@@ -406,7 +327,7 @@ impl Workspace {
                 }
             }
         } else {
-            println!("Directory not empty, skipping removal: {}", full_path.display());
+            trace!("Directory not empty, skipping removal: {}", full_path.display());
         }
         Ok(())
     }
@@ -415,13 +336,13 @@ impl Workspace {
         let full_path = self.root_path.join(path);
         if full_path.exists() {
             if full_path.is_file() {
-                println!("Path {} exists as file, removing to create directory.", full_path.display());
+                trace!("Path {} exists as file, removing to create directory.", full_path.display());
                 std::fs::remove_file(&full_path).map_err(Error::IO)?;
-                 println!("Creating directory: {}", full_path.display());
+                 trace!("Creating directory: {}", full_path.display());
                  std::fs::create_dir_all(&full_path).map_err(Error::IO)
             } else { Ok(()) }
         } else {
-            //println!("Creating directory: {}", full_path.display()); // Reduce noise
+            //trace!("Creating directory: {}", full_path.display()); // Reduce noise
             std::fs::create_dir_all(&full_path).map_err(Error::IO)
         }
     }
@@ -429,10 +350,10 @@ impl Workspace {
     pub fn force_remove_directory(&self, path: &Path) -> Result<(), Error> {
         let full_path = self.root_path.join(path);
         if full_path.exists() && full_path.is_dir() {
-            println!("Force removing directory and contents: {}", full_path.display());
+            trace!("Force removing directory and contents: {}", full_path.display());
              match std::fs::remove_dir_all(&full_path) {
                  Ok(_) => {
-                     println!("  Successfully force removed directory: {}", full_path.display());
+                     trace!("  Successfully force removed directory: {}", full_path.display());
                      Ok(())
                  },
                  Err(e) => {
@@ -444,7 +365,7 @@ impl Workspace {
              println!("Warning: force_remove_directory called on non-directory path: {}", full_path.display());
              self.remove_file(path) // Attempt to remove as file
         } else {
-             //println!("Directory does not exist, nothing to force remove: {}", full_path.display()); // Reduce noise
+             //trace!("Directory does not exist, nothing to force remove: {}", full_path.display()); // Reduce noise
              Ok(())
         }
     }
@@ -464,29 +385,12 @@ impl Workspace {
     }
 
     pub fn read_head(&self) -> Result<String, Error> {
-        let head_path = self.root_path.join(".ash/HEAD");
-        
-        if !head_path.exists() {
+        let git_path = git_dir::resolve(&self.root_path);
+        if !git_path.join("HEAD").exists() {
             return Err(Error::Generic("HEAD file not found".to_string()));
         }
-        
-        let content = std::fs::read_to_string(head_path).map_err(Error::IO)?;
-        let content = content.trim();
-        
-        // Check if it's a symbolic reference
-        if content.starts_with("ref: ") {
-            let ref_path = content[5..].trim();
-            let full_ref_path = self.root_path.join(".ash").join(ref_path);
-            
-            if !full_ref_path.exists() {
-                return Err(Error::Generic(format!("Referenced file not found: {}", ref_path)));
-            }
-            
-            let ref_content = std::fs::read_to_string(full_ref_path).map_err(Error::IO)?;
-            Ok(ref_content.trim().to_string())
-        } else {
-            // Direct reference to a commit
-            Ok(content.to_string())
-        }
+
+        Refs::new(&git_path).read_head()?
+            .ok_or_else(|| Error::Generic("HEAD does not point at a commit".to_string()))
     }
 }
\ No newline at end of file
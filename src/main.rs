@@ -8,14 +8,54 @@ use commands::commit::CommitCommand;
 use commands::diff::DiffCommand;
 use commands::init::InitCommand;
 use commands::add::AddCommand;
+use commands::add_interactive::AddInteractiveCommand;
 use commands::log::LogCommand;
 use commands::status::StatusCommand;
 use commands::branch::BranchCommand;
 // Imports for merge and related operations
-use commands::merge::MergeCommand;
+use commands::merge::{MergeCommand, MergeStrategy};
 use commands::merge_tool::MergeToolCommand;
+use commands::mv::MvCommand;
+use commands::check_attr::CheckAttrCommand;
 use commands::rm::RmCommand;
+use commands::restore::RestoreCommand;
 use commands::reset::ResetCommand;
+use commands::stash::{StashCommand, StashOptions};
+use commands::fetch::{FetchCommand, FetchOptions};
+use commands::push::PushCommand;
+use commands::clone::CloneCommand;
+use commands::comment::CommentCommand;
+use commands::blame::BlameCommand;
+use commands::remote::RemoteCommand;
+use commands::pull::PullCommand;
+use commands::rebase::{RebaseCommand, RebaseOptions};
+use commands::bisect::BisectCommand;
+use commands::bugreport::BugreportCommand;
+use commands::verify_repo_locks::VerifyRepoLocksCommand;
+use commands::grep::{GrepCommand, GrepOptions};
+use commands::show::ShowCommand;
+use commands::repack::{IndexOptions, RepackCommand};
+use commands::gc::GcCommand;
+use commands::reflog::ReflogCommand;
+use commands::tag::{TagCommand, TagListOptions, TagOptions};
+use commands::verify_tag::VerifyTagCommand;
+use commands::worktree::{WorktreeCommand, WorktreeOptions};
+use commands::clean::{CleanCommand, CleanOptions};
+use commands::request_pull::RequestPullCommand;
+use commands::format_patch::{FormatPatchCommand, FormatPatchOptions};
+use commands::apply::{ApplyCommand, ApplyOptions, PATCH_CONFLICTS, PATCH_FAILED};
+use commands::am::{AmCommand, AmOptions, AM_STOPPED};
+use commands::daemon::DaemonCommand;
+use commands::write_tree::WriteTreeCommand;
+use commands::update_ref::UpdateRefCommand;
+use commands::symbolic_ref::SymbolicRefCommand;
+use commands::for_each_ref::{ForEachRefCommand, ForEachRefOptions};
+use commands::var::VarCommand;
+use commands::commit_tree::CommitTreeCommand;
+use commands::read_tree::{ReadTreeCommand, ReadTreeOptions};
+use commands::subtree::SubtreeCommand;
+use commands::switch::{SwitchCommand, SwitchOptions};
+use commands::difftool::{DifftoolCommand, DifftoolOptions};
 use std::path::Path;
 use crate::core::index::index::Index;
 use crate::core::refs::Refs;
@@ -26,8 +66,13 @@ use crate::core::database::database::Database;
 use commands::commit_writer::CommitWriter;
 use crate::core::repository::pending_commit::PendingCommitType;
 use commands::commit::get_editor_command;
-use commands::cherry_pick::CherryPickCommand;
+use commands::cherry_pick::{CherryPickCommand, CherryPickOptions, CHERRY_PICK_STOPPED};
 use commands::revert::RevertCommand;
+use crate::core::repository::{format, git_dir};
+use crate::core::trace;
+use crate::core::advice;
+use crate::core::repository::autostash::Autostash;
+use crate::core::merge::conflict_summary::ConflictSummary;
 
 mod cli;
 mod commands;
@@ -39,25 +84,81 @@ mod core;
 const ORIG_HEAD: &str = "ORIG_HEAD";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    trace::init(&mut args);
 
+    let command_line = args.iter().skip(1).cloned().collect::<Vec<_>>().join(" ");
     match CliParser::parse(args) {
         Ok(cli_args) => {
+            // Commands that only create or serve repositories need not
+            // understand the one they are run in, and a bug report is
+            // most wanted when ash does not
+            if !matches!(cli_args.command, Command::Init { .. } | Command::Clone { .. } | Command::Daemon { .. } | Command::Bugreport { .. } | Command::VerifyRepoLocks { .. }) {
+                if let Err(e) = format::check_repository(Path::new(".")) {
+                    exit_with_error(&format!("fatal: {}", e));
+                }
+                trace::log_command(&git_dir::resolve(Path::new(".")), &format!("ash {}", command_line));
+            }
+
             match cli_args.command {
-                Command::Init { path } => handle_init_command(&path),
-                Command::Commit { message, amend, reuse_message, edit } => 
-                    handle_commit_command(&message, amend, reuse_message, edit),
-                Command::Add { paths } => handle_add_command(&paths),
-                Command::Status { porcelain, color } => handle_status_command(porcelain, &color),
-                Command::Diff { paths, cached } => handle_diff_command(&paths, cached),
-                Command::Branch { name, start_point, verbose, delete, force } => {
-                    handle_branch_command(&name, start_point.as_deref(), verbose, delete, force)
-                },
-                Command::Checkout { target } => handle_checkout_command(&target),
-                Command::Log { revisions, abbrev, format, patch, decorate } => {
-                    handle_log_command(&revisions, abbrev, &format, patch, &decorate)
-                },
-                Command::Merge { branch, message, abort, continue_merge, tool } => {
+                Command::Init { path, separate_git_dir, bare } => handle_init_command(&path, separate_git_dir.as_deref(), bare),
+                Command::Commit { message, amend, reuse_message, edit, verbose } => 
+                    handle_commit_command(&message, amend, reuse_message, edit, verbose),
+                Command::Add { paths, interactive, patch, intent_to_add } => handle_add_command(&paths, interactive, patch, intent_to_add),
+                Command::Status { porcelain, color, exit_code, conflicts, verbose } => {
+                    if conflicts {
+                        handle_status_conflicts_command()
+                    } else {
+                        handle_status_command(porcelain, verbose, &color, exit_code)
+                    }
+                },
+                Command::Diff { paths, cached, exit_code, submodule, renames } => {
+                    handle_diff_command(&paths, cached, exit_code, submodule.as_deref(), &renames)
+                },
+                Command::Branch { name, set_upstream, unset_upstream, .. } if set_upstream.is_some() || unset_upstream => {
+                    // The branch is the one positional argument, if given
+                    let branch = Some(name.as_str()).filter(|name| !name.is_empty());
+                    handle_branch_upstream_command(branch, set_upstream.as_deref())
+                },
+                Command::Branch { name, start_point, verbose, delete, force, rename, column, .. } => {
+                    handle_branch_command(&name, start_point.as_deref(), verbose, delete, force, rename, column.as_deref())
+                },
+                Command::Checkout { target, autostash, detach, merge, ours, theirs, paths, new_branch, force_branch } => {
+                    if ours || theirs {
+                        handle_checkout_side_command(&paths, theirs)
+                    } else if let Some(name) = new_branch {
+                        handle_checkout_branch_command(&name, &target, force_branch, autostash, merge)
+                    } else {
+                        handle_checkout_command(&target, autostash, detach, merge)
+                    }
+                },
+                Command::Log { revisions, abbrev, format, patch, decorate, diff_merges, reverse, boundary, ancestry_path, graph, stat, filters } => {
+                    let mut options = HashMap::new();
+                    options.insert("abbrev".to_string(), abbrev.to_string());
+                    options.insert("format".to_string(), format);
+                    options.insert("patch".to_string(), patch.to_string());
+                    options.insert("decorate".to_string(), decorate);
+                    options.insert("diff_merges".to_string(), diff_merges);
+                    options.insert("reverse".to_string(), reverse.to_string());
+                    options.insert("boundary".to_string(), boundary.to_string());
+                    options.insert("ancestry_path".to_string(), ancestry_path.to_string());
+                    options.insert("graph".to_string(), graph.to_string());
+                    options.insert("stat".to_string(), stat.unwrap_or_default());
+                    // Patterns add up, one per line; other limits take the last value
+                    for (name, value) in filters {
+                        match options.get_mut(&name) {
+                            Some(patterns) if ["author", "committer", "grep"].contains(&name.as_str()) => {
+                                patterns.push('\n');
+                                patterns.push_str(&value);
+                            },
+                            _ => {
+                                options.insert(name, value);
+                            },
+                        }
+                    }
+                    handle_log_command(&revisions, &options)
+                },
+                Command::Merge { branches, message, abort, continue_merge, tool, autostash, summary, squash, strategy, strategy_options } => {
                     if abort {
                         handle_merge_abort_command();
                     } else if continue_merge {
@@ -65,20 +166,120 @@ fn main() {
                             Ok(_) => process::exit(0),
                             Err(e) => exit_with_error(&format!("fatal: {}", e)),
                         }
-                    } else if tool.is_some() && branch.is_empty() {
+                    } else if tool.is_some() && branches.is_empty() {
                         handle_merge_tool_command(tool.as_deref());
                     } else {
-                        handle_merge_command(&branch, message.as_deref());
+                        handle_merge_command(&branches, message.as_deref(), autostash, summary, squash, strategy.as_deref(), &strategy_options);
                     }
                 },
+                Command::Mv { sources, destination, force, skip_errors, dry_run, verbose } => {
+                    handle_mv_command(&sources, &destination, force, skip_errors, dry_run, verbose)
+                },
                 Command::Rm { files, cached, force, recursive } => {
                     handle_rm_command(&files, cached, force, recursive)
                 },
+                Command::Restore { paths, source, staged, worktree } => {
+                    handle_restore_command(&paths, source.as_deref(), staged, worktree)
+                },
+                Command::Fetch { remote, refspecs, prune, prune_tags, negotiation_tips, refmap, filter } => {
+                    let options = FetchOptions { prune, prune_tags, negotiation_tips, refmap, filter };
+                    handle_fetch_command(remote.as_deref(), &refspecs, &options)
+                },
+                Command::Comment { action, commit, anchor, message, path } => {
+                    handle_comment_command(&action, commit.as_deref(), anchor.as_deref(), message.as_deref(), path.as_deref())
+                },
+                Command::Push { remote, refspecs, force, leases, mirror } => handle_push_command(remote.as_deref(), &refspecs, force, &leases, mirror),
+                Command::Clone { url, directory, bare, mirror, filter } => handle_clone_command(&url, directory.as_deref(), bare, mirror, filter.as_deref()),
+                Command::Pull { remote, branch, rebase } => handle_pull_command(remote.as_deref(), branch.as_deref(), rebase),
+                Command::Rebase { upstream, onto, interactive, exec, rebase_merges, continue_rebase, abort, skip } => {
+                    let options = RebaseOptions { onto, interactive, exec, rebase_merges };
+                    handle_rebase_command(upstream.as_deref(), &options, continue_rebase, abort, skip)
+                },
+                Command::Show { objects, patch } => handle_show_command(&objects, patch),
+                Command::CheckAttr { attributes, paths, all } => handle_check_attr_command(&attributes, &paths, all),
+                Command::Grep { patterns, args, paths, line_number, ignore_case, invert, word, fixed, files_with_matches, count, cached } => {
+                    let options = GrepOptions { line_number, ignore_case, invert, word, fixed, files_with_matches, count, cached };
+                    handle_grep_command(&patterns, &args, &paths, &options)
+                },
+                Command::VerifyRepoLocks { break_stale, stale_after } => handle_verify_repo_locks_command(break_stale, stale_after),
+                Command::Clean { paths, dry_run, force, interactive, directories, ignored, only_ignored, excludes } => {
+                    let options = CleanOptions { dry_run, force, interactive, directories, ignored, only_ignored, excludes, paths };
+                    handle_clean_command(&options)
+                },
+                Command::VerifyTag { tags, verbose } => handle_verify_tag_command(&tags, verbose),
+                Command::FormatPatch { revision, output_directory, stdout, numbered, start_number, subject_prefix, max_count } => {
+                    let options = FormatPatchOptions { output_directory, stdout, numbered, start_number, subject_prefix, max_count };
+                    handle_format_patch_command(revision.as_deref(), &options)
+                },
+                Command::Apply { patches, check, index, cached, reverse, strip, min_context, three_way, verbose } => {
+                    let options = ApplyOptions { check, index, cached, reverse, strip, min_context, three_way, verbose };
+                    handle_apply_command(&patches, &options)
+                },
+                Command::Am { mailboxes, three_way, continue_am, skip, abort, quit, show_current_patch } => {
+                    handle_am_command(&mailboxes, &AmOptions { three_way }, continue_am, skip, abort, quit, show_current_patch)
+                },
+                Command::UpdateRef { name, new, old, no_deref, message } => {
+                    handle_update_ref_command(&name, new.as_deref(), old.as_deref(), no_deref, message.as_deref())
+                },
+                Command::SymbolicRef { name, target, delete, short, quiet, message } => {
+                    handle_symbolic_ref_command(&name, target.as_deref(), delete, short, quiet, message.as_deref())
+                },
+                Command::ForEachRef { patterns, format, sort, count } => {
+                    handle_for_each_ref_command(&patterns, &ForEachRefOptions { format, sort, count })
+                },
+                Command::Var { name, list, show_origin } => handle_var_command(name.as_deref(), list, show_origin),
+                Command::WriteTree { prefix } => handle_write_tree_command(prefix.as_deref()),
+                Command::CommitTree { tree, parents, messages, file } => handle_commit_tree_command(&tree, &parents, &messages, file.as_deref()),
+                Command::ReadTree { trees, merge, update, prefix, empty } => {
+                    handle_read_tree_command(&trees, &ReadTreeOptions { merge, update, prefix, empty })
+                },
+                Command::Subtree { action, prefix, args, message, branch } => {
+                    handle_subtree_command(&action, &prefix, &args, message.as_deref(), branch.as_deref())
+                },
+                Command::Difftool { revisions, paths, dir_diff, tool, no_prompt, cached } => {
+                    let options = DifftoolOptions { dir_diff, tool, no_prompt, cached };
+                    handle_difftool_command(&revisions, &paths, &options)
+                },
+                Command::Switch { target, create, force_create, detach, merge, autostash } => {
+                    let options = SwitchOptions { create, force_create, detach, merge, autostash };
+                    handle_switch_command(target.as_deref(), &options)
+                },
+                Command::Bugreport { output, stdout } => handle_bugreport_command(output.as_deref(), stdout),
+                Command::Bisect { action, args } => handle_bisect_command(&action, &args),
+                Command::Worktree { action, args, verbose, porcelain, dry_run, new_branch, detach, force, reason, expire } => {
+                    let options = WorktreeOptions { verbose, porcelain, dry_run, new_branch, detach, force, reason, expire };
+                    handle_worktree_command(&action, &args, &options)
+                },
+                Command::Remote { action, args, verbose, dry_run, auto, delete } => handle_remote_command(&action, &args, verbose, dry_run, auto, delete),
+                Command::Blame { revision, file, ignore_revs, ignore_revs_file, abbrev, incremental } => {
+                    handle_blame_command(revision.as_deref(), &file, &ignore_revs, ignore_revs_file.as_deref(), abbrev, incremental)
+                },
+                Command::Repack { all, delete, write_commit_graph, write_bitmaps } => {
+                    handle_repack_command(all, delete, IndexOptions { write_commit_graph, write_bitmaps })
+                },
+                Command::Gc { prune, auto, detach, write_commit_graph, write_bitmaps } => {
+                    handle_gc_command(prune.as_deref(), auto, detach, IndexOptions { write_commit_graph, write_bitmaps })
+                },
+                Command::Reflog { reference } => handle_reflog_command(reference.as_deref()),
+                Command::Tag { args, annotate, message, delete, list, force, contains, no_contains, points_at, sort, sign, local_user, verify, column } => {
+                    let options = TagOptions { annotate, message, force, sign, local_user };
+                    let filters = TagListOptions { pattern: None, contains, no_contains, points_at, sort, column };
+                    handle_tag_command(&args, delete, list, verify, &options, filters)
+                },
+                Command::RequestPull { start, url, end } => handle_request_pull_command(&start, &url, end.as_deref()),
+                Command::Daemon { directories, base_path, export_all, listen, port } => {
+                    handle_daemon_command(&directories, base_path.as_deref(), export_all, listen.as_deref(), port)
+                },
+                Command::Stash { action, entry, message, include_untracked, keep_index, patch } => {
+                    let options = StashOptions { entry, message, include_untracked, keep_index, patch };
+                    handle_stash_command(&action, &options)
+                },
                 Command::Reset { files, soft, mixed, hard, force, reuse_message } => {
                     handle_reset_command(&files, soft, mixed, hard, force, reuse_message.as_deref())
                 },
-                Command::CherryPick { args, r#continue, abort, quit, mainline } => {
-                    handle_cherry_pick_command(&args, r#continue, abort, quit, mainline)
+                Command::CherryPick { args, r#continue, abort, quit, skip, mainline, allow_empty, keep_redundant_commits } => {
+                    let options = CherryPickOptions { mainline, allow_empty, keep_redundant_commits };
+                    handle_cherry_pick_command(&args, &options, r#continue, skip, abort, quit)
                 },
                 Command::Revert { args, r#continue, abort, quit, mainline } => {
                     handle_revert_command(&args, r#continue, abort, quit, mainline)
@@ -96,51 +297,72 @@ fn main() {
                 println!("{}", e);
             } else {
                 println!("Error parsing command: {}", e);
+                let git_path = git_dir::resolve(Path::new("."));
+                trace::log_command(&git_path, &format!("ash {}", command_line));
+                trace::log_command(&git_path, &format!("  Error parsing command: {}", e));
             }
             process::exit(1);
         }
     }
 }
 
-fn handle_commit_command(message: &str, amend: bool, reuse_message: Option<String>, edit: bool) {
-    match CommitCommand::execute(message, amend, reuse_message.as_deref(), edit) {
-        Ok(_) => process::exit(0),
+fn handle_commit_command(message: &str, amend: bool, reuse_message: Option<String>, edit: bool, verbose: bool) {
+    match CommitCommand::execute(message, amend, reuse_message.as_deref(), edit, verbose) {
+        Ok(_) => {
+            GcCommand::run_auto();
+            process::exit(0)
+        },
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_init_command(path: &str) {
-    match InitCommand::execute(path) {
+fn handle_init_command(path: &str, separate_git_dir: Option<&str>, bare: bool) {
+    match InitCommand::execute(path, separate_git_dir, bare) {
         Ok(_) => process::exit(0),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_add_command(paths: &[String]) {
-    match AddCommand::execute(paths) {
+fn handle_add_command(paths: &[String], interactive: bool, patch: bool, intent_to_add: bool) {
+    let result = if interactive || patch {
+        AddInteractiveCommand::execute(patch && !interactive, paths)
+    } else {
+        AddCommand::execute(paths, intent_to_add)
+    };
+
+    match result {
         Ok(_) => process::exit(0),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_status_command(porcelain: bool, color: &str) {
+fn handle_status_command(porcelain: bool, verbose: bool, color: &str, exit_code: bool) {
     // Set color mode environment variable
     std::env::set_var("ASH_COLOR", color);
 
-    match StatusCommand::execute(porcelain) {
-        Ok(_) => process::exit(0),
+    // With --exit-code a dirty tree exits with status 1
+    match StatusCommand::execute(porcelain, verbose) {
+        Ok(dirty) => process::exit(if exit_code && dirty { 1 } else { 0 }),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_diff_command(paths: &[String], cached: bool) {
-    match DiffCommand::execute(paths, cached) {
+fn handle_status_conflicts_command() {
+    match StatusCommand::conflicts() {
         Ok(_) => process::exit(0),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_branch_command(name: &str, start_point: Option<&str>, verbose: bool, delete: bool, force: bool) {
+fn handle_diff_command(paths: &[String], cached: bool, exit_code: bool, submodule: Option<&str>, renames: &[String]) {
+    // With --exit-code finding differences exits with status 1
+    match DiffCommand::execute(paths, cached, submodule, renames) {
+        Ok(found) => process::exit(if exit_code && found { 1 } else { 0 }),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_branch_command(name: &str, start_point: Option<&str>, verbose: bool, delete: bool, force: bool, rename: bool, column: Option<&str>) {
     // Set environment variables to pass flag information
     if verbose {
         std::env::set_var("ASH_BRANCH_VERBOSE", "1");
@@ -151,6 +373,12 @@ fn handle_branch_command(name: &str, start_point: Option<&str>, verbose: bool, d
     if force {
         std::env::set_var("ASH_BRANCH_FORCE", "1");
     }
+    if rename {
+        std::env::set_var("ASH_BRANCH_RENAME", "1");
+    }
+    if let Some(column) = column {
+        std::env::set_var("ASH_BRANCH_COLUMN", column);
+    }
 
     match BranchCommand::execute(name, start_point) {
         Ok(_) => process::exit(0),
@@ -158,23 +386,40 @@ fn handle_branch_command(name: &str, start_point: Option<&str>, verbose: bool, d
     }
 }
 
+fn handle_branch_upstream_command(branch: Option<&str>, upstream: Option<&str>) {
+    let result = match upstream {
+        Some(upstream) => BranchCommand::set_upstream(branch, upstream),
+        None => BranchCommand::unset_upstream(branch),
+    };
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_log_command(revisions: &[String], options: &HashMap<String, String>) {
+    match LogCommand::execute(revisions, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
 
-fn handle_log_command(revisions: &[String], abbrev: bool, format: &str, patch: bool, decorate: &str) {
-    // Convert options to HashMap for easier handling
-    let mut options = HashMap::new();
-    options.insert("abbrev".to_string(), abbrev.to_string());
-    options.insert("format".to_string(), format.to_string());
-    options.insert("patch".to_string(), patch.to_string());
-    options.insert("decorate".to_string(), decorate.to_string());
+fn handle_checkout_command(target: &str, autostash: Option<bool>, detach: bool, merge: bool) {
+    match CheckoutCommand::execute(target, autostash, detach, merge) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
 
-    match LogCommand::execute(revisions, &options) {
+fn handle_checkout_branch_command(name: &str, start_point: &str, force: bool, autostash: Option<bool>, merge: bool) {
+    match CheckoutCommand::create_branch(name, Some(start_point), force, autostash, merge) {
         Ok(_) => process::exit(0),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
 
-fn handle_checkout_command(target: &str) {
-    match CheckoutCommand::execute(target) {
+fn handle_checkout_side_command(paths: &[String], theirs: bool) {
+    match CheckoutCommand::checkout_side(paths, theirs) {
         Ok(_) => process::exit(0),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
@@ -194,13 +439,13 @@ fn handle_merge_continue_command() -> Result<(), Error> {
     
     // Initialize repository components
     let root_path = Path::new(".");
-    let git_path = root_path.join(".ash");
+    let git_path = git_dir::resolve(root_path);
     
     if !git_path.exists() {
         return Err(Error::Generic("Not an AsheraFlow repository: .ash directory not found".into()));
     }
     
-    let db_path = git_path.join("objects");
+    let db_path = git_dir::objects(&git_path);
     let mut database = Database::new(db_path);
     
     // Check for the index file
@@ -237,7 +482,9 @@ fn handle_merge_continue_command() -> Result<(), Error> {
     
     // If all conflicts are resolved, check for pending operation type and resume it
     if commit_writer.pending_commit.in_progress(PendingCommitType::Merge) {
-        return commit_writer.resume_merge(PendingCommitType::Merge, get_editor_command());
+        commit_writer.resume_merge(PendingCommitType::Merge, get_editor_command())?;
+        // Bring back changes stashed by `merge --autostash`
+        Autostash::new(&git_path).apply(&mut Repository::new(".")?)
     } else if commit_writer.pending_commit.in_progress(PendingCommitType::CherryPick) {
         return commit_writer.resume_merge(PendingCommitType::CherryPick, get_editor_command());
     } else if commit_writer.pending_commit.in_progress(PendingCommitType::Revert) {
@@ -249,6 +496,13 @@ fn handle_merge_continue_command() -> Result<(), Error> {
     }
 }
 
+fn handle_mv_command(sources: &[String], destination: &str, force: bool, skip_errors: bool, dry_run: bool, verbose: bool) {
+    match MvCommand::execute(sources, destination, force, skip_errors, dry_run, verbose) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
 fn handle_rm_command(files: &[String], cached: bool, force: bool, recursive: bool) {
     match RmCommand::execute(files, cached, force, recursive) {
         Ok(_) => process::exit(0),
@@ -256,6 +510,373 @@ fn handle_rm_command(files: &[String], cached: bool, force: bool, recursive: boo
     }
 }
 
+fn handle_restore_command(paths: &[String], source: Option<&str>, staged: bool, worktree: bool) {
+    match RestoreCommand::execute(paths, source, staged, worktree) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_stash_command(action: &str, options: &StashOptions) {
+    match StashCommand::execute(action, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_fetch_command(remote: Option<&str>, refspecs: &[String], options: &FetchOptions) {
+    match FetchCommand::execute(remote, refspecs, options) {
+        Ok(_) => {
+            GcCommand::run_auto();
+            process::exit(0)
+        },
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_comment_command(action: &str, commit: Option<&str>, anchor: Option<&str>, message: Option<&str>, path: Option<&str>) {
+    let result = match action {
+        "add" => CommentCommand::add(commit.unwrap_or("HEAD"), anchor.unwrap_or_default(), message.unwrap_or_default()),
+        _ => CommentCommand::list(commit, path),
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_push_command(remote: Option<&str>, refspecs: &[String], force: bool, leases: &[String], mirror: bool) {
+    match PushCommand::execute(remote, refspecs, force, leases, mirror) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_clone_command(url: &str, directory: Option<&str>, bare: bool, mirror: bool, filter: Option<&str>) {
+    match CloneCommand::execute(url, directory, bare, mirror, filter) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_pull_command(remote: Option<&str>, branch: Option<&str>, rebase: Option<bool>) {
+    match PullCommand::execute(remote, branch, rebase) {
+        Ok(_) => {
+            GcCommand::run_auto();
+            process::exit(0)
+        },
+        Err(e) if e.to_string().contains("fix conflicts") => {
+            println!("{}", e);
+            process::exit(1);
+        },
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_rebase_command(upstream: Option<&str>, options: &RebaseOptions, continue_rebase: bool, abort: bool, skip: bool) {
+    let result = if continue_rebase {
+        RebaseCommand::continue_rebase()
+    } else if abort {
+        RebaseCommand::abort()
+    } else if skip {
+        RebaseCommand::skip()
+    } else {
+        RebaseCommand::execute(upstream, options)
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        // The commit that did not apply has been reported already
+        Err(e) if e.to_string().contains("fix conflicts") => process::exit(1),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_bisect_command(action: &str, args: &[String]) {
+    match BisectCommand::execute(action, args) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_show_command(objects: &[String], patch: bool) {
+    match ShowCommand::execute(objects, patch) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_check_attr_command(attributes: &[String], paths: &[String], all: bool) {
+    match CheckAttrCommand::execute(attributes, paths, all) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_grep_command(patterns: &[String], args: &[String], paths: &[String], options: &GrepOptions) {
+    match GrepCommand::execute(patterns, args, paths, options) {
+        Ok(true) => process::exit(0),
+        // Nothing matched
+        Ok(false) => process::exit(1),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_verify_repo_locks_command(break_stale: bool, stale_after: Option<u64>) {
+    // Stale locks left behind exit with status 1
+    match VerifyRepoLocksCommand::execute(break_stale, stale_after) {
+        Ok(stale_left) => process::exit(if stale_left { 1 } else { 0 }),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_clean_command(options: &CleanOptions) {
+    match CleanCommand::execute(options) {
+        Ok(()) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_verify_tag_command(tags: &[String], verbose: bool) {
+    // A bad or missing signature exits with status 1
+    match VerifyTagCommand::execute(tags, verbose) {
+        Ok(good) => process::exit(if good { 0 } else { 1 }),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_update_ref_command(name: &str, new: Option<&str>, old: Option<&str>, no_deref: bool, message: Option<&str>) {
+    match UpdateRefCommand::execute(name, new, old, no_deref, message.unwrap_or_default()) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_for_each_ref_command(patterns: &[String], options: &ForEachRefOptions) {
+    match ForEachRefCommand::execute(patterns, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_var_command(name: Option<&str>, list: bool, show_origin: bool) {
+    match VarCommand::execute(name, list, show_origin) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_symbolic_ref_command(name: &str, target: Option<&str>, delete: bool, short: bool, quiet: bool, message: Option<&str>) {
+    // -q turns "not a symbolic ref" into a quiet exit status of 1; the
+    // global flag is taken out of the arguments before parsing
+    let quiet = quiet || trace::is_quiet();
+    let result = match target {
+        Some(target) => SymbolicRefCommand::write(name, target, message).map(|_| true),
+        None if delete => SymbolicRefCommand::delete(name, quiet),
+        None => SymbolicRefCommand::read(name, short, quiet),
+    };
+
+    match result {
+        Ok(true) => process::exit(0),
+        Ok(false) => process::exit(1),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_write_tree_command(prefix: Option<&str>) {
+    match WriteTreeCommand::execute(prefix) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_commit_tree_command(tree: &str, parents: &[String], messages: &[String], file: Option<&str>) {
+    match CommitTreeCommand::execute(tree, parents, messages, file) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_read_tree_command(trees: &[String], options: &ReadTreeOptions) {
+    match ReadTreeCommand::execute(trees, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_difftool_command(revisions: &[String], paths: &[String], options: &DifftoolOptions) {
+    match DifftoolCommand::execute(revisions, paths, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_switch_command(target: Option<&str>, options: &SwitchOptions) {
+    match SwitchCommand::execute(target, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_subtree_command(action: &str, prefix: &str, args: &[String], message: Option<&str>, branch: Option<&str>) {
+    let result = match action {
+        "add" => SubtreeCommand::add(prefix, args, message),
+        "merge" => SubtreeCommand::merge(prefix, &args[0], message),
+        "pull" => SubtreeCommand::pull(prefix, &args[0], &args[1], message),
+        _ => SubtreeCommand::split(prefix, args.first().map(String::as_str), branch),
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_bugreport_command(output: Option<&str>, stdout: bool) {
+    match BugreportCommand::execute(output, stdout) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_worktree_command(action: &str, args: &[String], options: &WorktreeOptions) {
+    let result = match action {
+        "add" => WorktreeCommand::add(&args[0], args.get(1).map(String::as_str), options.new_branch.as_deref(), options.detach),
+        "remove" => WorktreeCommand::remove(&args[0], options.force),
+        "lock" => WorktreeCommand::lock(&args[0], options.reason.as_deref()),
+        "unlock" => WorktreeCommand::unlock(&args[0]),
+        "prune" => WorktreeCommand::prune(options.dry_run, options.verbose, options.expire.as_deref()),
+        _ => WorktreeCommand::list(options.verbose, options.porcelain),
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_remote_command(action: &str, args: &[String], verbose: bool, dry_run: bool, auto: bool, delete: bool) {
+    let result = match action {
+        "add" => RemoteCommand::add(&args[0], &args[1]),
+        "remove" | "rm" => RemoteCommand::remove(&args[0]),
+        "rename" => RemoteCommand::rename(&args[0], &args[1]),
+        "show" => RemoteCommand::show(&args[0]),
+        "prune" => RemoteCommand::prune(&args[0], dry_run),
+        "set-head" => RemoteCommand::set_head(&args[0], args.get(1).map(String::as_str), auto, delete),
+        _ => RemoteCommand::list(verbose),
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_blame_command(revision: Option<&str>, file: &str, ignore_revs: &[String], ignore_revs_file: Option<&str>, abbrev: Option<usize>, incremental: bool) {
+    match BlameCommand::execute(revision, file, ignore_revs, ignore_revs_file, abbrev, incremental) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_repack_command(all: bool, delete: bool, options: IndexOptions) {
+    match RepackCommand::execute(all, delete, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_gc_command(prune: Option<&str>, auto: bool, detach: Option<bool>, options: IndexOptions) {
+    match GcCommand::execute(prune, auto, detach, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_reflog_command(reference: Option<&str>) {
+    match ReflogCommand::show(reference) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_tag_command(args: &[String], delete: bool, list: bool, verify: bool, options: &TagOptions, filters: TagListOptions) {
+    let result = if delete {
+        TagCommand::delete(args)
+    } else if verify {
+        // A bad or missing signature exits with status 1
+        match VerifyTagCommand::execute(args, true) {
+            Ok(good) => process::exit(if good { 0 } else { 1 }),
+            Err(e) => Err(e),
+        }
+    } else if list || args.is_empty() {
+        TagCommand::list(&TagListOptions { pattern: args.first().cloned(), ..filters })
+    } else if filters.column.is_some() {
+        Err(Error::Generic("--column is only allowed when listing tags".to_string()))
+    } else {
+        TagCommand::create(&args[0], args.get(1).map(String::as_str), options)
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_request_pull_command(start: &str, url: &str, end: Option<&str>) {
+    match RequestPullCommand::execute(start, url, end) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_format_patch_command(revision: Option<&str>, options: &FormatPatchOptions) {
+    match FormatPatchCommand::execute(revision, options) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_apply_command(patches: &[String], options: &ApplyOptions) {
+    match ApplyCommand::execute(patches, options) {
+        Ok(_) => process::exit(0),
+        // What did not apply has been reported already
+        Err(e) if e.to_string().contains(PATCH_FAILED) || e.to_string().contains(PATCH_CONFLICTS) => process::exit(1),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_am_command(mailboxes: &[String], options: &AmOptions, continue_am: bool, skip: bool, abort: bool, quit: bool, show_current_patch: bool) {
+    let result = if continue_am {
+        AmCommand::continue_am()
+    } else if skip {
+        AmCommand::skip()
+    } else if abort {
+        AmCommand::abort()
+    } else if quit {
+        AmCommand::quit()
+    } else if show_current_patch {
+        AmCommand::show_current_patch()
+    } else {
+        AmCommand::execute(mailboxes, options)
+    };
+
+    match result {
+        Ok(_) => process::exit(0),
+        // The patch that stopped the session has been reported already
+        Err(e) if e.to_string().contains(AM_STOPPED) => process::exit(1),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
+fn handle_daemon_command(directories: &[String], base_path: Option<&str>, export_all: bool, listen: Option<&str>, port: Option<u16>) {
+    match DaemonCommand::execute(directories, base_path, export_all, listen, port) {
+        Ok(_) => process::exit(0),
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    }
+}
+
 fn handle_reset_command(files: &[String], soft: bool, mixed: bool, hard: bool, force: bool, reuse_message: Option<&str>) {
     match ResetCommand::execute(files, soft, mixed, hard, force, reuse_message) {
         Ok(_) => process::exit(0),
@@ -263,9 +884,23 @@ fn handle_reset_command(files: &[String], soft: bool, mixed: bool, hard: bool, f
     }
 }
 
-fn handle_cherry_pick_command(commits: &[String], continue_op: bool, abort: bool, quit: bool, mainline: Option<u32>) {
-    match CherryPickCommand::execute(commits, continue_op, abort, quit, mainline) {
+fn handle_cherry_pick_command(commits: &[String], options: &CherryPickOptions, continue_op: bool, skip: bool, abort: bool, quit: bool) {
+    let result = if continue_op {
+        CherryPickCommand::continue_pick()
+    } else if skip {
+        CherryPickCommand::skip()
+    } else if abort {
+        CherryPickCommand::abort()
+    } else if quit {
+        CherryPickCommand::quit()
+    } else {
+        CherryPickCommand::execute(commits, options)
+    };
+
+    match result {
         Ok(_) => process::exit(0),
+        // The commit that stopped the sequence has been reported already
+        Err(e) if e.to_string().contains(CHERRY_PICK_STOPPED) => process::exit(1),
         Err(e) => exit_with_error(&format!("fatal: {}", e)),
     }
 }
@@ -279,6 +914,7 @@ fn handle_revert_command(commits: &[String], continue_op: bool, abort: bool, qui
 
 fn exit_with_error(message: &str) -> ! {
     eprintln!("{}", message); // Afișează eroarea pe stderr
+    trace::log_command(&git_dir::resolve(Path::new(".")), &format!("  {}", message));
     // Poți adăuga logica de afișare a mesajului de ajutor aici dacă dorești
     // if message.contains("Usage:") || ... {
     //     eprintln!("\n{}", CliParser::format_help());
@@ -287,9 +923,16 @@ fn exit_with_error(message: &str) -> ! {
 }
 
 // --- Păstrează funcția handle_merge_command originală ---
-fn handle_merge_command(branch: &str, message: Option<&str>) {
-    match MergeCommand::execute(branch, message) {
-        Ok(_) => process::exit(0),
+fn handle_merge_command(branches: &[String], message: Option<&str>, autostash: Option<bool>, summary: bool, squash: bool, strategy: Option<&str>, strategy_options: &[String]) {
+    let strategy = match MergeStrategy::parse(strategy, strategy_options) {
+        Ok(strategy) => strategy,
+        Err(e) => exit_with_error(&format!("fatal: {}", e)),
+    };
+    match MergeCommand::execute(branches, message, autostash, summary, squash, &strategy) {
+        Ok(_) => {
+            GcCommand::run_auto();
+            process::exit(0)
+        },
         Err(e) => {
             // Pentru erori specifice de merge, dorim să afișăm un mesaj mai clar
             if e.to_string().contains("Already up to date") {
@@ -297,8 +940,13 @@ fn handle_merge_command(branch: &str, message: Option<&str>) {
                 process::exit(0);
             } else if e.to_string().contains("fix conflicts") {
                 // Dacă există conflicte, dorim să afișăm un mesaj de eroare mai clar
+                if squash {
+                    println!("Squash commit -- not updating HEAD");
+                }
                 println!("{}", e);
-                println!("Conflicts detected. Fix conflicts and then run 'ash merge --continue'");
+                if !squash {
+                    advice::advise(advice::MERGE_CONFLICT, "Fix conflicts and then run 'ash merge --continue',\nor run 'ash merge --abort' to go back to where you were before the merge.");
+                }
                 process::exit(1);
             } else {
                 exit_with_error(&format!("fatal: {}", e));
@@ -316,7 +964,7 @@ fn handle_merge_abort_command() {
     };
     
     // Verificăm dacă există un merge în desfășurare
-    let git_path = Path::new(".").join(".ash");
+    let git_path = git_dir::resolve(Path::new("."));
     let merge_head_path = git_path.join("MERGE_HEAD");
     if !merge_head_path.exists() {
         exit_with_error("fatal: There is no merge to abort");
@@ -325,6 +973,7 @@ fn handle_merge_abort_command() {
     // Ștergem fișierele specifice merge-ului
     let _ = std::fs::remove_file(merge_head_path);
     let _ = std::fs::remove_file(git_path.join("MERGE_MSG"));
+    ConflictSummary::clear(&git_path);
     
     // Citim HEAD-ul original
     let orig_head_path = git_path.join(ORIG_HEAD);
@@ -337,6 +986,9 @@ fn handle_merge_abort_command() {
     match ResetCommand::execute(&[orig_head], false, false, true, true, None) {
         Ok(_) => {
             println!("Merge aborted");
+            if let Err(e) = Autostash::new(&git_path).apply(&mut repo) {
+                exit_with_error(&format!("fatal: {}", e));
+            }
             process::exit(0);
         },
         Err(e) => exit_with_error(&format!("fatal: Failed to reset to ORIG_HEAD: {}", e)),
@@ -0,0 +1,119 @@
+#!/bin/bash
+# Tests for ASH add -i
+# What the interactive menu puts in the index has to commit to a tree that
+# can be read back, including for paths missing from the working tree.
+
+# --- Configuration ---
+# Find the ASH executable
+if [ -n "$1" ]; then
+    ASH_EXECUTABLE="$1"
+elif [ -f "./target/release/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/release/AsheraFlow"
+elif [ -f "./target/debug/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/debug/AsheraFlow"
+else
+    echo "ASH executable not found. Build the project or provide the path as an argument."
+    echo "Usage: $0 [path-to-ash-executable]"
+    exit 1
+fi
+
+echo "Using ASH executable: $ASH_EXECUTABLE"
+ASH_CMD="$ASH_EXECUTABLE" # Alias for easier use
+export ASH_QUIET=1
+
+# --- Test Environment Setup ---
+TEST_DIR=$(mktemp -d)
+echo "Using temporary directory: ${TEST_DIR}"
+cd "$TEST_DIR" || exit 1
+
+# --- Colors and Counters ---
+RED="\033[0;31m"
+GREEN="\033[0;32m"
+YELLOW="\033[0;33m"
+BLUE="\033[0;34m"
+RESET="\033[0m"
+TESTS_PASSED=0
+TESTS_FAILED=0
+
+# --- Helper Functions ---
+function setup_repo() {
+    local repo_name=${1:-"test_repo"}
+    rm -rf "$repo_name" 2>/dev/null || true
+    mkdir -p "$repo_name"
+    (cd "$repo_name" && "$ASH_CMD" init . > /dev/null)
+    export GIT_AUTHOR_NAME="Test User"
+    export GIT_AUTHOR_EMAIL="test@example.com"
+    echo -e "${BLUE}Initialized repo in $TEST_DIR/$repo_name${RESET}"
+}
+
+function pass() {
+    echo -e "${GREEN}PASS: $1${RESET}"
+    TESTS_PASSED=$((TESTS_PASSED + 1))
+}
+
+function fail() {
+    echo -e "${RED}FAIL: $1${RESET}"
+    TESTS_FAILED=$((TESTS_FAILED + 1))
+}
+
+function commit_all() {
+    local repo_name="$1"
+    (cd "$repo_name" && "$ASH_CMD" add . > /dev/null && "$ASH_CMD" commit -m "$2" > /dev/null)
+}
+
+# --- Test Cases ---
+function test_revert_staged_deletion() {
+    echo -e "\n${BLUE}--- Test: Reverting a staged deletion, then committing ---${RESET}"
+    local repo="revert_deletion_repo"
+    setup_repo "$repo"
+    echo "one" > "$repo/a.txt"
+    echo "two" > "$repo/b.txt"
+    commit_all "$repo" "Initial"
+
+    (cd "$repo" && "$ASH_CMD" rm a.txt > /dev/null)
+    printf 'revert\n1\nquit\n' | (cd "$repo" && "$ASH_CMD" add -i > /dev/null)
+    echo "three" >> "$repo/b.txt"
+    (cd "$repo" && "$ASH_CMD" add b.txt > /dev/null && "$ASH_CMD" commit -m "Second" > /dev/null)
+
+    echo -e "${YELLOW}TEST: The reverted file is committed as the blob it was${RESET}"
+    if (cd "$repo" && "$ASH_CMD" restore --source=HEAD a.txt > /dev/null 2>&1) && [ "$(cat "$repo/a.txt")" == "one" ]; then
+        pass "The reverted file is committed as the blob it was"
+    else
+        fail "The reverted file is committed as the blob it was"
+    fi
+
+    echo -e "${YELLOW}TEST: Nothing is left to commit once the file is restored${RESET}"
+    local actual
+    actual=$(cd "$repo" && "$ASH_CMD" status --porcelain --color=never 2>&1)
+    if [ -z "$actual" ]; then
+        pass "Nothing is left to commit once the file is restored"
+    else
+        fail "Nothing is left to commit once the file is restored"
+        echo -e "Actual:\n$actual"
+    fi
+}
+
+
+# --- Run Tests ---
+test_revert_staged_deletion
+
+# --- Summary ---
+echo -e "\n${BLUE}--- Test Summary ---${RESET}"
+echo -e "${GREEN}Tests Passed: $TESTS_PASSED${RESET}"
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    echo -e "${RED}Tests Failed: $TESTS_FAILED${RESET}"
+else
+    echo -e "${GREEN}Tests Failed: $TESTS_FAILED${RESET}"
+fi
+
+# --- Cleanup ---
+cd ..
+rm -rf "$TEST_DIR"
+echo "Cleaned up temporary directory: $TEST_DIR"
+
+# Exit with status code indicating failure if any tests failed
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    exit 1
+else
+    exit 0
+fi
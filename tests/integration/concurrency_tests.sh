@@ -0,0 +1,227 @@
+#!/bin/bash
+# Stress tests for the ASH object database and index under concurrent use
+# Several ash processes run at once against one repository; none of their
+# writes may be lost, and none may leave temp or lock files behind.
+
+# --- Configuration ---
+# Find the ASH executable
+if [ -n "$1" ]; then
+    ASH_EXECUTABLE="$1"
+elif [ -f "./target/release/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/release/AsheraFlow"
+elif [ -f "./target/debug/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/debug/AsheraFlow"
+else
+    echo "ASH executable not found. Build the project or provide the path as an argument."
+    echo "Usage: $0 [path-to-ash-executable]"
+    exit 1
+fi
+
+echo "Using ASH executable: $ASH_EXECUTABLE"
+ASH_CMD="$ASH_EXECUTABLE" # Alias for easier use
+export ASH_QUIET=1
+
+# How many processes run at once, and how many files each adds
+WORKERS=${WORKERS:-8}
+FILES_PER_WORKER=${FILES_PER_WORKER:-25}
+
+# --- Test Environment Setup ---
+TEST_DIR=$(mktemp -d)
+echo "Using temporary directory: ${TEST_DIR}"
+cd "$TEST_DIR" || exit 1
+
+# --- Colors and Counters ---
+RED="\033[0;31m"
+GREEN="\033[0;32m"
+YELLOW="\033[0;33m"
+BLUE="\033[0;34m"
+RESET="\033[0m"
+TESTS_PASSED=0
+TESTS_FAILED=0
+
+# --- Helper Functions ---
+function setup_repo() {
+    local repo_name=${1:-"test_repo"}
+    rm -rf "$repo_name" 2>/dev/null || true
+    mkdir -p "$repo_name"
+    (cd "$repo_name" && "$ASH_CMD" init . > /dev/null)
+    export GIT_AUTHOR_NAME="Test User"
+    export GIT_AUTHOR_EMAIL="test@example.com"
+    echo -e "${BLUE}Initialized repo in $TEST_DIR/$repo_name${RESET}"
+}
+
+function pass() {
+    echo -e "${GREEN}PASS: $1${RESET}"
+    TESTS_PASSED=$((TESTS_PASSED + 1))
+}
+
+function fail() {
+    echo -e "${RED}FAIL: $1${RESET}"
+    TESTS_FAILED=$((TESTS_FAILED + 1))
+}
+
+# Start `ash add` in every worker's own directory at once and wait for all
+# of them; the number that failed is left in `failures`
+function add_in_parallel() {
+    local repo_name="$1"
+    local pids=()
+    failures=0
+    for worker in $(seq 1 "$WORKERS"); do
+        (cd "$repo_name" && "$ASH_CMD" add "worker_$worker" > "add_$worker.log" 2>&1) &
+        pids+=($!)
+    done
+    for pid in "${pids[@]}"; do
+        wait "$pid" || failures=$((failures + 1))
+    done
+}
+
+function assert_no_leftovers() {
+    local repo_name="$1"
+    local msg="$2"
+    echo -e "${YELLOW}TEST: $msg${RESET}"
+    local leftovers
+    leftovers=$(find "$repo_name/.ash" -name 'tmp_*' -o -name '*.lock')
+    if [ -z "$leftovers" ]; then
+        pass "$msg"
+    else
+        fail "$msg - found: $leftovers"
+    fi
+}
+
+# --- Test Cases ---
+function test_parallel_add_distinct_files() {
+    echo -e "\n${BLUE}--- Test: Parallel add of distinct files ---${RESET}"
+    local repo="distinct_repo"
+    setup_repo "$repo"
+    for worker in $(seq 1 "$WORKERS"); do
+        mkdir -p "$repo/worker_$worker"
+        for n in $(seq 1 "$FILES_PER_WORKER"); do
+            echo "worker $worker file $n" > "$repo/worker_$worker/file_$n.txt"
+        done
+    done
+
+    add_in_parallel "$repo"
+    echo -e "${YELLOW}TEST: every concurrent add succeeds${RESET}"
+    if [ "$failures" -eq 0 ]; then
+        pass "every concurrent add succeeds"
+    else
+        fail "every concurrent add succeeds - $failures of $WORKERS failed"
+        cat "$repo"/add_*.log
+    fi
+    rm -f "$repo"/add_*.log
+
+    # Each add loads the index only once it holds the lock, so none of
+    # the others' entries may have been dropped
+    echo -e "${YELLOW}TEST: the index holds every file added${RESET}"
+    local expected=$((WORKERS * FILES_PER_WORKER))
+    local staged
+    staged=$(cd "$repo" && "$ASH_CMD" status --porcelain --color=never | grep -c '^A ')
+    local untracked
+    untracked=$(cd "$repo" && "$ASH_CMD" status --porcelain --color=never | grep -c '^??')
+    if [ "$staged" -eq "$expected" ] && [ "$untracked" -eq 0 ]; then
+        pass "the index holds every file added ($staged)"
+    else
+        fail "the index holds every file added - $staged staged and $untracked untracked, expected $expected staged"
+    fi
+
+    echo -e "${YELLOW}TEST: the result can be committed${RESET}"
+    if (cd "$repo" && "$ASH_CMD" commit -m "All workers" > /dev/null 2>&1); then
+        pass "the result can be committed"
+    else
+        fail "the result can be committed"
+    fi
+    assert_no_leftovers "$repo" "Distinct files: no temp or lock files are left behind"
+}
+
+function test_parallel_add_same_objects() {
+    echo -e "\n${BLUE}--- Test: Parallel add of identical content ---${RESET}"
+    local repo="same_repo"
+    setup_repo "$repo"
+    # Every worker writes the very same objects at the same time
+    for worker in $(seq 1 "$WORKERS"); do
+        mkdir -p "$repo/worker_$worker"
+        for n in $(seq 1 "$FILES_PER_WORKER"); do
+            echo "shared content $n" > "$repo/worker_$worker/file_$n.txt"
+        done
+    done
+
+    add_in_parallel "$repo"
+    echo -e "${YELLOW}TEST: every concurrent add succeeds${RESET}"
+    if [ "$failures" -eq 0 ]; then
+        pass "every concurrent add succeeds"
+    else
+        fail "every concurrent add succeeds - $failures of $WORKERS failed"
+        cat "$repo"/add_*.log
+    fi
+    rm -f "$repo"/add_*.log
+
+    echo -e "${YELLOW}TEST: each shared object is stored once and intact${RESET}"
+    local objects
+    objects=$(find "$repo/.ash/objects" -type f | wc -l)
+    if (cd "$repo" && "$ASH_CMD" commit -m "Shared" > /dev/null 2>&1) \
+        && (cd "$repo" && "$ASH_CMD" log --oneline > /dev/null 2>&1) \
+        && [ "$objects" -eq "$FILES_PER_WORKER" ]; then
+        pass "each shared object is stored once and intact"
+    else
+        fail "each shared object is stored once and intact - $objects objects for $FILES_PER_WORKER contents"
+    fi
+    assert_no_leftovers "$repo" "Shared objects: no temp or lock files are left behind"
+}
+
+function test_held_lock_times_out() {
+    echo -e "\n${BLUE}--- Test: A lock that is never released ---${RESET}"
+    local repo="stale_repo"
+    setup_repo "$repo"
+    echo "content" > "$repo/file.txt"
+    touch "$repo/.ash/index.lock"
+
+    echo -e "${YELLOW}TEST: add gives up on a held index lock${RESET}"
+    local start end
+    start=$(date +%s)
+    if (cd "$repo" && "$ASH_CMD" add file.txt > /dev/null 2>&1); then
+        fail "add gives up on a held index lock - it succeeded"
+    else
+        end=$(date +%s)
+        if [ $((end - start)) -le 5 ]; then
+            pass "add gives up on a held index lock"
+        else
+            fail "add gives up on a held index lock - it took $((end - start))s"
+        fi
+    fi
+
+    echo -e "${YELLOW}TEST: add succeeds once the lock is released${RESET}"
+    (sleep 0.3 && rm -f "$repo/.ash/index.lock") &
+    if (cd "$repo" && "$ASH_CMD" add file.txt > /dev/null 2>&1); then
+        pass "add succeeds once the lock is released"
+    else
+        fail "add succeeds once the lock is released"
+    fi
+    wait
+}
+
+
+# --- Run Tests ---
+test_parallel_add_distinct_files
+test_parallel_add_same_objects
+test_held_lock_times_out
+
+# --- Summary ---
+echo -e "\n${BLUE}--- Test Summary ---${RESET}"
+echo -e "${GREEN}Tests Passed: $TESTS_PASSED${RESET}"
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    echo -e "${RED}Tests Failed: $TESTS_FAILED${RESET}"
+else
+    echo -e "${GREEN}Tests Failed: $TESTS_FAILED${RESET}"
+fi
+
+# --- Cleanup ---
+cd ..
+rm -rf "$TEST_DIR"
+echo "Cleaned up temporary directory: $TEST_DIR"
+
+# Exit with status code indicating failure if any tests failed
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    exit 1
+else
+    exit 0
+fi
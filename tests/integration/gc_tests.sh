@@ -0,0 +1,179 @@
+#!/bin/bash
+# Tests for ASH gc
+# Once refs are packed, everything that lists refs has to find them in
+# packed-refs: a second gc must keep what they point at, and clones and
+# for-each-ref must still see them.
+
+# --- Configuration ---
+# Find the ASH executable
+if [ -n "$1" ]; then
+    ASH_EXECUTABLE="$1"
+elif [ -f "./target/release/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/release/AsheraFlow"
+elif [ -f "./target/debug/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/debug/AsheraFlow"
+else
+    echo "ASH executable not found. Build the project or provide the path as an argument."
+    echo "Usage: $0 [path-to-ash-executable]"
+    exit 1
+fi
+
+echo "Using ASH executable: $ASH_EXECUTABLE"
+ASH_CMD="$ASH_EXECUTABLE" # Alias for easier use
+export ASH_QUIET=1
+
+# --- Test Environment Setup ---
+TEST_DIR=$(mktemp -d)
+echo "Using temporary directory: ${TEST_DIR}"
+cd "$TEST_DIR" || exit 1
+
+# --- Colors and Counters ---
+RED="\033[0;31m"
+GREEN="\033[0;32m"
+YELLOW="\033[0;33m"
+BLUE="\033[0;34m"
+RESET="\033[0m"
+TESTS_PASSED=0
+TESTS_FAILED=0
+
+# --- Helper Functions ---
+function setup_repo() {
+    local repo_name=${1:-"test_repo"}
+    rm -rf "$repo_name" 2>/dev/null || true
+    mkdir -p "$repo_name"
+    (cd "$repo_name" && "$ASH_CMD" init . > /dev/null)
+    export GIT_AUTHOR_NAME="Test User"
+    export GIT_AUTHOR_EMAIL="test@example.com"
+    echo -e "${BLUE}Initialized repo in $TEST_DIR/$repo_name${RESET}"
+}
+
+function pass() {
+    echo -e "${GREEN}PASS: $1${RESET}"
+    TESTS_PASSED=$((TESTS_PASSED + 1))
+}
+
+function fail() {
+    echo -e "${RED}FAIL: $1${RESET}"
+    TESTS_FAILED=$((TESTS_FAILED + 1))
+}
+
+# Check that a command run in the repository prints exactly what is given
+function assert_output() {
+    local repo_name="$1"
+    local command="$2"
+    local expected="$3"
+    local msg="$4"
+    echo -e "${YELLOW}TEST: $msg${RESET}"
+    local actual
+    actual=$(cd "$repo_name" && eval "\"\$ASH_CMD\" $command" 2>&1)
+    if [ "$actual" == "$expected" ]; then
+        pass "$msg"
+    else
+        fail "$msg"
+        echo -e "Expected:\n$expected\nActual:\n$actual"
+    fi
+}
+
+function commit_all() {
+    local repo_name="$1"
+    (cd "$repo_name" && "$ASH_CMD" add . > /dev/null && "$ASH_CMD" commit -m "$2" > /dev/null)
+}
+
+# --- Test Cases ---
+function test_gc_twice_keeps_packed_refs() {
+    echo -e "\n${BLUE}--- Test: Running gc again over packed refs ---${RESET}"
+    local repo="packed_repo"
+    setup_repo "$repo"
+    echo "one" > "$repo/file"
+    commit_all "$repo" "Initial"
+    (cd "$repo" && "$ASH_CMD" branch topic && "$ASH_CMD" tag -a v1 -m "Release" > /dev/null)
+    local refs
+    refs=$(cd "$repo" && "$ASH_CMD" for-each-ref --format='%(objecttype) %(refname)')
+
+    (cd "$repo" && "$ASH_CMD" gc --prune=now > /dev/null 2>&1 && "$ASH_CMD" gc --prune=now > /dev/null 2>&1)
+    assert_output "$repo" "for-each-ref --format='%(objecttype) %(refname)'" "$refs" "for-each-ref lists the packed refs"
+    assert_output "$repo" "for-each-ref --format='%(*objecttype) %(subject)' refs/tags" "commit Release" "The annotated tag object survives the second gc"
+
+    "$ASH_CMD" clone "$repo" packed_clone > /dev/null 2>&1
+    assert_output "packed_clone" "for-each-ref --format='%(refname)' refs/remotes" "refs/remotes/origin/HEAD
+refs/remotes/origin/master
+refs/remotes/origin/topic" "A clone sees the packed branches"
+    assert_output "packed_clone" "for-each-ref --format='%(subject)' refs/heads" "Initial" "A clone has the packed branches' commits"
+}
+
+function test_gc_keeps_worktree_heads() {
+    echo -e "\n${BLUE}--- Test: A commit only a linked worktree has checked out ---${RESET}"
+    local repo="worktree_repo"
+    setup_repo "$repo"
+    echo "one" > "$repo/file"
+    commit_all "$repo" "Initial"
+    (cd "$repo" && "$ASH_CMD" worktree add --detach ../worktree_tree > /dev/null 2>&1)
+    echo "two" > "worktree_tree/other"
+    commit_all "worktree_tree" "Work"
+    # Without its reflog only the worktree's HEAD names the commit
+    rm -rf "$repo/.ash/worktrees/worktree_tree/logs"
+
+    (cd "$repo" && "$ASH_CMD" gc --prune=now > /dev/null 2>&1)
+    assert_output "worktree_tree" "branch kept HEAD && \"\$ASH_CMD\" for-each-ref --format='%(subject)' refs/heads/kept" "Work" "The detached HEAD of a worktree survives gc"
+}
+
+function test_repack_writes_indexes() {
+    echo -e "\n${BLUE}--- Test: repack and gc write the commit-graph and bitmaps ---${RESET}"
+    local repo="index_repo"
+    setup_repo "$repo"
+    echo "one" > "$repo/file"
+    commit_all "$repo" "Initial"
+    echo "two" > "$repo/file"
+    commit_all "$repo" "Second"
+
+    (cd "$repo" && "$ASH_CMD" gc --no-write-commit-graph > /dev/null 2>&1)
+    echo -e "${YELLOW}TEST: gc --no-write-commit-graph writes no commit-graph${RESET}"
+    if [ ! -e "$repo/.ash/objects/info/commit-graph" ]; then
+        pass "gc --no-write-commit-graph writes no commit-graph"
+    else
+        fail "gc --no-write-commit-graph writes no commit-graph"
+    fi
+
+    assert_output "$repo" "-q repack -a -d --write-commit-graph --write-bitmaps" "Nothing new to pack.
+Wrote commit-graph with 2 commits
+Wrote bitmap index for 1 commits" "repack -a writes the commit-graph and a bitmap"
+    echo -e "${YELLOW}TEST: The bitmap sits next to the pack${RESET}"
+    if [ -e "$repo/.ash/objects/info/commit-graph" ] && ls "$repo"/.ash/objects/pack/*.bitmap > /dev/null 2>&1; then
+        pass "The bitmap sits next to the pack"
+    else
+        fail "The bitmap sits next to the pack"
+    fi
+
+    assert_output "$repo" "-q repack -b" "warning: disabling bitmap writing, as some objects are not being packed
+Nothing new to pack." "repack without -a writes no bitmap"
+
+    touch "$repo/.ash/gc.lock"
+    assert_output "$repo" "-q repack -a -d --write-bitmaps" "fatal: Lock error: gc or repack is already running in this repository (see ash verify-repo-locks)" "repack waits for no other maintenance"
+    rm -f "$repo/.ash/gc.lock"
+}
+
+# --- Run Tests ---
+test_gc_twice_keeps_packed_refs
+test_gc_keeps_worktree_heads
+test_repack_writes_indexes
+
+# --- Summary ---
+echo -e "\n${BLUE}--- Test Summary ---${RESET}"
+echo -e "${GREEN}Tests Passed: $TESTS_PASSED${RESET}"
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    echo -e "${RED}Tests Failed: $TESTS_FAILED${RESET}"
+else
+    echo -e "${GREEN}Tests Failed: $TESTS_FAILED${RESET}"
+fi
+
+# --- Cleanup ---
+cd ..
+rm -rf "$TEST_DIR"
+echo "Cleaned up temporary directory: $TEST_DIR"
+
+# Exit with status code indicating failure if any tests failed
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    exit 1
+else
+    exit 0
+fi
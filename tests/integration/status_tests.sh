@@ -0,0 +1,187 @@
+#!/bin/bash
+# Tests for how ASH status reports untracked paths
+# Untracked directories are collapsed to their outermost untracked
+# directory, including where a tracked file and a directory trade places,
+# and directories holding nothing but ignored files are left out.
+
+# --- Configuration ---
+# Find the ASH executable
+if [ -n "$1" ]; then
+    ASH_EXECUTABLE="$1"
+elif [ -f "./target/release/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/release/AsheraFlow"
+elif [ -f "./target/debug/AsheraFlow" ]; then
+    ASH_EXECUTABLE="$(pwd)/target/debug/AsheraFlow"
+else
+    echo "ASH executable not found. Build the project or provide the path as an argument."
+    echo "Usage: $0 [path-to-ash-executable]"
+    exit 1
+fi
+
+echo "Using ASH executable: $ASH_EXECUTABLE"
+ASH_CMD="$ASH_EXECUTABLE" # Alias for easier use
+export ASH_QUIET=1
+
+# --- Test Environment Setup ---
+TEST_DIR=$(mktemp -d)
+echo "Using temporary directory: ${TEST_DIR}"
+cd "$TEST_DIR" || exit 1
+
+# --- Colors and Counters ---
+RED="\033[0;31m"
+GREEN="\033[0;32m"
+YELLOW="\033[0;33m"
+BLUE="\033[0;34m"
+RESET="\033[0m"
+TESTS_PASSED=0
+TESTS_FAILED=0
+
+# --- Helper Functions ---
+function setup_repo() {
+    local repo_name=${1:-"test_repo"}
+    rm -rf "$repo_name" 2>/dev/null || true
+    mkdir -p "$repo_name"
+    (cd "$repo_name" && "$ASH_CMD" init . > /dev/null)
+    export GIT_AUTHOR_NAME="Test User"
+    export GIT_AUTHOR_EMAIL="test@example.com"
+    echo -e "${BLUE}Initialized repo in $TEST_DIR/$repo_name${RESET}"
+}
+
+function pass() {
+    echo -e "${GREEN}PASS: $1${RESET}"
+    TESTS_PASSED=$((TESTS_PASSED + 1))
+}
+
+function fail() {
+    echo -e "${RED}FAIL: $1${RESET}"
+    TESTS_FAILED=$((TESTS_FAILED + 1))
+}
+
+# Check that `ash status --porcelain` in the repository prints exactly
+# the lines given, in order
+function assert_status() {
+    local repo_name="$1"
+    local expected="$2"
+    local msg="$3"
+    echo -e "${YELLOW}TEST: $msg${RESET}"
+    local actual
+    actual=$(cd "$repo_name" && "$ASH_CMD" status --porcelain --color=never 2>&1)
+    if [ "$actual" == "$expected" ]; then
+        pass "$msg"
+    else
+        fail "$msg"
+        echo -e "Expected:\n$expected\nActual:\n$actual"
+    fi
+}
+
+function commit_all() {
+    local repo_name="$1"
+    (cd "$repo_name" && "$ASH_CMD" add . > /dev/null && "$ASH_CMD" commit -m "$2" > /dev/null)
+}
+
+# --- Test Cases ---
+function test_nested_untracked_directories() {
+    echo -e "\n${BLUE}--- Test: Untracked directories nested among tracked ones ---${RESET}"
+    local repo="nested_repo"
+    setup_repo "$repo"
+    mkdir -p "$repo/src/core"
+    echo "main" > "$repo/src/main.rs"
+    echo "lib" > "$repo/src/core/lib.rs"
+    commit_all "$repo" "Initial"
+
+    mkdir -p "$repo/src/core/new/deeper" "$repo/src/extra" "$repo/docs/api"
+    echo "a" > "$repo/src/core/new/a.rs"
+    echo "b" > "$repo/src/core/new/deeper/b.rs"
+    echo "c" > "$repo/src/extra/c.rs"
+    echo "d" > "$repo/docs/api/d.md"
+    echo "e" > "$repo/src/e.rs"
+    assert_status "$repo" "?? docs/
+?? src/core/new/
+?? src/e.rs
+?? src/extra/" "Nested mix: each untracked directory is reported once, as the outermost"
+}
+
+function test_ignored_and_empty_directories() {
+    echo -e "\n${BLUE}--- Test: Directories with nothing to report ---${RESET}"
+    local repo="ignored_repo"
+    setup_repo "$repo"
+    echo "*.log" > "$repo/.ashignore"
+    echo "tracked" > "$repo/tracked.txt"
+    commit_all "$repo" "Initial"
+
+    mkdir -p "$repo/logs/old" "$repo/empty/inner" "$repo/mixed"
+    echo "log" > "$repo/logs/old/run.log"
+    echo "log" > "$repo/mixed/run.log"
+    echo "kept" > "$repo/mixed/kept.txt"
+    assert_status "$repo" "?? mixed/" "Only ignored files or empty directories: nothing is reported"
+}
+
+function test_file_replaced_by_directory() {
+    echo -e "\n${BLUE}--- Test: A tracked file replaced by a directory ---${RESET}"
+    local repo="file_to_dir_repo"
+    setup_repo "$repo"
+    mkdir -p "$repo/lib"
+    echo "config" > "$repo/config"
+    echo "util" > "$repo/lib/util"
+    commit_all "$repo" "Initial"
+
+    rm "$repo/config" "$repo/lib/util"
+    mkdir -p "$repo/config/nested" "$repo/lib/util"
+    echo "x" > "$repo/config/nested/x"
+    echo "y" > "$repo/lib/util/y"
+    assert_status "$repo" " D config
+?? config/
+ D lib/util
+?? lib/util/" "File to directory: the file is deleted and the directory untracked"
+}
+
+function test_directory_replaced_by_file() {
+    echo -e "\n${BLUE}--- Test: A tracked directory replaced by a file ---${RESET}"
+    local repo="dir_to_file_repo"
+    setup_repo "$repo"
+    mkdir -p "$repo/assets/img"
+    echo "logo" > "$repo/assets/img/logo"
+    echo "keep" > "$repo/keep"
+    commit_all "$repo" "Initial"
+
+    rm -r "$repo/assets"
+    echo "assets" > "$repo/assets"
+    assert_status "$repo" "?? assets
+ D assets/img/logo" "Directory to file: its files are deleted and the file untracked"
+
+    # Once the deletion is staged the directory is no longer tracked
+    (cd "$repo" && "$ASH_CMD" rm --cached assets/img/logo > /dev/null 2>&1)
+    rm "$repo/assets"
+    mkdir -p "$repo/assets/img"
+    echo "new" > "$repo/assets/img/new"
+    assert_status "$repo" "?? assets/
+D  assets/img/logo" "Staged removal: the directory that came back is untracked as a whole"
+}
+
+
+# --- Run Tests ---
+test_nested_untracked_directories
+test_ignored_and_empty_directories
+test_file_replaced_by_directory
+test_directory_replaced_by_file
+
+# --- Summary ---
+echo -e "\n${BLUE}--- Test Summary ---${RESET}"
+echo -e "${GREEN}Tests Passed: $TESTS_PASSED${RESET}"
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    echo -e "${RED}Tests Failed: $TESTS_FAILED${RESET}"
+else
+    echo -e "${GREEN}Tests Failed: $TESTS_FAILED${RESET}"
+fi
+
+# --- Cleanup ---
+cd ..
+rm -rf "$TEST_DIR"
+echo "Cleaned up temporary directory: $TEST_DIR"
+
+# Exit with status code indicating failure if any tests failed
+if [ "$TESTS_FAILED" -gt 0 ]; then
+    exit 1
+else
+    exit 0
+fi